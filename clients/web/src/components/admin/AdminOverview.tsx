@@ -59,6 +59,10 @@ export function AdminOverview() {
       value: `${(stats.db_latency_ms ?? 0).toFixed(1)} ms`,
     },
     { label: "Storage", value: formatBytes(stats.storage_bytes ?? 0) },
+    {
+      label: "Attachments",
+      value: formatBytes(stats.attachment_bytes ?? 0),
+    },
   ];
 
   return (
@@ -31,6 +31,9 @@ const ACTION_OPTIONS: ActionOption[] = [
   { label: "Timeout Removed", value: "member_timeout_remove" },
   { label: "Role Added", value: "member_role_add" },
   { label: "Role Removed", value: "member_role_remove" },
+  { label: "Voice Muted/Deafened", value: "member_voice_update" },
+  { label: "Voice Moved", value: "member_voice_move" },
+  { label: "Voice Disconnected", value: "member_voice_disconnect" },
   // Role
   { label: "--- Role ---", value: "", disabled: true },
   { label: "Role Created", value: "role_create" },
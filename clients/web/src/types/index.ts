@@ -584,6 +584,16 @@ export interface AdminStatsResponse {
   uptime_secs: number | null;
   db_latency_ms: number;
   storage_bytes: number;
+  attachment_bytes: number;
+  server_storage_quota_bytes: number | null;
+  user_storage_quota_bytes: number | null;
+  top_storage_servers: ServerStorageUsage[];
+}
+
+export interface ServerStorageUsage {
+  server_id: string;
+  name: string;
+  attachment_bytes: number;
 }
 
 export interface AdminUserDto {
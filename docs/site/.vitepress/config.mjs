@@ -14,9 +14,16 @@ const selfHostingGuide = [
 const featuresGuide = [
   { text: 'Overview', link: '/features/overview' },
   { text: 'Text Channels & Threads', link: '/features/channels' },
+  { text: 'Forum Channels', link: '/features/forum-channels' },
   { text: 'Voice & Go Live', link: '/features/voice-and-screen-share' },
   { text: 'Direct Messages', link: '/features/direct-messages' },
+  { text: 'Notification Settings', link: '/features/notification-settings' },
+  { text: 'Push Notifications', link: '/features/push-notifications' },
+  { text: 'Email', link: '/features/email' },
   { text: 'Authentication', link: '/features/authentication' },
+  { text: 'Two-Factor Authentication', link: '/features/two-factor-auth' },
+  { text: 'Passkeys', link: '/features/passkeys' },
+  { text: 'Single Sign-On', link: '/features/single-sign-on' },
   { text: 'Roles & Permissions', link: '/features/roles-and-permissions' },
   { text: 'Channel Categories', link: '/features/channel-categories' },
   { text: 'Channel Permissions', link: '/features/channel-permissions' },
@@ -26,10 +33,12 @@ const featuresGuide = [
 const featuresMessages = [
   { text: 'Message Editing & Deletion', link: '/features/message-editing-deletion' },
   { text: 'Message Pinning', link: '/features/message-pinning' },
+  { text: 'Scheduled Messages', link: '/features/scheduled-messages' },
   { text: 'Message Search', link: '/features/message-search' },
   { text: 'Reactions', link: '/features/reactions' },
   { text: 'Polls & Events', link: '/features/polls-and-events' },
   { text: 'Link Previews & GIFs', link: '/features/link-previews-and-giphy' },
+  { text: 'File Attachments', link: '/features/attachments' },
   { text: 'Custom Emojis', link: '/features/custom-emojis' },
 ]
 
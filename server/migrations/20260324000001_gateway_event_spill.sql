@@ -0,0 +1,12 @@
+-- Migration: Gateway event spill table
+-- Description: Holds cross-node event bus payloads that exceed the Postgres
+-- NOTIFY payload limit. The NOTIFY carries only the row id; receiving nodes
+-- read the payload from here. Rows are short-lived and swept periodically.
+
+CREATE TABLE gateway_event_spill (
+    id BIGSERIAL PRIMARY KEY,
+    payload TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_gateway_event_spill_created ON gateway_event_spill(created_at);
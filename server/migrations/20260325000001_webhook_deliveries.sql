@@ -0,0 +1,53 @@
+-- Migration: Durable webhook deliveries
+-- Description: Outbound webhook deliveries are persisted so they survive
+-- restarts and can be inspected and replayed by server admins.
+--
+-- Design decisions:
+--   - One webhook_deliveries row per (webhook, event). The worker claims due
+--     rows with FOR UPDATE SKIP LOCKED, so several replicas can run workers
+--     against the same table without delivering a row twice.
+--   - A claimed row is 'in_flight' with a lease (locked_until). If the worker
+--     dies mid-request the lease expires and the row is claimed again.
+--   - 'failed' is the dead-letter state: retries are exhausted and the row is
+--     kept for inspection and manual redelivery.
+--   - URL and secret are read from webhooks at send time, so editing a webhook
+--     applies to deliveries that are still pending.
+--   - Every HTTP attempt is recorded in webhook_delivery_attempts with the
+--     response status (NULL when no response was received) and latency.
+
+CREATE TABLE webhook_deliveries (
+    id              UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    webhook_id      UUID NOT NULL REFERENCES webhooks(id) ON DELETE CASCADE,
+    event_type      TEXT NOT NULL,
+    payload         TEXT NOT NULL,
+    status          TEXT NOT NULL DEFAULT 'pending'
+                    CHECK (status IN ('pending', 'in_flight', 'succeeded', 'failed')),
+    attempt_count   INT NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    locked_until    TIMESTAMPTZ,
+    redelivery_of   UUID REFERENCES webhook_deliveries(id) ON DELETE SET NULL,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at    TIMESTAMPTZ
+);
+
+CREATE INDEX idx_webhook_deliveries_due
+    ON webhook_deliveries (next_attempt_at)
+    WHERE status IN ('pending', 'in_flight');
+CREATE INDEX idx_webhook_deliveries_webhook
+    ON webhook_deliveries (webhook_id, created_at DESC);
+CREATE INDEX idx_webhook_deliveries_completed
+    ON webhook_deliveries (completed_at)
+    WHERE completed_at IS NOT NULL;
+
+CREATE TABLE webhook_delivery_attempts (
+    id              BIGSERIAL PRIMARY KEY,
+    delivery_id     UUID NOT NULL REFERENCES webhook_deliveries(id) ON DELETE CASCADE,
+    attempt         INT NOT NULL,
+    response_status INT,
+    error           TEXT,
+    latency_ms      INT NOT NULL,
+    attempted_at    TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_webhook_delivery_attempts_delivery
+    ON webhook_delivery_attempts (delivery_id, attempt);
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS incoming_webhook_messages;
+DROP TABLE IF EXISTS incoming_webhooks;
@@ -0,0 +1,40 @@
+-- Migration: Incoming webhooks
+-- Description: Channel-bound webhooks that external tools (CI, monitoring,
+-- feed readers) can POST messages to without a bot account.
+--
+-- Design decisions:
+--   - The execute URL carries the webhook id and a secret token. Only the
+--     SHA-256 hash of the token is stored, like bot tokens.
+--   - Webhook posts are ordinary rows in messages with author_id NULL, so
+--     history, search, pins and export keep working unchanged. The webhook
+--     identity and any embeds live in incoming_webhook_messages, keyed by
+--     message_id (same side-table shape as polls and server_events).
+--   - username/avatar_url are copied onto each message at post time so a
+--     per-message override, a later rename, or deleting the webhook does not
+--     rewrite how old messages are attributed.
+
+CREATE TABLE incoming_webhooks (
+    id           UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    server_id    UUID NOT NULL REFERENCES servers(id) ON DELETE CASCADE,
+    channel_id   UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    created_by   UUID REFERENCES users(id) ON DELETE SET NULL,
+    name         TEXT NOT NULL CHECK (char_length(name) BETWEEN 1 AND 80),
+    avatar_url   TEXT,
+    token_hash   TEXT NOT NULL UNIQUE,
+    last_used_at TIMESTAMPTZ,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_incoming_webhooks_server ON incoming_webhooks(server_id);
+
+CREATE TABLE incoming_webhook_messages (
+    message_id UUID PRIMARY KEY REFERENCES messages(id) ON DELETE CASCADE,
+    webhook_id UUID REFERENCES incoming_webhooks(id) ON DELETE SET NULL,
+    username   TEXT NOT NULL,
+    avatar_url TEXT,
+    embeds     JSONB NOT NULL DEFAULT '[]'
+);
+
+CREATE INDEX idx_incoming_webhook_messages_webhook
+    ON incoming_webhook_messages(webhook_id)
+    WHERE webhook_id IS NOT NULL;
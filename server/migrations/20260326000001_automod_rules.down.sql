@@ -0,0 +1,2 @@
+ALTER TABLE automod_logs DROP COLUMN IF EXISTS rule_id;
+DROP TABLE IF EXISTS automod_rules;
@@ -0,0 +1,36 @@
+-- Migration: Automod rules
+-- Description: Configurable per-server automod rules evaluated by an
+-- in-memory compiled matcher, alongside the built-in spam/duplicate/word
+-- checks in automod_configs.
+--
+-- Design decisions:
+--   - trigger and actions are JSONB tagged unions ({"type": ...}) so new
+--     trigger kinds do not need a migration. They are validated and compiled
+--     by the server before being stored.
+--   - Scopes are UUID arrays; an empty array means "no restriction".
+--     channel_ids/role_ids limit where and to whom a rule applies;
+--     exempt_channel_ids/exempt_role_ids carve exceptions out of that.
+--   - Rules are independent of automod_configs.enabled, which keeps gating
+--     only the built-in checks.
+
+CREATE TABLE automod_rules (
+    id                 UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    server_id          UUID NOT NULL REFERENCES servers(id) ON DELETE CASCADE,
+    name               TEXT NOT NULL CHECK (char_length(name) BETWEEN 1 AND 100),
+    enabled            BOOLEAN NOT NULL DEFAULT TRUE,
+    trigger            JSONB NOT NULL,
+    actions            JSONB NOT NULL,
+    channel_ids        UUID[] NOT NULL DEFAULT '{}',
+    role_ids           UUID[] NOT NULL DEFAULT '{}',
+    exempt_channel_ids UUID[] NOT NULL DEFAULT '{}',
+    exempt_role_ids    UUID[] NOT NULL DEFAULT '{}',
+    created_by         UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at         TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at         TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_automod_rules_server ON automod_rules(server_id, created_at);
+
+-- Log entries produced by a rule point back at it.
+ALTER TABLE automod_logs
+    ADD COLUMN rule_id UUID REFERENCES automod_rules(id) ON DELETE SET NULL;
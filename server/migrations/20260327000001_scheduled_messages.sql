@@ -0,0 +1,41 @@
+-- Migration: Scheduled messages and reminders
+-- Description: Messages queued by a user to be posted into a channel at a
+-- future time, including "remind me" reminders.
+--
+-- Design decisions:
+--   - Rows are the queue: a background scheduler claims due rows with
+--     FOR UPDATE SKIP LOCKED and a lease (locked_until), like
+--     webhook_deliveries, so pending messages survive restarts and several
+--     replicas can poll the same table.
+--   - The posted message reuses the scheduled row's id. If a node dies after
+--     posting but before removing the row, the retry finds the message and
+--     does not post it twice.
+--   - Sent rows are deleted; the message itself is the record. Rows that can
+--     no longer be posted (author lost access, content now blocked by
+--     automod) stay as 'failed' with the reason until the author deletes them.
+
+CREATE TABLE scheduled_messages (
+    id            UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    channel_id    UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    author_id     UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind          TEXT NOT NULL DEFAULT 'message'
+                  CHECK (kind IN ('message', 'reminder')),
+    content       TEXT NOT NULL CHECK (char_length(content) BETWEEN 1 AND 4000),
+    reply_to      UUID REFERENCES messages(id) ON DELETE SET NULL,
+    send_at       TIMESTAMPTZ NOT NULL,
+    status        TEXT NOT NULL DEFAULT 'pending'
+                  CHECK (status IN ('pending', 'sending', 'failed')),
+    attempt_count INT NOT NULL DEFAULT 0,
+    locked_until  TIMESTAMPTZ,
+    last_error    TEXT,
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at    TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Scheduler scan: due pending rows and expired leases.
+CREATE INDEX idx_scheduled_messages_due
+    ON scheduled_messages(send_at)
+    WHERE status IN ('pending', 'sending');
+
+CREATE INDEX idx_scheduled_messages_author
+    ON scheduled_messages(author_id, send_at);
@@ -0,0 +1,7 @@
+DROP TABLE IF EXISTS forum_posts;
+DROP TABLE IF EXISTS forum_tags;
+
+DELETE FROM channels WHERE type = 'forum';
+ALTER TABLE channels DROP CONSTRAINT channels_type_check;
+ALTER TABLE channels ADD CONSTRAINT channels_type_check
+    CHECK (type IN ('text', 'voice'));
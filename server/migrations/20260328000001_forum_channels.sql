@@ -0,0 +1,45 @@
+-- Migration: Forum channels
+-- Description: Adds the 'forum' channel type, a per-channel tag set and the
+-- title/tag metadata carried by each forum post.
+--
+-- Design decisions:
+--   - A forum post is an ordinary root message in the forum channel; its
+--     replies are thread replies (messages.thread_id). Reactions, pins,
+--     edits, deletes and search all work on posts unchanged.
+--   - Post metadata lives in a side table keyed by message_id rather than as
+--     nullable columns on messages, which every other channel type would
+--     carry for nothing.
+--   - tag_ids is a UUID[] with a GIN index so "posts with tag X" is a single
+--     indexed containment query. Deleting a tag removes it from every post
+--     in the same transaction (no FK on array elements).
+--   - last_activity_at is bumped by each thread reply so the post list can
+--     sort by latest activity without aggregating over messages.
+
+ALTER TABLE channels DROP CONSTRAINT channels_type_check;
+ALTER TABLE channels ADD CONSTRAINT channels_type_check
+    CHECK (type IN ('text', 'voice', 'forum'));
+
+CREATE TABLE forum_tags (
+    id         UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    channel_id UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    name       TEXT NOT NULL CHECK (char_length(name) BETWEEN 1 AND 50),
+    emoji      TEXT,
+    position   INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (channel_id, name)
+);
+
+CREATE TABLE forum_posts (
+    message_id       UUID PRIMARY KEY REFERENCES messages(id) ON DELETE CASCADE,
+    channel_id       UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    title            TEXT NOT NULL CHECK (char_length(title) BETWEEN 1 AND 100),
+    tag_ids          UUID[] NOT NULL DEFAULT '{}',
+    last_activity_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_forum_posts_activity
+    ON forum_posts(channel_id, last_activity_at DESC, message_id DESC);
+CREATE INDEX idx_forum_posts_tags ON forum_posts USING GIN (tag_ids);
+CREATE INDEX idx_forum_posts_title_search
+    ON forum_posts USING GIN (to_tsvector('english', title));
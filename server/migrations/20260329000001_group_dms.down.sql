@@ -0,0 +1,8 @@
+DELETE FROM direct_message_channels WHERE is_group;
+
+ALTER TABLE direct_message_members DROP COLUMN IF EXISTS joined_at;
+
+ALTER TABLE direct_message_channels
+    DROP COLUMN IF EXISTS owner_id,
+    DROP COLUMN IF EXISTS name,
+    DROP COLUMN IF EXISTS is_group;
@@ -0,0 +1,22 @@
+-- Migration: Group DMs
+-- Description: Lets a DM channel hold more than two participants, with a
+-- name and an owner.
+--
+-- Design decisions:
+--   - Group DMs reuse direct_message_channels / direct_message_members /
+--     direct_messages rather than new tables, so sending, listing, read
+--     states and export work for both kinds through the same membership join.
+--   - is_group is fixed at creation. A 1:1 channel never gains members and
+--     a group never collapses into a 1:1, so the "find the existing DM
+--     between A and B" lookup only has to consider non-group channels.
+--   - owner_id is SET NULL when the owner's account is deleted. Ownership is
+--     otherwise handed to the longest-standing member when the owner leaves,
+--     which is what joined_at is for.
+
+ALTER TABLE direct_message_channels
+    ADD COLUMN is_group BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN name     TEXT CHECK (char_length(name) BETWEEN 1 AND 100),
+    ADD COLUMN owner_id UUID REFERENCES users(id) ON DELETE SET NULL;
+
+ALTER TABLE direct_message_members
+    ADD COLUMN joined_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS go_live_settings;
+DROP TABLE IF EXISTS go_live_viewers;
+DROP TABLE IF EXISTS go_live_sessions;
@@ -0,0 +1,39 @@
+-- Migration: Persistent Go Live sessions
+-- Description: Moves Go Live broadcasts out of process memory, tracks who is
+-- watching each broadcast, and adds per-server streaming limits.
+--
+-- Design decisions:
+--   - go_live_sessions is keyed by channel_id, so "one broadcaster per
+--     channel" is the primary key rather than an application-level lock.
+--   - broadcaster_id and viewer user_id reference voice_states(user_id) with
+--     ON DELETE CASCADE: a session or viewer row can never outlive the voice
+--     state it belongs to, even on paths that skip the explicit cleanup.
+--   - Viewer counts are derived from go_live_viewers rather than stored, so
+--     they cannot drift.
+--   - max_concurrent_streams = 0 means unlimited. Members holding any role in
+--     high_quality_role_ids (and the server owner) may exceed max_quality.
+
+CREATE TABLE go_live_sessions (
+    channel_id     UUID PRIMARY KEY REFERENCES channels(id) ON DELETE CASCADE,
+    broadcaster_id UUID NOT NULL UNIQUE REFERENCES voice_states(user_id) ON DELETE CASCADE,
+    quality        TEXT NOT NULL CHECK (quality IN ('480p', '720p', '1080p')),
+    started_at     TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE go_live_viewers (
+    channel_id UUID NOT NULL REFERENCES go_live_sessions(channel_id) ON DELETE CASCADE,
+    user_id    UUID NOT NULL REFERENCES voice_states(user_id) ON DELETE CASCADE,
+    joined_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (channel_id, user_id)
+);
+
+CREATE INDEX idx_go_live_viewers_user ON go_live_viewers(user_id);
+
+CREATE TABLE go_live_settings (
+    server_id              UUID PRIMARY KEY REFERENCES servers(id) ON DELETE CASCADE,
+    max_concurrent_streams INT NOT NULL DEFAULT 0 CHECK (max_concurrent_streams >= 0),
+    max_quality            TEXT NOT NULL DEFAULT '1080p'
+                               CHECK (max_quality IN ('480p', '720p', '1080p')),
+    high_quality_role_ids  UUID[] NOT NULL DEFAULT '{}',
+    updated_at             TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1 @@
+ALTER TABLE channel_read_states DROP COLUMN IF EXISTS last_message_id;
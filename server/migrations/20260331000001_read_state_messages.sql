@@ -0,0 +1,14 @@
+-- Migration: Ack to a specific message
+-- Description: Records which message a read state was acknowledged up to.
+--
+-- Design decisions:
+--   - last_read_at stays the cursor that unread and mention counts compare
+--     against; last_message_id is the message the client acked (or the
+--     newest message at ack time) so clients can draw the "new messages"
+--     divider without a timestamp lookup.
+--   - No FK, for the same reason channel_id has none: the id may point into
+--     messages or direct_messages.
+--   - Nullable: rows written before this migration, and acks of empty
+--     channels, have no message to point at.
+
+ALTER TABLE channel_read_states ADD COLUMN last_message_id UUID;
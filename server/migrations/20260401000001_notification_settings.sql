@@ -0,0 +1,48 @@
+-- Migration: Notification preferences
+-- Description: Per-user notification level, mute and @everyone suppression
+-- for servers, channel categories, server channels and DMs.
+--
+-- Design decisions:
+--   - One table with a scope column instead of four tables, so READY and the
+--     resolver load everything for a user with a single query.
+--   - Categories are text labels on channels, so a category row is keyed by
+--     (server_id, category). Renaming a category orphans its row, the same
+--     way it splits the channel group.
+--   - Server channels and DM channels get separate FK columns so each row
+--     cascades away with the thing it configures.
+--   - level and suppress_everyone are NULL when the row inherits them from
+--     the next scope up (channel -> category -> server). muted_until does
+--     not inherit: any active mute in the chain silences the channel.
+
+CREATE TABLE notification_settings (
+    id                UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id           UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    scope             TEXT NOT NULL CHECK (scope IN ('server', 'category', 'channel', 'dm')),
+    server_id         UUID REFERENCES servers(id) ON DELETE CASCADE,
+    category          TEXT CHECK (char_length(category) BETWEEN 1 AND 100),
+    channel_id        UUID REFERENCES channels(id) ON DELETE CASCADE,
+    dm_channel_id     UUID REFERENCES direct_message_channels(id) ON DELETE CASCADE,
+    level             TEXT CHECK (level IN ('all', 'mentions', 'none')),
+    muted_until       TIMESTAMPTZ,
+    suppress_everyone BOOLEAN,
+    updated_at        TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (
+        (scope = 'server'   AND server_id IS NOT NULL AND category IS NULL
+                            AND channel_id IS NULL AND dm_channel_id IS NULL) OR
+        (scope = 'category' AND server_id IS NOT NULL AND category IS NOT NULL
+                            AND channel_id IS NULL AND dm_channel_id IS NULL) OR
+        (scope = 'channel'  AND channel_id IS NOT NULL AND server_id IS NULL
+                            AND category IS NULL AND dm_channel_id IS NULL) OR
+        (scope = 'dm'       AND dm_channel_id IS NOT NULL AND server_id IS NULL
+                            AND category IS NULL AND channel_id IS NULL)
+    )
+);
+
+CREATE UNIQUE INDEX notification_settings_server_uniq
+    ON notification_settings (user_id, server_id) WHERE scope = 'server';
+CREATE UNIQUE INDEX notification_settings_category_uniq
+    ON notification_settings (user_id, server_id, category) WHERE scope = 'category';
+CREATE UNIQUE INDEX notification_settings_channel_uniq
+    ON notification_settings (user_id, channel_id) WHERE scope = 'channel';
+CREATE UNIQUE INDEX notification_settings_dm_uniq
+    ON notification_settings (user_id, dm_channel_id) WHERE scope = 'dm';
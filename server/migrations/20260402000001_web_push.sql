@@ -0,0 +1,33 @@
+-- Migration: Web Push
+-- Description: Browser push subscriptions and a per-user push opt-out.
+--
+-- Design decisions:
+--   - endpoint is UNIQUE: a browser that re-subscribes after a different
+--     user logs in on it moves the subscription to that user instead of
+--     notifying both.
+--   - p256dh and auth are the client's base64url keys from
+--     PushSubscription.getKey(); payloads are encrypted to them (RFC 8291),
+--     so the push service never sees message content.
+--   - Subscriptions the push service reports as gone (404/410) are deleted
+--     by the dispatcher; other failures only bump failure_count.
+--   - push_preferences has a row only for users who changed the default
+--     (push enabled).
+
+CREATE TABLE push_subscriptions (
+    id              UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id         UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    endpoint        TEXT NOT NULL UNIQUE,
+    p256dh          TEXT NOT NULL,
+    auth            TEXT NOT NULL,
+    failure_count   INT NOT NULL DEFAULT 0,
+    last_success_at TIMESTAMPTZ,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_push_subscriptions_user ON push_subscriptions (user_id);
+
+CREATE TABLE push_preferences (
+    user_id    UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    enabled    BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,35 @@
+-- Migration: Email delivery
+-- Description: Email verification and per-user email preferences.
+--
+-- Design decisions:
+--   - A verification token records the address it was sent to, and only
+--     verifies the account while that is still the account's address.
+--   - Tokens are stored as SHA-256 hashes, like password reset tokens.
+--   - email_preferences has a row only for users who changed the default
+--     (no digest). digest_last_run_at is claimed by the digest worker with
+--     FOR UPDATE SKIP LOCKED so replicas never send the same digest twice.
+
+ALTER TABLE users ADD COLUMN email_verified_at TIMESTAMPTZ;
+
+CREATE TABLE email_verification_tokens (
+    id         UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id    UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    email      TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at    TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_email_verification_tokens_user
+    ON email_verification_tokens (user_id, created_at DESC);
+
+CREATE TABLE email_preferences (
+    user_id            UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    mention_digest     BOOLEAN NOT NULL DEFAULT FALSE,
+    digest_last_run_at TIMESTAMPTZ,
+    updated_at         TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_email_preferences_digest
+    ON email_preferences (digest_last_run_at) WHERE mention_digest;
@@ -0,0 +1,4 @@
+ALTER TABLE instance_settings DROP COLUMN IF EXISTS require_two_factor;
+DROP TABLE IF EXISTS two_factor_challenges;
+DROP TABLE IF EXISTS two_factor_recovery_codes;
+DROP TABLE IF EXISTS user_totp;
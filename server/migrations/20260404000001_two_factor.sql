@@ -0,0 +1,43 @@
+-- Migration: TOTP two-factor authentication
+-- Description: Per-user TOTP secrets, one-time recovery codes, the short-lived
+-- challenges issued by /auth/login when a second step is needed, and an
+-- instance-wide setting to require 2FA.
+
+CREATE TABLE user_totp (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    -- Base32 secret shared with the user's authenticator app.
+    secret TEXT NOT NULL,
+    -- NULL until the user confirms enrollment with a valid code.
+    enabled_at TIMESTAMPTZ,
+    -- Last accepted time step; codes for this step or earlier are rejected.
+    last_used_step BIGINT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE two_factor_recovery_codes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    code_hash TEXT NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_two_factor_recovery_codes_user ON two_factor_recovery_codes(user_id);
+
+CREATE TABLE two_factor_challenges (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL UNIQUE,
+    -- 'verify': enter a code to finish login.
+    -- 'enroll': 2FA is required but not set up; enroll to finish login.
+    purpose TEXT NOT NULL CHECK (purpose IN ('verify', 'enroll')),
+    attempts INT NOT NULL DEFAULT 0,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_two_factor_challenges_user ON two_factor_challenges(user_id);
+
+ALTER TABLE instance_settings
+    ADD COLUMN require_two_factor TEXT NOT NULL DEFAULT 'off'
+        CHECK (require_two_factor IN ('off', 'admins', 'everyone'));
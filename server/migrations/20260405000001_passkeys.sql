@@ -0,0 +1,38 @@
+-- Migration: Passkeys (WebAuthn)
+-- Description: Per-user WebAuthn credentials, usable for passwordless login
+-- or as a second factor, and the one-time challenges of each ceremony.
+
+CREATE TABLE webauthn_credentials (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- Base64url credential ID chosen by the authenticator.
+    credential_id TEXT NOT NULL UNIQUE,
+    -- COSE_Key as returned at registration.
+    public_key BYTEA NOT NULL,
+    -- COSE algorithm: -7 (ES256), -8 (EdDSA) or -257 (RS256).
+    algorithm INT NOT NULL,
+    sign_count BIGINT NOT NULL DEFAULT 0,
+    name TEXT NOT NULL,
+    transports TEXT[] NOT NULL DEFAULT '{}',
+    -- The authenticator may sync this credential to other devices.
+    backup_eligible BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_webauthn_credentials_user ON webauthn_credentials(user_id);
+
+CREATE TABLE webauthn_challenges (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    challenge_hash TEXT NOT NULL UNIQUE,
+    -- NULL for passwordless login, where the user is not known yet.
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    -- 'register': add a passkey to the account.
+    -- 'login': passwordless login.
+    -- 'second_factor': finish a password login that needs 2FA.
+    purpose TEXT NOT NULL CHECK (purpose IN ('register', 'login', 'second_factor')),
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_webauthn_challenges_expires ON webauthn_challenges(expires_at);
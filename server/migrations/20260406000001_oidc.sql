@@ -0,0 +1,34 @@
+-- Migration: OpenID Connect single sign-on
+-- Description: Accounts linked to identity provider subjects, and the
+-- short-lived state of logins in progress.
+
+CREATE TABLE user_identities (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- The provider's issuer URL and its stable ID for the user (`sub`).
+    issuer TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    -- Email claim at the last login, for admins tracing an account.
+    email TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_login_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (issuer, subject)
+);
+
+CREATE INDEX idx_user_identities_user ON user_identities(user_id);
+
+CREATE TABLE oidc_login_states (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- SHA-256 of the `state` parameter sent to the provider.
+    state_hash TEXT NOT NULL UNIQUE,
+    nonce TEXT NOT NULL,
+    -- PKCE verifier, sent with the code exchange.
+    code_verifier TEXT NOT NULL,
+    -- Invite code given when starting the login, used if the instance is
+    -- invite-only and the login creates an account.
+    invite_code TEXT,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_oidc_login_states_expires ON oidc_login_states(expires_at);
@@ -0,0 +1,3 @@
+ALTER TABLE custom_emojis
+    DROP COLUMN IF EXISTS height,
+    DROP COLUMN IF EXISTS width;
@@ -0,0 +1,7 @@
+-- Migration: Custom emoji dimensions
+-- Description: Pixel size of each custom emoji image, recorded on upload.
+-- Emojis uploaded before this migration have no dimensions.
+
+ALTER TABLE custom_emojis
+    ADD COLUMN width  INTEGER,
+    ADD COLUMN height INTEGER;
@@ -0,0 +1,28 @@
+-- Migration: Resumable uploads
+-- Description: Attachment uploads sent in pieces over several requests
+-- (tus-style create / PATCH / HEAD) before being attached to a message.
+--
+-- Design decisions:
+--   - The bytes received so far live in a file under UPLOAD_STAGING_DIR named
+--     after the row id; upload_offset is how much of it is committed.
+--   - A PATCH holds a short lease (locked_until), renewed while the body
+--     streams, so two requests never append to the same file at once.
+--   - mime_type is sniffed from the first bytes and stays NULL until then.
+--   - Rows are deleted when the upload is attached to a message, cancelled,
+--     or found expired by the sweeper, which also removes the file.
+
+CREATE TABLE uploads (
+    id            UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id       UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    filename      TEXT NOT NULL,
+    upload_length BIGINT NOT NULL CHECK (upload_length BETWEEN 1 AND 52428800),
+    upload_offset BIGINT NOT NULL DEFAULT 0
+                  CHECK (upload_offset BETWEEN 0 AND upload_length),
+    mime_type     TEXT,
+    locked_until  TIMESTAMPTZ,
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at    TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_uploads_user_id ON uploads (user_id);
+CREATE INDEX idx_uploads_expires_at ON uploads (expires_at);
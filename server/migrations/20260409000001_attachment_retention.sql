@@ -0,0 +1,18 @@
+-- Migration: Attachment retention
+-- Description: Records when a message was soft-deleted, so the attachment
+-- collector can remove its files once the grace period has passed.
+--
+-- Design decisions:
+--   - deleted_at is set together with deleted = TRUE. Messages deleted before
+--     this migration get the time it ran, which starts their grace period now
+--     rather than collecting their files on the first sweep.
+--   - The partial index covers only deleted messages, which is all the
+--     collector ever scans.
+
+ALTER TABLE messages ADD COLUMN deleted_at TIMESTAMPTZ;
+
+UPDATE messages SET deleted_at = NOW() WHERE deleted;
+
+CREATE INDEX idx_messages_deleted_at ON messages(deleted_at) WHERE deleted;
+
+COMMENT ON COLUMN messages.deleted_at IS 'When the message was soft-deleted; attachments are collected a grace period later';
@@ -0,0 +1,234 @@
+//! Removing stored files nothing refers to any more.
+//!
+//! # Design
+//!
+//! Attachment files live in storage under `{message_id}/`, custom emoji
+//! images under `custom_emojis/{emoji_id}/`. Rows and files part ways in
+//! three ways:
+//!
+//! - Deleting a message only sets `deleted`, so its attachment rows and files
+//!   stay behind.
+//! - Deleting a channel or server cascades through `messages` and
+//!   `attachments` (and `custom_emojis`), leaving files with no row at all.
+//! - A crash between storing a file and inserting its row leaves the same.
+//!
+//! [`collect`] handles all of them in two steps. First it deletes attachment
+//! rows of messages deleted more than `ATTACHMENT_GC_GRACE_HOURS` ago together
+//! with their files. Then it reconciles storage against the database: every
+//! file under a message or emoji prefix that no row refers to and that was
+//! written before the grace period is removed. The grace period also protects
+//! uploads in flight, whose files are stored a moment before their rows are
+//! inserted. Files under any other prefix are left alone.
+//!
+//! Both steps go through [`Storage`], so they work the same for local disk
+//! and a bucket. [`start_collector`] runs [`collect`] periodically.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::storage::Storage;
+
+/// How often [`start_collector`] runs.
+const COLLECT_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Key prefix of custom emoji images.
+const EMOJI_PREFIX: &str = "custom_emojis";
+
+/// What one [`collect`] run did.
+#[derive(Debug, Default)]
+pub struct CollectReport {
+    /// Attachment rows of deleted messages removed with their files.
+    pub deleted_attachments: u64,
+    /// Files removed because no row refers to them.
+    pub orphaned_files: usize,
+    /// Bytes in those orphaned files.
+    pub orphaned_bytes: u64,
+    /// Keys of attachments whose file is not in storage. These are only
+    /// reported; the rows are kept.
+    pub missing_files: Vec<String>,
+}
+
+/// Spawn a background task that runs [`collect`] every few hours.
+pub fn start_collector(pool: PgPool, storage: Arc<dyn Storage>, grace: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(COLLECT_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            match collect(&pool, storage.as_ref(), grace).await {
+                Ok(report) => {
+                    if report.deleted_attachments > 0 || report.orphaned_files > 0 {
+                        tracing::info!(
+                            attachments = report.deleted_attachments,
+                            orphaned_files = report.orphaned_files,
+                            orphaned_bytes = report.orphaned_bytes,
+                            "Collected unused attachment files"
+                        );
+                    }
+                    if !report.missing_files.is_empty() {
+                        tracing::warn!(
+                            count = report.missing_files.len(),
+                            first = %report.missing_files[0],
+                            "Attachments are missing their files"
+                        );
+                    }
+                }
+                Err(e) => tracing::warn!(error = ?e, "Failed to collect attachment files"),
+            }
+        }
+    });
+}
+
+/// Remove attachments of messages deleted more than `grace` ago, and files
+/// older than `grace` that no attachment or custom emoji refers to.
+///
+/// Storage errors are logged and skipped; whatever was not removed is picked
+/// up by a later run.
+pub async fn collect(
+    pool: &PgPool,
+    storage: &dyn Storage,
+    grace: Duration,
+) -> Result<CollectReport, sqlx::Error> {
+    let mut report = CollectReport::default();
+    let grace_secs = grace.as_secs_f64();
+
+    // ── Step 1: attachments of deleted messages ──────────────────────────────
+
+    let deleted: Vec<Uuid> = sqlx::query_scalar(
+        "DELETE FROM attachments a
+         USING messages m
+         WHERE a.message_id = m.id
+           AND m.deleted
+           AND m.deleted_at < NOW() - make_interval(secs => $1)
+         RETURNING a.message_id",
+    )
+    .bind(grace_secs)
+    .fetch_all(pool)
+    .await?;
+    report.deleted_attachments = deleted.len() as u64;
+
+    // Every attachment of a deleted message goes at once, so the whole
+    // prefix can go, thumbnails included.
+    let messages: HashSet<Uuid> = deleted.into_iter().collect();
+    for message_id in messages {
+        if let Err(e) = storage.delete_prefix(&message_id.to_string()).await {
+            tracing::warn!(error = ?e, %message_id, "Failed to delete attachment files");
+        }
+    }
+
+    // ── Step 2: reconcile storage against the database ───────────────────────
+
+    // Listed first, so a row inserted meanwhile can only make a file look
+    // referenced, never missing.
+    let listed_at = Utc::now();
+    let files = match storage.list("").await {
+        Ok(files) => files,
+        Err(e) => {
+            tracing::warn!(error = ?e, "Failed to list storage");
+            return Ok(report);
+        }
+    };
+
+    let rows: Vec<(String, DateTime<Utc>)> =
+        sqlx::query_as("SELECT url, created_at FROM attachments")
+            .fetch_all(pool)
+            .await?;
+    let emojis: HashSet<Uuid> = sqlx::query_scalar("SELECT id FROM custom_emojis")
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect();
+    let attachments: HashSet<&str> = rows
+        .iter()
+        .filter_map(|(url, _)| url.strip_prefix("/files/"))
+        .collect();
+
+    let cutoff = chrono::Duration::from_std(grace)
+        .ok()
+        .and_then(|grace| listed_at.checked_sub_signed(grace))
+        .unwrap_or(DateTime::<Utc>::MIN_UTC);
+    let mut stored: HashSet<&str> = HashSet::new();
+    for file in &files {
+        stored.insert(&file.key);
+        if file.modified >= cutoff || is_referenced(&file.key, &attachments, &emojis) {
+            continue;
+        }
+        match storage.delete(&file.key).await {
+            Ok(()) => {
+                report.orphaned_files += 1;
+                report.orphaned_bytes += file.size;
+            }
+            Err(e) => tracing::warn!(error = ?e, key = %file.key, "Failed to delete orphaned file"),
+        }
+    }
+
+    report.missing_files = rows
+        .iter()
+        .filter(|(_, created_at)| *created_at < listed_at)
+        .filter_map(|(url, _)| url.strip_prefix("/files/"))
+        .filter(|key| !stored.contains(key))
+        .map(str::to_string)
+        .collect();
+
+    Ok(report)
+}
+
+/// Whether a row refers to the file under `key`. Keys outside the message
+/// and emoji prefixes are not ours to judge and count as referenced.
+fn is_referenced(key: &str, attachments: &HashSet<&str>, emojis: &HashSet<Uuid>) -> bool {
+    let Some((prefix, rest)) = key.split_once('/') else {
+        return true;
+    };
+    if prefix == EMOJI_PREFIX {
+        return rest
+            .split('/')
+            .next()
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .is_none_or(|id| emojis.contains(&id));
+    }
+    if Uuid::parse_str(prefix).is_err() {
+        return true;
+    }
+    if attachments.contains(key) {
+        return true;
+    }
+    // Thumbnails are stored next to the image as `{name}.{size}.webp`.
+    key.strip_suffix(".webp")
+        .and_then(|k| k.rsplit_once('.'))
+        .is_some_and(|(image, size)| size.parse::<u32>().is_ok() && attachments.contains(image))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognises_referenced_keys() {
+        let message = Uuid::new_v4();
+        let emoji = Uuid::new_v4();
+        let image = format!("{message}/abc_cat.png");
+        let attachments: HashSet<&str> = [image.as_str()].into_iter().collect();
+        let emojis: HashSet<Uuid> = [emoji].into_iter().collect();
+        let referenced = |key: &str| is_referenced(key, &attachments, &emojis);
+
+        assert!(referenced(&image));
+        assert!(referenced(&format!("{image}.256.webp")));
+        assert!(!referenced(&format!("{image}.big.webp")));
+        assert!(!referenced(&format!("{message}/def_dog.png")));
+        assert!(!referenced(&format!("{}/abc_cat.png", Uuid::new_v4())));
+
+        assert!(referenced(&format!("custom_emojis/{emoji}/x.png")));
+        assert!(!referenced(&format!(
+            "custom_emojis/{}/x.png",
+            Uuid::new_v4()
+        )));
+
+        assert!(referenced("avatars/someone.png"));
+        assert!(referenced("loose-file"));
+    }
+}
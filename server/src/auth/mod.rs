@@ -1,3 +1,7 @@
+pub mod oidc;
+pub mod totp;
+pub mod webauthn;
+
 use axum::{
     async_trait,
     extract::FromRequestParts,
@@ -0,0 +1,473 @@
+//! OpenID Connect relying party for single sign-on.
+//!
+//! Implements the authorization code flow with PKCE (RFC 7636, `S256`):
+//! provider discovery, building the authorization URL, exchanging the code at
+//! the token endpoint and validating the ID token. Account lookup and
+//! provisioning live in `crate::handlers::oidc`.
+//!
+//! The discovery document and the provider's signing keys are fetched on
+//! first use and kept in memory. When an ID token is signed with a key ID
+//! that is not in the cached set, the keys are fetched again (at most once a
+//! minute) so the provider can rotate them.
+
+use std::time::{Duration, Instant};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use rand::RngCore;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
+
+use crate::config::OidcConfig;
+
+/// Minimum time between two fetches of the provider's signing keys.
+const JWKS_REFETCH_INTERVAL: Duration = Duration::from_secs(60);
+/// Signature algorithms accepted on ID tokens. Symmetric algorithms are
+/// refused: they would make the client secret a signing key.
+const ALLOWED_ALGORITHMS: [Algorithm; 7] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum OidcError {
+    /// The provider could not be reached or answered with something that is
+    /// not a valid OIDC response. Not the user's fault.
+    #[error("identity provider error: {0}")]
+    Provider(String),
+    /// The provider or its ID token refused the login.
+    #[error("{0}")]
+    Rejected(String),
+}
+
+#[derive(Debug, Deserialize)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+    #[serde(default)]
+    token_endpoint_auth_methods_supported: Vec<String>,
+}
+
+struct Provider {
+    discovery: Discovery,
+    jwks: JwkSet,
+    jwks_fetched_at: Instant,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenError {
+    error: String,
+    error_description: Option<String>,
+}
+
+/// The parts of a login the client must keep until the callback: `state`
+/// comes back on the redirect, `nonce` must appear in the ID token and
+/// `code_verifier` proves to the provider that the code was requested here.
+pub struct AuthorizationRequest {
+    pub url: String,
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+}
+
+/// The user as described by a validated ID token.
+#[derive(Debug, Clone)]
+pub struct Identity {
+    pub subject: String,
+    pub email: Option<String>,
+    /// The provider vouches that the user owns `email`.
+    pub email_verified: bool,
+    pub preferred_username: Option<String>,
+    pub name: Option<String>,
+    pub groups: Vec<String>,
+}
+
+impl Identity {
+    /// `email`, when the provider marked it as verified.
+    pub fn verified_email(&self) -> Option<&str> {
+        self.email.as_deref().filter(|_| self.email_verified)
+    }
+
+    fn from_claims(claims: &Map<String, Value>, groups_claim: &str) -> Result<Self, OidcError> {
+        let text = |key: &str| {
+            claims
+                .get(key)
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+        };
+        let subject =
+            text("sub").ok_or_else(|| OidcError::Rejected("ID token has no subject".into()))?;
+        // Some providers send `email_verified` as the string "true".
+        let email_verified = match claims.get("email_verified") {
+            Some(Value::Bool(b)) => *b,
+            Some(Value::String(s)) => s == "true",
+            _ => false,
+        };
+        // A list of names, or a single space-separated string.
+        let groups = match claims.get(groups_claim) {
+            Some(Value::Array(items)) => items
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect(),
+            Some(Value::String(s)) => s.split_whitespace().map(str::to_string).collect(),
+            _ => Vec::new(),
+        };
+
+        Ok(Identity {
+            subject,
+            email: text("email"),
+            email_verified,
+            preferred_username: text("preferred_username"),
+            name: text("name"),
+            groups,
+        })
+    }
+}
+
+fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// PKCE `S256` challenge for a verifier.
+pub fn code_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+/// Relying party for the configured provider.
+pub struct OidcClient {
+    config: OidcConfig,
+    http: reqwest::Client,
+    provider: RwLock<Option<Provider>>,
+}
+
+impl OidcClient {
+    pub fn new(config: OidcConfig, http: reqwest::Client) -> Self {
+        OidcClient {
+            config,
+            http,
+            provider: RwLock::new(None),
+        }
+    }
+
+    pub fn config(&self) -> &OidcConfig {
+        &self.config
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T, OidcError> {
+        let response = self
+            .http
+            .get(url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| OidcError::Provider(format!("GET {url}: {e}")))?;
+        response
+            .json()
+            .await
+            .map_err(|e| OidcError::Provider(format!("GET {url}: {e}")))
+    }
+
+    /// Fetch the discovery document and signing keys if they are not cached.
+    async fn ensure_provider(&self) -> Result<(), OidcError> {
+        if self.provider.read().await.is_some() {
+            return Ok(());
+        }
+        let mut provider = self.provider.write().await;
+        if provider.is_some() {
+            return Ok(());
+        }
+
+        let url = format!("{}/.well-known/openid-configuration", self.config.issuer);
+        let discovery: Discovery = self.get_json(&url).await?;
+        if discovery.issuer.trim_end_matches('/') != self.config.issuer {
+            return Err(OidcError::Provider(format!(
+                "discovery document is for issuer {}, expected {}",
+                discovery.issuer, self.config.issuer
+            )));
+        }
+        let jwks = self.get_json(&discovery.jwks_uri).await?;
+        *provider = Some(Provider {
+            discovery,
+            jwks,
+            jwks_fetched_at: Instant::now(),
+        });
+        Ok(())
+    }
+
+    /// Start a login: a fresh state, nonce and PKCE verifier, and the URL to
+    /// send the browser to.
+    pub async fn authorization_request(&self) -> Result<AuthorizationRequest, OidcError> {
+        self.ensure_provider().await?;
+        let provider = self.provider.read().await;
+        let discovery = &provider
+            .as_ref()
+            .expect("provider was just loaded")
+            .discovery;
+
+        let state = random_token();
+        let nonce = random_token();
+        let code_verifier = random_token();
+
+        let mut url = url::Url::parse(&discovery.authorization_endpoint)
+            .map_err(|e| OidcError::Provider(format!("invalid authorization endpoint: {e}")))?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.config.client_id)
+            .append_pair("redirect_uri", &self.config.redirect_uri)
+            .append_pair("scope", &self.config.scopes.join(" "))
+            .append_pair("state", &state)
+            .append_pair("nonce", &nonce)
+            .append_pair("code_challenge", &code_challenge(&code_verifier))
+            .append_pair("code_challenge_method", "S256");
+
+        Ok(AuthorizationRequest {
+            url: url.into(),
+            state,
+            nonce,
+            code_verifier,
+        })
+    }
+
+    /// Exchange an authorization code for an ID token and validate it.
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        code_verifier: &str,
+        nonce: &str,
+    ) -> Result<Identity, OidcError> {
+        self.ensure_provider().await?;
+        let (token_endpoint, basic_auth) = {
+            let provider = self.provider.read().await;
+            let discovery = &provider
+                .as_ref()
+                .expect("provider was just loaded")
+                .discovery;
+            // client_secret_basic is the default; use client_secret_post only
+            // when the provider says it is all it supports.
+            let methods = &discovery.token_endpoint_auth_methods_supported;
+            let basic_auth =
+                methods.is_empty() || methods.iter().any(|m| m == "client_secret_basic");
+            (discovery.token_endpoint.clone(), basic_auth)
+        };
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.config.redirect_uri.as_str()),
+            ("code_verifier", code_verifier),
+            ("client_id", self.config.client_id.as_str()),
+        ];
+        let mut request = self.http.post(&token_endpoint);
+        match &self.config.client_secret {
+            Some(secret) if basic_auth => {
+                request = request.basic_auth(
+                    urlencoding::encode(&self.config.client_id),
+                    Some(urlencoding::encode(secret)),
+                );
+            }
+            Some(secret) => form.push(("client_secret", secret.as_str())),
+            None => {}
+        }
+
+        let response = request
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| OidcError::Provider(format!("token request failed: {e}")))?;
+        let status = response.status();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| OidcError::Provider(format!("token request failed: {e}")))?;
+        if status.is_client_error() {
+            // invalid_grant and friends: a used, expired or forged code.
+            let message = serde_json::from_slice::<TokenError>(&body)
+                .map(|e| e.error_description.unwrap_or(e.error))
+                .unwrap_or_else(|_| status.to_string());
+            return Err(OidcError::Rejected(format!(
+                "The identity provider refused the login: {message}"
+            )));
+        }
+        if !status.is_success() {
+            return Err(OidcError::Provider(format!(
+                "token endpoint returned {status}"
+            )));
+        }
+        let id_token = serde_json::from_slice::<TokenResponse>(&body)
+            .map_err(|e| OidcError::Provider(format!("malformed token response: {e}")))?
+            .id_token
+            .ok_or_else(|| OidcError::Provider("token response has no id_token".into()))?;
+
+        self.validate_id_token(&id_token, nonce).await
+    }
+
+    /// The decoding key for `kid`, refetching the provider's keys once if it
+    /// is unknown.
+    async fn decoding_key(&self, kid: Option<&str>) -> Result<DecodingKey, OidcError> {
+        let find = |jwks: &JwkSet| match kid {
+            Some(kid) => jwks.find(kid).cloned(),
+            // Without a key ID, only an unambiguous single key will do.
+            None if jwks.keys.len() == 1 => jwks.keys.first().cloned(),
+            None => None,
+        };
+
+        let (found, jwks_uri, stale) = {
+            let provider = self.provider.read().await;
+            let provider = provider.as_ref().expect("provider was just loaded");
+            (
+                find(&provider.jwks),
+                provider.discovery.jwks_uri.clone(),
+                provider.jwks_fetched_at.elapsed() >= JWKS_REFETCH_INTERVAL,
+            )
+        };
+        let jwk = match found {
+            Some(jwk) => jwk,
+            None if stale => {
+                let jwks: JwkSet = self.get_json(&jwks_uri).await?;
+                let jwk = find(&jwks);
+                if let Some(provider) = self.provider.write().await.as_mut() {
+                    provider.jwks = jwks;
+                    provider.jwks_fetched_at = Instant::now();
+                }
+                jwk.ok_or_else(|| {
+                    OidcError::Rejected("ID token signed with an unknown key".into())
+                })?
+            }
+            None => {
+                return Err(OidcError::Rejected(
+                    "ID token signed with an unknown key".into(),
+                ))
+            }
+        };
+        DecodingKey::from_jwk(&jwk)
+            .map_err(|e| OidcError::Provider(format!("unusable signing key: {e}")))
+    }
+
+    async fn validate_id_token(&self, id_token: &str, nonce: &str) -> Result<Identity, OidcError> {
+        let header = jsonwebtoken::decode_header(id_token)
+            .map_err(|_| OidcError::Rejected("Malformed ID token".into()))?;
+        if !ALLOWED_ALGORITHMS.contains(&header.alg) {
+            return Err(OidcError::Rejected(format!(
+                "ID token algorithm {:?} is not allowed",
+                header.alg
+            )));
+        }
+        let key = self.decoding_key(header.kid.as_deref()).await?;
+        // The exact issuer string, which may end in a slash.
+        let issuer = self
+            .provider
+            .read()
+            .await
+            .as_ref()
+            .expect("provider was just loaded")
+            .discovery
+            .issuer
+            .clone();
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[issuer]);
+        validation.set_audience(&[&self.config.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        let claims = jsonwebtoken::decode::<Map<String, Value>>(id_token, &key, &validation)
+            .map_err(|e| OidcError::Rejected(format!("Invalid ID token: {e}")))?
+            .claims;
+
+        if claims.get("nonce").and_then(Value::as_str) != Some(nonce) {
+            return Err(OidcError::Rejected("ID token nonce does not match".into()));
+        }
+        // With several audiences, the token must name this client as the party
+        // it was issued to.
+        if let Some(azp) = claims.get("azp").and_then(Value::as_str) {
+            if azp != self.config.client_id {
+                return Err(OidcError::Rejected(
+                    "ID token was issued to another client".into(),
+                ));
+            }
+        }
+
+        Identity::from_claims(&claims, &self.config.groups_claim)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn claims(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn code_challenge_is_s256() {
+        // RFC 7636, appendix B.
+        assert_eq!(
+            code_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[test]
+    fn identity_reads_standard_claims() {
+        let identity = Identity::from_claims(
+            &claims(json!({
+                "sub": "abc",
+                "email": "ada@example.com",
+                "email_verified": true,
+                "preferred_username": "ada",
+                "groups": ["eng", "admins"],
+            })),
+            "groups",
+        )
+        .unwrap();
+        assert_eq!(identity.subject, "abc");
+        assert_eq!(identity.verified_email(), Some("ada@example.com"));
+        assert_eq!(identity.preferred_username.as_deref(), Some("ada"));
+        assert_eq!(identity.groups, ["eng", "admins"]);
+    }
+
+    #[test]
+    fn identity_handles_provider_quirks() {
+        let identity = Identity::from_claims(
+            &claims(json!({
+                "sub": "abc",
+                "email": "ada@example.com",
+                "email_verified": "true",
+                "roles": "eng admins",
+            })),
+            "roles",
+        )
+        .unwrap();
+        assert!(identity.email_verified);
+        assert_eq!(identity.groups, ["eng", "admins"]);
+
+        let unverified = Identity::from_claims(
+            &claims(json!({ "sub": "abc", "email": "ada@example.com" })),
+            "groups",
+        )
+        .unwrap();
+        assert_eq!(unverified.verified_email(), None);
+        assert!(unverified.groups.is_empty());
+
+        assert!(Identity::from_claims(&claims(json!({ "email": "a@b.c" })), "groups").is_err());
+    }
+}
@@ -0,0 +1,456 @@
+//! WebAuthn (passkey) verification for registration and login ceremonies.
+//!
+//! Only what a relying party needs is implemented: parsing `clientDataJSON`,
+//! authenticator data and COSE public keys, and checking assertion
+//! signatures. Credentials are requested with `attestation: "none"`, so the
+//! attestation statement is not verified; the authenticator is trusted the
+//! same way a password manager would be. Supported algorithms are ES256,
+//! EdDSA (Ed25519) and RS256, which covers platform authenticators, security
+//! keys and Windows Hello.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::Value;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// COSE algorithm identifiers, in order of preference.
+pub const ES256: i64 = -7;
+pub const EDDSA: i64 = -8;
+pub const RS256: i64 = -257;
+pub const SUPPORTED_ALGORITHMS: [i64; 3] = [ES256, EDDSA, RS256];
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_BACKUP_ELIGIBLE: u8 = 0x08;
+const FLAG_ATTESTED_DATA: u8 = 0x40;
+
+/// Relying party identity the ceremonies are checked against.
+pub struct RelyingParty<'a> {
+    /// Domain the credentials are scoped to, e.g. "chat.example.com".
+    pub id: &'a str,
+    /// Origins the browser may report, e.g. "https://chat.example.com".
+    pub origins: &'a [String],
+}
+
+/// Which ceremony a `clientDataJSON` must belong to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ceremony {
+    Create,
+    Get,
+}
+
+impl Ceremony {
+    fn client_data_type(self) -> &'static str {
+        match self {
+            Ceremony::Create => "webauthn.create",
+            Ceremony::Get => "webauthn.get",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    kind: String,
+    challenge: String,
+    origin: String,
+    #[serde(rename = "crossOrigin", default)]
+    cross_origin: bool,
+}
+
+/// Check `clientDataJSON` for a ceremony and return the challenge it signs,
+/// base64url-encoded as the server issued it.
+pub fn client_data_challenge(
+    rp: &RelyingParty<'_>,
+    client_data_json: &[u8],
+    ceremony: Ceremony,
+) -> Result<String, &'static str> {
+    let data: ClientData =
+        serde_json::from_slice(client_data_json).map_err(|_| "Malformed client data")?;
+    if data.kind != ceremony.client_data_type() {
+        return Err("Client data is for a different ceremony");
+    }
+    if data.cross_origin || !rp.origins.contains(&data.origin) {
+        return Err("Origin is not allowed");
+    }
+    Ok(data.challenge)
+}
+
+/// The fields of authenticator data the server uses.
+struct AuthenticatorData {
+    flags: u8,
+    sign_count: u32,
+    /// Credential ID and COSE public key; present during registration.
+    attested: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+/// Parse authenticator data, checking that it is for this relying party and
+/// that the user was present.
+fn parse_authenticator_data(
+    rp: &RelyingParty<'_>,
+    data: &[u8],
+) -> Result<AuthenticatorData, &'static str> {
+    if data.len() < 37 {
+        return Err("Authenticator data is too short");
+    }
+    if data[..32] != Sha256::digest(rp.id.as_bytes())[..] {
+        return Err("Credential is for a different site");
+    }
+    let flags = data[32];
+    if flags & FLAG_USER_PRESENT == 0 {
+        return Err("User presence was not confirmed");
+    }
+    let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+
+    let attested = if flags & FLAG_ATTESTED_DATA != 0 {
+        // AAGUID (16 bytes), credential ID length (2), credential ID, COSE key.
+        let rest = data
+            .get(37 + 16..)
+            .ok_or("Attested credential data is truncated")?;
+        if rest.len() < 2 {
+            return Err("Attested credential data is truncated");
+        }
+        let id_len = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+        let credential_id = rest
+            .get(2..2 + id_len)
+            .ok_or("Attested credential data is truncated")?
+            .to_vec();
+        let key_bytes = &rest[2 + id_len..];
+        // Extensions may follow the key, so measure the key by decoding it.
+        let mut reader = key_bytes;
+        ciborium::de::from_reader::<Value, _>(&mut reader)
+            .map_err(|_| "Malformed credential public key")?;
+        let key_len = key_bytes.len() - reader.len();
+        Some((credential_id, key_bytes[..key_len].to_vec()))
+    } else {
+        None
+    };
+
+    Ok(AuthenticatorData {
+        flags,
+        sign_count,
+        attested,
+    })
+}
+
+/// A credential public key decoded from its COSE form.
+enum CoseKey {
+    Es256(p256::ecdsa::VerifyingKey),
+    EdDsa(ed25519_dalek::VerifyingKey),
+    Rs256(rsa::RsaPublicKey),
+}
+
+fn cose_field(map: &[(Value, Value)], label: i64) -> Option<&Value> {
+    map.iter()
+        .find(|(k, _)| {
+            k.as_integer()
+                .is_some_and(|i| i128::from(i) == label as i128)
+        })
+        .map(|(_, v)| v)
+}
+
+fn cose_int(map: &[(Value, Value)], label: i64) -> Option<i128> {
+    cose_field(map, label)?.as_integer().map(i128::from)
+}
+
+fn cose_bytes(map: &[(Value, Value)], label: i64) -> Option<&[u8]> {
+    cose_field(map, label)?.as_bytes().map(Vec::as_slice)
+}
+
+impl CoseKey {
+    fn parse(bytes: &[u8]) -> Result<Self, &'static str> {
+        let value: Value =
+            ciborium::de::from_reader(bytes).map_err(|_| "Malformed credential public key")?;
+        let map = value.as_map().ok_or("Malformed credential public key")?;
+        let kty = cose_int(map, 1);
+        let alg = cose_int(map, 3);
+
+        match (kty, alg.map(|a| a as i64)) {
+            // EC2, P-256
+            (Some(2), Some(ES256)) => {
+                let (Some(x), Some(y)) = (cose_bytes(map, -2), cose_bytes(map, -3)) else {
+                    return Err("Malformed ES256 key");
+                };
+                if cose_int(map, -1) != Some(1) || x.len() != 32 || y.len() != 32 {
+                    return Err("Malformed ES256 key");
+                }
+                let mut sec1 = Vec::with_capacity(65);
+                sec1.push(0x04);
+                sec1.extend_from_slice(x);
+                sec1.extend_from_slice(y);
+                p256::ecdsa::VerifyingKey::from_sec1_bytes(&sec1)
+                    .map(CoseKey::Es256)
+                    .map_err(|_| "Malformed ES256 key")
+            }
+            // OKP, Ed25519
+            (Some(1), Some(EDDSA)) => {
+                let x: &[u8; 32] = cose_bytes(map, -2)
+                    .and_then(|x| x.try_into().ok())
+                    .filter(|_| cose_int(map, -1) == Some(6))
+                    .ok_or("Malformed EdDSA key")?;
+                ed25519_dalek::VerifyingKey::from_bytes(x)
+                    .map(CoseKey::EdDsa)
+                    .map_err(|_| "Malformed EdDSA key")
+            }
+            // RSA
+            (Some(3), Some(RS256)) => {
+                let (Some(n), Some(e)) = (cose_bytes(map, -1), cose_bytes(map, -2)) else {
+                    return Err("Malformed RS256 key");
+                };
+                rsa::RsaPublicKey::new(
+                    rsa::BigUint::from_bytes_be(n),
+                    rsa::BigUint::from_bytes_be(e),
+                )
+                .map(CoseKey::Rs256)
+                .map_err(|_| "Malformed RS256 key")
+            }
+            _ => Err("Unsupported credential algorithm"),
+        }
+    }
+
+    fn algorithm(&self) -> i64 {
+        match self {
+            CoseKey::Es256(_) => ES256,
+            CoseKey::EdDsa(_) => EDDSA,
+            CoseKey::Rs256(_) => RS256,
+        }
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        use rsa::signature::Verifier;
+
+        match self {
+            CoseKey::Es256(key) => p256::ecdsa::Signature::from_der(signature)
+                .is_ok_and(|sig| key.verify(message, &sig).is_ok()),
+            CoseKey::EdDsa(key) => ed25519_dalek::Signature::from_slice(signature)
+                .is_ok_and(|sig| key.verify_strict(message, &sig).is_ok()),
+            CoseKey::Rs256(key) => rsa::pkcs1v15::Signature::try_from(signature).is_ok_and(|sig| {
+                rsa::pkcs1v15::VerifyingKey::<Sha256>::new(key.clone())
+                    .verify(message, &sig)
+                    .is_ok()
+            }),
+        }
+    }
+}
+
+/// A credential created by a registration ceremony.
+#[derive(Debug)]
+pub struct NewCredential {
+    pub credential_id: Vec<u8>,
+    /// COSE_Key bytes, stored as received.
+    pub public_key: Vec<u8>,
+    pub algorithm: i64,
+    pub sign_count: u32,
+    /// The credential may be synced to other devices (a multi-device passkey).
+    pub backup_eligible: bool,
+}
+
+/// Extract the new credential from a registration's attestation object.
+/// The client data must already have been checked with
+/// [`client_data_challenge`].
+pub fn verify_registration(
+    rp: &RelyingParty<'_>,
+    attestation_object: &[u8],
+) -> Result<NewCredential, &'static str> {
+    let value: Value =
+        ciborium::de::from_reader(attestation_object).map_err(|_| "Malformed attestation")?;
+    let auth_data = value
+        .as_map()
+        .and_then(|map| {
+            map.iter()
+                .find(|(k, _)| k.as_text() == Some("authData"))
+                .and_then(|(_, v)| v.as_bytes())
+        })
+        .ok_or("Malformed attestation")?;
+
+    let data = parse_authenticator_data(rp, auth_data)?;
+    let (credential_id, public_key) = data.attested.ok_or("Attestation has no credential data")?;
+    if credential_id.is_empty() || credential_id.len() > 1023 {
+        return Err("Invalid credential ID");
+    }
+    let algorithm = CoseKey::parse(&public_key)?.algorithm();
+
+    Ok(NewCredential {
+        credential_id,
+        public_key,
+        algorithm,
+        sign_count: data.sign_count,
+        backup_eligible: data.flags & FLAG_BACKUP_ELIGIBLE != 0,
+    })
+}
+
+/// Outcome of a valid assertion.
+#[derive(Debug)]
+pub struct Assertion {
+    pub sign_count: u32,
+    /// The authenticator checked a PIN or biometric, not just presence.
+    pub user_verified: bool,
+}
+
+/// Check an assertion's signature with a stored credential. The client data
+/// must already have been checked with [`client_data_challenge`].
+///
+/// `stored_sign_count` is the counter from the last use. Authenticators that
+/// keep a counter must report a higher one each time; a lower or equal value
+/// suggests a cloned authenticator and the assertion is refused.
+pub fn verify_assertion(
+    rp: &RelyingParty<'_>,
+    public_key: &[u8],
+    stored_sign_count: u32,
+    authenticator_data: &[u8],
+    client_data_json: &[u8],
+    signature: &[u8],
+) -> Result<Assertion, &'static str> {
+    let data = parse_authenticator_data(rp, authenticator_data)?;
+    let key = CoseKey::parse(public_key)?;
+
+    let mut message = authenticator_data.to_vec();
+    message.extend_from_slice(&Sha256::digest(client_data_json));
+    if !key.verify(&message, signature) {
+        return Err("Invalid signature");
+    }
+    if (data.sign_count != 0 || stored_sign_count != 0) && data.sign_count <= stored_sign_count {
+        return Err("Signature counter did not increase");
+    }
+
+    Ok(Assertion {
+        sign_count: data.sign_count,
+        user_verified: data.flags & FLAG_USER_VERIFIED != 0,
+    })
+}
+
+/// Base64url without padding, the encoding WebAuthn's JSON forms use for
+/// binary fields.
+pub fn encode(bytes: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Decode a base64url field, tolerating padding.
+pub fn decode(text: &str) -> Option<Vec<u8>> {
+    URL_SAFE_NO_PAD.decode(text.trim_end_matches('=')).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::Signer;
+
+    const ORIGIN: &str = "https://chat.example.com";
+
+    fn rp(origins: &[String]) -> RelyingParty<'_> {
+        RelyingParty {
+            id: "chat.example.com",
+            origins,
+        }
+    }
+
+    fn cbor(value: &Value) -> Vec<u8> {
+        let mut out = Vec::new();
+        ciborium::ser::into_writer(value, &mut out).unwrap();
+        out
+    }
+
+    fn ed25519_cose(key: &ed25519_dalek::SigningKey) -> Vec<u8> {
+        cbor(&Value::Map(vec![
+            (Value::from(1), Value::from(1)),
+            (Value::from(3), Value::from(EDDSA)),
+            (Value::from(-1), Value::from(6)),
+            (
+                Value::from(-2),
+                Value::Bytes(key.verifying_key().to_bytes().to_vec()),
+            ),
+        ]))
+    }
+
+    fn auth_data(flags: u8, sign_count: u32, attested: Option<(&[u8], &[u8])>) -> Vec<u8> {
+        let mut data = Sha256::digest(b"chat.example.com").to_vec();
+        data.push(flags);
+        data.extend_from_slice(&sign_count.to_be_bytes());
+        if let Some((id, key)) = attested {
+            data.extend_from_slice(&[0; 16]);
+            data.extend_from_slice(&(id.len() as u16).to_be_bytes());
+            data.extend_from_slice(id);
+            data.extend_from_slice(key);
+        }
+        data
+    }
+
+    #[test]
+    fn client_data_checks_type_and_origin() {
+        let origins = vec![ORIGIN.to_string()];
+        let json =
+            br#"{"type":"webauthn.get","challenge":"abc","origin":"https://chat.example.com"}"#;
+        assert_eq!(
+            client_data_challenge(&rp(&origins), json, Ceremony::Get).unwrap(),
+            "abc"
+        );
+        assert!(client_data_challenge(&rp(&origins), json, Ceremony::Create).is_err());
+
+        let evil = br#"{"type":"webauthn.get","challenge":"abc","origin":"https://evil.example"}"#;
+        assert!(client_data_challenge(&rp(&origins), evil, Ceremony::Get).is_err());
+    }
+
+    #[test]
+    fn registration_then_assertion_with_ed25519() {
+        let origins = vec![ORIGIN.to_string()];
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let cose = ed25519_cose(&key);
+        let attestation = cbor(&Value::Map(vec![
+            (Value::from("fmt"), Value::from("none")),
+            (Value::from("attStmt"), Value::Map(vec![])),
+            (
+                Value::from("authData"),
+                Value::Bytes(auth_data(0x45, 0, Some((b"cred-1", &cose)))),
+            ),
+        ]));
+
+        let credential = verify_registration(&rp(&origins), &attestation).unwrap();
+        assert_eq!(credential.credential_id, b"cred-1");
+        assert_eq!(credential.public_key, cose);
+        assert_eq!(credential.algorithm, EDDSA);
+
+        let client_data =
+            br#"{"type":"webauthn.get","challenge":"abc","origin":"https://chat.example.com"}"#;
+        let data = auth_data(0x05, 3, None);
+        let mut message = data.clone();
+        message.extend_from_slice(&Sha256::digest(client_data));
+        let signature = key.sign(&message).to_bytes();
+
+        let assertion =
+            verify_assertion(&rp(&origins), &cose, 0, &data, client_data, &signature).unwrap();
+        assert_eq!(assertion.sign_count, 3);
+        assert!(assertion.user_verified);
+
+        // Replayed counter.
+        assert!(verify_assertion(&rp(&origins), &cose, 3, &data, client_data, &signature).is_err());
+        // Tampered client data.
+        assert!(verify_assertion(
+            &rp(&origins),
+            &cose,
+            0,
+            &data,
+            br#"{"type":"webauthn.get","challenge":"xyz","origin":"https://chat.example.com"}"#,
+            &signature
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn authenticator_data_for_other_site_is_rejected() {
+        let origins = vec![ORIGIN.to_string()];
+        let mut data = auth_data(0x01, 0, None);
+        data[0] ^= 0xff;
+        assert!(parse_authenticator_data(&rp(&origins), &data).is_err());
+        // User presence flag missing.
+        assert!(parse_authenticator_data(&rp(&origins), &auth_data(0x00, 0, None)).is_err());
+    }
+
+    #[test]
+    fn base64url_round_trips() {
+        assert_eq!(
+            decode(&encode(b"\xff\x00passkey")).unwrap(),
+            b"\xff\x00passkey"
+        );
+        assert_eq!(decode("YQ==").unwrap(), b"a");
+    }
+}
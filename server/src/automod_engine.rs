@@ -0,0 +1,677 @@
+//! Compiled per-server automod configuration.
+//!
+//! Every message posted to a server is checked against its automod setup:
+//! the built-in word list (`automod_word_filters`) and the configurable rules
+//! in `automod_rules`. Loading and compiling those for each message would
+//! cost several queries and regex compilations, so a server's setup is
+//! compiled once into a [`CompiledAutomod`] and cached per node in an
+//! [`AutomodCache`]. Evaluating a message against it is pure and in-memory;
+//! only the author's roles and account age are fetched, and only when a rule
+//! needs them.
+//!
+//! Writes to the config, word list or rules invalidate the server's entry on
+//! every node over the event bus; the TTL only bounds staleness if an
+//! invalidation is lost.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use regex::{Regex, RegexBuilder};
+use sqlx::PgPool;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::models::{AutomodConfig, AutomodRule, AutomodRuleAction, AutomodTrigger};
+
+/// Upper bound on how long a compiled configuration is trusted.
+pub const AUTOMOD_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Compiled-size cap for user-supplied regular expressions.
+const MAX_REGEX_SIZE: usize = 1 << 20;
+
+/// `http(s)://host…` or `www.host…`; the host is extracted afterwards.
+static LINK_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)(?:https?://|\bwww\.)[^\s/?#<>"']+"#).unwrap());
+
+/// This server's `/invite/<code>` links plus Discord invites.
+static INVITE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(?:discord(?:app)?\.com/invite/|discord\.gg/|/invites?/)[a-z0-9-]+").unwrap()
+});
+
+// ── Matchers ──────────────────────────────────────────────────────────────────
+
+enum Matcher {
+    /// Capture group 1 is the matched keyword.
+    Keyword(Regex),
+    Regex(Vec<Regex>),
+    Link(Vec<String>),
+    Invite,
+    MentionSpam(usize),
+    Caps {
+        min_ratio: f64,
+        min_letters: usize,
+    },
+    AccountAge(chrono::Duration),
+}
+
+/// Compile a keyword list into one case-insensitive whole-word matcher.
+///
+/// Returns `Ok(None)` for an empty list.
+pub fn compile_keywords(keywords: &[String]) -> Result<Option<Regex>, String> {
+    let mut alternatives = Vec::with_capacity(keywords.len());
+    for keyword in keywords {
+        let keyword = keyword.trim();
+        let core = keyword.trim_matches('*');
+        if core.is_empty() {
+            return Err("Keywords must contain at least one non-wildcard character".into());
+        }
+        let prefix = if keyword.starts_with('*') { r"\w*" } else { "" };
+        let suffix = if keyword.ends_with('*') { r"\w*" } else { "" };
+        alternatives.push(format!("{prefix}{}{suffix}", regex::escape(core)));
+    }
+    if alternatives.is_empty() {
+        return Ok(None);
+    }
+    let pattern = format!(r"(?i)(?:^|\W)({})(?:\W|$)", alternatives.join("|"));
+    Regex::new(&pattern)
+        .map(Some)
+        .map_err(|e| format!("Keyword list is too large: {e}"))
+}
+
+fn compile_trigger(trigger: &AutomodTrigger) -> Result<Matcher, String> {
+    Ok(match trigger {
+        AutomodTrigger::Keyword { keywords } => match compile_keywords(keywords)? {
+            Some(re) => Matcher::Keyword(re),
+            None => return Err("keywords must not be empty".into()),
+        },
+        AutomodTrigger::Regex { patterns } => {
+            if patterns.is_empty() {
+                return Err("patterns must not be empty".into());
+            }
+            let compiled = patterns
+                .iter()
+                .map(|p| {
+                    RegexBuilder::new(p)
+                        .size_limit(MAX_REGEX_SIZE)
+                        .build()
+                        .map_err(|e| format!("Invalid regex '{p}': {e}"))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Matcher::Regex(compiled)
+        }
+        AutomodTrigger::Link { allowed_domains } => Matcher::Link(
+            allowed_domains
+                .iter()
+                .map(|d| d.trim().trim_start_matches("*.").to_ascii_lowercase())
+                .filter(|d| !d.is_empty())
+                .collect(),
+        ),
+        AutomodTrigger::Invite => Matcher::Invite,
+        AutomodTrigger::MentionSpam { max_mentions } => {
+            Matcher::MentionSpam(*max_mentions as usize)
+        }
+        AutomodTrigger::Caps {
+            min_ratio,
+            min_letters,
+        } => {
+            if !(0.0..=1.0).contains(min_ratio) || *min_ratio == 0.0 {
+                return Err("min_ratio must be greater than 0 and at most 1".into());
+            }
+            Matcher::Caps {
+                min_ratio: *min_ratio,
+                min_letters: (*min_letters).max(1) as usize,
+            }
+        }
+        AutomodTrigger::AccountAge { min_age_minutes } => {
+            Matcher::AccountAge(chrono::Duration::minutes(*min_age_minutes as i64))
+        }
+    })
+}
+
+/// Check that a trigger compiles, returning a user-facing error if not.
+pub fn validate_trigger(trigger: &AutomodTrigger) -> Result<(), String> {
+    compile_trigger(trigger).map(|_| ())
+}
+
+/// Stable name of a trigger kind, used as `automod_logs.rule_type`.
+pub fn trigger_type(trigger: &AutomodTrigger) -> &'static str {
+    match trigger {
+        AutomodTrigger::Keyword { .. } => "keyword",
+        AutomodTrigger::Regex { .. } => "regex",
+        AutomodTrigger::Link { .. } => "link",
+        AutomodTrigger::Invite => "invite",
+        AutomodTrigger::MentionSpam { .. } => "mention_spam",
+        AutomodTrigger::Caps { .. } => "caps",
+        AutomodTrigger::AccountAge { .. } => "account_age",
+    }
+}
+
+fn link_host(link: &str) -> String {
+    let lower = link.to_ascii_lowercase();
+    let authority = lower
+        .strip_prefix("https://")
+        .or_else(|| lower.strip_prefix("http://"))
+        .unwrap_or(&lower);
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    host.split(':').next().unwrap_or(host).to_string()
+}
+
+fn domain_allowed(host: &str, allowed: &[String]) -> bool {
+    allowed
+        .iter()
+        .any(|d| host == d || host.ends_with(&format!(".{d}")))
+}
+
+/// Distinct `@name` tokens in `content`, with trailing punctuation stripped.
+fn count_mentions(content: &str) -> usize {
+    content
+        .split_whitespace()
+        .filter_map(|word| {
+            word.strip_prefix('@')
+                .map(|name| name.trim_end_matches(|c: char| !c.is_alphanumeric() && c != '_'))
+        })
+        .filter(|name| !name.is_empty())
+        .map(str::to_lowercase)
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+impl Matcher {
+    /// `Some(matched_term)` if the message triggers; the term may be absent
+    /// for triggers that don't match a span of text.
+    fn find(&self, ctx: &MessageContext<'_>) -> Option<Option<String>> {
+        let content = ctx.content;
+        match self {
+            Matcher::Keyword(re) => re
+                .captures(content)
+                .and_then(|c| c.get(1))
+                .map(|m| Some(m.as_str().to_string())),
+            Matcher::Regex(patterns) => patterns
+                .iter()
+                .find_map(|re| re.find(content))
+                .map(|m| Some(m.as_str().to_string())),
+            Matcher::Link(allowed) => LINK_REGEX
+                .find_iter(content)
+                .find(|m| !domain_allowed(&link_host(m.as_str()), allowed))
+                .map(|m| Some(m.as_str().to_string())),
+            Matcher::Invite => INVITE_REGEX
+                .find(content)
+                .map(|m| Some(m.as_str().to_string())),
+            Matcher::MentionSpam(max) => {
+                let count = count_mentions(content);
+                (count > *max).then(|| Some(format!("{count} mentions")))
+            }
+            Matcher::Caps {
+                min_ratio,
+                min_letters,
+            } => {
+                let (upper, cased) = content.chars().fold((0usize, 0usize), |(u, n), c| {
+                    if c.is_uppercase() {
+                        (u + 1, n + 1)
+                    } else if c.is_lowercase() {
+                        (u, n + 1)
+                    } else {
+                        (u, n)
+                    }
+                });
+                (cased >= *min_letters && upper as f64 / cased as f64 >= *min_ratio).then_some(None)
+            }
+            Matcher::AccountAge(min_age) => {
+                let age = ctx.member.as_ref()?.account_age?;
+                (age < *min_age).then_some(None)
+            }
+        }
+    }
+}
+
+// ── Rules ─────────────────────────────────────────────────────────────────────
+
+/// An enabled rule ready for evaluation.
+pub struct CompiledRule {
+    pub id: Uuid,
+    pub name: String,
+    pub trigger_type: &'static str,
+    pub actions: Vec<AutomodRuleAction>,
+    matcher: Matcher,
+    channel_ids: Vec<Uuid>,
+    role_ids: Vec<Uuid>,
+    exempt_channel_ids: Vec<Uuid>,
+    exempt_role_ids: Vec<Uuid>,
+}
+
+impl CompiledRule {
+    fn compile(rule: AutomodRule) -> Result<Self, String> {
+        Ok(CompiledRule {
+            matcher: compile_trigger(&rule.trigger)?,
+            trigger_type: trigger_type(&rule.trigger),
+            id: rule.id,
+            name: rule.name,
+            actions: rule.actions,
+            channel_ids: rule.channel_ids,
+            role_ids: rule.role_ids,
+            exempt_channel_ids: rule.exempt_channel_ids,
+            exempt_role_ids: rule.exempt_role_ids,
+        })
+    }
+
+    fn needs_member_context(&self) -> bool {
+        !self.role_ids.is_empty()
+            || !self.exempt_role_ids.is_empty()
+            || matches!(self.matcher, Matcher::AccountAge(_))
+    }
+
+    fn applies_to(&self, ctx: &MessageContext<'_>) -> bool {
+        if !self.channel_ids.is_empty() && !self.channel_ids.contains(&ctx.channel_id) {
+            return false;
+        }
+        if self.exempt_channel_ids.contains(&ctx.channel_id) {
+            return false;
+        }
+        let roles = ctx.member.as_ref().map_or(&[][..], |m| m.role_ids);
+        if !self.role_ids.is_empty() && !roles.iter().any(|r| self.role_ids.contains(r)) {
+            return false;
+        }
+        !roles.iter().any(|r| self.exempt_role_ids.contains(r))
+    }
+
+    /// Whether any of this rule's actions blocks the message.
+    pub fn blocks(&self) -> bool {
+        self.actions
+            .iter()
+            .any(|a| matches!(a, AutomodRuleAction::Delete))
+    }
+}
+
+/// Author details some rules depend on. Absent for webhook posts.
+pub struct MemberContext<'a> {
+    pub role_ids: &'a [Uuid],
+    /// `None` when it was not loaded because no rule needs it.
+    pub account_age: Option<chrono::Duration>,
+}
+
+/// The message being checked.
+pub struct MessageContext<'a> {
+    pub channel_id: Uuid,
+    pub content: &'a str,
+    pub member: Option<MemberContext<'a>>,
+}
+
+pub struct RuleMatch<'a> {
+    pub rule: &'a CompiledRule,
+    pub matched: Option<String>,
+}
+
+// ── Compiled server configuration ─────────────────────────────────────────────
+
+/// Everything automod needs to check a message in one server.
+pub struct CompiledAutomod {
+    /// Built-in checks; `None` if the server never configured automod.
+    pub config: Option<AutomodConfig>,
+    word_filter: Option<Regex>,
+    rules: Vec<CompiledRule>,
+}
+
+impl CompiledAutomod {
+    /// Compile a server's configuration. Disabled rules are dropped; a stored
+    /// rule that no longer compiles is skipped with a warning.
+    pub fn compile(
+        config: Option<AutomodConfig>,
+        words: &[String],
+        rules: Vec<AutomodRule>,
+    ) -> Self {
+        let word_filter = compile_keywords(words).unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Failed to compile automod word filter");
+            None
+        });
+        let rules = rules
+            .into_iter()
+            .filter(|r| r.enabled)
+            .filter_map(|r| {
+                let id = r.id;
+                CompiledRule::compile(r)
+                    .map_err(|e| tracing::warn!(rule_id = %id, error = %e, "Skipping automod rule"))
+                    .ok()
+            })
+            .collect();
+        CompiledAutomod {
+            config,
+            word_filter,
+            rules,
+        }
+    }
+
+    /// Load and compile the configuration of `server_id`.
+    pub async fn load(pool: &PgPool, server_id: Uuid) -> Result<Self, sqlx::Error> {
+        let config = sqlx::query_as::<_, AutomodConfig>(
+            "SELECT server_id, enabled, spam_enabled, spam_max_messages, spam_window_secs,
+                    spam_action, duplicate_enabled, word_filter_enabled, word_filter_action,
+                    timeout_minutes, updated_at
+             FROM automod_configs WHERE server_id = $1",
+        )
+        .bind(server_id)
+        .fetch_optional(pool)
+        .await?;
+
+        let words: Vec<String> =
+            sqlx::query_scalar("SELECT word FROM automod_word_filters WHERE server_id = $1")
+                .bind(server_id)
+                .fetch_all(pool)
+                .await?;
+
+        let rules = sqlx::query_as::<_, AutomodRule>(
+            "SELECT id, server_id, name, enabled, trigger, actions, channel_ids, role_ids,
+                    exempt_channel_ids, exempt_role_ids, created_by, created_at, updated_at
+             FROM automod_rules WHERE server_id = $1 AND enabled = TRUE
+             ORDER BY created_at ASC",
+        )
+        .bind(server_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(Self::compile(config, &words, rules))
+    }
+
+    /// Whether evaluating rules needs the author's roles or account age.
+    pub fn needs_member_context(&self) -> bool {
+        self.rules.iter().any(CompiledRule::needs_member_context)
+    }
+
+    /// Every rule that applies to the message and matches it, in creation order.
+    pub fn evaluate(&self, ctx: &MessageContext<'_>) -> Vec<RuleMatch<'_>> {
+        self.rules
+            .iter()
+            .filter(|r| r.applies_to(ctx))
+            .filter_map(|rule| {
+                rule.matcher
+                    .find(ctx)
+                    .map(|matched| RuleMatch { rule, matched })
+            })
+            .collect()
+    }
+
+    /// The first word-list entry found in `content`, matched as a whole word.
+    pub fn match_word_filter(&self, content: &str) -> Option<String> {
+        let captures = self.word_filter.as_ref()?.captures(content)?;
+        Some(captures.get(1)?.as_str().to_lowercase())
+    }
+}
+
+// ── Cache ─────────────────────────────────────────────────────────────────────
+
+struct Entry {
+    compiled: Arc<CompiledAutomod>,
+    cached_at: Instant,
+}
+
+#[derive(Clone, Default)]
+pub struct AutomodCache {
+    entries: Arc<RwLock<HashMap<Uuid, Entry>>>,
+    /// Bumped on every invalidation so a load that raced with one is discarded.
+    generation: Arc<AtomicU64>,
+}
+
+impl AutomodCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the compiled configuration of `server_id`, loading it on a miss.
+    pub async fn get_or_load(
+        &self,
+        pool: &PgPool,
+        server_id: Uuid,
+    ) -> Result<Arc<CompiledAutomod>, sqlx::Error> {
+        if let Some(entry) = self.entries.read().await.get(&server_id) {
+            if entry.cached_at.elapsed() < AUTOMOD_CACHE_TTL {
+                return Ok(entry.compiled.clone());
+            }
+        }
+
+        let generation = self.generation.load(Ordering::Acquire);
+        let compiled = Arc::new(CompiledAutomod::load(pool, server_id).await?);
+        let mut entries = self.entries.write().await;
+        if self.generation.load(Ordering::Acquire) == generation {
+            entries.insert(
+                server_id,
+                Entry {
+                    compiled: compiled.clone(),
+                    cached_at: Instant::now(),
+                },
+            );
+        }
+        Ok(compiled)
+    }
+
+    /// Drop the cached configuration of `server_id`.
+    pub async fn invalidate(&self, server_id: Uuid) {
+        let mut entries = self.entries.write().await;
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        entries.remove(&server_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(trigger: AutomodTrigger) -> AutomodRule {
+        AutomodRule {
+            id: Uuid::new_v4(),
+            server_id: Uuid::new_v4(),
+            name: "test".into(),
+            enabled: true,
+            trigger,
+            actions: vec![AutomodRuleAction::Delete],
+            channel_ids: vec![],
+            role_ids: vec![],
+            exempt_channel_ids: vec![],
+            exempt_role_ids: vec![],
+            created_by: None,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    fn matches(rule: AutomodRule, content: &str) -> bool {
+        let compiled = CompiledAutomod::compile(None, &[], vec![rule]);
+        let ctx = MessageContext {
+            channel_id: Uuid::new_v4(),
+            content,
+            member: Some(MemberContext {
+                role_ids: &[],
+                account_age: None,
+            }),
+        };
+        !compiled.evaluate(&ctx).is_empty()
+    }
+
+    fn keywords(words: &[&str]) -> AutomodTrigger {
+        AutomodTrigger::Keyword {
+            keywords: words.iter().map(|w| w.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn keyword_matches_whole_words_only() {
+        assert!(matches(rule(keywords(&["ass"])), "what an ass!"));
+        assert!(matches(rule(keywords(&["ass"])), "ASS"));
+        assert!(!matches(rule(keywords(&["ass"])), "first class"));
+        assert!(!matches(rule(keywords(&["ass"])), "assessment"));
+    }
+
+    #[test]
+    fn keyword_wildcards_match_inside_words() {
+        assert!(matches(rule(keywords(&["*ass"])), "first class"));
+        assert!(matches(rule(keywords(&["ass*"])), "assessment"));
+        assert!(!matches(rule(keywords(&["ass*"])), "first class"));
+    }
+
+    #[test]
+    fn keyword_phrases_and_punctuation() {
+        assert!(matches(
+            rule(keywords(&["free nitro"])),
+            "get FREE NITRO now"
+        ));
+        assert!(matches(rule(keywords(&["spam"])), "(spam)"));
+    }
+
+    #[test]
+    fn empty_or_wildcard_only_keywords_are_rejected() {
+        assert!(validate_trigger(&keywords(&[])).is_err());
+        assert!(validate_trigger(&keywords(&["**"])).is_err());
+    }
+
+    #[test]
+    fn regex_trigger_and_invalid_pattern() {
+        let trigger = AutomodTrigger::Regex {
+            patterns: vec![r"(?i)b[a@]dw[o0]rd".into()],
+        };
+        assert!(matches(rule(trigger), "this is a B@DW0RD"));
+        assert!(validate_trigger(&AutomodTrigger::Regex {
+            patterns: vec!["(unclosed".into()]
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn link_trigger_respects_allowed_domains() {
+        let trigger = || AutomodTrigger::Link {
+            allowed_domains: vec!["example.com".into()],
+        };
+        assert!(matches(rule(trigger()), "see https://evil.test/x"));
+        assert!(matches(rule(trigger()), "see www.evil.test"));
+        assert!(!matches(rule(trigger()), "see https://docs.example.com/a"));
+        assert!(!matches(
+            rule(trigger()),
+            "see HTTPS://user@Example.com:443/"
+        ));
+        assert!(!matches(rule(trigger()), "no links here"));
+    }
+
+    #[test]
+    fn invite_trigger() {
+        assert!(matches(
+            rule(AutomodTrigger::Invite),
+            "join https://chat.example.com/invite/abc123"
+        ));
+        assert!(matches(rule(AutomodTrigger::Invite), "discord.gg/xyz"));
+        assert!(!matches(
+            rule(AutomodTrigger::Invite),
+            "https://example.com/docs"
+        ));
+    }
+
+    #[test]
+    fn mention_spam_counts_distinct_mentions() {
+        let trigger = || AutomodTrigger::MentionSpam { max_mentions: 2 };
+        assert!(!matches(rule(trigger()), "@a @b @a"));
+        assert!(matches(rule(trigger()), "@a @b @c!"));
+        assert!(matches(rule(trigger()), "@a @b @everyone"));
+    }
+
+    #[test]
+    fn caps_ratio_ignores_short_messages() {
+        let trigger = || AutomodTrigger::Caps {
+            min_ratio: 0.8,
+            min_letters: 5,
+        };
+        assert!(matches(rule(trigger()), "THIS IS LOUD"));
+        assert!(!matches(rule(trigger()), "OK!"));
+        assert!(!matches(rule(trigger()), "This Is Normal"));
+    }
+
+    #[test]
+    fn account_age_needs_member_context() {
+        let compiled = CompiledAutomod::compile(
+            None,
+            &[],
+            vec![rule(AutomodTrigger::AccountAge {
+                min_age_minutes: 60,
+            })],
+        );
+        assert!(compiled.needs_member_context());
+
+        let ctx = |age: Option<i64>| MessageContext {
+            channel_id: Uuid::new_v4(),
+            content: "hi",
+            member: age.map(|m| MemberContext {
+                role_ids: &[],
+                account_age: Some(chrono::Duration::minutes(m)),
+            }),
+        };
+        assert_eq!(compiled.evaluate(&ctx(Some(5))).len(), 1);
+        assert!(compiled.evaluate(&ctx(Some(120))).is_empty());
+        // Webhook posts have no account.
+        assert!(compiled.evaluate(&ctx(None)).is_empty());
+    }
+
+    #[test]
+    fn scopes_and_exemptions() {
+        let (scoped_channel, other_channel) = (Uuid::new_v4(), Uuid::new_v4());
+        let (moderator, newbie) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut r = rule(keywords(&["spam"]));
+        r.channel_ids = vec![scoped_channel];
+        r.exempt_role_ids = vec![moderator];
+        let compiled = CompiledAutomod::compile(None, &[], vec![r]);
+
+        let check = |channel_id, roles: &[Uuid]| {
+            !compiled
+                .evaluate(&MessageContext {
+                    channel_id,
+                    content: "spam",
+                    member: Some(MemberContext {
+                        role_ids: roles,
+                        account_age: None,
+                    }),
+                })
+                .is_empty()
+        };
+        assert!(check(scoped_channel, &[newbie]));
+        assert!(!check(other_channel, &[newbie]));
+        assert!(!check(scoped_channel, &[newbie, moderator]));
+    }
+
+    #[test]
+    fn role_scoped_rule_skips_other_members_and_webhooks() {
+        let target = Uuid::new_v4();
+        let mut r = rule(keywords(&["spam"]));
+        r.role_ids = vec![target];
+        let compiled = CompiledAutomod::compile(None, &[], vec![r]);
+
+        let eval = |roles: Option<&[Uuid]>| {
+            compiled
+                .evaluate(&MessageContext {
+                    channel_id: Uuid::new_v4(),
+                    content: "spam",
+                    member: roles.map(|role_ids| MemberContext {
+                        role_ids,
+                        account_age: None,
+                    }),
+                })
+                .len()
+        };
+        assert_eq!(eval(Some(&[target])), 1);
+        assert_eq!(eval(Some(&[])), 0);
+        assert_eq!(eval(None), 0);
+    }
+
+    #[test]
+    fn disabled_rules_are_not_compiled() {
+        let mut r = rule(keywords(&["spam"]));
+        r.enabled = false;
+        assert!(!matches(r, "spam"));
+    }
+
+    #[test]
+    fn word_filter_is_whole_word() {
+        let compiled = CompiledAutomod::compile(None, &["ass".to_string()], vec![]);
+        assert_eq!(
+            compiled.match_word_filter("you ass").as_deref(),
+            Some("ass")
+        );
+        assert!(compiled.match_word_filter("first class").is_none());
+    }
+}
@@ -2,6 +2,9 @@ use std::env;
 use std::fmt;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
+
+use uuid::Uuid;
 
 /// TURN server configuration for WebRTC NAT traversal.
 #[derive(Clone, Debug)]
@@ -10,6 +13,230 @@ pub struct TurnConfig {
     pub secret: String,
 }
 
+/// Embedded SFU settings for voice channels (see `crate::sfu`).
+#[derive(Clone, Debug)]
+pub struct SfuConfig {
+    /// Addresses advertised in ICE candidates when the server sits behind a
+    /// 1:1 NAT (from VOICE_SFU_PUBLIC_IPS, comma-separated).
+    pub public_ips: Vec<String>,
+    /// UDP port range for media (from VOICE_SFU_UDP_PORTS, e.g. "50000-50199").
+    /// `None` lets the OS pick ephemeral ports.
+    pub udp_ports: Option<(u16, u16)>,
+}
+
+/// Web Push settings (see `crate::push`).
+#[derive(Clone)]
+pub struct WebPushConfig {
+    /// VAPID private key: the raw 32-byte P-256 scalar, base64url-encoded
+    /// (from VAPID_PRIVATE_KEY). The public key is derived from it.
+    pub vapid_private_key: String,
+    /// Contact URI sent to push services in the VAPID `sub` claim (from
+    /// VAPID_SUBJECT, e.g. "mailto:admin@example.com").
+    pub subject: String,
+    /// Accept `http://` subscription endpoints (from WEB_PUSH_ALLOW_HTTP).
+    /// Only for local testing against a stand-in push service.
+    pub allow_http_endpoints: bool,
+}
+
+/// Manual Debug impl — never prints the VAPID private key.
+impl fmt::Debug for WebPushConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebPushConfig")
+            .field("vapid_private_key", &"[redacted]")
+            .field("subject", &self.subject)
+            .field("allow_http_endpoints", &self.allow_http_endpoints)
+            .finish()
+    }
+}
+
+/// Outgoing email settings (see `crate::mail`).
+#[derive(Clone, Debug)]
+pub struct MailConfig {
+    /// How emails leave the server (from MAIL_TRANSPORT).
+    pub transport: MailTransport,
+    /// Sender address, e.g. "Together <noreply@example.com>" (from MAIL_FROM).
+    pub from: String,
+    /// Base URL of the web client, used for links in emails (from PUBLIC_URL).
+    pub public_url: String,
+    /// Directory with template overrides (from MAIL_TEMPLATE_DIR).
+    pub template_dir: Option<PathBuf>,
+    /// Hours between mention digests (from MAIL_DIGEST_INTERVAL_HOURS,
+    /// default 24). `None` (set to 0) turns the digest off.
+    pub digest_interval_hours: Option<u32>,
+}
+
+/// Passkey (WebAuthn) settings (see `crate::auth::webauthn`).
+#[derive(Clone, Debug)]
+pub struct WebAuthnConfig {
+    /// Relying party ID: the domain passkeys are bound to, e.g.
+    /// "chat.example.com" (from WEBAUTHN_RP_ID). Changing it later makes
+    /// every registered passkey unusable.
+    pub rp_id: String,
+    /// Name shown by the browser when creating a passkey (from
+    /// WEBAUTHN_RP_NAME, default "Together").
+    pub rp_name: String,
+    /// Origins of the web client allowed to use passkeys (from
+    /// WEBAUTHN_ORIGINS, comma-separated, default "https://<rp_id>").
+    pub origins: Vec<String>,
+}
+
+/// OpenID Connect single sign-on settings (see `crate::auth::oidc`).
+#[derive(Clone)]
+pub struct OidcConfig {
+    /// Issuer URL of the identity provider (from OIDC_ISSUER). Endpoints and
+    /// signing keys come from its `/.well-known/openid-configuration`.
+    pub issuer: String,
+    /// From OIDC_CLIENT_ID.
+    pub client_id: String,
+    /// From OIDC_CLIENT_SECRET. `None` for a public client, which relies on
+    /// PKCE alone.
+    pub client_secret: Option<String>,
+    /// Web client page the provider sends the browser back to (from
+    /// OIDC_REDIRECT_URI). It posts `code` and `state` to
+    /// `/auth/oidc/callback`.
+    pub redirect_uri: String,
+    /// From OIDC_SCOPES, space-separated, default "openid email profile".
+    pub scopes: Vec<String>,
+    /// Shown on the login button (from OIDC_PROVIDER_NAME, default "SSO").
+    pub provider_name: String,
+    /// ID token claim listing the user's groups (from OIDC_GROUPS_CLAIM,
+    /// default "groups").
+    pub groups_claim: String,
+    /// Members of any of these groups are instance admins and everyone else
+    /// is not (from OIDC_ADMIN_GROUPS, comma-separated). Empty leaves
+    /// `is_admin` to the admin API.
+    pub admin_groups: Vec<String>,
+    /// Server roles granted to members of a group (from OIDC_ROLE_MAPPINGS,
+    /// e.g. "engineering=<role id>,support=<role id>").
+    pub role_mappings: Vec<(String, Uuid)>,
+    /// Refuse `/auth/register` so accounts only come from the provider (from
+    /// OIDC_DISABLE_REGISTRATION).
+    pub disable_registration: bool,
+}
+
+/// Manual Debug impl — never prints the client secret.
+impl fmt::Debug for OidcConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OidcConfig")
+            .field("issuer", &self.issuer)
+            .field("client_id", &self.client_id)
+            .field(
+                "client_secret",
+                &self.client_secret.as_ref().map(|_| "[redacted]"),
+            )
+            .field("redirect_uri", &self.redirect_uri)
+            .field("scopes", &self.scopes)
+            .field("provider_name", &self.provider_name)
+            .field("groups_claim", &self.groups_claim)
+            .field("admin_groups", &self.admin_groups)
+            .field("role_mappings", &self.role_mappings)
+            .field("disable_registration", &self.disable_registration)
+            .finish()
+    }
+}
+
+/// S3-compatible object storage for uploads (see `crate::storage`).
+#[derive(Clone)]
+pub struct S3Config {
+    /// API endpoint the server talks to, e.g. "https://s3.eu-west-1.amazonaws.com"
+    /// or "http://minio:9000" (from S3_ENDPOINT).
+    pub endpoint: String,
+    /// Endpoint used in presigned download URLs, for when browsers reach the
+    /// bucket at a different address than the server does (from
+    /// S3_PUBLIC_ENDPOINT, default S3_ENDPOINT).
+    pub public_endpoint: String,
+    /// From S3_BUCKET.
+    pub bucket: String,
+    /// Signing region (from S3_REGION, default "us-east-1").
+    pub region: String,
+    /// From S3_ACCESS_KEY_ID.
+    pub access_key_id: String,
+    /// From S3_SECRET_ACCESS_KEY.
+    pub secret_access_key: String,
+    /// Address the bucket as `<endpoint>/<bucket>` rather than
+    /// `<bucket>.<endpoint host>` (from S3_PATH_STYLE, default true).
+    pub path_style: bool,
+    /// How long presigned download URLs stay valid, in seconds (from
+    /// S3_PRESIGN_EXPIRY_SECS, default 300).
+    pub presign_expiry_secs: u32,
+}
+
+/// Manual Debug impl — never prints the secret access key.
+impl fmt::Debug for S3Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("S3Config")
+            .field("endpoint", &self.endpoint)
+            .field("public_endpoint", &self.public_endpoint)
+            .field("bucket", &self.bucket)
+            .field("region", &self.region)
+            .field("access_key_id", &self.access_key_id)
+            .field("secret_access_key", &"[redacted]")
+            .field("path_style", &self.path_style)
+            .field("presign_expiry_secs", &self.presign_expiry_secs)
+            .finish()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum MailTransport {
+    Smtp(SmtpConfig),
+    /// Write each email as a JSON file into this directory (from MAIL_DIR).
+    File(PathBuf),
+    /// Write each email to the log.
+    Log,
+}
+
+#[derive(Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub tls: SmtpTls,
+}
+
+/// Manual Debug impl — never prints the SMTP password.
+impl fmt::Debug for SmtpConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SmtpConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "[redacted]"))
+            .field("tls", &self.tls)
+            .finish()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmtpTls {
+    /// Plain connection upgraded with STARTTLS (port 587).
+    StartTls,
+    /// TLS from the first byte (port 465).
+    Tls,
+    /// No encryption; only for a relay on the same host or network.
+    None,
+}
+
+/// Which cross-node event bus backend to run (see `crate::event_bus`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventBusKind {
+    /// In-process only — correct for a single `together-server` instance.
+    Local,
+    /// Postgres LISTEN/NOTIFY — required when running several replicas.
+    Postgres,
+}
+
+/// Limits on the attachment bytes stored, from SERVER_STORAGE_QUOTA_MB and
+/// USER_STORAGE_QUOTA_MB. `None` (unset or 0) means no limit.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StorageQuota {
+    /// Bytes of attachments on one server's messages.
+    pub per_server: Option<u64>,
+    /// Bytes of attachments one user has posted, across all servers.
+    pub per_user: Option<u64>,
+}
+
 #[derive(Clone)]
 pub struct Config {
     pub database_url: String,
@@ -19,12 +246,42 @@ pub struct Config {
     /// true only when APP_ENV == "development"
     pub is_dev: bool,
     /// Root directory for uploaded files (from UPLOAD_DIR, default: ./data/uploads).
+    /// Used by the local storage backend, and as the source for
+    /// `--migrate-storage`.
     pub upload_dir: PathBuf,
+    /// Where uploads are written while they arrive (from UPLOAD_STAGING_DIR,
+    /// default: ./data/upload-staging). Finished files are moved to storage;
+    /// unfinished resumable uploads stay here until they complete or expire.
+    pub upload_staging_dir: PathBuf,
+    /// Object storage for uploads, present when STORAGE_BACKEND=s3. `None`
+    /// keeps files under `upload_dir`.
+    pub s3: Option<S3Config>,
+    /// Limits on attachment bytes per server and per user.
+    pub storage_quota: StorageQuota,
+    /// How long files of deleted messages are kept before the attachment
+    /// collector removes them (from ATTACHMENT_GC_GRACE_HOURS, default 168).
+    pub attachment_gc_grace: Duration,
     /// Allowed CORS origins in production (parsed from ALLOWED_ORIGINS, comma-separated).
     /// Empty means no cross-origin requests are allowed.
     pub allowed_origins: Vec<String>,
     /// Optional TURN server configuration for WebRTC.
     pub turn: Option<TurnConfig>,
+    /// Embedded SFU for voice (VOICE_SFU=true). `None` keeps voice channels
+    /// on peer-to-peer mesh signaling.
+    pub sfu: Option<SfuConfig>,
+    /// Event bus backend (from EVENT_BUS: "local" (default) or "postgres").
+    pub event_bus: EventBusKind,
+    /// Number of server replicas behind the load balancer (from REPLICA_COUNT,
+    /// default 1). Used to split per-node rate limits.
+    pub replica_count: u32,
+    /// Web Push for offline users, present when VAPID_PRIVATE_KEY is set.
+    pub web_push: Option<WebPushConfig>,
+    /// Outgoing email, present when MAIL_TRANSPORT is set.
+    pub mail: Option<MailConfig>,
+    /// Passkeys, present when WEBAUTHN_RP_ID is set.
+    pub webauthn: Option<WebAuthnConfig>,
+    /// Single sign-on, present when OIDC_ISSUER is set.
+    pub oidc: Option<OidcConfig>,
 }
 
 /// Manual Debug impl — never prints jwt_secret or database credentials in plaintext.
@@ -37,7 +294,18 @@ impl fmt::Debug for Config {
             .field("server_port", &self.server_port)
             .field("is_dev", &self.is_dev)
             .field("upload_dir", &self.upload_dir)
+            .field("upload_staging_dir", &self.upload_staging_dir)
+            .field("s3", &self.s3)
+            .field("storage_quota", &self.storage_quota)
+            .field("attachment_gc_grace", &self.attachment_gc_grace)
             .field("turn", &self.turn)
+            .field("sfu", &self.sfu)
+            .field("event_bus", &self.event_bus)
+            .field("replica_count", &self.replica_count)
+            .field("web_push", &self.web_push)
+            .field("mail", &self.mail)
+            .field("webauthn", &self.webauthn)
+            .field("oidc", &self.oidc)
             .finish()
     }
 }
@@ -58,6 +326,27 @@ impl Config {
         let database_url = env::var("DATABASE_URL")
             .map_err(|_| "DATABASE_URL environment variable is required".to_string())?;
 
+        let replica_count = env::var("REPLICA_COUNT")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(1);
+
+        let sfu = match env::var("VOICE_SFU").as_deref() {
+            Ok("true" | "1") => Some(sfu_config_from_env()?),
+            Err(_) | Ok("false" | "0" | "") => None,
+            Ok(other) => {
+                return Err(format!(
+                    "VOICE_SFU must be \"true\" or \"false\", got \"{other}\""
+                ))
+            }
+        };
+        // SFU rooms are held in memory on one node; participants connected to
+        // different replicas would not hear each other.
+        if sfu.is_some() && replica_count > 1 {
+            return Err("VOICE_SFU requires REPLICA_COUNT=1".to_string());
+        }
+
         Ok(Config {
             database_url,
             jwt_secret: jwt_secret.into(),
@@ -72,6 +361,34 @@ impl Config {
             upload_dir: PathBuf::from(
                 env::var("UPLOAD_DIR").unwrap_or_else(|_| "./data/uploads".to_string()),
             ),
+            upload_staging_dir: PathBuf::from(
+                env::var("UPLOAD_STAGING_DIR")
+                    .unwrap_or_else(|_| "./data/upload-staging".to_string()),
+            ),
+            s3: match env::var("STORAGE_BACKEND").as_deref() {
+                Err(_) | Ok("local" | "") => None,
+                Ok("s3") => Some(s3_config_from_env()?),
+                Ok(other) => {
+                    return Err(format!(
+                        "STORAGE_BACKEND must be \"local\" or \"s3\", got \"{other}\""
+                    ))
+                }
+            },
+            storage_quota: StorageQuota {
+                per_server: quota_from_env("SERVER_STORAGE_QUOTA_MB")?,
+                per_user: quota_from_env("USER_STORAGE_QUOTA_MB")?,
+            },
+            attachment_gc_grace: match env::var("ATTACHMENT_GC_GRACE_HOURS") {
+                Err(_) => Duration::from_secs(168 * 60 * 60),
+                Ok(hours) => match hours.trim().parse::<u64>() {
+                    Ok(h) => Duration::from_secs(h.saturating_mul(60 * 60)),
+                    Err(_) => {
+                        return Err(format!(
+                            "ATTACHMENT_GC_GRACE_HOURS must be a whole number, got \"{hours}\""
+                        ))
+                    }
+                },
+            },
             allowed_origins: env::var("ALLOWED_ORIGINS")
                 .map(|s| {
                     s.split(',')
@@ -89,6 +406,21 @@ impl Config {
                 }
                 _ => None,
             },
+            event_bus: match env::var("EVENT_BUS").as_deref() {
+                Err(_) | Ok("local") => EventBusKind::Local,
+                Ok("postgres") => EventBusKind::Postgres,
+                Ok(other) => {
+                    return Err(format!(
+                        "EVENT_BUS must be \"local\" or \"postgres\", got \"{other}\""
+                    ))
+                }
+            },
+            sfu,
+            replica_count,
+            web_push: web_push_config_from_env()?,
+            mail: mail_config_from_env()?,
+            webauthn: webauthn_config_from_env()?,
+            oidc: oidc_config_from_env()?,
         })
     }
 
@@ -96,3 +428,324 @@ impl Config {
         format!("{}:{}", self.server_host, self.server_port)
     }
 }
+
+/// A storage quota in megabytes from `name`, as bytes. Unset or 0 is no limit.
+fn quota_from_env(name: &str) -> Result<Option<u64>, String> {
+    match env::var(name) {
+        Err(_) => Ok(None),
+        Ok(mb) => match mb.trim().parse::<u64>() {
+            Ok(0) => Ok(None),
+            Ok(mb) => Ok(Some(mb.saturating_mul(1024 * 1024))),
+            Err(_) => Err(format!("{name} must be a whole number, got \"{mb}\"")),
+        },
+    }
+}
+
+fn sfu_config_from_env() -> Result<SfuConfig, String> {
+    let public_ips = env::var("VOICE_SFU_PUBLIC_IPS")
+        .map(|s| {
+            s.split(',')
+                .map(|ip| ip.trim().to_string())
+                .filter(|ip| !ip.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let udp_ports = match env::var("VOICE_SFU_UDP_PORTS") {
+        Err(_) => None,
+        Ok(range) => {
+            let parsed = range
+                .split_once('-')
+                .and_then(|(min, max)| Some((min.trim().parse().ok()?, max.trim().parse().ok()?)))
+                .filter(|(min, max): &(u16, u16)| *min > 0 && min <= max);
+            match parsed {
+                Some(ports) => Some(ports),
+                None => {
+                    return Err(format!(
+                        "VOICE_SFU_UDP_PORTS must look like \"50000-50199\", got \"{range}\""
+                    ))
+                }
+            }
+        }
+    };
+
+    Ok(SfuConfig {
+        public_ips,
+        udp_ports,
+    })
+}
+
+fn web_push_config_from_env() -> Result<Option<WebPushConfig>, String> {
+    let Ok(vapid_private_key) = env::var("VAPID_PRIVATE_KEY") else {
+        return Ok(None);
+    };
+    let subject = env::var("VAPID_SUBJECT")
+        .map_err(|_| "VAPID_SUBJECT is required when VAPID_PRIVATE_KEY is set".to_string())?;
+    if !(subject.starts_with("mailto:") || subject.starts_with("https://")) {
+        return Err("VAPID_SUBJECT must be a mailto: or https:// URI".to_string());
+    }
+    Ok(Some(WebPushConfig {
+        vapid_private_key,
+        subject,
+        allow_http_endpoints: matches!(
+            env::var("WEB_PUSH_ALLOW_HTTP").as_deref(),
+            Ok("true" | "1")
+        ),
+    }))
+}
+
+fn mail_config_from_env() -> Result<Option<MailConfig>, String> {
+    let transport = match env::var("MAIL_TRANSPORT").as_deref() {
+        Err(_) | Ok("") => return Ok(None),
+        Ok("smtp") => MailTransport::Smtp(smtp_config_from_env()?),
+        Ok("file") => MailTransport::File(PathBuf::from(
+            env::var("MAIL_DIR").unwrap_or_else(|_| "./data/mail".to_string()),
+        )),
+        Ok("log") => MailTransport::Log,
+        Ok(other) => {
+            return Err(format!(
+                "MAIL_TRANSPORT must be \"smtp\", \"file\" or \"log\", got \"{other}\""
+            ))
+        }
+    };
+    let from = env::var("MAIL_FROM")
+        .map_err(|_| "MAIL_FROM is required when MAIL_TRANSPORT is set".to_string())?;
+    let public_url = env::var("PUBLIC_URL")
+        .map_err(|_| "PUBLIC_URL is required when MAIL_TRANSPORT is set".to_string())?;
+    if !(public_url.starts_with("https://") || public_url.starts_with("http://")) {
+        return Err("PUBLIC_URL must be an http:// or https:// URL".to_string());
+    }
+    let digest_interval_hours = match env::var("MAIL_DIGEST_INTERVAL_HOURS") {
+        Err(_) => Some(24),
+        Ok(hours) => match hours.trim().parse::<u32>() {
+            Ok(0) => None,
+            Ok(h) => Some(h),
+            Err(_) => {
+                return Err(format!(
+                    "MAIL_DIGEST_INTERVAL_HOURS must be a whole number, got \"{hours}\""
+                ))
+            }
+        },
+    };
+    Ok(Some(MailConfig {
+        transport,
+        from,
+        public_url,
+        template_dir: env::var("MAIL_TEMPLATE_DIR").ok().map(PathBuf::from),
+        digest_interval_hours,
+    }))
+}
+
+fn webauthn_config_from_env() -> Result<Option<WebAuthnConfig>, String> {
+    let rp_id = match env::var("WEBAUTHN_RP_ID") {
+        Err(_) => return Ok(None),
+        Ok(id) if id.trim().is_empty() => return Ok(None),
+        Ok(id) => id.trim().to_ascii_lowercase(),
+    };
+    if rp_id.contains(['/', ':']) {
+        return Err(format!(
+            "WEBAUTHN_RP_ID must be a domain name without scheme or port, got \"{rp_id}\""
+        ));
+    }
+    let origins: Vec<String> = match env::var("WEBAUTHN_ORIGINS") {
+        Err(_) => vec![format!("https://{rp_id}")],
+        Ok(list) => list
+            .split(',')
+            .map(|o| o.trim().trim_end_matches('/').to_string())
+            .filter(|o| !o.is_empty())
+            .collect(),
+    };
+    if let Some(bad) = origins
+        .iter()
+        .find(|o| !(o.starts_with("https://") || o.starts_with("http://localhost")))
+    {
+        return Err(format!(
+            "WEBAUTHN_ORIGINS must be https:// origins (or http://localhost), got \"{bad}\""
+        ));
+    }
+    Ok(Some(WebAuthnConfig {
+        rp_id,
+        rp_name: env::var("WEBAUTHN_RP_NAME").unwrap_or_else(|_| "Together".to_string()),
+        origins,
+    }))
+}
+
+fn s3_config_from_env() -> Result<S3Config, String> {
+    let required = |name: &str| {
+        env::var(name)
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .ok_or_else(|| format!("{name} is required for STORAGE_BACKEND=s3"))
+    };
+    let endpoint = required("S3_ENDPOINT")?
+        .trim()
+        .trim_end_matches('/')
+        .to_string();
+    if !(endpoint.starts_with("https://") || endpoint.starts_with("http://")) {
+        return Err("S3_ENDPOINT must be an http:// or https:// URL".to_string());
+    }
+    let public_endpoint = match env::var("S3_PUBLIC_ENDPOINT") {
+        Ok(url) if !url.trim().is_empty() => {
+            let url = url.trim().trim_end_matches('/').to_string();
+            if !(url.starts_with("https://") || url.starts_with("http://")) {
+                return Err("S3_PUBLIC_ENDPOINT must be an http:// or https:// URL".to_string());
+            }
+            url
+        }
+        _ => endpoint.clone(),
+    };
+    let path_style = match env::var("S3_PATH_STYLE").as_deref() {
+        Err(_) | Ok("true" | "1" | "") => true,
+        Ok("false" | "0") => false,
+        Ok(other) => {
+            return Err(format!(
+                "S3_PATH_STYLE must be \"true\" or \"false\", got \"{other}\""
+            ))
+        }
+    };
+    let presign_expiry_secs = match env::var("S3_PRESIGN_EXPIRY_SECS") {
+        Err(_) => 300,
+        // SigV4 presigned URLs are valid for at most seven days.
+        Ok(secs) => match secs.trim().parse::<u32>() {
+            Ok(s) if (1..=604_800).contains(&s) => s,
+            _ => {
+                return Err(format!(
+                    "S3_PRESIGN_EXPIRY_SECS must be between 1 and 604800, got \"{secs}\""
+                ))
+            }
+        },
+    };
+
+    Ok(S3Config {
+        endpoint,
+        public_endpoint,
+        bucket: required("S3_BUCKET")?,
+        region: env::var("S3_REGION")
+            .ok()
+            .filter(|r| !r.is_empty())
+            .unwrap_or_else(|| "us-east-1".to_string()),
+        access_key_id: required("S3_ACCESS_KEY_ID")?,
+        secret_access_key: required("S3_SECRET_ACCESS_KEY")?,
+        path_style,
+        presign_expiry_secs,
+    })
+}
+
+/// Comma-separated list, trimmed, empty entries dropped.
+fn env_list(name: &str) -> Vec<String> {
+    env::var(name)
+        .map(|s| {
+            s.split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn oidc_config_from_env() -> Result<Option<OidcConfig>, String> {
+    let issuer = match env::var("OIDC_ISSUER") {
+        Err(_) => return Ok(None),
+        Ok(url) if url.trim().is_empty() => return Ok(None),
+        Ok(url) => url.trim().trim_end_matches('/').to_string(),
+    };
+    if !is_https_or_local(&issuer) {
+        return Err(format!(
+            "OIDC_ISSUER must be an https:// URL (or http://localhost), got \"{issuer}\""
+        ));
+    }
+    let client_id = env::var("OIDC_CLIENT_ID")
+        .map_err(|_| "OIDC_CLIENT_ID is required when OIDC_ISSUER is set".to_string())?;
+    let redirect_uri = env::var("OIDC_REDIRECT_URI")
+        .map_err(|_| "OIDC_REDIRECT_URI is required when OIDC_ISSUER is set".to_string())?;
+    if !(redirect_uri.starts_with("https://") || redirect_uri.starts_with("http://")) {
+        return Err("OIDC_REDIRECT_URI must be an http:// or https:// URL".to_string());
+    }
+
+    let scopes: Vec<String> = env::var("OIDC_SCOPES")
+        .unwrap_or_else(|_| "openid email profile".to_string())
+        .split_whitespace()
+        .map(str::to_string)
+        .collect();
+    if !scopes.iter().any(|s| s == "openid") {
+        return Err("OIDC_SCOPES must include \"openid\"".to_string());
+    }
+
+    let role_mappings = env_list("OIDC_ROLE_MAPPINGS")
+        .into_iter()
+        .map(|mapping| {
+            mapping
+                .rsplit_once('=')
+                .and_then(|(group, role)| {
+                    let role = Uuid::parse_str(role.trim()).ok()?;
+                    Some((group.trim().to_string(), role))
+                })
+                .filter(|(group, _)| !group.is_empty())
+                .ok_or_else(|| {
+                    format!("OIDC_ROLE_MAPPINGS entries must look like \"group=<role id>\", got \"{mapping}\"")
+                })
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(Some(OidcConfig {
+        issuer,
+        client_id,
+        client_secret: env::var("OIDC_CLIENT_SECRET")
+            .ok()
+            .filter(|s| !s.is_empty()),
+        redirect_uri,
+        scopes,
+        provider_name: env::var("OIDC_PROVIDER_NAME").unwrap_or_else(|_| "SSO".to_string()),
+        groups_claim: env::var("OIDC_GROUPS_CLAIM").unwrap_or_else(|_| "groups".to_string()),
+        admin_groups: env_list("OIDC_ADMIN_GROUPS"),
+        role_mappings,
+        disable_registration: matches!(
+            env::var("OIDC_DISABLE_REGISTRATION").as_deref(),
+            Ok("true" | "1")
+        ),
+    }))
+}
+
+/// `https://`, or plain `http://` to the local machine for development.
+fn is_https_or_local(url: &str) -> bool {
+    url.starts_with("https://")
+        || ["http://localhost", "http://127.0.0.1"]
+            .iter()
+            .any(|local| {
+                url.strip_prefix(local)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with([':', '/']))
+            })
+}
+
+fn smtp_config_from_env() -> Result<SmtpConfig, String> {
+    let host = env::var("SMTP_HOST")
+        .map_err(|_| "SMTP_HOST is required for MAIL_TRANSPORT=smtp".to_string())?;
+    let tls = match env::var("SMTP_TLS").as_deref() {
+        Err(_) | Ok("starttls") => SmtpTls::StartTls,
+        Ok("tls") => SmtpTls::Tls,
+        Ok("none") => SmtpTls::None,
+        Ok(other) => {
+            return Err(format!(
+                "SMTP_TLS must be \"starttls\", \"tls\" or \"none\", got \"{other}\""
+            ))
+        }
+    };
+    let port = match env::var("SMTP_PORT") {
+        Err(_) => match tls {
+            SmtpTls::StartTls => 587,
+            SmtpTls::Tls => 465,
+            SmtpTls::None => 25,
+        },
+        Ok(port) => port
+            .trim()
+            .parse()
+            .map_err(|_| format!("SMTP_PORT must be a port number, got \"{port}\""))?,
+    };
+    Ok(SmtpConfig {
+        host,
+        port,
+        username: env::var("SMTP_USERNAME").ok(),
+        password: env::var("SMTP_PASSWORD").ok(),
+        tls,
+    })
+}
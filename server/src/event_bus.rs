@@ -0,0 +1,361 @@
+//! Cross-node event fan-out.
+//!
+//! # Design
+//!
+//! Every real-time side effect that must reach users on *other* replicas is
+//! expressed as a [`BusMessage`] and handed to the [`EventBus`] stored in
+//! `AppState::events`. Each node applies every message to its own in-process
+//! state: gateway dispatches go to locally connected sessions through
+//! `ConnectionManager`, and cache invalidations drop the node's stale copies.
+//!
+//! Two backends exist:
+//!
+//! - [`LocalEventBus`] — single-node deployments (the default). Publishing
+//!   simply applies the message in-process.
+//! - [`PgEventBus`] — multi-replica deployments (`EVENT_BUS=postgres`). The
+//!   publishing node applies the message locally, then sends it to every other
+//!   node with Postgres `NOTIFY` on the existing pool. A listener task on each
+//!   node `LISTEN`s and applies messages that originated elsewhere.
+//!
+//! # Payload size
+//!
+//! `NOTIFY` payloads are capped at 8000 bytes. Larger messages (long chat
+//! messages, broadcasts to big servers) are written to `gateway_event_spill`
+//! and only the row id is notified. Spill rows are swept after a few minutes.
+//!
+//! # Shared state
+//!
+//! - **Gateway sessions** stay node-local. A RESUME that lands on a different
+//!   node than the dropped socket falls back to a fresh READY.
+//! - **Go Live sessions** live in Postgres (`go_live_sessions`), so every node
+//!   reads the same state and nothing needs replicating.
+//! - **Channel viewer caches** stay node-local; permission changes publish an
+//!   invalidation so every node drops its stale recipient lists.
+//! - **Compiled automod configurations** are cached per node the same way;
+//!   automod config, word list and rule changes publish an invalidation.
+//! - **Bot rate limiting** stays a per-node token bucket; the quota is divided
+//!   by `Config::replica_count` so the aggregate rate across replicas matches
+//!   the documented 50 req/s when load is spread evenly.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgListener;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::automod_engine::AutomodCache;
+use crate::websocket::channel_viewers::ChannelViewerCache;
+use crate::websocket::events::GatewayMessage;
+use crate::websocket::ConnectionManager;
+
+// ── Public API ────────────────────────────────────────────────────────────────
+
+/// A state change that every node must apply.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BusMessage {
+    /// Deliver a gateway DISPATCH to every local session of the listed users.
+    Dispatch {
+        user_ids: Vec<Uuid>,
+        event: GatewayMessage,
+    },
+    /// Permissions or membership changed in a server; cached channel
+    /// recipient lists for it are stale.
+    InvalidateChannelViewers { server_id: Uuid },
+    /// Automod settings changed in a server; its compiled rules are stale.
+    InvalidateAutomod { server_id: Uuid },
+}
+
+/// Pluggable transport for [`BusMessage`]s.
+///
+/// `publish` must apply the message on the local node before returning (so a
+/// REST handler's own node is always consistent) and make a best effort to
+/// deliver it to every other node. Failures are logged, never returned — a
+/// lost real-time event must not fail the triggering request.
+pub trait EventBus: Send + Sync {
+    fn publish(&self, msg: BusMessage) -> BoxFuture<'_, ()>;
+}
+
+/// Node-local state that bus messages are applied to.
+#[derive(Clone)]
+pub struct LocalState {
+    pub connections: ConnectionManager,
+    pub channel_viewers: ChannelViewerCache,
+    pub automod: AutomodCache,
+}
+
+impl LocalState {
+    /// Apply a bus message to this node's in-process state.
+    pub async fn apply(&self, msg: BusMessage) {
+        match msg {
+            BusMessage::Dispatch { user_ids, event } => {
+                self.connections.dispatch_to_users(&user_ids, event).await;
+            }
+            BusMessage::InvalidateChannelViewers { server_id } => {
+                self.channel_viewers.invalidate_server(server_id).await;
+            }
+            BusMessage::InvalidateAutomod { server_id } => {
+                self.automod.invalidate(server_id).await;
+            }
+        }
+    }
+}
+
+/// Single-node bus: messages are applied in-process only.
+pub struct LocalEventBus {
+    local: LocalState,
+}
+
+impl LocalEventBus {
+    pub fn new(local: LocalState) -> Self {
+        Self { local }
+    }
+}
+
+impl EventBus for LocalEventBus {
+    fn publish(&self, msg: BusMessage) -> BoxFuture<'_, ()> {
+        Box::pin(self.local.apply(msg))
+    }
+}
+
+/// Multi-node bus backed by Postgres `LISTEN/NOTIFY`.
+pub struct PgEventBus {
+    pool: PgPool,
+    node_id: Uuid,
+    local: LocalState,
+}
+
+impl PgEventBus {
+    /// Create the bus and spawn its listener task.
+    ///
+    /// Fails only if the initial `LISTEN` cannot be established; after that
+    /// the listener reconnects on its own.
+    pub async fn start(pool: PgPool, local: LocalState) -> Result<Arc<Self>, sqlx::Error> {
+        let mut listener = PgListener::connect_with(&pool).await?;
+        listener.listen(NOTIFY_CHANNEL).await?;
+
+        let bus = Arc::new(Self {
+            pool,
+            node_id: Uuid::new_v4(),
+            local,
+        });
+        tokio::spawn(run_listener(listener, Arc::clone(&bus)));
+        tokio::spawn(run_spill_sweeper(bus.pool.clone()));
+        Ok(bus)
+    }
+
+    async fn notify(&self, msg: &BusMessage) {
+        let body = match serde_json::to_string(msg) {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::error!(error = ?e, "Failed to serialize bus message; this is a programming error");
+                return;
+            }
+        };
+
+        let envelope = if body.len() <= MAX_INLINE_PAYLOAD {
+            Envelope {
+                origin: self.node_id,
+                message: Some(body),
+                spill_id: None,
+            }
+        } else {
+            let spill_id: i64 = match sqlx::query_scalar(
+                "INSERT INTO gateway_event_spill (payload) VALUES ($1) RETURNING id",
+            )
+            .bind(&body)
+            .fetch_one(&self.pool)
+            .await
+            {
+                Ok(id) => id,
+                Err(e) => {
+                    tracing::warn!(
+                        error = ?e,
+                        "Failed to spill oversized bus message; other nodes will miss this event"
+                    );
+                    return;
+                }
+            };
+            Envelope {
+                origin: self.node_id,
+                message: None,
+                spill_id: Some(spill_id),
+            }
+        };
+
+        let payload = match serde_json::to_string(&envelope) {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::error!(error = ?e, "Failed to serialize bus envelope; this is a programming error");
+                return;
+            }
+        };
+
+        if let Err(e) = sqlx::query("SELECT pg_notify($1, $2)")
+            .bind(NOTIFY_CHANNEL)
+            .bind(&payload)
+            .execute(&self.pool)
+            .await
+        {
+            tracing::warn!(
+                error = ?e,
+                "Failed to NOTIFY bus message; other nodes will miss this event"
+            );
+        }
+    }
+}
+
+impl EventBus for PgEventBus {
+    fn publish(&self, msg: BusMessage) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            self.notify(&msg).await;
+            self.local.apply(msg).await;
+        })
+    }
+}
+
+// ── Internal ──────────────────────────────────────────────────────────────────
+
+/// Postgres notification channel shared by all nodes.
+const NOTIFY_CHANNEL: &str = "together_events";
+
+/// Largest serialized message sent inline. Postgres rejects NOTIFY payloads of
+/// 8000 bytes or more; the envelope adds ~100 bytes of overhead and JSON string
+/// escaping can grow the message further, so leave generous headroom.
+const MAX_INLINE_PAYLOAD: usize = 3_500;
+
+/// Spill rows older than this are deleted by the sweeper.
+const SPILL_RETENTION_SECS: i64 = 300;
+
+/// Wire format of a NOTIFY payload.
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    /// Node that published the message; nodes ignore their own notifications.
+    origin: Uuid,
+    /// Serialized [`BusMessage`] when it fits inline.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    /// `gateway_event_spill.id` holding the message when it does not.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    spill_id: Option<i64>,
+}
+
+async fn run_listener(mut listener: PgListener, bus: Arc<PgEventBus>) {
+    loop {
+        let notification = match listener.recv().await {
+            Ok(n) => n,
+            Err(e) => {
+                // PgListener reconnects on the next recv(); anything notified
+                // while disconnected is lost.
+                tracing::warn!(error = ?e, "Event bus listener error; reconnecting");
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+
+        let envelope: Envelope = match serde_json::from_str(notification.payload()) {
+            Ok(e) => e,
+            Err(e) => {
+                tracing::warn!(error = ?e, "Ignoring malformed event bus notification");
+                continue;
+            }
+        };
+        if envelope.origin == bus.node_id {
+            continue;
+        }
+
+        let body = match (envelope.message, envelope.spill_id) {
+            (Some(body), _) => body,
+            (None, Some(id)) => {
+                match sqlx::query_scalar::<_, String>(
+                    "SELECT payload FROM gateway_event_spill WHERE id = $1",
+                )
+                .bind(id)
+                .fetch_optional(&bus.pool)
+                .await
+                {
+                    Ok(Some(body)) => body,
+                    Ok(None) => {
+                        tracing::warn!(
+                            spill_id = id,
+                            "Spilled bus message already swept; dropping"
+                        );
+                        continue;
+                    }
+                    Err(e) => {
+                        tracing::warn!(spill_id = id, error = ?e, "Failed to read spilled bus message");
+                        continue;
+                    }
+                }
+            }
+            (None, None) => continue,
+        };
+
+        match serde_json::from_str::<BusMessage>(&body) {
+            Ok(msg) => bus.local.apply(msg).await,
+            Err(e) => tracing::warn!(error = ?e, "Ignoring malformed bus message"),
+        }
+    }
+}
+
+async fn run_spill_sweeper(pool: PgPool) {
+    let mut interval = tokio::time::interval(Duration::from_secs(60));
+    loop {
+        interval.tick().await;
+        if let Err(e) = sqlx::query(
+            "DELETE FROM gateway_event_spill
+             WHERE created_at < NOW() - make_interval(secs => $1)",
+        )
+        .bind(SPILL_RETENTION_SECS as f64)
+        .execute(&pool)
+        .await
+        {
+            tracing::warn!(error = ?e, "Failed to sweep gateway_event_spill");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local_state() -> LocalState {
+        LocalState {
+            connections: ConnectionManager::new(),
+            channel_viewers: ChannelViewerCache::new(),
+            automod: AutomodCache::new(),
+        }
+    }
+
+    #[test]
+    fn bus_message_round_trips_through_json() {
+        let msg = BusMessage::Dispatch {
+            user_ids: vec![Uuid::new_v4()],
+            event: GatewayMessage::dispatch("MESSAGE_CREATE", serde_json::json!({"a": 1})),
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+        assert!(json.contains("\"kind\":\"dispatch\""));
+        let back: BusMessage = serde_json::from_str(&json).unwrap();
+        assert!(matches!(back, BusMessage::Dispatch { .. }));
+    }
+
+    #[tokio::test]
+    async fn local_bus_delivers_dispatch() {
+        let local = local_state();
+        let bus = LocalEventBus::new(local.clone());
+        let user = Uuid::new_v4();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        local.connections.add(user, tx).await;
+
+        bus.publish(BusMessage::Dispatch {
+            user_ids: vec![user],
+            event: GatewayMessage::dispatch("PING", serde_json::json!({})),
+        })
+        .await;
+
+        assert!(rx.recv().await.unwrap().contains("PING"));
+    }
+}
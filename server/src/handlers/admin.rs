@@ -8,16 +8,18 @@ use axum::{
     http::StatusCode,
     Json,
 };
+use serde_json::json;
 use std::time::Instant;
 use uuid::Uuid;
 
 use crate::{
     auth::AuthUser,
     error::{AppError, AppResult},
-    handlers::health::uptime_secs,
+    handlers::{audit::log_instance_action, health::uptime_secs, two_factor::remove_two_factor},
     models::{
         AdminListQuery, AdminServerDto, AdminServersResponse, AdminStatsResponse, AdminUserDto,
-        AdminUsersResponse, InstanceSettings, UpdateAdminUserRequest, UpdateSettingsRequest,
+        AdminUsersResponse, AuditAction, InstanceSettings, ServerStorageUsage,
+        UpdateAdminUserRequest, UpdateSettingsRequest,
     },
     state::AppState,
 };
@@ -59,7 +61,8 @@ async fn require_admin(pool: &sqlx::PgPool, user_id: Uuid) -> AppResult<()> {
 /// GET /admin/stats — Instance overview statistics.
 ///
 /// Returns aggregate counts, active WebSocket connections, uptime, DB latency,
-/// and upload directory storage size.
+/// the recorded size of stored uploads, and attachment usage against the
+/// storage quotas with the heaviest servers.
 pub async fn get_stats(
     State(state): State<AppState>,
     auth: AuthUser,
@@ -82,8 +85,29 @@ pub async fn get_stats(
     sqlx::query("SELECT 1").execute(&state.pool).await?;
     let db_latency_ms = db_start.elapsed().as_millis() as u64;
 
-    // Storage size — walk the upload directory.
-    let storage_bytes = calculate_storage_bytes(&state.upload_dir).await;
+    // Storage size from the recorded file sizes; listing the upload
+    // directory or bucket is too slow for a request. Attachment usage is
+    // what the quotas count: `file_size` of every row.
+    let (attachment_bytes, emoji_bytes): (i64, i64) = sqlx::query_as(
+        "SELECT
+            (SELECT COALESCE(SUM(file_size), 0) FROM attachments)::BIGINT,
+            (SELECT COALESCE(SUM(file_size), 0) FROM custom_emojis)::BIGINT",
+    )
+    .fetch_one(&state.pool)
+    .await?;
+    let storage_bytes = (attachment_bytes + emoji_bytes).max(0) as u64;
+    let top_storage_servers = sqlx::query_as::<_, ServerStorageUsage>(
+        "SELECT s.id AS server_id, s.name, SUM(a.file_size)::BIGINT AS attachment_bytes
+         FROM attachments a
+         JOIN messages m ON m.id = a.message_id
+         JOIN channels c ON c.id = m.channel_id
+         JOIN servers s ON s.id = c.server_id
+         GROUP BY s.id, s.name
+         ORDER BY attachment_bytes DESC
+         LIMIT 10",
+    )
+    .fetch_all(&state.pool)
+    .await?;
 
     Ok(Json(AdminStatsResponse {
         total_users: counts.0,
@@ -94,34 +118,13 @@ pub async fn get_stats(
         uptime_secs: uptime_secs(),
         db_latency_ms,
         storage_bytes,
+        attachment_bytes,
+        server_storage_quota_bytes: state.config.storage_quota.per_server,
+        user_storage_quota_bytes: state.config.storage_quota.per_user,
+        top_storage_servers,
     }))
 }
 
-/// Walk a directory tree and sum file sizes.
-async fn calculate_storage_bytes(dir: &std::path::Path) -> u64 {
-    let mut total: u64 = 0;
-    let mut stack = vec![dir.to_path_buf()];
-
-    while let Some(path) = stack.pop() {
-        let mut entries = match tokio::fs::read_dir(&path).await {
-            Ok(e) => e,
-            Err(_) => continue,
-        };
-        while let Ok(Some(entry)) = entries.next_entry().await {
-            let meta = match entry.metadata().await {
-                Ok(m) => m,
-                Err(_) => continue,
-            };
-            if meta.is_dir() {
-                stack.push(entry.path());
-            } else {
-                total += meta.len();
-            }
-        }
-    }
-    total
-}
-
 #[utoipa::path(
     get,
     path = "/admin/users",
@@ -332,6 +335,60 @@ pub async fn update_user(
     Ok(StatusCode::OK)
 }
 
+#[utoipa::path(
+    delete,
+    path = "/admin/users/{user_id}/two-factor",
+    params(
+        ("user_id" = Uuid, Path, description = "User ID"),
+    ),
+    responses(
+        (status = 204, description = "Two-factor authentication and passkeys removed"),
+        (status = 404, description = "User not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Admin"
+)]
+/// DELETE /admin/users/:user_id/two-factor — Remove a user's 2FA and
+/// passkeys, e.g. after they lost both their device and their recovery codes.
+///
+/// If the instance requires 2FA for the user, their next login asks them to
+/// enroll again. The reset is recorded in the audit log with the acting admin
+/// and the target user.
+pub async fn reset_two_factor(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<Uuid>,
+) -> AppResult<StatusCode> {
+    require_admin(&state.pool, auth.user_id()).await?;
+
+    let username: String = sqlx::query_scalar("SELECT username FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".into()))?;
+
+    let mut tx = state.pool.begin().await?;
+    remove_two_factor(&mut tx, user_id).await?;
+    let passkeys = sqlx::query("DELETE FROM webauthn_credentials WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    tx.commit().await?;
+
+    log_instance_action(
+        &state.pool,
+        auth.user_id(),
+        AuditAction::UserTwoFactorReset,
+        "user",
+        user_id,
+        json!({ "username": username, "passkeys_removed": passkeys }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
 #[utoipa::path(
     delete,
     path = "/admin/users/{user_id}",
@@ -596,10 +653,18 @@ pub async fn update_settings(
             ));
         }
     }
+    if let Some(ref policy) = req.require_two_factor {
+        if !matches!(policy.as_str(), "off" | "admins" | "everyone") {
+            return Err(AppError::Validation(
+                "require_two_factor must be one of: off, admins, everyone".into(),
+            ));
+        }
+    }
 
     let settings = sqlx::query_as::<_, InstanceSettings>(
         "UPDATE instance_settings
          SET registration_mode = COALESCE($1, registration_mode),
+             require_two_factor = COALESCE($3, require_two_factor),
              updated_at = NOW(),
              updated_by = $2
          WHERE id = 1
@@ -607,6 +672,7 @@ pub async fn update_settings(
     )
     .bind(&req.registration_mode)
     .bind(auth.user_id())
+    .bind(&req.require_two_factor)
     .fetch_one(&state.pool)
     .await?;
 
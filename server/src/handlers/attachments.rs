@@ -1,26 +1,30 @@
 use axum::{
-    body::Body,
-    extract::{Multipart, Path, State},
-    http::{header, StatusCode},
+    extract::{Multipart, Path, Query, State},
+    http::{HeaderMap, StatusCode},
     response::Response,
     Json,
 };
 use bytes::Bytes;
 use serde::Deserialize;
+use sqlx::PgConnection;
+use std::collections::HashSet;
 use std::path::PathBuf;
-use tokio::fs::File;
-use tokio_util::io::ReaderStream;
 use uuid::Uuid;
 
+use super::files::serve_stored_file;
 use super::shared::{
     fetch_channel_by_id, fetch_message, require_channel_permission, require_member,
     PERMISSION_ATTACH_FILES,
 };
 use crate::{
     auth::AuthUser,
+    config::StorageQuota,
     error::{AppError, AppResult},
-    models::Attachment,
+    images::{self, Thumbnail},
+    models::{AttachUploadsRequest, Attachment, Upload},
     state::AppState,
+    storage::{ResponseHeaders, Storage},
+    uploads::{self, staging_path, StagingFile},
 };
 
 // ============================================================================
@@ -30,13 +34,13 @@ use crate::{
 /// Maximum number of attachments allowed per message (Discord-compatible).
 const MAX_ATTACHMENTS_PER_MESSAGE: i64 = 10;
 
-/// Maximum file size in bytes (50 MB, matches the DB check constraint).
-const MAX_FILE_SIZE: usize = 52_428_800;
+/// Maximum file size in bytes (50 MB, matches the DB check constraints).
+pub const MAX_FILE_SIZE: u64 = 52_428_800;
 
 /// Allowlist of MIME types accepted for uploaded files.
 /// The MIME type is detected from magic bytes, not from the client-supplied
 /// Content-Type header, so this list is authoritative.
-const ALLOWED_MIME_TYPES: &[&str] = &[
+pub const ALLOWED_MIME_TYPES: &[&str] = &[
     "image/jpeg",
     "image/png",
     "image/gif",
@@ -50,6 +54,14 @@ const ALLOWED_MIME_TYPES: &[&str] = &[
     "text/plain",
 ];
 
+/// Browsers may keep attachments but must revalidate before each use, so the
+/// membership check runs every time; an unchanged file costs a `304`.
+const ATTACHMENT_CACHE_CONTROL: &str = "private, no-cache";
+
+/// Thumbnail sizes made for image attachments, in pixels along the longer
+/// side. Clients pick one with `?size=` when fetching the file.
+pub const THUMBNAIL_SIZES: &[u32] = &[256, 512, 1024];
+
 // ============================================================================
 // Handlers
 // ============================================================================
@@ -70,8 +82,17 @@ const ALLOWED_MIME_TYPES: &[&str] = &[
 /// POST /messages/:message_id/attachments — upload one or more files (author only).
 ///
 /// Expects a `multipart/form-data` body with one or more file fields named `files`.
-/// Each file is written to `{upload_dir}/{message_id}/{uuid}_{filename}` on disk
-/// and returned with a URL of `/files/{message_id}/{uuid}_{filename}`.
+/// Each file is stored under the key `{message_id}/{uuid}_{filename}` (see
+/// [`crate::storage`]) and returned with a URL of `/files/{message_id}/{uuid}_{filename}`.
+///
+/// Files are streamed to the staging directory rather than buffered (see
+/// [`crate::uploads`]): the size limit is checked on every chunk and the type
+/// is sniffed from the first bytes, so an oversized or disallowed file is
+/// refused before the rest of it is read.
+///
+/// Images are decoded first: their dimensions are recorded, metadata such as
+/// EXIF GPS tags is removed, and WebP thumbnails are stored next to the file
+/// (see [`crate::images`]).
 ///
 /// Authorization rules:
 /// - Caller must be authenticated.
@@ -80,50 +101,27 @@ const ALLOWED_MIME_TYPES: &[&str] = &[
 ///
 /// Validation:
 /// - Each file must be non-empty and ≤ 50 MB.
+/// - Images must decode and be at most 12,000 pixels on each side.
 /// - The combined attachment count for the message cannot exceed 10.
 ///
-/// The upload is atomic: all validation happens before any file is written to disk.
-/// If a disk write or database insert fails, any files already written are removed
-/// and the database transaction is rolled back.
+/// The upload is atomic: all validation happens before any file is stored.
+/// If a storage write or database insert fails, any files already stored are
+/// removed and the database transaction is rolled back.
 pub async fn upload_attachments(
     State(state): State<AppState>,
     auth: AuthUser,
     Path(message_id): Path<Uuid>,
     mut multipart: Multipart,
 ) -> AppResult<(StatusCode, Json<Vec<Attachment>>)> {
-    let message = fetch_message(&state.pool, message_id).await?;
-    let channel = fetch_channel_by_id(&state.pool, message.channel_id).await?;
-    require_member(&state.pool, channel.server_id, auth.user_id()).await?;
-
-    // Channel-level permission check (respects per-channel overrides).
-    require_channel_permission(
-        &state.pool,
-        channel.server_id,
-        message.channel_id,
-        auth.user_id(),
-        PERMISSION_ATTACH_FILES,
-        "You don't have permission to attach files in this channel",
-    )
-    .await?;
-
-    if message.author_id != Some(auth.user_id()) {
-        return Err(AppError::Forbidden(
-            "Only the message author can add attachments".into(),
-        ));
-    }
+    let (server_id, existing_count) = authorize_attach(&state, &auth, message_id).await?;
 
-    let existing_count: i64 =
-        sqlx::query_scalar("SELECT COUNT(*) FROM attachments WHERE message_id = $1")
-            .bind(message_id)
-            .fetch_one(&state.pool)
-            .await?;
-
-    // ── Pass 1: validate all fields before touching disk or the database ──────
+    // ── Pass 1: stage and validate all fields before touching storage ────────
 
+    let mut staged = StagedFiles(Vec::new());
     let mut pending: Vec<PendingFile> = Vec::new();
     let mut slot_count = existing_count;
 
-    while let Some(field) = multipart.next_field().await.map_err(|e| {
+    while let Some(mut field) = multipart.next_field().await.map_err(|e| {
         tracing::warn!(error = ?e, "Failed to read multipart field");
         AppError::Validation("Invalid multipart data".into())
     })? {
@@ -139,62 +137,24 @@ pub async fn upload_attachments(
 
         let filename = field.file_name().unwrap_or("unknown").to_string();
 
-        let data = field.bytes().await.map_err(|e| {
+        let path = staging_path(&state.config.upload_staging_dir, Uuid::new_v4());
+        staged.0.push(path.clone());
+        let mut file = StagingFile::create(path.clone(), MAX_FILE_SIZE, ALLOWED_MIME_TYPES)
+            .await
+            .map_err(|e| {
+                tracing::error!(error = ?e, "Failed to create staging file");
+                AppError::Internal
+            })?;
+
+        while let Some(chunk) = field.chunk().await.map_err(|e| {
             tracing::warn!(error = ?e, "Failed to read multipart field bytes");
             AppError::Validation("Failed to read file data".into())
-        })?;
-
-        if data.is_empty() {
-            return Err(AppError::Validation("Files must not be empty".into()));
-        }
-
-        if data.len() > MAX_FILE_SIZE {
-            return Err(AppError::Validation(
-                "File size exceeds the 50 MB limit".into(),
-            ));
-        }
-
-        // Detect MIME type from magic bytes, ignoring the client-supplied
-        // Content-Type header to prevent stored-XSS via disguised HTML uploads.
-        let mime_type = match infer::get(&data) {
-            Some(t) => t.mime_type().to_string(),
-            None => {
-                // No magic bytes detected — check if it's valid UTF-8 text
-                if std::str::from_utf8(&data).is_ok() {
-                    "text/plain".to_string()
-                } else {
-                    tracing::warn!(
-                        size = data.len(),
-                        "MIME type could not be detected from magic bytes for binary file"
-                    );
-                    return Err(AppError::Validation(
-                        "File type could not be determined. Please upload a supported file type (image, video, audio, PDF, or text).".into(),
-                    ));
-                }
-            }
-        };
-
-        if !ALLOWED_MIME_TYPES.contains(&mime_type.as_str()) {
-            return Err(AppError::Validation(format!(
-                "File type '{}' is not allowed",
-                mime_type
-            )));
+        })? {
+            file.write(&chunk).await?;
         }
+        let (size, mime_type) = file.finish().await?;
 
-        let stored_name = format!(
-            "{}_{}",
-            Uuid::new_v4().simple(),
-            sanitize_filename(&filename)
-        );
-        let url = format!("/files/{message_id}/{stored_name}");
-
-        pending.push(PendingFile {
-            filename,
-            mime_type,
-            data,
-            stored_name,
-            url,
-        });
+        pending.push(prepare_file(message_id, filename, path, size, mime_type).await?);
         slot_count += 1;
     }
 
@@ -204,91 +164,100 @@ pub async fn upload_attachments(
         ));
     }
 
-    // ── Pass 2: write all files to disk ───────────────────────────────────────
-
-    let dir = state.upload_dir.join(message_id.to_string());
+    let owner = QuotaOwner {
+        server_id,
+        user_id: auth.user_id(),
+    };
+    let created = store_attachments(&state, message_id, owner, &pending, &[]).await?;
+    Ok((StatusCode::CREATED, Json(created)))
+}
 
-    tokio::fs::create_dir_all(&dir).await.map_err(|e| {
-        tracing::error!(error = ?e, path = ?dir, "Failed to create upload directory");
-        AppError::Internal
-    })?;
+#[utoipa::path(
+    post,
+    path = "/messages/{message_id}/attachments/uploads",
+    params(
+        ("message_id" = Uuid, Path, description = "Message ID"),
+    ),
+    request_body = AttachUploadsRequest,
+    responses(
+        (status = 201, description = "Uploads attached", body = Vec<Attachment>),
+        (status = 404, description = "An upload does not exist, has expired or belongs to someone else"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Attachments"
+)]
+/// POST /messages/:message_id/attachments/uploads — attach finished resumable
+/// uploads (see [`super::uploads`]) to a message (author only).
+///
+/// Authorization, validation and storage are the same as for
+/// [`upload_attachments`]. Every upload must belong to the caller and be
+/// complete. Attached uploads are consumed: their rows and staged files are
+/// removed, and attaching the same upload twice fails.
+pub async fn attach_uploads(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(message_id): Path<Uuid>,
+    Json(req): Json<AttachUploadsRequest>,
+) -> AppResult<(StatusCode, Json<Vec<Attachment>>)> {
+    let (server_id, existing_count) = authorize_attach(&state, &auth, message_id).await?;
 
-    // On Unix, set directory permissions to prevent execution from within.
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let perms = PermissionsExt::from_mode(0o755);
-        if let Err(e) = tokio::fs::set_permissions(&dir, perms).await {
-            tracing::warn!(error = ?e, path = ?dir, "Failed to set upload directory permissions");
-            // Non-fatal: continue with default permissions
-        }
+    let ids = req.upload_ids;
+    if ids.is_empty() {
+        return Err(AppError::Validation("upload_ids must not be empty".into()));
+    }
+    if ids.iter().collect::<HashSet<_>>().len() != ids.len() {
+        return Err(AppError::Validation(
+            "upload_ids must not contain duplicates".into(),
+        ));
+    }
+    if existing_count + ids.len() as i64 > MAX_ATTACHMENTS_PER_MESSAGE {
+        return Err(AppError::Validation(format!(
+            "Messages may not have more than {MAX_ATTACHMENTS_PER_MESSAGE} attachments"
+        )));
     }
 
-    let mut written_paths: Vec<PathBuf> = Vec::new();
+    let uploads = sqlx::query_as::<_, Upload>(
+        "SELECT id, filename, upload_length, upload_offset, mime_type, created_at, expires_at
+         FROM uploads WHERE id = ANY($1) AND user_id = $2 AND expires_at > NOW()",
+    )
+    .bind(&ids)
+    .bind(auth.user_id())
+    .fetch_all(&state.pool)
+    .await?;
 
-    for p in &pending {
-        let file_path = dir.join(&p.stored_name);
-        if let Err(e) = tokio::fs::write(&file_path, &p.data).await {
-            tracing::error!(error = ?e, path = ?file_path, "Failed to write uploaded file");
-            cleanup_files(&written_paths).await;
-            return Err(AppError::Internal);
-        }
-        written_paths.push(file_path.clone());
+    // ── Pass 1: validate all uploads before touching storage ──────────────────
 
-        // On Unix, set file permissions to read/write for owner, read for others.
-        // Explicitly removes execute bit for security.
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let perms = PermissionsExt::from_mode(0o644);
-            if let Err(e) = tokio::fs::set_permissions(&file_path, perms).await {
-                tracing::warn!(error = ?e, path = ?file_path, "Failed to set upload file permissions");
-                // Non-fatal: file was written successfully
-            }
-        }
+    let mut pending: Vec<PendingFile> = Vec::new();
+    for id in &ids {
+        let upload = uploads
+            .iter()
+            .find(|u| u.id == *id)
+            .ok_or_else(|| AppError::NotFound(format!("Upload {id} not found")))?;
+        let mime_type = match &upload.mime_type {
+            Some(mime_type) if upload.upload_offset == upload.upload_length => mime_type,
+            _ => return Err(AppError::Validation(format!("Upload {id} is not complete"))),
+        };
+        let path = staging_path(&state.config.upload_staging_dir, *id);
+        pending.push(
+            prepare_file(
+                message_id,
+                upload.filename.clone(),
+                path,
+                upload.upload_length as u64,
+                mime_type.clone(),
+            )
+            .await?,
+        );
     }
 
-    // ── Pass 3: insert all rows in a single transaction ───────────────────────
-
-    let mut tx = match state.pool.begin().await {
-        Ok(tx) => tx,
-        Err(e) => {
-            tracing::error!(error = ?e, "Failed to begin upload transaction");
-            cleanup_files(&written_paths).await;
-            return Err(AppError::from(e));
-        }
+    let owner = QuotaOwner {
+        server_id,
+        user_id: auth.user_id(),
     };
-
-    let mut created: Vec<Attachment> = Vec::new();
+    let created = store_attachments(&state, message_id, owner, &pending, &ids).await?;
 
     for p in &pending {
-        match sqlx::query_as::<_, Attachment>(
-            "INSERT INTO attachments (message_id, filename, file_size, mime_type, url)
-             VALUES ($1, $2, $3, $4, $5)
-             RETURNING id, message_id, filename, file_size, mime_type, url, width, height, created_at",
-        )
-        .bind(message_id)
-        .bind(&p.filename)
-        .bind(p.data.len() as i64)
-        .bind(&p.mime_type)
-        .bind(&p.url)
-        .fetch_one(&mut *tx)
-        .await
-        {
-            Ok(att) => created.push(att),
-            Err(e) => {
-                tracing::error!(error = ?e, "Failed to insert attachment row; rolling back");
-                let _ = tx.rollback().await;
-                cleanup_files(&written_paths).await;
-                return Err(AppError::from(e));
-            }
-        }
-    }
-
-    if let Err(e) = tx.commit().await {
-        tracing::error!(error = ?e, "Failed to commit upload transaction; cleaning up files");
-        cleanup_files(&written_paths).await;
-        return Err(AppError::from(e));
+        uploads::remove_staged(&p.staged).await;
     }
 
     Ok((StatusCode::CREATED, Json(created)))
@@ -334,9 +303,14 @@ pub async fn list_attachments(
     params(
         ("message_id" = Uuid, Path, description = "Message ID"),
         ("filepath" = String, Path, description = "File path"),
+        FileQuery,
     ),
     responses(
-        (status = 200, description = "File content"),
+        (status = 200, description = "File content, or a WebP thumbnail when `size` is given"),
+        (status = 206, description = "The byte range asked for with `Range`"),
+        (status = 304, description = "Unchanged since the `If-None-Match` / `If-Modified-Since` copy"),
+        (status = 307, description = "Redirect to a presigned object storage URL (STORAGE_BACKEND=s3)"),
+        (status = 400, description = "Unsupported thumbnail size"),
     ),
     security(("bearer_auth" = [])),
     tag = "Attachments"
@@ -346,14 +320,35 @@ pub async fn list_attachments(
 /// Authorization and membership are checked before serving the file.
 /// The attachment URL is verified against the database so that only files
 /// successfully recorded in the DB are accessible.
+///
+/// With `?size=`, images larger than `size` are served as their WebP
+/// thumbnail. Everything else, including images uploaded before thumbnails
+/// existed, is served as the original file.
+///
+/// Responses carry `ETag` and `Last-Modified` and support `Range` requests,
+/// so video and audio can be seeked; conditional requests get `304`. These
+/// are only answered after the checks above.
+///
+/// With object storage the response is a `307` redirect to a short-lived
+/// presigned URL instead of the file itself.
 pub async fn serve_file(
     State(state): State<AppState>,
     auth: AuthUser,
     Path(params): Path<FileParams>,
+    Query(query): Query<FileQuery>,
+    request: HeaderMap,
 ) -> AppResult<Response> {
     let message_id = params.message_id;
     let filepath = params.filepath;
 
+    if let Some(size) = query.size {
+        if !THUMBNAIL_SIZES.contains(&size) {
+            return Err(AppError::Validation(format!(
+                "Thumbnail size must be one of {THUMBNAIL_SIZES:?}"
+            )));
+        }
+    }
+
     // Path traversal guard: our stored filenames never contain '/', so any
     // sub-path in the URL is either a crafted request or a bug.
     if filepath.contains('/') {
@@ -377,17 +372,33 @@ pub async fn serve_file(
     .await?
     .ok_or_else(|| AppError::NotFound("Attachment not found".into()))?;
 
-    let file_path = state
-        .upload_dir
-        .join(message_id.to_string())
-        .join(&filepath);
+    let thumbnail_size = match (query.size, attachment.width, attachment.height) {
+        (Some(size), Some(width), Some(height))
+            if images::has_thumbnail(width as u32, height as u32, size) =>
+        {
+            Some(size)
+        }
+        _ => None,
+    };
 
-    let file = File::open(&file_path).await.map_err(|e| {
-        tracing::error!(error = ?e, path = ?file_path, "Failed to open attachment file");
-        AppError::Internal
-    })?;
-    let stream = ReaderStream::new(file);
-    let body = Body::from_stream(stream);
+    let key = match thumbnail_size {
+        Some(size) => format!("{message_id}/{}", images::thumbnail_name(&filepath, size)),
+        None => format!("{message_id}/{filepath}"),
+    };
+
+    if thumbnail_size.is_some() {
+        let stem = attachment
+            .filename
+            .rsplit_once('.')
+            .map_or(attachment.filename.as_str(), |(stem, _)| stem);
+        let safe_name = sanitize_header_filename(&format!("{stem}.webp"));
+        let headers = ResponseHeaders {
+            content_type: Some("image/webp".to_string()),
+            content_disposition: Some(format!("inline; filename=\"{safe_name}\"")),
+            cache_control: Some(ATTACHMENT_CACHE_CONTROL.to_string()),
+        };
+        return serve_stored_file(state.storage.as_ref(), &key, headers, &request).await;
+    }
 
     let safe_name = sanitize_header_filename(&attachment.filename);
     let disposition = if attachment.mime_type.starts_with("image/")
@@ -399,30 +410,348 @@ pub async fn serve_file(
         format!("attachment; filename=\"{safe_name}\"")
     };
 
-    let response = Response::builder()
-        .status(StatusCode::OK)
-        .header(header::CONTENT_TYPE, attachment.mime_type)
-        .header(header::CONTENT_DISPOSITION, disposition)
-        .body(body)
-        .map_err(|_| AppError::Internal)?;
-
-    Ok(response)
+    let headers = ResponseHeaders {
+        content_type: Some(attachment.mime_type),
+        content_disposition: Some(disposition),
+        cache_control: Some(ATTACHMENT_CACHE_CONTROL.to_string()),
+    };
+    serve_stored_file(state.storage.as_ref(), &key, headers, &request).await
 }
 
 // ============================================================================
 // Private helpers
 // ============================================================================
 
-/// Intermediate representation of a multipart field parsed and validated but
-/// not yet written to disk or the database.
+/// Intermediate representation of an uploaded file validated but not yet
+/// stored or written to the database.
 struct PendingFile {
     filename: String,
     mime_type: String,
-    data: Bytes,
+    /// The staged upload, which is what gets stored unless `processed` is set.
+    staged: PathBuf,
+    /// Images re-encoded without metadata.
+    processed: Option<Bytes>,
+    size: i64,
+    width: Option<i32>,
+    height: Option<i32>,
+    thumbnails: Vec<Thumbnail>,
     stored_name: String,
     url: String,
 }
 
+/// Staging files written by one request, removed when it finishes whether
+/// or not it succeeded.
+struct StagedFiles(Vec<PathBuf>);
+
+impl Drop for StagedFiles {
+    fn drop(&mut self) {
+        for path in &self.0 {
+            if let Err(e) = std::fs::remove_file(path) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    tracing::warn!(error = ?e, path = ?path, "Failed to remove staged upload");
+                }
+            }
+        }
+    }
+}
+
+/// Check that the caller may add attachments to `message_id`, returning the
+/// message's server and how many attachments it already has.
+async fn authorize_attach(
+    state: &AppState,
+    auth: &AuthUser,
+    message_id: Uuid,
+) -> AppResult<(Uuid, i64)> {
+    let message = fetch_message(&state.pool, message_id).await?;
+    let channel = fetch_channel_by_id(&state.pool, message.channel_id).await?;
+    require_member(&state.pool, channel.server_id, auth.user_id()).await?;
+
+    // Channel-level permission check (respects per-channel overrides).
+    require_channel_permission(
+        &state.pool,
+        channel.server_id,
+        message.channel_id,
+        auth.user_id(),
+        PERMISSION_ATTACH_FILES,
+        "You don't have permission to attach files in this channel",
+    )
+    .await?;
+
+    if message.author_id != Some(auth.user_id()) {
+        return Err(AppError::Forbidden(
+            "Only the message author can add attachments".into(),
+        ));
+    }
+
+    let existing_count: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM attachments WHERE message_id = $1")
+            .bind(message_id)
+            .fetch_one(&state.pool)
+            .await?;
+    Ok((channel.server_id, existing_count))
+}
+
+/// Whose storage quotas an upload counts against.
+#[derive(Clone, Copy)]
+struct QuotaOwner {
+    server_id: Uuid,
+    user_id: Uuid,
+}
+
+/// Refuse `pending` if it would take the server or the uploader past their
+/// storage quota (`SERVER_STORAGE_QUOTA_MB`, `USER_STORAGE_QUOTA_MB`).
+///
+/// Usage is the `file_size` of every attachment row, including those of
+/// deleted messages that have not been collected yet; thumbnails are not
+/// counted. The check is only binding inside the transaction that inserts
+/// the rows, after [`lock_quotas`]: concurrent uploads to the same server or
+/// by the same user then wait for each other, so a quota is never exceeded.
+async fn check_quota(
+    conn: &mut PgConnection,
+    quota: StorageQuota,
+    owner: QuotaOwner,
+    pending: &[PendingFile],
+) -> AppResult<()> {
+    let incoming: i64 = pending.iter().map(|p| p.size).sum();
+
+    if let Some(limit) = quota.per_server {
+        let used: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(SUM(a.file_size), 0)::BIGINT
+             FROM attachments a
+             JOIN messages m ON m.id = a.message_id
+             JOIN channels c ON c.id = m.channel_id
+             WHERE c.server_id = $1",
+        )
+        .bind(owner.server_id)
+        .fetch_one(&mut *conn)
+        .await?;
+        if (used + incoming) as u64 > limit {
+            return Err(AppError::Validation(format!(
+                "This server has used its {} MB of attachment storage",
+                limit / (1024 * 1024)
+            )));
+        }
+    }
+
+    if let Some(limit) = quota.per_user {
+        let used: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(SUM(a.file_size), 0)::BIGINT
+             FROM attachments a
+             JOIN messages m ON m.id = a.message_id
+             WHERE m.author_id = $1",
+        )
+        .bind(owner.user_id)
+        .fetch_one(&mut *conn)
+        .await?;
+        if (used + incoming) as u64 > limit {
+            return Err(AppError::Validation(format!(
+                "You have used your {} MB of attachment storage",
+                limit / (1024 * 1024)
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Serialise quota checks per server and per user until the transaction
+/// ends. Locks are always taken server first, so two uploads cannot wait on
+/// each other.
+async fn lock_quotas(
+    conn: &mut PgConnection,
+    quota: StorageQuota,
+    owner: QuotaOwner,
+) -> Result<(), sqlx::Error> {
+    let limited = [
+        (quota.per_server, owner.server_id),
+        (quota.per_user, owner.user_id),
+    ];
+    for (_, id) in limited.iter().filter(|(limit, _)| limit.is_some()) {
+        let (hi, lo) = id.as_u64_pair();
+        sqlx::query("SELECT pg_advisory_xact_lock($1)")
+            .bind((hi ^ lo) as i64)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Turn a complete staged file into a [`PendingFile`]. Images are read back
+/// and processed in memory, one at a time; everything else stays on disk.
+async fn prepare_file(
+    message_id: Uuid,
+    filename: String,
+    staged: PathBuf,
+    size: u64,
+    mime_type: String,
+) -> AppResult<PendingFile> {
+    let (processed, size, width, height, thumbnails) = if mime_type.starts_with("image/") {
+        let data = tokio::fs::read(&staged).await.map_err(|e| {
+            tracing::error!(error = ?e, path = ?staged, "Failed to read staged upload");
+            AppError::Internal
+        })?;
+        let image = images::process(Bytes::from(data), &mime_type, THUMBNAIL_SIZES)
+            .await
+            .map_err(|e| AppError::Validation(e.to_string()))?;
+        (
+            Some(image.data.clone()),
+            image.data.len() as i64,
+            Some(image.width as i32),
+            Some(image.height as i32),
+            image.thumbnails,
+        )
+    } else {
+        (None, size as i64, None, None, Vec::new())
+    };
+
+    let stored_name = format!(
+        "{}_{}",
+        Uuid::new_v4().simple(),
+        sanitize_filename(&filename)
+    );
+    let url = format!("/files/{message_id}/{stored_name}");
+
+    Ok(PendingFile {
+        filename,
+        mime_type,
+        staged,
+        processed,
+        size,
+        width,
+        height,
+        thumbnails,
+        stored_name,
+        url,
+    })
+}
+
+/// Store `pending` files with their thumbnails and insert their rows in one
+/// transaction, consuming the resumable `uploads` they came from. The storage
+/// quotas of `owner` are enforced in that transaction. On failure everything
+/// already stored is removed again.
+async fn store_attachments(
+    state: &AppState,
+    message_id: Uuid,
+    owner: QuotaOwner,
+    pending: &[PendingFile],
+    uploads: &[Uuid],
+) -> AppResult<Vec<Attachment>> {
+    let quota = state.config.storage_quota;
+
+    // Refuse right away when the quota is already used up, rather than after
+    // storing the files. The binding check is repeated under the lock below.
+    check_quota(&mut *state.pool.acquire().await?, quota, owner, pending).await?;
+
+    // ── Pass 2: store all files ───────────────────────────────────────────────
+
+    let mut written_keys: Vec<String> = Vec::new();
+
+    for p in pending {
+        let key = format!("{message_id}/{}", p.stored_name);
+        let result = match &p.processed {
+            Some(data) => state.storage.put(&key, data.clone(), &p.mime_type).await,
+            None => state.storage.put_file(&key, &p.staged, &p.mime_type).await,
+        };
+        if let Err(e) = result {
+            tracing::error!(error = ?e, key, "Failed to store uploaded file");
+            cleanup_files(state.storage.as_ref(), &written_keys).await;
+            return Err(AppError::Internal);
+        }
+        written_keys.push(key);
+
+        for thumbnail in &p.thumbnails {
+            let key = format!(
+                "{message_id}/{}",
+                images::thumbnail_name(&p.stored_name, thumbnail.size)
+            );
+            let data = Bytes::copy_from_slice(&thumbnail.data);
+            if let Err(e) = state.storage.put(&key, data, "image/webp").await {
+                tracing::error!(error = ?e, key, "Failed to store thumbnail");
+                cleanup_files(state.storage.as_ref(), &written_keys).await;
+                return Err(AppError::Internal);
+            }
+            written_keys.push(key);
+        }
+    }
+
+    // ── Pass 3: insert all rows in a single transaction ───────────────────────
+
+    let mut tx = match state.pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to begin upload transaction");
+            cleanup_files(state.storage.as_ref(), &written_keys).await;
+            return Err(AppError::from(e));
+        }
+    };
+
+    let checked = match lock_quotas(&mut tx, quota, owner).await {
+        Ok(()) => check_quota(&mut tx, quota, owner, pending).await,
+        Err(e) => Err(AppError::from(e)),
+    };
+    if let Err(e) = checked {
+        let _ = tx.rollback().await;
+        cleanup_files(state.storage.as_ref(), &written_keys).await;
+        return Err(e);
+    }
+
+    let mut created: Vec<Attachment> = Vec::new();
+
+    for p in pending {
+        match sqlx::query_as::<_, Attachment>(
+            "INSERT INTO attachments (message_id, filename, file_size, mime_type, url, width, height)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)
+             RETURNING id, message_id, filename, file_size, mime_type, url, width, height, created_at",
+        )
+        .bind(message_id)
+        .bind(&p.filename)
+        .bind(p.size)
+        .bind(&p.mime_type)
+        .bind(&p.url)
+        .bind(p.width)
+        .bind(p.height)
+        .fetch_one(&mut *tx)
+        .await
+        {
+            Ok(att) => created.push(att),
+            Err(e) => {
+                tracing::error!(error = ?e, "Failed to insert attachment row; rolling back");
+                let _ = tx.rollback().await;
+                cleanup_files(state.storage.as_ref(), &written_keys).await;
+                return Err(AppError::from(e));
+            }
+        }
+    }
+
+    if !uploads.is_empty() {
+        // A concurrent request that attached or cancelled the same upload
+        // got here first.
+        let consumed = sqlx::query("DELETE FROM uploads WHERE id = ANY($1)")
+            .bind(uploads)
+            .execute(&mut *tx)
+            .await;
+        match consumed {
+            Ok(r) if r.rows_affected() == uploads.len() as u64 => {}
+            result => {
+                if let Err(e) = result {
+                    tracing::error!(error = ?e, "Failed to consume uploads; rolling back");
+                }
+                let _ = tx.rollback().await;
+                cleanup_files(state.storage.as_ref(), &written_keys).await;
+                return Err(AppError::NotFound("Upload not found".into()));
+            }
+        }
+    }
+
+    if let Err(e) = tx.commit().await {
+        tracing::error!(error = ?e, "Failed to commit upload transaction; cleaning up files");
+        cleanup_files(state.storage.as_ref(), &written_keys).await;
+        return Err(AppError::from(e));
+    }
+
+    Ok(created)
+}
+
 /// Path parameters for the file-serving route.
 #[derive(Deserialize)]
 pub struct FileParams {
@@ -430,11 +759,19 @@ pub struct FileParams {
     pub filepath: String,
 }
 
-/// Delete all paths in `paths`, logging any errors but not propagating them.
-async fn cleanup_files(paths: &[PathBuf]) {
-    for p in paths {
-        if let Err(e) = tokio::fs::remove_file(p).await {
-            tracing::warn!(error = ?e, path = ?p, "Failed to clean up orphaned upload file");
+/// Query parameters for the file-serving route.
+#[derive(Deserialize, utoipa::IntoParams)]
+pub struct FileQuery {
+    /// Serve the image's WebP thumbnail that fits within `size`×`size`
+    /// pixels: 256, 512 or 1024.
+    pub size: Option<u32>,
+}
+
+/// Delete all `keys` from storage, logging any errors but not propagating them.
+async fn cleanup_files(storage: &dyn Storage, keys: &[String]) {
+    for key in keys {
+        if let Err(e) = storage.delete(key).await {
+            tracing::warn!(error = ?e, key, "Failed to clean up orphaned upload file");
         }
     }
 }
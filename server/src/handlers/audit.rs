@@ -2,6 +2,7 @@
 //!
 //! Provides:
 //! - `log_action()` - Record an admin action
+//! - `log_instance_action()` - Record an instance admin action
 //! - `GET /servers/:id/audit-logs` - List audit logs (owner only)
 
 use axum::{
@@ -15,7 +16,7 @@ use super::shared::fetch_server;
 use crate::{
     auth::AuthUser,
     error::{AppError, AppResult},
-    models::{AuditLog, CreateAuditLog, ListAuditLogsQuery},
+    models::{AuditAction, AuditLog, CreateAuditLog, ListAuditLogsQuery},
     state::AppState,
 };
 
@@ -66,6 +67,47 @@ pub async fn log_action(pool: &PgPool, entry: &CreateAuditLog) {
     }
 }
 
+/// Log an instance admin action to the audit log.
+///
+/// Instance actions are not tied to a server, so the row has no `server_id`
+/// and does not appear in any server's audit log. Like [`log_action`], this
+/// logs and continues on error.
+pub async fn log_instance_action(
+    pool: &PgPool,
+    actor_id: Uuid,
+    action: AuditAction,
+    target_type: &str,
+    target_id: Uuid,
+    details: serde_json::Value,
+) {
+    let action_str = action.to_string();
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO audit_logs (server_id, actor_id, action, target_type, target_id, details)
+        VALUES (NULL, $1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(actor_id)
+    .bind(&action_str)
+    .bind(target_type)
+    .bind(target_id)
+    .bind(&details)
+    .execute(pool)
+    .await;
+
+    if let Err(e) = result {
+        tracing::error!(
+            error = ?e,
+            actor_id = %actor_id,
+            action = %action_str,
+            target_type,
+            target_id = %target_id,
+            "Failed to write instance audit log"
+        );
+    }
+}
+
 // ============================================================================
 // Handler
 // ============================================================================
@@ -145,5 +187,13 @@ mod tests {
         assert_eq!(AuditAction::ChannelDelete.to_string(), "channel_delete");
         assert_eq!(AuditAction::MemberKick.to_string(), "member_kick");
         assert_eq!(AuditAction::RoleUpdate.to_string(), "role_update");
+        assert_eq!(
+            AuditAction::MemberVoiceDisconnect.to_string(),
+            "member_voice_disconnect"
+        );
+        assert_eq!(
+            AuditAction::UserTwoFactorReset.to_string(),
+            "user_two_factor_reset"
+        );
     }
 }
@@ -2,6 +2,7 @@ use axum::{extract::State, http::StatusCode, Json};
 use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
 use tracing::info;
 use utoipa::ToSchema;
 use uuid::Uuid;
@@ -15,6 +16,11 @@ use crate::{
         validate_token, verify_password, AuthUser, TokenType,
     },
     error::{AppError, AppResult},
+    handlers::{
+        email::{new_email_token, send_verification_email},
+        two_factor::{self, TwoFactorChallenge},
+    },
+    mail::{self, Template},
     models::{User, UserDto},
     state::AppState,
 };
@@ -60,6 +66,14 @@ pub struct AuthResponse {
     pub user: UserDto,
 }
 
+/// Tokens, or a challenge when the account needs a second step (2FA).
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Authenticated(Box<AuthResponse>),
+    TwoFactor(TwoFactorChallenge),
+}
+
 // ============================================================================
 // Handlers
 // ============================================================================
@@ -69,7 +83,7 @@ pub struct AuthResponse {
     path = "/auth/register",
     request_body = RegisterRequest,
     responses(
-        (status = 201, description = "User registered", body = AuthResponse),
+        (status = 201, description = "User registered, with tokens or a two-factor enrollment challenge", body = LoginResponse),
         (status = 400, description = "Validation error"),
         (status = 403, description = "Registration closed or invalid invite"),
         (status = 409, description = "Username already taken")
@@ -79,10 +93,21 @@ pub struct AuthResponse {
 pub async fn register(
     State(state): State<AppState>,
     Json(req): Json<RegisterRequest>,
-) -> AppResult<(StatusCode, Json<AuthResponse>)> {
+) -> AppResult<(StatusCode, Json<LoginResponse>)> {
     req.validate()
         .map_err(|e| AppError::Validation(e.to_string()))?;
 
+    if state
+        .config
+        .oidc
+        .as_ref()
+        .is_some_and(|oidc| oidc.disable_registration)
+    {
+        return Err(AppError::Forbidden(
+            "Accounts are created through single sign-on".into(),
+        ));
+    }
+
     info!("Registering new user: {}", req.username);
 
     // ── Registration policy check ──────────────────────────────────────────
@@ -91,59 +116,23 @@ pub async fn register(
             .fetch_one(&state.pool)
             .await?;
 
-    match registration_mode.as_str() {
+    let invite_only = match registration_mode.as_str() {
         "closed" => {
             return Err(AppError::Forbidden(
                 "Registration is currently closed".into(),
             ));
         }
-        "invite_only" => {
-            let code = req.invite_code.as_deref().unwrap_or("").trim();
-            if code.is_empty() {
-                return Err(AppError::Validation(
-                    "An invite code is required to register".into(),
-                ));
-            }
-
-            // Validate invite: exists, not expired, not maxed out.
-            let valid: bool = sqlx::query_scalar(
-                "SELECT EXISTS(
-                     SELECT 1 FROM server_invites
-                     WHERE code = $1
-                       AND (expires_at IS NULL OR expires_at > NOW())
-                       AND (max_uses IS NULL OR uses < max_uses)
-                 )",
-            )
-            .bind(code)
-            .fetch_one(&state.pool)
-            .await?;
-
-            if !valid {
-                return Err(AppError::Validation(
-                    "Invalid or expired invite code".into(),
-                ));
-            }
-
-            // Increment uses atomically (race-safe).
-            sqlx::query(
-                "UPDATE server_invites SET uses = uses + 1
-                 WHERE code = $1
-                   AND (expires_at IS NULL OR expires_at > NOW())
-                   AND (max_uses IS NULL OR uses < max_uses)",
-            )
-            .bind(code)
-            .execute(&state.pool)
-            .await?;
-        }
-        _ => { /* "open" — proceed normally */ }
-    }
+        "invite_only" => true,
+        _ => false, // "open" — proceed normally
+    };
 
     let password_hash = hash_password(&req.password)?;
 
-    // Transaction: user creation and session insert are atomic.
-    // If the session insert fails, the user row is rolled back so the client
-    // does not end up locked out of an account they never successfully created.
+    // Transaction: the invite use is only counted if the user is created.
     let mut tx = state.pool.begin().await?;
+    if invite_only {
+        redeem_invite(&mut tx, req.invite_code.as_deref()).await?;
+    }
 
     // INSERT directly — the DB UNIQUE constraint handles duplicates.
     // From<sqlx::Error> maps PG error 23505 → AppError::Conflict (409).
@@ -159,42 +148,82 @@ pub async fn register(
     .bind(&password_hash)
     .fetch_one(&mut *tx)
     .await?;
+    tx.commit().await?;
 
     info!("User created: {} ({})", user.username, user.id);
 
-    let access_token = create_access_token(user.id, user.username.clone(), &state.jwt_secret)?;
-    let refresh_token = create_refresh_token(user.id, user.username.clone(), &state.jwt_secret)?;
-
-    // SHA-256 hash — deterministic, so sessions can be looked up by token hash.
-    let refresh_token_hash = hash_refresh_token(&refresh_token);
-
-    sqlx::query(
-        r#"
-        INSERT INTO sessions (user_id, refresh_token_hash, expires_at)
-        VALUES ($1, $2, NOW() + INTERVAL '7 days')
-        "#,
-    )
-    .bind(user.id)
-    .bind(&refresh_token_hash)
-    .execute(&mut *tx)
-    .await?;
+    if let (Some(mailer), Some(email)) = (&state.mailer, &user.email) {
+        // The account works without a verified address, so a failure here
+        // only costs the user a resend.
+        if let Err(e) =
+            send_verification_email(&state, mailer, user.id, &user.username, email).await
+        {
+            tracing::warn!(user_id = %user.id, error = ?e, "Failed to queue verification email");
+        }
+    }
 
-    tx.commit().await?;
+    // A new account has no second factor yet, so when the instance requires
+    // one this is an enrollment challenge and no session is created.
+    if let Some(challenge) = two_factor::login_challenge(&state, &user).await? {
+        info!(
+            "Two-factor enrollment pending for new user: {} ({})",
+            user.username, user.id
+        );
+        return Ok((
+            StatusCode::CREATED,
+            Json(LoginResponse::TwoFactor(challenge)),
+        ));
+    }
 
     Ok((
         StatusCode::CREATED,
-        Json(AuthResponse {
-            access_token,
-            refresh_token,
-            user: user.into(),
-        }),
+        Json(LoginResponse::Authenticated(Box::new(
+            start_session(&state, user).await?,
+        ))),
     ))
 }
 
+/// Check an invite code for an invite-only registration and count the use.
+///
+/// Runs on the caller's transaction so the use is only counted if the
+/// account is actually created.
+pub(crate) async fn redeem_invite(
+    conn: &mut PgConnection,
+    invite_code: Option<&str>,
+) -> AppResult<()> {
+    let code = invite_code.unwrap_or("").trim();
+    if code.is_empty() {
+        return Err(AppError::Validation(
+            "An invite code is required to register".into(),
+        ));
+    }
+
+    // Validate and count in one statement: exists, not expired, not maxed
+    // out (race-safe).
+    let redeemed = sqlx::query(
+        "UPDATE server_invites SET uses = uses + 1
+         WHERE code = $1
+           AND (expires_at IS NULL OR expires_at > NOW())
+           AND (max_uses IS NULL OR uses < max_uses)",
+    )
+    .bind(code)
+    .execute(conn)
+    .await?
+    .rows_affected();
+
+    if redeemed == 0 {
+        return Err(AppError::Validation(
+            "Invalid or expired invite code".into(),
+        ));
+    }
+    Ok(())
+}
+
 /// GET /instance/registration-mode — Public endpoint (no auth required).
 ///
 /// Returns the current registration mode so the login page can show/hide
-/// the register button and invite code field.
+/// the register button and invite code field, and under `sso` the single
+/// sign-on provider when one is configured.
 #[utoipa::path(
     get,
     path = "/instance/registration-mode",
@@ -211,7 +240,13 @@ pub async fn get_registration_mode(
             .fetch_one(&state.pool)
             .await?;
 
-    Ok(Json(json!({ "registration_mode": registration_mode })))
+    Ok(Json(json!({
+        "registration_mode": registration_mode,
+        "sso": state.config.oidc.as_ref().map(|oidc| json!({
+            "provider_name": oidc.provider_name,
+            "password_registration": !oidc.disable_registration,
+        })),
+    })))
 }
 
 #[utoipa::path(
@@ -219,7 +254,7 @@ pub async fn get_registration_mode(
     path = "/auth/login",
     request_body = LoginRequest,
     responses(
-        (status = 200, description = "Login successful", body = AuthResponse),
+        (status = 200, description = "Login successful, or a two-factor challenge", body = LoginResponse),
         (status = 400, description = "Validation error"),
         (status = 401, description = "Invalid credentials"),
         (status = 403, description = "Account disabled")
@@ -229,7 +264,7 @@ pub async fn get_registration_mode(
 pub async fn login(
     State(state): State<AppState>,
     Json(req): Json<LoginRequest>,
-) -> AppResult<Json<AuthResponse>> {
+) -> AppResult<Json<LoginResponse>> {
     req.validate()
         .map_err(|e| AppError::Validation(e.to_string()))?;
 
@@ -252,8 +287,24 @@ pub async fn login(
         ));
     }
 
+    if let Some(challenge) = two_factor::login_challenge(&state, &user).await? {
+        info!(
+            "Password accepted, two-factor step pending: {} ({})",
+            user.username, user.id
+        );
+        return Ok(Json(LoginResponse::TwoFactor(challenge)));
+    }
+
     info!("Login successful: {} ({})", user.username, user.id);
 
+    Ok(Json(LoginResponse::Authenticated(Box::new(
+        start_session(&state, user).await?,
+    ))))
+}
+
+/// Create a session for a user who has passed every login step and return
+/// their tokens.
+pub(crate) async fn start_session(state: &AppState, user: User) -> AppResult<AuthResponse> {
     let access_token = create_access_token(user.id, user.username.clone(), &state.jwt_secret)?;
     let refresh_token = create_refresh_token(user.id, user.username.clone(), &state.jwt_secret)?;
     let refresh_token_hash = hash_refresh_token(&refresh_token);
@@ -296,11 +347,11 @@ pub async fn login(
 
     tx.commit().await?;
 
-    Ok(Json(AuthResponse {
+    Ok(AuthResponse {
         access_token,
         refresh_token,
         user: user.into(),
-    }))
+    })
 }
 
 #[utoipa::path(
@@ -310,7 +361,7 @@ pub async fn login(
     responses(
         (status = 200, description = "Token refreshed", body = AuthResponse),
         (status = 401, description = "Invalid or expired refresh token"),
-        (status = 403, description = "Account disabled")
+        (status = 403, description = "Account disabled, or two-factor setup required")
     ),
     tag = "Auth"
 )]
@@ -360,6 +411,12 @@ pub async fn refresh_token(
         ));
     }
 
+    if two_factor::enrollment_pending(&state, &user).await? {
+        return Err(AppError::Forbidden(
+            "Two-factor authentication is required; log in again to set it up".into(),
+        ));
+    }
+
     info!("Token refresh for user: {} ({})", user.username, user.id);
 
     // Rotate the refresh token: generate a new one, hash it, and update the session row.
@@ -410,39 +467,59 @@ pub struct ResetPasswordRequest {
     pub new_password: String,
 }
 
-/// POST /auth/forgot-password — Generate a password reset token for a user.
+/// Reset links stay valid this long.
+const RESET_TOKEN_TTL_SECS: i64 = 3600;
+
+/// POST /auth/forgot-password — Start a password reset.
 ///
-/// Admin-only endpoint. Returns the reset token in the response body for
-/// manual delivery (e.g., admin sharing with user out-of-band).
+/// With email configured, anyone may call this: a reset link is mailed to
+/// the account with that address, and the response never says whether one
+/// exists. Admins always get the token back in the response for manual
+/// delivery (e.g., sharing with the user out-of-band), which is the only
+/// way to reset a password when the server cannot send mail.
 #[utoipa::path(
     post,
     path = "/auth/forgot-password",
     request_body = ForgotPasswordRequest,
     responses(
-        (status = 200, description = "Reset token generated", body = serde_json::Value),
+        (status = 200, description = "Reset token generated, or reset email queued", body = serde_json::Value),
         (status = 400, description = "Validation error"),
-        (status = 403, description = "Admin access required"),
-        (status = 404, description = "User not found")
+        (status = 401, description = "Authentication required (email not configured)"),
+        (status = 403, description = "Admin access required (email not configured)"),
+        (status = 404, description = "User not found (admin request)")
     ),
-    security(("bearer_auth" = [])),
+    security((), ("bearer_auth" = [])),
     tag = "Auth"
 )]
 pub async fn forgot_password(
     State(state): State<AppState>,
-    auth_user: AuthUser,
+    auth_user: Option<AuthUser>,
     Json(req): Json<ForgotPasswordRequest>,
 ) -> AppResult<Json<serde_json::Value>> {
     req.validate()
         .map_err(|e| AppError::Validation(e.to_string()))?;
 
-    // Admin-only endpoint — verify via DB lookup
-    let is_admin: bool = sqlx::query_scalar("SELECT is_admin FROM users WHERE id = $1")
-        .bind(auth_user.user_id())
-        .fetch_one(&state.pool)
-        .await?;
+    let is_admin = match &auth_user {
+        Some(auth_user) => {
+            sqlx::query_scalar("SELECT is_admin FROM users WHERE id = $1")
+                .bind(auth_user.user_id())
+                .fetch_one(&state.pool)
+                .await?
+        }
+        None => false,
+    };
 
     if !is_admin {
-        return Err(AppError::Forbidden("Admin access required".into()));
+        if state.mailer.is_none() {
+            return Err(match auth_user {
+                None => AppError::Auth("Missing or invalid Authorization header".into()),
+                Some(_) => AppError::Forbidden("Admin access required".into()),
+            });
+        }
+        email_password_reset(&state, &req.email).await?;
+        return Ok(Json(serde_json::json!({
+            "message": "If an account uses that email address, a reset link has been sent"
+        })));
     }
 
     // Find user by email — return 404 since this is admin-only (no enumeration risk)
@@ -452,43 +529,94 @@ pub async fn forgot_password(
         .await?
         .ok_or_else(|| AppError::NotFound(format!("No user found with email: {}", req.email)))?;
 
-    // Generate a secure reset token (32 bytes, base64url encoded)
-    let reset_token = {
-        use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
-        let bytes: [u8; 32] = rand::random();
-        URL_SAFE_NO_PAD.encode(bytes)
-    };
+    let reset_token = create_reset_token(&state, user.id).await?;
 
-    // Hash the token for storage (same pattern as refresh tokens)
-    let token_hash = hash_refresh_token(&reset_token);
+    info!(
+        "Password reset token created for user: {} ({})",
+        user.username, user.id
+    );
 
-    // Delete any existing reset tokens for this user
+    Ok(Json(serde_json::json!({
+        "message": "Password reset token generated",
+        "token": reset_token,
+        "expires_in_seconds": RESET_TOKEN_TTL_SECS,
+        "note": "Share this token with the user to reset their password"
+    })))
+}
+
+/// Replace the user's reset tokens with a new one and return it. Only the
+/// SHA-256 hash is stored (same pattern as refresh tokens).
+async fn create_reset_token(state: &AppState, user_id: Uuid) -> AppResult<String> {
+    let (reset_token, token_hash) = new_email_token();
+
+    let mut tx = state.pool.begin().await?;
     sqlx::query("DELETE FROM password_reset_tokens WHERE user_id = $1")
-        .bind(user.id)
-        .execute(&state.pool)
+        .bind(user_id)
+        .execute(&mut *tx)
         .await?;
-
-    // Insert new token (expires in 1 hour)
     sqlx::query(
         "INSERT INTO password_reset_tokens (user_id, token_hash, expires_at) \
-         VALUES ($1, $2, NOW() + INTERVAL '1 hour')",
+         VALUES ($1, $2, NOW() + make_interval(secs => $3))",
     )
-    .bind(user.id)
+    .bind(user_id)
     .bind(&token_hash)
-    .execute(&state.pool)
+    .bind(RESET_TOKEN_TTL_SECS as f64)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(reset_token)
+}
+
+/// Self-service reset: mail a link to the account with `email`, if any.
+///
+/// Unknown and disabled accounts are skipped silently, and a second request
+/// within a minute of the last one sends nothing, so the endpoint cannot be
+/// used to flood an inbox.
+async fn email_password_reset(state: &AppState, email: &str) -> AppResult<()> {
+    let Some(mailer) = &state.mailer else {
+        return Ok(());
+    };
+    let user: Option<(Uuid, String, bool)> = sqlx::query_as(
+        "SELECT u.id, u.username,
+                EXISTS(
+                    SELECT 1 FROM password_reset_tokens t
+                    WHERE t.user_id = u.id AND t.created_at > NOW() - INTERVAL '1 minute'
+                )
+         FROM users u
+         WHERE u.email = $1 AND NOT u.disabled",
+    )
+    .bind(email)
+    .fetch_optional(&state.pool)
     .await?;
+    let Some((user_id, username, recently_sent)) = user else {
+        return Ok(());
+    };
+    if recently_sent {
+        return Ok(());
+    }
 
+    let reset_token = create_reset_token(state, user_id).await?;
     info!(
-        "Password reset token created for user: {} ({})",
-        user.username, user.id
+        "Password reset email queued for user: {} ({})",
+        username, user_id
     );
 
-    Ok(Json(serde_json::json!({
-        "message": "Password reset token generated",
-        "token": reset_token,
-        "expires_in_seconds": 3600,
-        "note": "Share this token with the user to reset their password"
-    })))
+    let link = mailer.link(&format!("/reset-password?token={reset_token}"));
+    let expires_minutes = (RESET_TOKEN_TTL_SECS / 60).to_string();
+    mail::send(
+        state,
+        mailer.compose(
+            email,
+            Template::PasswordReset,
+            &[
+                ("username", &username),
+                ("link", &link),
+                ("expires_minutes", &expires_minutes),
+            ],
+        ),
+    );
+    Ok(())
 }
 
 /// POST /auth/reset-password — Reset password using token.
@@ -6,6 +6,10 @@
 //! - `GET /servers/:id/automod/words` — List word filters (owner only)
 //! - `POST /servers/:id/automod/words` — Add word filter (owner only)
 //! - `DELETE /servers/:id/automod/words/:word` — Remove word filter (owner only)
+//! - `GET /servers/:id/automod/rules` — List automod rules (owner only)
+//! - `POST /servers/:id/automod/rules` — Create an automod rule (owner only)
+//! - `PATCH /servers/:id/automod/rules/:rule_id` — Update an automod rule (owner only)
+//! - `DELETE /servers/:id/automod/rules/:rule_id` — Delete an automod rule (owner only)
 //! - `GET /servers/:id/automod/logs` — List automod logs (owner only)
 //! - `GET /servers/:id/bans` — List server bans (owner only)
 //! - `DELETE /servers/:id/bans/:user_id` — Remove a ban (owner only)
@@ -15,22 +19,64 @@ use axum::{
     http::StatusCode,
     Json,
 };
-use sqlx::Row;
 use uuid::Uuid;
 
-use super::shared::{fetch_server, require_permission, PERMISSION_BAN_MEMBERS};
+use super::incoming_webhooks::{insert_webhook_message, WebhookIdentity};
+use super::shared::{
+    fetch_channel_by_id, fetch_server, require_permission, PERMISSION_BAN_MEMBERS,
+};
 use crate::{
     auth::AuthUser,
+    automod_engine::{self, MemberContext, MessageContext, RuleMatch},
     error::{AppError, AppResult},
+    event_bus::BusMessage,
     handlers::audit::log_action,
     models::{
-        AddWordFilterRequest, AuditAction, AutomodConfig, AutomodLog, AutomodWordFilter,
-        CreateAuditLog, ServerBan, UpdateAutomodConfigRequest,
+        AddWordFilterRequest, AuditAction, AutomodConfig, AutomodLog, AutomodRule,
+        AutomodRuleAction, AutomodTrigger, AutomodWordFilter, ChannelType, CreateAuditLog,
+        CreateAutomodRuleRequest, EmbedField, MessageEmbed, ServerBan, UpdateAutomodConfigRequest,
+        UpdateAutomodRuleRequest,
     },
     state::AppState,
-    websocket::{broadcast_to_server, events::EVENT_MEMBER_UNBAN},
+    websocket::{
+        broadcast_to_channel, broadcast_to_server,
+        events::{EVENT_MEMBER_UNBAN, EVENT_MESSAGE_CREATE},
+        invalidate_channel_viewers,
+    },
 };
 
+/// Upper bound on rules per server; each one is evaluated on every message.
+const MAX_RULES_PER_SERVER: i64 = 25;
+const MAX_KEYWORDS: usize = 1000;
+const MAX_KEYWORD_LEN: usize = 60;
+const MAX_PATTERNS: usize = 10;
+const MAX_PATTERN_LEN: usize = 260;
+/// Longest timeout a rule can hand out: one week.
+const MAX_TIMEOUT_MINUTES: i32 = 10_080;
+
+const RULE_COLUMNS: &str = "id, server_id, name, enabled, trigger, actions, channel_ids, role_ids,
+     exempt_channel_ids, exempt_role_ids, created_by, created_at, updated_at";
+
+/// Drop the compiled automod configuration of `server_id` on every node.
+///
+/// Call after any write to the config, word list or rules.
+pub async fn invalidate_automod(state: &AppState, server_id: Uuid) {
+    state
+        .events
+        .publish(BusMessage::InvalidateAutomod { server_id })
+        .await;
+}
+
+async fn require_owner(state: &AppState, server_id: Uuid, auth: &AuthUser) -> AppResult<()> {
+    let server = fetch_server(&state.pool, server_id).await?;
+    if auth.user_id() != server.owner_id {
+        return Err(AppError::Forbidden(
+            "Only the server owner can manage automod".into(),
+        ));
+    }
+    Ok(())
+}
+
 // ============================================================================
 // Handlers
 // ============================================================================
@@ -167,6 +213,8 @@ pub async fn update_automod_config(
     .fetch_one(&state.pool)
     .await?;
 
+    invalidate_automod(&state, server_id).await;
+
     Ok(Json(config))
 }
 
@@ -257,6 +305,8 @@ pub async fn add_word_filter(
     .fetch_one(&state.pool)
     .await?;
 
+    invalidate_automod(&state, server_id).await;
+
     Ok((StatusCode::CREATED, Json(filter)))
 }
 
@@ -296,6 +346,8 @@ pub async fn remove_word_filter(
         .execute(&state.pool)
         .await?;
 
+    invalidate_automod(&state, server_id).await;
+
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -329,7 +381,7 @@ pub async fn list_automod_logs(
 
     let logs = sqlx::query_as::<_, AutomodLog>(
         "SELECT id, server_id, channel_id, user_id, username, rule_type, action_taken,
-                matched_term, message_content, created_at
+                matched_term, message_content, rule_id, created_at
          FROM automod_logs WHERE server_id = $1 ORDER BY created_at DESC LIMIT 100",
     )
     .bind(server_id)
@@ -339,6 +391,357 @@ pub async fn list_automod_logs(
     Ok(Json(logs))
 }
 
+// ── Rules ──────────────────────────────────────────────────────────────────
+
+/// Validated, normalized rule fields ready to be written.
+struct RuleFields {
+    name: String,
+    enabled: bool,
+    trigger: AutomodTrigger,
+    actions: Vec<AutomodRuleAction>,
+    channel_ids: Vec<Uuid>,
+    role_ids: Vec<Uuid>,
+    exempt_channel_ids: Vec<Uuid>,
+    exempt_role_ids: Vec<Uuid>,
+}
+
+fn validate_trigger_limits(trigger: &AutomodTrigger) -> AppResult<()> {
+    match trigger {
+        AutomodTrigger::Keyword { keywords } => {
+            if keywords.len() > MAX_KEYWORDS {
+                return Err(AppError::Validation(format!(
+                    "A rule can have at most {MAX_KEYWORDS} keywords"
+                )));
+            }
+            if keywords.iter().any(|k| k.chars().count() > MAX_KEYWORD_LEN) {
+                return Err(AppError::Validation(format!(
+                    "Keywords must be ≤{MAX_KEYWORD_LEN} characters"
+                )));
+            }
+        }
+        AutomodTrigger::Regex { patterns } => {
+            if patterns.len() > MAX_PATTERNS {
+                return Err(AppError::Validation(format!(
+                    "A rule can have at most {MAX_PATTERNS} patterns"
+                )));
+            }
+            if patterns.iter().any(|p| p.chars().count() > MAX_PATTERN_LEN) {
+                return Err(AppError::Validation(format!(
+                    "Patterns must be ≤{MAX_PATTERN_LEN} characters"
+                )));
+            }
+        }
+        _ => {}
+    }
+    automod_engine::validate_trigger(trigger).map_err(AppError::Validation)
+}
+
+/// Fail with 400 unless every id in `ids` is a channel (or role) of `server_id`.
+async fn require_server_ids(
+    pool: &sqlx::PgPool,
+    table: &str,
+    server_id: Uuid,
+    ids: &[Uuid],
+) -> AppResult<()> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+    let found: i64 = sqlx::query_scalar(&format!(
+        "SELECT COUNT(DISTINCT id) FROM {table} WHERE server_id = $1 AND id = ANY($2)"
+    ))
+    .bind(server_id)
+    .bind(ids)
+    .fetch_one(pool)
+    .await?;
+    let mut unique = ids.to_vec();
+    unique.sort_unstable();
+    unique.dedup();
+    if found != unique.len() as i64 {
+        let kind = if table == "roles" {
+            "roles"
+        } else {
+            "channels"
+        };
+        return Err(AppError::Validation(format!(
+            "Rule scope references {kind} outside this server"
+        )));
+    }
+    Ok(())
+}
+
+async fn validate_rule(
+    pool: &sqlx::PgPool,
+    server_id: Uuid,
+    rule: &mut RuleFields,
+) -> AppResult<()> {
+    rule.name = rule.name.trim().to_string();
+    if rule.name.is_empty() || rule.name.chars().count() > 100 {
+        return Err(AppError::Validation(
+            "Rule name must be 1–100 characters".into(),
+        ));
+    }
+
+    validate_trigger_limits(&rule.trigger)?;
+
+    if rule.actions.is_empty() {
+        return Err(AppError::Validation(
+            "A rule needs at least one action".into(),
+        ));
+    }
+    for action in &rule.actions {
+        match action {
+            AutomodRuleAction::Delete => {}
+            AutomodRuleAction::Timeout { duration_minutes } => {
+                if !(1..=MAX_TIMEOUT_MINUTES).contains(duration_minutes) {
+                    return Err(AppError::Validation(format!(
+                        "Timeout duration must be 1–{MAX_TIMEOUT_MINUTES} minutes"
+                    )));
+                }
+            }
+            AutomodRuleAction::Alert { channel_id } => {
+                let channel = fetch_channel_by_id(pool, *channel_id).await?;
+                if channel.server_id != server_id {
+                    return Err(AppError::Validation(
+                        "Alert channel must belong to this server".into(),
+                    ));
+                }
+                if !matches!(channel.r#type, ChannelType::Text) {
+                    return Err(AppError::Validation(
+                        "Alert channel must be a text channel".into(),
+                    ));
+                }
+            }
+        }
+    }
+
+    for ids in [&rule.channel_ids, &rule.exempt_channel_ids] {
+        require_server_ids(pool, "channels", server_id, ids).await?;
+    }
+    for ids in [&rule.role_ids, &rule.exempt_role_ids] {
+        require_server_ids(pool, "roles", server_id, ids).await?;
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/servers/{id}/automod/rules",
+    params(
+        ("id" = Uuid, Path, description = "Server ID"),
+    ),
+    responses(
+        (status = 200, description = "Automod rules in creation order", body = Vec<AutomodRule>),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Automod"
+)]
+/// GET /servers/:id/automod/rules — List all rules, including disabled ones.
+///
+/// Only the server owner can view automod rules.
+pub async fn list_automod_rules(
+    Path(server_id): Path<Uuid>,
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> AppResult<Json<Vec<AutomodRule>>> {
+    require_owner(&state, server_id, &auth).await?;
+
+    let rules = sqlx::query_as::<_, AutomodRule>(&format!(
+        "SELECT {RULE_COLUMNS} FROM automod_rules
+         WHERE server_id = $1 ORDER BY created_at ASC"
+    ))
+    .bind(server_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rules))
+}
+
+#[utoipa::path(
+    post,
+    path = "/servers/{id}/automod/rules",
+    params(
+        ("id" = Uuid, Path, description = "Server ID"),
+    ),
+    request_body = CreateAutomodRuleRequest,
+    responses(
+        (status = 201, description = "Rule created", body = AutomodRule),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Automod"
+)]
+/// POST /servers/:id/automod/rules — Create a rule.
+///
+/// Only the server owner can create rules. Rules take effect immediately and
+/// run regardless of the legacy `enabled` flag in the automod config.
+pub async fn create_automod_rule(
+    Path(server_id): Path<Uuid>,
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(body): Json<CreateAutomodRuleRequest>,
+) -> AppResult<(StatusCode, Json<AutomodRule>)> {
+    require_owner(&state, server_id, &auth).await?;
+
+    let mut fields = RuleFields {
+        name: body.name,
+        enabled: body.enabled.unwrap_or(true),
+        trigger: body.trigger,
+        actions: body.actions,
+        channel_ids: body.channel_ids,
+        role_ids: body.role_ids,
+        exempt_channel_ids: body.exempt_channel_ids,
+        exempt_role_ids: body.exempt_role_ids,
+    };
+    validate_rule(&state.pool, server_id, &mut fields).await?;
+
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM automod_rules WHERE server_id = $1")
+        .bind(server_id)
+        .fetch_one(&state.pool)
+        .await?;
+    if count >= MAX_RULES_PER_SERVER {
+        return Err(AppError::Validation(format!(
+            "A server can have at most {MAX_RULES_PER_SERVER} automod rules"
+        )));
+    }
+
+    let rule = sqlx::query_as::<_, AutomodRule>(&format!(
+        "INSERT INTO automod_rules
+             (server_id, name, enabled, trigger, actions, channel_ids, role_ids,
+              exempt_channel_ids, exempt_role_ids, created_by)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+         RETURNING {RULE_COLUMNS}"
+    ))
+    .bind(server_id)
+    .bind(&fields.name)
+    .bind(fields.enabled)
+    .bind(sqlx::types::Json(&fields.trigger))
+    .bind(sqlx::types::Json(&fields.actions))
+    .bind(&fields.channel_ids)
+    .bind(&fields.role_ids)
+    .bind(&fields.exempt_channel_ids)
+    .bind(&fields.exempt_role_ids)
+    .bind(auth.user_id())
+    .fetch_one(&state.pool)
+    .await?;
+
+    invalidate_automod(&state, server_id).await;
+
+    Ok((StatusCode::CREATED, Json(rule)))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/servers/{id}/automod/rules/{rule_id}",
+    params(
+        ("id" = Uuid, Path, description = "Server ID"),
+        ("rule_id" = Uuid, Path, description = "Rule ID"),
+    ),
+    request_body = UpdateAutomodRuleRequest,
+    responses(
+        (status = 200, description = "Updated rule", body = AutomodRule),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Automod"
+)]
+/// PATCH /servers/:id/automod/rules/:rule_id — Partially update a rule.
+///
+/// Only the server owner can update rules. The merged rule is validated as a
+/// whole, so e.g. replacing only the actions still re-checks the alert channel.
+pub async fn update_automod_rule(
+    Path((server_id, rule_id)): Path<(Uuid, Uuid)>,
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(body): Json<UpdateAutomodRuleRequest>,
+) -> AppResult<Json<AutomodRule>> {
+    require_owner(&state, server_id, &auth).await?;
+
+    let existing = sqlx::query_as::<_, AutomodRule>(&format!(
+        "SELECT {RULE_COLUMNS} FROM automod_rules WHERE id = $1 AND server_id = $2"
+    ))
+    .bind(rule_id)
+    .bind(server_id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Automod rule not found".into()))?;
+
+    let mut fields = RuleFields {
+        name: body.name.unwrap_or(existing.name),
+        enabled: body.enabled.unwrap_or(existing.enabled),
+        trigger: body.trigger.unwrap_or(existing.trigger),
+        actions: body.actions.unwrap_or(existing.actions),
+        channel_ids: body.channel_ids.unwrap_or(existing.channel_ids),
+        role_ids: body.role_ids.unwrap_or(existing.role_ids),
+        exempt_channel_ids: body
+            .exempt_channel_ids
+            .unwrap_or(existing.exempt_channel_ids),
+        exempt_role_ids: body.exempt_role_ids.unwrap_or(existing.exempt_role_ids),
+    };
+    validate_rule(&state.pool, server_id, &mut fields).await?;
+
+    let rule = sqlx::query_as::<_, AutomodRule>(&format!(
+        "UPDATE automod_rules SET
+             name = $3, enabled = $4, trigger = $5, actions = $6, channel_ids = $7,
+             role_ids = $8, exempt_channel_ids = $9, exempt_role_ids = $10,
+             updated_at = NOW()
+         WHERE id = $1 AND server_id = $2
+         RETURNING {RULE_COLUMNS}"
+    ))
+    .bind(rule_id)
+    .bind(server_id)
+    .bind(&fields.name)
+    .bind(fields.enabled)
+    .bind(sqlx::types::Json(&fields.trigger))
+    .bind(sqlx::types::Json(&fields.actions))
+    .bind(&fields.channel_ids)
+    .bind(&fields.role_ids)
+    .bind(&fields.exempt_channel_ids)
+    .bind(&fields.exempt_role_ids)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Automod rule not found".into()))?;
+
+    invalidate_automod(&state, server_id).await;
+
+    Ok(Json(rule))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/servers/{id}/automod/rules/{rule_id}",
+    params(
+        ("id" = Uuid, Path, description = "Server ID"),
+        ("rule_id" = Uuid, Path, description = "Rule ID"),
+    ),
+    responses(
+        (status = 204, description = "Rule deleted"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Automod"
+)]
+/// DELETE /servers/:id/automod/rules/:rule_id — Delete a rule.
+///
+/// Only the server owner can delete rules. Log entries produced by the rule
+/// are kept with `rule_id` cleared.
+pub async fn delete_automod_rule(
+    Path((server_id, rule_id)): Path<(Uuid, Uuid)>,
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> AppResult<StatusCode> {
+    require_owner(&state, server_id, &auth).await?;
+
+    let result = sqlx::query("DELETE FROM automod_rules WHERE id = $1 AND server_id = $2")
+        .bind(rule_id)
+        .bind(server_id)
+        .execute(&state.pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Automod rule not found".into()));
+    }
+
+    invalidate_automod(&state, server_id).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
 #[utoipa::path(
     get,
     path = "/servers/{id}/bans",
@@ -469,101 +872,154 @@ pub async fn check_timeout(pool: &sqlx::PgPool, server_id: Uuid, user_id: Uuid)
     Ok(())
 }
 
+/// Who posted the message being checked.
+#[derive(Debug, Clone, Copy)]
+pub enum MessageAuthor {
+    User(Uuid),
+    /// An incoming webhook. Content rules still apply, but there is no
+    /// account to time out, kick or ban, so only the message is blocked.
+    Webhook(Uuid),
+}
+
+impl MessageAuthor {
+    fn user_id(self) -> Option<Uuid> {
+        match self {
+            MessageAuthor::User(id) => Some(id),
+            MessageAuthor::Webhook(_) => None,
+        }
+    }
+
+    fn webhook_id(self) -> Option<Uuid> {
+        match self {
+            MessageAuthor::User(_) => None,
+            MessageAuthor::Webhook(id) => Some(id),
+        }
+    }
+}
+
+/// Matches messages by `author` in the duplicate and spam queries: `$2` is
+/// the author's user id and `$3` the webhook id (exactly one is non-NULL).
+const AUTHOR_FILTER: &str = "(m.author_id = $2
+       OR m.id IN (SELECT message_id FROM incoming_webhook_messages WHERE webhook_id = $3))";
+
 /// Returns Ok(()) if the message should be allowed, or Err(AppError::Forbidden) if blocked.
 /// message_id is None for pre-insert checks, Some(id) for post-insert spam check.
+///
+/// The server's configuration comes from the compiled cache in
+/// [`AppState::automod`]; rules are evaluated in memory.
 pub async fn check_automod(
-    pool: &sqlx::PgPool,
+    state: &AppState,
     server_id: Uuid,
     channel_id: Uuid,
-    user_id: Uuid,
+    author: MessageAuthor,
     username: &str,
     content: &str,
     message_id: Option<Uuid>,
 ) -> AppResult<()> {
-    // 1. Load config — if none exists or disabled, allow
-    let config = sqlx::query_as::<_, crate::models::AutomodConfig>(
-        "SELECT server_id, enabled, spam_enabled, spam_max_messages, spam_window_secs,
-                spam_action, duplicate_enabled, word_filter_enabled, word_filter_action,
-                timeout_minutes, updated_at
-         FROM automod_configs WHERE server_id = $1",
-    )
-    .bind(server_id)
-    .fetch_optional(pool)
-    .await?;
+    let pool = &state.pool;
+    let compiled = state.automod.get_or_load(pool, server_id).await?;
 
-    let config = match config {
-        Some(c) => c,
-        None => return Ok(()),
-    };
+    // 1. Rules (pre-insert only). They carry their own enabled flag and run
+    //    whether or not the built-in checks below are enabled.
+    if message_id.is_none() {
+        let member = match author.user_id() {
+            Some(user_id) if compiled.needs_member_context() => {
+                Some(load_member_context(pool, server_id, user_id).await?)
+            }
+            Some(_) => Some((Vec::new(), None)),
+            None => None,
+        };
+        let ctx = MessageContext {
+            channel_id,
+            content,
+            member: member
+                .as_ref()
+                .map(|(role_ids, account_age)| MemberContext {
+                    role_ids,
+                    account_age: *account_age,
+                }),
+        };
 
-    if !config.enabled {
-        return Ok(());
+        let matches = compiled.evaluate(&ctx);
+        let mut blocked_by = None;
+        for hit in &matches {
+            apply_rule_actions(state, server_id, channel_id, author, username, content, hit).await;
+            if blocked_by.is_none() && hit.rule.blocks() {
+                blocked_by = Some(hit.rule.name.as_str());
+            }
+        }
+        if let Some(name) = blocked_by {
+            return Err(AppError::Forbidden(format!(
+                "Message blocked by automod rule '{name}'"
+            )));
+        }
     }
 
-    // 2. Check active timeout — if user is timed out, block
+    // 2. Built-in checks — if never configured or disabled, allow
+    let config = match &compiled.config {
+        Some(c) if c.enabled => c,
+        _ => return Ok(()),
+    };
+
+    // 3. Check active timeout — if user is timed out, block
     let now = chrono::Utc::now();
-    let timeout_active = sqlx::query(
-        "SELECT expires_at FROM automod_timeouts WHERE user_id = $1 AND server_id = $2 AND expires_at > $3",
-    )
-    .bind(user_id)
-    .bind(server_id)
-    .bind(now)
-    .fetch_optional(pool)
-    .await?
-    .is_some();
+    if let Some(user_id) = author.user_id() {
+        let timeout_active = sqlx::query(
+            "SELECT expires_at FROM automod_timeouts WHERE user_id = $1 AND server_id = $2 AND expires_at > $3",
+        )
+        .bind(user_id)
+        .bind(server_id)
+        .bind(now)
+        .fetch_optional(pool)
+        .await?
+        .is_some();
 
-    if timeout_active {
-        return Err(AppError::Forbidden("User is timed out".into()));
+        if timeout_active {
+            return Err(AppError::Forbidden("User is timed out".into()));
+        }
     }
 
-    // 3. Word filter (pre-insert only: message_id.is_none())
+    // 4. Word filter (pre-insert only: message_id.is_none())
     if message_id.is_none() && config.word_filter_enabled {
-        let content_lower = content.to_lowercase();
-        let words = sqlx::query("SELECT word FROM automod_word_filters WHERE server_id = $1")
-            .bind(server_id)
-            .fetch_all(pool)
-            .await?;
-
-        for row in &words {
-            let Ok(word): Result<String, _> = row.try_get("word") else {
-                continue;
-            };
-            if content_lower.contains(&word) {
-                log_automod_action(
-                    pool,
-                    server_id,
-                    channel_id,
-                    user_id,
-                    username,
-                    "word_filter",
-                    &config.word_filter_action,
-                    Some(&word),
-                    Some(content),
-                )
-                .await;
+        if let Some(word) = compiled.match_word_filter(content) {
+            log_automod_action(
+                pool,
+                server_id,
+                channel_id,
+                author.user_id(),
+                username,
+                "word_filter",
+                &config.word_filter_action,
+                Some(&word),
+                Some(content),
+                None,
+            )
+            .await;
+            if let Some(user_id) = author.user_id() {
                 apply_action(
-                    pool,
+                    state,
                     server_id,
                     user_id,
                     &config.word_filter_action,
                     config.timeout_minutes,
                 )
                 .await?;
-                return Err(AppError::Forbidden("Message blocked by word filter".into()));
             }
+            return Err(AppError::Forbidden("Message blocked by word filter".into()));
         }
     }
 
-    // 4. Duplicate detection (pre-insert only)
+    // 5. Duplicate detection (pre-insert only)
     if message_id.is_none() && config.duplicate_enabled {
         let cutoff = now - chrono::Duration::seconds(30);
-        let duplicate = sqlx::query(
-            r#"SELECT id FROM messages
-               WHERE channel_id = $1 AND author_id = $2 AND content = $3
-                 AND created_at > $4 AND deleted = FALSE"#,
-        )
+        let duplicate = sqlx::query(&format!(
+            "SELECT m.id FROM messages m
+             WHERE m.channel_id = $1 AND {AUTHOR_FILTER}
+               AND m.content = $4 AND m.created_at > $5 AND m.deleted = FALSE"
+        ))
         .bind(channel_id)
-        .bind(user_id)
+        .bind(author.user_id())
+        .bind(author.webhook_id())
         .bind(content)
         .bind(cutoff)
         .fetch_optional(pool)
@@ -575,36 +1031,38 @@ pub async fn check_automod(
                 pool,
                 server_id,
                 channel_id,
-                user_id,
+                author.user_id(),
                 username,
                 "duplicate",
                 "delete",
                 None,
                 Some(content),
+                None,
             )
             .await;
             return Err(AppError::Forbidden("Duplicate message blocked".into()));
         }
     }
 
-    // 5. Spam detection (post-insert only: message_id.is_some())
+    // 6. Spam detection (post-insert only: message_id.is_some())
     if let Some(msg_id) = message_id {
         if config.spam_enabled {
             let window_start = now - chrono::Duration::seconds(config.spam_window_secs as i64);
-            let count: i64 = sqlx::query_scalar(
-                r#"SELECT COUNT(*) FROM messages
-                   WHERE channel_id = $1 AND author_id = $2
-                     AND created_at > $3 AND deleted = FALSE"#,
-            )
+            let count: i64 = sqlx::query_scalar(&format!(
+                "SELECT COUNT(*) FROM messages m
+                 WHERE m.channel_id = $1 AND {AUTHOR_FILTER}
+                   AND m.created_at > $4 AND m.deleted = FALSE"
+            ))
             .bind(channel_id)
-            .bind(user_id)
+            .bind(author.user_id())
+            .bind(author.webhook_id())
             .bind(window_start)
             .fetch_one(pool)
             .await?;
 
             if count > config.spam_max_messages as i64 {
                 // Soft-delete the message that just triggered it
-                sqlx::query("UPDATE messages SET deleted = TRUE WHERE id = $1")
+                sqlx::query("UPDATE messages SET deleted = TRUE, deleted_at = NOW() WHERE id = $1")
                     .bind(msg_id)
                     .execute(pool)
                     .await?;
@@ -613,22 +1071,25 @@ pub async fn check_automod(
                     pool,
                     server_id,
                     channel_id,
-                    user_id,
+                    author.user_id(),
                     username,
                     "spam",
                     &config.spam_action,
                     None,
                     Some(content),
+                    None,
                 )
                 .await;
-                let _ = apply_action(
-                    pool,
-                    server_id,
-                    user_id,
-                    &config.spam_action,
-                    config.timeout_minutes,
-                )
-                .await;
+                if let Some(user_id) = author.user_id() {
+                    let _ = apply_action(
+                        state,
+                        server_id,
+                        user_id,
+                        &config.spam_action,
+                        config.timeout_minutes,
+                    )
+                    .await;
+                }
             }
         }
     }
@@ -636,13 +1097,156 @@ pub async fn check_automod(
     Ok(())
 }
 
-async fn apply_action(
+/// The author's role ids in `server_id` and the age of their account.
+async fn load_member_context(
     pool: &sqlx::PgPool,
     server_id: Uuid,
     user_id: Uuid,
+) -> AppResult<(Vec<Uuid>, Option<chrono::Duration>)> {
+    let (role_ids, created_at): (Vec<Uuid>, chrono::DateTime<chrono::Utc>) = sqlx::query_as(
+        "SELECT COALESCE(
+                    (SELECT ARRAY_AGG(role_id) FROM member_roles
+                     WHERE user_id = $1 AND server_id = $2),
+                    '{}'),
+                u.created_at
+         FROM users u WHERE u.id = $1",
+    )
+    .bind(user_id)
+    .bind(server_id)
+    .fetch_one(pool)
+    .await?;
+    Ok((role_ids, Some(chrono::Utc::now() - created_at)))
+}
+
+/// Run the non-blocking actions of a matched rule and log the match.
+///
+/// Failures are logged and swallowed: a broken alert channel must not let a
+/// message through or block one that no rule blocks.
+async fn apply_rule_actions(
+    state: &AppState,
+    server_id: Uuid,
+    channel_id: Uuid,
+    author: MessageAuthor,
+    username: &str,
+    content: &str,
+    hit: &RuleMatch<'_>,
+) {
+    let mut taken = Vec::with_capacity(hit.rule.actions.len());
+    for action in &hit.rule.actions {
+        match action {
+            AutomodRuleAction::Delete => taken.push("delete"),
+            AutomodRuleAction::Timeout { duration_minutes } => {
+                // Webhooks have no account to time out.
+                let Some(user_id) = author.user_id() else {
+                    continue;
+                };
+                if let Err(e) =
+                    apply_action(state, server_id, user_id, "timeout", *duration_minutes).await
+                {
+                    tracing::warn!(error = ?e, rule_id = %hit.rule.id, "Automod timeout failed");
+                    continue;
+                }
+                taken.push("timeout");
+            }
+            AutomodRuleAction::Alert {
+                channel_id: alert_channel_id,
+            } => {
+                let embed = alert_embed(channel_id, author, username, content, hit);
+                match post_alert(state, server_id, *alert_channel_id, embed).await {
+                    Ok(()) => taken.push("alert"),
+                    Err(e) => {
+                        tracing::warn!(error = ?e, rule_id = %hit.rule.id, "Automod alert failed")
+                    }
+                }
+            }
+        }
+    }
+
+    log_automod_action(
+        &state.pool,
+        server_id,
+        channel_id,
+        author.user_id(),
+        username,
+        hit.rule.trigger_type,
+        &taken.join(","),
+        hit.matched.as_deref(),
+        Some(content),
+        Some(hit.rule.id),
+    )
+    .await;
+}
+
+fn alert_embed(
+    channel_id: Uuid,
+    author: MessageAuthor,
+    username: &str,
+    content: &str,
+    hit: &RuleMatch<'_>,
+) -> MessageEmbed {
+    let field = |name: &str, value: String| EmbedField {
+        name: name.into(),
+        value,
+        inline: true,
+    };
+    let author_label = match author {
+        MessageAuthor::User(id) => format!("{username} ({id})"),
+        MessageAuthor::Webhook(_) => format!("{username} (webhook)"),
+    };
+    let mut fields = vec![
+        field("Author", author_label),
+        field("Channel", format!("<#{channel_id}>")),
+        field("Trigger", hit.rule.trigger_type.into()),
+    ];
+    if let Some(matched) = &hit.matched {
+        fields.push(field("Matched", matched.clone()));
+    }
+    MessageEmbed {
+        title: Some(format!("Automod rule triggered: {}", hit.rule.name)),
+        description: Some(content.chars().take(1000).collect()),
+        url: None,
+        color: Some(0xED4245),
+        fields,
+        footer: None,
+        image_url: None,
+        thumbnail_url: None,
+        timestamp: Some(chrono::Utc::now()),
+    }
+}
+
+/// Post an alert into `channel_id` as "AutoMod" and broadcast it.
+async fn post_alert(
+    state: &AppState,
+    server_id: Uuid,
+    channel_id: Uuid,
+    embed: MessageEmbed,
+) -> AppResult<()> {
+    let dto = insert_webhook_message(
+        &state.pool,
+        server_id,
+        channel_id,
+        WebhookIdentity {
+            webhook_id: None,
+            username: "AutoMod",
+            avatar_url: None,
+        },
+        "",
+        &[embed],
+    )
+    .await?;
+    let payload = serde_json::to_value(&dto).map_err(|_| AppError::Internal)?;
+    broadcast_to_channel(state, server_id, channel_id, EVENT_MESSAGE_CREATE, payload).await;
+    Ok(())
+}
+
+async fn apply_action(
+    state: &AppState,
+    server_id: Uuid,
+    user_id: Uuid,
     action: &str,
     timeout_minutes: i32,
 ) -> AppResult<()> {
+    let pool = &state.pool;
     match action {
         "timeout" => {
             let expires_at = chrono::Utc::now() + chrono::Duration::minutes(timeout_minutes as i64);
@@ -663,6 +1267,7 @@ async fn apply_action(
                 .bind(server_id)
                 .execute(pool)
                 .await?;
+            invalidate_channel_viewers(state, server_id).await;
         }
         "ban" => {
             sqlx::query(
@@ -680,6 +1285,7 @@ async fn apply_action(
                 .bind(server_id)
                 .execute(pool)
                 .await?;
+            invalidate_channel_viewers(state, server_id).await;
         }
         // "delete" and unknown — no server-level action (message handling is caller's responsibility)
         _ => {}
@@ -692,17 +1298,18 @@ async fn log_automod_action(
     pool: &sqlx::PgPool,
     server_id: Uuid,
     channel_id: Uuid,
-    user_id: Uuid,
+    user_id: Option<Uuid>,
     username: &str,
     rule_type: &str,
     action_taken: &str,
     matched_term: Option<&str>,
     message_content: Option<&str>,
+    rule_id: Option<Uuid>,
 ) {
     // Fire-and-forget: ignore errors so automod logging never blocks message delivery
     let _ = sqlx::query(
-        r#"INSERT INTO automod_logs (server_id, channel_id, user_id, username, rule_type, action_taken, matched_term, message_content)
-           VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"#,
+        r#"INSERT INTO automod_logs (server_id, channel_id, user_id, username, rule_type, action_taken, matched_term, message_content, rule_id)
+           VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)"#,
     )
     .bind(server_id)
     .bind(channel_id)
@@ -712,6 +1319,7 @@ async fn log_automod_action(
     .bind(action_taken)
     .bind(matched_term)
     .bind(message_content)
+    .bind(rule_id)
     .execute(pool)
     .await;
 }
@@ -22,6 +22,7 @@ use crate::{
     websocket::{
         broadcast_to_server,
         events::{EVENT_CHANNEL_OVERRIDE_DELETE, EVENT_CHANNEL_OVERRIDE_UPDATE},
+        invalidate_channel_viewers,
     },
 };
 
@@ -148,6 +149,8 @@ pub async fn set_override(
     .fetch_one(&state.pool)
     .await?;
 
+    invalidate_channel_viewers(&state, channel.server_id).await;
+
     // Broadcast + audit.
     match serde_json::to_value(&ov) {
         Ok(payload) => {
@@ -231,6 +234,8 @@ pub async fn delete_override(
         return Err(AppError::NotFound("Override not found".into()));
     }
 
+    invalidate_channel_viewers(&state, channel.server_id).await;
+
     let payload = json!({
         "channel_id": channel_id,
         "override_id": override_id,
@@ -1,21 +1,22 @@
 use axum::{
-    body::Body,
-    extract::{Multipart, Path, State},
-    http::{header, StatusCode},
+    extract::{Multipart, Path, Query, State},
+    http::{HeaderMap, StatusCode},
     response::Response,
     Json,
 };
-use std::path::PathBuf;
-use tokio::fs::File;
-use tokio_util::io::ReaderStream;
+use bytes::Bytes;
+use serde::Deserialize;
 use uuid::Uuid;
 
+use super::files::serve_stored_file;
 use super::shared::{fetch_server, require_manage_emojis, require_member};
 use crate::{
     auth::AuthUser,
     error::{AppError, AppResult},
+    images,
     models::{CustomEmoji, CustomEmojiDto},
     state::AppState,
+    storage::{ResponseHeaders, Storage},
     websocket::{
         broadcast_to_server,
         events::{EVENT_CUSTOM_EMOJI_CREATE, EVENT_CUSTOM_EMOJI_DELETE},
@@ -35,6 +36,9 @@ const MAX_EMOJI_IMAGE_SIZE: usize = 262_144;
 /// Allowed MIME types for emoji images.
 const ALLOWED_EMOJI_MIME_TYPES: &[&str] = &["image/jpeg", "image/png", "image/gif", "image/webp"];
 
+/// Thumbnail sizes made for emoji images, in pixels along the longer side.
+pub const EMOJI_THUMBNAIL_SIZES: &[u32] = &[32, 64, 128];
+
 // ============================================================================
 // Handlers
 // ============================================================================
@@ -62,7 +66,7 @@ pub async fn list_custom_emojis(
     require_member(&state.pool, server_id, auth.user_id()).await?;
 
     let rows = sqlx::query_as::<_, CustomEmoji>(
-        "SELECT id, server_id, created_by, name, filename, content_type, file_size, created_at
+        "SELECT id, server_id, created_by, name, filename, content_type, file_size, width, height, created_at
          FROM custom_emojis
          WHERE server_id = $1
          ORDER BY created_at ASC",
@@ -96,6 +100,9 @@ pub async fn list_custom_emojis(
 /// - `name`  — text field: emoji name (1–32 chars, `[a-z0-9_-]` only)
 /// - `image` — file field: image bytes (JPEG / PNG / GIF / WebP, ≤ 256 KB)
 ///
+/// The image is decoded to record its dimensions, stripped of metadata, and
+/// stored with WebP thumbnails (see [`crate::images`]).
+///
 /// On success returns `201 Created` with the created emoji DTO.
 pub async fn upload_custom_emoji(
     State(state): State<AppState>,
@@ -195,6 +202,10 @@ pub async fn upload_custom_emoji(
         }
     };
 
+    let image = images::process(data, &mime_type, EMOJI_THUMBNAIL_SIZES)
+        .await
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
     let ext = match mime_type.as_str() {
         "image/jpeg" => "jpg",
         "image/png" => "png",
@@ -203,59 +214,41 @@ pub async fn upload_custom_emoji(
         _ => "bin",
     };
 
-    // ── Write file to disk ────────────────────────────────────────────────────
+    // ── Store the image and its thumbnails ────────────────────────────────────
 
     let emoji_id = Uuid::new_v4();
     let stored_filename = format!("{}.{}", Uuid::new_v4().simple(), ext);
-
-    let dir = state
-        .upload_dir
-        .join("custom_emojis")
-        .join(emoji_id.to_string());
-
-    tokio::fs::create_dir_all(&dir).await.map_err(|e| {
-        tracing::error!(error = ?e, path = ?dir, "Failed to create emoji upload directory");
-        AppError::Internal
-    })?;
-
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let perms = std::fs::Permissions::from_mode(0o755);
-        if let Err(e) = tokio::fs::set_permissions(&dir, perms).await {
-            tracing::warn!(error = ?e, path = ?dir, "Failed to set emoji directory permissions");
-        }
-    }
-
-    let file_path = dir.join(&stored_filename);
-    if let Err(e) = tokio::fs::write(&file_path, &data).await {
-        tracing::error!(error = ?e, path = ?file_path, "Failed to write emoji image file");
-        if let Err(e) = tokio::fs::remove_file(&file_path).await {
-            tracing::warn!(error = ?e, "Failed to cleanup emoji file");
-        }
-        if let Err(e) = tokio::fs::remove_dir(&dir).await {
-            tracing::warn!(error = ?e, "Failed to cleanup emoji dir");
-        }
-        return Err(AppError::Internal);
-    }
-
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let perms = std::fs::Permissions::from_mode(0o644);
-        if let Err(e) = tokio::fs::set_permissions(&file_path, perms).await {
-            tracing::warn!(error = ?e, path = ?file_path, "Failed to set emoji file permissions");
+    let prefix = emoji_prefix(emoji_id);
+
+    let files = std::iter::once((
+        stored_filename.clone(),
+        image.data.clone(),
+        mime_type.as_str(),
+    ))
+    .chain(image.thumbnails.iter().map(|thumbnail| {
+        (
+            images::thumbnail_name(&stored_filename, thumbnail.size),
+            Bytes::copy_from_slice(&thumbnail.data),
+            "image/webp",
+        )
+    }));
+    for (name, contents, content_type) in files {
+        let key = format!("{prefix}/{name}");
+        if let Err(e) = state.storage.put(&key, contents, content_type).await {
+            tracing::error!(error = ?e, key, "Failed to store emoji image file");
+            cleanup_emoji(state.storage.as_ref(), &prefix).await;
+            return Err(AppError::Internal);
         }
     }
 
     // ── Insert into the database ──────────────────────────────────────────────
 
-    let file_size = data.len() as i64;
+    let file_size = image.data.len() as i64;
 
     let row = match sqlx::query_as::<_, CustomEmoji>(
-        "INSERT INTO custom_emojis (id, server_id, created_by, name, filename, content_type, file_size)
-         VALUES ($1, $2, $3, $4, $5, $6, $7)
-         RETURNING id, server_id, created_by, name, filename, content_type, file_size, created_at",
+        "INSERT INTO custom_emojis (id, server_id, created_by, name, filename, content_type, file_size, width, height)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+         RETURNING id, server_id, created_by, name, filename, content_type, file_size, width, height, created_at",
     )
     .bind(emoji_id)
     .bind(server_id)
@@ -264,11 +257,14 @@ pub async fn upload_custom_emoji(
     .bind(&stored_filename)
     .bind(&mime_type)
     .bind(file_size)
+    .bind(image.width as i32)
+    .bind(image.height as i32)
     .fetch_one(&state.pool)
     .await
     {
         Ok(row) => row,
         Err(e) => {
+            cleanup_emoji(state.storage.as_ref(), &prefix).await;
             // On unique constraint violation for (server_id, name) report a friendly 400.
             if let sqlx::Error::Database(ref db_err) = e {
                 let constraint = db_err.constraint().unwrap_or("");
@@ -278,8 +274,6 @@ pub async fn upload_custom_emoji(
                     )));
                 }
             }
-            // For any other DB error clean up the written file first.
-            cleanup_files(&[file_path]).await;
             return Err(AppError::from(e));
         }
     };
@@ -327,7 +321,7 @@ pub async fn delete_custom_emoji(
 
     let row = sqlx::query_as::<_, CustomEmoji>(
         "DELETE FROM custom_emojis WHERE id = $1 AND server_id = $2
-         RETURNING id, server_id, created_by, name, filename, content_type, file_size, created_at",
+         RETURNING id, server_id, created_by, name, filename, content_type, file_size, width, height, created_at",
     )
     .bind(emoji_id)
     .bind(server_id)
@@ -335,18 +329,9 @@ pub async fn delete_custom_emoji(
     .await?
     .ok_or_else(|| AppError::NotFound("Emoji not found".into()))?;
 
-    // Best-effort file cleanup — log a warning on failure but do not fail the response.
-    let dir = state
-        .upload_dir
-        .join("custom_emojis")
-        .join(row.id.to_string());
-    let file_path = dir.join(&row.filename);
-    if let Err(e) = tokio::fs::remove_file(&file_path).await {
-        tracing::warn!(error = ?e, path = ?file_path, "Failed to delete custom emoji file");
-    }
-    if let Err(e) = tokio::fs::remove_dir(&dir).await {
-        tracing::warn!(error = ?e, path = ?dir, "Failed to remove custom emoji dir");
-    }
+    // Best-effort file cleanup (the image and its thumbnails) — log a warning
+    // on failure but do not fail the response.
+    cleanup_emoji(state.storage.as_ref(), &emoji_prefix(row.id)).await;
 
     broadcast_to_server(
         &state,
@@ -367,9 +352,13 @@ pub async fn delete_custom_emoji(
     path = "/emojis/{emoji_id}",
     params(
         ("emoji_id" = Uuid, Path, description = "Emoji ID"),
+        EmojiImageQuery,
     ),
     responses(
-        (status = 200, description = "Emoji image content"),
+        (status = 200, description = "Emoji image content, or a WebP thumbnail when `size` is given"),
+        (status = 304, description = "Unchanged since the `If-None-Match` / `If-Modified-Since` copy"),
+        (status = 307, description = "Redirect to a presigned object storage URL (STORAGE_BACKEND=s3)"),
+        (status = 400, description = "Unsupported thumbnail size"),
     ),
     tag = "CustomEmojis"
 )]
@@ -377,13 +366,32 @@ pub async fn delete_custom_emoji(
 ///
 /// Responds with the raw image bytes and appropriate `Content-Type`.
 /// Sets `Cache-Control: public, max-age=86400` (24 h) since emoji images are
-/// immutable once uploaded (delete and re-upload creates a new ID).
+/// immutable once uploaded (delete and re-upload creates a new ID). After
+/// that the `ETag` lets clients revalidate with a `304` instead of
+/// downloading the image again.
+///
+/// With `?size=`, emojis larger than `size` are served as their WebP
+/// thumbnail; smaller ones, and emojis uploaded before thumbnails existed,
+/// as the original image.
+///
+/// With object storage the response is a `307` redirect to a short-lived
+/// presigned URL, which carries the same headers.
 pub async fn serve_custom_emoji_image(
     State(state): State<AppState>,
     Path(emoji_id): Path<Uuid>,
+    Query(query): Query<EmojiImageQuery>,
+    request: HeaderMap,
 ) -> AppResult<Response> {
+    if let Some(size) = query.size {
+        if !EMOJI_THUMBNAIL_SIZES.contains(&size) {
+            return Err(AppError::Validation(format!(
+                "Thumbnail size must be one of {EMOJI_THUMBNAIL_SIZES:?}"
+            )));
+        }
+    }
+
     let row = sqlx::query_as::<_, CustomEmoji>(
-        "SELECT id, server_id, created_by, name, filename, content_type, file_size, created_at
+        "SELECT id, server_id, created_by, name, filename, content_type, file_size, width, height, created_at
          FROM custom_emojis WHERE id = $1",
     )
     .bind(emoji_id)
@@ -391,39 +399,53 @@ pub async fn serve_custom_emoji_image(
     .await?
     .ok_or_else(|| AppError::NotFound("Emoji not found".into()))?;
 
-    let file_path = state
-        .upload_dir
-        .join("custom_emojis")
-        .join(row.id.to_string())
-        .join(&row.filename);
-
-    let file = File::open(&file_path).await.map_err(|e| {
-        tracing::error!(error = ?e, path = ?file_path, "Failed to open emoji image file");
-        AppError::Internal
-    })?;
-
-    let stream = ReaderStream::new(file);
-    let body = Body::from_stream(stream);
+    let thumbnail_size = match (query.size, row.width, row.height) {
+        (Some(size), Some(width), Some(height))
+            if images::has_thumbnail(width as u32, height as u32, size) =>
+        {
+            Some(size)
+        }
+        _ => None,
+    };
 
-    let response = Response::builder()
-        .status(StatusCode::OK)
-        .header(header::CONTENT_TYPE, row.content_type)
-        .header(header::CACHE_CONTROL, "public, max-age=86400")
-        .body(body)
-        .map_err(|_| AppError::Internal)?;
+    let prefix = emoji_prefix(row.id);
+    let (key, content_type) = match thumbnail_size {
+        Some(size) => (
+            format!("{prefix}/{}", images::thumbnail_name(&row.filename, size)),
+            "image/webp".to_string(),
+        ),
+        None => (format!("{prefix}/{}", row.filename), row.content_type),
+    };
 
-    Ok(response)
+    let headers = ResponseHeaders {
+        content_type: Some(content_type),
+        content_disposition: None,
+        cache_control: Some("public, max-age=86400".to_string()),
+    };
+    serve_stored_file(state.storage.as_ref(), &key, headers, &request).await
 }
 
 // ============================================================================
 // Private helpers
 // ============================================================================
 
-/// Delete all paths in `paths`, logging any errors but not propagating them.
-async fn cleanup_files(paths: &[PathBuf]) {
-    for p in paths {
-        if let Err(e) = tokio::fs::remove_file(p).await {
-            tracing::warn!(error = ?e, path = ?p, "Failed to clean up orphaned emoji file");
-        }
+/// Query parameters for the emoji image route.
+#[derive(Deserialize, utoipa::IntoParams)]
+pub struct EmojiImageQuery {
+    /// Serve the emoji's WebP thumbnail that fits within `size`×`size`
+    /// pixels: 32, 64 or 128.
+    pub size: Option<u32>,
+}
+
+/// Storage prefix holding an emoji's image and its thumbnails.
+fn emoji_prefix(emoji_id: Uuid) -> String {
+    format!("custom_emojis/{emoji_id}")
+}
+
+/// Delete an emoji's image and thumbnails, logging any errors but not
+/// propagating them.
+async fn cleanup_emoji(storage: &dyn Storage, prefix: &str) {
+    if let Err(e) = storage.delete_prefix(prefix).await {
+        tracing::warn!(error = ?e, prefix, "Failed to clean up emoji files");
     }
 }
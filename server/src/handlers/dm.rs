@@ -5,6 +5,8 @@ use axum::{
 };
 use chrono::{DateTime, Utc};
 use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
 use utoipa::ToSchema;
 use uuid::Uuid;
 use validator::Validate;
@@ -14,10 +16,22 @@ use crate::{
     auth::AuthUser,
     error::{AppError, AppResult},
     models::{DirectMessage, DirectMessageChannelDto, UserDto},
+    push::{self, PushJob},
     state::AppState,
-    websocket::{broadcast_to_user_list, EVENT_DM_CHANNEL_CREATE, EVENT_DM_MESSAGE_CREATE},
+    websocket::{
+        broadcast_to_user_list,
+        events::{
+            EVENT_DM_CHANNEL_DELETE, EVENT_DM_CHANNEL_RECIPIENT_ADD,
+            EVENT_DM_CHANNEL_RECIPIENT_REMOVE, EVENT_DM_CHANNEL_UPDATE,
+        },
+        EVENT_DM_CHANNEL_CREATE, EVENT_DM_MESSAGE_CREATE,
+    },
 };
 
+/// Participants a group DM can hold, including the owner.
+pub const MAX_GROUP_DM_MEMBERS: usize = 10;
+const MAX_GROUP_NAME_CHARS: usize = 100;
+
 // ============================================================================
 // Input validation
 // ============================================================================
@@ -28,6 +42,19 @@ pub struct OpenDmRequest {
     pub user_id: Uuid,
 }
 
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateGroupDmRequest {
+    /// Other participants; the caller is added as owner.
+    pub user_ids: Vec<Uuid>,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateGroupDmRequest {
+    /// New group name; an empty string clears it.
+    pub name: String,
+}
+
 #[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct SendDmRequest {
     #[validate(length(
@@ -68,71 +95,314 @@ async fn require_dm_member(pool: &sqlx::PgPool, channel_id: Uuid, user_id: Uuid)
     }
 }
 
-/// Build a `DirectMessageChannelDto` for a given channel + requesting user.
-async fn build_channel_dto(
+/// A participant's public profile (no email or admin flag).
+#[derive(sqlx::FromRow)]
+struct DmUserRow {
+    id: Uuid,
+    username: String,
+    avatar_url: Option<String>,
+    bio: Option<String>,
+    pronouns: Option<String>,
+    status: String,
+    custom_status: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl From<DmUserRow> for UserDto {
+    fn from(r: DmUserRow) -> Self {
+        UserDto {
+            id: r.id,
+            username: r.username,
+            email: None, // Strip email from other users' profiles
+            email_verified: false,
+            avatar_url: r.avatar_url,
+            bio: r.bio,
+            pronouns: r.pronouns,
+
+            status: r.status,
+            custom_status: r.custom_status,
+            activity: None,
+            created_at: r.created_at,
+            is_admin: false, // Strip is_admin from other users' profiles
+        }
+    }
+}
+
+const DM_USER_COLUMNS: &str =
+    "u.id, u.username, u.avatar_url, u.bio, u.pronouns, u.status, u.custom_status, u.created_at";
+
+/// Fetch the DM channels `user_id` belongs to (or just `only`, if given),
+/// most recently active first, each with its other participants.
+///
+/// Shared by the DM endpoints and the gateway READY payload.
+pub async fn fetch_dm_channel_dtos(
     pool: &sqlx::PgPool,
-    channel_id: Uuid,
-    requesting_user_id: Uuid,
-) -> AppResult<DirectMessageChannelDto> {
+    user_id: Uuid,
+    only: Option<Uuid>,
+) -> AppResult<Vec<DirectMessageChannelDto>> {
     #[derive(sqlx::FromRow)]
-    struct Row {
-        channel_created_at: DateTime<Utc>,
-        recipient_id: Uuid,
-        recipient_username: String,
-        recipient_avatar_url: Option<String>,
-        recipient_bio: Option<String>,
-        recipient_pronouns: Option<String>,
-        recipient_status: String,
-        recipient_custom_status: Option<String>,
-        recipient_created_at: DateTime<Utc>,
+    struct ChannelRow {
+        id: Uuid,
+        is_group: bool,
+        name: Option<String>,
+        owner_id: Option<Uuid>,
+        created_at: DateTime<Utc>,
         last_message_at: Option<DateTime<Utc>>,
     }
 
-    let row = sqlx::query_as::<_, Row>(
+    #[derive(sqlx::FromRow)]
+    struct MemberRow {
+        channel_id: Uuid,
+        #[sqlx(flatten)]
+        user: DmUserRow,
+    }
+
+    let channels = sqlx::query_as::<_, ChannelRow>(
         "SELECT
-             dmc.created_at        AS channel_created_at,
-             u.id                  AS recipient_id,
-             u.username            AS recipient_username,
-             u.avatar_url          AS recipient_avatar_url,
-             u.bio                 AS recipient_bio,
-             u.pronouns            AS recipient_pronouns,
-             u.status              AS recipient_status,
-             u.custom_status       AS recipient_custom_status,
-             u.created_at          AS recipient_created_at,
+             dmc.id, dmc.is_group, dmc.name, dmc.owner_id, dmc.created_at,
              (SELECT MAX(dm.created_at)
               FROM direct_messages dm
               WHERE dm.channel_id = dmc.id AND dm.deleted = FALSE
              ) AS last_message_at
          FROM direct_message_channels dmc
-         JOIN direct_message_members dmm ON dmm.channel_id = dmc.id AND dmm.user_id != $2
+         JOIN direct_message_members me ON me.channel_id = dmc.id AND me.user_id = $1
+         WHERE $2::uuid IS NULL OR dmc.id = $2
+         ORDER BY last_message_at DESC NULLS LAST, dmc.created_at DESC",
+    )
+    .bind(user_id)
+    .bind(only)
+    .fetch_all(pool)
+    .await?;
+
+    let ids: Vec<Uuid> = channels.iter().map(|c| c.id).collect();
+    let members = sqlx::query_as::<_, MemberRow>(&format!(
+        "SELECT dmm.channel_id, {DM_USER_COLUMNS}
+         FROM direct_message_members dmm
          JOIN users u ON u.id = dmm.user_id
-         WHERE dmc.id = $1",
+         WHERE dmm.channel_id = ANY($1) AND dmm.user_id != $2
+         ORDER BY dmm.joined_at, u.id"
+    ))
+    .bind(&ids as &[Uuid])
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut recipients: HashMap<Uuid, Vec<UserDto>> = HashMap::new();
+    for m in members {
+        recipients
+            .entry(m.channel_id)
+            .or_default()
+            .push(m.user.into());
+    }
+
+    Ok(channels
+        .into_iter()
+        .filter_map(|c| {
+            let recipients = recipients.remove(&c.id).unwrap_or_default();
+            let recipient = if c.is_group {
+                None
+            } else {
+                // A 1:1 DM whose other participant deleted their account has
+                // nobody left to talk to; leave it out.
+                Some(recipients.first()?.clone())
+            };
+            Some(DirectMessageChannelDto {
+                id: c.id,
+                recipient,
+                recipients,
+                is_group: c.is_group,
+                name: c.name,
+                owner_id: c.owner_id,
+                created_at: c.created_at,
+                last_message_at: c.last_message_at,
+            })
+        })
+        .collect())
+}
+
+/// Build a `DirectMessageChannelDto` for a given channel + requesting user.
+async fn build_channel_dto(
+    pool: &sqlx::PgPool,
+    channel_id: Uuid,
+    requesting_user_id: Uuid,
+) -> AppResult<DirectMessageChannelDto> {
+    fetch_dm_channel_dtos(pool, requesting_user_id, Some(channel_id))
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::NotFound("DM channel not found".into()))
+}
+
+/// Current participants of a DM channel.
+async fn fetch_member_ids(pool: &sqlx::PgPool, channel_id: Uuid) -> AppResult<Vec<Uuid>> {
+    Ok(sqlx::query_scalar(
+        "SELECT user_id FROM direct_message_members WHERE channel_id = $1
+         ORDER BY joined_at, user_id",
+    )
+    .bind(channel_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+#[derive(sqlx::FromRow)]
+struct GroupRow {
+    id: Uuid,
+    is_group: bool,
+    name: Option<String>,
+    owner_id: Option<Uuid>,
+}
+
+/// Fetch a group DM the caller belongs to. 404 for non-members, 400 for 1:1
+/// channels.
+async fn fetch_group(pool: &sqlx::PgPool, channel_id: Uuid, user_id: Uuid) -> AppResult<GroupRow> {
+    require_dm_member(pool, channel_id, user_id).await?;
+    let group = sqlx::query_as::<_, GroupRow>(
+        "SELECT id, is_group, name, owner_id FROM direct_message_channels WHERE id = $1",
     )
     .bind(channel_id)
-    .bind(requesting_user_id)
     .fetch_optional(pool)
     .await?
     .ok_or_else(|| AppError::NotFound("DM channel not found".into()))?;
+    if !group.is_group {
+        return Err(AppError::Validation(
+            "This action is only available in group DMs".into(),
+        ));
+    }
+    Ok(group)
+}
 
-    Ok(DirectMessageChannelDto {
-        id: channel_id,
-        recipient: UserDto {
-            id: row.recipient_id,
-            username: row.recipient_username,
-            email: None, // Strip email from other users' profiles
-            avatar_url: row.recipient_avatar_url,
-            bio: row.recipient_bio,
-            pronouns: row.recipient_pronouns,
+/// Normalise a group name: trimmed, empty means no name.
+fn normalize_group_name(name: Option<&str>) -> AppResult<Option<String>> {
+    let Some(name) = name.map(str::trim).filter(|n| !n.is_empty()) else {
+        return Ok(None);
+    };
+    if name.chars().count() > MAX_GROUP_NAME_CHARS {
+        return Err(AppError::Validation(format!(
+            "Group name must be at most {MAX_GROUP_NAME_CHARS} characters"
+        )));
+    }
+    Ok(Some(name.to_owned()))
+}
 
-            status: row.recipient_status,
-            custom_status: row.recipient_custom_status,
-            activity: None,
-            created_at: row.recipient_created_at,
-            is_admin: false, // Strip is_admin from other users' profiles
-        },
-        created_at: row.channel_created_at,
-        last_message_at: row.last_message_at,
-    })
+async fn broadcast_json<T: serde::Serialize>(
+    state: &AppState,
+    user_ids: &[Uuid],
+    event_type: &str,
+    data: &T,
+) {
+    match serde_json::to_value(data) {
+        Ok(payload) => broadcast_to_user_list(state, user_ids, event_type, payload).await,
+        Err(e) => {
+            tracing::error!(
+                event_type,
+                error = ?e,
+                "Failed to serialize DM event for broadcast; this is a programming error"
+            );
+        }
+    }
+}
+
+/// Send each listed member their own view of the channel (recipients exclude
+/// the viewer, so the payload differs per member).
+async fn broadcast_channel_create(state: &AppState, channel_id: Uuid, user_ids: &[Uuid]) {
+    for &user_id in user_ids {
+        match build_channel_dto(&state.pool, channel_id, user_id).await {
+            Ok(dto) => {
+                broadcast_json(state, &[user_id], EVENT_DM_CHANNEL_CREATE, &dto).await;
+            }
+            Err(e) => {
+                tracing::warn!(
+                    channel_id = %channel_id,
+                    user_id = %user_id,
+                    error = ?e,
+                    "Failed to build DM channel for broadcast"
+                );
+            }
+        }
+    }
+}
+
+/// Remove `user_id` from a group DM, clear their read state, and hand
+/// ownership to the longest-standing member if the owner left. The channel
+/// is deleted with its last member.
+async fn remove_group_member(state: &AppState, group: &GroupRow, user_id: Uuid) -> AppResult<()> {
+    let mut tx = state.pool.begin().await?;
+
+    // Serialise membership changes on this channel.
+    let owner_id: Option<Uuid> =
+        sqlx::query_scalar("SELECT owner_id FROM direct_message_channels WHERE id = $1 FOR UPDATE")
+            .bind(group.id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+    let removed =
+        sqlx::query("DELETE FROM direct_message_members WHERE channel_id = $1 AND user_id = $2")
+            .bind(group.id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+    if removed.rows_affected() == 0 {
+        return Err(AppError::NotFound("User is not in this group".into()));
+    }
+
+    sqlx::query("DELETE FROM channel_read_states WHERE user_id = $1 AND channel_id = $2")
+        .bind(user_id)
+        .bind(group.id)
+        .execute(&mut *tx)
+        .await?;
+
+    let remaining: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT user_id FROM direct_message_members WHERE channel_id = $1
+         ORDER BY joined_at, user_id",
+    )
+    .bind(group.id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut new_owner = None;
+    if remaining.is_empty() {
+        sqlx::query("DELETE FROM direct_message_channels WHERE id = $1")
+            .bind(group.id)
+            .execute(&mut *tx)
+            .await?;
+    } else if owner_id.is_none() || owner_id == Some(user_id) {
+        new_owner = Some(remaining[0]);
+        sqlx::query("UPDATE direct_message_channels SET owner_id = $2 WHERE id = $1")
+            .bind(group.id)
+            .bind(new_owner)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    broadcast_json(
+        state,
+        &[user_id],
+        EVENT_DM_CHANNEL_DELETE,
+        &json!({ "id": group.id }),
+    )
+    .await;
+    if !remaining.is_empty() {
+        broadcast_json(
+            state,
+            &remaining,
+            EVENT_DM_CHANNEL_RECIPIENT_REMOVE,
+            &json!({ "channel_id": group.id, "user_id": user_id }),
+        )
+        .await;
+    }
+    if let Some(owner_id) = new_owner {
+        broadcast_json(
+            state,
+            &remaining,
+            EVENT_DM_CHANNEL_UPDATE,
+            &json!({ "id": group.id, "name": group.name, "owner_id": owner_id }),
+        )
+        .await;
+    }
+
+    Ok(())
 }
 
 // ============================================================================
@@ -215,7 +485,8 @@ pub async fn open_dm_channel(
          FROM direct_message_members dmm1
          JOIN direct_message_members dmm2
            ON dmm1.channel_id = dmm2.channel_id AND dmm2.user_id = $2
-         WHERE dmm1.user_id = $1
+         JOIN direct_message_channels dmc ON dmc.id = dmm1.channel_id
+         WHERE dmm1.user_id = $1 AND dmc.is_group = FALSE
          LIMIT 1",
     )
     .bind(my_id)
@@ -295,71 +566,274 @@ pub async fn list_dm_channels(
     State(state): State<AppState>,
     auth: AuthUser,
 ) -> AppResult<Json<Vec<DirectMessageChannelDto>>> {
-    #[derive(sqlx::FromRow)]
-    struct Row {
-        channel_id: Uuid,
-        channel_created_at: DateTime<Utc>,
-        recipient_id: Uuid,
-        recipient_username: String,
-        recipient_avatar_url: Option<String>,
-        recipient_bio: Option<String>,
-        recipient_pronouns: Option<String>,
-        recipient_status: String,
-        recipient_custom_status: Option<String>,
-        recipient_created_at: DateTime<Utc>,
-        last_message_at: Option<DateTime<Utc>>,
+    let channels = fetch_dm_channel_dtos(&state.pool, auth.user_id(), None).await?;
+    Ok(Json(channels))
+}
+
+/// POST /dm-channels/groups — create a group DM owned by the caller.
+///
+/// Unlike 1:1 DMs this is not idempotent: the same people can share several
+/// groups. Every participant receives `DM_CHANNEL_CREATE`.
+#[utoipa::path(
+    post,
+    path = "/dm-channels/groups",
+    request_body = CreateGroupDmRequest,
+    responses(
+        (status = 201, description = "Group DM created", body = DirectMessageChannelDto),
+        (status = 400, description = "Validation error"),
+        (status = 404, description = "A user was not found")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "DirectMessages"
+)]
+pub async fn create_group_dm(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(req): Json<CreateGroupDmRequest>,
+) -> AppResult<(StatusCode, Json<DirectMessageChannelDto>)> {
+    let my_id = auth.user_id();
+    let name = normalize_group_name(req.name.as_deref())?;
+
+    let mut member_ids = vec![my_id];
+    for id in req.user_ids {
+        if !member_ids.contains(&id) {
+            member_ids.push(id);
+        }
+    }
+    if member_ids.len() < 2 {
+        return Err(AppError::Validation(
+            "A group DM needs at least one other participant".into(),
+        ));
+    }
+    if member_ids.len() > MAX_GROUP_DM_MEMBERS {
+        return Err(AppError::Validation(format!(
+            "Group DMs can have at most {MAX_GROUP_DM_MEMBERS} participants"
+        )));
     }
 
-    let rows = sqlx::query_as::<_, Row>(
-        "SELECT
-             dmc.id             AS channel_id,
-             dmc.created_at     AS channel_created_at,
-             u.id               AS recipient_id,
-             u.username         AS recipient_username,
-             u.avatar_url       AS recipient_avatar_url,
-             u.bio              AS recipient_bio,
-             u.pronouns         AS recipient_pronouns,
-             u.status           AS recipient_status,
-             u.custom_status    AS recipient_custom_status,
-             u.created_at       AS recipient_created_at,
-             (SELECT MAX(dm.created_at)
-              FROM direct_messages dm
-              WHERE dm.channel_id = dmc.id AND dm.deleted = FALSE
-             ) AS last_message_at
-         FROM direct_message_channels dmc
-         JOIN direct_message_members dmm1 ON dmm1.channel_id = dmc.id AND dmm1.user_id = $1
-         JOIN direct_message_members dmm2 ON dmm2.channel_id = dmc.id AND dmm2.user_id != $1
-         JOIN users u ON u.id = dmm2.user_id
-         ORDER BY last_message_at DESC NULLS LAST",
+    let found: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE id = ANY($1)")
+        .bind(&member_ids as &[Uuid])
+        .fetch_one(&state.pool)
+        .await?;
+    if found != member_ids.len() as i64 {
+        return Err(AppError::NotFound("User not found".into()));
+    }
+
+    let mut tx = state.pool.begin().await?;
+    let channel_id: Uuid = sqlx::query_scalar(
+        "INSERT INTO direct_message_channels (is_group, name, owner_id)
+         VALUES (TRUE, $1, $2)
+         RETURNING id",
     )
-    .bind(auth.user_id())
-    .fetch_all(&state.pool)
+    .bind(&name)
+    .bind(my_id)
+    .fetch_one(&mut *tx)
     .await?;
 
-    let channels = rows
-        .into_iter()
-        .map(|r| DirectMessageChannelDto {
-            id: r.channel_id,
-            recipient: UserDto {
-                id: r.recipient_id,
-                username: r.recipient_username,
-                email: None, // Strip email from other users' profiles
-                avatar_url: r.recipient_avatar_url,
-                bio: r.recipient_bio,
-                pronouns: r.recipient_pronouns,
-
-                status: r.recipient_status,
-                custom_status: r.recipient_custom_status,
-                activity: None,
-                created_at: r.recipient_created_at,
-                is_admin: false, // Strip is_admin from other users' profiles
-            },
-            created_at: r.channel_created_at,
-            last_message_at: r.last_message_at,
-        })
-        .collect();
+    // One row at a time with clock_timestamp() (NOW() is fixed for the whole
+    // transaction) so joined_at, and with it the order ownership passes down,
+    // follows the request: owner first, then user_ids as listed.
+    for user_id in &member_ids {
+        sqlx::query(
+            "INSERT INTO direct_message_members (channel_id, user_id, joined_at)
+             VALUES ($1, $2, clock_timestamp())",
+        )
+        .bind(channel_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
 
-    Ok(Json(channels))
+    broadcast_channel_create(&state, channel_id, &member_ids).await;
+
+    let dto = build_channel_dto(&state.pool, channel_id, my_id).await?;
+    Ok((StatusCode::CREATED, Json(dto)))
+}
+
+/// PATCH /dm-channels/:id — rename a group DM (any participant).
+///
+/// An empty name clears it. Participants receive `DM_CHANNEL_UPDATE`.
+#[utoipa::path(
+    patch,
+    path = "/dm-channels/{id}",
+    request_body = UpdateGroupDmRequest,
+    params(("id" = Uuid, Path, description = "DM channel ID")),
+    responses(
+        (status = 200, description = "Group DM updated", body = DirectMessageChannelDto),
+        (status = 400, description = "Validation error or not a group DM"),
+        (status = 404, description = "DM channel not found")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "DirectMessages"
+)]
+pub async fn update_group_dm(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(channel_id): Path<Uuid>,
+    Json(req): Json<UpdateGroupDmRequest>,
+) -> AppResult<Json<DirectMessageChannelDto>> {
+    let group = fetch_group(&state.pool, channel_id, auth.user_id()).await?;
+    let name = normalize_group_name(Some(&req.name))?;
+
+    sqlx::query("UPDATE direct_message_channels SET name = $2 WHERE id = $1")
+        .bind(group.id)
+        .bind(&name)
+        .execute(&state.pool)
+        .await?;
+
+    let member_ids = fetch_member_ids(&state.pool, group.id).await?;
+    broadcast_json(
+        &state,
+        &member_ids,
+        EVENT_DM_CHANNEL_UPDATE,
+        &json!({ "id": group.id, "name": name, "owner_id": group.owner_id }),
+    )
+    .await;
+
+    let dto = build_channel_dto(&state.pool, group.id, auth.user_id()).await?;
+    Ok(Json(dto))
+}
+
+/// PUT /dm-channels/:id/recipients/:user_id — add a participant to a group
+/// DM (any participant).
+///
+/// Existing participants receive `DM_CHANNEL_RECIPIENT_ADD`; the new
+/// participant receives `DM_CHANNEL_CREATE`. Adding a current participant is
+/// a no-op.
+#[utoipa::path(
+    put,
+    path = "/dm-channels/{id}/recipients/{user_id}",
+    params(
+        ("id" = Uuid, Path, description = "DM channel ID"),
+        ("user_id" = Uuid, Path, description = "User to add")
+    ),
+    responses(
+        (status = 204, description = "Participant added"),
+        (status = 400, description = "Not a group DM, or the group is full"),
+        (status = 404, description = "DM channel or user not found")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "DirectMessages"
+)]
+pub async fn add_group_dm_recipient(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((channel_id, user_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<StatusCode> {
+    let group = fetch_group(&state.pool, channel_id, auth.user_id()).await?;
+
+    let user = sqlx::query_as::<_, DmUserRow>(&format!(
+        "SELECT {DM_USER_COLUMNS} FROM users u WHERE u.id = $1"
+    ))
+    .bind(user_id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".into()))?;
+
+    let mut tx = state.pool.begin().await?;
+
+    // Lock the channel so concurrent adds cannot overshoot the cap.
+    sqlx::query("SELECT id FROM direct_message_channels WHERE id = $1 FOR UPDATE")
+        .bind(group.id)
+        .execute(&mut *tx)
+        .await?;
+
+    let existing: Vec<Uuid> =
+        sqlx::query_scalar("SELECT user_id FROM direct_message_members WHERE channel_id = $1")
+            .bind(group.id)
+            .fetch_all(&mut *tx)
+            .await?;
+    if existing.contains(&user_id) {
+        return Ok(StatusCode::NO_CONTENT);
+    }
+    if existing.len() >= MAX_GROUP_DM_MEMBERS {
+        return Err(AppError::Validation(format!(
+            "Group DMs can have at most {MAX_GROUP_DM_MEMBERS} participants"
+        )));
+    }
+
+    sqlx::query("INSERT INTO direct_message_members (channel_id, user_id) VALUES ($1, $2)")
+        .bind(group.id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    broadcast_json(
+        &state,
+        &existing,
+        EVENT_DM_CHANNEL_RECIPIENT_ADD,
+        &json!({ "channel_id": group.id, "user": UserDto::from(user) }),
+    )
+    .await;
+    broadcast_channel_create(&state, group.id, &[user_id]).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /dm-channels/:id/recipients/:user_id — remove a participant from a
+/// group DM.
+///
+/// Only the owner can remove someone else; anyone can remove themselves,
+/// which is the same as leaving.
+#[utoipa::path(
+    delete,
+    path = "/dm-channels/{id}/recipients/{user_id}",
+    params(
+        ("id" = Uuid, Path, description = "DM channel ID"),
+        ("user_id" = Uuid, Path, description = "User to remove")
+    ),
+    responses(
+        (status = 204, description = "Participant removed"),
+        (status = 400, description = "Not a group DM"),
+        (status = 403, description = "Only the owner can remove participants"),
+        (status = 404, description = "DM channel or participant not found")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "DirectMessages"
+)]
+pub async fn remove_group_dm_recipient(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((channel_id, user_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<StatusCode> {
+    let group = fetch_group(&state.pool, channel_id, auth.user_id()).await?;
+    if user_id != auth.user_id() && group.owner_id != Some(auth.user_id()) {
+        return Err(AppError::Forbidden(
+            "Only the group owner can remove participants".into(),
+        ));
+    }
+
+    remove_group_member(&state, &group, user_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /dm-channels/:id — leave a group DM.
+///
+/// If the owner leaves, ownership passes to the longest-standing remaining
+/// participant. The channel and its history are deleted when the last
+/// participant leaves. 1:1 DMs cannot be left.
+#[utoipa::path(
+    delete,
+    path = "/dm-channels/{id}",
+    params(("id" = Uuid, Path, description = "DM channel ID")),
+    responses(
+        (status = 204, description = "Left the group DM"),
+        (status = 400, description = "Not a group DM"),
+        (status = 404, description = "DM channel not found")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "DirectMessages"
+)]
+pub async fn leave_dm_channel(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(channel_id): Path<Uuid>,
+) -> AppResult<StatusCode> {
+    let group = fetch_group(&state.pool, channel_id, auth.user_id()).await?;
+    remove_group_member(&state, &group, auth.user_id()).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
 
 /// POST /dm-channels/:id/messages — send a message to a DM channel.
@@ -397,7 +871,7 @@ pub async fn send_dm_message(
     .fetch_one(&state.pool)
     .await?;
 
-    // Get both participants to broadcast to.
+    // Fan out to every participant, including the author's other sessions.
     let participant_ids: Vec<Uuid> = match sqlx::query_scalar(
         "SELECT user_id FROM direct_message_members WHERE channel_id = $1",
     )
@@ -430,6 +904,16 @@ pub async fn send_dm_message(
         }
     }
 
+    push::notify(
+        &state,
+        PushJob::DirectMessage {
+            channel_id,
+            message_id: message.id,
+            author_id: auth.user_id(),
+            content: message.content.clone(),
+        },
+    );
+
     Ok((StatusCode::CREATED, Json(message)))
 }
 
@@ -0,0 +1,274 @@
+//! Email address verification and email preferences. Sending lives in
+//! `crate::mail`.
+
+use axum::{extract::State, http::StatusCode, Json};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::Deserialize;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::{hash_refresh_token, AuthUser},
+    error::{AppError, AppResult},
+    mail::{self, Mailer, Template},
+    models::EmailPreferences,
+    state::AppState,
+};
+
+/// Verification links stay valid this long.
+const VERIFICATION_TTL_HOURS: i64 = 24;
+/// Minimum gap between two verification emails to the same user.
+const RESEND_COOLDOWN_SECS: i64 = 60;
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct VerifyEmailRequest {
+    #[validate(length(min = 1, max = 256))]
+    pub token: String,
+}
+
+/// A random single-use token and the SHA-256 hash that is stored for it.
+pub(crate) fn new_email_token() -> (String, String) {
+    let bytes: [u8; 32] = rand::random();
+    let token = URL_SAFE_NO_PAD.encode(bytes);
+    let hash = hash_refresh_token(&token);
+    (token, hash)
+}
+
+pub(crate) fn require_mailer(state: &AppState) -> AppResult<&Mailer> {
+    state
+        .mailer
+        .as_ref()
+        .ok_or_else(|| AppError::NotFound("Email is not configured on this server".into()))
+}
+
+/// Issue a verification token for `email` and send the link. Earlier unused
+/// tokens for the user are replaced.
+pub(crate) async fn send_verification_email(
+    state: &AppState,
+    mailer: &Mailer,
+    user_id: Uuid,
+    username: &str,
+    email: &str,
+) -> AppResult<()> {
+    let (token, token_hash) = new_email_token();
+
+    let mut tx = state.pool.begin().await?;
+    sqlx::query("DELETE FROM email_verification_tokens WHERE user_id = $1 AND used_at IS NULL")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO email_verification_tokens (user_id, email, token_hash, expires_at)
+         VALUES ($1, $2, $3, NOW() + make_interval(hours => $4::INT))",
+    )
+    .bind(user_id)
+    .bind(email)
+    .bind(&token_hash)
+    .bind(VERIFICATION_TTL_HOURS as i32)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    let link = mailer.link(&format!("/verify-email?token={token}"));
+    mail::send(
+        state,
+        mailer.compose(
+            email,
+            Template::VerifyEmail,
+            &[("username", username), ("link", &link)],
+        ),
+    );
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/verify-email",
+    request_body = VerifyEmailRequest,
+    responses(
+        (status = 200, description = "Email address verified", body = serde_json::Value),
+        (status = 400, description = "Validation error"),
+        (status = 401, description = "Invalid or expired verification token")
+    ),
+    tag = "Auth"
+)]
+/// POST /auth/verify-email — confirm an address with the token from the
+/// verification email. No authentication: the link may be opened on a
+/// device where the user is not logged in.
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Json(req): Json<VerifyEmailRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    req.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let token_hash = hash_refresh_token(&req.token);
+    let mut tx = state.pool.begin().await?;
+
+    // The token only counts while the address it was sent to is still the
+    // account's address.
+    let user_id: Uuid = sqlx::query_scalar(
+        "UPDATE email_verification_tokens t SET used_at = NOW()
+         FROM users u
+         WHERE t.token_hash = $1
+           AND t.used_at IS NULL
+           AND t.expires_at > NOW()
+           AND u.id = t.user_id
+           AND u.email = t.email
+         RETURNING t.user_id",
+    )
+    .bind(&token_hash)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::Auth("Invalid or expired verification token".into()))?;
+
+    sqlx::query(
+        "UPDATE users SET email_verified_at = NOW(), updated_at = NOW()
+         WHERE id = $1 AND email_verified_at IS NULL",
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    info!("Email verified for user: {}", user_id);
+
+    Ok(Json(serde_json::json!({
+        "message": "Email address verified"
+    })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/@me/email/verification",
+    responses(
+        (status = 202, description = "Verification email queued"),
+        (status = 400, description = "No email on the account, already verified, or asked too recently"),
+        (status = 404, description = "Email is not configured")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Users"
+)]
+/// POST /users/@me/email/verification — send a new verification link.
+pub async fn resend_verification_email(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> AppResult<StatusCode> {
+    let mailer = require_mailer(&state)?;
+
+    let (email, verified, recently_sent): (Option<String>, bool, bool) = sqlx::query_as(
+        "SELECT u.email,
+                u.email_verified_at IS NOT NULL,
+                EXISTS(
+                    SELECT 1 FROM email_verification_tokens t
+                    WHERE t.user_id = u.id
+                      AND t.created_at > NOW() - make_interval(secs => $2)
+                )
+         FROM users u WHERE u.id = $1",
+    )
+    .bind(auth.user_id())
+    .bind(RESEND_COOLDOWN_SECS as f64)
+    .fetch_one(&state.pool)
+    .await?;
+
+    let email =
+        email.ok_or_else(|| AppError::Validation("Your account has no email address".into()))?;
+    if verified {
+        return Err(AppError::Validation(
+            "Your email address is already verified".into(),
+        ));
+    }
+    if recently_sent {
+        return Err(AppError::Validation(
+            "A verification email was sent recently; check your inbox".into(),
+        ));
+    }
+
+    send_verification_email(&state, mailer, auth.user_id(), auth.username(), &email).await?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/@me/email-preferences",
+    responses(
+        (status = 200, description = "The user's email preferences", body = EmailPreferences),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Users"
+)]
+/// GET /users/@me/email-preferences
+pub async fn get_email_preferences(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> AppResult<Json<EmailPreferences>> {
+    let mention_digest: Option<bool> =
+        sqlx::query_scalar("SELECT mention_digest FROM email_preferences WHERE user_id = $1")
+            .bind(auth.user_id())
+            .fetch_optional(&state.pool)
+            .await?;
+    Ok(Json(EmailPreferences {
+        mention_digest: mention_digest.unwrap_or(false),
+    }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/users/@me/email-preferences",
+    request_body = EmailPreferences,
+    responses(
+        (status = 200, description = "Updated email preferences", body = EmailPreferences),
+        (status = 400, description = "Digest unavailable or email not verified"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Users"
+)]
+/// PUT /users/@me/email-preferences — opt in to (or out of) the unread
+/// mention digest. Opting in needs a verified email address.
+pub async fn update_email_preferences(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(req): Json<EmailPreferences>,
+) -> AppResult<Json<EmailPreferences>> {
+    if req.mention_digest {
+        let digest_enabled = state
+            .mailer
+            .as_ref()
+            .is_some_and(|m| m.digest_interval().is_some());
+        if !digest_enabled {
+            return Err(AppError::Validation(
+                "The mention digest is not enabled on this server".into(),
+            ));
+        }
+        let verified: bool = sqlx::query_scalar(
+            "SELECT email IS NOT NULL AND email_verified_at IS NOT NULL FROM users WHERE id = $1",
+        )
+        .bind(auth.user_id())
+        .fetch_one(&state.pool)
+        .await?;
+        if !verified {
+            return Err(AppError::Validation(
+                "Verify your email address before turning on the digest".into(),
+            ));
+        }
+    }
+
+    // Opting in restarts the digest window so old mentions are not mailed.
+    sqlx::query(
+        "INSERT INTO email_preferences (user_id, mention_digest)
+         VALUES ($1, $2)
+         ON CONFLICT (user_id) DO UPDATE SET
+             mention_digest     = EXCLUDED.mention_digest,
+             digest_last_run_at = CASE
+                 WHEN email_preferences.mention_digest THEN email_preferences.digest_last_run_at
+             END,
+             updated_at         = NOW()",
+    )
+    .bind(auth.user_id())
+    .bind(req.mention_digest)
+    .execute(&state.pool)
+    .await?;
+    Ok(Json(req))
+}
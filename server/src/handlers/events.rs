@@ -10,7 +10,7 @@ use crate::{
     error::{AppError, AppResult},
     models::{CreateEventPayload, MessageDto, ServerEventDto},
     state::AppState,
-    websocket::{broadcast_to_server, events::EVENT_MESSAGE_CREATE},
+    websocket::{broadcast_to_channel, events::EVENT_MESSAGE_CREATE},
 };
 
 use super::shared::{fetch_channel_by_id, require_member};
@@ -94,9 +94,10 @@ pub async fn create_event(
     let mut dto = MessageDto::from_message(message);
     dto.event = Some(event_dto);
 
-    broadcast_to_server(
+    broadcast_to_channel(
         &state,
         channel.server_id,
+        channel.id,
         EVENT_MESSAGE_CREATE,
         serde_json::to_value(&dto).unwrap_or_default(),
     )
@@ -68,10 +68,26 @@ struct ExportMessage {
     author_username: Option<String>,
     content: String,
     reply_to: Option<Uuid>,
+    thread_id: Option<Uuid>,
+    /// Forum posts only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    /// Forum posts only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag_ids: Option<Vec<Uuid>>,
     edited_at: Option<DateTime<Utc>>,
     created_at: DateTime<Utc>,
 }
 
+#[derive(Serialize, FromRow)]
+struct ExportForumTag {
+    id: Uuid,
+    channel_id: Uuid,
+    name: String,
+    emoji: Option<String>,
+    position: i32,
+}
+
 #[derive(Serialize, FromRow)]
 struct ExportDmMessage {
     id: Uuid,
@@ -84,7 +100,8 @@ struct ExportDmMessage {
 #[derive(FromRow)]
 struct DmChannelRow {
     id: Uuid,
-    partner_username: String,
+    /// Group name, or the other participants' usernames.
+    label: String,
 }
 
 // ============================================================================
@@ -151,8 +168,10 @@ use super::shared::sanitize_header_filename;
 ///     channels.json                        — all channels
 ///     members.json                         — member list (no credentials)
 ///     roles.json                           — role definitions
-///     messages/{channel-slug}-{id}.jsonl   — newline-delimited JSON per text channel
-///     dm_messages/{user-slug}-{id}.jsonl   — requesting user's DMs
+///     forum_tags.json                      — tag sets of forum channels
+///     messages/{channel-slug}-{id}.jsonl   — newline-delimited JSON per text or
+///                                            forum channel (posts carry title and tag_ids)
+///     dm_messages/{label-slug}-{id}.jsonl  — requesting user's DMs and group DMs
 pub async fn export_server(
     State(state): State<AppState>,
     auth: AuthUser,
@@ -198,6 +217,17 @@ pub async fn export_server(
     .fetch_all(&state.pool)
     .await?;
 
+    let forum_tags = sqlx::query_as::<_, ExportForumTag>(
+        "SELECT t.id, t.channel_id, t.name, t.emoji, t.position
+         FROM forum_tags t
+         JOIN channels c ON c.id = t.channel_id
+         WHERE c.server_id = $1
+         ORDER BY c.position, t.position, t.created_at",
+    )
+    .bind(server_id)
+    .fetch_all(&state.pool)
+    .await?;
+
     // ── Build ZIP in memory ───────────────────────────────────────────────────
 
     let buf = Cursor::new(Vec::<u8>::new());
@@ -220,19 +250,22 @@ pub async fn export_server(
     add_json!(format!("{dir}channels.json"), &channels);
     add_json!(format!("{dir}members.json"), &members);
     add_json!(format!("{dir}roles.json"), &roles);
+    add_json!(format!("{dir}forum_tags.json"), &forum_tags);
 
     // ── Per-channel message files ─────────────────────────────────────────────
 
     for ch in &channels {
-        if ch.channel_type != "text" {
+        if ch.channel_type != "text" && ch.channel_type != "forum" {
             continue;
         }
 
         let messages = sqlx::query_as::<_, ExportMessage>(
             "SELECT m.id, m.author_id, u.username AS author_username,
-                    m.content, m.reply_to, m.edited_at, m.created_at
+                    m.content, m.reply_to, m.thread_id, fp.title, fp.tag_ids,
+                    m.edited_at, m.created_at
              FROM messages m
              LEFT JOIN users u ON u.id = m.author_id
+             LEFT JOIN forum_posts fp ON fp.message_id = m.id
              WHERE m.channel_id = $1 AND m.deleted = FALSE
              ORDER BY m.created_at ASC",
         )
@@ -262,11 +295,16 @@ pub async fn export_server(
 
     let dm_channels = sqlx::query_as::<_, DmChannelRow>(
         "SELECT dmc.id,
-                COALESCE(u.username, 'unknown') AS partner_username
+                COALESCE(
+                    dmc.name,
+                    (SELECT string_agg(u.username, '-' ORDER BY u.username)
+                     FROM direct_message_members other
+                     JOIN users u ON u.id = other.user_id
+                     WHERE other.channel_id = dmc.id AND other.user_id != $1),
+                    'unknown'
+                ) AS label
          FROM direct_message_channels dmc
          JOIN direct_message_members my ON my.channel_id = dmc.id AND my.user_id = $1
-         JOIN direct_message_members other ON other.channel_id = dmc.id AND other.user_id != $1
-         JOIN users u ON u.id = other.user_id
          ORDER BY dmc.created_at",
     )
     .bind(auth.user_id())
@@ -291,11 +329,7 @@ pub async fn export_server(
         }
 
         zip.start_file(
-            format!(
-                "{dir}dm_messages/{}-{}.jsonl",
-                to_slug(&dm.partner_username),
-                dm.id
-            ),
+            format!("{dir}dm_messages/{}-{}.jsonl", to_slug(&dm.label), dm.id),
             opts,
         )
         .map_err(|_| AppError::Internal)?;
@@ -0,0 +1,319 @@
+//! Serving stored files over HTTP.
+//!
+//! Attachments and custom emoji images are answered through
+//! [`serve_stored_file`] once the calling handler has checked access. Files
+//! on local disk are streamed with validators and byte-range support; files
+//! in a bucket are redirected to a presigned URL, where the object store
+//! does the same.
+
+use std::io::SeekFrom;
+
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, StatusCode},
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+
+use crate::{
+    error::{AppError, AppResult},
+    storage::{Download, ResponseHeaders, Storage},
+};
+
+/// Answer a download of `key` from `storage`: stream the file with `headers`,
+/// or redirect to a presigned bucket URL that carries them. Callers check
+/// access first.
+///
+/// Files streamed from local disk carry an `ETag` and `Last-Modified`, and
+/// `request` headers are honoured the way a static file server would:
+/// `If-None-Match` / `If-Modified-Since` get `304 Not Modified`, and a single
+/// `Range` gets `206 Partial Content` (subject to `If-Range`) so media can be
+/// seeked. Object storage does the same itself behind the redirect.
+pub async fn serve_stored_file(
+    storage: &dyn Storage,
+    key: &str,
+    headers: ResponseHeaders,
+    request: &HeaderMap,
+) -> AppResult<Response> {
+    let download = storage.download(key, &headers).await.map_err(|e| {
+        tracing::error!(error = ?e, key, "Failed to open stored file");
+        AppError::Internal
+    })?;
+
+    match download {
+        Download::File(file) => serve_local_file(file, key, headers, request).await,
+        // The presigned URL stops working after `expires_in`, so clients may
+        // only reuse the redirect for part of that.
+        Download::Redirect { url, expires_in } => Response::builder()
+            .status(StatusCode::TEMPORARY_REDIRECT)
+            .header(header::LOCATION, url)
+            .header(
+                header::CACHE_CONTROL,
+                format!("private, max-age={}", expires_in.as_secs() / 2),
+            )
+            .body(Body::empty())
+            .map_err(|_| AppError::Internal),
+    }
+}
+
+/// The conditional and range handling behind [`serve_stored_file`].
+async fn serve_local_file(
+    mut file: tokio::fs::File,
+    key: &str,
+    headers: ResponseHeaders,
+    request: &HeaderMap,
+) -> AppResult<Response> {
+    let read_failed = |e: std::io::Error| {
+        tracing::error!(error = ?e, key, "Failed to read stored file");
+        AppError::Internal
+    };
+    let metadata = file.metadata().await.map_err(read_failed)?;
+    let len = metadata.len();
+    // Whole seconds, since that is all `Last-Modified` can express.
+    let modified = metadata
+        .modified()
+        .ok()
+        .map(DateTime::<Utc>::from)
+        .and_then(|t| DateTime::from_timestamp(t.timestamp(), 0));
+    // Stored files are never rewritten in place, so size and modification
+    // time identify the content.
+    let etag = format!("\"{:x}-{:x}\"", modified.map_or(0, |t| t.timestamp()), len);
+
+    let mut response = Response::builder()
+        .header(header::ETAG, &etag)
+        .header(header::ACCEPT_RANGES, "bytes");
+    if let Some(modified) = modified {
+        response = response.header(header::LAST_MODIFIED, http_date(modified));
+    }
+    if let Some(cache_control) = headers.cache_control {
+        response = response.header(header::CACHE_CONTROL, cache_control);
+    }
+
+    if is_not_modified(request, &etag, modified) {
+        return response
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .map_err(|_| AppError::Internal);
+    }
+
+    for (name, value) in [
+        (header::CONTENT_TYPE, headers.content_type),
+        (header::CONTENT_DISPOSITION, headers.content_disposition),
+    ] {
+        if let Some(value) = value {
+            response = response.header(name, value);
+        }
+    }
+
+    let range = match request.get(header::RANGE).and_then(|v| v.to_str().ok()) {
+        Some(range) if if_range_matches(request, &etag, modified) => parse_range(range, len),
+        _ => ByteRange::Whole,
+    };
+
+    let response = match range {
+        ByteRange::Whole => response
+            .status(StatusCode::OK)
+            .header(header::CONTENT_LENGTH, len)
+            .body(Body::from_stream(ReaderStream::new(file))),
+        ByteRange::Part { start, end } => {
+            file.seek(SeekFrom::Start(start))
+                .await
+                .map_err(read_failed)?;
+            let part_len = end - start + 1;
+            response
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(header::CONTENT_RANGE, format!("bytes {start}-{end}/{len}"))
+                .header(header::CONTENT_LENGTH, part_len)
+                .body(Body::from_stream(ReaderStream::new(file.take(part_len))))
+        }
+        ByteRange::Unsatisfiable => response
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{len}"))
+            .body(Body::empty()),
+    };
+    // Stored names and content types end up in headers; a value `http`
+    // refuses is an error, not a panic.
+    response.map_err(|_| AppError::Internal)
+}
+
+/// What to send for a `Range` header.
+#[derive(Debug, PartialEq, Eq)]
+enum ByteRange {
+    /// No usable range: send the whole file.
+    Whole,
+    /// Bytes `start..=end`.
+    Part { start: u64, end: u64 },
+    /// The range starts past the end of the file.
+    Unsatisfiable,
+}
+
+/// Parse `Range: bytes=...` against a file of `len` bytes. Only a single
+/// range is supported; anything else, including multiple ranges, is ignored
+/// and the whole file sent, which RFC 9110 allows.
+fn parse_range(value: &str, len: u64) -> ByteRange {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return ByteRange::Whole;
+    };
+    let Some((first, last)) = spec.split_once('-') else {
+        return ByteRange::Whole;
+    };
+    if spec.contains(',') {
+        return ByteRange::Whole;
+    }
+
+    let (first, last) = (first.trim(), last.trim());
+    if first.is_empty() {
+        // `bytes=-N`: the last N bytes.
+        return match last.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if len == 0 => ByteRange::Unsatisfiable,
+            Ok(n) => ByteRange::Part {
+                start: len.saturating_sub(n),
+                end: len - 1,
+            },
+            Err(_) => ByteRange::Whole,
+        };
+    }
+
+    let Ok(start) = first.parse::<u64>() else {
+        return ByteRange::Whole;
+    };
+    let end = if last.is_empty() {
+        None
+    } else {
+        match last.parse::<u64>() {
+            Ok(end) if end >= start => Some(end),
+            _ => return ByteRange::Whole,
+        }
+    };
+    if start >= len {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Part {
+        start,
+        end: end.map_or(len - 1, |end| end.min(len - 1)),
+    }
+}
+
+/// Whether the client's cached copy is current. `If-None-Match` takes
+/// precedence over `If-Modified-Since`, and compares entity tags weakly.
+fn is_not_modified(request: &HeaderMap, etag: &str, modified: Option<DateTime<Utc>>) -> bool {
+    if let Some(if_none_match) = request.get(header::IF_NONE_MATCH) {
+        return if_none_match.to_str().is_ok_and(|tags| {
+            tags.split(',')
+                .map(|tag| tag.trim())
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+        });
+    }
+    let since = request
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| DateTime::parse_from_rfc2822(v).ok());
+    matches!((modified, since), (Some(modified), Some(since)) if modified <= since)
+}
+
+/// Whether a `Range` applies under `If-Range`: only if the client's entity
+/// tag (compared strongly) or date still describes the file.
+fn if_range_matches(request: &HeaderMap, etag: &str, modified: Option<DateTime<Utc>>) -> bool {
+    let Some(if_range) = request.get(header::IF_RANGE) else {
+        return true;
+    };
+    let Ok(if_range) = if_range.to_str() else {
+        return false;
+    };
+    if if_range.starts_with('"') || if_range.starts_with("W/") {
+        return if_range == etag;
+    }
+    match (modified, DateTime::parse_from_rfc2822(if_range)) {
+        (Some(modified), Ok(date)) => modified == date,
+        _ => false,
+    }
+}
+
+/// Format a timestamp as an HTTP date (`Sun, 06 Nov 1994 08:49:37 GMT`).
+fn http_date(t: DateTime<Utc>) -> String {
+    t.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+// ── Unit tests ────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_single_byte_ranges() {
+        let part = |start, end| ByteRange::Part { start, end };
+        assert_eq!(parse_range("bytes=0-99", 1000), part(0, 99));
+        assert_eq!(parse_range("bytes=500-", 1000), part(500, 999));
+        assert_eq!(parse_range("bytes=-100", 1000), part(900, 999));
+        // Ends past the file are clamped, suffixes longer than it take all.
+        assert_eq!(parse_range("bytes=900-5000", 1000), part(900, 999));
+        assert_eq!(parse_range("bytes=-5000", 1000), part(0, 999));
+    }
+
+    #[test]
+    fn unusable_ranges_fall_back_or_fail() {
+        assert_eq!(parse_range("bytes=1000-", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-0", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-", 0), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-1,5-6", 1000), ByteRange::Whole);
+        assert_eq!(parse_range("bytes=9-3", 1000), ByteRange::Whole);
+        assert_eq!(parse_range("items=0-1", 1000), ByteRange::Whole);
+        assert_eq!(parse_range("bytes=a-b", 1000), ByteRange::Whole);
+    }
+
+    #[test]
+    fn conditional_headers_compare_tags_and_dates() {
+        let modified = DateTime::from_timestamp(784_111_777, 0);
+        let etag = "\"2ebc2aa1-3e8\"";
+        let request = |name, value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(name, value.parse().unwrap());
+            headers
+        };
+
+        assert_eq!(
+            http_date(modified.unwrap()),
+            "Sun, 06 Nov 1994 08:49:37 GMT"
+        );
+        assert!(is_not_modified(
+            &request(header::IF_NONE_MATCH, "\"other\", W/\"2ebc2aa1-3e8\""),
+            etag,
+            modified
+        ));
+        assert!(!is_not_modified(
+            &request(header::IF_NONE_MATCH, "\"other\""),
+            etag,
+            modified
+        ));
+        assert!(is_not_modified(
+            &request(header::IF_MODIFIED_SINCE, "Sun, 06 Nov 1994 08:49:37 GMT"),
+            etag,
+            modified
+        ));
+        assert!(!is_not_modified(
+            &request(header::IF_MODIFIED_SINCE, "Sun, 06 Nov 1994 08:49:36 GMT"),
+            etag,
+            modified
+        ));
+
+        assert!(if_range_matches(
+            &request(header::IF_RANGE, etag),
+            etag,
+            modified
+        ));
+        assert!(!if_range_matches(
+            &request(header::IF_RANGE, "W/\"2ebc2aa1-3e8\""),
+            etag,
+            modified
+        ));
+        assert!(if_range_matches(
+            &request(header::IF_RANGE, "Sun, 06 Nov 1994 08:49:37 GMT"),
+            etag,
+            modified
+        ));
+    }
+}
@@ -0,0 +1,559 @@
+//! Forum channels: per-channel tag sets and the post list.
+//!
+//! Provides:
+//! - `GET /channels/:channel_id/forum/tags` — List the channel's tags
+//! - `POST /channels/:channel_id/forum/tags` — Create a tag
+//! - `PATCH /channels/:channel_id/forum/tags/:tag_id` — Rename or reorder a tag
+//! - `DELETE /channels/:channel_id/forum/tags/:tag_id` — Delete a tag
+//! - `GET /channels/:channel_id/posts` — List posts by latest activity
+//! - `PATCH /channels/:channel_id/posts/:post_id` — Edit a post's title or tags
+//!
+//! A post is a root message created with `POST /channels/:channel_id/messages`
+//! and a `title`; replies are thread replies on that message.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use super::messages::enrich_messages;
+use super::shared::{
+    compute_channel_permissions, fetch_channel_by_id, require_channel_permission, require_member,
+    PERMISSION_MANAGE_CHANNELS, PERMISSION_MANAGE_MESSAGES, PERMISSION_VIEW_CHANNEL,
+};
+use super::webhooks::dispatch_event;
+use crate::{
+    auth::AuthUser,
+    error::{AppError, AppResult},
+    models::{
+        Channel, ChannelType, CreateForumTagDto, ForumTag, Message, MessageDto, UpdateForumPostDto,
+        UpdateForumTagDto,
+    },
+    state::AppState,
+    websocket::{broadcast_to_channel, events::EVENT_MESSAGE_UPDATE},
+};
+
+const MAX_TAGS_PER_CHANNEL: i64 = 20;
+const MAX_TAGS_PER_POST: usize = 5;
+const MAX_TAG_NAME_CHARS: usize = 50;
+const MAX_TAG_EMOJI_CHARS: usize = 64;
+const MAX_TITLE_CHARS: usize = 100;
+
+const TAG_COLUMNS: &str = "id, channel_id, name, emoji, position, created_at";
+
+// ============================================================================
+// Validation shared with message creation
+// ============================================================================
+
+/// Trim and length-check a post title.
+pub fn validate_post_title(title: &str) -> AppResult<String> {
+    let title = title.trim();
+    if title.is_empty() || title.chars().count() > MAX_TITLE_CHARS {
+        return Err(AppError::Validation(format!(
+            "Post title must be 1–{MAX_TITLE_CHARS} characters"
+        )));
+    }
+    Ok(title.to_owned())
+}
+
+/// De-duplicate `tag_ids` and check they all belong to the forum channel.
+pub async fn validate_post_tags(
+    pool: &sqlx::PgPool,
+    channel_id: Uuid,
+    tag_ids: &[Uuid],
+) -> AppResult<Vec<Uuid>> {
+    let mut unique: Vec<Uuid> = Vec::with_capacity(tag_ids.len());
+    for id in tag_ids {
+        if !unique.contains(id) {
+            unique.push(*id);
+        }
+    }
+    if unique.len() > MAX_TAGS_PER_POST {
+        return Err(AppError::Validation(format!(
+            "A post can have at most {MAX_TAGS_PER_POST} tags"
+        )));
+    }
+    if unique.is_empty() {
+        return Ok(unique);
+    }
+
+    let found: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM forum_tags WHERE channel_id = $1 AND id = ANY($2)",
+    )
+    .bind(channel_id)
+    .bind(&unique as &[Uuid])
+    .fetch_one(pool)
+    .await?;
+    if found != unique.len() as i64 {
+        return Err(AppError::Validation(
+            "Tags must come from this channel's tag set".into(),
+        ));
+    }
+    Ok(unique)
+}
+
+// ============================================================================
+// Private helpers
+// ============================================================================
+
+/// Fetch the channel, check membership and that it is a forum channel.
+async fn fetch_forum_channel(
+    state: &AppState,
+    channel_id: Uuid,
+    user_id: Uuid,
+) -> AppResult<Channel> {
+    let channel = fetch_channel_by_id(&state.pool, channel_id).await?;
+    require_member(&state.pool, channel.server_id, user_id).await?;
+    if !matches!(channel.r#type, ChannelType::Forum) {
+        return Err(AppError::Validation(
+            "Channel is not a forum channel".into(),
+        ));
+    }
+    Ok(channel)
+}
+
+async fn require_manage_tags(state: &AppState, channel: &Channel, user_id: Uuid) -> AppResult<()> {
+    require_channel_permission(
+        &state.pool,
+        channel.server_id,
+        channel.id,
+        user_id,
+        PERMISSION_MANAGE_CHANNELS,
+        "You need the Manage Channels permission to manage forum tags",
+    )
+    .await
+}
+
+fn validate_tag_name(name: &str) -> AppResult<String> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_TAG_NAME_CHARS {
+        return Err(AppError::Validation(format!(
+            "Tag name must be 1–{MAX_TAG_NAME_CHARS} characters"
+        )));
+    }
+    Ok(name.to_owned())
+}
+
+fn validate_tag_emoji(emoji: &str) -> AppResult<()> {
+    if emoji.chars().count() > MAX_TAG_EMOJI_CHARS {
+        return Err(AppError::Validation(format!(
+            "Tag emoji must be ≤ {MAX_TAG_EMOJI_CHARS} characters"
+        )));
+    }
+    Ok(())
+}
+
+fn tag_name_conflict(e: AppError) -> AppError {
+    match e {
+        AppError::Conflict(_) => {
+            AppError::Conflict("A tag with that name already exists in this channel".into())
+        }
+        other => other,
+    }
+}
+
+// ============================================================================
+// Tag handlers
+// ============================================================================
+
+/// GET /channels/:channel_id/forum/tags — the channel's tags in display order.
+#[utoipa::path(
+    get,
+    path = "/channels/{channel_id}/forum/tags",
+    params(("channel_id" = Uuid, Path, description = "Forum channel ID")),
+    responses(
+        (status = 200, description = "Forum tags", body = Vec<ForumTag>),
+        (status = 400, description = "Not a forum channel"),
+        (status = 403, description = "No permission to view channel"),
+        (status = 404, description = "Channel not found")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Forum"
+)]
+pub async fn list_forum_tags(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(channel_id): Path<Uuid>,
+) -> AppResult<Json<Vec<ForumTag>>> {
+    let channel = fetch_forum_channel(&state, channel_id, auth.user_id()).await?;
+    require_channel_permission(
+        &state.pool,
+        channel.server_id,
+        channel_id,
+        auth.user_id(),
+        PERMISSION_VIEW_CHANNEL,
+        "You don't have permission to view this channel",
+    )
+    .await?;
+
+    let tags = sqlx::query_as::<_, ForumTag>(&format!(
+        "SELECT {TAG_COLUMNS} FROM forum_tags
+         WHERE channel_id = $1
+         ORDER BY position ASC, created_at ASC"
+    ))
+    .bind(channel_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(tags))
+}
+
+/// POST /channels/:channel_id/forum/tags — add a tag (MANAGE_CHANNELS).
+#[utoipa::path(
+    post,
+    path = "/channels/{channel_id}/forum/tags",
+    request_body = CreateForumTagDto,
+    params(("channel_id" = Uuid, Path, description = "Forum channel ID")),
+    responses(
+        (status = 201, description = "Tag created", body = ForumTag),
+        (status = 400, description = "Validation error or not a forum channel"),
+        (status = 403, description = "Missing Manage Channels permission"),
+        (status = 404, description = "Channel not found"),
+        (status = 409, description = "Tag name already used in this channel")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Forum"
+)]
+pub async fn create_forum_tag(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(channel_id): Path<Uuid>,
+    Json(req): Json<CreateForumTagDto>,
+) -> AppResult<(StatusCode, Json<ForumTag>)> {
+    let channel = fetch_forum_channel(&state, channel_id, auth.user_id()).await?;
+    require_manage_tags(&state, &channel, auth.user_id()).await?;
+
+    let name = validate_tag_name(&req.name)?;
+    if let Some(emoji) = &req.emoji {
+        validate_tag_emoji(emoji)?;
+    }
+    if req.position.is_some_and(|p| p < 0) {
+        return Err(AppError::Validation("Position must be ≥ 0".into()));
+    }
+
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM forum_tags WHERE channel_id = $1")
+        .bind(channel_id)
+        .fetch_one(&state.pool)
+        .await?;
+    if count >= MAX_TAGS_PER_CHANNEL {
+        return Err(AppError::Validation(format!(
+            "A forum channel can have at most {MAX_TAGS_PER_CHANNEL} tags"
+        )));
+    }
+
+    // New tags go last unless a position is given.
+    let tag = sqlx::query_as::<_, ForumTag>(&format!(
+        "INSERT INTO forum_tags (channel_id, name, emoji, position)
+         VALUES ($1, $2, $3, COALESCE($4, (SELECT COUNT(*)::int FROM forum_tags WHERE channel_id = $1)))
+         RETURNING {TAG_COLUMNS}"
+    ))
+    .bind(channel_id)
+    .bind(&name)
+    .bind(&req.emoji)
+    .bind(req.position)
+    .fetch_one(&state.pool)
+    .await
+    .map_err(|e| tag_name_conflict(e.into()))?;
+
+    Ok((StatusCode::CREATED, Json(tag)))
+}
+
+/// PATCH /channels/:channel_id/forum/tags/:tag_id — edit a tag (MANAGE_CHANNELS).
+#[utoipa::path(
+    patch,
+    path = "/channels/{channel_id}/forum/tags/{tag_id}",
+    request_body = UpdateForumTagDto,
+    params(
+        ("channel_id" = Uuid, Path, description = "Forum channel ID"),
+        ("tag_id" = Uuid, Path, description = "Tag ID")
+    ),
+    responses(
+        (status = 200, description = "Tag updated", body = ForumTag),
+        (status = 400, description = "Validation error"),
+        (status = 403, description = "Missing Manage Channels permission"),
+        (status = 404, description = "Channel or tag not found"),
+        (status = 409, description = "Tag name already used in this channel")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Forum"
+)]
+pub async fn update_forum_tag(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((channel_id, tag_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<UpdateForumTagDto>,
+) -> AppResult<Json<ForumTag>> {
+    let channel = fetch_forum_channel(&state, channel_id, auth.user_id()).await?;
+    require_manage_tags(&state, &channel, auth.user_id()).await?;
+
+    let name = req.name.as_deref().map(validate_tag_name).transpose()?;
+    if let Some(emoji) = &req.emoji {
+        validate_tag_emoji(emoji)?;
+    }
+    if req.position.is_some_and(|p| p < 0) {
+        return Err(AppError::Validation("Position must be ≥ 0".into()));
+    }
+
+    let tag = sqlx::query_as::<_, ForumTag>(&format!(
+        "UPDATE forum_tags
+         SET name = COALESCE($3, name),
+             emoji = COALESCE($4, emoji),
+             position = COALESCE($5, position)
+         WHERE id = $1 AND channel_id = $2
+         RETURNING {TAG_COLUMNS}"
+    ))
+    .bind(tag_id)
+    .bind(channel_id)
+    .bind(name)
+    .bind(&req.emoji)
+    .bind(req.position)
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|e| tag_name_conflict(e.into()))?
+    .ok_or_else(|| AppError::NotFound("Tag not found".into()))?;
+
+    Ok(Json(tag))
+}
+
+/// DELETE /channels/:channel_id/forum/tags/:tag_id — delete a tag and remove
+/// it from every post (MANAGE_CHANNELS).
+#[utoipa::path(
+    delete,
+    path = "/channels/{channel_id}/forum/tags/{tag_id}",
+    params(
+        ("channel_id" = Uuid, Path, description = "Forum channel ID"),
+        ("tag_id" = Uuid, Path, description = "Tag ID")
+    ),
+    responses(
+        (status = 204, description = "Tag deleted"),
+        (status = 403, description = "Missing Manage Channels permission"),
+        (status = 404, description = "Channel or tag not found")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Forum"
+)]
+pub async fn delete_forum_tag(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((channel_id, tag_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<StatusCode> {
+    let channel = fetch_forum_channel(&state, channel_id, auth.user_id()).await?;
+    require_manage_tags(&state, &channel, auth.user_id()).await?;
+
+    let mut tx = state.pool.begin().await?;
+    let result = sqlx::query("DELETE FROM forum_tags WHERE id = $1 AND channel_id = $2")
+        .bind(tag_id)
+        .bind(channel_id)
+        .execute(&mut *tx)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Tag not found".into()));
+    }
+    sqlx::query(
+        "UPDATE forum_posts SET tag_ids = array_remove(tag_ids, $1)
+         WHERE channel_id = $2 AND tag_ids @> ARRAY[$1]",
+    )
+    .bind(tag_id)
+    .bind(channel_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ============================================================================
+// Post handlers
+// ============================================================================
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ListForumPostsQuery {
+    /// Only posts carrying this tag.
+    pub tag_id: Option<Uuid>,
+    /// Cursor: return posts less recently active than the post with this ID.
+    /// An unknown ID returns an empty page.
+    pub before: Option<Uuid>,
+    /// Maximum number of posts to return (default 25, max 100).
+    pub limit: Option<i64>,
+}
+
+/// GET /channels/:channel_id/posts — posts ordered by latest activity.
+///
+/// A post's activity time is its newest thread reply, or its creation time
+/// if it has none. Each entry is the post's root message with `forum_post`
+/// and `thread_reply_count` filled in.
+#[utoipa::path(
+    get,
+    path = "/channels/{channel_id}/posts",
+    params(
+        ("channel_id" = Uuid, Path, description = "Forum channel ID"),
+        ListForumPostsQuery
+    ),
+    responses(
+        (status = 200, description = "Forum posts, most recently active first", body = Vec<MessageDto>),
+        (status = 400, description = "Not a forum channel"),
+        (status = 403, description = "No permission to view channel"),
+        (status = 404, description = "Channel not found")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Forum"
+)]
+pub async fn list_forum_posts(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(channel_id): Path<Uuid>,
+    Query(query): Query<ListForumPostsQuery>,
+) -> AppResult<Json<Vec<MessageDto>>> {
+    let channel = fetch_forum_channel(&state, channel_id, auth.user_id()).await?;
+    require_channel_permission(
+        &state.pool,
+        channel.server_id,
+        channel_id,
+        auth.user_id(),
+        PERMISSION_VIEW_CHANNEL,
+        "You don't have permission to view this channel",
+    )
+    .await?;
+
+    let limit = query.limit.unwrap_or(25).clamp(1, 100);
+
+    // Compound cursor on (last_activity_at, message_id), resolved from the
+    // cursor post so ties on activity time still page deterministically.
+    let posts = sqlx::query_as::<_, Message>(
+        "SELECT m.id, m.channel_id, m.author_id, m.content, m.reply_to,
+                m.mention_user_ids, m.mention_everyone, m.thread_id,
+                COALESCE(
+                  (SELECT COUNT(*)::int FROM messages t
+                   WHERE t.thread_id = m.id AND t.deleted = FALSE),
+                  0
+                ) AS thread_reply_count,
+                m.edited_at, m.deleted, m.created_at,
+                m.pinned, m.pinned_by, m.pinned_at
+         FROM forum_posts fp
+         JOIN messages m ON m.id = fp.message_id
+         WHERE fp.channel_id = $1
+           AND m.deleted = FALSE
+           AND ($2::uuid IS NULL OR fp.tag_ids @> ARRAY[$2::uuid])
+           AND ($3::uuid IS NULL OR (fp.last_activity_at, fp.message_id) < (
+               SELECT last_activity_at, message_id FROM forum_posts
+               WHERE message_id = $3 AND channel_id = $1
+           ))
+         ORDER BY fp.last_activity_at DESC, fp.message_id DESC
+         LIMIT $4",
+    )
+    .bind(channel_id)
+    .bind(query.tag_id)
+    .bind(query.before)
+    .bind(limit)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let enriched = enrich_messages(&state.pool, auth.user_id(), posts).await?;
+    Ok(Json(enriched))
+}
+
+/// PATCH /channels/:channel_id/posts/:post_id — change a post's title or tags
+/// (author, or MANAGE_MESSAGES in the channel). Broadcasts `MESSAGE_UPDATE`.
+#[utoipa::path(
+    patch,
+    path = "/channels/{channel_id}/posts/{post_id}",
+    request_body = UpdateForumPostDto,
+    params(
+        ("channel_id" = Uuid, Path, description = "Forum channel ID"),
+        ("post_id" = Uuid, Path, description = "Post (root message) ID")
+    ),
+    responses(
+        (status = 200, description = "Post updated", body = MessageDto),
+        (status = 400, description = "Validation error"),
+        (status = 403, description = "Not the author and missing Manage Messages"),
+        (status = 404, description = "Channel or post not found")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Forum"
+)]
+pub async fn update_forum_post(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((channel_id, post_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<UpdateForumPostDto>,
+) -> AppResult<Json<MessageDto>> {
+    let channel = fetch_forum_channel(&state, channel_id, auth.user_id()).await?;
+
+    let post = sqlx::query_as::<_, Message>(
+        "SELECT m.id, m.channel_id, m.author_id, m.content, m.reply_to,
+                m.mention_user_ids, m.mention_everyone, m.thread_id,
+                COALESCE(
+                  (SELECT COUNT(*)::int FROM messages t
+                   WHERE t.thread_id = m.id AND t.deleted = FALSE),
+                  0
+                ) AS thread_reply_count,
+                m.edited_at, m.deleted, m.created_at,
+                m.pinned, m.pinned_by, m.pinned_at
+         FROM forum_posts fp
+         JOIN messages m ON m.id = fp.message_id
+         WHERE fp.message_id = $1 AND fp.channel_id = $2 AND m.deleted = FALSE",
+    )
+    .bind(post_id)
+    .bind(channel_id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Post not found".into()))?;
+
+    if post.author_id != Some(auth.user_id()) {
+        let perms =
+            compute_channel_permissions(&state.pool, channel.server_id, channel_id, auth.user_id())
+                .await?;
+        if perms & PERMISSION_MANAGE_MESSAGES == 0 {
+            return Err(AppError::Forbidden(
+                "Only the author or a moderator can edit this post".into(),
+            ));
+        }
+    }
+
+    let title = req.title.as_deref().map(validate_post_title).transpose()?;
+    let tag_ids = match &req.tag_ids {
+        Some(ids) => Some(validate_post_tags(&state.pool, channel_id, ids).await?),
+        None => None,
+    };
+
+    sqlx::query(
+        "UPDATE forum_posts
+         SET title = COALESCE($2, title),
+             tag_ids = COALESCE($3, tag_ids)
+         WHERE message_id = $1",
+    )
+    .bind(post_id)
+    .bind(title)
+    .bind(tag_ids)
+    .execute(&state.pool)
+    .await?;
+
+    let enriched = enrich_messages(&state.pool, auth.user_id(), vec![post]).await?;
+    let dto = enriched
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::Internal)?;
+
+    match serde_json::to_value(&dto) {
+        Ok(payload) => {
+            broadcast_to_channel(
+                &state,
+                channel.server_id,
+                channel.id,
+                EVENT_MESSAGE_UPDATE,
+                payload.clone(),
+            )
+            .await;
+            dispatch_event(&state, channel.server_id, "message.updated", payload).await;
+        }
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to serialize MessageDto for broadcast");
+        }
+    }
+
+    Ok(Json(dto))
+}
@@ -3,26 +3,31 @@ use axum::{
     http::StatusCode,
     Json,
 };
-use chrono::Utc;
 use serde::Deserialize;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
-use super::shared::{fetch_channel_by_id, require_member};
+use super::shared::{fetch_channel_by_id, fetch_server, require_member};
 use crate::{
     auth::AuthUser,
     error::{AppError, AppResult},
-    models::ChannelType,
-    state::{AppState, GoLiveSession},
+    models::{ChannelType, GoLiveSession, GoLiveSettings, UpdateGoLiveSettingsRequest},
+    state::AppState,
     websocket::{
         broadcast_to_server,
-        events::{EVENT_GO_LIVE_START, EVENT_GO_LIVE_STOP},
+        events::{EVENT_GO_LIVE_START, EVENT_GO_LIVE_STOP, EVENT_GO_LIVE_VIEWER_UPDATE},
     },
 };
 
-/// Allowed quality tiers for a Go Live session.
+/// Allowed quality tiers for a Go Live session, lowest first.
 const VALID_QUALITIES: &[&str] = &["480p", "720p", "1080p"];
 
+/// Session columns plus the derived viewer count; callers append a WHERE.
+const SESSION_SELECT: &str = "SELECT g.channel_id, g.broadcaster_id, g.quality, g.started_at,
+            (SELECT COUNT(*) FROM go_live_viewers v WHERE v.channel_id = g.channel_id)
+                AS viewer_count
+     FROM go_live_sessions g";
+
 // ============================================================================
 // Request / response types
 // ============================================================================
@@ -46,11 +51,19 @@ fn require_voice_channel(channel: &crate::models::Channel) -> AppResult<()> {
     Ok(())
 }
 
+fn quality_rank(quality: &str) -> AppResult<usize> {
+    VALID_QUALITIES
+        .iter()
+        .position(|q| *q == quality)
+        .ok_or_else(|| AppError::Validation("quality must be one of: 480p, 720p, 1080p".into()))
+}
+
 /// Verify the requesting user is currently in the target voice channel.
 async fn require_in_voice_channel(
     state: &AppState,
     user_id: Uuid,
     channel_id: Uuid,
+    message: &str,
 ) -> AppResult<()> {
     let in_channel: Option<bool> =
         sqlx::query_scalar("SELECT TRUE FROM voice_states WHERE user_id = $1 AND channel_id = $2")
@@ -60,21 +73,177 @@ async fn require_in_voice_channel(
             .await?;
 
     if in_channel.is_none() {
-        return Err(AppError::Validation(
-            "You must be in the voice channel to go live".into(),
-        ));
+        return Err(AppError::Validation(message.into()));
+    }
+    Ok(())
+}
+
+async fn fetch_session(
+    pool: &sqlx::PgPool,
+    channel_id: Uuid,
+) -> Result<Option<GoLiveSession>, sqlx::Error> {
+    sqlx::query_as::<_, GoLiveSession>(&format!("{SESSION_SELECT} WHERE g.channel_id = $1"))
+        .bind(channel_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Load a server's Go Live limits, falling back to the defaults when the
+/// owner has never configured them.
+async fn fetch_settings(pool: &sqlx::PgPool, server_id: Uuid) -> AppResult<GoLiveSettings> {
+    let settings = sqlx::query_as::<_, GoLiveSettings>(
+        "SELECT server_id, max_concurrent_streams, max_quality, high_quality_role_ids
+         FROM go_live_settings WHERE server_id = $1",
+    )
+    .bind(server_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(settings.unwrap_or(GoLiveSettings {
+        server_id,
+        max_concurrent_streams: 0,
+        max_quality: "1080p".into(),
+        high_quality_role_ids: Vec::new(),
+    }))
+}
+
+/// Reject `quality` if it exceeds the server cap and the user is neither the
+/// owner nor holds one of the exempt roles.
+async fn check_quality_allowed(
+    state: &AppState,
+    settings: &GoLiveSettings,
+    owner_id: Uuid,
+    user_id: Uuid,
+    quality: &str,
+) -> AppResult<()> {
+    if quality_rank(quality)? <= quality_rank(&settings.max_quality)? || user_id == owner_id {
+        return Ok(());
+    }
+
+    let exempt: bool = sqlx::query_scalar(
+        "SELECT EXISTS (
+             SELECT 1 FROM member_roles
+             WHERE server_id = $1 AND user_id = $2 AND role_id = ANY($3)
+         )",
+    )
+    .bind(settings.server_id)
+    .bind(user_id)
+    .bind(&settings.high_quality_role_ids)
+    .fetch_one(&state.pool)
+    .await?;
+
+    if !exempt {
+        return Err(AppError::Forbidden(format!(
+            "Your roles do not allow streaming above {}",
+            settings.max_quality
+        )));
     }
     Ok(())
 }
 
+/// Broadcast the current viewer count of `channel_id`'s session, if it is
+/// still live.
+async fn broadcast_viewer_update(state: &AppState, server_id: Uuid, channel_id: Uuid) {
+    match fetch_session(&state.pool, channel_id).await {
+        Ok(Some(session)) => {
+            let payload = serde_json::json!({
+                "channel_id":     session.channel_id,
+                "broadcaster_id": session.broadcaster_id,
+                "viewer_count":   session.viewer_count,
+            });
+            broadcast_to_server(state, server_id, EVENT_GO_LIVE_VIEWER_UPDATE, payload).await;
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::warn!(
+                channel_id = %channel_id,
+                error      = ?e,
+                "Failed to load Go Live session for viewer update"
+            );
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct EndedGoLiveRow {
+    channel_id: Uuid,
+    server_id: Uuid,
+}
+
+/// End `user_id`'s broadcast in `channel_id` and stop them watching it.
+///
+/// Must be called before the user's voice state in `channel_id` is removed
+/// or moved to another channel. The foreign keys onto `voice_states` would
+/// drop the rows anyway, but silently — this broadcasts `GO_LIVE_STOP` and
+/// the new viewer count so clients do not keep showing a stale stream.
+/// Failures are logged, never returned, so a voice leave is never blocked by
+/// Go Live cleanup.
+pub async fn end_go_live_for_user(state: &AppState, user_id: Uuid, channel_id: Uuid) {
+    let ended = sqlx::query_as::<_, EndedGoLiveRow>(
+        "DELETE FROM go_live_sessions g
+         USING channels c
+         WHERE c.id = g.channel_id AND g.broadcaster_id = $1 AND g.channel_id = $2
+         RETURNING g.channel_id, c.server_id",
+    )
+    .bind(user_id)
+    .bind(channel_id)
+    .fetch_optional(&state.pool)
+    .await;
+
+    match ended {
+        Ok(Some(row)) => {
+            let payload = serde_json::json!({
+                "channel_id":     row.channel_id,
+                "broadcaster_id": user_id,
+            });
+            broadcast_to_server(state, row.server_id, EVENT_GO_LIVE_STOP, payload).await;
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::warn!(
+                user_id = %user_id,
+                error   = ?e,
+                "Failed to end Go Live session during voice cleanup"
+            );
+        }
+    }
+
+    let watched = sqlx::query_as::<_, EndedGoLiveRow>(
+        "DELETE FROM go_live_viewers v
+         USING channels c
+         WHERE c.id = v.channel_id AND v.user_id = $1 AND v.channel_id = $2
+         RETURNING v.channel_id, c.server_id",
+    )
+    .bind(user_id)
+    .bind(channel_id)
+    .fetch_optional(&state.pool)
+    .await;
+
+    match watched {
+        Ok(Some(row)) => {
+            broadcast_viewer_update(state, row.server_id, row.channel_id).await;
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::warn!(
+                user_id = %user_id,
+                error   = ?e,
+                "Failed to remove Go Live viewer during voice cleanup"
+            );
+        }
+    }
+}
+
 // ============================================================================
 // Handlers
 // ============================================================================
 
 /// POST /channels/:channel_id/go-live — start a Go Live broadcast.
 ///
-/// Enforces: one broadcaster per channel, broadcaster must be in the channel.
-/// Broadcasts `GO_LIVE_START` to all server members on success.
+/// Enforces: one broadcaster per channel, broadcaster must be in the channel,
+/// and the server's stream cap and quality cap. Calling it again while live
+/// changes the quality. Broadcasts `GO_LIVE_START` to all server members on
+/// success.
 #[utoipa::path(
     post,
     path = "/channels/{channel_id}/go-live",
@@ -84,6 +253,8 @@ async fn require_in_voice_channel(
     request_body = StartGoLiveRequest,
     responses(
         (status = 201, description = "Go Live session started", body = GoLiveSession),
+        (status = 400, description = "Not in the channel, channel already live, or stream cap reached"),
+        (status = 403, description = "Quality above the server cap for the caller's roles"),
     ),
     security(("bearer_auth" = [])),
     tag = "GoLive"
@@ -97,37 +268,82 @@ pub async fn start_go_live(
     let channel = fetch_channel_by_id(&state.pool, channel_id).await?;
     require_member(&state.pool, channel.server_id, auth.user_id()).await?;
     require_voice_channel(&channel)?;
-    require_in_voice_channel(&state, auth.user_id(), channel_id).await?;
+    require_in_voice_channel(
+        &state,
+        auth.user_id(),
+        channel_id,
+        "You must be in the voice channel to go live",
+    )
+    .await?;
 
     let quality = req.quality.unwrap_or_else(|| "720p".to_string());
+    quality_rank(&quality)?;
 
-    if !VALID_QUALITIES.contains(&quality.as_str()) {
-        return Err(AppError::Validation(
-            "quality must be one of: 480p, 720p, 1080p".into(),
-        ));
-    }
+    let server = fetch_server(&state.pool, channel.server_id).await?;
+    let settings = fetch_settings(&state.pool, server.id).await?;
+    check_quality_allowed(&state, &settings, server.owner_id, auth.user_id(), &quality).await?;
+
+    // Lock the server row so concurrent starts cannot both slip under the
+    // stream cap.
+    let mut tx = state.pool.begin().await?;
+    sqlx::query("SELECT 1 FROM servers WHERE id = $1 FOR UPDATE")
+        .bind(server.id)
+        .execute(&mut *tx)
+        .await?;
 
-    let session = {
-        let mut sessions = state.go_live_sessions.write().await;
+    let existing: Option<Uuid> =
+        sqlx::query_scalar("SELECT broadcaster_id FROM go_live_sessions WHERE channel_id = $1")
+            .bind(channel_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+    match existing {
+        Some(broadcaster_id) if broadcaster_id != auth.user_id() => {
+            return Err(AppError::Validation(
+                "Another user is already broadcasting in this channel".into(),
+            ));
+        }
+        // Caller is already the broadcaster — only the quality changes.
+        Some(_) => {}
+        None if settings.max_concurrent_streams > 0 => {
+            let live: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*)
+                 FROM go_live_sessions g
+                 JOIN channels c ON c.id = g.channel_id
+                 WHERE c.server_id = $1",
+            )
+            .bind(server.id)
+            .fetch_one(&mut *tx)
+            .await?;
 
-        // Reject if someone else is already live in this channel.
-        if let Some(existing) = sessions.get(&channel_id) {
-            if existing.broadcaster_id != auth.user_id() {
-                return Err(AppError::Validation(
-                    "Another user is already broadcasting in this channel".into(),
-                ));
+            if live >= i64::from(settings.max_concurrent_streams) {
+                return Err(AppError::Validation(format!(
+                    "This server allows at most {} concurrent streams",
+                    settings.max_concurrent_streams
+                )));
             }
-            // Caller is already the broadcaster — update quality and return.
         }
+        None => {}
+    }
+
+    sqlx::query(
+        "INSERT INTO go_live_sessions (channel_id, broadcaster_id, quality)
+         VALUES ($1, $2, $3)
+         ON CONFLICT (channel_id) DO UPDATE SET quality = EXCLUDED.quality",
+    )
+    .bind(channel_id)
+    .bind(auth.user_id())
+    .bind(&quality)
+    .execute(&mut *tx)
+    .await?;
+
+    let session =
+        sqlx::query_as::<_, GoLiveSession>(&format!("{SESSION_SELECT} WHERE g.channel_id = $1"))
+            .bind(channel_id)
+            .fetch_one(&mut *tx)
+            .await?;
 
-        let session = GoLiveSession {
-            broadcaster_id: auth.user_id(),
-            quality: quality.clone(),
-            started_at: Utc::now(),
-        };
-        sessions.insert(channel_id, session.clone());
-        session
-    };
+    tx.commit().await?;
 
     // Broadcast to all server members so viewers can show the Go Live banner.
     let payload = serde_json::json!({
@@ -143,7 +359,7 @@ pub async fn start_go_live(
 
 /// DELETE /channels/:channel_id/go-live — end the active Go Live broadcast.
 ///
-/// Only the current broadcaster (or a server admin) may stop the session.
+/// Only the current broadcaster may stop the session.
 /// Broadcasts `GO_LIVE_STOP` to all server members.
 #[utoipa::path(
     delete,
@@ -167,19 +383,32 @@ pub async fn stop_go_live(
     require_member(&state.pool, channel.server_id, auth.user_id()).await?;
     require_voice_channel(&channel)?;
 
-    {
-        let mut sessions = state.go_live_sessions.write().await;
-        let session = sessions.get(&channel_id).ok_or_else(|| {
-            AppError::NotFound("No active Go Live session in this channel".into())
-        })?;
+    let broadcaster_id: Uuid =
+        sqlx::query_scalar("SELECT broadcaster_id FROM go_live_sessions WHERE channel_id = $1")
+            .bind(channel_id)
+            .fetch_optional(&state.pool)
+            .await?
+            .ok_or_else(|| {
+                AppError::NotFound("No active Go Live session in this channel".into())
+            })?;
 
-        if session.broadcaster_id != auth.user_id() {
-            return Err(AppError::Forbidden(
-                "Only the broadcaster can end the Go Live session".into(),
-            ));
-        }
+    if broadcaster_id != auth.user_id() {
+        return Err(AppError::Forbidden(
+            "Only the broadcaster can end the Go Live session".into(),
+        ));
+    }
+
+    let result =
+        sqlx::query("DELETE FROM go_live_sessions WHERE channel_id = $1 AND broadcaster_id = $2")
+            .bind(channel_id)
+            .bind(auth.user_id())
+            .execute(&state.pool)
+            .await?;
 
-        sessions.remove(&channel_id);
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(
+            "No active Go Live session in this channel".into(),
+        ));
     }
 
     let payload = serde_json::json!({
@@ -201,7 +430,7 @@ pub async fn stop_go_live(
         ("channel_id" = Uuid, Path, description = "Channel ID"),
     ),
     responses(
-        (status = 200, description = "Active Go Live session"),
+        (status = 200, description = "Active Go Live session", body = GoLiveSession),
         (status = 404, description = "No active Go Live session"),
     ),
     security(("bearer_auth" = [])),
@@ -211,19 +440,220 @@ pub async fn get_go_live(
     State(state): State<AppState>,
     auth: AuthUser,
     Path(channel_id): Path<Uuid>,
-) -> AppResult<Json<serde_json::Value>> {
+) -> AppResult<Json<GoLiveSession>> {
+    let channel = fetch_channel_by_id(&state.pool, channel_id).await?;
+    require_member(&state.pool, channel.server_id, auth.user_id()).await?;
+    require_voice_channel(&channel)?;
+
+    fetch_session(&state.pool, channel_id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound("No active Go Live session".into()))
+}
+
+/// POST /channels/:channel_id/go-live/viewers — start watching the broadcast.
+///
+/// The viewer must be in the voice channel and cannot be the broadcaster.
+/// Idempotent. Broadcasts `GO_LIVE_VIEWER_UPDATE` when the count changes.
+#[utoipa::path(
+    post,
+    path = "/channels/{channel_id}/go-live/viewers",
+    params(
+        ("channel_id" = Uuid, Path, description = "Channel ID"),
+    ),
+    responses(
+        (status = 200, description = "Watching; returns the session with its new viewer count", body = GoLiveSession),
+        (status = 400, description = "Not in the channel, or caller is the broadcaster"),
+        (status = 404, description = "No active Go Live session"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "GoLive"
+)]
+pub async fn join_go_live(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(channel_id): Path<Uuid>,
+) -> AppResult<Json<GoLiveSession>> {
+    let channel = fetch_channel_by_id(&state.pool, channel_id).await?;
+    require_member(&state.pool, channel.server_id, auth.user_id()).await?;
+    require_voice_channel(&channel)?;
+    require_in_voice_channel(
+        &state,
+        auth.user_id(),
+        channel_id,
+        "You must be in the voice channel to watch",
+    )
+    .await?;
+
+    let session = fetch_session(&state.pool, channel_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No active Go Live session".into()))?;
+
+    if session.broadcaster_id == auth.user_id() {
+        return Err(AppError::Validation(
+            "You cannot watch your own broadcast".into(),
+        ));
+    }
+
+    let result = sqlx::query(
+        "INSERT INTO go_live_viewers (channel_id, user_id)
+         VALUES ($1, $2)
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(channel_id)
+    .bind(auth.user_id())
+    .execute(&state.pool)
+    .await?;
+
+    if result.rows_affected() > 0 {
+        broadcast_viewer_update(&state, channel.server_id, channel_id).await;
+    }
+
+    fetch_session(&state.pool, channel_id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound("No active Go Live session".into()))
+}
+
+/// DELETE /channels/:channel_id/go-live/viewers — stop watching the broadcast.
+///
+/// Returns 404 if the caller is not watching. Broadcasts
+/// `GO_LIVE_VIEWER_UPDATE`.
+#[utoipa::path(
+    delete,
+    path = "/channels/{channel_id}/go-live/viewers",
+    params(
+        ("channel_id" = Uuid, Path, description = "Channel ID"),
+    ),
+    responses(
+        (status = 204, description = "Stopped watching"),
+        (status = 404, description = "Not watching this broadcast"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "GoLive"
+)]
+pub async fn leave_go_live(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(channel_id): Path<Uuid>,
+) -> AppResult<StatusCode> {
     let channel = fetch_channel_by_id(&state.pool, channel_id).await?;
     require_member(&state.pool, channel.server_id, auth.user_id()).await?;
     require_voice_channel(&channel)?;
 
-    let sessions = state.go_live_sessions.read().await;
-    match sessions.get(&channel_id) {
-        Some(session) => Ok(Json(serde_json::json!({
-            "channel_id":     channel_id,
-            "broadcaster_id": session.broadcaster_id,
-            "quality":        session.quality,
-            "started_at":     session.started_at,
-        }))),
-        None => Err(AppError::NotFound("No active Go Live session".into())),
+    let result = sqlx::query("DELETE FROM go_live_viewers WHERE channel_id = $1 AND user_id = $2")
+        .bind(channel_id)
+        .bind(auth.user_id())
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Not watching this broadcast".into()));
+    }
+
+    broadcast_viewer_update(&state, channel.server_id, channel_id).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /servers/:id/go-live/settings — the server's Go Live limits.
+///
+/// Visible to every member so clients can hide quality tiers they may not use.
+#[utoipa::path(
+    get,
+    path = "/servers/{id}/go-live/settings",
+    params(
+        ("id" = Uuid, Path, description = "Server ID"),
+    ),
+    responses(
+        (status = 200, description = "Go Live settings", body = GoLiveSettings),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "GoLive"
+)]
+pub async fn get_go_live_settings(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(server_id): Path<Uuid>,
+) -> AppResult<Json<GoLiveSettings>> {
+    require_member(&state.pool, server_id, auth.user_id()).await?;
+    Ok(Json(fetch_settings(&state.pool, server_id).await?))
+}
+
+/// PATCH /servers/:id/go-live/settings — upsert the server's Go Live limits.
+///
+/// Only the server owner can change them. Existing broadcasts are not ended
+/// when a cap is lowered; the cap applies to the next start.
+#[utoipa::path(
+    patch,
+    path = "/servers/{id}/go-live/settings",
+    params(
+        ("id" = Uuid, Path, description = "Server ID"),
+    ),
+    request_body = UpdateGoLiveSettingsRequest,
+    responses(
+        (status = 200, description = "Updated Go Live settings", body = GoLiveSettings),
+        (status = 403, description = "Not the server owner"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "GoLive"
+)]
+pub async fn update_go_live_settings(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(server_id): Path<Uuid>,
+    Json(req): Json<UpdateGoLiveSettingsRequest>,
+) -> AppResult<Json<GoLiveSettings>> {
+    let server = fetch_server(&state.pool, server_id).await?;
+    if auth.user_id() != server.owner_id {
+        return Err(AppError::Forbidden(
+            "Only the server owner can manage Go Live settings".into(),
+        ));
+    }
+
+    if req.max_concurrent_streams.is_some_and(|n| n < 0) {
+        return Err(AppError::Validation(
+            "max_concurrent_streams must be 0 (unlimited) or greater".into(),
+        ));
+    }
+    if let Some(max_quality) = &req.max_quality {
+        quality_rank(max_quality)?;
+    }
+
+    let mut role_ids = req.high_quality_role_ids;
+    if let Some(ids) = role_ids.as_mut() {
+        ids.sort_unstable();
+        ids.dedup();
+        let found: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM roles WHERE server_id = $1 AND id = ANY($2)")
+                .bind(server_id)
+                .bind(&*ids)
+                .fetch_one(&state.pool)
+                .await?;
+        if found != ids.len() as i64 {
+            return Err(AppError::Validation(
+                "high_quality_role_ids must be roles in this server".into(),
+            ));
+        }
     }
+
+    let settings = sqlx::query_as::<_, GoLiveSettings>(
+        "INSERT INTO go_live_settings
+             (server_id, max_concurrent_streams, max_quality, high_quality_role_ids)
+         VALUES ($1, COALESCE($2, 0), COALESCE($3, '1080p'), COALESCE($4, '{}'))
+         ON CONFLICT (server_id) DO UPDATE SET
+             max_concurrent_streams = COALESCE($2, go_live_settings.max_concurrent_streams),
+             max_quality            = COALESCE($3, go_live_settings.max_quality),
+             high_quality_role_ids  = COALESCE($4, go_live_settings.high_quality_role_ids),
+             updated_at             = NOW()
+         RETURNING server_id, max_concurrent_streams, max_quality, high_quality_role_ids",
+    )
+    .bind(server_id)
+    .bind(req.max_concurrent_streams)
+    .bind(req.max_quality)
+    .bind(role_ids)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(settings))
 }
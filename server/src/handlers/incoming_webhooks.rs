@@ -0,0 +1,631 @@
+//! Incoming webhooks: channel-bound URLs that external tools POST messages to.
+//!
+//! Management endpoints live under `/servers/:id/incoming-webhooks` and need
+//! the same permission as outgoing webhooks. The execute endpoint,
+//! `POST /webhooks/:webhook_id/:token`, is unauthenticated — the secret token
+//! in the URL is the credential. Posts are stored as ordinary messages with no
+//! `author_id`; the webhook identity and embeds are attached by
+//! `enrich_messages`.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use uuid::Uuid;
+
+use super::automod::{check_automod, MessageAuthor};
+use super::messages::{enrich_messages, resolve_mentions};
+use super::shared::{fetch_channel_by_id, require_member};
+use super::webhooks::{dispatch_event, require_manage_webhooks};
+use crate::{
+    auth::AuthUser,
+    bot_auth::{generate_bot_token, hash_bot_token},
+    error::{AppError, AppResult},
+    models::{
+        ChannelType, CreateIncomingWebhookDto, ExecuteIncomingWebhookDto, IncomingWebhook,
+        IncomingWebhookTokenResponse, Message, MessageDto, MessageEmbed, UpdateIncomingWebhookDto,
+    },
+    state::AppState,
+    websocket::{broadcast_to_channel, events::EVENT_MESSAGE_CREATE},
+};
+
+const MAX_INCOMING_WEBHOOKS_PER_SERVER: i64 = 10;
+const MAX_EMBEDS: usize = 10;
+const MAX_EMBED_FIELDS: usize = 25;
+/// Combined text budget across all embeds of one message.
+const MAX_EMBED_TOTAL_CHARS: usize = 6000;
+
+const INCOMING_WEBHOOK_COLUMNS: &str =
+    "id, server_id, channel_id, created_by, name, avatar_url, last_used_at, created_at";
+
+// ── Validation ────────────────────────────────────────────────────────────────
+
+fn validate_name(name: &str) -> AppResult<String> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > 80 {
+        return Err(AppError::Validation(
+            "Webhook name must be 1–80 characters".into(),
+        ));
+    }
+    Ok(name.to_string())
+}
+
+fn validate_http_url(field: &str, url: &str) -> AppResult<()> {
+    let lower = url.to_ascii_lowercase();
+    if !lower.starts_with("http://") && !lower.starts_with("https://") {
+        return Err(AppError::Validation(format!(
+            "{field} must use http:// or https://"
+        )));
+    }
+    if url.len() > 2000 {
+        return Err(AppError::Validation(format!(
+            "{field} must be ≤2000 characters"
+        )));
+    }
+    Ok(())
+}
+
+fn check_len(field: &str, value: &Option<String>, max: usize) -> AppResult<usize> {
+    let len = value.as_deref().map_or(0, |v| v.chars().count());
+    if len > max {
+        return Err(AppError::Validation(format!(
+            "{field} must be ≤{max} characters"
+        )));
+    }
+    Ok(len)
+}
+
+fn validate_embeds(embeds: &[MessageEmbed]) -> AppResult<()> {
+    if embeds.len() > MAX_EMBEDS {
+        return Err(AppError::Validation(format!(
+            "A message can have at most {MAX_EMBEDS} embeds"
+        )));
+    }
+
+    let mut total = 0;
+    for embed in embeds {
+        total += check_len("Embed title", &embed.title, 256)?;
+        total += check_len("Embed description", &embed.description, 4096)?;
+        total += check_len("Embed footer", &embed.footer, 2048)?;
+        for (field, url) in [
+            ("Embed url", &embed.url),
+            ("Embed image_url", &embed.image_url),
+            ("Embed thumbnail_url", &embed.thumbnail_url),
+        ] {
+            if let Some(url) = url {
+                validate_http_url(field, url)?;
+            }
+        }
+        if let Some(color) = embed.color {
+            if !(0..=0xFF_FFFF).contains(&color) {
+                return Err(AppError::Validation(
+                    "Embed color must be between 0 and 16777215".into(),
+                ));
+            }
+        }
+        if embed.fields.len() > MAX_EMBED_FIELDS {
+            return Err(AppError::Validation(format!(
+                "An embed can have at most {MAX_EMBED_FIELDS} fields"
+            )));
+        }
+        for f in &embed.fields {
+            let name_len = f.name.chars().count();
+            let value_len = f.value.chars().count();
+            if name_len == 0 || name_len > 256 || value_len == 0 || value_len > 1024 {
+                return Err(AppError::Validation(
+                    "Embed field names must be 1–256 and values 1–1024 characters".into(),
+                ));
+            }
+            total += name_len + value_len;
+        }
+        let has_content = total > 0
+            || embed.url.is_some()
+            || embed.image_url.is_some()
+            || embed.thumbnail_url.is_some();
+        if !has_content {
+            return Err(AppError::Validation("Embeds must not be empty".into()));
+        }
+    }
+
+    if total > MAX_EMBED_TOTAL_CHARS {
+        return Err(AppError::Validation(format!(
+            "Embeds must contain ≤{MAX_EMBED_TOTAL_CHARS} characters in total"
+        )));
+    }
+    Ok(())
+}
+
+/// Fetch a channel and require that it is a text channel of `server_id`.
+async fn require_text_channel(
+    pool: &sqlx::PgPool,
+    server_id: Uuid,
+    channel_id: Uuid,
+) -> AppResult<()> {
+    let channel = fetch_channel_by_id(pool, channel_id).await?;
+    if channel.server_id != server_id {
+        return Err(AppError::NotFound("Channel not found".into()));
+    }
+    if !matches!(channel.r#type, ChannelType::Text) {
+        return Err(AppError::Validation(
+            "Incoming webhooks can only post to text channels".into(),
+        ));
+    }
+    Ok(())
+}
+
+fn token_response(webhook: IncomingWebhook, token: String) -> IncomingWebhookTokenResponse {
+    let url = format!("/webhooks/{}/{}", webhook.id, token);
+    IncomingWebhookTokenResponse {
+        webhook,
+        token,
+        url,
+    }
+}
+
+/// Display identity of a message posted without a user account.
+pub struct WebhookIdentity<'a> {
+    /// `None` for server-generated posts such as automod alerts.
+    pub webhook_id: Option<Uuid>,
+    pub username: &'a str,
+    pub avatar_url: Option<&'a str>,
+}
+
+/// Insert a message with no `author_id`, attributed to `identity`, and return
+/// it enriched. Callers broadcast it themselves.
+pub async fn insert_webhook_message(
+    pool: &sqlx::PgPool,
+    server_id: Uuid,
+    channel_id: Uuid,
+    identity: WebhookIdentity<'_>,
+    content: &str,
+    embeds: &[MessageEmbed],
+) -> AppResult<MessageDto> {
+    let (mention_user_ids, mention_everyone) = resolve_mentions(pool, server_id, content).await?;
+    let embeds_json = serde_json::to_value(embeds).map_err(|_| AppError::Internal)?;
+
+    let mut tx = pool.begin().await?;
+    let message = sqlx::query_as::<_, Message>(
+        "INSERT INTO messages (channel_id, author_id, content, mention_user_ids, mention_everyone)
+         VALUES ($1, NULL, $2, $3, $4)
+         RETURNING id, channel_id, author_id, content, reply_to,
+                   mention_user_ids, mention_everyone, thread_id,
+                   0 AS thread_reply_count, edited_at, deleted, created_at",
+    )
+    .bind(channel_id)
+    .bind(content)
+    .bind(&mention_user_ids as &[Uuid])
+    .bind(mention_everyone)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO incoming_webhook_messages (message_id, webhook_id, username, avatar_url, embeds)
+         VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(message.id)
+    .bind(identity.webhook_id)
+    .bind(identity.username)
+    .bind(identity.avatar_url)
+    .bind(&embeds_json)
+    .execute(&mut *tx)
+    .await?;
+
+    if let Some(webhook_id) = identity.webhook_id {
+        sqlx::query("UPDATE incoming_webhooks SET last_used_at = NOW() WHERE id = $1")
+            .bind(webhook_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    // No calling user: the id only feeds poll vote lookup, and these posts
+    // never carry a poll.
+    enrich_messages(pool, Uuid::nil(), vec![message])
+        .await?
+        .into_iter()
+        .next()
+        .ok_or(AppError::Internal)
+}
+
+// ── POST /servers/:id/incoming-webhooks ───────────────────────────────────────
+
+#[utoipa::path(
+    post,
+    path = "/servers/{id}/incoming-webhooks",
+    params(("id" = Uuid, Path, description = "Server ID")),
+    request_body = CreateIncomingWebhookDto,
+    responses(
+        (status = 201, description = "Incoming webhook created", body = IncomingWebhookTokenResponse),
+        (status = 400, description = "Validation error"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Channel not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Webhooks"
+)]
+pub async fn create_incoming_webhook(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(server_id): Path<Uuid>,
+    Json(payload): Json<CreateIncomingWebhookDto>,
+) -> AppResult<(StatusCode, Json<IncomingWebhookTokenResponse>)> {
+    require_member(&state.pool, server_id, auth.user_id()).await?;
+    require_manage_webhooks(&state.pool, server_id, auth.user_id()).await?;
+
+    let name = validate_name(&payload.name)?;
+    let avatar_url = payload.avatar_url.filter(|u| !u.is_empty());
+    if let Some(url) = &avatar_url {
+        validate_http_url("Avatar URL", url)?;
+    }
+    require_text_channel(&state.pool, server_id, payload.channel_id).await?;
+
+    let count: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM incoming_webhooks WHERE server_id = $1")
+            .bind(server_id)
+            .fetch_one(&state.pool)
+            .await?;
+    if count >= MAX_INCOMING_WEBHOOKS_PER_SERVER {
+        return Err(AppError::Validation(format!(
+            "Servers are limited to {MAX_INCOMING_WEBHOOKS_PER_SERVER} incoming webhooks"
+        )));
+    }
+
+    let token = generate_bot_token();
+    let webhook = sqlx::query_as::<_, IncomingWebhook>(&format!(
+        "INSERT INTO incoming_webhooks (server_id, channel_id, created_by, name, avatar_url, token_hash)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         RETURNING {INCOMING_WEBHOOK_COLUMNS}"
+    ))
+    .bind(server_id)
+    .bind(payload.channel_id)
+    .bind(auth.user_id())
+    .bind(&name)
+    .bind(&avatar_url)
+    .bind(hash_bot_token(&token))
+    .fetch_one(&state.pool)
+    .await?;
+
+    tracing::info!(
+        webhook_id = %webhook.id,
+        server_id = %server_id,
+        channel_id = %webhook.channel_id,
+        created_by = %auth.user_id(),
+        "Incoming webhook created"
+    );
+
+    Ok((StatusCode::CREATED, Json(token_response(webhook, token))))
+}
+
+// ── GET /servers/:id/incoming-webhooks ────────────────────────────────────────
+
+#[utoipa::path(
+    get,
+    path = "/servers/{id}/incoming-webhooks",
+    params(("id" = Uuid, Path, description = "Server ID")),
+    responses(
+        (status = 200, description = "List of incoming webhooks", body = Vec<IncomingWebhook>),
+        (status = 403, description = "Insufficient permissions"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Webhooks"
+)]
+pub async fn list_incoming_webhooks(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(server_id): Path<Uuid>,
+) -> AppResult<Json<Vec<IncomingWebhook>>> {
+    require_member(&state.pool, server_id, auth.user_id()).await?;
+    require_manage_webhooks(&state.pool, server_id, auth.user_id()).await?;
+
+    let webhooks = sqlx::query_as::<_, IncomingWebhook>(&format!(
+        "SELECT {INCOMING_WEBHOOK_COLUMNS} FROM incoming_webhooks
+         WHERE server_id = $1 ORDER BY created_at ASC"
+    ))
+    .bind(server_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(webhooks))
+}
+
+// ── PATCH /servers/:id/incoming-webhooks/:webhook_id ──────────────────────────
+
+#[utoipa::path(
+    patch,
+    path = "/servers/{id}/incoming-webhooks/{webhook_id}",
+    params(
+        ("id" = Uuid, Path, description = "Server ID"),
+        ("webhook_id" = Uuid, Path, description = "Incoming webhook ID"),
+    ),
+    request_body = UpdateIncomingWebhookDto,
+    responses(
+        (status = 200, description = "Incoming webhook updated", body = IncomingWebhook),
+        (status = 400, description = "Validation error"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Webhook or channel not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Webhooks"
+)]
+pub async fn update_incoming_webhook(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path((server_id, webhook_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<UpdateIncomingWebhookDto>,
+) -> AppResult<Json<IncomingWebhook>> {
+    require_member(&state.pool, server_id, auth.user_id()).await?;
+    require_manage_webhooks(&state.pool, server_id, auth.user_id()).await?;
+
+    let existing = sqlx::query_as::<_, IncomingWebhook>(&format!(
+        "SELECT {INCOMING_WEBHOOK_COLUMNS} FROM incoming_webhooks
+         WHERE id = $1 AND server_id = $2"
+    ))
+    .bind(webhook_id)
+    .bind(server_id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Webhook not found".into()))?;
+
+    let name = match &payload.name {
+        Some(n) => validate_name(n)?,
+        None => existing.name,
+    };
+    let avatar_url = match payload.avatar_url {
+        Some(u) if u.is_empty() => None,
+        Some(u) => {
+            validate_http_url("Avatar URL", &u)?;
+            Some(u)
+        }
+        None => existing.avatar_url,
+    };
+    let channel_id = match payload.channel_id {
+        Some(c) => {
+            require_text_channel(&state.pool, server_id, c).await?;
+            c
+        }
+        None => existing.channel_id,
+    };
+
+    let updated = sqlx::query_as::<_, IncomingWebhook>(&format!(
+        "UPDATE incoming_webhooks SET name = $1, avatar_url = $2, channel_id = $3
+         WHERE id = $4 AND server_id = $5
+         RETURNING {INCOMING_WEBHOOK_COLUMNS}"
+    ))
+    .bind(&name)
+    .bind(&avatar_url)
+    .bind(channel_id)
+    .bind(webhook_id)
+    .bind(server_id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    tracing::info!(
+        webhook_id = %webhook_id,
+        updated_by = %auth.user_id(),
+        "Incoming webhook updated"
+    );
+
+    Ok(Json(updated))
+}
+
+// ── DELETE /servers/:id/incoming-webhooks/:webhook_id ─────────────────────────
+
+#[utoipa::path(
+    delete,
+    path = "/servers/{id}/incoming-webhooks/{webhook_id}",
+    params(
+        ("id" = Uuid, Path, description = "Server ID"),
+        ("webhook_id" = Uuid, Path, description = "Incoming webhook ID"),
+    ),
+    responses(
+        (status = 204, description = "Incoming webhook deleted"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Webhook not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Webhooks"
+)]
+pub async fn delete_incoming_webhook(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path((server_id, webhook_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<StatusCode> {
+    require_member(&state.pool, server_id, auth.user_id()).await?;
+    require_manage_webhooks(&state.pool, server_id, auth.user_id()).await?;
+
+    let rows = sqlx::query("DELETE FROM incoming_webhooks WHERE id = $1 AND server_id = $2")
+        .bind(webhook_id)
+        .bind(server_id)
+        .execute(&state.pool)
+        .await?
+        .rows_affected();
+
+    if rows == 0 {
+        return Err(AppError::NotFound("Webhook not found".into()));
+    }
+
+    tracing::info!(
+        webhook_id = %webhook_id,
+        deleted_by = %auth.user_id(),
+        "Incoming webhook deleted"
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ── POST /servers/:id/incoming-webhooks/:webhook_id/token ─────────────────────
+
+/// Replace the webhook's secret token. The old URL stops working immediately.
+#[utoipa::path(
+    post,
+    path = "/servers/{id}/incoming-webhooks/{webhook_id}/token",
+    params(
+        ("id" = Uuid, Path, description = "Server ID"),
+        ("webhook_id" = Uuid, Path, description = "Incoming webhook ID"),
+    ),
+    responses(
+        (status = 200, description = "Token regenerated", body = IncomingWebhookTokenResponse),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Webhook not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Webhooks"
+)]
+pub async fn regenerate_incoming_webhook_token(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path((server_id, webhook_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<Json<IncomingWebhookTokenResponse>> {
+    require_member(&state.pool, server_id, auth.user_id()).await?;
+    require_manage_webhooks(&state.pool, server_id, auth.user_id()).await?;
+
+    let token = generate_bot_token();
+    let webhook = sqlx::query_as::<_, IncomingWebhook>(&format!(
+        "UPDATE incoming_webhooks SET token_hash = $1
+         WHERE id = $2 AND server_id = $3
+         RETURNING {INCOMING_WEBHOOK_COLUMNS}"
+    ))
+    .bind(hash_bot_token(&token))
+    .bind(webhook_id)
+    .bind(server_id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Webhook not found".into()))?;
+
+    tracing::info!(
+        webhook_id = %webhook_id,
+        regenerated_by = %auth.user_id(),
+        "Incoming webhook token regenerated"
+    );
+
+    Ok(Json(token_response(webhook, token)))
+}
+
+// ── POST /webhooks/:webhook_id/:token ─────────────────────────────────────────
+
+/// Post a message into the webhook's channel. No bearer token — the secret
+/// token in the URL authenticates the caller.
+#[utoipa::path(
+    post,
+    path = "/webhooks/{webhook_id}/{token}",
+    params(
+        ("webhook_id" = Uuid, Path, description = "Incoming webhook ID"),
+        ("token" = String, Path, description = "Secret webhook token"),
+    ),
+    request_body = ExecuteIncomingWebhookDto,
+    responses(
+        (status = 201, description = "Message posted", body = MessageDto),
+        (status = 400, description = "Validation error"),
+        (status = 403, description = "Blocked by automod"),
+        (status = 404, description = "Unknown webhook or invalid token"),
+    ),
+    tag = "Webhooks"
+)]
+pub async fn execute_incoming_webhook(
+    State(state): State<AppState>,
+    Path((webhook_id, token)): Path<(Uuid, String)>,
+    Json(payload): Json<ExecuteIncomingWebhookDto>,
+) -> AppResult<(StatusCode, Json<MessageDto>)> {
+    // Same 404 for an unknown id and a wrong token, so ids can't be probed.
+    let webhook = sqlx::query_as::<_, IncomingWebhook>(&format!(
+        "SELECT {INCOMING_WEBHOOK_COLUMNS} FROM incoming_webhooks
+         WHERE id = $1 AND token_hash = $2"
+    ))
+    .bind(webhook_id)
+    .bind(hash_bot_token(&token))
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Unknown webhook".into()))?;
+
+    let content = payload.content.unwrap_or_default();
+    if content.chars().count() > 4000 {
+        return Err(AppError::Validation(
+            "Message content must be ≤4 000 characters".into(),
+        ));
+    }
+    if content.trim().is_empty() && payload.embeds.is_empty() {
+        return Err(AppError::Validation(
+            "A webhook message needs content or at least one embed".into(),
+        ));
+    }
+    validate_embeds(&payload.embeds)?;
+
+    let username = match payload.username {
+        Some(u) => validate_name(&u)?,
+        None => webhook.name.clone(),
+    };
+    let avatar_url = match payload.avatar_url.filter(|u| !u.is_empty()) {
+        Some(u) => {
+            validate_http_url("Avatar URL", &u)?;
+            Some(u)
+        }
+        None => webhook.avatar_url.clone(),
+    };
+
+    let author = MessageAuthor::Webhook(webhook.id);
+
+    // Pre-insert automod check (word filter, duplicate detection)
+    check_automod(
+        &state,
+        webhook.server_id,
+        webhook.channel_id,
+        author,
+        &username,
+        &content,
+        None,
+    )
+    .await?;
+
+    let dto = insert_webhook_message(
+        &state.pool,
+        webhook.server_id,
+        webhook.channel_id,
+        WebhookIdentity {
+            webhook_id: Some(webhook.id),
+            username: &username,
+            avatar_url: avatar_url.as_deref(),
+        },
+        &content,
+        &payload.embeds,
+    )
+    .await?;
+    let message_id = dto.id;
+
+    // Post-insert automod check (spam detection)
+    check_automod(
+        &state,
+        webhook.server_id,
+        webhook.channel_id,
+        author,
+        &username,
+        &content,
+        Some(message_id),
+    )
+    .await?;
+
+    match serde_json::to_value(&dto) {
+        Ok(payload) => {
+            broadcast_to_channel(
+                &state,
+                webhook.server_id,
+                webhook.channel_id,
+                EVENT_MESSAGE_CREATE,
+                payload.clone(),
+            )
+            .await;
+            dispatch_event(&state, webhook.server_id, "message.created", payload).await;
+        }
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to serialize MessageDto for broadcast");
+        }
+    }
+
+    tracing::debug!(
+        webhook_id = %webhook.id,
+        message_id = %message_id,
+        "Incoming webhook message posted"
+    );
+
+    Ok((StatusCode::CREATED, Json(dto)))
+}
@@ -10,12 +10,14 @@ use super::shared::{require_member, require_permission, PERMISSION_CREATE_INVITE
 use crate::{
     auth::AuthUser,
     error::{AppError, AppResult},
-    handlers::audit::log_action,
+    handlers::{audit::log_action, email::require_mailer},
+    mail::{self, Template},
     models::{AuditAction, CreateAuditLog, CreateInviteRequest, InvitePreviewDto, ServerInvite},
     state::AppState,
     websocket::{
         broadcast_to_server,
         events::{EVENT_INVITE_CREATE, EVENT_INVITE_DELETE},
+        invalidate_channel_viewers,
     },
 };
 
@@ -41,6 +43,7 @@ fn generate_invite_code() -> String {
         (status = 201, description = "Invite created", body = ServerInvite),
         (status = 400, description = "Validation error"),
         (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "`email` given but email is not configured"),
     ),
     security(("bearer_auth" = [])),
     tag = "Invites"
@@ -79,6 +82,17 @@ pub async fn create_invite(
         }
     }
 
+    // Check the recipient before creating anything.
+    let mailer = match &req.email {
+        Some(email) => {
+            if !validator::ValidateEmail::validate_email(email) {
+                return Err(AppError::Validation("email is not a valid address".into()));
+            }
+            Some(require_mailer(&state)?)
+        }
+        None => None,
+    };
+
     let expires_at = req
         .expires_in_hours
         .map(|h| chrono::Utc::now() + chrono::Duration::hours(h));
@@ -128,12 +142,37 @@ pub async fn create_invite(
             action: AuditAction::InviteCreate,
             target_type: Some("invite".into()),
             target_id: Some(invite.id),
-            details: json!({ "code": &invite.code, "max_uses": invite.max_uses }),
+            details: json!({
+                "code": &invite.code,
+                "max_uses": invite.max_uses,
+                "emailed": req.email.is_some(),
+            }),
             ip_address: None,
         },
     )
     .await;
 
+    if let (Some(mailer), Some(email)) = (mailer, &req.email) {
+        let server_name: String = sqlx::query_scalar("SELECT name FROM servers WHERE id = $1")
+            .bind(server_id)
+            .fetch_one(&state.pool)
+            .await?;
+        let link = mailer.link(&format!("/invite/{}", invite.code));
+        mail::send(
+            &state,
+            mailer.compose(
+                email,
+                Template::Invite,
+                &[
+                    ("inviter", auth.username()),
+                    ("server_name", &server_name),
+                    ("link", &link),
+                    ("code", &invite.code),
+                ],
+            ),
+        );
+    }
+
     // Broadcast to server members.
     match serde_json::to_value(&invite) {
         Ok(payload) => {
@@ -423,6 +462,7 @@ pub async fn accept_invite(
     }
 
     tx.commit().await?;
+    invalidate_channel_viewers(&state, invite.server_id).await;
 
     Ok((
         StatusCode::CREATED,
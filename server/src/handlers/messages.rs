@@ -9,7 +9,8 @@ use utoipa::ToSchema;
 use uuid::Uuid;
 use validator::Validate;
 
-use super::automod::{check_automod, check_timeout};
+use super::automod::{check_automod, check_timeout, MessageAuthor};
+use super::forum::{validate_post_tags, validate_post_title};
 use super::shared::{
     fetch_channel_by_id, fetch_message, fetch_message_including_deleted, fetch_server,
     require_channel_permission, require_member, validation_error, PERMISSION_SEND_MESSAGES,
@@ -19,10 +20,14 @@ use super::webhooks::dispatch_event;
 use crate::{
     auth::AuthUser,
     error::{AppError, AppResult},
-    models::{CreateMessageDto, Message, MessageDto, PollDto, ServerEventDto, UpdateMessageDto},
+    models::{
+        ChannelType, CreateMessageDto, ForumPostInfo, Message, MessageDto, MessageEmbed, PollDto,
+        ServerEventDto, UpdateMessageDto, WebhookAuthor,
+    },
+    push::{self, PushJob},
     state::AppState,
     websocket::{
-        broadcast_to_server,
+        broadcast_to_channel,
         events::{
             EVENT_MESSAGE_CREATE, EVENT_MESSAGE_DELETE, EVENT_MESSAGE_UPDATE,
             EVENT_THREAD_MESSAGE_CREATE,
@@ -45,6 +50,11 @@ pub struct CreateMessageRequest {
     ))]
     pub content: String,
     pub reply_to: Option<Uuid>,
+    /// Post title; required in forum channels and rejected elsewhere.
+    pub title: Option<String>,
+    /// Tags for a forum post, from the channel's tag set.
+    #[serde(default)]
+    pub tag_ids: Vec<Uuid>,
 }
 
 /// Request body for posting a reply into a thread.
@@ -108,10 +118,27 @@ struct EventMapRow {
     created_at: chrono::DateTime<chrono::Utc>,
 }
 
-/// Batch-enrich a list of messages with poll and event data.
-/// Runs 2 queries regardless of message count (no N+1 for event/poll mapping),
+#[derive(sqlx::FromRow)]
+struct WebhookMessageRow {
+    message_id: uuid::Uuid,
+    webhook_id: Option<uuid::Uuid>,
+    username: String,
+    avatar_url: Option<String>,
+    embeds: serde_json::Value,
+}
+
+#[derive(sqlx::FromRow)]
+struct ForumPostRow {
+    message_id: uuid::Uuid,
+    title: String,
+    tag_ids: Vec<uuid::Uuid>,
+    last_activity_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Batch-enrich a list of messages with poll, event, webhook and forum post data.
+/// Runs 4 queries regardless of message count (no N+1 for event/poll mapping),
 /// plus one query per poll found on this page (typically 0–2 per page).
-async fn enrich_messages(
+pub async fn enrich_messages(
     pool: &sqlx::PgPool,
     caller_id: uuid::Uuid,
     messages: Vec<Message>,
@@ -139,6 +166,24 @@ async fn enrich_messages(
     .fetch_all(pool)
     .await?;
 
+    // Map message_id → webhook author + embeds (only for webhook posts)
+    let webhook_rows = sqlx::query_as::<_, WebhookMessageRow>(
+        "SELECT message_id, webhook_id, username, avatar_url, embeds
+         FROM incoming_webhook_messages WHERE message_id = ANY($1)",
+    )
+    .bind(&ids as &[uuid::Uuid])
+    .fetch_all(pool)
+    .await?;
+
+    // Map message_id → forum post title and tags (only for forum posts)
+    let forum_rows = sqlx::query_as::<_, ForumPostRow>(
+        "SELECT message_id, title, tag_ids, last_activity_at
+         FROM forum_posts WHERE message_id = ANY($1)",
+    )
+    .bind(&ids as &[uuid::Uuid])
+    .fetch_all(pool)
+    .await?;
+
     // Build poll_id map: message_id → poll_id
     let poll_id_map: std::collections::HashMap<uuid::Uuid, uuid::Uuid> =
         poll_rows.iter().map(|r| (r.message_id, r.id)).collect();
@@ -161,6 +206,39 @@ async fn enrich_messages(
         })
         .collect();
 
+    let mut webhook_map: std::collections::HashMap<uuid::Uuid, (WebhookAuthor, Vec<MessageEmbed>)> =
+        webhook_rows
+            .into_iter()
+            .map(|r| {
+                let embeds = serde_json::from_value(r.embeds).unwrap_or_default();
+                (
+                    r.message_id,
+                    (
+                        WebhookAuthor {
+                            id: r.webhook_id,
+                            username: r.username,
+                            avatar_url: r.avatar_url,
+                        },
+                        embeds,
+                    ),
+                )
+            })
+            .collect();
+
+    let mut forum_map: std::collections::HashMap<uuid::Uuid, ForumPostInfo> = forum_rows
+        .into_iter()
+        .map(|r| {
+            (
+                r.message_id,
+                ForumPostInfo {
+                    title: r.title,
+                    tag_ids: r.tag_ids,
+                    last_activity_at: r.last_activity_at,
+                },
+            )
+        })
+        .collect();
+
     // Fetch PollDtos (one call per poll; typically 0–2 per page)
     let mut poll_dto_map: std::collections::HashMap<uuid::Uuid, PollDto> =
         std::collections::HashMap::new();
@@ -177,11 +255,58 @@ async fn enrich_messages(
             let mut dto = MessageDto::from_message(m);
             dto.poll = poll_dto_map.remove(&id);
             dto.event = event_map.remove(&id);
+            if let Some((author, embeds)) = webhook_map.remove(&id) {
+                dto.webhook = Some(author);
+                dto.embeds = embeds;
+            }
+            dto.forum_post = forum_map.remove(&id);
             dto
         })
         .collect())
 }
 
+/// Parse `@username` / `@everyone` tokens from `content` and resolve the
+/// usernames to IDs among current members of `server_id`.
+pub async fn resolve_mentions(
+    pool: &sqlx::PgPool,
+    server_id: uuid::Uuid,
+    content: &str,
+) -> AppResult<(Vec<uuid::Uuid>, bool)> {
+    // Use token-level check to avoid matching mid-word (e.g. "email@everyone.com").
+    let mention_everyone = content.split_whitespace().any(|word| {
+        word.strip_prefix('@')
+            .map(|name| {
+                name.trim_end_matches(|c: char| !c.is_alphanumeric() && c != '_') == "everyone"
+            })
+            .unwrap_or(false)
+    });
+    let mention_words: Vec<&str> = content
+        .split_whitespace()
+        .filter_map(|word| {
+            // Strip trailing punctuation so "@alice!" resolves to "alice".
+            word.strip_prefix('@')
+                .map(|name| name.trim_end_matches(|c: char| !c.is_alphanumeric() && c != '_'))
+        })
+        .filter(|name| !name.is_empty() && *name != "everyone")
+        .collect();
+
+    let mention_user_ids: Vec<uuid::Uuid> = if mention_words.is_empty() {
+        vec![]
+    } else {
+        sqlx::query_scalar(
+            "SELECT sm.user_id FROM server_members sm
+             JOIN users u ON u.id = sm.user_id
+             WHERE sm.server_id = $1 AND u.username = ANY($2)",
+        )
+        .bind(server_id)
+        .bind(&mention_words as &[&str])
+        .fetch_all(pool)
+        .await?
+    };
+
+    Ok((mention_user_ids, mention_everyone))
+}
+
 // ============================================================================
 // Handlers
 // ============================================================================
@@ -209,37 +334,124 @@ pub async fn create_message(
 ) -> AppResult<(StatusCode, Json<MessageDto>)> {
     req.validate().map_err(validation_error)?;
 
+    let forum = match req.title {
+        Some(title) => Some(NewForumPost {
+            title,
+            tag_ids: req.tag_ids,
+        }),
+        None if !req.tag_ids.is_empty() => {
+            return Err(AppError::Validation(
+                "Tags can only be set on forum posts".into(),
+            ));
+        }
+        None => None,
+    };
+
+    let dto = post_message(
+        &state,
+        auth.user_id(),
+        auth.username(),
+        NewMessage {
+            channel_id,
+            content: req.content,
+            reply_to: req.reply_to,
+            id: None,
+            forum,
+        },
+    )
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(dto)))
+}
+
+/// A message to post through [`post_message`].
+pub struct NewMessage {
+    pub channel_id: Uuid,
+    /// Already length-validated by the caller.
+    pub content: String,
+    pub reply_to: Option<Uuid>,
+    /// Id for the new row; `None` lets the database generate one.
+    pub id: Option<Uuid>,
+    /// Title and tags; required in forum channels and rejected elsewhere.
+    pub forum: Option<NewForumPost>,
+}
+
+/// Forum post metadata for a [`NewMessage`].
+pub struct NewForumPost {
+    pub title: String,
+    pub tag_ids: Vec<Uuid>,
+}
+
+/// Post a message as `author_id`: membership and SEND_MESSAGES checks,
+/// timeout and automod, insert, `MESSAGE_CREATE` broadcast and the
+/// `message.created` webhook.
+///
+/// Shared by `create_message` and the scheduled message worker.
+pub async fn post_message(
+    state: &AppState,
+    author_id: Uuid,
+    username: &str,
+    new: NewMessage,
+) -> AppResult<MessageDto> {
+    let channel_id = new.channel_id;
     let channel = fetch_channel_by_id(&state.pool, channel_id).await?;
-    require_member(&state.pool, channel.server_id, auth.user_id()).await?;
+    require_member(&state.pool, channel.server_id, author_id).await?;
 
     // Channel-level permission check (respects per-channel overrides).
     require_channel_permission(
         &state.pool,
         channel.server_id,
         channel_id,
-        auth.user_id(),
+        author_id,
         PERMISSION_SEND_MESSAGES,
         "You don't have permission to send messages in this channel",
     )
     .await?;
 
+    // Top-level messages in a forum channel are posts; everywhere else they
+    // are plain messages.
+    let forum_post = match (&channel.r#type, new.forum) {
+        (ChannelType::Forum, Some(post)) => {
+            if new.reply_to.is_some() {
+                return Err(AppError::Validation(
+                    "Forum posts cannot reply to another message; reply in the post's thread"
+                        .into(),
+                ));
+            }
+            let title = validate_post_title(&post.title)?;
+            let tag_ids = validate_post_tags(&state.pool, channel_id, &post.tag_ids).await?;
+            Some((title, tag_ids))
+        }
+        (ChannelType::Forum, None) => {
+            return Err(AppError::Validation(
+                "Posts in a forum channel need a title".into(),
+            ));
+        }
+        (_, Some(_)) => {
+            return Err(AppError::Validation(
+                "Only forum channels take a post title".into(),
+            ));
+        }
+        (_, None) => None,
+    };
+
     // Check for active timeout (manual or automated) before any automod processing.
-    check_timeout(&state.pool, channel.server_id, auth.user_id()).await?;
+    check_timeout(&state.pool, channel.server_id, author_id).await?;
 
     // Pre-insert automod check (word filter, duplicate detection)
     check_automod(
-        &state.pool,
+        state,
         channel.server_id,
         channel_id,
-        auth.user_id(),
-        auth.username(),
-        &req.content,
+        MessageAuthor::User(author_id),
+        username,
+        &new.content,
         None,
     )
     .await?;
 
     // Validate reply_to: target must exist in the same channel and not be deleted.
-    if let Some(reply_to_id) = req.reply_to {
+    if let Some(reply_to_id) = new.reply_to {
         let exists: bool = sqlx::query_scalar(
             "SELECT EXISTS(
                  SELECT 1 FROM messages
@@ -256,65 +468,52 @@ pub async fn create_message(
         }
     }
 
-    let content = req.content.clone();
+    let content = new.content.clone();
     let dto = CreateMessageDto {
-        content: req.content,
-        reply_to: req.reply_to,
+        content: new.content,
+        reply_to: new.reply_to,
     };
 
-    // Parse @mention tokens from content.
-    // Use token-level check to avoid matching mid-word (e.g. "email@everyone.com").
-    let mention_everyone = dto.content.split_whitespace().any(|word| {
-        word.strip_prefix('@')
-            .map(|name| {
-                name.trim_end_matches(|c: char| !c.is_alphanumeric() && c != '_') == "everyone"
-            })
-            .unwrap_or(false)
-    });
-    let mention_words: Vec<&str> = dto
-        .content
-        .split_whitespace()
-        .filter_map(|word| {
-            // Strip trailing punctuation so "@alice!" resolves to "alice".
-            word.strip_prefix('@')
-                .map(|name| name.trim_end_matches(|c: char| !c.is_alphanumeric() && c != '_'))
-        })
-        .filter(|name| !name.is_empty() && *name != "everyone")
-        .collect();
+    let (mention_user_ids, mention_everyone) =
+        resolve_mentions(&state.pool, channel.server_id, &dto.content).await?;
 
-    // Resolve @username tokens to user IDs among current server members.
-    let mention_user_ids: Vec<uuid::Uuid> = if mention_words.is_empty() {
-        vec![]
-    } else {
-        sqlx::query_scalar(
-            "SELECT sm.user_id FROM server_members sm
-             JOIN users u ON u.id = sm.user_id
-             WHERE sm.server_id = $1 AND u.username = ANY($2)",
-        )
-        .bind(channel.server_id)
-        .bind(&mention_words as &[&str])
-        .fetch_all(&state.pool)
-        .await?
-    };
+    let mut tx = state.pool.begin().await?;
 
     let message = sqlx::query_as::<_, Message>(
-        "INSERT INTO messages (channel_id, author_id, content, reply_to, mention_user_ids, mention_everyone)
-         VALUES ($1, $2, $3, $4, $5, $6)
+        "INSERT INTO messages (id, channel_id, author_id, content, reply_to, mention_user_ids, mention_everyone)
+         VALUES (COALESCE($7, gen_random_uuid()), $1, $2, $3, $4, $5, $6)
          RETURNING id, channel_id, author_id, content, reply_to,
                    mention_user_ids, mention_everyone, thread_id,
                    0 AS thread_reply_count, edited_at, deleted, created_at",
     )
     .bind(channel_id)
-    .bind(auth.user_id())
+    .bind(author_id)
     .bind(&dto.content)
     .bind(dto.reply_to)
     .bind(&mention_user_ids as &[uuid::Uuid])
     .bind(mention_everyone)
-    .fetch_one(&state.pool)
+    .bind(new.id)
+    .fetch_one(&mut *tx)
     .await?;
 
+    if let Some((title, tag_ids)) = forum_post {
+        sqlx::query(
+            "INSERT INTO forum_posts (message_id, channel_id, title, tag_ids, last_activity_at)
+             VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(message.id)
+        .bind(channel_id)
+        .bind(title)
+        .bind(&tag_ids as &[Uuid])
+        .bind(message.created_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
     let message_id = message.id;
-    let enriched = enrich_messages(&state.pool, auth.user_id(), vec![message]).await?;
+    let enriched = enrich_messages(&state.pool, author_id, vec![message]).await?;
     let dto = enriched
         .into_iter()
         .next()
@@ -322,34 +521,49 @@ pub async fn create_message(
 
     // Post-insert automod check (spam detection)
     check_automod(
-        &state.pool,
+        state,
         channel.server_id,
         channel_id,
-        auth.user_id(),
-        auth.username(),
+        MessageAuthor::User(author_id),
+        username,
         &content,
         Some(message_id),
     )
     .await?;
 
-    // Broadcast MESSAGE_CREATE to all connected server members.
+    // Broadcast MESSAGE_CREATE to members who can view the channel.
     match serde_json::to_value(&dto) {
         Ok(payload) => {
-            broadcast_to_server(
-                &state,
+            broadcast_to_channel(
+                state,
                 channel.server_id,
+                channel.id,
                 EVENT_MESSAGE_CREATE,
                 payload.clone(),
             )
             .await;
-            dispatch_event(&state, channel.server_id, "message.created", payload).await;
+            dispatch_event(state, channel.server_id, "message.created", payload).await;
         }
         Err(e) => {
             tracing::error!(error = ?e, "Failed to serialize MessageDto for broadcast");
         }
     }
 
-    Ok((StatusCode::CREATED, Json(dto)))
+    push::notify(
+        state,
+        PushJob::ChannelMessage {
+            server_id: channel.server_id,
+            channel_id,
+            message_id,
+            author_id,
+            content,
+            mention_user_ids,
+            mention_everyone,
+            reply_to: dto.reply_to,
+        },
+    );
+
+    Ok(dto)
 }
 
 /// GET /channels/:channel_id/messages — list messages with cursor pagination (members only).
@@ -554,12 +768,13 @@ pub async fn update_message(
         .next()
         .ok_or_else(|| AppError::Internal)?;
 
-    // Broadcast MESSAGE_UPDATE to all connected server members.
+    // Broadcast MESSAGE_UPDATE to members who can view the channel.
     match serde_json::to_value(&dto) {
         Ok(payload) => {
-            broadcast_to_server(
+            broadcast_to_channel(
                 &state,
                 channel.server_id,
+                channel.id,
                 EVENT_MESSAGE_UPDATE,
                 payload.clone(),
             )
@@ -613,21 +828,23 @@ pub async fn delete_message(
     }
 
     // AND deleted = FALSE ensures rows_affected() == 0 on a concurrent double-delete.
-    let result =
-        sqlx::query("UPDATE messages SET deleted = TRUE WHERE id = $1 AND deleted = FALSE")
-            .bind(message_id)
-            .execute(&state.pool)
-            .await?;
+    let result = sqlx::query(
+        "UPDATE messages SET deleted = TRUE, deleted_at = NOW() WHERE id = $1 AND deleted = FALSE",
+    )
+    .bind(message_id)
+    .execute(&state.pool)
+    .await?;
 
     if result.rows_affected() == 0 {
         return Err(AppError::NotFound("Message not found".into()));
     }
 
-    // Broadcast MESSAGE_DELETE to all connected server members.
+    // Broadcast MESSAGE_DELETE to members who can view the channel.
     let delete_payload = json!({ "id": message_id, "channel_id": message.channel_id });
-    broadcast_to_server(
+    broadcast_to_channel(
         &state,
         channel.server_id,
+        channel.id,
         EVENT_MESSAGE_DELETE,
         delete_payload.clone(),
     )
@@ -691,7 +908,7 @@ pub async fn get_message(
     responses(
         (status = 201, description = "Thread reply created", body = MessageDto),
         (status = 400, description = "Cannot thread off a thread reply"),
-        (status = 403, description = "Not a server member"),
+        (status = 403, description = "No permission to send messages"),
         (status = 404, description = "Channel or message not found")
     ),
     security(("bearer_auth" = [])),
@@ -709,6 +926,15 @@ pub async fn create_thread_reply(
     // reading any message data, to avoid leaking message existence to non-members.
     let channel = fetch_channel_by_id(&state.pool, channel_id).await?;
     require_member(&state.pool, channel.server_id, auth.user_id()).await?;
+    require_channel_permission(
+        &state.pool,
+        channel.server_id,
+        channel_id,
+        auth.user_id(),
+        PERMISSION_SEND_MESSAGES,
+        "You don't have permission to send messages in this channel",
+    )
+    .await?;
 
     let parent = fetch_message(&state.pool, message_id).await?;
 
@@ -772,18 +998,30 @@ pub async fn create_thread_reply(
     .fetch_one(&state.pool)
     .await?;
 
+    // Replies keep a forum post at the top of the activity-sorted post list.
+    // No-op for threads outside forum channels.
+    sqlx::query(
+        "UPDATE forum_posts SET last_activity_at = GREATEST(last_activity_at, $2)
+         WHERE message_id = $1",
+    )
+    .bind(message_id)
+    .bind(message.created_at)
+    .execute(&state.pool)
+    .await?;
+
     let enriched = enrich_messages(&state.pool, auth.user_id(), vec![message]).await?;
     let dto = enriched
         .into_iter()
         .next()
         .ok_or_else(|| AppError::Internal)?;
 
-    // Broadcast THREAD_MESSAGE_CREATE to all connected server members.
+    // Broadcast THREAD_MESSAGE_CREATE to members who can view the channel.
     match serde_json::to_value(&dto) {
         Ok(payload) => {
-            broadcast_to_server(
+            broadcast_to_channel(
                 &state,
                 channel.server_id,
+                channel.id,
                 EVENT_THREAD_MESSAGE_CREATE,
                 payload,
             )
@@ -794,6 +1032,20 @@ pub async fn create_thread_reply(
         }
     }
 
+    push::notify(
+        &state,
+        PushJob::ChannelMessage {
+            server_id: channel.server_id,
+            channel_id,
+            message_id: dto.id,
+            author_id: auth.user_id(),
+            content: req.content,
+            mention_user_ids,
+            mention_everyone,
+            reply_to: Some(message_id),
+        },
+    );
+
     Ok((StatusCode::CREATED, Json(dto)))
 }
 
@@ -816,7 +1068,7 @@ pub async fn create_thread_reply(
     ),
     responses(
         (status = 200, description = "List of thread replies", body = Vec<MessageDto>),
-        (status = 403, description = "Not a server member"),
+        (status = 403, description = "No permission to view channel"),
         (status = 404, description = "Channel or message not found")
     ),
     security(("bearer_auth" = [])),
@@ -830,6 +1082,15 @@ pub async fn list_thread_replies(
 ) -> AppResult<Json<Vec<MessageDto>>> {
     let channel = fetch_channel_by_id(&state.pool, channel_id).await?;
     require_member(&state.pool, channel.server_id, auth.user_id()).await?;
+    require_channel_permission(
+        &state.pool,
+        channel.server_id,
+        channel_id,
+        auth.user_id(),
+        PERMISSION_VIEW_CHANNEL,
+        "You don't have permission to view this channel",
+    )
+    .await?;
 
     // Verify the parent message exists and belongs to this channel.
     let parent = fetch_message(&state.pool, message_id).await?;
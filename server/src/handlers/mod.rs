@@ -8,25 +8,36 @@ pub mod channel_overrides;
 pub mod channels;
 pub mod custom_emojis;
 pub mod dm;
+pub mod email;
 pub mod events;
 pub mod export;
+pub mod files;
+pub mod forum;
 pub mod giphy;
 pub mod go_live;
 pub mod health;
 pub mod ice;
+pub mod incoming_webhooks;
 pub mod invites;
 pub mod link_preview;
 pub mod messages;
 pub mod moderation;
+pub mod notification_settings;
+pub mod oidc;
+pub mod passkeys;
 pub mod pins;
 pub mod polls;
+pub mod push;
 pub mod reactions;
 pub mod read_states;
 pub mod roles;
+pub mod scheduled_messages;
 pub mod search;
 pub mod servers;
 pub mod shared;
 pub mod templates;
+pub mod two_factor;
+pub mod uploads;
 pub mod users;
 pub mod voice;
 pub mod webhooks;
@@ -18,7 +18,7 @@ use super::shared::{
 use crate::{
     auth::AuthUser,
     error::AppResult,
-    handlers::audit::log_action,
+    handlers::{audit::log_action, go_live::end_go_live_for_user},
     models::{
         AuditAction, AutomodTimeout, BanMemberRequest, CreateAuditLog, KickMemberRequest,
         TimeoutMemberRequest, VoiceStateDto,
@@ -30,6 +30,7 @@ use crate::{
             EVENT_MEMBER_BAN, EVENT_MEMBER_KICK, EVENT_MEMBER_TIMEOUT, EVENT_MEMBER_TIMEOUT_REMOVE,
             EVENT_VOICE_STATE_UPDATE,
         },
+        invalidate_channel_viewers,
     },
 };
 
@@ -71,6 +72,15 @@ pub async fn kick_member(
     let reason = body.and_then(|b| b.0.reason);
 
     // Clean up voice state if the target is in a voice channel.
+    let voice_channel: Option<Uuid> =
+        sqlx::query_scalar("SELECT channel_id FROM voice_states WHERE user_id = $1")
+            .bind(target_user_id)
+            .fetch_optional(&state.pool)
+            .await?;
+    if let Some(channel_id) = voice_channel {
+        end_go_live_for_user(&state, target_user_id, channel_id).await;
+    }
+
     let voice_removed = sqlx::query_scalar::<_, Uuid>(
         "DELETE FROM voice_states WHERE user_id = $1 RETURNING channel_id",
     )
@@ -104,6 +114,7 @@ pub async fn kick_member(
         .bind(target_user_id)
         .execute(&state.pool)
         .await?;
+    invalidate_channel_viewers(&state, server_id).await;
 
     log_action(
         &state.pool,
@@ -159,6 +170,15 @@ pub async fn ban_member(
     let reason = body.and_then(|b| b.0.reason);
 
     // Clean up voice state.
+    let voice_channel: Option<Uuid> =
+        sqlx::query_scalar("SELECT channel_id FROM voice_states WHERE user_id = $1")
+            .bind(target_user_id)
+            .fetch_optional(&state.pool)
+            .await?;
+    if let Some(channel_id) = voice_channel {
+        end_go_live_for_user(&state, target_user_id, channel_id).await;
+    }
+
     let voice_removed = sqlx::query_scalar::<_, Uuid>(
         "DELETE FROM voice_states WHERE user_id = $1 RETURNING channel_id",
     )
@@ -210,6 +230,7 @@ pub async fn ban_member(
         .await?;
 
     tx.commit().await?;
+    invalidate_channel_viewers(&state, server_id).await;
 
     log_action(
         &state.pool,
@@ -0,0 +1,637 @@
+//! Notification preferences: per-user level, mute and `@everyone`
+//! suppression for servers, categories, channels and DMs.
+//!
+//! [`resolve`] is the single place that decides how a channel notifies a
+//! user. Read-state counts use it today; anything that pushes notifications
+//! should use it too rather than reading the table directly.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use super::shared::{
+    fetch_channel_by_id, require_channel_permission, require_member, PERMISSION_VIEW_CHANNEL,
+};
+use crate::{
+    auth::AuthUser,
+    error::{AppError, AppResult},
+    models::{NotificationSetting, UpdateNotificationSettingsRequest},
+    state::AppState,
+    websocket::{broadcast_to_user_list, events::EVENT_NOTIFICATION_SETTINGS_UPDATE},
+};
+
+const VALID_LEVELS: &[&str] = &["all", "mentions", "none"];
+
+const SETTING_SELECT: &str =
+    "SELECT scope, server_id, category, COALESCE(channel_id, dm_channel_id) AS channel_id,
+            level, muted_until, suppress_everyone, updated_at
+     FROM notification_settings";
+
+// ============================================================================
+// Resolution
+// ============================================================================
+
+/// How much a channel should notify the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationLevel {
+    All,
+    Mentions,
+    None,
+}
+
+impl NotificationLevel {
+    fn parse(level: &str) -> Option<Self> {
+        match level {
+            "all" => Some(Self::All),
+            "mentions" => Some(Self::Mentions),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+}
+
+/// The settings that apply to one channel after inheritance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EffectiveNotificationSettings {
+    pub level: NotificationLevel,
+    /// An active mute anywhere in the channel's scope chain.
+    pub muted: bool,
+    pub suppress_everyone: bool,
+}
+
+impl EffectiveNotificationSettings {
+    /// Muted or set to `none`: no badges, no notifications.
+    pub fn is_silenced(&self) -> bool {
+        self.muted || self.level == NotificationLevel::None
+    }
+}
+
+/// What a setting is being resolved for.
+#[derive(Debug, Clone, Copy)]
+pub enum NotificationTarget<'a> {
+    Channel {
+        server_id: Uuid,
+        category: Option<&'a str>,
+        channel_id: Uuid,
+    },
+    Dm {
+        channel_id: Uuid,
+    },
+}
+
+/// Resolve a user's settings for `target`.
+///
+/// `settings` are all of the user's rows (see [`load_notification_settings`]).
+/// The most specific row that sets `level` / `suppress_everyone` wins:
+/// channel, then category, then server. Defaults are `all` and not
+/// suppressed. A mute on any of those rows that has not expired applies.
+pub fn resolve(
+    settings: &[NotificationSetting],
+    target: NotificationTarget<'_>,
+    now: DateTime<Utc>,
+) -> EffectiveNotificationSettings {
+    // Rows that apply to the target, most specific first.
+    let chain: Vec<&NotificationSetting> = match target {
+        NotificationTarget::Channel {
+            server_id,
+            category,
+            channel_id,
+        } => {
+            let category = category.filter(|c| !c.is_empty());
+            let channel = settings
+                .iter()
+                .find(|s| s.scope == "channel" && s.channel_id == Some(channel_id));
+            let category = category.and_then(|name| {
+                settings.iter().find(|s| {
+                    s.scope == "category"
+                        && s.server_id == Some(server_id)
+                        && s.category.as_deref() == Some(name)
+                })
+            });
+            let server = settings
+                .iter()
+                .find(|s| s.scope == "server" && s.server_id == Some(server_id));
+            [channel, category, server].into_iter().flatten().collect()
+        }
+        NotificationTarget::Dm { channel_id } => settings
+            .iter()
+            .filter(|s| s.scope == "dm" && s.channel_id == Some(channel_id))
+            .collect(),
+    };
+
+    EffectiveNotificationSettings {
+        level: chain
+            .iter()
+            .find_map(|s| s.level.as_deref().and_then(NotificationLevel::parse))
+            .unwrap_or(NotificationLevel::All),
+        muted: chain
+            .iter()
+            .any(|s| s.muted_until.is_some_and(|until| until > now)),
+        suppress_everyone: chain
+            .iter()
+            .find_map(|s| s.suppress_everyone)
+            .unwrap_or(false),
+    }
+}
+
+/// Every notification setting row for the user.
+pub async fn load_notification_settings(
+    pool: &sqlx::PgPool,
+    user_id: Uuid,
+) -> Result<Vec<NotificationSetting>, sqlx::Error> {
+    sqlx::query_as::<_, NotificationSetting>(&format!(
+        "{SETTING_SELECT} WHERE user_id = $1 ORDER BY updated_at"
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+// ============================================================================
+// Storage
+// ============================================================================
+
+/// Which row a PUT or DELETE addresses.
+enum SettingKey {
+    Server(Uuid),
+    Category(Uuid, String),
+    Channel(Uuid),
+    Dm(Uuid),
+}
+
+impl SettingKey {
+    fn scope(&self) -> &'static str {
+        match self {
+            Self::Server(_) => "server",
+            Self::Category(..) => "category",
+            Self::Channel(_) => "channel",
+            Self::Dm(_) => "dm",
+        }
+    }
+
+    /// Conflict target matching the scope's partial unique index.
+    fn conflict_target(&self) -> &'static str {
+        match self {
+            Self::Server(_) => "(user_id, server_id) WHERE scope = 'server'",
+            Self::Category(..) => "(user_id, server_id, category) WHERE scope = 'category'",
+            Self::Channel(_) => "(user_id, channel_id) WHERE scope = 'channel'",
+            Self::Dm(_) => "(user_id, dm_channel_id) WHERE scope = 'dm'",
+        }
+    }
+
+    /// (server_id, category, channel_id, dm_channel_id) column values.
+    fn columns(&self) -> (Option<Uuid>, Option<&str>, Option<Uuid>, Option<Uuid>) {
+        match self {
+            Self::Server(id) => (Some(*id), None, None, None),
+            Self::Category(id, name) => (Some(*id), Some(name), None, None),
+            Self::Channel(id) => (None, None, Some(*id), None),
+            Self::Dm(id) => (None, None, None, Some(*id)),
+        }
+    }
+}
+
+async fn upsert_setting(
+    state: &AppState,
+    user_id: Uuid,
+    key: SettingKey,
+    req: UpdateNotificationSettingsRequest,
+) -> AppResult<NotificationSetting> {
+    if let Some(level) = &req.level {
+        if !VALID_LEVELS.contains(&level.as_str()) {
+            return Err(AppError::Validation(
+                "level must be one of: all, mentions, none".into(),
+            ));
+        }
+    }
+
+    let (server_id, category, channel_id, dm_channel_id) = key.columns();
+    let setting = sqlx::query_as::<_, NotificationSetting>(&format!(
+        "INSERT INTO notification_settings
+             (user_id, scope, server_id, category, channel_id, dm_channel_id,
+              level, muted_until, suppress_everyone)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+         ON CONFLICT {} DO UPDATE SET
+             level             = EXCLUDED.level,
+             muted_until       = EXCLUDED.muted_until,
+             suppress_everyone = EXCLUDED.suppress_everyone,
+             updated_at        = NOW()
+         RETURNING scope, server_id, category,
+                   COALESCE(channel_id, dm_channel_id) AS channel_id,
+                   level, muted_until, suppress_everyone, updated_at",
+        key.conflict_target()
+    ))
+    .bind(user_id)
+    .bind(key.scope())
+    .bind(server_id)
+    .bind(category)
+    .bind(channel_id)
+    .bind(dm_channel_id)
+    .bind(&req.level)
+    .bind(req.muted_until)
+    .bind(req.suppress_everyone)
+    .fetch_one(&state.pool)
+    .await?;
+
+    dispatch_settings(state, user_id).await?;
+    Ok(setting)
+}
+
+async fn delete_setting(state: &AppState, user_id: Uuid, key: SettingKey) -> AppResult<()> {
+    let (server_id, category, channel_id, dm_channel_id) = key.columns();
+    let deleted = sqlx::query(
+        "DELETE FROM notification_settings
+         WHERE user_id = $1 AND scope = $2
+           AND server_id     IS NOT DISTINCT FROM $3
+           AND category      IS NOT DISTINCT FROM $4
+           AND channel_id    IS NOT DISTINCT FROM $5
+           AND dm_channel_id IS NOT DISTINCT FROM $6",
+    )
+    .bind(user_id)
+    .bind(key.scope())
+    .bind(server_id)
+    .bind(category)
+    .bind(channel_id)
+    .bind(dm_channel_id)
+    .execute(&state.pool)
+    .await?;
+
+    if deleted.rows_affected() > 0 {
+        dispatch_settings(state, user_id).await?;
+    }
+    Ok(())
+}
+
+/// Send the user's full settings list to all of their sessions.
+async fn dispatch_settings(state: &AppState, user_id: Uuid) -> AppResult<()> {
+    let settings = load_notification_settings(&state.pool, user_id).await?;
+    broadcast_to_user_list(
+        state,
+        &[user_id],
+        EVENT_NOTIFICATION_SETTINGS_UPDATE,
+        serde_json::json!({ "notification_settings": settings }),
+    )
+    .await;
+    Ok(())
+}
+
+async fn require_dm_member(pool: &sqlx::PgPool, channel_id: Uuid, user_id: Uuid) -> AppResult<()> {
+    let is_member: bool = sqlx::query_scalar(
+        "SELECT EXISTS(
+             SELECT 1 FROM direct_message_members
+             WHERE channel_id = $1 AND user_id = $2
+         )",
+    )
+    .bind(channel_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+
+    if !is_member {
+        return Err(AppError::NotFound("DM channel not found".into()));
+    }
+    Ok(())
+}
+
+async fn channel_key(state: &AppState, user_id: Uuid, channel_id: Uuid) -> AppResult<SettingKey> {
+    let channel = fetch_channel_by_id(&state.pool, channel_id).await?;
+    require_member(&state.pool, channel.server_id, user_id).await?;
+    require_channel_permission(
+        &state.pool,
+        channel.server_id,
+        channel_id,
+        user_id,
+        PERMISSION_VIEW_CHANNEL,
+        "You don't have permission to view this channel",
+    )
+    .await?;
+    Ok(SettingKey::Channel(channel_id))
+}
+
+async fn category_key(
+    state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    category: String,
+) -> AppResult<SettingKey> {
+    require_member(&state.pool, server_id, user_id).await?;
+    if category.is_empty() || category.chars().count() > 100 {
+        return Err(AppError::Validation(
+            "category must be between 1 and 100 characters".into(),
+        ));
+    }
+    Ok(SettingKey::Category(server_id, category))
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+#[utoipa::path(
+    get,
+    path = "/users/@me/notification-settings",
+    responses(
+        (status = 200, description = "All of the user's notification settings", body = Vec<NotificationSetting>),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "NotificationSettings"
+)]
+/// GET /users/@me/notification-settings — every override the user has set.
+/// Targets without a row use the defaults.
+pub async fn list_notification_settings(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> AppResult<Json<Vec<NotificationSetting>>> {
+    Ok(Json(
+        load_notification_settings(&state.pool, auth.user_id()).await?,
+    ))
+}
+
+#[utoipa::path(
+    put,
+    path = "/servers/{id}/notification-settings",
+    params(
+        ("id" = Uuid, Path, description = "Server ID"),
+    ),
+    request_body = UpdateNotificationSettingsRequest,
+    responses(
+        (status = 200, description = "Updated setting", body = NotificationSetting),
+        (status = 400, description = "Invalid level"),
+        (status = 404, description = "Server not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "NotificationSettings"
+)]
+/// PUT /servers/:id/notification-settings — set the server-wide defaults.
+pub async fn put_server_notification_settings(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(server_id): Path<Uuid>,
+    Json(req): Json<UpdateNotificationSettingsRequest>,
+) -> AppResult<Json<NotificationSetting>> {
+    require_member(&state.pool, server_id, auth.user_id()).await?;
+    let setting =
+        upsert_setting(&state, auth.user_id(), SettingKey::Server(server_id), req).await?;
+    Ok(Json(setting))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/servers/{id}/notification-settings",
+    params(
+        ("id" = Uuid, Path, description = "Server ID"),
+    ),
+    responses(
+        (status = 204, description = "Server settings reset to defaults"),
+        (status = 404, description = "Server not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "NotificationSettings"
+)]
+/// DELETE /servers/:id/notification-settings — back to the defaults.
+pub async fn delete_server_notification_settings(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(server_id): Path<Uuid>,
+) -> AppResult<StatusCode> {
+    require_member(&state.pool, server_id, auth.user_id()).await?;
+    delete_setting(&state, auth.user_id(), SettingKey::Server(server_id)).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    put,
+    path = "/servers/{id}/categories/{category}/notification-settings",
+    params(
+        ("id" = Uuid, Path, description = "Server ID"),
+        ("category" = String, Path, description = "Category name, URL-encoded"),
+    ),
+    request_body = UpdateNotificationSettingsRequest,
+    responses(
+        (status = 200, description = "Updated setting", body = NotificationSetting),
+        (status = 400, description = "Invalid level or category name"),
+        (status = 404, description = "Server not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "NotificationSettings"
+)]
+/// PUT /servers/:id/categories/:category/notification-settings — settings
+/// for every channel labelled with `category`. Category names are
+/// case-sensitive, matching how channels are grouped.
+pub async fn put_category_notification_settings(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((server_id, category)): Path<(Uuid, String)>,
+    Json(req): Json<UpdateNotificationSettingsRequest>,
+) -> AppResult<Json<NotificationSetting>> {
+    let key = category_key(&state, auth.user_id(), server_id, category).await?;
+    let setting = upsert_setting(&state, auth.user_id(), key, req).await?;
+    Ok(Json(setting))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/servers/{id}/categories/{category}/notification-settings",
+    params(
+        ("id" = Uuid, Path, description = "Server ID"),
+        ("category" = String, Path, description = "Category name, URL-encoded"),
+    ),
+    responses(
+        (status = 204, description = "Category settings removed"),
+        (status = 404, description = "Server not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "NotificationSettings"
+)]
+/// DELETE /servers/:id/categories/:category/notification-settings
+pub async fn delete_category_notification_settings(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((server_id, category)): Path<(Uuid, String)>,
+) -> AppResult<StatusCode> {
+    let key = category_key(&state, auth.user_id(), server_id, category).await?;
+    delete_setting(&state, auth.user_id(), key).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    put,
+    path = "/channels/{channel_id}/notification-settings",
+    params(
+        ("channel_id" = Uuid, Path, description = "Channel ID"),
+    ),
+    request_body = UpdateNotificationSettingsRequest,
+    responses(
+        (status = 200, description = "Updated setting", body = NotificationSetting),
+        (status = 400, description = "Invalid level"),
+        (status = 404, description = "Channel not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "NotificationSettings"
+)]
+/// PUT /channels/:channel_id/notification-settings — per-channel override.
+pub async fn put_channel_notification_settings(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(channel_id): Path<Uuid>,
+    Json(req): Json<UpdateNotificationSettingsRequest>,
+) -> AppResult<Json<NotificationSetting>> {
+    let key = channel_key(&state, auth.user_id(), channel_id).await?;
+    let setting = upsert_setting(&state, auth.user_id(), key, req).await?;
+    Ok(Json(setting))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/channels/{channel_id}/notification-settings",
+    params(
+        ("channel_id" = Uuid, Path, description = "Channel ID"),
+    ),
+    responses(
+        (status = 204, description = "Channel override removed"),
+        (status = 404, description = "Channel not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "NotificationSettings"
+)]
+/// DELETE /channels/:channel_id/notification-settings
+pub async fn delete_channel_notification_settings(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(channel_id): Path<Uuid>,
+) -> AppResult<StatusCode> {
+    let key = channel_key(&state, auth.user_id(), channel_id).await?;
+    delete_setting(&state, auth.user_id(), key).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    put,
+    path = "/dm-channels/{id}/notification-settings",
+    params(
+        ("id" = Uuid, Path, description = "DM channel ID"),
+    ),
+    request_body = UpdateNotificationSettingsRequest,
+    responses(
+        (status = 200, description = "Updated setting", body = NotificationSetting),
+        (status = 400, description = "Invalid level"),
+        (status = 404, description = "DM channel not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "NotificationSettings"
+)]
+/// PUT /dm-channels/:id/notification-settings — mute or quiet a DM.
+pub async fn put_dm_notification_settings(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(channel_id): Path<Uuid>,
+    Json(req): Json<UpdateNotificationSettingsRequest>,
+) -> AppResult<Json<NotificationSetting>> {
+    require_dm_member(&state.pool, channel_id, auth.user_id()).await?;
+    let setting = upsert_setting(&state, auth.user_id(), SettingKey::Dm(channel_id), req).await?;
+    Ok(Json(setting))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/dm-channels/{id}/notification-settings",
+    params(
+        ("id" = Uuid, Path, description = "DM channel ID"),
+    ),
+    responses(
+        (status = 204, description = "DM override removed"),
+        (status = 404, description = "DM channel not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "NotificationSettings"
+)]
+/// DELETE /dm-channels/:id/notification-settings
+pub async fn delete_dm_notification_settings(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(channel_id): Path<Uuid>,
+) -> AppResult<StatusCode> {
+    require_dm_member(&state.pool, channel_id, auth.user_id()).await?;
+    delete_setting(&state, auth.user_id(), SettingKey::Dm(channel_id)).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn row(scope: &str) -> NotificationSetting {
+        NotificationSetting {
+            scope: scope.into(),
+            server_id: None,
+            category: None,
+            channel_id: None,
+            level: None,
+            muted_until: None,
+            suppress_everyone: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn channel_target(server_id: Uuid, channel_id: Uuid) -> NotificationTarget<'static> {
+        NotificationTarget::Channel {
+            server_id,
+            category: Some("Text"),
+            channel_id,
+        }
+    }
+
+    #[test]
+    fn defaults_without_rows() {
+        let effective = resolve(
+            &[],
+            channel_target(Uuid::new_v4(), Uuid::new_v4()),
+            Utc::now(),
+        );
+        assert_eq!(effective.level, NotificationLevel::All);
+        assert!(!effective.muted);
+        assert!(!effective.suppress_everyone);
+    }
+
+    #[test]
+    fn most_specific_level_wins_and_nulls_inherit() {
+        let (server_id, channel_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut server = row("server");
+        server.server_id = Some(server_id);
+        server.level = Some("none".into());
+        server.suppress_everyone = Some(true);
+        let mut category = row("category");
+        category.server_id = Some(server_id);
+        category.category = Some("Text".into());
+        category.level = Some("mentions".into());
+        let mut channel = row("channel");
+        channel.channel_id = Some(channel_id);
+
+        let settings = [server, category, channel];
+        let effective = resolve(&settings, channel_target(server_id, channel_id), Utc::now());
+        assert_eq!(effective.level, NotificationLevel::Mentions);
+        assert!(effective.suppress_everyone);
+    }
+
+    #[test]
+    fn any_active_mute_in_chain_applies() {
+        let (server_id, channel_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let now = Utc::now();
+        let mut server = row("server");
+        server.server_id = Some(server_id);
+        server.muted_until = Some(now + Duration::hours(1));
+        let mut channel = row("channel");
+        channel.channel_id = Some(channel_id);
+        channel.level = Some("all".into());
+
+        let settings = [server.clone(), channel];
+        assert!(resolve(&settings, channel_target(server_id, channel_id), now).is_silenced());
+
+        server.muted_until = Some(now - Duration::hours(1));
+        assert!(!resolve(&[server], channel_target(server_id, channel_id), now).is_silenced());
+    }
+}
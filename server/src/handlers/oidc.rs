@@ -0,0 +1,490 @@
+//! Single sign-on through an OpenID Connect provider. The protocol side lives
+//! in `crate::auth::oidc`; this module maps provider identities to accounts.
+//!
+//! A login goes `POST /auth/oidc/authorize` → the provider → the web client's
+//! redirect page → `POST /auth/oidc/callback`. The callback finds the account
+//! for the identity, in order:
+//!
+//! 1. an account already linked to the provider subject;
+//! 2. an account whose verified email matches the provider's verified email,
+//!    which is then linked;
+//! 3. a new account, if `instance_settings.registration_mode` allows it.
+//!
+//! On every login the user's groups are applied: `OIDC_ADMIN_GROUPS` sets
+//! `is_admin`, and `OIDC_ROLE_MAPPINGS` adds or removes roles in the servers
+//! the user is a member of.
+
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgExecutor, PgPool};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::{
+        hash_password, hash_refresh_token,
+        oidc::{Identity, OidcClient, OidcError},
+    },
+    config::OidcConfig,
+    error::{AppError, AppResult},
+    handlers::{
+        auth::{redeem_invite, start_session, LoginResponse},
+        email::new_email_token,
+        two_factor,
+    },
+    models::User,
+    state::AppState,
+    websocket::{
+        broadcast_to_server,
+        events::{EVENT_MEMBER_ROLE_ADD, EVENT_MEMBER_ROLE_REMOVE},
+        invalidate_channel_viewers,
+    },
+};
+
+/// How long the user has to finish logging in at the provider.
+const LOGIN_STATE_TTL_SECS: i64 = 600;
+const MAX_USERNAME_LEN: usize = 32;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Default, Deserialize, Validate, ToSchema)]
+pub struct OidcAuthorizeRequest {
+    /// Used if the instance is invite-only and this login creates an account.
+    #[validate(length(max = 64))]
+    pub invite_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OidcAuthorizeResponse {
+    /// Send the browser here.
+    pub authorization_url: String,
+    /// Also in `authorization_url`; comes back on the redirect.
+    pub state: String,
+    pub expires_in_seconds: i64,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct OidcCallbackRequest {
+    /// `code` from the redirect.
+    #[validate(length(min = 1, max = 2048))]
+    pub code: String,
+    /// `state` from the redirect.
+    #[validate(length(min = 1, max = 256))]
+    pub state: String,
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+fn require_oidc(state: &AppState) -> AppResult<&OidcClient> {
+    state
+        .oidc
+        .as_deref()
+        .ok_or_else(|| AppError::NotFound("Single sign-on is not configured on this server".into()))
+}
+
+fn provider_error(e: OidcError) -> AppError {
+    match e {
+        OidcError::Rejected(message) => AppError::Auth(message),
+        OidcError::Provider(message) => {
+            warn!("Single sign-on failed: {message}");
+            AppError::Internal
+        }
+    }
+}
+
+/// A username that fits the registration rules (2–32 of `[A-Za-z0-9_]`),
+/// derived from what the provider knows about the user.
+fn base_username(identity: &Identity) -> String {
+    let source = identity
+        .preferred_username
+        .as_deref()
+        .or(identity.name.as_deref())
+        .or(identity
+            .email
+            .as_deref()
+            .and_then(|email| email.split('@').next()))
+        .unwrap_or("");
+    let mut username: String = source
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => c,
+            _ => '_',
+        })
+        .take(MAX_USERNAME_LEN)
+        .collect();
+    username = username.trim_matches('_').to_string();
+    if username.len() < 2 {
+        username = "user".to_string();
+    }
+    username
+}
+
+/// `base_username`, with a numeric suffix if the name is taken.
+async fn available_username(pool: &PgPool, identity: &Identity) -> AppResult<String> {
+    let base = base_username(identity);
+    let mut candidate = base.clone();
+    for _ in 0..10 {
+        let taken: bool =
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE username = $1)")
+                .bind(&candidate)
+                .fetch_one(pool)
+                .await?;
+        if !taken {
+            return Ok(candidate);
+        }
+        let suffix = format!("_{}", rand::random::<u16>() % 10_000);
+        let stem: String = base.chars().take(MAX_USERNAME_LEN - suffix.len()).collect();
+        candidate = format!("{stem}{suffix}");
+    }
+    Err(AppError::Conflict("Username already taken".into()))
+}
+
+async fn link_identity(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    issuer: &str,
+    identity: &Identity,
+) -> AppResult<()> {
+    sqlx::query(
+        "INSERT INTO user_identities (user_id, issuer, subject, email) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(user_id)
+    .bind(issuer)
+    .bind(&identity.subject)
+    .bind(&identity.email)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// The account for a provider identity, linking or creating one if needed.
+async fn resolve_user(
+    state: &AppState,
+    config: &OidcConfig,
+    identity: &Identity,
+    invite_code: Option<&str>,
+) -> AppResult<User> {
+    let linked = sqlx::query_as::<_, User>(
+        "UPDATE user_identities i SET last_login_at = NOW(), email = $3
+         FROM users u
+         WHERE i.user_id = u.id AND i.issuer = $1 AND i.subject = $2
+         RETURNING u.*",
+    )
+    .bind(&config.issuer)
+    .bind(&identity.subject)
+    .bind(&identity.email)
+    .fetch_optional(&state.pool)
+    .await?;
+    if let Some(user) = linked {
+        return Ok(user);
+    }
+
+    if let Some(email) = identity.verified_email() {
+        let existing =
+            sqlx::query_as::<_, User>("SELECT * FROM users WHERE lower(email) = lower($1)")
+                .bind(email)
+                .fetch_optional(&state.pool)
+                .await?;
+        if let Some(user) = existing {
+            // Linking to an address nobody proved they own would let whoever
+            // registered it locally take over the provider's user.
+            if user.email_verified_at.is_none() {
+                return Err(AppError::Conflict(
+                    "An account with this email already exists, but its email address \
+                     is not verified"
+                        .into(),
+                ));
+            }
+            link_identity(&state.pool, user.id, &config.issuer, identity).await?;
+            info!(
+                "Single sign-on linked to existing account: {} ({})",
+                user.username, user.id
+            );
+            return Ok(user);
+        }
+    }
+
+    let registration_mode: String =
+        sqlx::query_scalar("SELECT registration_mode FROM instance_settings WHERE id = 1")
+            .fetch_one(&state.pool)
+            .await?;
+    let invite_only = match registration_mode.as_str() {
+        "closed" => {
+            return Err(AppError::Forbidden(
+                "No account is linked to this identity and registration is closed".into(),
+            ));
+        }
+        "invite_only" => true,
+        _ => false,
+    };
+
+    let username = available_username(&state.pool, identity).await?;
+    // The account has no usable password until the user sets one through a
+    // password reset.
+    let password_hash = hash_password(&new_email_token().0)?;
+    let email = identity.verified_email();
+
+    // The invite use is counted in the same transaction, so it is not lost
+    // when the insert fails.
+    let mut tx = state.pool.begin().await?;
+    if invite_only {
+        redeem_invite(&mut tx, invite_code).await?;
+    }
+    let user = sqlx::query_as::<_, User>(
+        "INSERT INTO users (username, email, password_hash, status, email_verified_at)
+         VALUES ($1, $2, $3, 'offline', CASE WHEN $2::TEXT IS NULL THEN NULL ELSE NOW() END)
+         RETURNING *",
+    )
+    .bind(&username)
+    .bind(email)
+    .bind(&password_hash)
+    .fetch_one(&mut *tx)
+    .await?;
+    link_identity(&mut *tx, user.id, &config.issuer, identity).await?;
+    tx.commit().await?;
+
+    info!(
+        "User created through single sign-on: {} ({})",
+        user.username, user.id
+    );
+    Ok(user)
+}
+
+/// Apply the provider's groups to instance admin and mapped server roles.
+async fn apply_groups(
+    state: &AppState,
+    config: &OidcConfig,
+    user_id: Uuid,
+    groups: &[String],
+) -> AppResult<()> {
+    let in_group = |group: &String| groups.contains(group);
+
+    if !config.admin_groups.is_empty() {
+        let is_admin = config.admin_groups.iter().any(in_group);
+        sqlx::query(
+            "UPDATE users SET is_admin = $2, updated_at = NOW()
+             WHERE id = $1 AND is_admin <> $2",
+        )
+        .bind(user_id)
+        .bind(is_admin)
+        .execute(&state.pool)
+        .await?;
+    }
+
+    for (group, role_id) in &config.role_mappings {
+        let granted = in_group(group);
+        // Only servers the user already belongs to; the role's server comes
+        // from the role itself.
+        let changed: Option<(Uuid, String, Option<String>)> = if granted {
+            sqlx::query_as(
+                "WITH added AS (
+                     INSERT INTO member_roles (user_id, server_id, role_id)
+                     SELECT m.user_id, r.server_id, r.id
+                     FROM roles r
+                     JOIN server_members m ON m.server_id = r.server_id AND m.user_id = $1
+                     WHERE r.id = $2
+                     ON CONFLICT DO NOTHING
+                     RETURNING server_id
+                 )
+                 SELECT r.server_id, r.name, r.color FROM roles r JOIN added a ON a.server_id = r.server_id
+                 WHERE r.id = $2",
+            )
+        } else {
+            sqlx::query_as(
+                "WITH removed AS (
+                     DELETE FROM member_roles WHERE user_id = $1 AND role_id = $2
+                     RETURNING server_id
+                 )
+                 SELECT r.server_id, r.name, r.color FROM roles r JOIN removed d ON d.server_id = r.server_id
+                 WHERE r.id = $2",
+            )
+        }
+        .bind(user_id)
+        .bind(role_id)
+        .fetch_optional(&state.pool)
+        .await?;
+
+        let Some((server_id, role_name, role_color)) = changed else {
+            continue;
+        };
+        invalidate_channel_viewers(state, server_id).await;
+        let event = if granted {
+            EVENT_MEMBER_ROLE_ADD
+        } else {
+            EVENT_MEMBER_ROLE_REMOVE
+        };
+        let payload = json!({
+            "server_id": server_id,
+            "user_id": user_id,
+            "role_id": role_id,
+            "role_name": role_name,
+            "role_color": role_color,
+        });
+        broadcast_to_server(state, server_id, event, payload).await;
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+#[utoipa::path(
+    post,
+    path = "/auth/oidc/authorize",
+    request_body = OidcAuthorizeRequest,
+    responses(
+        (status = 200, description = "Where to send the browser", body = OidcAuthorizeResponse),
+        (status = 404, description = "Single sign-on is not configured"),
+        (status = 500, description = "The identity provider could not be reached"),
+    ),
+    tag = "Auth"
+)]
+/// POST /auth/oidc/authorize — start a single sign-on login.
+pub async fn oidc_authorize(
+    State(state): State<AppState>,
+    Json(req): Json<OidcAuthorizeRequest>,
+) -> AppResult<Json<OidcAuthorizeResponse>> {
+    let client = require_oidc(&state)?;
+    req.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let request = client
+        .authorization_request()
+        .await
+        .map_err(provider_error)?;
+
+    let mut tx = state.pool.begin().await?;
+    sqlx::query("DELETE FROM oidc_login_states WHERE expires_at < NOW()")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO oidc_login_states (state_hash, nonce, code_verifier, invite_code, expires_at)
+         VALUES ($1, $2, $3, $4, NOW() + make_interval(secs => $5))",
+    )
+    .bind(hash_refresh_token(&request.state))
+    .bind(&request.nonce)
+    .bind(&request.code_verifier)
+    .bind(req.invite_code.as_deref().map(str::trim))
+    .bind(LOGIN_STATE_TTL_SECS as f64)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(Json(OidcAuthorizeResponse {
+        authorization_url: request.url,
+        state: request.state,
+        expires_in_seconds: LOGIN_STATE_TTL_SECS,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/oidc/callback",
+    request_body = OidcCallbackRequest,
+    responses(
+        (status = 200, description = "Login successful, or a two-factor challenge", body = LoginResponse),
+        (status = 400, description = "Invite code missing or invalid"),
+        (status = 401, description = "Invalid or expired state, or the provider refused the login"),
+        (status = 403, description = "Account disabled, or registration closed"),
+        (status = 404, description = "Single sign-on is not configured"),
+        (status = 409, description = "An unverified account already uses this email"),
+        (status = 500, description = "The identity provider could not be reached"),
+    ),
+    tag = "Auth"
+)]
+/// POST /auth/oidc/callback — finish a single sign-on login with the `code`
+/// and `state` the provider redirected back with.
+///
+/// The response is the same as `/auth/login`: tokens, or a two-factor
+/// challenge when the account has 2FA.
+pub async fn oidc_callback(
+    State(state): State<AppState>,
+    Json(req): Json<OidcCallbackRequest>,
+) -> AppResult<Json<LoginResponse>> {
+    let client = require_oidc(&state)?;
+    req.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+    let config = client.config();
+
+    let (nonce, code_verifier, invite_code): (String, String, Option<String>) = sqlx::query_as(
+        "DELETE FROM oidc_login_states
+         WHERE state_hash = $1 AND expires_at > NOW()
+         RETURNING nonce, code_verifier, invite_code",
+    )
+    .bind(hash_refresh_token(&req.state))
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::Auth("Invalid or expired login state".into()))?;
+
+    let identity = client
+        .exchange_code(&req.code, &code_verifier, &nonce)
+        .await
+        .map_err(provider_error)?;
+
+    let user = resolve_user(&state, config, &identity, invite_code.as_deref()).await?;
+    if user.disabled {
+        return Err(AppError::Forbidden(
+            "Your account has been disabled by an administrator".into(),
+        ));
+    }
+    apply_groups(&state, config, user.id, &identity.groups).await?;
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user.id)
+        .fetch_one(&state.pool)
+        .await?;
+
+    if let Some(challenge) = two_factor::login_challenge(&state, &user).await? {
+        info!(
+            "Single sign-on accepted, two-factor step pending: {} ({})",
+            user.username, user.id
+        );
+        return Ok(Json(LoginResponse::TwoFactor(challenge)));
+    }
+
+    info!("Login successful (SSO): {} ({})", user.username, user.id);
+    Ok(Json(LoginResponse::Authenticated(Box::new(
+        start_session(&state, user).await?,
+    ))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(preferred_username: Option<&str>, email: Option<&str>) -> Identity {
+        Identity {
+            subject: "sub".into(),
+            email: email.map(str::to_string),
+            email_verified: true,
+            preferred_username: preferred_username.map(str::to_string),
+            name: None,
+            groups: vec![],
+        }
+    }
+
+    #[test]
+    fn usernames_follow_registration_rules() {
+        assert_eq!(
+            base_username(&identity(Some("ada.lovelace"), None)),
+            "ada_lovelace"
+        );
+        assert_eq!(
+            base_username(&identity(None, Some("grace-hopper@example.com"))),
+            "grace_hopper"
+        );
+        assert_eq!(base_username(&identity(Some("é"), None)), "user");
+        assert_eq!(base_username(&identity(None, None)), "user");
+        assert_eq!(
+            base_username(&identity(Some(&"x".repeat(50)), None)).len(),
+            MAX_USERNAME_LEN
+        );
+    }
+}
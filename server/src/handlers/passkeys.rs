@@ -0,0 +1,814 @@
+//! Passkeys (WebAuthn): registering and managing credentials, passwordless
+//! login, and passkeys as the second login step. Signature and data checks
+//! live in `crate::auth::webauthn`.
+//!
+//! Every ceremony starts with an options request that issues a one-time
+//! challenge; the browser passes the options to `navigator.credentials` and
+//! the result is posted back. Options and credentials use the WebAuthn JSON
+//! forms (`PublicKeyCredential.parseCreationOptionsFromJSON()` and
+//! `credential.toJSON()`), with binary fields as base64url.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::{
+        hash_refresh_token,
+        webauthn::{self, Ceremony, RelyingParty, SUPPORTED_ALGORITHMS},
+        AuthUser,
+    },
+    config::WebAuthnConfig,
+    error::{AppError, AppResult},
+    handlers::{
+        auth::{start_session, AuthResponse},
+        email::new_email_token,
+        two_factor::{
+            self, claim_challenge, find_challenge, record_failed_attempt, require_password,
+            ChallengePurpose, ChallengeRequest,
+        },
+    },
+    models::User,
+    state::AppState,
+};
+
+/// How long the browser and the server wait for a ceremony to finish.
+const CEREMONY_TIMEOUT_SECS: i64 = 300;
+const MAX_PASSKEYS_PER_USER: i64 = 20;
+const DEFAULT_PASSKEY_NAME: &str = "Passkey";
+/// Transport hints the WebAuthn spec defines; anything else is dropped.
+const KNOWN_TRANSPORTS: [&str; 6] = ["ble", "hybrid", "internal", "nfc", "smart-card", "usb"];
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct Passkey {
+    pub id: Uuid,
+    pub name: String,
+    /// How the browser can reach the authenticator, e.g. "internal", "hybrid".
+    pub transports: Vec<String>,
+    /// Synced to other devices by the platform (iCloud Keychain, Google
+    /// Password Manager, ...).
+    pub backup_eligible: bool,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct PasskeyOptionsRequest {
+    #[validate(length(min = 1, max = 128))]
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CreatePasskeyRequest {
+    /// Defaults to "Passkey".
+    #[validate(length(min = 1, max = 64))]
+    pub name: Option<String>,
+    pub credential: RegistrationCredential,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct UpdatePasskeyRequest {
+    #[validate(length(min = 1, max = 64))]
+    pub name: String,
+}
+
+/// `PublicKeyCredential.toJSON()` after `navigator.credentials.create()`.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RegistrationCredential {
+    pub id: String,
+    pub response: AttestationResponse,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+    #[serde(default)]
+    pub transports: Vec<String>,
+}
+
+/// `PublicKeyCredential.toJSON()` after `navigator.credentials.get()`.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AssertionCredential {
+    pub id: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+    #[serde(rename = "userHandle")]
+    pub user_handle: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PasskeyLoginRequest {
+    pub credential: AssertionCredential,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct PasskeySecondFactorRequest {
+    #[validate(length(min = 1, max = 256))]
+    pub challenge: String,
+    pub credential: AssertionCredential,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RelyingPartyEntity {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserEntity {
+    /// The user's ID as 16 bytes, base64url. Returned as `userHandle` by
+    /// discoverable credentials.
+    pub id: String,
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CredentialParameters {
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// COSE algorithm identifier.
+    pub alg: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CredentialDescriptor {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub id: String,
+    pub transports: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticatorSelection {
+    pub resident_key: String,
+    pub user_verification: String,
+}
+
+/// `PublicKeyCredentialCreationOptionsJSON`.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyCreationOptions {
+    pub challenge: String,
+    pub rp: RelyingPartyEntity,
+    pub user: UserEntity,
+    pub pub_key_cred_params: Vec<CredentialParameters>,
+    /// Milliseconds.
+    pub timeout: i64,
+    /// The user's existing passkeys, so an authenticator is not registered
+    /// twice.
+    pub exclude_credentials: Vec<CredentialDescriptor>,
+    pub authenticator_selection: AuthenticatorSelection,
+    pub attestation: String,
+}
+
+/// `PublicKeyCredentialRequestOptionsJSON`.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyRequestOptions {
+    pub challenge: String,
+    pub rp_id: String,
+    /// Milliseconds.
+    pub timeout: i64,
+    /// Empty for passwordless login: the browser offers every passkey it has
+    /// for this site.
+    pub allow_credentials: Vec<CredentialDescriptor>,
+    pub user_verification: String,
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+fn require_webauthn(state: &AppState) -> AppResult<&WebAuthnConfig> {
+    state
+        .config
+        .webauthn
+        .as_ref()
+        .ok_or_else(|| AppError::NotFound("Passkeys are not configured on this server".into()))
+}
+
+fn relying_party(config: &WebAuthnConfig) -> RelyingParty<'_> {
+    RelyingParty {
+        id: &config.rp_id,
+        origins: &config.origins,
+    }
+}
+
+fn decode_field(value: &str, field: &str) -> AppResult<Vec<u8>> {
+    webauthn::decode(value)
+        .ok_or_else(|| AppError::Validation(format!("{field} must be base64url")))
+}
+
+/// Issue a ceremony challenge. Only the hash is stored.
+async fn issue_challenge(pool: &PgPool, user_id: Option<Uuid>, purpose: &str) -> AppResult<String> {
+    let (challenge, challenge_hash) = new_email_token();
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM webauthn_challenges WHERE expires_at < NOW()")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO webauthn_challenges (challenge_hash, user_id, purpose, expires_at)
+         VALUES ($1, $2, $3, NOW() + make_interval(secs => $4))",
+    )
+    .bind(&challenge_hash)
+    .bind(user_id)
+    .bind(purpose)
+    .bind(CEREMONY_TIMEOUT_SECS as f64)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(challenge)
+}
+
+/// Check the client data of a ceremony and use up the challenge it answers.
+/// Returns the user the challenge was issued to, if any. Failures are
+/// reported through `reject`.
+async fn take_challenge(
+    pool: &PgPool,
+    rp: &RelyingParty<'_>,
+    client_data_json: &[u8],
+    ceremony: Ceremony,
+    purpose: &str,
+    reject: fn(String) -> AppError,
+) -> AppResult<Option<Uuid>> {
+    let challenge = webauthn::client_data_challenge(rp, client_data_json, ceremony)
+        .map_err(|e| reject(e.into()))?;
+
+    let row: Option<(Option<Uuid>,)> = sqlx::query_as(
+        "DELETE FROM webauthn_challenges
+         WHERE challenge_hash = $1 AND purpose = $2 AND expires_at > NOW()
+         RETURNING user_id",
+    )
+    .bind(hash_refresh_token(&challenge))
+    .bind(purpose)
+    .fetch_optional(pool)
+    .await?;
+    row.map(|(user_id,)| user_id)
+        .ok_or_else(|| reject("Invalid or expired challenge".into()))
+}
+
+/// Descriptors of a user's passkeys, for `excludeCredentials` and
+/// `allowCredentials`.
+async fn credential_descriptors(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> AppResult<Vec<CredentialDescriptor>> {
+    let rows: Vec<(String, Vec<String>)> = sqlx::query_as(
+        "SELECT credential_id, transports FROM webauthn_credentials
+         WHERE user_id = $1 ORDER BY created_at",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(id, transports)| CredentialDescriptor {
+            kind: "public-key".into(),
+            id,
+            transports,
+        })
+        .collect())
+}
+
+fn request_options(
+    config: &WebAuthnConfig,
+    challenge: String,
+    allow_credentials: Vec<CredentialDescriptor>,
+    user_verification: &str,
+) -> PasskeyRequestOptions {
+    PasskeyRequestOptions {
+        challenge,
+        rp_id: config.rp_id.clone(),
+        timeout: CEREMONY_TIMEOUT_SECS * 1000,
+        allow_credentials,
+        user_verification: user_verification.into(),
+    }
+}
+
+/// Verify an assertion against the stored credential and record its use.
+/// With `expected_user`, the passkey must belong to that user. Returns the
+/// credential's owner.
+async fn check_assertion(
+    state: &AppState,
+    rp: &RelyingParty<'_>,
+    credential: &AssertionCredential,
+    client_data_json: &[u8],
+    expected_user: Option<Uuid>,
+    require_user_verification: bool,
+) -> AppResult<User> {
+    let response = &credential.response;
+    let authenticator_data = decode_field(&response.authenticator_data, "authenticatorData")?;
+    let signature = decode_field(&response.signature, "signature")?;
+    let credential_id = webauthn::encode(&decode_field(&credential.id, "id")?);
+
+    let row: Option<(Uuid, Uuid, Vec<u8>, i64)> = sqlx::query_as(
+        "SELECT id, user_id, public_key, sign_count FROM webauthn_credentials
+         WHERE credential_id = $1",
+    )
+    .bind(&credential_id)
+    .fetch_optional(&state.pool)
+    .await?;
+    let Some((id, user_id, public_key, sign_count)) = row else {
+        return Err(AppError::Auth("Unknown passkey".into()));
+    };
+    if expected_user.is_some_and(|expected| expected != user_id) {
+        return Err(AppError::Auth("Unknown passkey".into()));
+    }
+    if let Some(handle) = &response.user_handle {
+        if decode_field(handle, "userHandle")? != user_id.as_bytes() {
+            return Err(AppError::Auth(
+                "Passkey does not belong to this user".into(),
+            ));
+        }
+    }
+
+    let assertion = webauthn::verify_assertion(
+        rp,
+        &public_key,
+        sign_count as u32,
+        &authenticator_data,
+        client_data_json,
+        &signature,
+    )
+    .map_err(|e| AppError::Auth(e.into()))?;
+    if require_user_verification && !assertion.user_verified {
+        return Err(AppError::Auth(
+            "Passkey login requires user verification (PIN or biometrics)".into(),
+        ));
+    }
+
+    sqlx::query(
+        "UPDATE webauthn_credentials SET sign_count = $2, last_used_at = NOW() WHERE id = $1",
+    )
+    .bind(id)
+    .bind(assertion.sign_count as i64)
+    .execute(&state.pool)
+    .await?;
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(&state.pool)
+        .await?;
+    if user.disabled {
+        return Err(AppError::Forbidden(
+            "Your account has been disabled by an administrator".into(),
+        ));
+    }
+    Ok(user)
+}
+
+// ============================================================================
+// Account endpoints
+// ============================================================================
+
+#[utoipa::path(
+    get,
+    path = "/users/@me/passkeys",
+    responses(
+        (status = 200, description = "The user's passkeys", body = Vec<Passkey>),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Users"
+)]
+/// GET /users/@me/passkeys
+pub async fn list_passkeys(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> AppResult<Json<Vec<Passkey>>> {
+    let passkeys = sqlx::query_as::<_, Passkey>(
+        "SELECT id, name, transports, backup_eligible, created_at, last_used_at
+         FROM webauthn_credentials
+         WHERE user_id = $1
+         ORDER BY created_at",
+    )
+    .bind(auth.user_id())
+    .fetch_all(&state.pool)
+    .await?;
+    Ok(Json(passkeys))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/@me/passkeys/options",
+    request_body = PasskeyOptionsRequest,
+    responses(
+        (status = 200, description = "Options for navigator.credentials.create()", body = PasskeyCreationOptions),
+        (status = 403, description = "Incorrect password"),
+        (status = 404, description = "Passkeys are not configured"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Users"
+)]
+/// POST /users/@me/passkeys/options — start registering a passkey.
+pub async fn passkey_creation_options(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(req): Json<PasskeyOptionsRequest>,
+) -> AppResult<Json<PasskeyCreationOptions>> {
+    let config = require_webauthn(&state)?;
+    req.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+    let user = require_password(&state.pool, auth.user_id(), &req.password).await?;
+
+    let exclude_credentials = credential_descriptors(&state.pool, user.id).await?;
+    let challenge = issue_challenge(&state.pool, Some(user.id), "register").await?;
+
+    Ok(Json(PasskeyCreationOptions {
+        challenge,
+        rp: RelyingPartyEntity {
+            id: config.rp_id.clone(),
+            name: config.rp_name.clone(),
+        },
+        user: UserEntity {
+            id: webauthn::encode(user.id.as_bytes()),
+            name: user.username.clone(),
+            display_name: user.username,
+        },
+        pub_key_cred_params: SUPPORTED_ALGORITHMS
+            .iter()
+            .map(|&alg| CredentialParameters {
+                kind: "public-key".into(),
+                alg,
+            })
+            .collect(),
+        timeout: CEREMONY_TIMEOUT_SECS * 1000,
+        exclude_credentials,
+        authenticator_selection: AuthenticatorSelection {
+            resident_key: "required".into(),
+            user_verification: "preferred".into(),
+        },
+        attestation: "none".into(),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/@me/passkeys",
+    request_body = CreatePasskeyRequest,
+    responses(
+        (status = 201, description = "Passkey registered", body = Passkey),
+        (status = 400, description = "Invalid credential or challenge, or too many passkeys"),
+        (status = 404, description = "Passkeys are not configured"),
+        (status = 409, description = "Passkey already registered"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Users"
+)]
+/// POST /users/@me/passkeys — finish registering a passkey.
+pub async fn create_passkey(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(req): Json<CreatePasskeyRequest>,
+) -> AppResult<(StatusCode, Json<Passkey>)> {
+    let config = require_webauthn(&state)?;
+    req.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+    let rp = relying_party(config);
+    let response = &req.credential.response;
+    let client_data_json = decode_field(&response.client_data_json, "clientDataJSON")?;
+    let attestation_object = decode_field(&response.attestation_object, "attestationObject")?;
+
+    let owner = take_challenge(
+        &state.pool,
+        &rp,
+        &client_data_json,
+        Ceremony::Create,
+        "register",
+        AppError::Validation,
+    )
+    .await?;
+    if owner != Some(auth.user_id()) {
+        return Err(AppError::Validation("Invalid or expired challenge".into()));
+    }
+    let credential = webauthn::verify_registration(&rp, &attestation_object)
+        .map_err(|e| AppError::Validation(e.into()))?;
+
+    let existing: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM webauthn_credentials WHERE user_id = $1")
+            .bind(auth.user_id())
+            .fetch_one(&state.pool)
+            .await?;
+    if existing >= MAX_PASSKEYS_PER_USER {
+        return Err(AppError::Validation(format!(
+            "A user can have at most {MAX_PASSKEYS_PER_USER} passkeys"
+        )));
+    }
+
+    let transports: Vec<String> = response
+        .transports
+        .iter()
+        .filter(|t| KNOWN_TRANSPORTS.contains(&t.as_str()))
+        .cloned()
+        .collect();
+    let passkey = sqlx::query_as::<_, Passkey>(
+        "INSERT INTO webauthn_credentials
+             (user_id, credential_id, public_key, algorithm, sign_count, name, transports, backup_eligible)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+         ON CONFLICT (credential_id) DO NOTHING
+         RETURNING id, name, transports, backup_eligible, created_at, last_used_at",
+    )
+    .bind(auth.user_id())
+    .bind(webauthn::encode(&credential.credential_id))
+    .bind(&credential.public_key)
+    .bind(credential.algorithm as i32)
+    .bind(credential.sign_count as i64)
+    .bind(req.name.as_deref().unwrap_or(DEFAULT_PASSKEY_NAME).trim())
+    .bind(&transports)
+    .bind(credential.backup_eligible)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::Conflict("This passkey is already registered".into()))?;
+
+    info!("Passkey registered for user: {}", auth.user_id());
+    Ok((StatusCode::CREATED, Json(passkey)))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/users/@me/passkeys/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Passkey ID"),
+    ),
+    request_body = UpdatePasskeyRequest,
+    responses(
+        (status = 200, description = "Passkey renamed", body = Passkey),
+        (status = 404, description = "Passkey not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Users"
+)]
+/// PATCH /users/@me/passkeys/:id — rename a passkey.
+pub async fn update_passkey(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<UpdatePasskeyRequest>,
+) -> AppResult<Json<Passkey>> {
+    req.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let passkey = sqlx::query_as::<_, Passkey>(
+        "UPDATE webauthn_credentials SET name = $3
+         WHERE id = $1 AND user_id = $2
+         RETURNING id, name, transports, backup_eligible, created_at, last_used_at",
+    )
+    .bind(id)
+    .bind(auth.user_id())
+    .bind(req.name.trim())
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Passkey not found".into()))?;
+    Ok(Json(passkey))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/@me/passkeys/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Passkey ID"),
+    ),
+    responses(
+        (status = 204, description = "Passkey removed"),
+        (status = 403, description = "Last second factor, and 2FA is required on this instance"),
+        (status = 404, description = "Passkey not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Users"
+)]
+/// DELETE /users/@me/passkeys/:id — remove a passkey.
+pub async fn delete_passkey(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> AppResult<StatusCode> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(auth.user_id())
+        .fetch_one(&state.pool)
+        .await?;
+    let factors = two_factor::two_factor_state(&state.pool, &user).await?;
+    if factors.required && !factors.totp && factors.passkeys <= 1 {
+        return Err(AppError::Forbidden(
+            "Two-factor authentication is required on this server; \
+             add another passkey or an authenticator app first"
+                .into(),
+        ));
+    }
+
+    let deleted = sqlx::query("DELETE FROM webauthn_credentials WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(user.id)
+        .execute(&state.pool)
+        .await?;
+    if deleted.rows_affected() == 0 {
+        return Err(AppError::NotFound("Passkey not found".into()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ============================================================================
+// Login endpoints
+// ============================================================================
+
+#[utoipa::path(
+    post,
+    path = "/auth/passkey/options",
+    responses(
+        (status = 200, description = "Options for navigator.credentials.get()", body = PasskeyRequestOptions),
+        (status = 404, description = "Passkeys are not configured"),
+    ),
+    tag = "Auth"
+)]
+/// POST /auth/passkey/options — start a passwordless login.
+pub async fn passkey_login_options(
+    State(state): State<AppState>,
+) -> AppResult<Json<PasskeyRequestOptions>> {
+    let config = require_webauthn(&state)?;
+    let challenge = issue_challenge(&state.pool, None, "login").await?;
+    Ok(Json(request_options(
+        config,
+        challenge,
+        Vec::new(),
+        "required",
+    )))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/passkey/login",
+    request_body = PasskeyLoginRequest,
+    responses(
+        (status = 200, description = "Login successful", body = AuthResponse),
+        (status = 401, description = "Invalid passkey, signature or challenge"),
+        (status = 403, description = "Account disabled"),
+        (status = 404, description = "Passkeys are not configured"),
+    ),
+    tag = "Auth"
+)]
+/// POST /auth/passkey/login — log in with a passkey instead of a password.
+///
+/// The authenticator must verify the user (PIN or biometrics), so the
+/// passkey stands in for both the password and the second factor.
+pub async fn passkey_login(
+    State(state): State<AppState>,
+    Json(req): Json<PasskeyLoginRequest>,
+) -> AppResult<Json<AuthResponse>> {
+    let config = require_webauthn(&state)?;
+    let rp = relying_party(config);
+    let client_data_json =
+        decode_field(&req.credential.response.client_data_json, "clientDataJSON")?;
+
+    take_challenge(
+        &state.pool,
+        &rp,
+        &client_data_json,
+        Ceremony::Get,
+        "login",
+        AppError::Auth,
+    )
+    .await?;
+    let user = check_assertion(&state, &rp, &req.credential, &client_data_json, None, true).await?;
+
+    info!(
+        "Login successful (passkey): {} ({})",
+        user.username, user.id
+    );
+    Ok(Json(start_session(&state, user).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/two-factor/passkey/options",
+    request_body = ChallengeRequest,
+    responses(
+        (status = 200, description = "Options for navigator.credentials.get()", body = PasskeyRequestOptions),
+        (status = 400, description = "The user has no passkeys"),
+        (status = 401, description = "Invalid or expired challenge"),
+        (status = 404, description = "Passkeys are not configured"),
+    ),
+    tag = "Auth"
+)]
+/// POST /auth/two-factor/passkey/options — use a passkey for the second
+/// step of a password login.
+pub async fn passkey_second_factor_options(
+    State(state): State<AppState>,
+    Json(req): Json<ChallengeRequest>,
+) -> AppResult<Json<PasskeyRequestOptions>> {
+    let config = require_webauthn(&state)?;
+    req.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+    let (_, user) = find_challenge(&state, &req.challenge, ChallengePurpose::Verify).await?;
+
+    let allow_credentials = credential_descriptors(&state.pool, user.id).await?;
+    if allow_credentials.is_empty() {
+        return Err(AppError::Validation("No passkeys are registered".into()));
+    }
+    let challenge = issue_challenge(&state.pool, Some(user.id), "second_factor").await?;
+    Ok(Json(request_options(
+        config,
+        challenge,
+        allow_credentials,
+        "discouraged",
+    )))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/two-factor/passkey",
+    request_body = PasskeySecondFactorRequest,
+    responses(
+        (status = 200, description = "Login successful", body = AuthResponse),
+        (status = 401, description = "Invalid passkey or signature, or invalid or expired challenge"),
+        (status = 403, description = "Account disabled"),
+        (status = 404, description = "Passkeys are not configured"),
+    ),
+    tag = "Auth"
+)]
+/// POST /auth/two-factor/passkey — finish logging in with a passkey as the
+/// second factor. A failure counts against the login challenge like a wrong
+/// code.
+pub async fn verify_two_factor_passkey(
+    State(state): State<AppState>,
+    Json(req): Json<PasskeySecondFactorRequest>,
+) -> AppResult<Json<AuthResponse>> {
+    let config = require_webauthn(&state)?;
+    req.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+    let rp = relying_party(config);
+    let (challenge_id, user) =
+        find_challenge(&state, &req.challenge, ChallengePurpose::Verify).await?;
+    let client_data_json =
+        decode_field(&req.credential.response.client_data_json, "clientDataJSON")?;
+
+    let checked = async {
+        let owner = take_challenge(
+            &state.pool,
+            &rp,
+            &client_data_json,
+            Ceremony::Get,
+            "second_factor",
+            AppError::Auth,
+        )
+        .await?;
+        if owner != Some(user.id) {
+            return Err(AppError::Auth("Invalid or expired challenge".into()));
+        }
+        check_assertion(
+            &state,
+            &rp,
+            &req.credential,
+            &client_data_json,
+            Some(user.id),
+            false,
+        )
+        .await
+    }
+    .await;
+    let user = match checked {
+        Ok(user) => user,
+        Err(e) => {
+            if matches!(e, AppError::Auth(_)) {
+                record_failed_attempt(&state.pool, challenge_id).await?;
+            }
+            return Err(e);
+        }
+    };
+
+    let mut tx = state.pool.begin().await?;
+    claim_challenge(&mut tx, challenge_id).await?;
+    tx.commit().await?;
+
+    info!(
+        "Login successful (2FA passkey): {} ({})",
+        user.username, user.id
+    );
+    Ok(Json(start_session(&state, user).await?))
+}
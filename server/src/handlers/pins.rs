@@ -12,7 +12,7 @@ use crate::{
     models::MessageDto,
     state::AppState,
     websocket::{
-        broadcast_to_server,
+        broadcast_to_channel,
         events::{EVENT_MESSAGE_PIN, EVENT_MESSAGE_UNPIN},
     },
 };
@@ -68,9 +68,10 @@ pub async fn pin_message(
         return Ok(StatusCode::NO_CONTENT);
     }
 
-    broadcast_to_server(
+    broadcast_to_channel(
         &state,
         channel.server_id,
+        channel.id,
         EVENT_MESSAGE_PIN,
         serde_json::json!({
             "message_id": message_id,
@@ -128,9 +129,10 @@ pub async fn unpin_message(
         return Err(AppError::NotFound("Message is not pinned".into()));
     }
 
-    broadcast_to_server(
+    broadcast_to_channel(
         &state,
         channel.server_id,
+        channel.id,
         EVENT_MESSAGE_UNPIN,
         serde_json::json!({
             "message_id": message_id,
@@ -218,6 +220,9 @@ pub async fn list_pinned_messages(
             pinned_at: r.pinned_at,
             poll: None,
             event: None,
+            webhook: None,
+            embeds: Vec::new(),
+            forum_post: None,
         })
         .collect();
 
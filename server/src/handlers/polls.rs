@@ -12,7 +12,7 @@ use crate::{
     models::{CastVotePayload, CreatePollPayload, MessageDto, PollDto, PollOptionDto},
     state::AppState,
     websocket::{
-        broadcast_to_server,
+        broadcast_to_channel,
         events::{EVENT_MESSAGE_CREATE, EVENT_POLL_VOTE},
     },
 };
@@ -185,9 +185,10 @@ pub async fn create_poll(
     let mut dto = MessageDto::from_message(message);
     dto.poll = Some(poll_dto);
 
-    broadcast_to_server(
+    broadcast_to_channel(
         &state,
         channel.server_id,
+        channel.id,
         EVENT_MESSAGE_CREATE,
         serde_json::to_value(&dto).unwrap_or_default(),
     )
@@ -291,9 +292,10 @@ pub async fn cast_vote(
 
     let dto = fetch_poll_dto(&state.pool, poll_id, auth.user_id()).await?;
 
-    broadcast_to_server(
+    broadcast_to_channel(
         &state,
         poll.server_id,
+        poll.channel_id,
         EVENT_POLL_VOTE,
         json!({
             "poll_id": poll_id,
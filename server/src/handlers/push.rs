@@ -0,0 +1,251 @@
+//! Web Push subscription management. Delivery lives in `crate::push`.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    auth::AuthUser,
+    error::{AppError, AppResult},
+    models::{CreatePushSubscriptionRequest, PushPreferences, PushSubscriptionDto},
+    push::{decode_subscription_keys, resolve_public_endpoint, PushDispatcher},
+    state::AppState,
+};
+
+/// Subscriptions kept per user; one per browser or device.
+const MAX_SUBSCRIPTIONS_PER_USER: i64 = 20;
+const MAX_ENDPOINT_LEN: usize = 2048;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VapidPublicKeyResponse {
+    /// Pass as `applicationServerKey` to `pushManager.subscribe()`.
+    pub public_key: String,
+}
+
+fn require_push(state: &AppState) -> AppResult<&PushDispatcher> {
+    state
+        .push
+        .as_ref()
+        .ok_or_else(|| AppError::NotFound("Web Push is not configured on this server".into()))
+}
+
+/// Endpoints must be https URLs whose host resolves only to public
+/// addresses. A deployment that allows `http://` endpoints for testing also
+/// accepts private addresses. Delivery resolves the host again and connects
+/// to the address it checked.
+async fn validate_endpoint(push: &PushDispatcher, endpoint: &str) -> AppResult<()> {
+    if endpoint.len() > MAX_ENDPOINT_LEN {
+        return Err(AppError::Validation("endpoint is too long".into()));
+    }
+    let url = url::Url::parse(endpoint)
+        .map_err(|_| AppError::Validation("endpoint must be a URL".into()))?;
+    match url.scheme() {
+        "https" => {}
+        "http" if push.allow_http_endpoints() => {}
+        _ => return Err(AppError::Validation("endpoint must use https://".into())),
+    }
+    if url.host().is_none() {
+        return Err(AppError::Validation("endpoint has no host".into()));
+    }
+    if !push.allow_http_endpoints() {
+        resolve_public_endpoint(&url)
+            .await
+            .map_err(AppError::Validation)?;
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/push/vapid-public-key",
+    responses(
+        (status = 200, description = "Server's VAPID public key", body = VapidPublicKeyResponse),
+        (status = 404, description = "Web Push is not configured"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Push"
+)]
+/// GET /push/vapid-public-key — the key browsers subscribe with.
+pub async fn get_vapid_public_key(
+    State(state): State<AppState>,
+    _auth: AuthUser,
+) -> AppResult<Json<VapidPublicKeyResponse>> {
+    let push = require_push(&state)?;
+    Ok(Json(VapidPublicKeyResponse {
+        public_key: push.vapid_public_key().to_string(),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/@me/push-subscriptions",
+    request_body = CreatePushSubscriptionRequest,
+    responses(
+        (status = 201, description = "Subscription registered", body = PushSubscriptionDto),
+        (status = 400, description = "Invalid endpoint or keys, or too many subscriptions"),
+        (status = 404, description = "Web Push is not configured"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Push"
+)]
+/// POST /users/@me/push-subscriptions — register this browser for push.
+///
+/// Registering an endpoint that already exists replaces its keys and moves
+/// it to the current user.
+pub async fn create_push_subscription(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(req): Json<CreatePushSubscriptionRequest>,
+) -> AppResult<(StatusCode, Json<PushSubscriptionDto>)> {
+    let push = require_push(&state)?;
+    validate_endpoint(push, &req.endpoint).await?;
+    decode_subscription_keys(&req.keys.p256dh, &req.keys.auth).map_err(AppError::Validation)?;
+
+    let existing: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM push_subscriptions WHERE user_id = $1 AND endpoint <> $2",
+    )
+    .bind(auth.user_id())
+    .bind(&req.endpoint)
+    .fetch_one(&state.pool)
+    .await?;
+    if existing >= MAX_SUBSCRIPTIONS_PER_USER {
+        return Err(AppError::Validation(format!(
+            "A user can have at most {MAX_SUBSCRIPTIONS_PER_USER} push subscriptions"
+        )));
+    }
+
+    let subscription = sqlx::query_as::<_, PushSubscriptionDto>(
+        "INSERT INTO push_subscriptions (user_id, endpoint, p256dh, auth)
+         VALUES ($1, $2, $3, $4)
+         ON CONFLICT (endpoint) DO UPDATE SET
+             user_id         = EXCLUDED.user_id,
+             p256dh          = EXCLUDED.p256dh,
+             auth            = EXCLUDED.auth,
+             failure_count   = 0,
+             last_success_at = NULL,
+             created_at      = NOW()
+         RETURNING id, endpoint, created_at, last_success_at",
+    )
+    .bind(auth.user_id())
+    .bind(&req.endpoint)
+    .bind(&req.keys.p256dh)
+    .bind(&req.keys.auth)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(subscription)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/@me/push-subscriptions",
+    responses(
+        (status = 200, description = "The user's push subscriptions", body = Vec<PushSubscriptionDto>),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Push"
+)]
+/// GET /users/@me/push-subscriptions — list registered browsers.
+pub async fn list_push_subscriptions(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> AppResult<Json<Vec<PushSubscriptionDto>>> {
+    let subscriptions = sqlx::query_as::<_, PushSubscriptionDto>(
+        "SELECT id, endpoint, created_at, last_success_at
+         FROM push_subscriptions
+         WHERE user_id = $1
+         ORDER BY created_at",
+    )
+    .bind(auth.user_id())
+    .fetch_all(&state.pool)
+    .await?;
+    Ok(Json(subscriptions))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/@me/push-subscriptions/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Subscription ID"),
+    ),
+    responses(
+        (status = 204, description = "Subscription removed"),
+        (status = 404, description = "Subscription not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Push"
+)]
+/// DELETE /users/@me/push-subscriptions/:id — stop pushing to a browser.
+pub async fn delete_push_subscription(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> AppResult<StatusCode> {
+    let deleted = sqlx::query("DELETE FROM push_subscriptions WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(auth.user_id())
+        .execute(&state.pool)
+        .await?;
+    if deleted.rows_affected() == 0 {
+        return Err(AppError::NotFound("Push subscription not found".into()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/@me/push-preferences",
+    responses(
+        (status = 200, description = "The user's push preferences", body = PushPreferences),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Push"
+)]
+/// GET /users/@me/push-preferences
+pub async fn get_push_preferences(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> AppResult<Json<PushPreferences>> {
+    let enabled: Option<bool> =
+        sqlx::query_scalar("SELECT enabled FROM push_preferences WHERE user_id = $1")
+            .bind(auth.user_id())
+            .fetch_optional(&state.pool)
+            .await?;
+    Ok(Json(PushPreferences {
+        enabled: enabled.unwrap_or(true),
+    }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/users/@me/push-preferences",
+    request_body = PushPreferences,
+    responses(
+        (status = 200, description = "Updated push preferences", body = PushPreferences),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Push"
+)]
+/// PUT /users/@me/push-preferences — turn push off (or back on) for every
+/// subscription the user has. Subscriptions are kept while push is off.
+pub async fn update_push_preferences(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(req): Json<PushPreferences>,
+) -> AppResult<Json<PushPreferences>> {
+    sqlx::query(
+        "INSERT INTO push_preferences (user_id, enabled)
+         VALUES ($1, $2)
+         ON CONFLICT (user_id) DO UPDATE SET enabled = EXCLUDED.enabled, updated_at = NOW()",
+    )
+    .bind(auth.user_id())
+    .bind(req.enabled)
+    .execute(&state.pool)
+    .await?;
+    Ok(Json(req))
+}
@@ -15,7 +15,7 @@ use crate::{
     models::ReactionCount,
     state::AppState,
     websocket::{
-        broadcast_to_server,
+        broadcast_to_channel,
         events::{EVENT_REACTION_ADD, EVENT_REACTION_REMOVE},
     },
 };
@@ -97,9 +97,10 @@ pub async fn add_reaction(
     .execute(&state.pool)
     .await?;
 
-    broadcast_to_server(
+    broadcast_to_channel(
         &state,
         channel.server_id,
+        channel.id,
         EVENT_REACTION_ADD,
         serde_json::json!({
             "message_id": message_id,
@@ -161,9 +162,10 @@ pub async fn remove_reaction(
         return Err(AppError::NotFound("Reaction not found".into()));
     }
 
-    broadcast_to_server(
+    broadcast_to_channel(
         &state,
         channel.server_id,
+        channel.id,
         EVENT_REACTION_REMOVE,
         serde_json::json!({
             "message_id": message_id,
@@ -1,11 +1,293 @@
+//! Read states: per-user read position, unread counts and mention badges.
+//!
+//! The cursor is `channel_read_states.last_read_at`; counts are always
+//! derived from messages newer than it, never stored. Channels the user has
+//! never acked fall back to when they joined the server or DM, so new members
+//! do not see a server's whole history as unread.
+//!
+//! Every ack dispatches `READ_STATE_UPDATE` to all of the user's sessions so
+//! other tabs and devices clear their badges too.
+//!
+//! Counts respect notification settings: muted channels and channels set to
+//! `none` report zero, and `suppress_everyone` drops `@everyone` mentions.
+
 use axum::{
     extract::{Path, State},
     http::StatusCode,
+    Json,
 };
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
-use super::shared::{fetch_channel_by_id, require_member};
-use crate::{auth::AuthUser, error::AppResult, state::AppState};
+use super::{
+    notification_settings::{load_notification_settings, resolve, NotificationTarget},
+    shared::{
+        fetch_channel_by_id, require_channel_permission, require_member, PERMISSION_VIEW_CHANNEL,
+    },
+};
+use crate::{
+    auth::AuthUser,
+    error::{AppError, AppResult},
+    models::{AckChannelRequest, NotificationSetting, ReadStateDto},
+    state::AppState,
+    websocket::{broadcast_to_user_list, channel_viewers, events::EVENT_READ_STATE_UPDATE},
+};
+
+// ============================================================================
+// Count queries
+// ============================================================================
+
+/// Raw counts for one channel, before notification settings are applied.
+/// `server_id` is NULL for DM channels.
+#[derive(sqlx::FromRow)]
+struct CountRow {
+    channel_id: Uuid,
+    server_id: Option<Uuid>,
+    category: Option<String>,
+    last_read_at: Option<DateTime<Utc>>,
+    last_message_id: Option<Uuid>,
+    unread_count: i64,
+    /// Messages that mention the user by name.
+    direct_mentions: i64,
+    /// `@everyone` messages that do not also mention the user by name.
+    everyone_mentions: i64,
+}
+
+impl CountRow {
+    fn into_dto(self, settings: &[NotificationSetting], now: DateTime<Utc>) -> ReadStateDto {
+        let target = match self.server_id {
+            Some(server_id) => NotificationTarget::Channel {
+                server_id,
+                category: self.category.as_deref(),
+                channel_id: self.channel_id,
+            },
+            None => NotificationTarget::Dm {
+                channel_id: self.channel_id,
+            },
+        };
+        let effective = resolve(settings, target, now);
+        let (unread_count, mention_count) = if effective.is_silenced() {
+            (0, 0)
+        } else if effective.suppress_everyone {
+            (self.unread_count, self.direct_mentions)
+        } else {
+            (
+                self.unread_count,
+                self.direct_mentions + self.everyone_mentions,
+            )
+        };
+
+        ReadStateDto {
+            channel_id: self.channel_id,
+            last_read_at: self.last_read_at,
+            last_message_id: self.last_message_id,
+            unread_count,
+            mention_count,
+        }
+    }
+}
+
+/// Apply the user's notification settings to raw counts.
+async fn apply_settings(
+    pool: &sqlx::PgPool,
+    user_id: Uuid,
+    rows: Vec<CountRow>,
+) -> AppResult<Vec<ReadStateDto>> {
+    if rows.is_empty() {
+        return Ok(vec![]);
+    }
+    let settings = load_notification_settings(pool, user_id).await?;
+    let now = Utc::now();
+    Ok(rows
+        .into_iter()
+        .map(|row| row.into_dto(&settings, now))
+        .collect())
+}
+
+/// Counts for the given server channels. Channels in servers the user is
+/// not a member of are skipped.
+async fn server_counts(
+    pool: &sqlx::PgPool,
+    user_id: Uuid,
+    channel_ids: &[Uuid],
+) -> Result<Vec<CountRow>, sqlx::Error> {
+    sqlx::query_as::<_, CountRow>(
+        "SELECT c.id AS channel_id,
+                c.server_id,
+                c.category,
+                crs.last_read_at,
+                crs.last_message_id,
+                COUNT(m.id) AS unread_count,
+                COUNT(m.id) FILTER (
+                    WHERE $1 = ANY(m.mention_user_ids)
+                ) AS direct_mentions,
+                COUNT(m.id) FILTER (
+                    WHERE m.mention_everyone AND NOT ($1 = ANY(m.mention_user_ids))
+                ) AS everyone_mentions
+         FROM channels c
+         JOIN server_members sm ON sm.server_id = c.server_id AND sm.user_id = $1
+         LEFT JOIN channel_read_states crs ON crs.channel_id = c.id AND crs.user_id = $1
+         LEFT JOIN messages m ON m.channel_id = c.id
+             AND m.created_at > COALESCE(crs.last_read_at, sm.joined_at)
+             AND m.deleted = FALSE
+             AND m.author_id IS DISTINCT FROM $1
+         WHERE c.id = ANY($2)
+         GROUP BY c.id, crs.last_read_at, crs.last_message_id",
+    )
+    .bind(user_id)
+    .bind(channel_ids)
+    .fetch_all(pool)
+    .await
+}
+
+/// Counts for the user's DM channels, optionally limited to one.
+async fn dm_counts(
+    pool: &sqlx::PgPool,
+    user_id: Uuid,
+    channel_id: Option<Uuid>,
+) -> Result<Vec<CountRow>, sqlx::Error> {
+    sqlx::query_as::<_, CountRow>(
+        "SELECT dmm.channel_id,
+                NULL::UUID AS server_id,
+                NULL::TEXT AS category,
+                crs.last_read_at,
+                crs.last_message_id,
+                COUNT(dm.id) AS unread_count,
+                0::BIGINT AS direct_mentions,
+                0::BIGINT AS everyone_mentions
+         FROM direct_message_members dmm
+         LEFT JOIN channel_read_states crs
+             ON crs.channel_id = dmm.channel_id AND crs.user_id = $1
+         LEFT JOIN direct_messages dm ON dm.channel_id = dmm.channel_id
+             AND dm.created_at > COALESCE(crs.last_read_at, dmm.joined_at)
+             AND dm.deleted = FALSE
+             AND dm.author_id IS DISTINCT FROM $1
+         WHERE dmm.user_id = $1
+           AND ($2::UUID IS NULL OR dmm.channel_id = $2)
+         GROUP BY dmm.channel_id, crs.last_read_at, crs.last_message_id",
+    )
+    .bind(user_id)
+    .bind(channel_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Message-bearing channels the user can view, across all their servers or
+/// just `server_id`.
+async fn visible_channel_ids(
+    state: &AppState,
+    user_id: Uuid,
+    server_id: Option<Uuid>,
+) -> AppResult<Vec<Uuid>> {
+    let channels: Vec<(Uuid, Uuid)> = sqlx::query_as(
+        "SELECT c.id, c.server_id
+         FROM channels c
+         JOIN server_members sm ON sm.server_id = c.server_id AND sm.user_id = $1
+         WHERE c.type <> 'voice'
+           AND ($2::UUID IS NULL OR c.server_id = $2)",
+    )
+    .bind(user_id)
+    .bind(server_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let mut visible = Vec::with_capacity(channels.len());
+    for (channel_id, server_id) in channels {
+        if channel_viewers(state, server_id, channel_id)
+            .await?
+            .contains(&user_id)
+        {
+            visible.push(channel_id);
+        }
+    }
+    Ok(visible)
+}
+
+/// Read states for every channel the user can see, for the READY payload.
+pub async fn ready_read_states(state: &AppState, user_id: Uuid) -> AppResult<Vec<ReadStateDto>> {
+    let channel_ids = visible_channel_ids(state, user_id, None).await?;
+    let mut rows = server_counts(&state.pool, user_id, &channel_ids).await?;
+    rows.extend(dm_counts(&state.pool, user_id, None).await?);
+    apply_settings(&state.pool, user_id, rows).await
+}
+
+/// Send the user's new read states to all of their sessions.
+async fn dispatch_read_states(state: &AppState, user_id: Uuid, read_states: Vec<ReadStateDto>) {
+    if read_states.is_empty() {
+        return;
+    }
+    broadcast_to_user_list(
+        state,
+        &[user_id],
+        EVENT_READ_STATE_UPDATE,
+        serde_json::json!({ "read_states": read_states }),
+    )
+    .await;
+}
+
+/// Resolve the ack cursor: the given message's timestamp, or now with the
+/// newest message as `last_message_id`.
+///
+/// `table` is a fixed table name, never user input.
+async fn resolve_ack_target(
+    pool: &sqlx::PgPool,
+    table: &str,
+    channel_id: Uuid,
+    message_id: Option<Uuid>,
+) -> AppResult<(DateTime<Utc>, Option<Uuid>)> {
+    match message_id {
+        Some(message_id) => {
+            let created_at: DateTime<Utc> = sqlx::query_scalar(&format!(
+                "SELECT created_at FROM {table} WHERE id = $1 AND channel_id = $2"
+            ))
+            .bind(message_id)
+            .bind(channel_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Message not found".into()))?;
+            Ok((created_at, Some(message_id)))
+        }
+        None => {
+            let latest: Option<Uuid> = sqlx::query_scalar(&format!(
+                "SELECT id FROM {table}
+                 WHERE channel_id = $1 AND deleted = FALSE
+                 ORDER BY created_at DESC
+                 LIMIT 1"
+            ))
+            .bind(channel_id)
+            .fetch_optional(pool)
+            .await?;
+            Ok((Utc::now(), latest))
+        }
+    }
+}
+
+async fn upsert_read_state(
+    pool: &sqlx::PgPool,
+    user_id: Uuid,
+    channel_id: Uuid,
+    last_read_at: DateTime<Utc>,
+    last_message_id: Option<Uuid>,
+) -> AppResult<()> {
+    sqlx::query(
+        "INSERT INTO channel_read_states (user_id, channel_id, last_read_at, last_message_id)
+         VALUES ($1, $2, $3, $4)
+         ON CONFLICT (user_id, channel_id)
+         DO UPDATE SET last_read_at    = EXCLUDED.last_read_at,
+                       last_message_id = EXCLUDED.last_message_id",
+    )
+    .bind(user_id)
+    .bind(channel_id)
+    .bind(last_read_at)
+    .bind(last_message_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
 
 #[utoipa::path(
     post,
@@ -13,35 +295,54 @@ use crate::{auth::AuthUser, error::AppResult, state::AppState};
     params(
         ("channel_id" = Uuid, Path, description = "Channel ID"),
     ),
+    request_body(content = Option<AckChannelRequest>, description = "Optional message to ack up to"),
     responses(
         (status = 204, description = "Channel marked as read"),
+        (status = 404, description = "Channel or message not found"),
     ),
     security(("bearer_auth" = [])),
     tag = "ReadStates"
 )]
 /// POST /channels/:channel_id/ack — mark a server channel as read.
 ///
-/// Upserts the user's read-state to `NOW()`.  Called by the client whenever
-/// the user views a channel (tab focus, navigation).
+/// Without a body the whole channel is read. With `message_id`, the cursor
+/// moves to that message — earlier or later than the current one — so
+/// clients can also "mark unread from here". Dispatches `READ_STATE_UPDATE`
+/// to the user's sessions.
 pub async fn ack_channel(
     State(state): State<AppState>,
     auth: AuthUser,
     Path(channel_id): Path<Uuid>,
+    body: Option<Json<AckChannelRequest>>,
 ) -> AppResult<StatusCode> {
     let channel = fetch_channel_by_id(&state.pool, channel_id).await?;
     require_member(&state.pool, channel.server_id, auth.user_id()).await?;
+    require_channel_permission(
+        &state.pool,
+        channel.server_id,
+        channel_id,
+        auth.user_id(),
+        PERMISSION_VIEW_CHANNEL,
+        "You don't have permission to view this channel",
+    )
+    .await?;
 
-    sqlx::query(
-        "INSERT INTO channel_read_states (user_id, channel_id, last_read_at)
-         VALUES ($1, $2, NOW())
-         ON CONFLICT (user_id, channel_id)
-         DO UPDATE SET last_read_at = EXCLUDED.last_read_at",
+    let message_id = body.and_then(|b| b.0.message_id);
+    let (last_read_at, last_message_id) =
+        resolve_ack_target(&state.pool, "messages", channel_id, message_id).await?;
+    upsert_read_state(
+        &state.pool,
+        auth.user_id(),
+        channel_id,
+        last_read_at,
+        last_message_id,
     )
-    .bind(auth.user_id())
-    .bind(channel_id)
-    .execute(&state.pool)
     .await?;
 
+    let rows = server_counts(&state.pool, auth.user_id(), &[channel_id]).await?;
+    let read_states = apply_settings(&state.pool, auth.user_id(), rows).await?;
+    dispatch_read_states(&state, auth.user_id(), read_states).await;
+
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -51,17 +352,22 @@ pub async fn ack_channel(
     params(
         ("id" = Uuid, Path, description = "DM channel ID"),
     ),
+    request_body(content = Option<AckChannelRequest>, description = "Optional message to ack up to"),
     responses(
         (status = 204, description = "DM channel marked as read"),
+        (status = 404, description = "DM channel or message not found"),
     ),
     security(("bearer_auth" = [])),
     tag = "ReadStates"
 )]
 /// POST /dm-channels/:channel_id/ack — mark a DM channel as read.
+///
+/// Accepts the same optional `message_id` as [`ack_channel`].
 pub async fn ack_dm_channel(
     State(state): State<AppState>,
     auth: AuthUser,
     Path(channel_id): Path<Uuid>,
+    body: Option<Json<AckChannelRequest>>,
 ) -> AppResult<StatusCode> {
     // Verify membership.
     let is_member: bool = sqlx::query_scalar(
@@ -76,21 +382,75 @@ pub async fn ack_dm_channel(
     .await?;
 
     if !is_member {
-        return Err(crate::error::AppError::NotFound(
-            "DM channel not found".into(),
-        ));
+        return Err(AppError::NotFound("DM channel not found".into()));
+    }
+
+    let message_id = body.and_then(|b| b.0.message_id);
+    let (last_read_at, last_message_id) =
+        resolve_ack_target(&state.pool, "direct_messages", channel_id, message_id).await?;
+    upsert_read_state(
+        &state.pool,
+        auth.user_id(),
+        channel_id,
+        last_read_at,
+        last_message_id,
+    )
+    .await?;
+
+    let rows = dm_counts(&state.pool, auth.user_id(), Some(channel_id)).await?;
+    let read_states = apply_settings(&state.pool, auth.user_id(), rows).await?;
+    dispatch_read_states(&state, auth.user_id(), read_states).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/servers/{id}/ack",
+    params(
+        ("id" = Uuid, Path, description = "Server ID"),
+    ),
+    responses(
+        (status = 204, description = "Every visible channel in the server marked as read"),
+        (status = 404, description = "Server not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "ReadStates"
+)]
+/// POST /servers/:id/ack — mark every channel the user can see in a server
+/// as read. Dispatches one `READ_STATE_UPDATE` covering all of them.
+pub async fn ack_server(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(server_id): Path<Uuid>,
+) -> AppResult<StatusCode> {
+    require_member(&state.pool, server_id, auth.user_id()).await?;
+
+    let channel_ids = visible_channel_ids(&state, auth.user_id(), Some(server_id)).await?;
+    if channel_ids.is_empty() {
+        return Ok(StatusCode::NO_CONTENT);
     }
 
     sqlx::query(
-        "INSERT INTO channel_read_states (user_id, channel_id, last_read_at)
-         VALUES ($1, $2, NOW())
+        "INSERT INTO channel_read_states (user_id, channel_id, last_read_at, last_message_id)
+         SELECT $1, c.id, NOW(),
+                (SELECT m.id FROM messages m
+                 WHERE m.channel_id = c.id AND m.deleted = FALSE
+                 ORDER BY m.created_at DESC
+                 LIMIT 1)
+         FROM UNNEST($2::UUID[]) AS c(id)
          ON CONFLICT (user_id, channel_id)
-         DO UPDATE SET last_read_at = EXCLUDED.last_read_at",
+         DO UPDATE SET last_read_at    = EXCLUDED.last_read_at,
+                       last_message_id = EXCLUDED.last_message_id",
     )
     .bind(auth.user_id())
-    .bind(channel_id)
+    .bind(&channel_ids)
     .execute(&state.pool)
     .await?;
 
+    let rows = server_counts(&state.pool, auth.user_id(), &channel_ids).await?;
+    let read_states = apply_settings(&state.pool, auth.user_id(), rows).await?;
+    dispatch_read_states(&state, auth.user_id(), read_states).await;
+
     Ok(StatusCode::NO_CONTENT)
 }
@@ -27,6 +27,7 @@ use crate::{
             EVENT_MEMBER_ROLE_ADD, EVENT_MEMBER_ROLE_REMOVE, EVENT_ROLE_CREATE, EVENT_ROLE_DELETE,
             EVENT_ROLE_UPDATE,
         },
+        invalidate_channel_viewers,
     },
 };
 
@@ -310,6 +311,10 @@ pub async fn update_role(
     .fetch_one(&state.pool)
     .await?;
 
+    if req.permissions.is_some() {
+        invalidate_channel_viewers(&state, server_id).await;
+    }
+
     match serde_json::to_value(&updated) {
         Ok(payload) => {
             broadcast_to_server(&state, server_id, EVENT_ROLE_UPDATE, payload).await;
@@ -401,6 +406,7 @@ pub async fn delete_role(
         .bind(role_id)
         .execute(&state.pool)
         .await?;
+    invalidate_channel_viewers(&state, server_id).await;
 
     let payload = json!({ "server_id": server_id, "role_id": role_id });
     broadcast_to_server(&state, server_id, EVENT_ROLE_DELETE, payload).await;
@@ -491,6 +497,7 @@ pub async fn assign_role(
     .bind(role_id)
     .execute(&state.pool)
     .await?;
+    invalidate_channel_viewers(&state, server_id).await;
 
     let payload = json!({
         "server_id": server_id,
@@ -590,6 +597,7 @@ pub async fn remove_role(
         .bind(role_id)
         .execute(&state.pool)
         .await?;
+    invalidate_channel_viewers(&state, server_id).await;
 
     let payload = json!({
         "server_id": server_id,
@@ -0,0 +1,306 @@
+//! Scheduled messages and reminders.
+//!
+//! Provides:
+//! - `POST /channels/:channel_id/scheduled-messages` — Queue a message or reminder
+//! - `GET /users/@me/scheduled-messages` — List the caller's queued messages
+//! - `PATCH /users/@me/scheduled-messages/:id` — Edit or re-queue a queued message
+//! - `DELETE /users/@me/scheduled-messages/:id` — Cancel a queued message
+//!
+//! Messages are posted by the background worker in `crate::scheduled_messages`.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use super::shared::{
+    fetch_channel_by_id, require_channel_permission, require_member, PERMISSION_SEND_MESSAGES,
+};
+use crate::{
+    auth::AuthUser,
+    error::{AppError, AppResult},
+    models::{
+        ChannelType, CreateScheduledMessageDto, ScheduledMessage, ScheduledMessageKind,
+        ScheduledMessageStatus, UpdateScheduledMessageDto,
+    },
+    state::AppState,
+};
+
+/// Queued (pending or failed) messages a user may have at once.
+const MAX_SCHEDULED_PER_USER: i64 = 100;
+/// How far ahead a message can be scheduled.
+const MAX_SCHEDULE_DAYS: i64 = 365;
+/// Reminders are posted with a `@username Reminder: ` prefix, so leave room
+/// for it under the 4 000 character message limit.
+const MAX_REMINDER_CHARS: usize = 3900;
+
+const SCHEDULED_COLUMNS: &str = "id, channel_id, author_id, kind, content, reply_to, send_at,
+     status, last_error, created_at, updated_at";
+
+fn validate_content(kind: ScheduledMessageKind, content: &str) -> AppResult<()> {
+    let max = match kind {
+        ScheduledMessageKind::Message => 4000,
+        ScheduledMessageKind::Reminder => MAX_REMINDER_CHARS,
+    };
+    let len = content.chars().count();
+    if content.trim().is_empty() || len > max {
+        return Err(AppError::Validation(format!(
+            "Message content must be 1–{max} characters"
+        )));
+    }
+    Ok(())
+}
+
+fn validate_send_at(send_at: DateTime<Utc>) -> AppResult<()> {
+    let now = Utc::now();
+    if send_at <= now {
+        return Err(AppError::Validation("send_at must be in the future".into()));
+    }
+    if send_at > now + chrono::Duration::days(MAX_SCHEDULE_DAYS) {
+        return Err(AppError::Validation(format!(
+            "send_at must be within {MAX_SCHEDULE_DAYS} days"
+        )));
+    }
+    Ok(())
+}
+
+async fn fetch_own(state: &AppState, id: Uuid, user_id: Uuid) -> AppResult<ScheduledMessage> {
+    sqlx::query_as::<_, ScheduledMessage>(&format!(
+        "SELECT {SCHEDULED_COLUMNS} FROM scheduled_messages WHERE id = $1 AND author_id = $2"
+    ))
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Scheduled message not found".into()))
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+/// POST /channels/:channel_id/scheduled-messages — queue a message for later.
+///
+/// Membership and SEND_MESSAGES are checked now so obvious mistakes fail
+/// early, and again at send time since they can change in between.
+#[utoipa::path(
+    post,
+    path = "/channels/{channel_id}/scheduled-messages",
+    request_body = CreateScheduledMessageDto,
+    params(("channel_id" = Uuid, Path, description = "Channel ID")),
+    responses(
+        (status = 201, description = "Message scheduled", body = ScheduledMessage),
+        (status = 400, description = "Validation error"),
+        (status = 403, description = "No permission to send messages"),
+        (status = 404, description = "Channel not found")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Messages"
+)]
+pub async fn create_scheduled_message(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(channel_id): Path<Uuid>,
+    Json(req): Json<CreateScheduledMessageDto>,
+) -> AppResult<(StatusCode, Json<ScheduledMessage>)> {
+    validate_content(req.kind, &req.content)?;
+    validate_send_at(req.send_at)?;
+
+    let channel = fetch_channel_by_id(&state.pool, channel_id).await?;
+    require_member(&state.pool, channel.server_id, auth.user_id()).await?;
+    if matches!(channel.r#type, ChannelType::Forum) {
+        return Err(AppError::Validation(
+            "Messages cannot be scheduled in forum channels".into(),
+        ));
+    }
+    require_channel_permission(
+        &state.pool,
+        channel.server_id,
+        channel_id,
+        auth.user_id(),
+        PERMISSION_SEND_MESSAGES,
+        "You don't have permission to send messages in this channel",
+    )
+    .await?;
+
+    if let Some(reply_to_id) = req.reply_to {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS(
+                 SELECT 1 FROM messages
+                 WHERE id = $1 AND channel_id = $2 AND deleted = FALSE
+             )",
+        )
+        .bind(reply_to_id)
+        .bind(channel_id)
+        .fetch_one(&state.pool)
+        .await?;
+
+        if !exists {
+            return Err(AppError::NotFound("Reply target message not found".into()));
+        }
+    }
+
+    let queued: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM scheduled_messages WHERE author_id = $1")
+            .bind(auth.user_id())
+            .fetch_one(&state.pool)
+            .await?;
+    if queued >= MAX_SCHEDULED_PER_USER {
+        return Err(AppError::Validation(format!(
+            "You can have at most {MAX_SCHEDULED_PER_USER} scheduled messages"
+        )));
+    }
+
+    let scheduled = sqlx::query_as::<_, ScheduledMessage>(&format!(
+        "INSERT INTO scheduled_messages (channel_id, author_id, kind, content, reply_to, send_at)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         RETURNING {SCHEDULED_COLUMNS}"
+    ))
+    .bind(channel_id)
+    .bind(auth.user_id())
+    .bind(req.kind)
+    .bind(&req.content)
+    .bind(req.reply_to)
+    .bind(req.send_at)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(scheduled)))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ListScheduledMessagesQuery {
+    /// Only messages queued for this channel.
+    pub channel_id: Option<Uuid>,
+}
+
+/// GET /users/@me/scheduled-messages — the caller's queued messages, soonest
+/// first. Posted messages are removed from the queue; failed ones stay until
+/// edited or deleted.
+#[utoipa::path(
+    get,
+    path = "/users/@me/scheduled-messages",
+    params(ListScheduledMessagesQuery),
+    responses(
+        (status = 200, description = "Queued messages", body = Vec<ScheduledMessage>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Messages"
+)]
+pub async fn list_scheduled_messages(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Query(query): Query<ListScheduledMessagesQuery>,
+) -> AppResult<Json<Vec<ScheduledMessage>>> {
+    let messages = sqlx::query_as::<_, ScheduledMessage>(&format!(
+        "SELECT {SCHEDULED_COLUMNS} FROM scheduled_messages
+         WHERE author_id = $1 AND ($2::uuid IS NULL OR channel_id = $2)
+         ORDER BY send_at ASC, id ASC"
+    ))
+    .bind(auth.user_id())
+    .bind(query.channel_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(messages))
+}
+
+/// PATCH /users/@me/scheduled-messages/:id — edit a queued message.
+///
+/// Editing a failed message re-queues it; if its time has passed and no new
+/// `send_at` is given, it is posted on the scheduler's next poll.
+#[utoipa::path(
+    patch,
+    path = "/users/@me/scheduled-messages/{id}",
+    request_body = UpdateScheduledMessageDto,
+    params(("id" = Uuid, Path, description = "Scheduled message ID")),
+    responses(
+        (status = 200, description = "Updated scheduled message", body = ScheduledMessage),
+        (status = 400, description = "Validation error"),
+        (status = 404, description = "Scheduled message not found"),
+        (status = 409, description = "Message is being sent")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Messages"
+)]
+pub async fn update_scheduled_message(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<UpdateScheduledMessageDto>,
+) -> AppResult<Json<ScheduledMessage>> {
+    let existing = fetch_own(&state, id, auth.user_id()).await?;
+
+    if let Some(content) = &req.content {
+        validate_content(existing.kind, content)?;
+    }
+    if let Some(send_at) = req.send_at {
+        validate_send_at(send_at)?;
+    }
+
+    // The status guard makes this a no-op if the scheduler claimed the row
+    // after it was read above.
+    let updated = sqlx::query_as::<_, ScheduledMessage>(&format!(
+        "UPDATE scheduled_messages
+         SET content = COALESCE($3, content),
+             send_at = COALESCE($4, send_at),
+             status = 'pending',
+             attempt_count = 0,
+             last_error = NULL,
+             updated_at = NOW()
+         WHERE id = $1 AND author_id = $2 AND status <> 'sending'
+         RETURNING {SCHEDULED_COLUMNS}"
+    ))
+    .bind(id)
+    .bind(auth.user_id())
+    .bind(req.content)
+    .bind(req.send_at)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::Conflict("Message is being sent".into()))?;
+
+    Ok(Json(updated))
+}
+
+/// DELETE /users/@me/scheduled-messages/:id — cancel a queued message.
+#[utoipa::path(
+    delete,
+    path = "/users/@me/scheduled-messages/{id}",
+    params(("id" = Uuid, Path, description = "Scheduled message ID")),
+    responses(
+        (status = 204, description = "Scheduled message cancelled"),
+        (status = 404, description = "Scheduled message not found"),
+        (status = 409, description = "Message is being sent")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Messages"
+)]
+pub async fn delete_scheduled_message(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> AppResult<StatusCode> {
+    let existing = fetch_own(&state, id, auth.user_id()).await?;
+    if existing.status == ScheduledMessageStatus::Sending {
+        return Err(AppError::Conflict("Message is being sent".into()));
+    }
+
+    let result = sqlx::query(
+        "DELETE FROM scheduled_messages
+         WHERE id = $1 AND author_id = $2 AND status <> 'sending'",
+    )
+    .bind(id)
+    .bind(auth.user_id())
+    .execute(&state.pool)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::Conflict("Message is being sent".into()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
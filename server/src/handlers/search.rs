@@ -1,7 +1,8 @@
 //! Message search handler using PostgreSQL full-text search.
 //!
 //! Provides server-wide or channel-scoped message search with relevance ranking
-//! and result highlighting.
+//! and result highlighting. Forum post titles are searched alongside message
+//! content; a title match returns the post's root message.
 
 use axum::{
     extract::{Path, Query, State},
@@ -29,6 +30,17 @@ const DEFAULT_LIMIT: i64 = 50;
 /// Maximum number of results per page.
 const MAX_LIMIT: i64 = 100;
 
+/// Ids of messages whose content, or forum post title, matches `$1`. Each
+/// branch uses its own GIN index.
+const MATCHING_IDS: &str = "
+    WITH hits AS (
+        SELECT id FROM messages
+        WHERE to_tsvector('english', content) @@ plainto_tsquery('english', $1)
+        UNION
+        SELECT message_id FROM forum_posts
+        WHERE to_tsvector('english', title) @@ plainto_tsquery('english', $1)
+    )";
+
 // ============================================================================
 // Handler
 // ============================================================================
@@ -81,30 +93,37 @@ pub async fn search_messages(
     // Search with optional channel filter
     let results: Vec<SearchRow> = if let Some(channel_id) = params.channel_id {
         // Channel-scoped search — join channels to verify the channel belongs to this server
-        sqlx::query_as::<_, SearchRow>(
+        sqlx::query_as::<_, SearchRow>(&format!(
             r#"
+            {MATCHING_IDS}
             SELECT
                 m.id,
                 m.channel_id,
                 m.author_id,
                 u.username AS author_username,
                 m.content,
+                m.thread_id,
+                fp.title AS post_title,
                 ts_headline('english', m.content, plainto_tsquery('english', $1),
                     'StartSel=<mark> StopSel=</mark> MaxWords=35 MinWords=15') AS highlight,
                 m.created_at,
-                ts_rank_cd(to_tsvector('english', m.content), plainto_tsquery('english', $1)) AS rank
-            FROM messages m
+                (ts_rank_cd(to_tsvector('english', m.content), plainto_tsquery('english', $1))
+                 + CASE WHEN fp.message_id = m.id
+                        THEN ts_rank_cd(to_tsvector('english', fp.title), plainto_tsquery('english', $1))
+                        ELSE 0 END)::real AS rank
+            FROM hits h
+            JOIN messages m ON m.id = h.id
             LEFT JOIN users u ON m.author_id = u.id
+            LEFT JOIN forum_posts fp ON fp.message_id = COALESCE(m.thread_id, m.id)
             JOIN channels c ON m.channel_id = c.id
             WHERE m.deleted = FALSE
               AND m.channel_id = $2
               AND c.server_id = $3
-              AND to_tsvector('english', m.content) @@ plainto_tsquery('english', $1)
               AND ($4::uuid IS NULL OR m.created_at < (SELECT created_at FROM messages WHERE id = $4))
             ORDER BY rank DESC, m.created_at DESC
             LIMIT $5
-            "#,
-        )
+            "#
+        ))
         .bind(&params.q)
         .bind(channel_id)
         .bind(server_id)
@@ -114,29 +133,36 @@ pub async fn search_messages(
         .await?
     } else {
         // Server-wide search
-        sqlx::query_as::<_, SearchRow>(
+        sqlx::query_as::<_, SearchRow>(&format!(
             r#"
+            {MATCHING_IDS}
             SELECT
                 m.id,
                 m.channel_id,
                 m.author_id,
                 u.username AS author_username,
                 m.content,
+                m.thread_id,
+                fp.title AS post_title,
                 ts_headline('english', m.content, plainto_tsquery('english', $1),
                     'StartSel=<mark> StopSel=</mark> MaxWords=35 MinWords=15') AS highlight,
                 m.created_at,
-                ts_rank_cd(to_tsvector('english', m.content), plainto_tsquery('english', $1)) AS rank
-            FROM messages m
+                (ts_rank_cd(to_tsvector('english', m.content), plainto_tsquery('english', $1))
+                 + CASE WHEN fp.message_id = m.id
+                        THEN ts_rank_cd(to_tsvector('english', fp.title), plainto_tsquery('english', $1))
+                        ELSE 0 END)::real AS rank
+            FROM hits h
+            JOIN messages m ON m.id = h.id
             LEFT JOIN users u ON m.author_id = u.id
+            LEFT JOIN forum_posts fp ON fp.message_id = COALESCE(m.thread_id, m.id)
             JOIN channels c ON m.channel_id = c.id
             WHERE m.deleted = FALSE
               AND c.server_id = $2
-              AND to_tsvector('english', m.content) @@ plainto_tsquery('english', $1)
               AND ($3::uuid IS NULL OR m.created_at < (SELECT created_at FROM messages WHERE id = $3))
             ORDER BY rank DESC, m.created_at DESC
             LIMIT $4
-            "#,
-        )
+            "#
+        ))
         .bind(&params.q)
         .bind(server_id)
         .bind(params.before)
@@ -147,35 +173,37 @@ pub async fn search_messages(
 
     // Get total count — exact COUNT(*), but runs a second full-text query
     let total: i64 = if let Some(channel_id) = params.channel_id {
-        sqlx::query_scalar(
+        sqlx::query_scalar(&format!(
             r#"
+            {MATCHING_IDS}
             SELECT COUNT(*)
-            FROM messages m
+            FROM hits h
+            JOIN messages m ON m.id = h.id
             JOIN channels c ON m.channel_id = c.id
             WHERE m.deleted = FALSE
-              AND m.channel_id = $1
-              AND c.server_id = $2
-              AND to_tsvector('english', m.content) @@ plainto_tsquery('english', $3)
-            "#,
-        )
+              AND m.channel_id = $2
+              AND c.server_id = $3
+            "#
+        ))
+        .bind(&params.q)
         .bind(channel_id)
         .bind(server_id)
-        .bind(&params.q)
         .fetch_one(&state.pool)
         .await?
     } else {
-        sqlx::query_scalar(
+        sqlx::query_scalar(&format!(
             r#"
+            {MATCHING_IDS}
             SELECT COUNT(*)
-            FROM messages m
+            FROM hits h
+            JOIN messages m ON m.id = h.id
             JOIN channels c ON m.channel_id = c.id
             WHERE m.deleted = FALSE
-              AND c.server_id = $1
-              AND to_tsvector('english', m.content) @@ plainto_tsquery('english', $2)
-            "#,
-        )
-        .bind(server_id)
+              AND c.server_id = $2
+            "#
+        ))
         .bind(&params.q)
+        .bind(server_id)
         .fetch_one(&state.pool)
         .await?
     };
@@ -213,6 +241,8 @@ struct SearchRow {
     author_id: Option<Uuid>,
     author_username: Option<String>,
     content: String,
+    thread_id: Option<Uuid>,
+    post_title: Option<String>,
     highlight: String,
     created_at: DateTime<Utc>,
     rank: f32,
@@ -226,6 +256,8 @@ impl From<SearchRow> for SearchResult {
             author_id: row.author_id,
             author_username: row.author_username,
             content: row.content,
+            thread_id: row.thread_id,
+            post_title: row.post_title,
             highlight: row.highlight,
             created_at: row.created_at,
             rank: row.rank,
@@ -20,6 +20,7 @@ use crate::{
         UpdateServerDto,
     },
     state::AppState,
+    websocket::invalidate_channel_viewers,
 };
 
 // ============================================================================
@@ -503,6 +504,7 @@ pub async fn join_server(
         .bind(server_id)
         .execute(&state.pool)
         .await?;
+    invalidate_channel_viewers(&state, server_id).await;
 
     Ok((
         StatusCode::CREATED,
@@ -546,6 +548,7 @@ pub async fn leave_server(
         .bind(auth.user_id())
         .execute(&state.pool)
         .await?;
+    invalidate_channel_viewers(&state, server_id).await;
 
     Ok(StatusCode::NO_CONTENT)
 }
@@ -129,7 +129,7 @@ pub async fn require_member(
 // Permission bitflag constants (mirrors migrations/20240216000003_roles_and_permissions.sql)
 pub const PERMISSION_VIEW_CHANNEL: i64 = 1; // bit 0
 pub const PERMISSION_SEND_MESSAGES: i64 = 2; // bit 1
-const PERMISSION_MANAGE_MESSAGES: i64 = 4; // bit 2
+pub const PERMISSION_MANAGE_MESSAGES: i64 = 4; // bit 2
 pub const PERMISSION_ATTACH_FILES: i64 = 8; // bit 3
 pub const PERMISSION_ADD_REACTIONS: i64 = 16; // bit 4
 pub const PERMISSION_CONNECT_VOICE: i64 = 32; // bit 5
@@ -235,10 +235,11 @@ pub async fn require_manage_emojis(
 }
 
 /// Sanitize a filename for safe use inside a `Content-Disposition` header.
-/// Replaces `"` with `'` and strips `\r`, `\n`, `\0` to prevent header injection.
+/// Replaces `"` with `'` and strips control characters, which would allow
+/// header injection or make the header value invalid.
 pub fn sanitize_header_filename(name: &str) -> String {
     name.chars()
-        .filter(|c| !matches!(c, '\r' | '\n' | '\0'))
+        .filter(|c| !c.is_control())
         .map(|c| if c == '"' { '\'' } else { c })
         .collect()
 }
@@ -295,7 +296,8 @@ pub async fn require_permission(
 /// - Cannot target self (400)
 /// - Cannot target the server owner (403)
 /// - Server owner always passes
-/// - Otherwise requires the specified permission bit or ADMINISTRATOR
+/// - Otherwise requires the specified permission bit or ADMINISTRATOR, and
+///   the actor's highest role must sit above the target's (403)
 pub async fn can_moderate(
     pool: &sqlx::PgPool,
     server_id: Uuid,
@@ -338,13 +340,21 @@ pub async fn can_moderate(
     .fetch_one(pool)
     .await?;
 
-    if has_perm {
-        Ok(())
-    } else {
-        Err(AppError::Forbidden(
+    if !has_perm {
+        return Err(AppError::Forbidden(
             "You lack the required permission for this action".into(),
-        ))
+        ));
+    }
+
+    let actor_highest = get_user_highest_position(pool, server_id, actor_id).await?;
+    let target_highest = get_user_highest_position(pool, server_id, target_id).await?;
+    if actor_highest <= target_highest {
+        return Err(AppError::Forbidden(
+            "Cannot moderate a member whose highest role is at or above yours".into(),
+        ));
     }
+
+    Ok(())
 }
 
 /// Return the highest role position held by `user_id` in `server_id`, or 0
@@ -407,67 +417,77 @@ pub async fn compute_channel_permissions(
     channel_id: Uuid,
     user_id: Uuid,
 ) -> AppResult<i64> {
-    // 1. Server owner gets all bits.
     let is_owner: bool =
         sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM servers WHERE id = $1 AND owner_id = $2)")
             .bind(server_id)
             .bind(user_id)
             .fetch_one(pool)
             .await?;
-
-    if is_owner {
-        return Ok(i64::MAX);
-    }
-
-    // 2. Base permissions from server-level roles.
-    // Default permissions for all members (equivalent to Discord's @everyone):
-    // VIEW_CHANNEL | SEND_MESSAGES | ADD_REACTIONS | ATTACH_FILES | CONNECT_VOICE | SPEAK
-    const DEFAULT_MEMBER_PERMS: i64 = 1 | 2 | 16 | 8 | 32 | 64; // 123
     let role_perms = get_user_permissions(pool, server_id, user_id).await?;
-    let base_perms = role_perms | DEFAULT_MEMBER_PERMS;
+    let overrides = fetch_channel_overrides(pool, channel_id).await?;
 
-    // ADMINISTRATOR bypasses all channel overrides.
-    if base_perms & PERMISSION_ADMINISTRATOR != 0 {
-        return Ok(i64::MAX);
-    }
+    // Role IDs only matter when there are overrides to match them against.
+    let user_role_ids: Vec<Uuid> = if overrides.is_empty() {
+        Vec::new()
+    } else {
+        sqlx::query_scalar("SELECT role_id FROM member_roles WHERE server_id = $1 AND user_id = $2")
+            .bind(server_id)
+            .bind(user_id)
+            .fetch_all(pool)
+            .await?
+    };
+
+    Ok(resolve_channel_permissions(
+        is_owner,
+        role_perms,
+        user_id,
+        &user_role_ids,
+        &overrides,
+    ))
+}
 
-    // 3. Fetch all overrides for this channel.
-    #[derive(sqlx::FromRow)]
-    struct OverrideRow {
-        role_id: Option<Uuid>,
-        user_id: Option<Uuid>,
-        allow: i64,
-        deny: i64,
-    }
+/// Default permissions for all members (equivalent to Discord's @everyone):
+/// VIEW_CHANNEL | SEND_MESSAGES | ADD_REACTIONS | ATTACH_FILES | CONNECT_VOICE | SPEAK
+const DEFAULT_MEMBER_PERMS: i64 = 1 | 2 | 16 | 8 | 32 | 64; // 123
+
+/// One row of `channel_permission_overrides`, as needed for permission resolution.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ChannelOverride {
+    pub role_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+    pub allow: i64,
+    pub deny: i64,
+}
 
-    let overrides = sqlx::query_as::<_, OverrideRow>(
+async fn fetch_channel_overrides(
+    pool: &sqlx::PgPool,
+    channel_id: Uuid,
+) -> AppResult<Vec<ChannelOverride>> {
+    Ok(sqlx::query_as::<_, ChannelOverride>(
         "SELECT role_id, user_id, allow, deny
          FROM channel_permission_overrides WHERE channel_id = $1",
     )
     .bind(channel_id)
     .fetch_all(pool)
-    .await?;
-
-    // Short-circuit: no overrides means base perms apply unchanged.
-    if overrides.is_empty() {
-        return Ok(base_perms);
-    }
-
-    // 4. Fetch user's role IDs in this server.
-    let user_role_ids: Vec<Uuid> = sqlx::query_scalar(
-        "SELECT role_id FROM member_roles WHERE server_id = $1 AND user_id = $2",
-    )
-    .bind(server_id)
-    .bind(user_id)
-    .fetch_all(pool)
-    .await?;
+    .await?)
+}
 
-    // 5. Merge role overrides: OR of all matching role allows and denies.
+/// Apply a channel's overrides to a member's base permissions.
+///
+/// Role overrides are merged first (OR of all matching allows and denies,
+/// deny clears bits, allow sets bits), then the user-specific override is
+/// applied on top. Owner / ADMINISTRATOR bypass is the caller's job.
+pub fn apply_channel_overrides(
+    base_perms: i64,
+    user_id: Uuid,
+    user_role_ids: &[Uuid],
+    overrides: &[ChannelOverride],
+) -> i64 {
     let mut role_allow: i64 = 0;
     let mut role_deny: i64 = 0;
-    let mut user_override: Option<&OverrideRow> = None;
+    let mut user_override: Option<&ChannelOverride> = None;
 
-    for ov in &overrides {
+    for ov in overrides {
         if ov.user_id == Some(user_id) {
             user_override = Some(ov);
         } else if let Some(rid) = ov.role_id {
@@ -478,15 +498,91 @@ pub async fn compute_channel_permissions(
         }
     }
 
-    // 6. Apply role overrides: deny clears bits, allow sets bits.
     let mut perms = (base_perms & !role_deny) | role_allow;
 
-    // 7. Apply user-specific override (highest priority).
     if let Some(uo) = user_override {
         perms = (perms & !uo.deny) | uo.allow;
     }
 
-    Ok(perms)
+    perms
+}
+
+/// Resolve a member's effective permissions in a channel.
+///
+/// This is the single place the resolution order of
+/// [`compute_channel_permissions`] is implemented; [`compute_channel_viewers`]
+/// runs it for every member of a server.
+fn resolve_channel_permissions(
+    is_owner: bool,
+    role_perms: i64,
+    user_id: Uuid,
+    user_role_ids: &[Uuid],
+    overrides: &[ChannelOverride],
+) -> i64 {
+    if is_owner {
+        return i64::MAX;
+    }
+    let base_perms = role_perms | DEFAULT_MEMBER_PERMS;
+    // ADMINISTRATOR bypasses all channel overrides.
+    if base_perms & PERMISSION_ADMINISTRATOR != 0 {
+        return i64::MAX;
+    }
+    apply_channel_overrides(base_perms, user_id, user_role_ids, overrides)
+}
+
+/// Return every member of `server_id` whose effective permissions in
+/// `channel_id` include VIEW_CHANNEL.
+///
+/// Loads what [`resolve_channel_permissions`] needs for the whole member list
+/// in three queries instead of several per member, so it is safe to call on
+/// every channel-scoped gateway broadcast.
+pub async fn compute_channel_viewers(
+    pool: &sqlx::PgPool,
+    server_id: Uuid,
+    channel_id: Uuid,
+) -> AppResult<Vec<Uuid>> {
+    let owner_id: Option<Uuid> = sqlx::query_scalar("SELECT owner_id FROM servers WHERE id = $1")
+        .bind(server_id)
+        .fetch_optional(pool)
+        .await?;
+
+    #[derive(sqlx::FromRow)]
+    struct MemberRow {
+        user_id: Uuid,
+        role_perms: i64,
+        role_ids: Vec<Uuid>,
+    }
+
+    let members = sqlx::query_as::<_, MemberRow>(
+        "SELECT sm.user_id,
+                COALESCE(BIT_OR(r.permissions), 0) AS role_perms,
+                COALESCE(ARRAY_AGG(r.id) FILTER (WHERE r.id IS NOT NULL), '{}') AS role_ids
+         FROM server_members sm
+         LEFT JOIN member_roles mr ON mr.server_id = sm.server_id AND mr.user_id = sm.user_id
+         LEFT JOIN roles r ON r.id = mr.role_id
+         WHERE sm.server_id = $1
+         GROUP BY sm.user_id",
+    )
+    .bind(server_id)
+    .fetch_all(pool)
+    .await?;
+
+    let overrides = fetch_channel_overrides(pool, channel_id).await?;
+
+    Ok(members
+        .into_iter()
+        .filter(|m| {
+            let perms = resolve_channel_permissions(
+                Some(m.user_id) == owner_id,
+                m.role_perms,
+                m.user_id,
+                &m.role_ids,
+                &overrides,
+            );
+            perms & PERMISSION_VIEW_CHANNEL != 0
+        })
+        .map(|m| m.user_id)
+        .collect())
 }
 
 /// Verify the user has a specific permission bit in a channel (including overrides).
@@ -507,3 +603,50 @@ pub async fn require_channel_permission(
         Err(AppError::Forbidden(error_message.into()))
     }
 }
+
+// ── Unit tests ────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_owner_admin_role_and_user_overrides_in_order() {
+        let user = Uuid::new_v4();
+        let role = Uuid::new_v4();
+        let overrides = [
+            ChannelOverride {
+                role_id: Some(role),
+                user_id: None,
+                allow: 0,
+                deny: PERMISSION_VIEW_CHANNEL | 2,
+            },
+            ChannelOverride {
+                role_id: None,
+                user_id: Some(user),
+                allow: PERMISSION_VIEW_CHANNEL,
+                deny: 0,
+            },
+        ];
+        let resolve = |is_owner, role_perms, role_ids: &[Uuid], overrides: &[ChannelOverride]| {
+            resolve_channel_permissions(is_owner, role_perms, user, role_ids, overrides)
+        };
+
+        assert_eq!(resolve(false, 0, &[], &[]), DEFAULT_MEMBER_PERMS);
+        assert_eq!(resolve(true, 0, &[role], &overrides), i64::MAX);
+        assert_eq!(
+            resolve(false, PERMISSION_ADMINISTRATOR, &[role], &overrides),
+            i64::MAX
+        );
+        // The role denies VIEW_CHANNEL and SEND_MESSAGES; the user override
+        // gives VIEW_CHANNEL back.
+        assert_eq!(
+            resolve(false, 0, &[role], &overrides),
+            DEFAULT_MEMBER_PERMS & !2
+        );
+        assert_eq!(
+            resolve(false, 0, &[role], &overrides[..1]),
+            DEFAULT_MEMBER_PERMS & !(PERMISSION_VIEW_CHANNEL | 2)
+        );
+    }
+}
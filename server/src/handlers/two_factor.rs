@@ -0,0 +1,791 @@
+//! TOTP two-factor authentication: enrollment, the second login step,
+//! recovery codes and the instance-wide requirement. Code generation and
+//! checking live in `crate::auth::totp`.
+//!
+//! When a user with 2FA logs in, `/auth/login` returns a challenge instead
+//! of tokens; `/auth/two-factor/verify` exchanges the challenge and a code
+//! for tokens. When the instance requires 2FA and the user has none, the
+//! challenge is an `enroll` challenge: `/auth/two-factor/setup` and
+//! `/auth/two-factor/enroll` set it up and finish the login.
+//!
+//! A registered passkey also counts as a second factor; the passkey side of
+//! the `verify` step lives in `crate::handlers::passkeys`.
+
+use axum::{extract::State, Json};
+use data_encoding::BASE32_NOPAD;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::{hash_refresh_token, totp, verify_password, AuthUser},
+    error::{AppError, AppResult},
+    handlers::{
+        auth::{start_session, AuthResponse},
+        email::new_email_token,
+    },
+    models::User,
+    state::AppState,
+};
+
+/// Login challenges stay valid this long.
+const CHALLENGE_TTL_SECS: i64 = 300;
+/// Wrong codes allowed per challenge before the user must log in again.
+const MAX_CHALLENGE_ATTEMPTS: i32 = 5;
+/// Recovery codes issued at a time.
+const RECOVERY_CODE_COUNT: usize = 10;
+/// Issuer shown in authenticator apps.
+const ISSUER: &str = "Together";
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TwoFactorStatus {
+    /// An authenticator app (TOTP) is set up.
+    pub enabled: bool,
+    /// The instance requires 2FA for this user.
+    pub required: bool,
+    pub recovery_codes_remaining: i64,
+    /// Registered passkeys, which can also answer the second login step.
+    pub passkeys: i64,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct TwoFactorSetupRequest {
+    #[validate(length(min = 1, max = 128))]
+    pub password: String,
+}
+
+/// A new secret to add to an authenticator app, as text, as an `otpauth://`
+/// URI and as a QR code of that URI.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TwoFactorSetup {
+    pub secret: String,
+    pub otpauth_url: String,
+    /// SVG document.
+    pub qr_svg: String,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct TwoFactorCodeRequest {
+    /// A 6-digit code from the authenticator app, or a recovery code.
+    #[validate(length(min = 1, max = 32))]
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct DisableTwoFactorRequest {
+    #[validate(length(min = 1, max = 128))]
+    pub password: String,
+    #[validate(length(min = 1, max = 32))]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RecoveryCodes {
+    /// Each code works once. They are shown only now.
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ChallengePurpose {
+    /// Send a code to `/auth/two-factor/verify`.
+    Verify,
+    /// 2FA is required but not set up: use `/auth/two-factor/setup` and
+    /// `/auth/two-factor/enroll`.
+    Enroll,
+}
+
+impl ChallengePurpose {
+    fn as_str(self) -> &'static str {
+        match self {
+            ChallengePurpose::Verify => "verify",
+            ChallengePurpose::Enroll => "enroll",
+        }
+    }
+}
+
+/// A way to answer a `verify` challenge.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TwoFactorMethod {
+    /// A code from the authenticator app or a recovery code, sent to
+    /// `/auth/two-factor/verify`.
+    Totp,
+    /// A passkey, through `/auth/two-factor/passkey/options` and
+    /// `/auth/two-factor/passkey`.
+    Passkey,
+}
+
+/// Returned by `/auth/login` in place of tokens when a second step is needed.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TwoFactorChallenge {
+    pub two_factor: ChallengePurpose,
+    pub challenge: String,
+    pub expires_in_seconds: i64,
+    /// How the user can answer a `verify` challenge. Empty for `enroll`.
+    pub methods: Vec<TwoFactorMethod>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ChallengeRequest {
+    #[validate(length(min = 1, max = 256))]
+    pub challenge: String,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ChallengeCodeRequest {
+    #[validate(length(min = 1, max = 256))]
+    pub challenge: String,
+    #[validate(length(min = 1, max = 32))]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TwoFactorEnrollResponse {
+    #[serde(flatten)]
+    pub auth: AuthResponse,
+    pub recovery_codes: Vec<String>,
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+fn unix_now() -> u64 {
+    chrono::Utc::now().timestamp().max(0) as u64
+}
+
+/// Whether `require_two_factor` ('off', 'admins' or 'everyone') covers a user.
+fn policy_applies(policy: &str, is_admin: bool) -> bool {
+    match policy {
+        "everyone" => true,
+        "admins" => is_admin,
+        _ => false,
+    }
+}
+
+/// A user's second factors, and whether the instance requires one.
+pub(crate) struct Factors {
+    /// TOTP is enabled.
+    pub totp: bool,
+    /// Number of registered passkeys.
+    pub passkeys: i64,
+    pub required: bool,
+}
+
+impl Factors {
+    pub fn any(&self) -> bool {
+        self.totp || self.passkeys > 0
+    }
+
+    fn methods(&self) -> Vec<TwoFactorMethod> {
+        let mut methods = Vec::new();
+        if self.totp {
+            methods.push(TwoFactorMethod::Totp);
+        }
+        if self.passkeys > 0 {
+            methods.push(TwoFactorMethod::Passkey);
+        }
+        methods
+    }
+}
+
+pub(crate) async fn two_factor_state(pool: &PgPool, user: &User) -> AppResult<Factors> {
+    let (totp, passkeys, policy): (bool, i64, String) = sqlx::query_as(
+        "SELECT EXISTS(SELECT 1 FROM user_totp WHERE user_id = $1 AND enabled_at IS NOT NULL),
+                (SELECT COUNT(*) FROM webauthn_credentials WHERE user_id = $1),
+                (SELECT require_two_factor FROM instance_settings WHERE id = 1)",
+    )
+    .bind(user.id)
+    .fetch_one(pool)
+    .await?;
+    Ok(Factors {
+        totp,
+        passkeys,
+        required: policy_applies(&policy, user.is_admin),
+    })
+}
+
+/// The challenge for the second login step this user needs, if any.
+pub(crate) async fn login_challenge(
+    state: &AppState,
+    user: &User,
+) -> AppResult<Option<TwoFactorChallenge>> {
+    let factors = two_factor_state(&state.pool, user).await?;
+    let (purpose, methods) = if factors.any() {
+        (ChallengePurpose::Verify, factors.methods())
+    } else if factors.required {
+        (ChallengePurpose::Enroll, Vec::new())
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(
+        create_challenge(state, user.id, purpose, methods).await?,
+    ))
+}
+
+/// True when the instance requires 2FA for this user and they have not set
+/// it up. Such users cannot refresh their session; logging in again walks
+/// them through enrollment.
+pub(crate) async fn enrollment_pending(state: &AppState, user: &User) -> AppResult<bool> {
+    let factors = two_factor_state(&state.pool, user).await?;
+    Ok(factors.required && !factors.any())
+}
+
+/// Issue a login challenge. Only the hash is stored.
+async fn create_challenge(
+    state: &AppState,
+    user_id: Uuid,
+    purpose: ChallengePurpose,
+    methods: Vec<TwoFactorMethod>,
+) -> AppResult<TwoFactorChallenge> {
+    let (challenge, token_hash) = new_email_token();
+
+    let mut tx = state.pool.begin().await?;
+    sqlx::query("DELETE FROM two_factor_challenges WHERE user_id = $1 AND expires_at < NOW()")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO two_factor_challenges (user_id, token_hash, purpose, expires_at)
+         VALUES ($1, $2, $3, NOW() + make_interval(secs => $4))",
+    )
+    .bind(user_id)
+    .bind(&token_hash)
+    .bind(purpose.as_str())
+    .bind(CHALLENGE_TTL_SECS as f64)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(TwoFactorChallenge {
+        two_factor: purpose,
+        challenge,
+        expires_in_seconds: CHALLENGE_TTL_SECS,
+        methods,
+    })
+}
+
+/// Look up a live challenge and its user.
+pub(crate) async fn find_challenge(
+    state: &AppState,
+    challenge: &str,
+    purpose: ChallengePurpose,
+) -> AppResult<(Uuid, User)> {
+    let row: Option<(Uuid, Uuid)> = sqlx::query_as(
+        "SELECT id, user_id FROM two_factor_challenges
+         WHERE token_hash = $1 AND purpose = $2 AND expires_at > NOW() AND attempts < $3",
+    )
+    .bind(hash_refresh_token(challenge))
+    .bind(purpose.as_str())
+    .bind(MAX_CHALLENGE_ATTEMPTS)
+    .fetch_optional(&state.pool)
+    .await?;
+    let (challenge_id, user_id) =
+        row.ok_or_else(|| AppError::Auth("Invalid or expired challenge".into()))?;
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(&state.pool)
+        .await?;
+    if user.disabled {
+        return Err(AppError::Forbidden(
+            "Your account has been disabled by an administrator".into(),
+        ));
+    }
+    Ok((challenge_id, user))
+}
+
+pub(crate) async fn record_failed_attempt(pool: &PgPool, challenge_id: Uuid) -> AppResult<()> {
+    sqlx::query("UPDATE two_factor_challenges SET attempts = attempts + 1 WHERE id = $1")
+        .bind(challenge_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Use up a challenge once its second step succeeded. Fails if a concurrent
+/// request already used it.
+pub(crate) async fn claim_challenge(conn: &mut PgConnection, challenge_id: Uuid) -> AppResult<()> {
+    let claimed = sqlx::query("DELETE FROM two_factor_challenges WHERE id = $1")
+        .bind(challenge_id)
+        .execute(&mut *conn)
+        .await?;
+    if claimed.rows_affected() == 0 {
+        return Err(AppError::Auth("Invalid or expired challenge".into()));
+    }
+    Ok(())
+}
+
+/// Check a TOTP code against the user's secret: the enabled one, or the one
+/// waiting for confirmation when `pending`. A code is accepted once; later
+/// codes from the same or an earlier time step are refused.
+async fn check_totp(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    code: &str,
+    pending: bool,
+) -> AppResult<bool> {
+    let row: Option<(String, Option<i64>)> = sqlx::query_as(
+        "SELECT secret, last_used_step FROM user_totp
+         WHERE user_id = $1 AND (enabled_at IS NULL) = $2
+         FOR UPDATE",
+    )
+    .bind(user_id)
+    .bind(pending)
+    .fetch_optional(&mut *conn)
+    .await?;
+    let Some((secret, last_used_step)) = row else {
+        return Ok(false);
+    };
+    let Some(step) = totp::verify(&secret, code, unix_now()) else {
+        return Ok(false);
+    };
+    if last_used_step.is_some_and(|last| step as i64 <= last) {
+        return Ok(false);
+    }
+
+    sqlx::query("UPDATE user_totp SET last_used_step = $2 WHERE user_id = $1")
+        .bind(user_id)
+        .bind(step as i64)
+        .execute(&mut *conn)
+        .await?;
+    Ok(true)
+}
+
+/// Recovery codes are compared without case, spaces or dashes.
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Accept a TOTP code or an unused recovery code for a user with 2FA
+/// enabled. Recovery codes are used up.
+async fn consume_code(conn: &mut PgConnection, user_id: Uuid, code: &str) -> AppResult<bool> {
+    let code = code.trim();
+    if code.len() == totp::DIGITS && code.bytes().all(|b| b.is_ascii_digit()) {
+        return check_totp(conn, user_id, code, false).await;
+    }
+
+    let used = sqlx::query(
+        "UPDATE two_factor_recovery_codes SET used_at = NOW()
+         WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL",
+    )
+    .bind(user_id)
+    .bind(hash_refresh_token(&normalize_recovery_code(code)))
+    .execute(&mut *conn)
+    .await?;
+    Ok(used.rows_affected() > 0)
+}
+
+/// Replace all of a user's recovery codes and return the new ones.
+async fn replace_recovery_codes(conn: &mut PgConnection, user_id: Uuid) -> AppResult<Vec<String>> {
+    sqlx::query("DELETE FROM two_factor_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let mut codes = Vec::with_capacity(RECOVERY_CODE_COUNT);
+    for _ in 0..RECOVERY_CODE_COUNT {
+        let bytes: [u8; 5] = rand::random();
+        let raw = BASE32_NOPAD.encode(&bytes).to_ascii_lowercase();
+        sqlx::query("INSERT INTO two_factor_recovery_codes (user_id, code_hash) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(hash_refresh_token(&raw))
+            .execute(&mut *conn)
+            .await?;
+        codes.push(format!("{}-{}", &raw[..4], &raw[4..]));
+    }
+    Ok(codes)
+}
+
+/// Store a new pending secret for the user, replacing any earlier one that
+/// was never confirmed.
+async fn begin_setup(pool: &PgPool, user_id: Uuid, username: &str) -> AppResult<TwoFactorSetup> {
+    let secret = totp::generate_secret();
+    let stored = sqlx::query(
+        "INSERT INTO user_totp (user_id, secret) VALUES ($1, $2)
+         ON CONFLICT (user_id) DO UPDATE
+         SET secret = EXCLUDED.secret, last_used_step = NULL, created_at = NOW()
+         WHERE user_totp.enabled_at IS NULL",
+    )
+    .bind(user_id)
+    .bind(&secret)
+    .execute(pool)
+    .await?;
+    if stored.rows_affected() == 0 {
+        return Err(AppError::Conflict(
+            "Two-factor authentication is already enabled".into(),
+        ));
+    }
+
+    let otpauth_url = totp::otpauth_url(ISSUER, username, &secret);
+    let qr_svg = totp::qr_svg(&otpauth_url).ok_or(AppError::Internal)?;
+    Ok(TwoFactorSetup {
+        secret,
+        otpauth_url,
+        qr_svg,
+    })
+}
+
+/// Confirm the pending secret with a code from the app. Returns the new
+/// recovery codes, or `None` when the code is wrong.
+async fn finish_setup(pool: &PgPool, user_id: Uuid, code: &str) -> AppResult<Option<Vec<String>>> {
+    let mut tx = pool.begin().await?;
+    if !check_totp(&mut tx, user_id, code.trim(), true).await? {
+        return Ok(None);
+    }
+    sqlx::query("UPDATE user_totp SET enabled_at = NOW() WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    let codes = replace_recovery_codes(&mut tx, user_id).await?;
+    tx.commit().await?;
+
+    info!("Two-factor authentication enabled for user: {}", user_id);
+    Ok(Some(codes))
+}
+
+pub(crate) async fn require_password(
+    pool: &PgPool,
+    user_id: Uuid,
+    password: &str,
+) -> AppResult<User> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::Auth("User not found".into()))?;
+    if !verify_password(password, &user.password_hash)? {
+        return Err(AppError::Forbidden("Incorrect password".into()));
+    }
+    Ok(user)
+}
+
+// ============================================================================
+// Account endpoints
+// ============================================================================
+
+#[utoipa::path(
+    get,
+    path = "/users/@me/two-factor",
+    responses(
+        (status = 200, description = "Two-factor status", body = TwoFactorStatus),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Users"
+)]
+/// GET /users/@me/two-factor
+pub async fn get_two_factor(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> AppResult<Json<TwoFactorStatus>> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(auth.user_id())
+        .fetch_one(&state.pool)
+        .await?;
+    let factors = two_factor_state(&state.pool, &user).await?;
+    let recovery_codes_remaining: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM two_factor_recovery_codes WHERE user_id = $1 AND used_at IS NULL",
+    )
+    .bind(user.id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(TwoFactorStatus {
+        enabled: factors.totp,
+        required: factors.required,
+        recovery_codes_remaining: if factors.totp {
+            recovery_codes_remaining
+        } else {
+            0
+        },
+        passkeys: factors.passkeys,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/@me/two-factor/setup",
+    request_body = TwoFactorSetupRequest,
+    responses(
+        (status = 200, description = "New secret to confirm", body = TwoFactorSetup),
+        (status = 403, description = "Incorrect password"),
+        (status = 409, description = "Two-factor authentication is already enabled")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Users"
+)]
+/// POST /users/@me/two-factor/setup — start enrollment. 2FA is not active
+/// until the secret is confirmed with a code.
+pub async fn setup_two_factor(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(req): Json<TwoFactorSetupRequest>,
+) -> AppResult<Json<TwoFactorSetup>> {
+    req.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+    let user = require_password(&state.pool, auth.user_id(), &req.password).await?;
+
+    Ok(Json(
+        begin_setup(&state.pool, user.id, &user.username).await?,
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/@me/two-factor/confirm",
+    request_body = TwoFactorCodeRequest,
+    responses(
+        (status = 200, description = "2FA enabled", body = RecoveryCodes),
+        (status = 400, description = "Invalid code, or setup not started")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Users"
+)]
+/// POST /users/@me/two-factor/confirm — enable 2FA with a code from the
+/// authenticator app.
+pub async fn confirm_two_factor(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(req): Json<TwoFactorCodeRequest>,
+) -> AppResult<Json<RecoveryCodes>> {
+    req.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let recovery_codes = finish_setup(&state.pool, auth.user_id(), &req.code)
+        .await?
+        .ok_or_else(|| AppError::Validation("Invalid code, or setup was not started".into()))?;
+    Ok(Json(RecoveryCodes { recovery_codes }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/@me/two-factor/disable",
+    request_body = DisableTwoFactorRequest,
+    responses(
+        (status = 200, description = "2FA disabled", body = TwoFactorStatus),
+        (status = 400, description = "2FA is not enabled, or invalid code"),
+        (status = 403, description = "Incorrect password, or 2FA is required on this instance")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Users"
+)]
+/// POST /users/@me/two-factor/disable — turn 2FA off. Needs the password
+/// and a current code or recovery code.
+pub async fn disable_two_factor(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(req): Json<DisableTwoFactorRequest>,
+) -> AppResult<Json<TwoFactorStatus>> {
+    req.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+    let user = require_password(&state.pool, auth.user_id(), &req.password).await?;
+
+    let factors = two_factor_state(&state.pool, &user).await?;
+    if !factors.totp {
+        return Err(AppError::Validation(
+            "Two-factor authentication is not enabled".into(),
+        ));
+    }
+    // Passkeys still satisfy the requirement once the app is removed.
+    if factors.required && factors.passkeys == 0 {
+        return Err(AppError::Forbidden(
+            "Two-factor authentication is required on this server".into(),
+        ));
+    }
+
+    let mut tx = state.pool.begin().await?;
+    if !consume_code(&mut tx, user.id, &req.code).await? {
+        return Err(AppError::Validation("Invalid code".into()));
+    }
+    remove_two_factor(&mut tx, user.id).await?;
+    tx.commit().await?;
+
+    info!("Two-factor authentication disabled for user: {}", user.id);
+    Ok(Json(TwoFactorStatus {
+        enabled: false,
+        required: factors.required,
+        recovery_codes_remaining: 0,
+        passkeys: factors.passkeys,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/@me/two-factor/recovery-codes",
+    request_body = TwoFactorCodeRequest,
+    responses(
+        (status = 200, description = "New recovery codes; the old ones stop working", body = RecoveryCodes),
+        (status = 400, description = "2FA is not enabled, or invalid code")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Users"
+)]
+/// POST /users/@me/two-factor/recovery-codes — replace the recovery codes.
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(req): Json<TwoFactorCodeRequest>,
+) -> AppResult<Json<RecoveryCodes>> {
+    req.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let mut tx = state.pool.begin().await?;
+    if !consume_code(&mut tx, auth.user_id(), &req.code).await? {
+        return Err(AppError::Validation(
+            "Invalid code, or two-factor authentication is not enabled".into(),
+        ));
+    }
+    let recovery_codes = replace_recovery_codes(&mut tx, auth.user_id()).await?;
+    tx.commit().await?;
+
+    Ok(Json(RecoveryCodes { recovery_codes }))
+}
+
+/// Delete a user's secret, recovery codes and open challenges.
+pub(crate) async fn remove_two_factor(conn: &mut PgConnection, user_id: Uuid) -> AppResult<()> {
+    for table in [
+        "user_totp",
+        "two_factor_recovery_codes",
+        "two_factor_challenges",
+    ] {
+        sqlx::query(&format!("DELETE FROM {table} WHERE user_id = $1"))
+            .bind(user_id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Login endpoints
+// ============================================================================
+
+#[utoipa::path(
+    post,
+    path = "/auth/two-factor/verify",
+    request_body = ChallengeCodeRequest,
+    responses(
+        (status = 200, description = "Login successful", body = AuthResponse),
+        (status = 401, description = "Invalid code, or invalid or expired challenge"),
+        (status = 403, description = "Account disabled")
+    ),
+    tag = "Auth"
+)]
+/// POST /auth/two-factor/verify — finish logging in with a code from the
+/// authenticator app or a recovery code.
+pub async fn verify_two_factor(
+    State(state): State<AppState>,
+    Json(req): Json<ChallengeCodeRequest>,
+) -> AppResult<Json<AuthResponse>> {
+    req.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+    let (challenge_id, user) =
+        find_challenge(&state, &req.challenge, ChallengePurpose::Verify).await?;
+
+    let mut tx = state.pool.begin().await?;
+    if !consume_code(&mut tx, user.id, &req.code).await? {
+        drop(tx);
+        record_failed_attempt(&state.pool, challenge_id).await?;
+        return Err(AppError::Auth("Invalid two-factor code".into()));
+    }
+    claim_challenge(&mut tx, challenge_id).await?;
+    tx.commit().await?;
+
+    info!("Login successful (2FA): {} ({})", user.username, user.id);
+    Ok(Json(start_session(&state, user).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/two-factor/setup",
+    request_body = ChallengeRequest,
+    responses(
+        (status = 200, description = "New secret to confirm", body = TwoFactorSetup),
+        (status = 401, description = "Invalid or expired challenge")
+    ),
+    tag = "Auth"
+)]
+/// POST /auth/two-factor/setup — start the enrollment that an `enroll`
+/// challenge requires.
+pub async fn setup_two_factor_at_login(
+    State(state): State<AppState>,
+    Json(req): Json<ChallengeRequest>,
+) -> AppResult<Json<TwoFactorSetup>> {
+    req.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+    let (_, user) = find_challenge(&state, &req.challenge, ChallengePurpose::Enroll).await?;
+
+    Ok(Json(
+        begin_setup(&state.pool, user.id, &user.username).await?,
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/two-factor/enroll",
+    request_body = ChallengeCodeRequest,
+    responses(
+        (status = 200, description = "2FA enabled and login successful", body = TwoFactorEnrollResponse),
+        (status = 401, description = "Invalid code, or invalid or expired challenge"),
+        (status = 403, description = "Account disabled")
+    ),
+    tag = "Auth"
+)]
+/// POST /auth/two-factor/enroll — confirm the new secret and finish logging
+/// in.
+pub async fn enroll_two_factor(
+    State(state): State<AppState>,
+    Json(req): Json<ChallengeCodeRequest>,
+) -> AppResult<Json<TwoFactorEnrollResponse>> {
+    req.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+    let (challenge_id, user) =
+        find_challenge(&state, &req.challenge, ChallengePurpose::Enroll).await?;
+
+    let Some(recovery_codes) = finish_setup(&state.pool, user.id, &req.code).await? else {
+        record_failed_attempt(&state.pool, challenge_id).await?;
+        return Err(AppError::Auth("Invalid two-factor code".into()));
+    };
+    sqlx::query("DELETE FROM two_factor_challenges WHERE id = $1")
+        .bind(challenge_id)
+        .execute(&state.pool)
+        .await?;
+
+    info!(
+        "Login successful (2FA enrolled): {} ({})",
+        user.username, user.id
+    );
+    Ok(Json(TwoFactorEnrollResponse {
+        auth: start_session(&state, user).await?,
+        recovery_codes,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_covers_expected_users() {
+        assert!(!policy_applies("off", true));
+        assert!(policy_applies("admins", true));
+        assert!(!policy_applies("admins", false));
+        assert!(policy_applies("everyone", false));
+    }
+
+    #[test]
+    fn recovery_codes_normalize() {
+        assert_eq!(normalize_recovery_code(" ABCD-efgh "), "abcdefgh");
+    }
+}
@@ -0,0 +1,498 @@
+//! Resumable uploads, following the core of the tus 1.0.0 protocol with its
+//! creation and termination extensions.
+//!
+//! A client creates an upload with its total size, sends the bytes in one or
+//! more `PATCH` requests, and after an interruption asks with `HEAD` how much
+//! arrived before continuing from there. A finished upload is attached to a
+//! message with `POST /messages/:message_id/attachments/uploads`.
+//!
+//! The bytes are staged on the local disk of the node that received them
+//! (see [`crate::uploads`]). With several replicas, either share
+//! `UPLOAD_STAGING_DIR` between them or route `/uploads` stickily.
+
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures::StreamExt;
+use uuid::Uuid;
+
+use super::attachments::{ALLOWED_MIME_TYPES, MAX_FILE_SIZE};
+use crate::{
+    auth::AuthUser,
+    error::{AppError, AppResult},
+    models::Upload,
+    state::AppState,
+    uploads::{self, staging_path, StagingFile, UploadError, UPLOAD_EXPIRY},
+};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+/// Protocol version sent in the `Tus-Resumable` header.
+const TUS_VERSION: &str = "1.0.0";
+
+/// Content type every `PATCH` body must declare.
+const PATCH_CONTENT_TYPE: &str = "application/offset+octet-stream";
+
+/// Unfinished or unattached uploads a user may have at once.
+const MAX_PENDING_UPLOADS: i64 = 10;
+
+/// Longest `filename` accepted in `Upload-Metadata`, the same cap multipart
+/// file names are cut to.
+const MAX_FILENAME_CHARS: usize = 128;
+
+/// How long a `PATCH` holds an upload before another request may take over.
+/// Renewed while the body is still arriving.
+const LEASE: Duration = Duration::from_secs(5 * 60);
+
+/// How often a `PATCH` renews its lease.
+const LEASE_RENEWAL: Duration = Duration::from_secs(60);
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+#[utoipa::path(
+    post,
+    path = "/uploads",
+    params(
+        ("Upload-Length" = u64, Header, description = "Total size of the file in bytes (at most 50 MB)"),
+        ("Upload-Metadata" = Option<String>, Header, description = "tus metadata; `filename` is the base64-encoded file name"),
+    ),
+    responses(
+        (status = 201, description = "Upload created; its URL is in `Location`", body = Upload),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Attachments"
+)]
+/// POST /uploads — start a resumable upload.
+///
+/// The file size must be declared up front and is checked against the same
+/// 50 MB limit as multipart uploads. Uploads expire 24 hours after creation
+/// whether or not they were finished; a user may have 10 at a time.
+pub async fn create_upload(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    let length = header_u64(&headers, "upload-length")?
+        .ok_or_else(|| AppError::Validation("Upload-Length header is required".into()))?;
+    if length == 0 {
+        return Err(UploadError::Empty.into());
+    }
+    if length > MAX_FILE_SIZE {
+        return Err(UploadError::TooLarge(MAX_FILE_SIZE).into());
+    }
+    let filename = metadata_filename(&headers)?.unwrap_or_else(|| "unknown".to_string());
+
+    let pending: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM uploads WHERE user_id = $1 AND expires_at > NOW()",
+    )
+    .bind(auth.user_id())
+    .fetch_one(&state.pool)
+    .await?;
+    if pending >= MAX_PENDING_UPLOADS {
+        return Err(AppError::Validation(format!(
+            "You may not have more than {MAX_PENDING_UPLOADS} unfinished uploads"
+        )));
+    }
+
+    let upload = sqlx::query_as::<_, Upload>(
+        "INSERT INTO uploads (user_id, filename, upload_length, expires_at)
+         VALUES ($1, $2, $3, NOW() + make_interval(secs => $4))
+         RETURNING id, filename, upload_length, upload_offset, mime_type, created_at, expires_at",
+    )
+    .bind(auth.user_id())
+    .bind(&filename)
+    .bind(length as i64)
+    .bind(UPLOAD_EXPIRY.as_secs_f64())
+    .fetch_one(&state.pool)
+    .await?;
+
+    let path = staging_path(&state.config.upload_staging_dir, upload.id);
+    if let Err(e) = StagingFile::create(path, length, ALLOWED_MIME_TYPES).await {
+        tracing::error!(error = ?e, upload_id = %upload.id, "Failed to create staging file");
+        let _ = sqlx::query("DELETE FROM uploads WHERE id = $1")
+            .bind(upload.id)
+            .execute(&state.pool)
+            .await;
+        return Err(AppError::Internal);
+    }
+
+    let mut response = (StatusCode::CREATED, Json(&upload)).into_response();
+    let headers = response.headers_mut();
+    headers.insert(
+        header::LOCATION,
+        header_value(&format!("/uploads/{}", upload.id)),
+    );
+    headers.insert("upload-offset", header_value("0"));
+    headers.insert("tus-resumable", HeaderValue::from_static(TUS_VERSION));
+    Ok(response)
+}
+
+#[utoipa::path(
+    head,
+    path = "/uploads/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Upload ID"),
+    ),
+    responses(
+        (status = 200, description = "Progress in the `Upload-Offset` and `Upload-Length` headers"),
+        (status = 404, description = "No such upload, or it has expired"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Attachments"
+)]
+/// HEAD /uploads/:id — how many bytes of an upload have arrived.
+///
+/// Clients call this after an interrupted `PATCH` and resume from the
+/// returned `Upload-Offset`.
+pub async fn head_upload(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> AppResult<Response> {
+    let upload = fetch_upload(&state, id, auth.user_id()).await?;
+
+    let mut response = StatusCode::OK.into_response();
+    let headers = response.headers_mut();
+    headers.insert(
+        "upload-offset",
+        header_value(&upload.upload_offset.to_string()),
+    );
+    headers.insert(
+        "upload-length",
+        header_value(&upload.upload_length.to_string()),
+    );
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    headers.insert("tus-resumable", HeaderValue::from_static(TUS_VERSION));
+    Ok(response)
+}
+
+#[utoipa::path(
+    patch,
+    path = "/uploads/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Upload ID"),
+        ("Upload-Offset" = u64, Header, description = "Offset the body starts at; must equal the upload's current offset"),
+    ),
+    request_body(content_type = "application/offset+octet-stream", description = "The next bytes of the file"),
+    responses(
+        (status = 204, description = "Bytes appended; the new offset is in `Upload-Offset`"),
+        (status = 400, description = "Wrong content type, body past `Upload-Length`, or a disallowed file type"),
+        (status = 404, description = "No such upload, or it has expired"),
+        (status = 409, description = "`Upload-Offset` is stale, or another request is writing this upload"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Attachments"
+)]
+/// PATCH /uploads/:id — append bytes to an upload.
+///
+/// The body is streamed to disk. Bytes that arrive before the connection
+/// drops are kept, so the client can resume from the offset `HEAD` reports.
+/// The type is sniffed once the first bytes are in; a disallowed file
+/// cancels the whole upload.
+pub async fn patch_upload(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    body: Body,
+) -> AppResult<Response> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok());
+    if content_type != Some(PATCH_CONTENT_TYPE) {
+        return Err(AppError::Validation(format!(
+            "Content-Type must be {PATCH_CONTENT_TYPE}"
+        )));
+    }
+    let offset = header_u64(&headers, "upload-offset")?
+        .ok_or_else(|| AppError::Validation("Upload-Offset header is required".into()))?;
+
+    fetch_upload(&state, id, auth.user_id()).await?;
+
+    // Take the lease so no other request appends to the file meanwhile, and
+    // read the offset under it.
+    let upload = sqlx::query_as::<_, Upload>(
+        "UPDATE uploads SET locked_until = NOW() + make_interval(secs => $3)
+         WHERE id = $1 AND user_id = $2
+           AND (locked_until IS NULL OR locked_until < NOW())
+         RETURNING id, filename, upload_length, upload_offset, mime_type, created_at, expires_at",
+    )
+    .bind(id)
+    .bind(auth.user_id())
+    .bind(LEASE.as_secs_f64())
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::Conflict("Another request is already writing this upload".into()))?;
+    if offset != upload.upload_offset as u64 {
+        release(&state, id).await;
+        return Err(AppError::Conflict(format!(
+            "Upload-Offset {offset} does not match the upload's offset {}",
+            upload.upload_offset
+        )));
+    }
+
+    let new_offset = match append(&state, &upload, body).await {
+        Ok(new_offset) => new_offset,
+        Err(e) => {
+            release(&state, id).await;
+            return Err(e);
+        }
+    };
+
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    let headers = response.headers_mut();
+    headers.insert("upload-offset", header_value(&new_offset.to_string()));
+    headers.insert("tus-resumable", HeaderValue::from_static(TUS_VERSION));
+    Ok(response)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/uploads/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Upload ID"),
+    ),
+    responses(
+        (status = 204, description = "Upload cancelled"),
+        (status = 404, description = "No such upload"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Attachments"
+)]
+/// DELETE /uploads/:id — cancel an upload and discard its bytes.
+pub async fn delete_upload(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> AppResult<Response> {
+    let deleted = sqlx::query("DELETE FROM uploads WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(auth.user_id())
+        .execute(&state.pool)
+        .await?;
+    if deleted.rows_affected() == 0 {
+        return Err(AppError::NotFound("Upload not found".into()));
+    }
+    uploads::remove_staged(&staging_path(&state.config.upload_staging_dir, id)).await;
+
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    response
+        .headers_mut()
+        .insert("tus-resumable", HeaderValue::from_static(TUS_VERSION));
+    Ok(response)
+}
+
+// ============================================================================
+// Private helpers
+// ============================================================================
+
+/// The caller's unexpired upload `id`.
+async fn fetch_upload(state: &AppState, id: Uuid, user_id: Uuid) -> AppResult<Upload> {
+    sqlx::query_as::<_, Upload>(
+        "SELECT id, filename, upload_length, upload_offset, mime_type, created_at, expires_at
+         FROM uploads WHERE id = $1 AND user_id = $2 AND expires_at > NOW()",
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Upload not found".into()))
+}
+
+/// Stream `body` onto the end of `upload` and record the new offset, which
+/// releases the lease. Returns the new offset.
+async fn append(state: &AppState, upload: &Upload, body: Body) -> AppResult<u64> {
+    let path = staging_path(&state.config.upload_staging_dir, upload.id);
+    let mut file = StagingFile::resume(
+        path.clone(),
+        upload.upload_offset as u64,
+        upload.mime_type.clone(),
+        upload.upload_length as u64,
+        ALLOWED_MIME_TYPES,
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!(error = ?e, upload_id = %upload.id, "Failed to open staged upload");
+        if e.kind() == std::io::ErrorKind::NotFound {
+            AppError::NotFound("Upload data not found on this server".into())
+        } else {
+            AppError::Internal
+        }
+    })?;
+
+    let mut stream = body.into_data_stream();
+    let mut renewed = Instant::now();
+    let mut interrupted = false;
+    while let Some(chunk) = stream.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                tracing::info!(error = ?e, upload_id = %upload.id, "Upload body interrupted");
+                interrupted = true;
+                break;
+            }
+        };
+        match file.write(&chunk).await {
+            Ok(()) => {}
+            Err(UploadError::TooLarge(_)) => {
+                // Keep what fitted; the client can see the offset and retry.
+                file.sync().await.map_err(UploadError::from)?;
+                record(state, upload, file.written(), file.mime_type()).await?;
+                return Err(AppError::Validation(
+                    "Request body runs past Upload-Length".into(),
+                ));
+            }
+            Err(e @ (UploadError::NotAllowed(_) | UploadError::Undetectable)) => {
+                discard(state, upload.id).await;
+                return Err(e.into());
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        if renewed.elapsed() >= LEASE_RENEWAL {
+            renewed = Instant::now();
+            if let Err(e) = sqlx::query(
+                "UPDATE uploads SET locked_until = NOW() + make_interval(secs => $2)
+                 WHERE id = $1",
+            )
+            .bind(upload.id)
+            .bind(LEASE.as_secs_f64())
+            .execute(&state.pool)
+            .await
+            {
+                tracing::warn!(error = ?e, upload_id = %upload.id, "Failed to renew upload lease");
+            }
+        }
+    }
+
+    let written = file.written();
+    if interrupted || written < upload.upload_length as u64 {
+        file.sync().await.map_err(UploadError::from)?;
+        let mime_type = file.mime_type().map(str::to_string);
+        record(state, upload, written, mime_type.as_deref()).await?;
+        if interrupted {
+            return Err(AppError::Validation("Upload interrupted".into()));
+        }
+        return Ok(written);
+    }
+
+    match file.finish().await {
+        Ok((size, mime_type)) => {
+            record(state, upload, size, Some(&mime_type)).await?;
+            Ok(size)
+        }
+        Err(e @ (UploadError::Empty | UploadError::Io(_))) => Err(e.into()),
+        Err(e) => {
+            discard(state, upload.id).await;
+            Err(e.into())
+        }
+    }
+}
+
+/// Save progress and release the lease. If the upload was cancelled while
+/// the body was arriving, its file is removed instead.
+async fn record(
+    state: &AppState,
+    upload: &Upload,
+    offset: u64,
+    mime_type: Option<&str>,
+) -> AppResult<()> {
+    let updated = sqlx::query(
+        "UPDATE uploads SET upload_offset = $2, mime_type = $3, locked_until = NULL
+         WHERE id = $1",
+    )
+    .bind(upload.id)
+    .bind(offset as i64)
+    .bind(mime_type)
+    .execute(&state.pool)
+    .await?;
+    if updated.rows_affected() == 0 {
+        uploads::remove_staged(&staging_path(&state.config.upload_staging_dir, upload.id)).await;
+        return Err(AppError::NotFound("Upload not found".into()));
+    }
+    Ok(())
+}
+
+/// Release the lease without recording progress.
+async fn release(state: &AppState, id: Uuid) {
+    if let Err(e) = sqlx::query("UPDATE uploads SET locked_until = NULL WHERE id = $1")
+        .bind(id)
+        .execute(&state.pool)
+        .await
+    {
+        tracing::warn!(error = ?e, upload_id = %id, "Failed to release upload lease");
+    }
+}
+
+/// Delete an upload that can never be attached, with its file.
+async fn discard(state: &AppState, id: Uuid) {
+    if let Err(e) = sqlx::query("DELETE FROM uploads WHERE id = $1")
+        .bind(id)
+        .execute(&state.pool)
+        .await
+    {
+        tracing::warn!(error = ?e, upload_id = %id, "Failed to delete rejected upload");
+    }
+    uploads::remove_staged(&staging_path(&state.config.upload_staging_dir, id)).await;
+}
+
+/// Parse a non-negative integer header such as `Upload-Length`.
+fn header_u64(headers: &HeaderMap, name: &str) -> AppResult<Option<u64>> {
+    headers
+        .get(name)
+        .map(|v| {
+            v.to_str()
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .ok_or_else(|| AppError::Validation(format!("Invalid {name} header")))
+        })
+        .transpose()
+}
+
+/// The `filename` entry of a tus `Upload-Metadata` header: comma-separated
+/// `key base64value` pairs. The name becomes the attachment's file name, so
+/// control characters and names over [`MAX_FILENAME_CHARS`] are refused.
+fn metadata_filename(headers: &HeaderMap) -> AppResult<Option<String>> {
+    let Some(metadata) = headers.get("upload-metadata") else {
+        return Ok(None);
+    };
+    let invalid = || AppError::Validation("Invalid Upload-Metadata header".into());
+    let metadata = metadata.to_str().map_err(|_| invalid())?;
+    for pair in metadata.split(',') {
+        let mut parts = pair.trim().splitn(2, ' ');
+        if parts.next() != Some("filename") {
+            continue;
+        }
+        let value = parts.next().unwrap_or("").trim();
+        let decoded = STANDARD.decode(value).map_err(|_| invalid())?;
+        let filename = String::from_utf8(decoded).map_err(|_| invalid())?;
+        if filename.chars().any(char::is_control) {
+            return Err(AppError::Validation(
+                "File name must not contain control characters".into(),
+            ));
+        }
+        if filename.chars().count() > MAX_FILENAME_CHARS {
+            return Err(AppError::Validation(format!(
+                "File name must be at most {MAX_FILENAME_CHARS} characters"
+            )));
+        }
+        return Ok(Some(filename).filter(|f| !f.is_empty()));
+    }
+    Ok(None)
+}
+
+/// Header value for a string built from ASCII digits, UUIDs and paths.
+fn header_value(value: &str) -> HeaderValue {
+    HeaderValue::from_str(value).expect("header value is ASCII")
+}
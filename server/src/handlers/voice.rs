@@ -7,12 +7,18 @@ use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
 use super::shared::{
-    fetch_channel_by_id, require_channel_permission, require_member, PERMISSION_CONNECT_VOICE,
+    can_moderate, fetch_channel_by_id, require_channel_permission, require_member,
+    PERMISSION_CONNECT_VOICE, PERMISSION_MUTE_MEMBERS,
 };
 use crate::{
     auth::AuthUser,
     error::{AppError, AppResult},
-    models::{ChannelType, UpdateVoiceStateRequest, VoiceState, VoiceStateDto},
+    handlers::{audit::log_action, go_live::end_go_live_for_user},
+    models::{
+        AuditAction, ChannelType, CreateAuditLog, ModerateVoiceStateRequest,
+        MoveVoiceMemberRequest, UpdateVoiceStateRequest, VoiceJoinResponse, VoiceMode, VoiceState,
+        VoiceStateDto,
+    },
     state::AppState,
     websocket::{broadcast_to_server, events::EVENT_VOICE_STATE_UPDATE},
 };
@@ -107,11 +113,31 @@ async fn broadcast_voice_leave(state: &AppState, user_id: Uuid, server_id: Uuid)
 
 /// Query row used to find a user's current voice channel before a join UPSERT.
 ///
-/// Fetched before the UPSERT so we can detect cross-server channel switches
-/// and broadcast a leave event to the old server.
+/// Fetched before the UPSERT so we can end the user's Go Live activity in the
+/// old channel and broadcast a leave event on cross-server switches.
 #[derive(sqlx::FromRow)]
 struct PriorVoiceLocation {
+    channel_id: Uuid,
+    server_id: Uuid,
+}
+
+/// Return the voice channel `user_id` is in on `server_id`, or 404.
+async fn current_voice_channel(
+    state: &AppState,
+    user_id: Uuid,
     server_id: Uuid,
+) -> AppResult<Uuid> {
+    sqlx::query_scalar::<_, Uuid>(
+        "SELECT vs.channel_id
+         FROM voice_states vs
+         JOIN channels c ON c.id = vs.channel_id
+         WHERE vs.user_id = $1 AND c.server_id = $2",
+    )
+    .bind(user_id)
+    .bind(server_id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Member is not in a voice channel on this server".into()))
 }
 
 /// Query row for listing voice channel participants, including username.
@@ -146,6 +172,10 @@ struct VoiceParticipantRow {
 /// If the user was in a voice channel on a *different* server, a
 /// `VOICE_STATE_UPDATE` leave event is broadcast to that server so its
 /// members do not see a ghost participant.
+///
+/// The response's `mode` tells the client whether to connect its media to
+/// the embedded SFU or to each peer directly. An SFU session from a previous
+/// channel is closed here; the client opens a new one with a publisher offer.
 #[utoipa::path(
     post,
     path = "/channels/{channel_id}/voice",
@@ -153,7 +183,7 @@ struct VoiceParticipantRow {
         ("channel_id" = Uuid, Path, description = "Channel ID"),
     ),
     responses(
-        (status = 201, description = "Joined voice channel", body = VoiceStateDto),
+        (status = 201, description = "Joined voice channel", body = VoiceJoinResponse),
     ),
     security(("bearer_auth" = [])),
     tag = "Voice"
@@ -162,7 +192,7 @@ pub async fn join_voice_channel(
     State(state): State<AppState>,
     auth: AuthUser,
     Path(channel_id): Path<Uuid>,
-) -> AppResult<(StatusCode, Json<VoiceStateDto>)> {
+) -> AppResult<(StatusCode, Json<VoiceJoinResponse>)> {
     let channel = fetch_channel_by_id(&state.pool, channel_id).await?;
     require_member(&state.pool, channel.server_id, auth.user_id()).await?;
     require_voice_channel(&channel)?;
@@ -182,7 +212,7 @@ pub async fn join_voice_channel(
     // If they are in a channel on a different server we must broadcast a leave
     // to that server — the UPSERT overwrites the DB row silently.
     let prior: Option<PriorVoiceLocation> = match sqlx::query_as::<_, PriorVoiceLocation>(
-        "SELECT c.server_id
+        "SELECT vs.channel_id, c.server_id
          FROM voice_states vs
          JOIN channels c ON vs.channel_id = c.id
          WHERE vs.user_id = $1",
@@ -203,6 +233,12 @@ pub async fn join_voice_channel(
         }
     };
 
+    // Rejoining resets self_screen, so any broadcast or viewing in the old
+    // channel ends even when the channel is unchanged.
+    if let Some(prior) = &prior {
+        end_go_live_for_user(&state, auth.user_id(), prior.channel_id).await;
+    }
+
     let vs = sqlx::query_as::<_, VoiceState>(
         "INSERT INTO voice_states (user_id, channel_id)
          VALUES ($1, $2)
@@ -232,7 +268,21 @@ pub async fn join_voice_channel(
         }
     }
 
-    Ok((StatusCode::CREATED, Json(VoiceStateDto::from(vs))))
+    let mode = match &state.sfu {
+        Some(sfu) => {
+            sfu.retain_channel(auth.user_id(), channel_id).await;
+            VoiceMode::Sfu
+        }
+        None => VoiceMode::Mesh,
+    };
+
+    Ok((
+        StatusCode::CREATED,
+        Json(VoiceJoinResponse {
+            voice_state: VoiceStateDto::from(vs),
+            mode,
+        }),
+    ))
 }
 
 /// DELETE /channels/:channel_id/voice — leave a voice channel.
@@ -260,6 +310,8 @@ pub async fn leave_voice_channel(
     require_member(&state.pool, channel.server_id, auth.user_id()).await?;
     require_voice_channel(&channel)?;
 
+    end_go_live_for_user(&state, auth.user_id(), channel_id).await;
+
     let result = sqlx::query("DELETE FROM voice_states WHERE user_id = $1 AND channel_id = $2")
         .bind(auth.user_id())
         .bind(channel_id)
@@ -270,6 +322,9 @@ pub async fn leave_voice_channel(
         return Err(AppError::NotFound("Not in this voice channel".into()));
     }
 
+    if let Some(sfu) = &state.sfu {
+        sfu.remove_peer(auth.user_id()).await;
+    }
     broadcast_voice_leave(&state, auth.user_id(), channel.server_id).await;
 
     Ok(StatusCode::NO_CONTENT)
@@ -281,6 +336,8 @@ pub async fn leave_voice_channel(
 /// Returns 404 if the user is not currently in this channel.
 /// Only `self_mute` and `self_deaf` are accepted; `server_mute`/`server_deaf`
 /// are excluded from the request type to prevent privilege escalation.
+/// Clearing `self_mute` while server-muted (or `self_deaf` while
+/// server-deafened) returns 403.
 #[utoipa::path(
     patch,
     path = "/channels/{channel_id}/voice",
@@ -290,6 +347,7 @@ pub async fn leave_voice_channel(
     request_body = UpdateVoiceStateRequest,
     responses(
         (status = 200, description = "Voice state updated", body = VoiceStateDto),
+        (status = 403, description = "Server-muted or server-deafened by a moderator"),
         (status = 404, description = "Not in this voice channel"),
     ),
     security(("bearer_auth" = [])),
@@ -316,6 +374,32 @@ pub async fn update_voice_state(
     require_member(&state.pool, channel.server_id, auth.user_id()).await?;
     require_voice_channel(&channel)?;
 
+    // A moderator restriction cannot be lifted by the user's own toggle. If
+    // the row is missing, the UPDATE below reports the 404.
+    if req.self_mute == Some(false) || req.self_deaf == Some(false) {
+        let restrictions: Option<(bool, bool)> = sqlx::query_as(
+            "SELECT server_mute, server_deaf FROM voice_states
+             WHERE user_id = $1 AND channel_id = $2",
+        )
+        .bind(auth.user_id())
+        .bind(channel_id)
+        .fetch_optional(&state.pool)
+        .await?;
+
+        if let Some((server_mute, server_deaf)) = restrictions {
+            if server_mute && req.self_mute == Some(false) {
+                return Err(AppError::Forbidden(
+                    "You have been server-muted by a moderator".into(),
+                ));
+            }
+            if server_deaf && req.self_deaf == Some(false) {
+                return Err(AppError::Forbidden(
+                    "You have been server-deafened by a moderator".into(),
+                ));
+            }
+        }
+    }
+
     let vs = sqlx::query_as::<_, VoiceState>(
         "UPDATE voice_states
          SET self_mute   = COALESCE($1, self_mute),
@@ -336,6 +420,9 @@ pub async fn update_voice_state(
     .await?
     .ok_or_else(|| AppError::NotFound("Not in this voice channel".into()))?;
 
+    if let Some(sfu) = &state.sfu {
+        sfu.update_voice_state(&vs).await;
+    }
     broadcast_voice_update(&state, &vs, channel.server_id).await;
 
     Ok(Json(VoiceStateDto::from(vs)))
@@ -414,3 +501,270 @@ pub async fn list_voice_participants(
 
     Ok(Json(participants))
 }
+
+// ============================================================================
+// Moderator controls
+// ============================================================================
+
+/// PATCH /servers/:id/members/:user_id/voice — server-mute / server-deafen.
+///
+/// Requires MUTE_MEMBERS (or ADMINISTRATOR) and a role above the target's.
+/// At least one field must be provided. Returns 404 if the target is not in
+/// a voice channel on this server. The flags persist across channel
+/// switches until a moderator clears them.
+#[utoipa::path(
+    patch,
+    path = "/servers/{id}/members/{user_id}/voice",
+    params(
+        ("id" = Uuid, Path, description = "Server ID"),
+        ("user_id" = Uuid, Path, description = "Target user ID"),
+    ),
+    request_body = ModerateVoiceStateRequest,
+    responses(
+        (status = 200, description = "Voice state updated", body = VoiceStateDto),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Member is not in a voice channel on this server"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Voice"
+)]
+pub async fn moderate_voice_state(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((server_id, target_user_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<ModerateVoiceStateRequest>,
+) -> AppResult<Json<VoiceStateDto>> {
+    if req.server_mute.is_none() && req.server_deaf.is_none() {
+        return Err(AppError::Validation(
+            "At least one field (server_mute or server_deaf) must be provided".into(),
+        ));
+    }
+
+    require_member(&state.pool, server_id, auth.user_id()).await?;
+    require_member(&state.pool, server_id, target_user_id).await?;
+    can_moderate(
+        &state.pool,
+        server_id,
+        auth.user_id(),
+        target_user_id,
+        PERMISSION_MUTE_MEMBERS,
+    )
+    .await?;
+
+    let vs = sqlx::query_as::<_, VoiceState>(
+        "UPDATE voice_states vs
+         SET server_mute = COALESCE($1, vs.server_mute),
+             server_deaf = COALESCE($2, vs.server_deaf)
+         FROM channels c
+         WHERE c.id = vs.channel_id AND vs.user_id = $3 AND c.server_id = $4
+         RETURNING vs.user_id, vs.channel_id, vs.self_mute, vs.self_deaf,
+                   vs.self_video, vs.self_screen, vs.server_mute, vs.server_deaf,
+                   vs.joined_at",
+    )
+    .bind(req.server_mute)
+    .bind(req.server_deaf)
+    .bind(target_user_id)
+    .bind(server_id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Member is not in a voice channel on this server".into()))?;
+
+    if let Some(sfu) = &state.sfu {
+        sfu.update_voice_state(&vs).await;
+    }
+    broadcast_voice_update(&state, &vs, server_id).await;
+
+    log_action(
+        &state.pool,
+        &CreateAuditLog {
+            server_id,
+            actor_id: auth.user_id(),
+            action: AuditAction::MemberVoiceUpdate,
+            target_type: Some("user".into()),
+            target_id: Some(target_user_id),
+            details: serde_json::json!({
+                "channel_id": vs.channel_id,
+                "server_mute": req.server_mute,
+                "server_deaf": req.server_deaf,
+            }),
+            ip_address: None,
+        },
+    )
+    .await;
+
+    Ok(Json(VoiceStateDto::from(vs)))
+}
+
+/// POST /servers/:id/members/:user_id/voice/move — move a member to another
+/// voice channel on the same server.
+///
+/// Requires MUTE_MEMBERS (or ADMINISTRATOR) and a role above the target's;
+/// the target must also be allowed to connect to the destination channel.
+/// Self flags are kept, video and screen share are reset as on a normal
+/// join. The target's SFU session is closed and their client reconnects to
+/// the new channel on the `VOICE_STATE_UPDATE`.
+#[utoipa::path(
+    post,
+    path = "/servers/{id}/members/{user_id}/voice/move",
+    params(
+        ("id" = Uuid, Path, description = "Server ID"),
+        ("user_id" = Uuid, Path, description = "Target user ID"),
+    ),
+    request_body = MoveVoiceMemberRequest,
+    responses(
+        (status = 200, description = "Member moved", body = VoiceStateDto),
+        (status = 400, description = "Destination is not a voice channel"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Member is not in a voice channel on this server"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Voice"
+)]
+pub async fn move_voice_member(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((server_id, target_user_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<MoveVoiceMemberRequest>,
+) -> AppResult<Json<VoiceStateDto>> {
+    require_member(&state.pool, server_id, auth.user_id()).await?;
+    require_member(&state.pool, server_id, target_user_id).await?;
+    can_moderate(
+        &state.pool,
+        server_id,
+        auth.user_id(),
+        target_user_id,
+        PERMISSION_MUTE_MEMBERS,
+    )
+    .await?;
+
+    let channel = fetch_channel_by_id(&state.pool, req.channel_id).await?;
+    if channel.server_id != server_id {
+        return Err(AppError::NotFound("Channel not found".into()));
+    }
+    require_voice_channel(&channel)?;
+    require_channel_permission(
+        &state.pool,
+        server_id,
+        channel.id,
+        target_user_id,
+        PERMISSION_CONNECT_VOICE,
+        "Member doesn't have permission to connect to that voice channel",
+    )
+    .await?;
+
+    let from_channel_id = current_voice_channel(&state, target_user_id, server_id).await?;
+    end_go_live_for_user(&state, target_user_id, from_channel_id).await;
+
+    let vs = sqlx::query_as::<_, VoiceState>(
+        "UPDATE voice_states vs
+         SET channel_id  = $1,
+             self_video  = FALSE,
+             self_screen = FALSE,
+             joined_at   = NOW()
+         FROM channels c
+         WHERE c.id = vs.channel_id AND vs.user_id = $2 AND c.server_id = $3
+         RETURNING vs.user_id, vs.channel_id, vs.self_mute, vs.self_deaf,
+                   vs.self_video, vs.self_screen, vs.server_mute, vs.server_deaf,
+                   vs.joined_at",
+    )
+    .bind(channel.id)
+    .bind(target_user_id)
+    .bind(server_id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Member is not in a voice channel on this server".into()))?;
+
+    if let Some(sfu) = &state.sfu {
+        sfu.retain_channel(target_user_id, channel.id).await;
+    }
+    broadcast_voice_update(&state, &vs, server_id).await;
+
+    log_action(
+        &state.pool,
+        &CreateAuditLog {
+            server_id,
+            actor_id: auth.user_id(),
+            action: AuditAction::MemberVoiceMove,
+            target_type: Some("user".into()),
+            target_id: Some(target_user_id),
+            details: serde_json::json!({ "channel_id": channel.id }),
+            ip_address: None,
+        },
+    )
+    .await;
+
+    Ok(Json(VoiceStateDto::from(vs)))
+}
+
+/// DELETE /servers/:id/members/:user_id/voice — disconnect a member from
+/// voice.
+///
+/// Requires MUTE_MEMBERS (or ADMINISTRATOR) and a role above the target's.
+/// Returns 404 if the target is not in a voice channel on this server.
+#[utoipa::path(
+    delete,
+    path = "/servers/{id}/members/{user_id}/voice",
+    params(
+        ("id" = Uuid, Path, description = "Server ID"),
+        ("user_id" = Uuid, Path, description = "Target user ID"),
+    ),
+    responses(
+        (status = 204, description = "Member disconnected"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Member is not in a voice channel on this server"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Voice"
+)]
+pub async fn disconnect_voice_member(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((server_id, target_user_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<StatusCode> {
+    require_member(&state.pool, server_id, auth.user_id()).await?;
+    require_member(&state.pool, server_id, target_user_id).await?;
+    can_moderate(
+        &state.pool,
+        server_id,
+        auth.user_id(),
+        target_user_id,
+        PERMISSION_MUTE_MEMBERS,
+    )
+    .await?;
+
+    let channel_id = current_voice_channel(&state, target_user_id, server_id).await?;
+    end_go_live_for_user(&state, target_user_id, channel_id).await;
+
+    let result = sqlx::query("DELETE FROM voice_states WHERE user_id = $1 AND channel_id = $2")
+        .bind(target_user_id)
+        .bind(channel_id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(
+            "Member is not in a voice channel on this server".into(),
+        ));
+    }
+
+    if let Some(sfu) = &state.sfu {
+        sfu.remove_peer(target_user_id).await;
+    }
+    broadcast_voice_leave(&state, target_user_id, server_id).await;
+
+    log_action(
+        &state.pool,
+        &CreateAuditLog {
+            server_id,
+            actor_id: auth.user_id(),
+            action: AuditAction::MemberVoiceDisconnect,
+            target_type: Some("user".into()),
+            target_id: Some(target_user_id),
+            details: serde_json::json!({ "channel_id": channel_id }),
+            ip_address: None,
+        },
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
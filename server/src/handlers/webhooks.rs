@@ -1,10 +1,11 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     Json,
 };
 use serde_json::{json, Value};
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 use chrono::{DateTime, Utc};
@@ -14,7 +15,11 @@ use super::shared::{fetch_server, require_member};
 use crate::{
     auth::AuthUser,
     error::{AppError, AppResult},
-    models::{CreateWebhookDto, UpdateWebhookDto, Webhook, WebhookCreatedResponse, WebhookDto},
+    models::{
+        CreateWebhookDto, ListWebhookDeliveriesQuery, UpdateWebhookDto, Webhook,
+        WebhookCreatedResponse, WebhookDelivery, WebhookDeliveryAttempt, WebhookDeliveryDto,
+        WebhookDto,
+    },
     state::AppState,
     webhook_delivery::{fire_event, DeliveryJob},
 };
@@ -58,7 +63,7 @@ impl From<WebhookNoSecret> for WebhookDto {
 // ── Permission guard ──────────────────────────────────────────────────────────
 
 /// Require that the user is the server owner or has ADMINISTRATOR (bit 13).
-async fn require_manage_webhooks(
+pub async fn require_manage_webhooks(
     pool: &sqlx::PgPool,
     server_id: Uuid,
     user_id: Uuid,
@@ -500,32 +505,190 @@ pub async fn test_webhook(
 
     let body = serde_json::to_string(&test_payload).map_err(|_| AppError::Internal)?;
 
-    state.webhook_queue.send(DeliveryJob::new(
-        webhook.id,
-        webhook.url,
-        webhook.secret,
-        body,
-    ));
+    state
+        .webhook_queue
+        .send(DeliveryJob::new(webhook.id, "ping", body))
+        .await?;
 
     Ok(StatusCode::ACCEPTED)
 }
 
+// ── GET /servers/:id/webhooks/:webhook_id/deliveries ──────────────────────────
+
+const DEFAULT_DELIVERY_LIMIT: i64 = 50;
+const MAX_DELIVERY_LIMIT: i64 = 100;
+const DELIVERY_STATUSES: &[&str] = &["pending", "in_flight", "succeeded", "failed"];
+
+/// Verify the webhook exists on this server, returning 404 otherwise.
+async fn require_webhook(pool: &sqlx::PgPool, server_id: Uuid, webhook_id: Uuid) -> AppResult<()> {
+    let exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM webhooks WHERE id = $1 AND server_id = $2)",
+    )
+    .bind(webhook_id)
+    .bind(server_id)
+    .fetch_one(pool)
+    .await?;
+    if exists {
+        Ok(())
+    } else {
+        Err(AppError::NotFound("Webhook not found".into()))
+    }
+}
+
+/// List recent deliveries for a webhook, newest first, with attempt history.
+#[utoipa::path(
+    get,
+    path = "/servers/{id}/webhooks/{webhook_id}/deliveries",
+    params(
+        ("id" = Uuid, Path, description = "Server ID"),
+        ("webhook_id" = Uuid, Path, description = "Webhook ID"),
+        ListWebhookDeliveriesQuery,
+    ),
+    responses(
+        (status = 200, description = "Deliveries with attempt history"),
+        (status = 400, description = "Invalid status filter"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Webhook not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Webhooks"
+)]
+pub async fn list_deliveries(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path((server_id, webhook_id)): Path<(Uuid, Uuid)>,
+    Query(params): Query<ListWebhookDeliveriesQuery>,
+) -> AppResult<Json<Value>> {
+    require_member(&state.pool, server_id, auth.user_id()).await?;
+    require_manage_webhooks(&state.pool, server_id, auth.user_id()).await?;
+    require_webhook(&state.pool, server_id, webhook_id).await?;
+
+    if let Some(status) = &params.status {
+        if !DELIVERY_STATUSES.contains(&status.as_str()) {
+            return Err(AppError::Validation(format!(
+                "Unknown status '{}'. Valid statuses: {}",
+                status,
+                DELIVERY_STATUSES.join(", ")
+            )));
+        }
+    }
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_DELIVERY_LIMIT)
+        .clamp(1, MAX_DELIVERY_LIMIT);
+
+    let deliveries = sqlx::query_as::<_, WebhookDelivery>(
+        "SELECT id, webhook_id, event_type, payload, status, attempt_count,
+                next_attempt_at, redelivery_of, created_at, completed_at
+         FROM webhook_deliveries
+         WHERE webhook_id = $1
+           AND ($2::text IS NULL OR status = $2)
+           AND ($3::timestamptz IS NULL OR created_at < $3)
+         ORDER BY created_at DESC
+         LIMIT $4",
+    )
+    .bind(webhook_id)
+    .bind(&params.status)
+    .bind(params.before)
+    .bind(limit)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let ids: Vec<Uuid> = deliveries.iter().map(|d| d.id).collect();
+    let attempts = sqlx::query_as::<_, WebhookDeliveryAttempt>(
+        "SELECT delivery_id, attempt, response_status, error, latency_ms, attempted_at
+         FROM webhook_delivery_attempts
+         WHERE delivery_id = ANY($1)
+         ORDER BY delivery_id, attempt",
+    )
+    .bind(&ids)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let mut attempts_by_delivery: HashMap<Uuid, Vec<WebhookDeliveryAttempt>> = HashMap::new();
+    for attempt in attempts {
+        attempts_by_delivery
+            .entry(attempt.delivery_id)
+            .or_default()
+            .push(attempt);
+    }
+
+    let deliveries: Vec<WebhookDeliveryDto> = deliveries
+        .into_iter()
+        .map(|d| {
+            let attempts = attempts_by_delivery.remove(&d.id).unwrap_or_default();
+            WebhookDeliveryDto::new(d, attempts)
+        })
+        .collect();
+
+    Ok(Json(json!({ "deliveries": deliveries })))
+}
+
+// ── POST /servers/:id/webhooks/:webhook_id/deliveries/:delivery_id/redeliver ──
+
+/// Queue a new delivery with the same payload as an earlier one.
+#[utoipa::path(
+    post,
+    path = "/servers/{id}/webhooks/{webhook_id}/deliveries/{delivery_id}/redeliver",
+    params(
+        ("id" = Uuid, Path, description = "Server ID"),
+        ("webhook_id" = Uuid, Path, description = "Webhook ID"),
+        ("delivery_id" = Uuid, Path, description = "Delivery to replay"),
+    ),
+    responses(
+        (status = 202, description = "Redelivery queued; body contains the new delivery id"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "Webhook or delivery not found"),
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Webhooks"
+)]
+pub async fn redeliver_delivery(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path((server_id, webhook_id, delivery_id)): Path<(Uuid, Uuid, Uuid)>,
+) -> AppResult<(StatusCode, Json<Value>)> {
+    require_member(&state.pool, server_id, auth.user_id()).await?;
+    require_manage_webhooks(&state.pool, server_id, auth.user_id()).await?;
+    require_webhook(&state.pool, server_id, webhook_id).await?;
+
+    let belongs: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM webhook_deliveries WHERE id = $1 AND webhook_id = $2)",
+    )
+    .bind(delivery_id)
+    .bind(webhook_id)
+    .fetch_one(&state.pool)
+    .await?;
+    if !belongs {
+        return Err(AppError::NotFound("Delivery not found".into()));
+    }
+
+    let new_id = state
+        .webhook_queue
+        .redeliver(delivery_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Delivery not found".into()))?;
+
+    tracing::info!(
+        webhook_id = %webhook_id,
+        delivery_id = %delivery_id,
+        redelivery_id = %new_id,
+        requested_by = %auth.user_id(),
+        "Webhook redelivery queued"
+    );
+
+    Ok((StatusCode::ACCEPTED, Json(json!({ "id": new_id }))))
+}
+
 // ── Internal helper (called from other handlers) ──────────────────────────────
 
 /// Fire a webhook event for all matching webhooks on a server.
-/// Non-blocking — schedules delivery jobs on the queue.
+/// Persists delivery rows for the background worker; never waits on HTTP.
 pub async fn dispatch_event(
     state: &AppState,
     server_id: Uuid,
     event_type: &str,
     data: serde_json::Value,
 ) {
-    fire_event(
-        &state.webhook_queue,
-        &state.pool,
-        server_id,
-        event_type,
-        data,
-    )
-    .await;
+    fire_event(&state.webhook_queue, server_id, event_type, data).await;
 }
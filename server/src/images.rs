@@ -0,0 +1,346 @@
+//! Image processing for uploaded attachments and custom emojis.
+//!
+//! # Design
+//!
+//! [`process`] runs once per uploaded image, before anything is written to
+//! disk. It decodes the image to check it and read its dimensions, removes
+//! metadata, and renders WebP thumbnails.
+//!
+//! Metadata is removed without re-encoding where possible, so the stored file
+//! keeps its original quality:
+//!
+//! - JPEG: EXIF and XMP (`APP1`), IPTC (`APP13`) and comment segments;
+//! - PNG: `eXIf`, `tEXt`, `zTXt`, `iTXt` and `tIME` chunks;
+//! - WebP: `EXIF` and `XMP ` chunks;
+//! - GIF: stored as uploaded (GIF has no EXIF).
+//!
+//! Colour profiles are kept. An image whose EXIF orientation is not the
+//! default is rotated and re-encoded instead, since dropping the orientation
+//! tag would otherwise show it sideways.
+//!
+//! A thumbnail fits within a `size`×`size` square and is only made for sizes
+//! smaller than the image's longer side: see [`has_thumbnail`]. Thumbnails of
+//! animated GIFs show the first frame. They are stored next to the original
+//! under [`thumbnail_name`].
+
+use std::io::Cursor;
+
+use bytes::Bytes;
+use image::{
+    codecs::{jpeg::JpegEncoder, png::PngEncoder},
+    metadata::Orientation,
+    DynamicImage, ImageDecoder, ImageEncoder, ImageFormat, ImageReader, Limits,
+};
+use img_parts::{jpeg::markers, png::Png, webp::WebP, ImageEXIF};
+
+/// Images wider or taller than this are refused.
+pub const MAX_DIMENSION: u32 = 12_000;
+
+/// Upper bound on memory the decoder may allocate for one image.
+const MAX_DECODE_ALLOC: u64 = 512 * 1024 * 1024;
+
+/// WebP quality for thumbnails (0–100).
+const THUMBNAIL_QUALITY: f32 = 80.0;
+
+/// JPEG quality when an image has to be re-encoded to apply its orientation.
+const REENCODE_JPEG_QUALITY: u8 = 90;
+
+/// PNG chunks that carry metadata rather than pixels or colour information.
+const PNG_METADATA_CHUNKS: &[[u8; 4]] = &[*b"eXIf", *b"tEXt", *b"zTXt", *b"iTXt", *b"tIME"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImageError {
+    #[error("The image could not be read. Upload a valid JPEG, PNG, GIF, or WebP file.")]
+    Undecodable,
+
+    #[error("Images may not be larger than {MAX_DIMENSION}×{MAX_DIMENSION} pixels")]
+    TooLarge,
+}
+
+/// An uploaded image, ready to store.
+pub struct ProcessedImage {
+    /// The image without metadata, in its original format.
+    pub data: Bytes,
+    pub width: u32,
+    pub height: u32,
+    pub thumbnails: Vec<Thumbnail>,
+}
+
+/// A WebP rendering of an image that fits within `size`×`size`.
+pub struct Thumbnail {
+    pub size: u32,
+    pub data: Vec<u8>,
+}
+
+/// Whether an image of `width`×`height` gets a thumbnail at `size`.
+pub fn has_thumbnail(width: u32, height: u32, size: u32) -> bool {
+    width.max(height) > size
+}
+
+/// File name of the `size` thumbnail of the stored file `stored_name`.
+pub fn thumbnail_name(stored_name: &str, size: u32) -> String {
+    format!("{stored_name}.{size}.webp")
+}
+
+/// Decode, clean and thumbnail an image whose type was detected as
+/// `mime_type`. Runs on the blocking thread pool.
+pub async fn process(
+    data: Bytes,
+    mime_type: &str,
+    sizes: &'static [u32],
+) -> Result<ProcessedImage, ImageError> {
+    let format = ImageFormat::from_mime_type(mime_type).ok_or(ImageError::Undecodable)?;
+    tokio::task::spawn_blocking(move || process_sync(data, format, sizes))
+        .await
+        .map_err(|e| {
+            tracing::error!(error = ?e, "Image processing task failed");
+            ImageError::Undecodable
+        })?
+}
+
+fn process_sync(
+    data: Bytes,
+    format: ImageFormat,
+    sizes: &[u32],
+) -> Result<ProcessedImage, ImageError> {
+    let (image, orientation) = decode(&data, format)?;
+
+    let (image, data) = if orientation == Orientation::NoTransforms {
+        (image, strip_metadata(data, format)?)
+    } else {
+        let mut image = image;
+        image.apply_orientation(orientation);
+        let data = encode(&image, format)?;
+        (image, data)
+    };
+
+    let (width, height) = (image.width(), image.height());
+
+    // Largest first, each rendered from the one before to keep resizing cheap.
+    let mut wanted: Vec<u32> = sizes
+        .iter()
+        .copied()
+        .filter(|&size| has_thumbnail(width, height, size))
+        .collect();
+    wanted.sort_unstable_by(|a, b| b.cmp(a));
+
+    let mut thumbnails = Vec::with_capacity(wanted.len());
+    let mut source = image;
+    for size in wanted {
+        source = source.thumbnail(size, size);
+        let rgba = source.to_rgba8();
+        let data = webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height())
+            .encode(THUMBNAIL_QUALITY)
+            .to_vec();
+        thumbnails.push(Thumbnail { size, data });
+    }
+
+    Ok(ProcessedImage {
+        data,
+        width,
+        height,
+        thumbnails,
+    })
+}
+
+fn decode(data: &[u8], format: ImageFormat) -> Result<(DynamicImage, Orientation), ImageError> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+
+    let mut reader = ImageReader::with_format(Cursor::new(data), format);
+    reader.limits(limits);
+    let mut decoder = reader.into_decoder().map_err(decode_error)?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let image = DynamicImage::from_decoder(decoder).map_err(decode_error)?;
+    Ok((image, orientation))
+}
+
+fn decode_error(e: image::ImageError) -> ImageError {
+    match e {
+        image::ImageError::Limits(_) => ImageError::TooLarge,
+        e => {
+            tracing::debug!(error = %e, "Uploaded image could not be decoded");
+            ImageError::Undecodable
+        }
+    }
+}
+
+/// Remove metadata from an encoded image without touching its pixels.
+fn strip_metadata(data: Bytes, format: ImageFormat) -> Result<Bytes, ImageError> {
+    match format {
+        ImageFormat::Jpeg => {
+            let mut jpeg = img_parts::jpeg::Jpeg::from_bytes(data.clone())
+                .map_err(|_| ImageError::Undecodable)?;
+            let before = jpeg.segments().len();
+            jpeg.segments_mut().retain(|segment| {
+                !matches!(
+                    segment.marker(),
+                    markers::APP1 | markers::APP13 | markers::COM
+                )
+            });
+            if jpeg.segments().len() == before {
+                return Ok(data);
+            }
+            Ok(jpeg.encoder().bytes())
+        }
+        ImageFormat::Png => {
+            let mut png = Png::from_bytes(data.clone()).map_err(|_| ImageError::Undecodable)?;
+            let before = png.chunks().len();
+            png.chunks_mut()
+                .retain(|chunk| !PNG_METADATA_CHUNKS.contains(&chunk.kind()));
+            if png.chunks().len() == before {
+                return Ok(data);
+            }
+            Ok(png.encoder().bytes())
+        }
+        ImageFormat::WebP => {
+            let mut webp = WebP::from_bytes(data.clone()).map_err(|_| ImageError::Undecodable)?;
+            if !webp.has_chunk(*b"EXIF") && !webp.has_chunk(*b"XMP ") {
+                return Ok(data);
+            }
+            webp.remove_chunks_by_id(*b"XMP ");
+            // Also clears the EXIF and XMP flags in the VP8X header.
+            webp.set_exif(None);
+            Ok(webp.encoder().bytes())
+        }
+        _ => Ok(data),
+    }
+}
+
+/// Encode `image` in `format`, for images that had to be rotated.
+fn encode(image: &DynamicImage, format: ImageFormat) -> Result<Bytes, ImageError> {
+    let mut out = Vec::new();
+    let result = match format {
+        ImageFormat::Jpeg => {
+            let rgb = image.to_rgb8();
+            JpegEncoder::new_with_quality(&mut out, REENCODE_JPEG_QUALITY).write_image(
+                &rgb,
+                rgb.width(),
+                rgb.height(),
+                image::ExtendedColorType::Rgb8,
+            )
+        }
+        ImageFormat::Png => {
+            let rgba = image.to_rgba8();
+            PngEncoder::new(&mut out).write_image(
+                &rgba,
+                rgba.width(),
+                rgba.height(),
+                image::ExtendedColorType::Rgba8,
+            )
+        }
+        _ => {
+            let rgba = image.to_rgba8();
+            out = webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height())
+                .encode_lossless()
+                .to_vec();
+            Ok(())
+        }
+    };
+    result.map_err(|e| {
+        tracing::error!(error = %e, "Failed to re-encode rotated image");
+        ImageError::Undecodable
+    })?;
+    Ok(Bytes::from(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    fn jpeg(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbImage::from_pixel(width, height, Rgb([200, 40, 40]));
+        let mut out = Vec::new();
+        JpegEncoder::new_with_quality(&mut out, 90)
+            .write_image(&image, width, height, image::ExtendedColorType::Rgb8)
+            .unwrap();
+        out
+    }
+
+    /// `jpeg` with an EXIF segment holding only an orientation tag and a
+    /// comment segment, both after the JFIF header.
+    fn jpeg_with_exif(width: u32, height: u32, orientation: u16) -> Vec<u8> {
+        let mut tiff =
+            b"MM\x00\x2a\x00\x00\x00\x08\x00\x01\x01\x12\x00\x03\x00\x00\x00\x01".to_vec();
+        tiff.extend_from_slice(&orientation.to_be_bytes());
+        tiff.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        let mut app1 = b"Exif\x00\x00".to_vec();
+        app1.extend_from_slice(&tiff);
+
+        let plain = jpeg(width, height);
+        let mut out = plain[..2].to_vec();
+        out.extend_from_slice(&[0xFF, markers::APP1]);
+        out.extend_from_slice(&((app1.len() + 2) as u16).to_be_bytes());
+        out.extend_from_slice(&app1);
+        out.extend_from_slice(&[0xFF, markers::COM, 0x00, 0x07]);
+        out.extend_from_slice(b"GPS!!");
+        out.extend_from_slice(&plain[2..]);
+        out
+    }
+
+    #[test]
+    fn records_dimensions_and_makes_smaller_thumbnails() {
+        let image = process_sync(
+            Bytes::from(jpeg(600, 300)),
+            ImageFormat::Jpeg,
+            &[128, 512, 1024],
+        )
+        .unwrap();
+        assert_eq!((image.width, image.height), (600, 300));
+
+        let sizes: Vec<u32> = image.thumbnails.iter().map(|t| t.size).collect();
+        assert_eq!(sizes, vec![512, 128]);
+        let small = image::load_from_memory(&image.thumbnails[1].data).unwrap();
+        assert_eq!((small.width(), small.height()), (128, 64));
+        assert_eq!(
+            image::guess_format(&image.thumbnails[0].data).unwrap(),
+            ImageFormat::WebP
+        );
+    }
+
+    #[test]
+    fn strips_exif_and_comments_without_reencoding() {
+        let plain = jpeg(40, 20);
+        let image = process_sync(
+            Bytes::from(jpeg_with_exif(40, 20, 1)),
+            ImageFormat::Jpeg,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(image.data.as_ref(), plain.as_slice());
+    }
+
+    #[test]
+    fn applies_orientation_before_stripping_it() {
+        // 6 = rotate 90° clockwise.
+        let image = process_sync(
+            Bytes::from(jpeg_with_exif(40, 20, 6)),
+            ImageFormat::Jpeg,
+            &[],
+        )
+        .unwrap();
+        assert_eq!((image.width, image.height), (20, 40));
+        let jpeg = img_parts::jpeg::Jpeg::from_bytes(image.data).unwrap();
+        assert!(jpeg.exif().is_none());
+    }
+
+    #[test]
+    fn leaves_clean_files_untouched() {
+        let plain = Bytes::from(jpeg(10, 10));
+        let image = process_sync(plain.clone(), ImageFormat::Jpeg, &[]).unwrap();
+        assert_eq!(image.data, plain);
+    }
+
+    #[test]
+    fn refuses_broken_images() {
+        let mut broken = jpeg(10, 10);
+        broken.truncate(broken.len() / 2);
+        assert!(matches!(
+            process_sync(Bytes::from(broken), ImageFormat::Jpeg, &[]),
+            Err(ImageError::Undecodable)
+        ));
+    }
+}
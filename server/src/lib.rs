@@ -1,11 +1,21 @@
+pub mod attachment_gc;
 pub mod auth;
+pub mod automod_engine;
 pub mod bot_auth;
 pub mod config;
 pub mod db;
 pub mod error;
+pub mod event_bus;
 pub mod handlers;
+pub mod images;
+pub mod mail;
 pub mod models;
 pub mod openapi;
+pub mod push;
+pub mod scheduled_messages;
+pub mod sfu;
 pub mod state;
+pub mod storage;
+pub mod uploads;
 pub mod webhook_delivery;
 pub mod websocket;
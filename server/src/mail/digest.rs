@@ -0,0 +1,231 @@
+//! Unread-mention digest emails.
+//!
+//! Users opt in through `PUT /users/@me/email-preferences`. Every
+//! [`POLL_INTERVAL`] the worker claims users whose last run is at least the
+//! digest interval old, stamping `digest_last_run_at` in the same statement
+//! (`FOR UPDATE SKIP LOCKED`, so replicas never claim the same user).
+//!
+//! A claimed user gets an email only if something new arrived since their
+//! previous run: a mention in a channel that still has unread mentions, or a
+//! message in a DM that is still unread. The email lists every such channel
+//! with its counts, after the user's notification settings are applied (see
+//! [`ready_read_states`]), so muted channels never appear.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use super::{templates::escape_html, Template};
+use crate::{error::AppResult, handlers::read_states::ready_read_states, state::AppState};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(300);
+/// Users claimed per poll.
+const BATCH_SIZE: i64 = 50;
+
+/// Spawn the digest worker. Call once in `main` when
+/// [`super::Mailer::digest_interval`] is set.
+pub fn start_worker(state: AppState, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(POLL_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            loop {
+                match run_due(&state, interval).await {
+                    Ok(n) if n as i64 == BATCH_SIZE => continue,
+                    Ok(_) => break,
+                    Err(e) => {
+                        tracing::warn!(error = ?e, "Failed to run mention digests");
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+#[derive(sqlx::FromRow)]
+struct DueUser {
+    user_id: Uuid,
+    username: String,
+    email: String,
+    /// Start of the window: the previous run, or when the user opted in.
+    since: DateTime<Utc>,
+}
+
+/// Claim one batch of users whose digest is due and email those with new
+/// activity. Returns how many users were claimed.
+pub async fn run_due(state: &AppState, interval: Duration) -> AppResult<usize> {
+    let Some(mailer) = &state.mailer else {
+        return Ok(0);
+    };
+
+    let due = sqlx::query_as::<_, DueUser>(
+        "WITH due AS (
+             SELECT ep.user_id, COALESCE(ep.digest_last_run_at, ep.updated_at) AS since
+             FROM email_preferences ep
+             JOIN users u ON u.id = ep.user_id
+             WHERE ep.mention_digest
+               AND u.email IS NOT NULL
+               AND u.email_verified_at IS NOT NULL
+               AND NOT u.disabled
+               AND (ep.digest_last_run_at IS NULL
+                    OR ep.digest_last_run_at <= NOW() - make_interval(secs => $1))
+             ORDER BY ep.digest_last_run_at NULLS FIRST
+             LIMIT $2
+             FOR UPDATE OF ep SKIP LOCKED
+         )
+         UPDATE email_preferences ep SET digest_last_run_at = NOW()
+         FROM due
+         JOIN users u ON u.id = due.user_id
+         WHERE ep.user_id = due.user_id
+         RETURNING ep.user_id, u.username, u.email, due.since",
+    )
+    .bind(interval.as_secs_f64())
+    .bind(BATCH_SIZE)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let claimed = due.len();
+    for user in due {
+        let user_id = user.user_id;
+        match build_digest(state, &user).await {
+            Ok(Some(vars)) => {
+                let link = mailer.link("/");
+                let mut vars: Vec<(&str, &str)> =
+                    vars.iter().map(|(k, v)| (*k, v.as_str())).collect();
+                vars.push(("username", &user.username));
+                vars.push(("link", &link));
+                let email = mailer.compose(&user.email, Template::MentionDigest, &vars);
+                if let Err(e) = mailer.send(&email).await {
+                    tracing::warn!(%user_id, error = %e, "Failed to send mention digest");
+                }
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!(%user_id, error = ?e, "Failed to build mention digest"),
+        }
+    }
+    Ok(claimed)
+}
+
+/// Template variables for one user's digest, or `None` when nothing new
+/// arrived since the previous run.
+async fn build_digest(
+    state: &AppState,
+    user: &DueUser,
+) -> AppResult<Option<Vec<(&'static str, String)>>> {
+    let read_states: HashMap<Uuid, (i64, i64)> = ready_read_states(state, user.user_id)
+        .await?
+        .into_iter()
+        .filter(|rs| rs.unread_count > 0)
+        .map(|rs| (rs.channel_id, (rs.unread_count, rs.mention_count)))
+        .collect();
+    if read_states.is_empty() {
+        return Ok(None);
+    }
+    let channel_ids: Vec<Uuid> = read_states.keys().copied().collect();
+
+    let channels: Vec<(Uuid, String, String)> = sqlx::query_as(
+        "SELECT c.id, c.name, s.name
+         FROM channels c JOIN servers s ON s.id = c.server_id
+         WHERE c.id = ANY($1)
+         ORDER BY s.name, c.position, c.name",
+    )
+    .bind(&channel_ids)
+    .fetch_all(&state.pool)
+    .await?;
+    let dms: Vec<(Uuid, String)> = sqlx::query_as(
+        "SELECT dmm.channel_id, string_agg(u.username, ', ' ORDER BY u.username)
+         FROM direct_message_members dmm
+         JOIN users u ON u.id = dmm.user_id
+         WHERE dmm.channel_id = ANY($1) AND dmm.user_id <> $2
+         GROUP BY dmm.channel_id
+         ORDER BY 2",
+    )
+    .bind(&channel_ids)
+    .bind(user.user_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let mut lines = Vec::new();
+    let mut total = 0;
+    let mut mention_channels = Vec::new();
+    for (channel_id, channel_name, server_name) in &channels {
+        let (_, mentions) = read_states[channel_id];
+        if mentions == 0 {
+            continue;
+        }
+        total += mentions;
+        mention_channels.push(*channel_id);
+        lines.push(format!(
+            "#{channel_name} in {server_name}: {mentions} {}",
+            plural(mentions, "mention")
+        ));
+    }
+    let mut dm_channels = Vec::new();
+    for (channel_id, members) in &dms {
+        let (unread, _) = read_states[channel_id];
+        total += unread;
+        dm_channels.push(*channel_id);
+        lines.push(format!(
+            "Messages from {members}: {unread} {}",
+            plural(unread, "unread message")
+        ));
+    }
+    if lines.is_empty() {
+        return Ok(None);
+    }
+
+    let has_new: bool = sqlx::query_scalar(
+        "SELECT EXISTS(
+                    SELECT 1 FROM messages m
+                    WHERE m.channel_id = ANY($1)
+                      AND m.created_at > $3
+                      AND m.deleted = FALSE
+                      AND m.author_id IS DISTINCT FROM $4
+                      AND ($4 = ANY(m.mention_user_ids) OR m.mention_everyone)
+                )
+             OR EXISTS(
+                    SELECT 1 FROM direct_messages dm
+                    WHERE dm.channel_id = ANY($2)
+                      AND dm.created_at > $3
+                      AND dm.deleted = FALSE
+                      AND dm.author_id IS DISTINCT FROM $4
+                )",
+    )
+    .bind(&mention_channels)
+    .bind(&dm_channels)
+    .bind(user.since)
+    .bind(user.user_id)
+    .fetch_one(&state.pool)
+    .await?;
+    if !has_new {
+        return Ok(None);
+    }
+
+    let items = lines
+        .iter()
+        .map(|line| format!("- {line}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let items_html = lines
+        .iter()
+        .map(|line| format!("<li>{}</li>", escape_html(line)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(Some(vec![
+        ("count", total.to_string()),
+        ("items", items),
+        ("items_html", items_html),
+    ]))
+}
+
+fn plural(n: i64, word: &str) -> String {
+    if n == 1 {
+        word.to_string()
+    } else {
+        format!("{word}s")
+    }
+}
@@ -0,0 +1,201 @@
+//! Outgoing email: password resets, address verification, invites and the
+//! unread-mention digest.
+//!
+//! # Design
+//!
+//! [`Mailer`] renders a [`Template`] into an [`Email`] and hands it to one of
+//! three transports chosen by `MAIL_TRANSPORT`:
+//!
+//! - `smtp`: a pooled SMTP connection (STARTTLS, implicit TLS, or plain for
+//!   a local relay);
+//! - `file`: one JSON file per email in `MAIL_DIR`, for tests and local
+//!   development;
+//! - `log`: the email is written to the log at `info` level.
+//!
+//! Handlers call [`send`], which sends in a background task so a slow SMTP
+//! server never holds up the request and response times do not reveal
+//! whether an address has an account. Failures are logged, not retried.
+
+pub mod digest;
+pub mod templates;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use serde::Serialize;
+
+pub use templates::Template;
+use templates::Templates;
+
+use crate::{
+    config::{MailConfig, MailTransport, SmtpTls},
+    state::AppState,
+};
+
+/// A rendered email ready to send.
+#[derive(Debug, Clone, Serialize)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+enum Transport {
+    Smtp(AsyncSmtpTransport<Tokio1Executor>),
+    File(PathBuf),
+    Log,
+}
+
+struct Inner {
+    transport: Transport,
+    from: Mailbox,
+    public_url: String,
+    templates: Templates,
+    digest_interval: Option<Duration>,
+}
+
+/// Cheaply cloneable handle to the configured transport and templates.
+#[derive(Clone)]
+pub struct Mailer {
+    inner: Arc<Inner>,
+}
+
+impl Mailer {
+    pub fn new(config: &MailConfig) -> Result<Self, String> {
+        let from: Mailbox = config
+            .from
+            .parse()
+            .map_err(|e| format!("MAIL_FROM is not a valid address: {e}"))?;
+        let templates = Templates::load(config.template_dir.as_deref())?;
+
+        let transport = match &config.transport {
+            MailTransport::Smtp(smtp) => {
+                let builder = match smtp.tls {
+                    SmtpTls::StartTls => {
+                        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp.host)
+                            .map_err(|e| format!("Invalid SMTP_HOST: {e}"))?
+                    }
+                    SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&smtp.host)
+                        .map_err(|e| format!("Invalid SMTP_HOST: {e}"))?,
+                    SmtpTls::None => {
+                        AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp.host)
+                    }
+                };
+                let mut builder = builder.port(smtp.port);
+                if let (Some(username), Some(password)) = (&smtp.username, &smtp.password) {
+                    builder =
+                        builder.credentials(Credentials::new(username.clone(), password.clone()));
+                }
+                Transport::Smtp(builder.build())
+            }
+            MailTransport::File(dir) => {
+                std::fs::create_dir_all(dir)
+                    .map_err(|e| format!("Failed to create MAIL_DIR {}: {e}", dir.display()))?;
+                Transport::File(dir.clone())
+            }
+            MailTransport::Log => Transport::Log,
+        };
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                transport,
+                from,
+                public_url: config.public_url.trim_end_matches('/').to_string(),
+                templates,
+                digest_interval: config
+                    .digest_interval_hours
+                    .map(|h| Duration::from_secs(u64::from(h) * 3600)),
+            }),
+        })
+    }
+
+    /// Absolute URL for a path in the web client, e.g. `/invite/abc`.
+    pub fn link(&self, path: &str) -> String {
+        format!("{}{path}", self.inner.public_url)
+    }
+
+    /// How often the mention digest runs, or `None` when it is turned off.
+    pub fn digest_interval(&self) -> Option<Duration> {
+        self.inner.digest_interval
+    }
+
+    pub fn compose(&self, to: &str, template: Template, vars: &[(&str, &str)]) -> Email {
+        let rendered = self.inner.templates.render(template, vars);
+        Email {
+            to: to.to_string(),
+            subject: rendered.subject,
+            text: rendered.text,
+            html: rendered.html,
+        }
+    }
+
+    pub async fn send(&self, email: &Email) -> Result<(), String> {
+        match &self.inner.transport {
+            Transport::Smtp(smtp) => {
+                let to: Mailbox = email
+                    .to
+                    .parse()
+                    .map_err(|e| format!("Invalid recipient: {e}"))?;
+                let message = Message::builder()
+                    .from(self.inner.from.clone())
+                    .to(to)
+                    .subject(&email.subject)
+                    .multipart(MultiPart::alternative_plain_html(
+                        email.text.clone(),
+                        email.html.clone(),
+                    ))
+                    .map_err(|e| format!("Failed to build message: {e}"))?;
+                smtp.send(message)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| format!("SMTP delivery failed: {e}"))
+            }
+            Transport::File(dir) => {
+                let path = dir.join(format!(
+                    "{}-{}.json",
+                    chrono::Utc::now().format("%Y%m%dT%H%M%S%.6f"),
+                    uuid::Uuid::new_v4()
+                ));
+                let json = serde_json::to_vec_pretty(&serde_json::json!({
+                    "from": self.inner.from.to_string(),
+                    "to": email.to,
+                    "subject": email.subject,
+                    "text": email.text,
+                    "html": email.html,
+                }))
+                .map_err(|e| e.to_string())?;
+                tokio::fs::write(&path, json)
+                    .await
+                    .map_err(|e| format!("Failed to write {}: {e}", path.display()))
+            }
+            Transport::Log => {
+                tracing::info!(
+                    to = %email.to,
+                    subject = %email.subject,
+                    "Email (log transport):\n{}",
+                    email.text
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Send `email` in the background. A no-op when no mailer is configured.
+pub fn send(state: &AppState, email: Email) {
+    let Some(mailer) = state.mailer.clone() else {
+        return;
+    };
+    tokio::spawn(async move {
+        if let Err(e) = mailer.send(&email).await {
+            tracing::warn!(subject = %email.subject, error = %e, "Failed to send email");
+        }
+    });
+}
@@ -0,0 +1,219 @@
+//! Email templates.
+//!
+//! Each template is a pair of files: `<name>.txt` starts with a
+//! `Subject: ...` line and a blank line, followed by the plain-text body;
+//! `<name>.html` is the HTML body. Built-in copies are compiled in, and a
+//! directory set by `MAIL_TEMPLATE_DIR` can replace any of them.
+//!
+//! Placeholders are `{{name}}`. In the HTML body the value is escaped;
+//! `{{{name}}}` inserts it as-is, for fragments the caller already built
+//! from escaped parts.
+
+use std::path::Path;
+
+/// The emails the server sends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Template {
+    PasswordReset,
+    VerifyEmail,
+    Invite,
+    MentionDigest,
+}
+
+impl Template {
+    const ALL: [Template; 4] = [
+        Template::PasswordReset,
+        Template::VerifyEmail,
+        Template::Invite,
+        Template::MentionDigest,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PasswordReset => "password_reset",
+            Self::VerifyEmail => "verify_email",
+            Self::Invite => "invite",
+            Self::MentionDigest => "mention_digest",
+        }
+    }
+
+    fn builtin(self) -> (&'static str, &'static str) {
+        match self {
+            Self::PasswordReset => (
+                include_str!("templates/password_reset.txt"),
+                include_str!("templates/password_reset.html"),
+            ),
+            Self::VerifyEmail => (
+                include_str!("templates/verify_email.txt"),
+                include_str!("templates/verify_email.html"),
+            ),
+            Self::Invite => (
+                include_str!("templates/invite.txt"),
+                include_str!("templates/invite.html"),
+            ),
+            Self::MentionDigest => (
+                include_str!("templates/mention_digest.txt"),
+                include_str!("templates/mention_digest.html"),
+            ),
+        }
+    }
+}
+
+struct Parsed {
+    subject: String,
+    text: String,
+    html: String,
+}
+
+/// Rendered subject and bodies.
+pub struct Rendered {
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+pub struct Templates {
+    parsed: Vec<Parsed>,
+}
+
+impl Templates {
+    /// Load every template, preferring files in `dir` over the built-in copy.
+    pub fn load(dir: Option<&Path>) -> Result<Self, String> {
+        let mut parsed = Vec::with_capacity(Template::ALL.len());
+        for template in Template::ALL {
+            let (builtin_text, builtin_html) = template.builtin();
+            let text = read_override(dir, template, "txt")?;
+            let html = read_override(dir, template, "html")?;
+            let text = text.as_deref().unwrap_or(builtin_text);
+            let (subject, body) = split_subject(text).ok_or_else(|| {
+                format!(
+                    "{}.txt must start with a \"Subject:\" line",
+                    template.name()
+                )
+            })?;
+            parsed.push(Parsed {
+                subject: subject.to_string(),
+                text: body.to_string(),
+                html: html.unwrap_or_else(|| builtin_html.to_string()),
+            });
+        }
+        Ok(Self { parsed })
+    }
+
+    pub fn render(&self, template: Template, vars: &[(&str, &str)]) -> Rendered {
+        let index = Template::ALL
+            .iter()
+            .position(|t| *t == template)
+            .expect("every template is loaded");
+        let parsed = &self.parsed[index];
+        Rendered {
+            subject: substitute(&parsed.subject, vars, false),
+            text: substitute(&parsed.text, vars, false),
+            html: substitute(&parsed.html, vars, true),
+        }
+    }
+}
+
+fn read_override(
+    dir: Option<&Path>,
+    template: Template,
+    ext: &str,
+) -> Result<Option<String>, String> {
+    let Some(dir) = dir else {
+        return Ok(None);
+    };
+    let path = dir.join(format!("{}.{ext}", template.name()));
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read {}: {e}", path.display())),
+    }
+}
+
+fn split_subject(text: &str) -> Option<(&str, &str)> {
+    let (first, rest) = text.split_once('\n')?;
+    let subject = first.strip_prefix("Subject:")?.trim();
+    Some((subject, rest.trim_start_matches(['\r', '\n'])))
+}
+
+/// Replace `{{name}}` and `{{{name}}}` placeholders. Unknown names are left
+/// in place so a typo in a custom template is visible in the sent email.
+fn substitute(template: &str, vars: &[(&str, &str)], escape: bool) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start..];
+        let (raw, open, close) = if after.starts_with("{{{") {
+            (true, 3, "}}}")
+        } else {
+            (false, 2, "}}")
+        };
+        let Some(end) = after[open..].find(close) else {
+            out.push_str(after);
+            return out;
+        };
+        let name = after[open..open + end].trim();
+        let token_len = open + end + close.len();
+        match vars.iter().find(|(key, _)| *key == name) {
+            Some((_, value)) if escape && !raw => out.push_str(&escape_html(value)),
+            Some((_, value)) => out.push_str(value),
+            None => out.push_str(&after[..token_len]),
+        }
+        rest = &after[token_len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+pub fn escape_html(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_templates_parse() {
+        let templates = Templates::load(None).unwrap();
+        let rendered = templates.render(
+            Template::Invite,
+            &[
+                ("inviter", "alice"),
+                ("server_name", "Rust <3"),
+                ("link", "https://chat.example.com/invite/abc"),
+                ("code", "abc"),
+            ],
+        );
+        assert_eq!(rendered.subject, "alice invited you to Rust <3 on Together");
+        assert!(rendered.text.starts_with("alice invited you"));
+        assert!(rendered.html.contains("Rust &lt;3"));
+    }
+
+    #[test]
+    fn raw_placeholders_skip_escaping() {
+        let vars = [("a", "<b>x</b>")];
+        assert_eq!(
+            substitute("{{a}}|{{{a}}}", &vars, true),
+            "&lt;b&gt;x&lt;/b&gt;|<b>x</b>"
+        );
+        assert_eq!(substitute("{{a}}", &vars, false), "<b>x</b>");
+    }
+
+    #[test]
+    fn unknown_placeholders_are_kept() {
+        assert_eq!(substitute("hi {{ who }} {{", &[], true), "hi {{ who }} {{");
+    }
+}
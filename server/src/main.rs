@@ -3,7 +3,7 @@ use axum::{
     http::{header, HeaderValue, Method, Request, StatusCode},
     middleware::{self, Next},
     response::{IntoResponse, Response},
-    routing::{delete, get, patch, post},
+    routing::{delete, get, head, patch, post},
     Router,
 };
 use axum_prometheus::PrometheusMetricLayer;
@@ -22,11 +22,21 @@ use tokio::sync::RwLock;
 
 use tower_governor::{governor::GovernorConfigBuilder, GovernorLayer};
 
-use together_server::config::Config;
+use together_server::attachment_gc;
+use together_server::auth::oidc::OidcClient;
+use together_server::automod_engine::AutomodCache;
+use together_server::config::{Config, EventBusKind};
+use together_server::event_bus::{EventBus, LocalEventBus, LocalState, PgEventBus};
+use together_server::mail;
 use together_server::openapi::ApiDoc;
+use together_server::push;
+use together_server::scheduled_messages;
+use together_server::sfu;
 use together_server::state::AppState;
+use together_server::storage::{self, LocalStorage, S3Storage, Storage};
+use together_server::uploads;
 use together_server::webhook_delivery;
-use together_server::websocket::ConnectionManager;
+use together_server::websocket::{channel_viewers::ChannelViewerCache, ConnectionManager};
 use together_server::{db, handlers, websocket};
 
 /// Middleware that restricts access to the metrics endpoint to loopback connections only.
@@ -83,6 +93,36 @@ async fn main() {
     let config = Config::from_env().expect("Failed to load configuration");
     info!("📝 Configuration loaded");
 
+    // --migrate-storage: copy every file under UPLOAD_DIR into the configured
+    // S3 bucket, then exit. Safe to rerun; files already copied are skipped.
+    if args.get(1).map(String::as_str) == Some("--migrate-storage") {
+        let Some(s3) = config.s3.clone() else {
+            eprintln!("--migrate-storage needs STORAGE_BACKEND=s3 and the S3_* settings");
+            std::process::exit(1);
+        };
+        let target = S3Storage::new(s3).expect("Invalid S3 configuration");
+        info!(
+            "📦 Copying {} to bucket {}",
+            config.upload_dir.display(),
+            config.s3.as_ref().map_or("", |s3| s3.bucket.as_str())
+        );
+        match storage::migrate_local(&config.upload_dir, &target).await {
+            Ok(report) => {
+                info!(
+                    copied = report.copied,
+                    skipped = report.skipped,
+                    bytes = report.bytes,
+                    "📦 Storage migration complete"
+                );
+                return;
+            }
+            Err(e) => {
+                eprintln!("Storage migration failed: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+
     // Create database connection pool
     let pool = db::create_pool(&config.database_url)
         .await
@@ -133,22 +173,32 @@ async fn main() {
 
     let addr = config.server_addr();
 
-    // Create upload directory if it doesn't exist yet.
-    tokio::fs::create_dir_all(&config.upload_dir)
-        .await
-        .expect("Failed to create upload directory");
-    info!("📂 Upload directory: {}", config.upload_dir.display());
-
-    // On Unix, set upload directory permissions to prevent execution.
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        if let Err(e) =
-            tokio::fs::set_permissions(&config.upload_dir, PermissionsExt::from_mode(0o755)).await
-        {
-            tracing::warn!(error = ?e, "Failed to set upload directory permissions");
+    let storage: Arc<dyn Storage> = match &config.s3 {
+        Some(s3) => {
+            info!("📂 Upload storage: bucket {} at {}", s3.bucket, s3.endpoint);
+            Arc::new(S3Storage::new(s3.clone()).expect("Invalid S3 configuration"))
         }
-    }
+        None => {
+            // Create upload directory if it doesn't exist yet.
+            tokio::fs::create_dir_all(&config.upload_dir)
+                .await
+                .expect("Failed to create upload directory");
+            info!("📂 Upload directory: {}", config.upload_dir.display());
+
+            // On Unix, set upload directory permissions to prevent execution.
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                if let Err(e) =
+                    tokio::fs::set_permissions(&config.upload_dir, PermissionsExt::from_mode(0o755))
+                        .await
+                {
+                    tracing::warn!(error = ?e, "Failed to set upload directory permissions");
+                }
+            }
+            Arc::new(LocalStorage::new(config.upload_dir.clone()))
+        }
+    };
 
     let http_client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(10))
@@ -163,20 +213,119 @@ async fn main() {
     let webhook_queue = webhook_delivery::start_worker(pool.clone(), http_client.clone());
     info!("📬 Webhook delivery worker started");
 
+    // Start the cross-node event bus. Every node delivers dispatches to its
+    // own connected users; the Postgres backend relays them between replicas.
+    let local_state = LocalState {
+        connections: ConnectionManager::new(),
+        channel_viewers: ChannelViewerCache::new(),
+        automod: AutomodCache::new(),
+    };
+    local_state.connections.start_pruning();
+    let events: Arc<dyn EventBus> = match config.event_bus {
+        EventBusKind::Local => Arc::new(LocalEventBus::new(local_state.clone())),
+        EventBusKind::Postgres => PgEventBus::start(pool.clone(), local_state.clone())
+            .await
+            .expect("Failed to start Postgres event bus"),
+    };
+    info!("📡 Event bus: {:?}", config.event_bus);
+
+    let sfu = config.sfu.as_ref().map(|sfu_config| {
+        sfu::Sfu::new(sfu_config, events.clone()).expect("Failed to start voice SFU")
+    });
+    info!(
+        "🎙️ Voice: {}",
+        if sfu.is_some() {
+            "embedded SFU"
+        } else {
+            "mesh"
+        }
+    );
+
+    let (push, push_jobs) = match &config.web_push {
+        Some(web_push) => {
+            let (push, jobs) =
+                push::PushDispatcher::new(web_push).expect("Invalid Web Push configuration");
+            (Some(push), Some(jobs))
+        }
+        None => (None, None),
+    };
+
+    let mailer = config
+        .mail
+        .as_ref()
+        .map(|mail| mail::Mailer::new(mail).expect("Invalid email configuration"));
+
+    let oidc = config
+        .oidc
+        .clone()
+        .map(|oidc| Arc::new(OidcClient::new(oidc, http_client.clone())));
+
     let app_state = AppState {
         pool,
         jwt_secret: config.jwt_secret.clone(),
-        connections: ConnectionManager::new(),
-        upload_dir: config.upload_dir.clone(),
+        connections: local_state.connections,
+        storage,
         link_preview_cache: Arc::new(RwLock::new(HashMap::new())),
         http_client,
         giphy_api_key,
         config: Arc::new(config.clone()),
-        bot_rate_limiter: AppState::new_bot_rate_limiter(),
-        go_live_sessions: Arc::new(RwLock::new(HashMap::new())),
+        bot_rate_limiter: AppState::new_bot_rate_limiter(config.replica_count),
+        channel_viewers: local_state.channel_viewers,
+        automod: local_state.automod,
         webhook_queue,
+        events,
+        sfu,
+        push,
+        mailer,
+        oidc,
     };
 
+    // Start the scheduled message worker. It posts through the normal message
+    // path, so it needs the full AppState rather than just the pool.
+    scheduled_messages::start_worker(app_state.clone());
+    info!("⏰ Scheduled message worker started");
+
+    // Uploads are staged on local disk while they arrive, whatever the
+    // storage backend.
+    tokio::fs::create_dir_all(&config.upload_staging_dir)
+        .await
+        .expect("Failed to create upload staging directory");
+    uploads::start_sweeper(app_state.pool.clone(), config.upload_staging_dir.clone());
+    info!(
+        "📥 Upload staging directory: {}",
+        config.upload_staging_dir.display()
+    );
+
+    // Files of deleted messages, channels and servers are removed from
+    // storage once the grace period has passed.
+    attachment_gc::start_collector(
+        app_state.pool.clone(),
+        app_state.storage.clone(),
+        config.attachment_gc_grace,
+    );
+    info!(
+        "🧹 Attachment collector started (grace period {}h)",
+        config.attachment_gc_grace.as_secs() / 3600
+    );
+
+    if let Some(jobs) = push_jobs {
+        push::start_worker(app_state.clone(), jobs);
+        info!("🔔 Web Push dispatcher started");
+    }
+
+    if let Some(interval) = app_state.mailer.as_ref().and_then(|m| m.digest_interval()) {
+        mail::digest::start_worker(app_state.clone(), interval);
+        info!("📧 Mention digest worker started");
+    }
+
+    if let Some(webauthn) = &config.webauthn {
+        info!("🔑 Passkeys enabled for {}", webauthn.rp_id);
+    }
+
+    if let Some(oidc) = &config.oidc {
+        info!("🪪 Single sign-on through {}", oidc.issuer);
+    }
+
     // Prometheus metrics layer
     let (prometheus_layer, metric_handle) = PrometheusMetricLayer::pair();
 
@@ -230,6 +379,37 @@ async fn main() {
             post(handlers::auth::forgot_password),
         )
         .route("/auth/reset-password", post(handlers::auth::reset_password))
+        .route("/auth/verify-email", post(handlers::email::verify_email))
+        .route(
+            "/auth/two-factor/verify",
+            post(handlers::two_factor::verify_two_factor),
+        )
+        .route(
+            "/auth/two-factor/setup",
+            post(handlers::two_factor::setup_two_factor_at_login),
+        )
+        .route(
+            "/auth/two-factor/enroll",
+            post(handlers::two_factor::enroll_two_factor),
+        )
+        .route(
+            "/auth/two-factor/passkey/options",
+            post(handlers::passkeys::passkey_second_factor_options),
+        )
+        .route(
+            "/auth/two-factor/passkey",
+            post(handlers::passkeys::verify_two_factor_passkey),
+        )
+        .route(
+            "/auth/passkey/options",
+            post(handlers::passkeys::passkey_login_options),
+        )
+        .route(
+            "/auth/passkey/login",
+            post(handlers::passkeys::passkey_login),
+        )
+        .route("/auth/oidc/authorize", post(handlers::oidc::oidc_authorize))
+        .route("/auth/oidc/callback", post(handlers::oidc::oidc_callback))
         .route_layer(GovernorLayer {
             config: auth_governor_conf,
         });
@@ -272,6 +452,10 @@ async fn main() {
             "/admin/users/:user_id",
             patch(handlers::admin::update_user).delete(handlers::admin::delete_user),
         )
+        .route(
+            "/admin/users/:user_id/two-factor",
+            delete(handlers::admin::reset_two_factor),
+        )
         .route("/admin/servers", get(handlers::admin::list_servers))
         .route(
             "/admin/servers/:server_id",
@@ -314,6 +498,15 @@ async fn main() {
             "/servers/:id/members/:user_id/timeout",
             post(handlers::moderation::timeout_member).delete(handlers::moderation::remove_timeout),
         )
+        .route(
+            "/servers/:id/members/:user_id/voice",
+            patch(handlers::voice::moderate_voice_state)
+                .delete(handlers::voice::disconnect_voice_member),
+        )
+        .route(
+            "/servers/:id/members/:user_id/voice/move",
+            post(handlers::voice::move_voice_member),
+        )
         // Role management routes (permission-gated)
         .route(
             "/servers/:id/roles",
@@ -348,6 +541,15 @@ async fn main() {
             "/servers/:id/automod/words/:word",
             delete(handlers::automod::remove_word_filter),
         )
+        .route(
+            "/servers/:id/automod/rules",
+            get(handlers::automod::list_automod_rules).post(handlers::automod::create_automod_rule),
+        )
+        .route(
+            "/servers/:id/automod/rules/:rule_id",
+            patch(handlers::automod::update_automod_rule)
+                .delete(handlers::automod::delete_automod_rule),
+        )
         .route(
             "/servers/:id/automod/logs",
             get(handlers::automod::list_automod_logs),
@@ -402,6 +604,19 @@ async fn main() {
             "/channels/:channel_id/messages",
             get(handlers::messages::list_messages),
         )
+        .route(
+            "/channels/:channel_id/scheduled-messages",
+            post(handlers::scheduled_messages::create_scheduled_message),
+        )
+        .route(
+            "/users/@me/scheduled-messages",
+            get(handlers::scheduled_messages::list_scheduled_messages),
+        )
+        .route(
+            "/users/@me/scheduled-messages/:id",
+            patch(handlers::scheduled_messages::update_scheduled_message)
+                .delete(handlers::scheduled_messages::delete_scheduled_message),
+        )
         .route(
             "/messages/:message_id",
             patch(handlers::messages::update_message),
@@ -424,6 +639,23 @@ async fn main() {
             "/channels/:channel_id/messages/:message_id/thread",
             post(handlers::messages::create_thread_reply),
         )
+        // Forum routes (protected)
+        .route(
+            "/channels/:channel_id/forum/tags",
+            get(handlers::forum::list_forum_tags).post(handlers::forum::create_forum_tag),
+        )
+        .route(
+            "/channels/:channel_id/forum/tags/:tag_id",
+            patch(handlers::forum::update_forum_tag).delete(handlers::forum::delete_forum_tag),
+        )
+        .route(
+            "/channels/:channel_id/posts",
+            get(handlers::forum::list_forum_posts),
+        )
+        .route(
+            "/channels/:channel_id/posts/:post_id",
+            patch(handlers::forum::update_forum_post),
+        )
         // Reaction routes (protected, nested under channel message)
         .route(
             "/channels/:channel_id/messages/:message_id/reactions",
@@ -455,6 +687,91 @@ async fn main() {
             "/channels/:channel_id/ack",
             post(handlers::read_states::ack_channel),
         )
+        .route("/servers/:id/ack", post(handlers::read_states::ack_server))
+        // Notification settings routes
+        .route(
+            "/users/@me/notification-settings",
+            get(handlers::notification_settings::list_notification_settings),
+        )
+        .route(
+            "/servers/:id/notification-settings",
+            axum::routing::put(handlers::notification_settings::put_server_notification_settings)
+                .delete(handlers::notification_settings::delete_server_notification_settings),
+        )
+        .route(
+            "/servers/:id/categories/:category/notification-settings",
+            axum::routing::put(handlers::notification_settings::put_category_notification_settings)
+                .delete(handlers::notification_settings::delete_category_notification_settings),
+        )
+        .route(
+            "/channels/:channel_id/notification-settings",
+            axum::routing::put(handlers::notification_settings::put_channel_notification_settings)
+                .delete(handlers::notification_settings::delete_channel_notification_settings),
+        )
+        .route(
+            "/dm-channels/:id/notification-settings",
+            axum::routing::put(handlers::notification_settings::put_dm_notification_settings)
+                .delete(handlers::notification_settings::delete_dm_notification_settings),
+        )
+        // Web Push routes
+        .route(
+            "/push/vapid-public-key",
+            get(handlers::push::get_vapid_public_key),
+        )
+        .route(
+            "/users/@me/push-subscriptions",
+            get(handlers::push::list_push_subscriptions)
+                .post(handlers::push::create_push_subscription),
+        )
+        .route(
+            "/users/@me/push-subscriptions/:id",
+            delete(handlers::push::delete_push_subscription),
+        )
+        .route(
+            "/users/@me/push-preferences",
+            get(handlers::push::get_push_preferences).put(handlers::push::update_push_preferences),
+        )
+        .route(
+            "/users/@me/email/verification",
+            post(handlers::email::resend_verification_email),
+        )
+        .route(
+            "/users/@me/email-preferences",
+            get(handlers::email::get_email_preferences)
+                .put(handlers::email::update_email_preferences),
+        )
+        .route(
+            "/users/@me/two-factor",
+            get(handlers::two_factor::get_two_factor),
+        )
+        .route(
+            "/users/@me/two-factor/setup",
+            post(handlers::two_factor::setup_two_factor),
+        )
+        .route(
+            "/users/@me/two-factor/confirm",
+            post(handlers::two_factor::confirm_two_factor),
+        )
+        .route(
+            "/users/@me/two-factor/disable",
+            post(handlers::two_factor::disable_two_factor),
+        )
+        .route(
+            "/users/@me/two-factor/recovery-codes",
+            post(handlers::two_factor::regenerate_recovery_codes),
+        )
+        .route(
+            "/users/@me/passkeys",
+            get(handlers::passkeys::list_passkeys).post(handlers::passkeys::create_passkey),
+        )
+        .route(
+            "/users/@me/passkeys/options",
+            post(handlers::passkeys::passkey_creation_options),
+        )
+        .route(
+            "/users/@me/passkeys/:id",
+            patch(handlers::passkeys::update_passkey).delete(handlers::passkeys::delete_passkey),
+        )
         // Bot management routes (user-scoped, protected)
         .route("/bots", post(handlers::bots::create_bot))
         .route("/bots", get(handlers::bots::list_bots))
@@ -472,6 +789,16 @@ async fn main() {
         // DM routes (protected, user-scoped)
         .route("/dm-channels", post(handlers::dm::open_dm_channel))
         .route("/dm-channels", get(handlers::dm::list_dm_channels))
+        .route("/dm-channels/groups", post(handlers::dm::create_group_dm))
+        .route(
+            "/dm-channels/:id",
+            patch(handlers::dm::update_group_dm).delete(handlers::dm::leave_dm_channel),
+        )
+        .route(
+            "/dm-channels/:id/recipients/:user_id",
+            axum::routing::put(handlers::dm::add_group_dm_recipient)
+                .delete(handlers::dm::remove_group_dm_recipient),
+        )
         .route(
             "/dm-channels/:id/messages",
             post(handlers::dm::send_dm_message),
@@ -494,6 +821,19 @@ async fn main() {
             "/messages/:message_id/attachments",
             get(handlers::attachments::list_attachments),
         )
+        .route(
+            "/messages/:message_id/attachments/uploads",
+            post(handlers::attachments::attach_uploads),
+        )
+        // Resumable upload routes (protected, owner-scoped). PATCH bodies are
+        // streamed and bounded by Upload-Length, not by a body limit layer.
+        .route("/uploads", post(handlers::uploads::create_upload))
+        .route(
+            "/uploads/:id",
+            head(handlers::uploads::head_upload)
+                .patch(handlers::uploads::patch_upload)
+                .delete(handlers::uploads::delete_upload),
+        )
         // Authenticated file serving (auth + membership checked before serving)
         .route(
             "/files/:message_id/*filepath",
@@ -555,6 +895,34 @@ async fn main() {
             "/servers/:id/webhooks/:webhook_id/test",
             post(handlers::webhooks::test_webhook),
         )
+        .route(
+            "/servers/:id/webhooks/:webhook_id/deliveries",
+            get(handlers::webhooks::list_deliveries),
+        )
+        .route(
+            "/servers/:id/webhooks/:webhook_id/deliveries/:delivery_id/redeliver",
+            post(handlers::webhooks::redeliver_delivery),
+        )
+        // Incoming webhook management (protected, admin/owner only)
+        .route(
+            "/servers/:id/incoming-webhooks",
+            get(handlers::incoming_webhooks::list_incoming_webhooks)
+                .post(handlers::incoming_webhooks::create_incoming_webhook),
+        )
+        .route(
+            "/servers/:id/incoming-webhooks/:webhook_id",
+            patch(handlers::incoming_webhooks::update_incoming_webhook)
+                .delete(handlers::incoming_webhooks::delete_incoming_webhook),
+        )
+        .route(
+            "/servers/:id/incoming-webhooks/:webhook_id/token",
+            post(handlers::incoming_webhooks::regenerate_incoming_webhook_token),
+        )
+        // Incoming webhook execution (public — the token in the URL is the credential)
+        .route(
+            "/webhooks/:webhook_id/:token",
+            post(handlers::incoming_webhooks::execute_incoming_webhook),
+        )
         // Voice routes (protected, nested under channel)
         .route(
             "/channels/:channel_id/voice",
@@ -585,6 +953,15 @@ async fn main() {
             "/channels/:channel_id/go-live",
             get(handlers::go_live::get_go_live),
         )
+        .route(
+            "/channels/:channel_id/go-live/viewers",
+            post(handlers::go_live::join_go_live).delete(handlers::go_live::leave_go_live),
+        )
+        .route(
+            "/servers/:id/go-live/settings",
+            get(handlers::go_live::get_go_live_settings)
+                .patch(handlers::go_live::update_go_live_settings),
+        )
         // ICE servers for WebRTC (protected, returns TURN credentials)
         .route("/ice-servers", get(handlers::ice::get_ice_servers))
         // WebSocket gateway
@@ -28,6 +28,7 @@ pub struct User {
     pub is_admin: bool,
     pub disabled: bool,
     pub disabled_at: Option<DateTime<Utc>>,
+    pub email_verified_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Deserialize, ToSchema)]
@@ -38,11 +39,13 @@ pub struct CreateUserDto {
 }
 
 /// Public user shape returned by all API responses.
-#[derive(Debug, Serialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct UserDto {
     pub id: Uuid,
     pub username: String,
     pub email: Option<String>,
+    /// True once the user confirmed `email` through a verification link.
+    pub email_verified: bool,
     pub avatar_url: Option<String>,
     pub bio: Option<String>,
     pub pronouns: Option<String>,
@@ -59,6 +62,7 @@ impl From<User> for UserDto {
             id: user.id,
             username: user.username,
             email: user.email,
+            email_verified: user.email_verified_at.is_some(),
             avatar_url: user.avatar_url,
             bio: user.bio,
             pronouns: user.pronouns,
@@ -202,12 +206,15 @@ pub struct InstanceSettings {
     pub registration_mode: String,
     pub updated_at: DateTime<Utc>,
     pub updated_by: Option<Uuid>,
+    /// Who must use two-factor authentication: "off", "admins" or "everyone".
+    pub require_two_factor: String,
 }
 
 #[derive(Debug, Deserialize, ToSchema)]
 #[serde(deny_unknown_fields)]
 pub struct UpdateSettingsRequest {
     pub registration_mode: Option<String>,
+    pub require_two_factor: Option<String>,
 }
 
 // ============================================================================
@@ -286,6 +293,8 @@ pub struct MemberDto {
 pub enum ChannelType {
     Text,
     Voice,
+    /// Top-level messages are titled, tagged posts; replies are threads.
+    Forum,
 }
 
 #[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
@@ -316,6 +325,53 @@ pub struct UpdateChannelDto {
     pub position: Option<i32>,
 }
 
+// ============================================================================
+// Forum Models
+// ============================================================================
+
+/// A tag from a forum channel's tag set.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct ForumTag {
+    pub id: Uuid,
+    pub channel_id: Uuid,
+    pub name: String,
+    pub emoji: Option<String>,
+    pub position: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Title and tags of a forum post, attached to its root message.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ForumPostInfo {
+    pub title: String,
+    pub tag_ids: Vec<Uuid>,
+    /// Time of the newest reply, or the post itself if it has none.
+    pub last_activity_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateForumTagDto {
+    pub name: String,
+    pub emoji: Option<String>,
+    pub position: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateForumTagDto {
+    pub name: Option<String>,
+    pub emoji: Option<String>,
+    pub position: Option<i32>,
+}
+
+/// Edit a forum post's title or tags. Content is edited through
+/// `PATCH /channels/:channel_id/messages/:message_id` as usual.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateForumPostDto {
+    pub title: Option<String>,
+    /// Replaces the post's tags.
+    pub tag_ids: Option<Vec<Uuid>>,
+}
+
 // ============================================================================
 // Message Models
 // ============================================================================
@@ -447,6 +503,25 @@ impl VoiceStateDto {
     }
 }
 
+/// How media flows in a voice channel on this instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VoiceMode {
+    /// Peers connect to each other, signaling through the `VOICE_SIGNAL` relay.
+    Mesh,
+    /// Every participant connects to the server's embedded SFU.
+    Sfu,
+}
+
+/// Response to POST /channels/:id/voice: the new voice state plus the mode
+/// the client must use to connect its media.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VoiceJoinResponse {
+    #[serde(flatten)]
+    pub voice_state: VoiceStateDto,
+    pub mode: VoiceMode,
+}
+
 /// Request body for PATCH /channels/:id/voice.
 ///
 /// Only user-controlled flags are accepted; `server_mute`/`server_deaf` are
@@ -461,6 +536,68 @@ pub struct UpdateVoiceStateRequest {
     pub self_screen: Option<bool>,
 }
 
+/// Request body for PATCH /servers/:id/members/:user_id/voice.
+///
+/// Moderator-applied flags; the target cannot clear them through
+/// `UpdateVoiceStateRequest`.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ModerateVoiceStateRequest {
+    pub server_mute: Option<bool>,
+    pub server_deaf: Option<bool>,
+}
+
+/// Request body for POST /servers/:id/members/:user_id/voice/move.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MoveVoiceMemberRequest {
+    /// Voice channel in the same server to move the member into.
+    pub channel_id: Uuid,
+}
+
+// ── Go Live ─────────────────────────────────────────────────────────────────
+
+/// An active Go Live broadcast in a voice channel.
+///
+/// At most one session exists per channel (`go_live_sessions` is keyed by
+/// channel). The row is removed when the broadcaster stops, leaves or moves
+/// out of the channel.
+#[derive(Debug, Clone, sqlx::FromRow, Serialize, ToSchema)]
+pub struct GoLiveSession {
+    pub channel_id: Uuid,
+    /// The user currently broadcasting.
+    pub broadcaster_id: Uuid,
+    /// Requested quality tier: "480p", "720p", or "1080p".
+    pub quality: String,
+    pub started_at: DateTime<Utc>,
+    /// Number of channel participants currently watching the broadcast.
+    pub viewer_count: i64,
+}
+
+/// Per-server Go Live limits. Servers without a row use the defaults
+/// (unlimited streams, 1080p for everyone).
+#[derive(Debug, Clone, sqlx::FromRow, Serialize, ToSchema)]
+pub struct GoLiveSettings {
+    pub server_id: Uuid,
+    /// Maximum simultaneous broadcasts across the server's voice channels;
+    /// 0 means unlimited.
+    pub max_concurrent_streams: i32,
+    /// Highest quality tier members may stream at.
+    pub max_quality: String,
+    /// Roles whose members (along with the owner) may exceed `max_quality`.
+    pub high_quality_role_ids: Vec<Uuid>,
+}
+
+/// Request body for PATCH /servers/:id/go-live/settings. Omitted fields keep
+/// their current value.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateGoLiveSettingsRequest {
+    pub max_concurrent_streams: Option<i32>,
+    pub max_quality: Option<String>,
+    pub high_quality_role_ids: Option<Vec<Uuid>>,
+}
+
 // ============================================================================
 // Attachment Models
 // ============================================================================
@@ -479,6 +616,26 @@ pub struct Attachment {
     pub created_at: DateTime<Utc>,
 }
 
+/// A resumable upload, visible only to the user who created it. Once
+/// `upload_offset` reaches `upload_length` it can be attached to a message.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct Upload {
+    pub id: Uuid,
+    pub filename: String,
+    pub upload_length: i64,
+    pub upload_offset: i64,
+    /// Sniffed from the first bytes; `null` until enough have arrived.
+    pub mime_type: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Request body for attaching finished resumable uploads to a message.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AttachUploadsRequest {
+    pub upload_ids: Vec<Uuid>,
+}
+
 // ── Custom Emojis ────────────────────────────────────────────────────────────
 
 /// A custom emoji uploaded to a server.
@@ -491,6 +648,8 @@ pub struct CustomEmoji {
     pub filename: String,
     pub content_type: String,
     pub file_size: i64,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -505,6 +664,8 @@ pub struct CustomEmojiDto {
     pub url: String,
     pub content_type: String,
     pub file_size: i64,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -519,6 +680,8 @@ impl CustomEmojiDto {
             name: row.name,
             content_type: row.content_type,
             file_size: row.file_size,
+            width: row.width,
+            height: row.height,
             created_at: row.created_at,
         }
     }
@@ -539,8 +702,16 @@ pub struct DirectMessageChannel {
 #[derive(Debug, Serialize, ToSchema)]
 pub struct DirectMessageChannelDto {
     pub id: Uuid,
-    /// The other participant (not the requesting user).
-    pub recipient: UserDto,
+    /// The other participant of a 1:1 DM (not the requesting user).
+    /// `None` for group DMs; use `recipients`.
+    pub recipient: Option<UserDto>,
+    /// Every participant except the requesting user, oldest member first.
+    pub recipients: Vec<UserDto>,
+    pub is_group: bool,
+    /// Group DMs only; `None` shows the participants' names instead.
+    pub name: Option<String>,
+    /// Group DMs only. Only the owner can remove other participants.
+    pub owner_id: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     /// Timestamp of the most recent non-deleted message, used for list
     /// ordering and last-active display. `None` when no messages exist yet.
@@ -619,20 +790,164 @@ pub struct ReadState {
     pub user_id: Uuid,
     pub channel_id: Uuid,
     pub last_read_at: DateTime<Utc>,
+    /// Message the user acked up to, if the channel had any messages.
+    pub last_message_id: Option<Uuid>,
+}
+
+/// Request body for POST /channels/:channel_id/ack and
+/// POST /dm-channels/:id/ack. Without `message_id` the whole channel is
+/// marked read; with it, everything after that message becomes unread again.
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AckChannelRequest {
+    pub message_id: Option<Uuid>,
+}
+
+/// A user's read position and badge counts for one channel, sent in the
+/// READY `read_states` array and in `READ_STATE_UPDATE` events.
+///
+/// Channels the user has never acked count from when they joined the server
+/// (or the DM). `mention_count` is always 0 for DM channels. Both counts
+/// are 0 when the user has muted the channel or set it to `none`.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct ReadStateDto {
+    pub channel_id: Uuid,
+    pub last_read_at: Option<DateTime<Utc>>,
+    pub last_message_id: Option<Uuid>,
+    pub unread_count: i64,
+    pub mention_count: i64,
 }
 
 /// Unread summary returned in the READY event.
 ///
-/// `unread_count` is always >= 1 (the query uses `HAVING COUNT > 0`).
-/// Channels with no `channel_read_states` row are omitted entirely — this
-/// means "never acknowledged" and "zero unread" are indistinguishable until
-/// the user first acknowledges the channel.
+/// `unread_count` is always >= 1; channels with nothing unread are omitted.
+/// Derived from the READY `read_states` array and kept for older clients.
 #[derive(Debug, FromRow, Serialize, ToSchema)]
 pub struct UnreadCount {
     pub channel_id: Uuid,
     pub unread_count: i64,
 }
 
+// ============================================================================
+// Notification Settings Models
+// ============================================================================
+
+/// A user's notification preferences for one server, category, channel or
+/// DM. Returned by GET /users/@me/notification-settings and in READY.
+///
+/// `level` and `suppress_everyone` are `null` when inherited from the next
+/// scope up (channel → category → server). `channel_id` holds the DM channel
+/// id for `scope = "dm"`.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct NotificationSetting {
+    /// One of `server`, `category`, `channel`, `dm`.
+    pub scope: String,
+    pub server_id: Option<Uuid>,
+    pub category: Option<String>,
+    pub channel_id: Option<Uuid>,
+    /// One of `all`, `mentions`, `none`.
+    pub level: Option<String>,
+    pub muted_until: Option<DateTime<Utc>>,
+    pub suppress_everyone: Option<bool>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Request body for the PUT …/notification-settings endpoints. Replaces the
+/// whole row; `null` or omitted fields inherit from the next scope up.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateNotificationSettingsRequest {
+    /// One of `all`, `mentions`, `none`.
+    pub level: Option<String>,
+    /// Silence the target until this time.
+    pub muted_until: Option<DateTime<Utc>>,
+    /// Do not count `@everyone` as a mention.
+    pub suppress_everyone: Option<bool>,
+}
+
+// ============================================================================
+// Web Push Models
+// ============================================================================
+
+/// A browser push subscription registered by the current user.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct PushSubscriptionDto {
+    pub id: Uuid,
+    pub endpoint: String,
+    pub created_at: DateTime<Utc>,
+    pub last_success_at: Option<DateTime<Utc>>,
+}
+
+/// Keys from the browser's `PushSubscription.toJSON()`.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PushSubscriptionKeys {
+    /// Client P-256 public key, base64url.
+    pub p256dh: String,
+    /// 16-byte authentication secret, base64url.
+    pub auth: String,
+}
+
+/// Request body for POST /users/@me/push-subscriptions — the browser's
+/// `PushSubscription.toJSON()` as-is. Unknown fields such as
+/// `expirationTime` are ignored.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreatePushSubscriptionRequest {
+    pub endpoint: String,
+    pub keys: PushSubscriptionKeys,
+}
+
+/// Per-user Web Push switch. Users without a stored preference have push
+/// enabled.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PushPreferences {
+    pub enabled: bool,
+}
+
+// ── Email ──────────────────────────────────────────────────────────────────
+/// Per-user email switches. Users without a stored preference get no digest.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct EmailPreferences {
+    /// Periodic email listing unread mentions and direct messages.
+    pub mention_digest: bool,
+}
+
+// ── Embeds & webhook authors ───────────────────────────────────────────────
+/// Rich embed attached to a message posted by an incoming webhook.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MessageEmbed {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Link target for the title (http/https).
+    pub url: Option<String>,
+    /// Accent colour as a 24-bit RGB integer, e.g. `5793266` for `#5865F2`.
+    pub color: Option<i32>,
+    #[serde(default)]
+    pub fields: Vec<EmbedField>,
+    pub footer: Option<String>,
+    pub image_url: Option<String>,
+    pub thumbnail_url: Option<String>,
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EmbedField {
+    pub name: String,
+    pub value: String,
+    #[serde(default)]
+    pub inline: bool,
+}
+
+/// Display identity of the incoming webhook that posted a message.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct WebhookAuthor {
+    /// `null` once the webhook has been deleted.
+    pub id: Option<Uuid>,
+    pub username: String,
+    pub avatar_url: Option<String>,
+}
+
 // ── MessageDto ─────────────────────────────────────────────────────────────
 /// API response for a message. Wraps Message with optional rich content.
 #[derive(Debug, Serialize, ToSchema)]
@@ -656,6 +971,11 @@ pub struct MessageDto {
     pub poll: Option<PollDto>,
     /// Some when the message was created by /event
     pub event: Option<ServerEventDto>,
+    /// Some when the message was posted by an incoming webhook (`author_id` is null)
+    pub webhook: Option<WebhookAuthor>,
+    pub embeds: Vec<MessageEmbed>,
+    /// Some on the root message of a forum post
+    pub forum_post: Option<ForumPostInfo>,
 }
 
 impl MessageDto {
@@ -678,6 +998,9 @@ impl MessageDto {
             pinned_at: msg.pinned_at,
             poll: None,
             event: None,
+            webhook: None,
+            embeds: Vec::new(),
+            forum_post: None,
         }
     }
 }
@@ -796,6 +1119,9 @@ pub enum AuditAction {
     MemberTimeoutRemove,
     MemberRoleAdd,
     MemberRoleRemove,
+    MemberVoiceUpdate,
+    MemberVoiceMove,
+    MemberVoiceDisconnect,
 
     // Role actions
     RoleCreate,
@@ -809,6 +1135,9 @@ pub enum AuditAction {
     // Channel override actions
     ChannelOverrideUpdate,
     ChannelOverrideDelete,
+
+    // Instance admin actions
+    UserTwoFactorReset,
 }
 
 // ── Moderation Request DTOs ─────────────────────────────────────────────────
@@ -872,6 +1201,11 @@ pub struct SearchResult {
     pub author_id: Option<Uuid>,
     pub author_username: Option<String>,
     pub content: String,
+    /// Set when the match is a thread reply (including forum post replies).
+    pub thread_id: Option<Uuid>,
+    /// Title of the forum post the match belongs to: the post itself or a
+    /// reply in its thread.
+    pub post_title: Option<String>,
     /// HTML snippet with matching terms wrapped in <mark> tags.
     pub highlight: String,
     pub created_at: DateTime<Utc>,
@@ -1015,13 +1349,104 @@ pub struct AutomodLog {
     pub channel_id: Option<Uuid>,
     pub user_id: Option<Uuid>,
     pub username: Option<String>,
+    /// `spam`, `duplicate`, `word_filter`, or the trigger type of a rule.
     pub rule_type: String,
     pub action_taken: String,
     pub matched_term: Option<String>,
     pub message_content: Option<String>,
+    /// Set when the entry was produced by an automod rule.
+    pub rule_id: Option<Uuid>,
     pub created_at: DateTime<Utc>,
 }
 
+/// What an automod rule looks for in a message.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AutomodTrigger {
+    /// Whole-word, case-insensitive keywords. A leading or trailing `*`
+    /// also matches inside words (`*ass` matches "class").
+    Keyword { keywords: Vec<String> },
+    /// Regular expressions (Rust `regex` syntax); any match triggers.
+    Regex { patterns: Vec<String> },
+    /// Any http(s) or www. link whose host is not in `allowed_domains`
+    /// (subdomains of an allowed domain are allowed too).
+    Link {
+        #[serde(default)]
+        allowed_domains: Vec<String>,
+    },
+    /// Server invite links (`/invite/<code>`) and Discord invites.
+    Invite,
+    /// More than `max_mentions` distinct @mentions (including @everyone).
+    MentionSpam { max_mentions: u32 },
+    /// At least `min_ratio` of the letters are uppercase, in messages with
+    /// at least `min_letters` letters.
+    Caps { min_ratio: f64, min_letters: u32 },
+    /// The author's account is younger than `min_age_minutes`.
+    AccountAge { min_age_minutes: u32 },
+}
+
+/// What an automod rule does when it matches.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AutomodRuleAction {
+    /// Block the message.
+    Delete,
+    /// Time the author out of the server.
+    Timeout { duration_minutes: i32 },
+    /// Post a report into a log channel.
+    Alert { channel_id: Uuid },
+}
+
+/// A configurable automod rule.
+#[derive(Debug, Clone, sqlx::FromRow, Serialize, ToSchema)]
+pub struct AutomodRule {
+    pub id: Uuid,
+    pub server_id: Uuid,
+    pub name: String,
+    pub enabled: bool,
+    #[sqlx(json)]
+    pub trigger: AutomodTrigger,
+    #[sqlx(json)]
+    pub actions: Vec<AutomodRuleAction>,
+    /// Only apply in these channels (empty = every channel).
+    pub channel_ids: Vec<Uuid>,
+    /// Only apply to members with one of these roles (empty = everyone).
+    pub role_ids: Vec<Uuid>,
+    pub exempt_channel_ids: Vec<Uuid>,
+    pub exempt_role_ids: Vec<Uuid>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateAutomodRuleRequest {
+    pub name: String,
+    pub enabled: Option<bool>,
+    pub trigger: AutomodTrigger,
+    pub actions: Vec<AutomodRuleAction>,
+    #[serde(default)]
+    pub channel_ids: Vec<Uuid>,
+    #[serde(default)]
+    pub role_ids: Vec<Uuid>,
+    #[serde(default)]
+    pub exempt_channel_ids: Vec<Uuid>,
+    #[serde(default)]
+    pub exempt_role_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateAutomodRuleRequest {
+    pub name: Option<String>,
+    pub enabled: Option<bool>,
+    pub trigger: Option<AutomodTrigger>,
+    pub actions: Option<Vec<AutomodRuleAction>>,
+    pub channel_ids: Option<Vec<Uuid>>,
+    pub role_ids: Option<Vec<Uuid>>,
+    pub exempt_channel_ids: Option<Vec<Uuid>>,
+    pub exempt_role_ids: Option<Vec<Uuid>>,
+}
+
 #[derive(Debug, Clone, sqlx::FromRow, Serialize, ToSchema)]
 pub struct ServerBan {
     pub user_id: Uuid,
@@ -1187,6 +1612,202 @@ pub struct UpdateWebhookDto {
     pub enabled: Option<bool>,
 }
 
+/// One HTTP attempt of a webhook delivery.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct WebhookDeliveryAttempt {
+    #[serde(skip)]
+    pub delivery_id: Uuid,
+    pub attempt: i32,
+    /// HTTP status returned by the receiver; `null` if no response arrived.
+    pub response_status: Option<i32>,
+    /// Transport error (timeout, connection refused, …) when no response arrived.
+    pub error: Option<String>,
+    pub latency_ms: i32,
+    pub attempted_at: DateTime<Utc>,
+}
+
+/// Database row for a persisted outbound webhook delivery.
+#[derive(Debug, Clone, FromRow)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event_type: String,
+    pub payload: String,
+    pub status: String,
+    pub attempt_count: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub redelivery_of: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// Webhook delivery with its attempt history, as returned by
+/// GET /servers/:id/webhooks/:webhook_id/deliveries.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WebhookDeliveryDto {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event_type: String,
+    /// The exact JSON body sent to the receiver.
+    pub payload: serde_json::Value,
+    /// `pending`, `in_flight`, `succeeded` or `failed` (retries exhausted).
+    pub status: String,
+    pub attempt_count: i32,
+    /// When the next retry is scheduled; only set while `pending`.
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    /// The delivery this one replays, if it was created by a redelivery.
+    pub redelivery_of: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub attempts: Vec<WebhookDeliveryAttempt>,
+}
+
+impl WebhookDeliveryDto {
+    pub fn new(d: WebhookDelivery, attempts: Vec<WebhookDeliveryAttempt>) -> Self {
+        let payload = serde_json::from_str(&d.payload).unwrap_or(serde_json::Value::Null);
+        WebhookDeliveryDto {
+            id: d.id,
+            webhook_id: d.webhook_id,
+            event_type: d.event_type,
+            payload,
+            next_attempt_at: (d.status == "pending").then_some(d.next_attempt_at),
+            status: d.status,
+            attempt_count: d.attempt_count,
+            redelivery_of: d.redelivery_of,
+            created_at: d.created_at,
+            completed_at: d.completed_at,
+            attempts,
+        }
+    }
+}
+
+/// Query parameters for listing webhook deliveries.
+#[derive(Debug, Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct ListWebhookDeliveriesQuery {
+    /// Filter by status (`pending`, `in_flight`, `succeeded`, `failed`).
+    pub status: Option<String>,
+    /// Cursor for pagination (created_at).
+    pub before: Option<DateTime<Utc>>,
+    /// Maximum results (default 50, max 100).
+    pub limit: Option<i64>,
+}
+
+// ── Incoming Webhook Models ─────────────────────────────────────────────────
+
+/// A channel-bound incoming webhook. The token hash is never loaded into
+/// this struct; the plaintext token is only returned at creation.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct IncomingWebhook {
+    pub id: Uuid,
+    pub server_id: Uuid,
+    pub channel_id: Uuid,
+    pub created_by: Option<Uuid>,
+    pub name: String,
+    pub avatar_url: Option<String>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Response for creating an incoming webhook or regenerating its token.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IncomingWebhookTokenResponse {
+    pub webhook: IncomingWebhook,
+    /// Plaintext secret token — shown once only.
+    pub token: String,
+    /// Path to POST messages to: `/webhooks/{id}/{token}`.
+    pub url: String,
+}
+
+/// Request body for POST /servers/:id/incoming-webhooks.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateIncomingWebhookDto {
+    pub name: String,
+    pub channel_id: Uuid,
+    pub avatar_url: Option<String>,
+}
+
+/// Request body for PATCH /servers/:id/incoming-webhooks/:webhook_id.
+/// An empty `avatar_url` clears the default avatar.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateIncomingWebhookDto {
+    pub name: Option<String>,
+    pub channel_id: Option<Uuid>,
+    pub avatar_url: Option<String>,
+}
+
+/// Request body for POST /webhooks/:webhook_id/:token.
+/// At least one of `content` or `embeds` is required.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ExecuteIncomingWebhookDto {
+    pub content: Option<String>,
+    /// Overrides the webhook's name for this message only.
+    pub username: Option<String>,
+    /// Overrides the webhook's avatar for this message only.
+    pub avatar_url: Option<String>,
+    #[serde(default)]
+    pub embeds: Vec<MessageEmbed>,
+}
+
+// ── Scheduled Message Models ───────────────────────────────────────────────
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize, sqlx::Type, ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+pub enum ScheduledMessageKind {
+    /// Posted exactly as written.
+    #[default]
+    Message,
+    /// Posted as `@<author> Reminder: <content>` so the author is notified.
+    Reminder,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, sqlx::Type, ToSchema)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+pub enum ScheduledMessageStatus {
+    Pending,
+    /// Claimed by the scheduler and being posted.
+    Sending,
+    /// Could not be posted; see `last_error`.
+    Failed,
+}
+
+/// A message queued to be posted at `send_at`. Deleted once posted.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct ScheduledMessage {
+    pub id: Uuid,
+    pub channel_id: Uuid,
+    pub author_id: Uuid,
+    pub kind: ScheduledMessageKind,
+    pub content: String,
+    pub reply_to: Option<Uuid>,
+    pub send_at: DateTime<Utc>,
+    pub status: ScheduledMessageStatus,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Request body for POST /channels/:channel_id/scheduled-messages.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateScheduledMessageDto {
+    pub content: String,
+    pub send_at: DateTime<Utc>,
+    #[serde(default)]
+    pub kind: ScheduledMessageKind,
+    pub reply_to: Option<Uuid>,
+}
+
+/// Request body for PATCH /users/@me/scheduled-messages/:id. Editing a
+/// failed message re-queues it.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateScheduledMessageDto {
+    pub content: Option<String>,
+    pub send_at: Option<DateTime<Utc>>,
+}
+
 // ── Invite Models ──────────────────────────────────────────────────────────
 
 /// Database row for a server invite link.
@@ -1218,6 +1839,9 @@ pub struct InvitePreviewDto {
 pub struct CreateInviteRequest {
     pub max_uses: Option<i32>,
     pub expires_in_hours: Option<i64>,
+    /// Also email the invite link to this address. Needs email to be
+    /// configured on the server.
+    pub email: Option<String>,
 }
 
 // ============================================================================
@@ -1234,7 +1858,25 @@ pub struct AdminStatsResponse {
     pub active_ws_connections: usize,
     pub uptime_secs: Option<u64>,
     pub db_latency_ms: u64,
+    /// Recorded size of stored attachments and custom emojis. Computed from
+    /// the database rather than by listing storage.
     pub storage_bytes: u64,
+    /// Sum of attachment `file_size`s, the figure storage quotas count.
+    pub attachment_bytes: i64,
+    /// SERVER_STORAGE_QUOTA_MB in bytes; `None` means no limit.
+    pub server_storage_quota_bytes: Option<u64>,
+    /// USER_STORAGE_QUOTA_MB in bytes; `None` means no limit.
+    pub user_storage_quota_bytes: Option<u64>,
+    /// The servers with the most attachment bytes, largest first (at most 10).
+    pub top_storage_servers: Vec<ServerStorageUsage>,
+}
+
+/// Attachment storage used by one server, in [`AdminStatsResponse`].
+#[derive(Debug, FromRow, Serialize, ToSchema)]
+pub struct ServerStorageUsage {
+    pub server_id: Uuid,
+    pub name: String,
+    pub attachment_bytes: i64,
 }
 
 /// Admin-enriched user row for GET /admin/users.
@@ -3,7 +3,6 @@ use utoipa::{Modify, OpenApi};
 
 use crate::handlers;
 use crate::models;
-use crate::state;
 
 #[derive(OpenApi)]
 #[openapi(
@@ -20,6 +19,29 @@ use crate::state;
         handlers::auth::refresh_token,
         handlers::auth::forgot_password,
         handlers::auth::reset_password,
+        handlers::email::verify_email,
+        handlers::email::resend_verification_email,
+        handlers::email::get_email_preferences,
+        handlers::email::update_email_preferences,
+        handlers::two_factor::verify_two_factor,
+        handlers::two_factor::setup_two_factor_at_login,
+        handlers::two_factor::enroll_two_factor,
+        handlers::two_factor::get_two_factor,
+        handlers::two_factor::setup_two_factor,
+        handlers::two_factor::confirm_two_factor,
+        handlers::two_factor::disable_two_factor,
+        handlers::two_factor::regenerate_recovery_codes,
+        handlers::passkeys::list_passkeys,
+        handlers::passkeys::passkey_creation_options,
+        handlers::passkeys::create_passkey,
+        handlers::passkeys::update_passkey,
+        handlers::passkeys::delete_passkey,
+        handlers::passkeys::passkey_login_options,
+        handlers::passkeys::passkey_login,
+        handlers::passkeys::passkey_second_factor_options,
+        handlers::passkeys::verify_two_factor_passkey,
+        handlers::oidc::oidc_authorize,
+        handlers::oidc::oidc_callback,
         handlers::auth::get_registration_mode,
         // Health
         handlers::health::health_check,
@@ -48,16 +70,32 @@ use crate::state;
         // Messages
         handlers::messages::create_message,
         handlers::messages::list_messages,
+        handlers::scheduled_messages::create_scheduled_message,
+        handlers::scheduled_messages::list_scheduled_messages,
+        handlers::scheduled_messages::update_scheduled_message,
+        handlers::scheduled_messages::delete_scheduled_message,
         handlers::messages::update_message,
         handlers::messages::delete_message,
         handlers::messages::get_message,
         handlers::messages::create_thread_reply,
         handlers::messages::list_thread_replies,
+        // Forum
+        handlers::forum::list_forum_tags,
+        handlers::forum::create_forum_tag,
+        handlers::forum::update_forum_tag,
+        handlers::forum::delete_forum_tag,
+        handlers::forum::list_forum_posts,
+        handlers::forum::update_forum_post,
         // DMs
         handlers::dm::open_dm_channel,
         handlers::dm::list_dm_channels,
         handlers::dm::send_dm_message,
         handlers::dm::list_dm_messages,
+        handlers::dm::create_group_dm,
+        handlers::dm::update_group_dm,
+        handlers::dm::add_group_dm_recipient,
+        handlers::dm::remove_group_dm_recipient,
+        handlers::dm::leave_dm_channel,
         // Search
         handlers::search::search_messages,
         // Reactions
@@ -80,10 +118,17 @@ use crate::state;
         handlers::voice::leave_voice_channel,
         handlers::voice::update_voice_state,
         handlers::voice::list_voice_participants,
+        handlers::voice::moderate_voice_state,
+        handlers::voice::move_voice_member,
+        handlers::voice::disconnect_voice_member,
         // Go Live
         handlers::go_live::start_go_live,
         handlers::go_live::stop_go_live,
         handlers::go_live::get_go_live,
+        handlers::go_live::join_go_live,
+        handlers::go_live::leave_go_live,
+        handlers::go_live::get_go_live_settings,
+        handlers::go_live::update_go_live_settings,
         // ICE
         handlers::ice::get_ice_servers,
         // Bots
@@ -102,6 +147,14 @@ use crate::state;
         handlers::webhooks::update_webhook,
         handlers::webhooks::delete_webhook,
         handlers::webhooks::test_webhook,
+        handlers::webhooks::list_deliveries,
+        handlers::webhooks::redeliver_delivery,
+        handlers::incoming_webhooks::create_incoming_webhook,
+        handlers::incoming_webhooks::list_incoming_webhooks,
+        handlers::incoming_webhooks::update_incoming_webhook,
+        handlers::incoming_webhooks::delete_incoming_webhook,
+        handlers::incoming_webhooks::regenerate_incoming_webhook_token,
+        handlers::incoming_webhooks::execute_incoming_webhook,
         // Invites
         handlers::invites::create_invite,
         handlers::invites::list_invites,
@@ -131,6 +184,7 @@ use crate::state;
         handlers::admin::list_users,
         handlers::admin::update_user,
         handlers::admin::delete_user,
+        handlers::admin::reset_two_factor,
         handlers::admin::list_servers,
         handlers::admin::delete_server,
         // Automod
@@ -139,6 +193,10 @@ use crate::state;
         handlers::automod::list_word_filters,
         handlers::automod::add_word_filter,
         handlers::automod::remove_word_filter,
+        handlers::automod::list_automod_rules,
+        handlers::automod::create_automod_rule,
+        handlers::automod::update_automod_rule,
+        handlers::automod::delete_automod_rule,
         handlers::automod::list_automod_logs,
         handlers::automod::list_bans,
         handlers::automod::remove_ban,
@@ -148,6 +206,11 @@ use crate::state;
         handlers::attachments::upload_attachments,
         handlers::attachments::list_attachments,
         handlers::attachments::serve_file,
+        handlers::attachments::attach_uploads,
+        handlers::uploads::create_upload,
+        handlers::uploads::head_upload,
+        handlers::uploads::patch_upload,
+        handlers::uploads::delete_upload,
         // Custom emojis
         handlers::custom_emojis::list_custom_emojis,
         handlers::custom_emojis::upload_custom_emoji,
@@ -162,6 +225,22 @@ use crate::state;
         // Read states
         handlers::read_states::ack_channel,
         handlers::read_states::ack_dm_channel,
+        handlers::read_states::ack_server,
+        handlers::notification_settings::list_notification_settings,
+        handlers::notification_settings::put_server_notification_settings,
+        handlers::notification_settings::delete_server_notification_settings,
+        handlers::notification_settings::put_category_notification_settings,
+        handlers::notification_settings::delete_category_notification_settings,
+        handlers::notification_settings::put_channel_notification_settings,
+        handlers::notification_settings::delete_channel_notification_settings,
+        handlers::notification_settings::put_dm_notification_settings,
+        handlers::notification_settings::delete_dm_notification_settings,
+        handlers::push::get_vapid_public_key,
+        handlers::push::create_push_subscription,
+        handlers::push::list_push_subscriptions,
+        handlers::push::delete_push_subscription,
+        handlers::push::get_push_preferences,
+        handlers::push::update_push_preferences,
         // Export
         handlers::export::export_server,
     ),
@@ -188,11 +267,28 @@ use crate::state;
         models::MessageDto,
         models::CreateMessageDto,
         models::UpdateMessageDto,
+        models::ForumTag,
+        models::ForumPostInfo,
+        models::CreateForumTagDto,
+        models::UpdateForumTagDto,
+        models::UpdateForumPostDto,
+        models::MessageEmbed,
+        models::EmbedField,
+        models::WebhookAuthor,
         // Voice models
         models::VoiceStateDto,
+        models::VoiceJoinResponse,
+        models::VoiceMode,
         models::UpdateVoiceStateRequest,
+        models::ModerateVoiceStateRequest,
+        models::MoveVoiceMemberRequest,
+        models::GoLiveSession,
+        models::GoLiveSettings,
+        models::UpdateGoLiveSettingsRequest,
         // Attachment
         models::Attachment,
+        models::Upload,
+        models::AttachUploadsRequest,
         // Custom emoji
         models::CustomEmojiDto,
         // DM models
@@ -204,6 +300,15 @@ use crate::state;
         models::ReactionCount,
         // Read states
         models::UnreadCount,
+        models::ReadStateDto,
+        models::AckChannelRequest,
+        models::NotificationSetting,
+        models::UpdateNotificationSettingsRequest,
+        models::PushSubscriptionDto,
+        models::PushSubscriptionKeys,
+        models::CreatePushSubscriptionRequest,
+        models::PushPreferences,
+        models::EmailPreferences,
         // Polls
         models::PollOption,
         models::PollDto,
@@ -239,6 +344,11 @@ use crate::state;
         models::AutomodWordFilter,
         models::AddWordFilterRequest,
         models::AutomodLog,
+        models::AutomodTrigger,
+        models::AutomodRuleAction,
+        models::AutomodRule,
+        models::CreateAutomodRuleRequest,
+        models::UpdateAutomodRuleRequest,
         models::ServerBan,
         models::AutomodTimeout,
         // Roles
@@ -254,6 +364,19 @@ use crate::state;
         models::WebhookCreatedResponse,
         models::CreateWebhookDto,
         models::UpdateWebhookDto,
+        models::WebhookDeliveryDto,
+        models::WebhookDeliveryAttempt,
+        models::ListWebhookDeliveriesQuery,
+        models::IncomingWebhook,
+        models::IncomingWebhookTokenResponse,
+        models::CreateIncomingWebhookDto,
+        models::UpdateIncomingWebhookDto,
+        models::ExecuteIncomingWebhookDto,
+        models::ScheduledMessageKind,
+        models::ScheduledMessageStatus,
+        models::ScheduledMessage,
+        models::CreateScheduledMessageDto,
+        models::UpdateScheduledMessageDto,
         // Invites
         models::ServerInvite,
         models::InvitePreviewDto,
@@ -266,6 +389,7 @@ use crate::state;
         models::UpdateSettingsRequest,
         // Admin
         models::AdminStatsResponse,
+        models::ServerStorageUsage,
         models::AdminUserDto,
         models::AdminUsersResponse,
         models::AdminServerDto,
@@ -279,8 +403,42 @@ use crate::state;
         handlers::auth::LoginRequest,
         handlers::auth::RefreshRequest,
         handlers::auth::AuthResponse,
+        handlers::auth::LoginResponse,
         handlers::auth::ForgotPasswordRequest,
+        handlers::email::VerifyEmailRequest,
         handlers::auth::ResetPasswordRequest,
+        handlers::two_factor::TwoFactorStatus,
+        handlers::two_factor::TwoFactorSetupRequest,
+        handlers::two_factor::TwoFactorSetup,
+        handlers::two_factor::TwoFactorCodeRequest,
+        handlers::two_factor::DisableTwoFactorRequest,
+        handlers::two_factor::RecoveryCodes,
+        handlers::two_factor::ChallengePurpose,
+        handlers::two_factor::TwoFactorChallenge,
+        handlers::two_factor::ChallengeRequest,
+        handlers::two_factor::ChallengeCodeRequest,
+        handlers::two_factor::TwoFactorMethod,
+        handlers::two_factor::TwoFactorEnrollResponse,
+        handlers::passkeys::Passkey,
+        handlers::passkeys::PasskeyOptionsRequest,
+        handlers::passkeys::CreatePasskeyRequest,
+        handlers::passkeys::UpdatePasskeyRequest,
+        handlers::passkeys::RegistrationCredential,
+        handlers::passkeys::AttestationResponse,
+        handlers::passkeys::AssertionCredential,
+        handlers::passkeys::AssertionResponse,
+        handlers::passkeys::PasskeyLoginRequest,
+        handlers::passkeys::PasskeySecondFactorRequest,
+        handlers::passkeys::RelyingPartyEntity,
+        handlers::passkeys::UserEntity,
+        handlers::passkeys::CredentialParameters,
+        handlers::passkeys::CredentialDescriptor,
+        handlers::passkeys::AuthenticatorSelection,
+        handlers::passkeys::PasskeyCreationOptions,
+        handlers::passkeys::PasskeyRequestOptions,
+        handlers::oidc::OidcAuthorizeRequest,
+        handlers::oidc::OidcAuthorizeResponse,
+        handlers::oidc::OidcCallbackRequest,
         handlers::health::HealthResponse,
         handlers::health::DatabaseHealth,
         handlers::health::ConnectionsHealth,
@@ -292,10 +450,10 @@ use crate::state;
         handlers::bots::ListBotsResponse,
         handlers::bots::BotLogsResponse,
         handlers::go_live::StartGoLiveRequest,
+        handlers::push::VapidPublicKeyResponse,
         handlers::servers::CreateServerRequest,
         handlers::servers::UpdateServerRequest,
         handlers::servers::MemberWithRolesDto,
-        state::GoLiveSession,
     )),
     modifiers(&SecurityAddon),
     tags(
@@ -306,6 +464,7 @@ use crate::state;
         (name = "Channels", description = "Channel management"),
         (name = "Messages", description = "Message CRUD and threads"),
         (name = "DirectMessages", description = "Direct message channels"),
+        (name = "Forum", description = "Forum channel tags and posts"),
         (name = "Search", description = "Full-text message search"),
         (name = "Reactions", description = "Message reactions"),
         (name = "Pins", description = "Pinned messages"),
@@ -329,6 +488,8 @@ use crate::state;
         (name = "Giphy", description = "GIF search via Giphy"),
         (name = "LinkPreview", description = "Open Graph link previews"),
         (name = "ReadStates", description = "Read state acknowledgement"),
+        (name = "NotificationSettings", description = "Per-user notification preferences"),
+        (name = "Push", description = "Web Push subscriptions for offline notifications"),
         (name = "Export", description = "Server data export"),
     )
 )]
@@ -0,0 +1,663 @@
+//! Web Push notifications for users who are not connected to the gateway.
+//!
+//! # Design
+//!
+//! Message handlers hand a [`PushJob`] to [`PushDispatcher::notify`], which
+//! only queues it on an in-process channel so the request never waits on a
+//! push service. A background worker turns each job into recipients:
+//!
+//! - channel messages: users mentioned by name, the author of the message
+//!   being replied to (or the thread's root message), and for `@everyone`
+//!   every member who can view the channel;
+//! - DMs: every other participant.
+//!
+//! A recipient is skipped if they wrote the message, have a live gateway
+//! session on this node ([`ConnectionManager::is_connected`]), are set to
+//! `dnd`, turned push off, or the channel is silenced for them by their
+//! notification settings (see [`crate::handlers::notification_settings`]).
+//! `@everyone` is also skipped when they suppress it.
+//!
+//! Each remaining subscription receives one encrypted request (RFC 8291,
+//! `aes128gcm`) signed with the server's VAPID key (RFC 8292). Delivery is
+//! best-effort: jobs are not persisted and failed requests are not retried.
+//! Subscriptions the push service reports as gone (404 or 410) are deleted.
+//!
+//! [`ConnectionManager::is_connected`]: crate::websocket::ConnectionManager::is_connected
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use aes_gcm::{aead::Aead, Aes128Gcm, KeyInit};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hkdf::Hkdf;
+use p256::{
+    ecdsa::{signature::Signer, Signature, SigningKey},
+    elliptic_curve::sec1::ToEncodedPoint,
+    PublicKey, SecretKey,
+};
+use rand::RngCore;
+use sha2::Sha256;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::{
+    error::AppResult,
+    handlers::{
+        link_preview::is_private_ip,
+        notification_settings::{load_notification_settings, resolve, NotificationTarget},
+        shared::fetch_channel_by_id,
+    },
+    state::AppState,
+    websocket::channel_viewers,
+};
+
+/// How long a push service should hold a notification for an offline device.
+const TTL_SECS: u32 = 24 * 3600;
+/// Lifetime of the VAPID JWT; RFC 8292 allows at most 24 hours.
+const VAPID_JWT_SECS: i64 = 12 * 3600;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Message text included in a notification is cut to this many characters.
+const MAX_BODY_CHARS: usize = 500;
+/// Record size advertised in the `aes128gcm` header. Payloads always fit in
+/// a single record.
+const RECORD_SIZE: u32 = 4096;
+
+// ── VAPID ─────────────────────────────────────────────────────────────────────
+
+/// The server's VAPID key pair.
+pub struct VapidKey {
+    signing_key: SigningKey,
+    /// Uncompressed public key, base64url — what browsers pass to
+    /// `pushManager.subscribe({ applicationServerKey })`.
+    public_key: String,
+}
+
+impl VapidKey {
+    /// Parse a base64url-encoded raw 32-byte P-256 private key.
+    pub fn from_base64url(private_key: &str) -> Result<Self, String> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(private_key.trim().trim_end_matches('='))
+            .map_err(|_| "VAPID_PRIVATE_KEY must be base64url".to_string())?;
+        let secret = SecretKey::from_slice(&bytes)
+            .map_err(|_| "VAPID_PRIVATE_KEY must be a 32-byte P-256 private key".to_string())?;
+        let public_key =
+            URL_SAFE_NO_PAD.encode(secret.public_key().to_encoded_point(false).as_bytes());
+        Ok(Self {
+            signing_key: SigningKey::from(secret),
+            public_key,
+        })
+    }
+
+    pub fn public_key(&self) -> &str {
+        &self.public_key
+    }
+
+    /// `Authorization` header value for a request to `endpoint`.
+    fn authorization(&self, endpoint: &url::Url, subject: &str) -> String {
+        let audience = endpoint.origin().ascii_serialization();
+        let header = URL_SAFE_NO_PAD.encode(br#"{"typ":"JWT","alg":"ES256"}"#);
+        let claims = URL_SAFE_NO_PAD.encode(
+            serde_json::json!({
+                "aud": audience,
+                "exp": chrono::Utc::now().timestamp() + VAPID_JWT_SECS,
+                "sub": subject,
+            })
+            .to_string(),
+        );
+        let signing_input = format!("{header}.{claims}");
+        let signature: Signature = self.signing_key.sign(signing_input.as_bytes());
+        let jwt = format!(
+            "{signing_input}.{}",
+            URL_SAFE_NO_PAD.encode(signature.to_bytes())
+        );
+        format!("vapid t={jwt}, k={}", self.public_key)
+    }
+}
+
+// ── Payload encryption (RFC 8291) ─────────────────────────────────────────────
+
+/// Decode and check a subscription's `p256dh` and `auth` keys.
+pub fn decode_subscription_keys(p256dh: &str, auth: &str) -> Result<(PublicKey, [u8; 16]), String> {
+    let p256dh = URL_SAFE_NO_PAD
+        .decode(p256dh.trim_end_matches('='))
+        .ok()
+        .and_then(|bytes| PublicKey::from_sec1_bytes(&bytes).ok())
+        .ok_or_else(|| "keys.p256dh must be a base64url P-256 public key".to_string())?;
+    let auth = URL_SAFE_NO_PAD
+        .decode(auth.trim_end_matches('='))
+        .ok()
+        .and_then(|bytes| <[u8; 16]>::try_from(bytes).ok())
+        .ok_or_else(|| "keys.auth must be 16 base64url-encoded bytes".to_string())?;
+    Ok((p256dh, auth))
+}
+
+/// Encrypt `plaintext` for a subscription as a single `aes128gcm` record.
+pub fn encrypt_payload(ua_public: &PublicKey, auth_secret: &[u8; 16], plaintext: &[u8]) -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+    let as_secret = p256::ecdh::EphemeralSecret::random(&mut rng);
+    let as_public = as_secret.public_key().to_encoded_point(false);
+    let ua_public_bytes = ua_public.to_encoded_point(false);
+    let shared = as_secret.diffie_hellman(ua_public);
+
+    // IKM = HKDF(auth_secret, ecdh_secret, "WebPush: info" || 0 || ua || as)
+    let mut key_info = Vec::with_capacity(14 + 65 + 65);
+    key_info.extend_from_slice(b"WebPush: info\0");
+    key_info.extend_from_slice(ua_public_bytes.as_bytes());
+    key_info.extend_from_slice(as_public.as_bytes());
+    let mut ikm = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(auth_secret), shared.raw_secret_bytes())
+        .expand(&key_info, &mut ikm)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+
+    let mut salt = [0u8; 16];
+    rng.fill_bytes(&mut salt);
+    let prk = Hkdf::<Sha256>::new(Some(&salt), &ikm);
+    let mut cek = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    prk.expand(b"Content-Encoding: aes128gcm\0", &mut cek)
+        .expect("16 bytes is a valid HKDF-SHA256 output length");
+    prk.expand(b"Content-Encoding: nonce\0", &mut nonce)
+        .expect("12 bytes is a valid HKDF-SHA256 output length");
+
+    // A single, final record: plaintext followed by the 0x02 delimiter.
+    let mut record = Vec::with_capacity(plaintext.len() + 1);
+    record.extend_from_slice(plaintext);
+    record.push(0x02);
+    let ciphertext = Aes128Gcm::new(&cek.into())
+        .encrypt(&nonce.into(), record.as_slice())
+        .expect("AES-GCM encryption of an in-memory buffer cannot fail");
+
+    // Header: salt || record size || key id length || key id (our public key).
+    let mut body = Vec::with_capacity(16 + 4 + 1 + 65 + ciphertext.len());
+    body.extend_from_slice(&salt);
+    body.extend_from_slice(&RECORD_SIZE.to_be_bytes());
+    body.push(as_public.as_bytes().len() as u8);
+    body.extend_from_slice(as_public.as_bytes());
+    body.extend_from_slice(&ciphertext);
+    body
+}
+
+// ── Dispatcher ────────────────────────────────────────────────────────────────
+
+/// A newly posted message that may need push notifications.
+#[derive(Debug, Clone)]
+pub enum PushJob {
+    ChannelMessage {
+        server_id: Uuid,
+        channel_id: Uuid,
+        message_id: Uuid,
+        author_id: Uuid,
+        content: String,
+        mention_user_ids: Vec<Uuid>,
+        mention_everyone: bool,
+        /// The message replied to, or the root of the thread posted in.
+        reply_to: Option<Uuid>,
+    },
+    DirectMessage {
+        channel_id: Uuid,
+        message_id: Uuid,
+        author_id: Uuid,
+        content: String,
+    },
+}
+
+/// Cheap handle for queuing push jobs; present on `AppState` when Web Push
+/// is configured.
+#[derive(Clone)]
+pub struct PushDispatcher {
+    vapid: Arc<VapidKey>,
+    subject: Arc<str>,
+    allow_http_endpoints: bool,
+    jobs: mpsc::UnboundedSender<PushJob>,
+}
+
+impl PushDispatcher {
+    /// Create the dispatcher handle and the receiver to pass to
+    /// [`start_worker`] once `AppState` exists.
+    pub fn new(
+        config: &crate::config::WebPushConfig,
+    ) -> Result<(Self, mpsc::UnboundedReceiver<PushJob>), String> {
+        let vapid = VapidKey::from_base64url(&config.vapid_private_key)?;
+        let (jobs, rx) = mpsc::unbounded_channel();
+        Ok((
+            Self {
+                vapid: Arc::new(vapid),
+                subject: config.subject.as_str().into(),
+                allow_http_endpoints: config.allow_http_endpoints,
+                jobs,
+            },
+            rx,
+        ))
+    }
+
+    pub fn vapid_public_key(&self) -> &str {
+        self.vapid.public_key()
+    }
+
+    /// Whether `http://` endpoints and private addresses are accepted.
+    pub fn allow_http_endpoints(&self) -> bool {
+        self.allow_http_endpoints
+    }
+
+    /// Queue a job. Never blocks; a stopped worker drops the job.
+    pub fn notify(&self, job: PushJob) {
+        let _ = self.jobs.send(job);
+    }
+}
+
+/// Queue `job` if Web Push is configured.
+pub fn notify(state: &AppState, job: PushJob) {
+    if let Some(push) = &state.push {
+        push.notify(job);
+    }
+}
+
+/// Spawn the background worker that sends queued jobs.
+///
+/// Call once after `AppState` is built, with the receiver returned by
+/// [`PushDispatcher::new`].
+pub fn start_worker(state: AppState, mut jobs: mpsc::UnboundedReceiver<PushJob>) {
+    tokio::spawn(async move {
+        while let Some(job) = jobs.recv().await {
+            let state = state.clone();
+            tokio::spawn(async move {
+                if let Err(e) = dispatch(&state, job).await {
+                    tracing::warn!(error = ?e, "Failed to dispatch push notifications");
+                }
+            });
+        }
+    });
+}
+
+/// Why a recipient is being notified, most important first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Reason {
+    Mention,
+    Reply,
+    Everyone,
+    DirectMessage,
+}
+
+impl Reason {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Mention | Self::Everyone => "mention",
+            Self::Reply => "reply",
+            Self::DirectMessage => "dm",
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct Subscription {
+    id: Uuid,
+    user_id: Uuid,
+    endpoint: String,
+    p256dh: String,
+    auth: String,
+}
+
+/// Work out who should hear about `job` and send to their subscriptions.
+async fn dispatch(state: &AppState, job: PushJob) -> AppResult<()> {
+    let Some(push) = &state.push else {
+        return Ok(());
+    };
+
+    let (author_id, message_id, channel_id, server_id, content, candidates) = match job {
+        PushJob::ChannelMessage {
+            server_id,
+            channel_id,
+            message_id,
+            author_id,
+            content,
+            mention_user_ids,
+            mention_everyone,
+            reply_to,
+        } => {
+            let mut candidates: Vec<(Uuid, Reason)> = mention_user_ids
+                .into_iter()
+                .map(|id| (id, Reason::Mention))
+                .collect();
+            if let Some(reply_to) = reply_to {
+                let replied_author: Option<Option<Uuid>> =
+                    sqlx::query_scalar("SELECT author_id FROM messages WHERE id = $1")
+                        .bind(reply_to)
+                        .fetch_optional(&state.pool)
+                        .await?;
+                if let Some(Some(id)) = replied_author {
+                    candidates.push((id, Reason::Reply));
+                }
+            }
+            let viewers = channel_viewers(state, server_id, channel_id).await?;
+            if mention_everyone {
+                candidates.extend(viewers.iter().map(|id| (*id, Reason::Everyone)));
+            }
+            candidates.retain(|(id, _)| viewers.contains(id));
+            (
+                author_id,
+                message_id,
+                channel_id,
+                Some(server_id),
+                content,
+                candidates,
+            )
+        }
+        PushJob::DirectMessage {
+            channel_id,
+            message_id,
+            author_id,
+            content,
+        } => {
+            let members: Vec<Uuid> = sqlx::query_scalar(
+                "SELECT user_id FROM direct_message_members WHERE channel_id = $1",
+            )
+            .bind(channel_id)
+            .fetch_all(&state.pool)
+            .await?;
+            let candidates = members
+                .into_iter()
+                .map(|id| (id, Reason::DirectMessage))
+                .collect();
+            (author_id, message_id, channel_id, None, content, candidates)
+        }
+    };
+
+    // One entry per user with their most important reason.
+    let mut recipients: Vec<(Uuid, Reason)> = Vec::new();
+    for (user_id, reason) in candidates {
+        if user_id == author_id || state.connections.is_connected(user_id).await {
+            continue;
+        }
+        match recipients.iter_mut().find(|(id, _)| *id == user_id) {
+            Some(existing) => existing.1 = existing.1.min(reason),
+            None => recipients.push((user_id, reason)),
+        }
+    }
+    if recipients.is_empty() {
+        return Ok(());
+    }
+
+    let user_ids: Vec<Uuid> = recipients.iter().map(|(id, _)| *id).collect();
+    let subscriptions = sqlx::query_as::<_, Subscription>(
+        "SELECT s.id, s.user_id, s.endpoint, s.p256dh, s.auth
+         FROM push_subscriptions s
+         JOIN users u ON u.id = s.user_id
+         LEFT JOIN push_preferences p ON p.user_id = s.user_id
+         WHERE s.user_id = ANY($1)
+           AND u.status <> 'dnd'
+           AND COALESCE(p.enabled, TRUE)",
+    )
+    .bind(&user_ids)
+    .fetch_all(&state.pool)
+    .await?;
+    if subscriptions.is_empty() {
+        return Ok(());
+    }
+
+    let author_username: String = sqlx::query_scalar("SELECT username FROM users WHERE id = $1")
+        .bind(author_id)
+        .fetch_optional(&state.pool)
+        .await?
+        .unwrap_or_default();
+    let category = match server_id {
+        Some(_) => fetch_channel_by_id(&state.pool, channel_id)
+            .await
+            .ok()
+            .and_then(|c| c.category),
+        None => None,
+    };
+    let target = match server_id {
+        Some(server_id) => NotificationTarget::Channel {
+            server_id,
+            category: category.as_deref(),
+            channel_id,
+        },
+        None => NotificationTarget::Dm { channel_id },
+    };
+    let body: String = content.chars().take(MAX_BODY_CHARS).collect();
+    let now = chrono::Utc::now();
+
+    for (user_id, reason) in recipients {
+        let user_subscriptions: Vec<&Subscription> = subscriptions
+            .iter()
+            .filter(|s| s.user_id == user_id)
+            .collect();
+        if user_subscriptions.is_empty() {
+            continue;
+        }
+
+        let settings = load_notification_settings(&state.pool, user_id).await?;
+        let effective = resolve(&settings, target, now);
+        if effective.is_silenced() || (reason == Reason::Everyone && effective.suppress_everyone) {
+            continue;
+        }
+
+        let payload = serde_json::json!({
+            "type": reason.as_str(),
+            "server_id": server_id,
+            "channel_id": channel_id,
+            "message_id": message_id,
+            "author": { "id": author_id, "username": author_username },
+            "body": body,
+        })
+        .to_string();
+
+        for subscription in user_subscriptions {
+            send(state, push, subscription, payload.as_bytes()).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolve the host of `endpoint` and return the address to connect to.
+/// Fails if the host does not resolve or any of its addresses is private or
+/// reserved.
+pub async fn resolve_public_endpoint(endpoint: &url::Url) -> Result<SocketAddr, String> {
+    let host = endpoint.host_str().ok_or("endpoint has no host")?;
+    let port = endpoint.port_or_known_default().unwrap_or(443);
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host(format!("{host}:{port}"))
+        .await
+        .map_err(|_| "could not resolve endpoint host".to_string())?
+        .collect();
+    if addrs.iter().any(|addr| is_private_ip(addr.ip())) {
+        return Err("endpoint must be a public push service".into());
+    }
+    addrs
+        .first()
+        .copied()
+        .ok_or_else(|| "could not resolve endpoint host".into())
+}
+
+/// A client for one request to `endpoint`. Redirects are not followed, and
+/// unless private endpoints are allowed the checked address is pinned so the
+/// host cannot resolve elsewhere between the check and the request.
+async fn endpoint_client(
+    push: &PushDispatcher,
+    endpoint: &url::Url,
+) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none());
+    if !push.allow_http_endpoints {
+        let addr = resolve_public_endpoint(endpoint).await?;
+        if let Some(host) = endpoint.host_str() {
+            builder = builder.resolve(host, addr);
+        }
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// Send one notification and record the outcome on the subscription.
+async fn send(
+    state: &AppState,
+    push: &PushDispatcher,
+    subscription: &Subscription,
+    payload: &[u8],
+) {
+    let Ok(endpoint) = url::Url::parse(&subscription.endpoint) else {
+        return;
+    };
+    let Ok((ua_public, auth)) = decode_subscription_keys(&subscription.p256dh, &subscription.auth)
+    else {
+        return;
+    };
+
+    let client = match endpoint_client(push, &endpoint).await {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::warn!(subscription_id = %subscription.id, error = %e, "Push endpoint refused");
+            let failed = sqlx::query(
+                "UPDATE push_subscriptions SET failure_count = failure_count + 1 WHERE id = $1",
+            )
+            .bind(subscription.id)
+            .execute(&state.pool)
+            .await;
+            if let Err(e) = failed {
+                tracing::warn!(subscription_id = %subscription.id, error = ?e, "Failed to record push outcome");
+            }
+            return;
+        }
+    };
+
+    let body = encrypt_payload(&ua_public, &auth, payload);
+    let result = client
+        .post(endpoint.as_str())
+        .header(
+            "Authorization",
+            push.vapid.authorization(&endpoint, &push.subject),
+        )
+        .header("TTL", TTL_SECS.to_string())
+        .header("Urgency", "high")
+        .header("Content-Encoding", "aes128gcm")
+        .header("Content-Type", "application/octet-stream")
+        .body(body)
+        .send()
+        .await;
+
+    let outcome = match result {
+        Ok(response) if response.status().is_success() => sqlx::query(
+            "UPDATE push_subscriptions SET failure_count = 0, last_success_at = NOW()
+             WHERE id = $1",
+        ),
+        Ok(response) if matches!(response.status().as_u16(), 404 | 410) => {
+            tracing::debug!(subscription_id = %subscription.id, "Push subscription expired");
+            sqlx::query("DELETE FROM push_subscriptions WHERE id = $1")
+        }
+        Ok(response) => {
+            tracing::warn!(
+                subscription_id = %subscription.id,
+                status = %response.status(),
+                "Push service rejected notification"
+            );
+            sqlx::query(
+                "UPDATE push_subscriptions SET failure_count = failure_count + 1 WHERE id = $1",
+            )
+        }
+        Err(e) => {
+            tracing::warn!(subscription_id = %subscription.id, error = ?e, "Push request failed");
+            sqlx::query(
+                "UPDATE push_subscriptions SET failure_count = failure_count + 1 WHERE id = $1",
+            )
+        }
+    };
+    if let Err(e) = outcome.bind(subscription.id).execute(&state.pool).await {
+        tracing::warn!(subscription_id = %subscription.id, error = ?e, "Failed to record push outcome");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdh::diffie_hellman;
+
+    /// Decrypt as a user agent would (RFC 8291 §3.4 from the receiving side).
+    fn decrypt(ua_secret: &SecretKey, auth: &[u8; 16], body: &[u8]) -> Vec<u8> {
+        let salt = &body[..16];
+        let key_len = body[20] as usize;
+        let as_public = PublicKey::from_sec1_bytes(&body[21..21 + key_len]).unwrap();
+        let ciphertext = &body[21 + key_len..];
+
+        let shared = diffie_hellman(ua_secret.to_nonzero_scalar(), as_public.as_affine());
+        let mut key_info = b"WebPush: info\0".to_vec();
+        key_info.extend_from_slice(ua_secret.public_key().to_encoded_point(false).as_bytes());
+        key_info.extend_from_slice(as_public.to_encoded_point(false).as_bytes());
+        let mut ikm = [0u8; 32];
+        Hkdf::<Sha256>::new(Some(auth), shared.raw_secret_bytes())
+            .expand(&key_info, &mut ikm)
+            .unwrap();
+        let prk = Hkdf::<Sha256>::new(Some(salt), &ikm);
+        let mut cek = [0u8; 16];
+        let mut nonce = [0u8; 12];
+        prk.expand(b"Content-Encoding: aes128gcm\0", &mut cek)
+            .unwrap();
+        prk.expand(b"Content-Encoding: nonce\0", &mut nonce)
+            .unwrap();
+
+        let mut record = Aes128Gcm::new(&cek.into())
+            .decrypt(&nonce.into(), ciphertext)
+            .unwrap();
+        assert_eq!(record.pop(), Some(0x02), "missing last-record delimiter");
+        record
+    }
+
+    #[test]
+    fn payload_round_trips() {
+        let ua_secret = SecretKey::random(&mut rand::thread_rng());
+        let auth = [7u8; 16];
+        let body = encrypt_payload(&ua_secret.public_key(), &auth, b"hello push");
+        assert_eq!(&body[16..20], &RECORD_SIZE.to_be_bytes());
+        assert_eq!(decrypt(&ua_secret, &auth, &body), b"hello push");
+    }
+
+    #[test]
+    fn vapid_header_carries_public_key_and_audience() {
+        let secret = SecretKey::random(&mut rand::thread_rng());
+        let key = VapidKey::from_base64url(&URL_SAFE_NO_PAD.encode(secret.to_bytes())).unwrap();
+        let endpoint = url::Url::parse("https://push.example.com/send/abc").unwrap();
+        let header = key.authorization(&endpoint, "mailto:ops@example.com");
+
+        let (jwt, k) = header
+            .strip_prefix("vapid t=")
+            .and_then(|rest| rest.split_once(", k="))
+            .unwrap();
+        assert_eq!(k, key.public_key());
+        let claims: serde_json::Value = serde_json::from_slice(
+            &URL_SAFE_NO_PAD
+                .decode(jwt.split('.').nth(1).unwrap())
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(claims["aud"], "https://push.example.com");
+        assert_eq!(claims["sub"], "mailto:ops@example.com");
+    }
+
+    #[test]
+    fn rejects_malformed_subscription_keys() {
+        assert!(decode_subscription_keys("not-a-key", "AAAAAAAAAAAAAAAAAAAAAA").is_err());
+        let public = SecretKey::random(&mut rand::thread_rng()).public_key();
+        let p256dh = URL_SAFE_NO_PAD.encode(public.to_encoded_point(false).as_bytes());
+        assert!(decode_subscription_keys(&p256dh, "c2hvcnQ").is_err());
+        assert!(decode_subscription_keys(&p256dh, "AAAAAAAAAAAAAAAAAAAAAA").is_ok());
+    }
+
+    #[tokio::test]
+    async fn refuses_endpoints_resolving_to_private_addresses() {
+        for endpoint in [
+            "https://localhost/push",
+            "https://127.0.0.1:8443/push",
+            "https://10.0.0.1/push",
+            "https://169.254.169.254/latest",
+            "https://[::1]/push",
+        ] {
+            let url = url::Url::parse(endpoint).unwrap();
+            assert!(resolve_public_endpoint(&url).await.is_err(), "{endpoint}");
+        }
+        let url = url::Url::parse("https://93.184.216.34/push").unwrap();
+        assert_eq!(
+            resolve_public_endpoint(&url).await.unwrap(),
+            "93.184.216.34:443".parse().unwrap()
+        );
+    }
+}
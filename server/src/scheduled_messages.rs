@@ -0,0 +1,196 @@
+//! Background scheduler for scheduled messages and reminders.
+//!
+//! # Design
+//!
+//! Each queued message is a row in `scheduled_messages`, so pending messages
+//! survive restarts. A background worker claims due rows with
+//! `FOR UPDATE SKIP LOCKED` (safe with several replicas polling the same
+//! table) and posts them through [`crate::handlers::messages::post_message`],
+//! the same path as `POST /channels/:id/messages`: membership and permission
+//! checks, timeout, automod, `MESSAGE_CREATE` broadcast and the
+//! `message.created` webhook all apply at send time.
+//!
+//! A claimed row carries a lease (`locked_until`). If posting fails with a
+//! transient error, or the process dies mid-post, the lease expires and the
+//! row is retried, up to [`MAX_ATTEMPTS`]. The posted message reuses the
+//! row's id, so a retry first checks whether the message already exists and
+//! never posts it twice.
+//!
+//! Errors the author has to fix (lost access to the channel, content blocked
+//! by automod, reply target deleted) mark the row `failed` with the reason.
+
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    handlers::messages::{post_message, NewMessage},
+    models::ScheduledMessageKind,
+    state::AppState,
+};
+
+/// Attempts made before a transient failure marks the row `failed`.
+pub const MAX_ATTEMPTS: i32 = 5;
+
+/// How long a claimed row is reserved, and so the delay before a retry.
+const LEASE_SECS: f64 = 60.0;
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Rows claimed per poll.
+const BATCH_SIZE: i64 = 50;
+
+/// Spawn the background scheduler.
+///
+/// Call once in `main` after `AppState` is built. Messages that came due
+/// while the server was down are posted on the first poll.
+pub fn start_worker(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            loop {
+                match dispatch_due(&state).await {
+                    // A full batch means more rows may already be due.
+                    Ok(n) if n as i64 == BATCH_SIZE => continue,
+                    Ok(_) => break,
+                    Err(e) => {
+                        tracing::warn!(error = ?e, "Failed to claim scheduled messages");
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+#[derive(sqlx::FromRow)]
+struct ClaimedMessage {
+    id: Uuid,
+    channel_id: Uuid,
+    author_id: Uuid,
+    username: String,
+    kind: ScheduledMessageKind,
+    content: String,
+    reply_to: Option<Uuid>,
+    attempt_count: i32,
+}
+
+/// Claim and post one batch of due messages. Returns how many were claimed.
+pub async fn dispatch_due(state: &AppState) -> Result<usize, sqlx::Error> {
+    let claimed = sqlx::query_as::<_, ClaimedMessage>(
+        "WITH due AS (
+             SELECT id FROM scheduled_messages
+             WHERE (status = 'pending' AND send_at <= NOW())
+                OR (status = 'sending' AND locked_until < NOW())
+             ORDER BY send_at
+             LIMIT $1
+             FOR UPDATE SKIP LOCKED
+         )
+         UPDATE scheduled_messages s
+         SET status = 'sending',
+             locked_until = NOW() + make_interval(secs => $2),
+             attempt_count = s.attempt_count + 1
+         FROM due, users u
+         WHERE s.id = due.id AND u.id = s.author_id
+         RETURNING s.id, s.channel_id, s.author_id, u.username, s.kind, s.content,
+                   s.reply_to, s.attempt_count",
+    )
+    .bind(BATCH_SIZE)
+    .bind(LEASE_SECS)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let count = claimed.len();
+    for message in claimed {
+        let id = message.id;
+        if let Err(e) = send(state, message).await {
+            tracing::warn!(scheduled_message_id = %id, error = ?e, "Failed to record scheduled message outcome");
+        }
+    }
+    Ok(count)
+}
+
+/// Post one claimed message and record the outcome.
+async fn send(state: &AppState, message: ClaimedMessage) -> Result<(), sqlx::Error> {
+    // A previous attempt may have posted it before the row was removed.
+    let already_posted: bool =
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM messages WHERE id = $1)")
+            .bind(message.id)
+            .fetch_one(&state.pool)
+            .await?;
+
+    let result = if already_posted {
+        Ok(())
+    } else {
+        let content = match message.kind {
+            ScheduledMessageKind::Message => message.content,
+            ScheduledMessageKind::Reminder => {
+                format!("@{} Reminder: {}", message.username, message.content)
+            }
+        };
+        post_message(
+            state,
+            message.author_id,
+            &message.username,
+            NewMessage {
+                channel_id: message.channel_id,
+                content,
+                reply_to: message.reply_to,
+                id: Some(message.id),
+                forum: None,
+            },
+        )
+        .await
+        .map(|_| ())
+    };
+
+    match result {
+        Ok(()) => {
+            sqlx::query("DELETE FROM scheduled_messages WHERE id = $1")
+                .bind(message.id)
+                .execute(&state.pool)
+                .await?;
+            tracing::debug!(scheduled_message_id = %message.id, "Scheduled message posted");
+        }
+        // Transient: keep the lease so the row is retried once it expires.
+        // A conflict means the id is already taken, i.e. a concurrent or
+        // earlier attempt posted it; the retry sees that and cleans up.
+        Err(e @ (AppError::Database(_) | AppError::Internal | AppError::Conflict(_)))
+            if message.attempt_count < MAX_ATTEMPTS =>
+        {
+            tracing::warn!(scheduled_message_id = %message.id, error = ?e, "Scheduled message failed; will retry");
+            sqlx::query("UPDATE scheduled_messages SET last_error = $2 WHERE id = $1")
+                .bind(message.id)
+                .bind("Temporary error while posting; retrying")
+                .execute(&state.pool)
+                .await?;
+        }
+        Err(e) => {
+            sqlx::query(
+                "UPDATE scheduled_messages
+                 SET status = 'failed', locked_until = NULL, last_error = $2, updated_at = NOW()
+                 WHERE id = $1",
+            )
+            .bind(message.id)
+            .bind(failure_reason(&e))
+            .execute(&state.pool)
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// User-facing reason for a failed scheduled message.
+fn failure_reason(e: &AppError) -> String {
+    match e {
+        AppError::Auth(m)
+        | AppError::Validation(m)
+        | AppError::NotFound(m)
+        | AppError::Conflict(m)
+        | AppError::Forbidden(m) => m.clone(),
+        AppError::Database(_) | AppError::Internal => {
+            "The message could not be posted after several attempts".into()
+        }
+    }
+}
@@ -0,0 +1,656 @@
+//! Embedded selective forwarding unit (SFU) for voice channels.
+//!
+//! # Design
+//!
+//! Without the SFU (the default), voice is full mesh: clients exchange
+//! offers, answers and ICE candidates through the `VOICE_SIGNAL` relay and
+//! every participant uploads one stream per peer. That stops scaling at
+//! around six people. With `VOICE_SFU=true`, each client instead opens a
+//! WebRTC connection to the server, uploads its tracks once, and the SFU
+//! forwards the RTP packets to everyone else in the channel unchanged (no
+//! decoding or mixing).
+//!
+//! Each participant holds two peer connections with the SFU:
+//!
+//! - **publisher** — the client offers and the SFU answers. Carries the
+//!   client's microphone, camera and screen tracks. The client renegotiates
+//!   whenever it adds or removes a track.
+//! - **subscriber** — the SFU offers and the client answers. Carries every
+//!   other participant's tracks; the SFU renegotiates when tracks come and go.
+//!
+//! Having a single offerer per connection means offers can never collide.
+//! A forwarded track keeps the publisher's track id and uses the publisher's
+//! user id as its stream id, so clients can tell whose audio they are
+//! playing.
+//!
+//! The SFU follows `voice_states`: joining another channel or leaving voice
+//! (over REST or by disconnecting) closes the participant's connections, and
+//! while a participant is self- or server-muted their audio is dropped here
+//! rather than trusting the client to stop sending.
+//!
+//! Rooms live in this process, so every participant must be connected to the
+//! same node; `Config::from_env` rejects `VOICE_SFU` with `REPLICA_COUNT > 1`.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+use webrtc::api::interceptor_registry::register_default_interceptors;
+use webrtc::api::media_engine::MediaEngine;
+use webrtc::api::setting_engine::SettingEngine;
+use webrtc::api::{APIBuilder, API};
+use webrtc::ice::udp_network::{EphemeralUDP, UDPNetwork};
+use webrtc::ice_transport::ice_candidate::{RTCIceCandidate, RTCIceCandidateInit};
+use webrtc::ice_transport::ice_candidate_type::RTCIceCandidateType;
+use webrtc::interceptor::registry::Registry;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
+use webrtc::rtp_transceiver::rtp_codec::RTPCodecType;
+use webrtc::rtp_transceiver::rtp_sender::RTCRtpSender;
+use webrtc::track::track_local::track_local_static_rtp::TrackLocalStaticRTP;
+use webrtc::track::track_local::{TrackLocal, TrackLocalWriter};
+use webrtc::track::track_remote::TrackRemote;
+
+use crate::config::SfuConfig;
+use crate::event_bus::{BusMessage, EventBus};
+use crate::models::VoiceState;
+use crate::websocket::events::{GatewayMessage, EVENT_VOICE_SIGNAL};
+
+// ── Signals ───────────────────────────────────────────────────────────────────
+
+/// Which of a participant's two connections a signal belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SfuTransport {
+    Publisher,
+    Subscriber,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SfuSignalType {
+    Offer,
+    Answer,
+    Candidate,
+}
+
+/// A `VOICE_SIGNAL` payload addressed to the SFU rather than another peer.
+///
+/// `candidate` is a JSON-encoded `RTCIceCandidateInit`, the same encoding
+/// clients use for peer-to-peer signals.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SfuSignal {
+    pub transport: SfuTransport,
+    #[serde(rename = "type")]
+    pub signal_type: SfuSignalType,
+    pub sdp: Option<String>,
+    pub candidate: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SfuError {
+    #[error("invalid signal: {0}")]
+    InvalidSignal(&'static str),
+    #[error("no SFU session; send a publisher offer first")]
+    NoSession,
+    #[error(transparent)]
+    WebRtc(#[from] webrtc::Error),
+}
+
+// ── State ─────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct TrackKey {
+    publisher: Uuid,
+    track_id: String,
+}
+
+/// A track a participant is sending, fanned out to the rest of the room.
+struct PublishedTrack {
+    local: Arc<TrackLocalStaticRTP>,
+    kind: RTPCodecType,
+    /// SSRC on the publisher connection, for keyframe requests.
+    ssrc: u32,
+}
+
+struct Peer {
+    publisher: Arc<RTCPeerConnection>,
+    subscriber: Arc<RTCPeerConnection>,
+    /// Senders on `subscriber`, one per forwarded track.
+    senders: HashMap<TrackKey, Arc<RTCRtpSender>>,
+    /// A subscriber offer is waiting for the client's answer.
+    offer_pending: bool,
+    /// Tracks changed while an offer was pending; offer again on answer.
+    renegotiate: bool,
+    /// Whether the subscriber connection has ever been offered.
+    negotiated: bool,
+    /// Drop this participant's audio (self- or server-muted).
+    muted: Arc<AtomicBool>,
+}
+
+#[derive(Default)]
+struct Room {
+    peers: HashMap<Uuid, Peer>,
+    tracks: HashMap<TrackKey, PublishedTrack>,
+}
+
+#[derive(Default)]
+struct Rooms {
+    /// Keyed by voice channel ID.
+    rooms: HashMap<Uuid, Room>,
+    /// Participant → the channel their peer belongs to.
+    channel_of: HashMap<Uuid, Uuid>,
+}
+
+/// The embedded SFU. Build once with [`Sfu::new`] and share the `Arc`.
+pub struct Sfu {
+    api: API,
+    events: Arc<dyn EventBus>,
+    rooms: Mutex<Rooms>,
+}
+
+impl Sfu {
+    pub fn new(config: &SfuConfig, events: Arc<dyn EventBus>) -> Result<Arc<Self>, SfuError> {
+        let mut media_engine = MediaEngine::default();
+        media_engine.register_default_codecs()?;
+        let registry = register_default_interceptors(Registry::new(), &mut media_engine)?;
+
+        let mut settings = SettingEngine::default();
+        if let Some((min, max)) = config.udp_ports {
+            let ports = EphemeralUDP::new(min, max).map_err(webrtc::Error::from)?;
+            settings.set_udp_network(UDPNetwork::Ephemeral(ports));
+        }
+        if !config.public_ips.is_empty() {
+            settings.set_nat_1to1_ips(config.public_ips.clone(), RTCIceCandidateType::Host);
+        }
+
+        let api = APIBuilder::new()
+            .with_media_engine(media_engine)
+            .with_interceptor_registry(registry)
+            .with_setting_engine(settings)
+            .build();
+
+        Ok(Arc::new(Sfu {
+            api,
+            events,
+            rooms: Mutex::new(Rooms::default()),
+        }))
+    }
+
+    /// Apply a signal from a participant currently in `voice.channel_id`.
+    ///
+    /// A publisher offer from someone without a session (or with a session
+    /// in another channel) starts a fresh one.
+    pub async fn handle_signal(
+        self: &Arc<Self>,
+        voice: &VoiceState,
+        signal: SfuSignal,
+    ) -> Result<(), SfuError> {
+        let user_id = voice.user_id;
+        match (signal.transport, signal.signal_type) {
+            (SfuTransport::Publisher, SfuSignalType::Offer) => {
+                let sdp = signal
+                    .sdp
+                    .ok_or(SfuError::InvalidSignal("offer without sdp"))?;
+                let offer = RTCSessionDescription::offer(sdp)?;
+                let muted = voice.self_mute || voice.server_mute;
+                self.publisher_offer(user_id, voice.channel_id, muted, offer)
+                    .await
+            }
+            (SfuTransport::Subscriber, SfuSignalType::Answer) => {
+                let sdp = signal
+                    .sdp
+                    .ok_or(SfuError::InvalidSignal("answer without sdp"))?;
+                self.subscriber_answer(user_id, sdp).await
+            }
+            (transport, SfuSignalType::Candidate) => {
+                let candidate: RTCIceCandidateInit = signal
+                    .candidate
+                    .as_deref()
+                    .and_then(|c| serde_json::from_str(c).ok())
+                    .ok_or(SfuError::InvalidSignal("malformed candidate"))?;
+                let pc = {
+                    let rooms = self.rooms.lock().await;
+                    let peer = rooms.peer(user_id).ok_or(SfuError::NoSession)?;
+                    match transport {
+                        SfuTransport::Publisher => peer.publisher.clone(),
+                        SfuTransport::Subscriber => peer.subscriber.clone(),
+                    }
+                };
+                pc.add_ice_candidate(candidate).await?;
+                Ok(())
+            }
+            (SfuTransport::Publisher, _) => Err(SfuError::InvalidSignal(
+                "the client offers on the publisher connection",
+            )),
+            (SfuTransport::Subscriber, _) => Err(SfuError::InvalidSignal(
+                "the SFU offers on the subscriber connection",
+            )),
+        }
+    }
+
+    /// Close a participant's connections if they are not in `channel_id`.
+    ///
+    /// Called when a user joins a voice channel: a session left over from
+    /// their previous channel must not keep forwarding media.
+    pub async fn retain_channel(self: &Arc<Self>, user_id: Uuid, channel_id: Uuid) {
+        let current = self.rooms.lock().await.channel_of.get(&user_id).copied();
+        if current.is_some_and(|c| c != channel_id) {
+            self.remove_peer(user_id).await;
+        }
+    }
+
+    /// Close a participant's connections and stop forwarding their tracks.
+    pub async fn remove_peer(self: &Arc<Self>, user_id: Uuid) {
+        let peer = {
+            let mut rooms = self.rooms.lock().await;
+            let Some(channel_id) = rooms.channel_of.remove(&user_id) else {
+                return;
+            };
+            let Some(room) = rooms.rooms.get_mut(&channel_id) else {
+                return;
+            };
+            let peer = room.peers.remove(&user_id);
+            let keys: Vec<TrackKey> = room
+                .tracks
+                .keys()
+                .filter(|k| k.publisher == user_id)
+                .cloned()
+                .collect();
+            for key in &keys {
+                room.tracks.remove(key);
+            }
+            self.unsubscribe_all(channel_id, room, &keys).await;
+            if room.peers.is_empty() {
+                rooms.rooms.remove(&channel_id);
+            }
+            peer
+        };
+
+        if let Some(peer) = peer {
+            for pc in [peer.publisher, peer.subscriber] {
+                if let Err(e) = pc.close().await {
+                    tracing::debug!(user_id = %user_id, error = ?e, "Error closing SFU peer connection");
+                }
+            }
+            tracing::debug!(user_id = %user_id, "SFU session closed");
+        }
+    }
+
+    /// Apply a voice state change. Only mute affects forwarding.
+    pub async fn update_voice_state(&self, voice: &VoiceState) {
+        let rooms = self.rooms.lock().await;
+        if let Some(peer) = rooms.peer(voice.user_id) {
+            peer.muted
+                .store(voice.self_mute || voice.server_mute, Ordering::Relaxed);
+        }
+    }
+
+    /// Participants with an SFU session in `channel_id`.
+    pub async fn participants(&self, channel_id: Uuid) -> Vec<Uuid> {
+        let rooms = self.rooms.lock().await;
+        rooms
+            .rooms
+            .get(&channel_id)
+            .map(|room| room.peers.keys().copied().collect())
+            .unwrap_or_default()
+    }
+
+    // ── Negotiation ───────────────────────────────────────────────────────────
+
+    async fn publisher_offer(
+        self: &Arc<Self>,
+        user_id: Uuid,
+        channel_id: Uuid,
+        muted: bool,
+        offer: RTCSessionDescription,
+    ) -> Result<(), SfuError> {
+        self.retain_channel(user_id, channel_id).await;
+
+        let mut rooms = self.rooms.lock().await;
+        let publisher = match rooms.peer(user_id) {
+            Some(peer) => peer.publisher.clone(),
+            None => {
+                self.create_peer(&mut rooms, user_id, channel_id, muted)
+                    .await?
+            }
+        };
+
+        publisher.set_remote_description(offer).await?;
+        let answer = publisher.create_answer(None).await?;
+        publisher.set_local_description(answer.clone()).await?;
+        self.send_signal(
+            user_id,
+            SfuTransport::Publisher,
+            serde_json::json!({ "type": "answer", "sdp": answer.sdp }),
+        )
+        .await;
+
+        // A new participant gets everything already being published.
+        if let Some(room) = rooms.rooms.get_mut(&channel_id) {
+            if let Some(peer) = room.peers.get_mut(&user_id) {
+                if !peer.negotiated && !peer.senders.is_empty() {
+                    self.offer_subscriber(user_id, peer).await;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn subscriber_answer(
+        self: &Arc<Self>,
+        user_id: Uuid,
+        sdp: String,
+    ) -> Result<(), SfuError> {
+        let mut rooms = self.rooms.lock().await;
+        let peer = rooms.peer_mut(user_id).ok_or(SfuError::NoSession)?;
+        if !peer.offer_pending {
+            return Err(SfuError::InvalidSignal("no subscriber offer is pending"));
+        }
+        let answer = RTCSessionDescription::answer(sdp)?;
+        peer.subscriber.set_remote_description(answer).await?;
+        peer.offer_pending = false;
+        if peer.renegotiate {
+            peer.renegotiate = false;
+            self.offer_subscriber(user_id, peer).await;
+        }
+        Ok(())
+    }
+
+    /// Send a fresh subscriber offer, or queue one if an offer is in flight.
+    async fn offer_subscriber(&self, user_id: Uuid, peer: &mut Peer) {
+        if peer.offer_pending {
+            peer.renegotiate = true;
+            return;
+        }
+        let offer = match peer.subscriber.create_offer(None).await {
+            Ok(offer) => offer,
+            Err(e) => {
+                tracing::warn!(user_id = %user_id, error = ?e, "Failed to create SFU subscriber offer");
+                return;
+            }
+        };
+        if let Err(e) = peer.subscriber.set_local_description(offer.clone()).await {
+            tracing::warn!(user_id = %user_id, error = ?e, "Failed to apply SFU subscriber offer");
+            return;
+        }
+        peer.offer_pending = true;
+        peer.negotiated = true;
+        self.send_signal(
+            user_id,
+            SfuTransport::Subscriber,
+            serde_json::json!({ "type": "offer", "sdp": offer.sdp }),
+        )
+        .await;
+    }
+
+    async fn create_peer(
+        self: &Arc<Self>,
+        rooms: &mut Rooms,
+        user_id: Uuid,
+        channel_id: Uuid,
+        muted: bool,
+    ) -> Result<Arc<RTCPeerConnection>, SfuError> {
+        let publisher = Arc::new(
+            self.api
+                .new_peer_connection(RTCConfiguration::default())
+                .await?,
+        );
+        let subscriber = Arc::new(
+            self.api
+                .new_peer_connection(RTCConfiguration::default())
+                .await?,
+        );
+        self.forward_candidates(user_id, SfuTransport::Publisher, &publisher);
+        self.forward_candidates(user_id, SfuTransport::Subscriber, &subscriber);
+
+        let muted = Arc::new(AtomicBool::new(muted));
+        let sfu = Arc::downgrade(self);
+        let track_muted = muted.clone();
+        publisher.on_track(Box::new(move |track, _receiver| {
+            if let (Some(track), Some(sfu)) = (track, sfu.upgrade()) {
+                let muted = track_muted.clone();
+                // Registration takes the rooms lock; never do that inside a
+                // webrtc callback.
+                tokio::spawn(async move { sfu.publish(channel_id, user_id, track, muted).await });
+            }
+            Box::pin(async {})
+        }));
+
+        let mut peer = Peer {
+            publisher: publisher.clone(),
+            subscriber,
+            senders: HashMap::new(),
+            offer_pending: false,
+            renegotiate: false,
+            negotiated: false,
+            muted,
+        };
+
+        let room = rooms.rooms.entry(channel_id).or_default();
+        let existing: Vec<TrackKey> = room.tracks.keys().cloned().collect();
+        for key in existing {
+            let track = &room.tracks[&key];
+            if let Some(sender) = subscribe(user_id, &peer, track).await {
+                peer.senders.insert(key.clone(), sender);
+                if let Some(source) = room.peers.get(&key.publisher) {
+                    request_keyframe(source, track).await;
+                }
+            }
+        }
+        room.peers.insert(user_id, peer);
+        rooms.channel_of.insert(user_id, channel_id);
+        tracing::debug!(user_id = %user_id, channel_id = %channel_id, "SFU session opened");
+
+        Ok(publisher)
+    }
+
+    fn forward_candidates(&self, user_id: Uuid, transport: SfuTransport, pc: &RTCPeerConnection) {
+        let events = self.events.clone();
+        pc.on_ice_candidate(Box::new(move |candidate: Option<RTCIceCandidate>| {
+            let events = events.clone();
+            Box::pin(async move {
+                let Some(json) = candidate
+                    .and_then(|c| c.to_json().ok())
+                    .and_then(|c| serde_json::to_string(&c).ok())
+                else {
+                    return;
+                };
+                send_signal(
+                    &*events,
+                    user_id,
+                    transport,
+                    serde_json::json!({ "type": "candidate", "candidate": json }),
+                )
+                .await;
+            })
+        }));
+    }
+
+    // ── Forwarding ────────────────────────────────────────────────────────────
+
+    /// Register a track a participant started sending and forward its RTP
+    /// until the track ends.
+    async fn publish(
+        self: Arc<Self>,
+        channel_id: Uuid,
+        user_id: Uuid,
+        remote: Arc<TrackRemote>,
+        muted: Arc<AtomicBool>,
+    ) {
+        let key = TrackKey {
+            publisher: user_id,
+            track_id: remote.id().await,
+        };
+        let local = Arc::new(TrackLocalStaticRTP::new(
+            remote.codec().await.capability,
+            key.track_id.clone(),
+            user_id.to_string(),
+        ));
+        let track = PublishedTrack {
+            local: local.clone(),
+            kind: remote.kind(),
+            ssrc: remote.ssrc(),
+        };
+
+        {
+            let mut rooms = self.rooms.lock().await;
+            let Some(room) = rooms.rooms.get_mut(&channel_id) else {
+                return;
+            };
+            if !room.peers.contains_key(&user_id) {
+                return;
+            }
+            let mut subscribed = false;
+            let others: Vec<Uuid> = room
+                .peers
+                .keys()
+                .copied()
+                .filter(|id| *id != user_id)
+                .collect();
+            for other in others {
+                let peer = room.peers.get_mut(&other).expect("listed above");
+                if let Some(sender) = subscribe(other, peer, &track).await {
+                    peer.senders.insert(key.clone(), sender);
+                    self.offer_subscriber(other, peer).await;
+                    subscribed = true;
+                }
+            }
+            if let (true, Some(source)) = (subscribed, room.peers.get(&user_id)) {
+                request_keyframe(source, &track).await;
+            }
+            room.tracks.insert(key.clone(), track);
+        }
+        tracing::debug!(user_id = %user_id, track_id = %key.track_id, "SFU track published");
+
+        let is_audio = remote.kind() == RTPCodecType::Audio;
+        while let Ok((packet, _)) = remote.read_rtp().await {
+            if is_audio && muted.load(Ordering::Relaxed) {
+                continue;
+            }
+            // Errors are per subscriber binding (e.g. one closed connection);
+            // the packet still reached everyone else.
+            let _ = local.write_rtp(&packet).await;
+        }
+
+        self.unpublish(channel_id, key).await;
+    }
+
+    /// Stop forwarding a track that ended on the publisher's side.
+    async fn unpublish(self: Arc<Self>, channel_id: Uuid, key: TrackKey) {
+        let mut rooms = self.rooms.lock().await;
+        let Some(room) = rooms.rooms.get_mut(&channel_id) else {
+            return;
+        };
+        if room.tracks.remove(&key).is_some() {
+            self.unsubscribe_all(channel_id, room, std::slice::from_ref(&key))
+                .await;
+        }
+    }
+
+    /// Remove `keys` from every subscriber in `room` and renegotiate.
+    async fn unsubscribe_all(&self, channel_id: Uuid, room: &mut Room, keys: &[TrackKey]) {
+        for (user_id, peer) in room.peers.iter_mut() {
+            let mut changed = false;
+            for key in keys {
+                if let Some(sender) = peer.senders.remove(key) {
+                    if let Err(e) = peer.subscriber.remove_track(&sender).await {
+                        tracing::debug!(
+                            channel_id = %channel_id,
+                            user_id = %user_id,
+                            error = ?e,
+                            "Failed to remove forwarded track"
+                        );
+                    }
+                    changed = true;
+                }
+            }
+            if changed {
+                self.offer_subscriber(*user_id, peer).await;
+            }
+        }
+    }
+
+    async fn send_signal(&self, user_id: Uuid, transport: SfuTransport, data: serde_json::Value) {
+        send_signal(&*self.events, user_id, transport, data).await;
+    }
+}
+
+impl Rooms {
+    fn peer(&self, user_id: Uuid) -> Option<&Peer> {
+        let channel_id = self.channel_of.get(&user_id)?;
+        self.rooms.get(channel_id)?.peers.get(&user_id)
+    }
+
+    fn peer_mut(&mut self, user_id: Uuid) -> Option<&mut Peer> {
+        let channel_id = self.channel_of.get(&user_id)?;
+        self.rooms.get_mut(channel_id)?.peers.get_mut(&user_id)
+    }
+}
+
+/// Add `track` to a participant's subscriber connection.
+async fn subscribe(
+    user_id: Uuid,
+    peer: &Peer,
+    track: &PublishedTrack,
+) -> Option<Arc<RTCRtpSender>> {
+    let local: Arc<dyn TrackLocal + Send + Sync> = track.local.clone();
+    match peer.subscriber.add_track(local).await {
+        Ok(sender) => {
+            // Drain RTCP so the interceptors (NACK, reports) keep running.
+            let rtcp = sender.clone();
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 1500];
+                while rtcp.read(&mut buf).await.is_ok() {}
+            });
+            Some(sender)
+        }
+        Err(e) => {
+            tracing::warn!(user_id = %user_id, error = ?e, "Failed to add forwarded track");
+            None
+        }
+    }
+}
+
+/// Ask the publisher for a keyframe so a new subscriber's video starts
+/// without waiting for the next scheduled one.
+async fn request_keyframe(source: &Peer, track: &PublishedTrack) {
+    if track.kind != RTPCodecType::Video {
+        return;
+    }
+    let pli = PictureLossIndication {
+        sender_ssrc: 0,
+        media_ssrc: track.ssrc,
+    };
+    let _ = source.publisher.write_rtcp(&[Box::new(pli)]).await;
+}
+
+/// Deliver an SFU signal as `VOICE_SIGNAL` with `from_user_id: null`.
+async fn send_signal(
+    events: &dyn EventBus,
+    user_id: Uuid,
+    transport: SfuTransport,
+    data: serde_json::Value,
+) {
+    let mut payload = serde_json::json!({
+        "from_user_id": null,
+        "transport": transport,
+        "sdp": null,
+        "candidate": null,
+        "stream_type": null,
+    });
+    if let (Some(map), serde_json::Value::Object(extra)) = (payload.as_object_mut(), data) {
+        map.extend(extra);
+    }
+    events
+        .publish(BusMessage::Dispatch {
+            user_ids: vec![user_id],
+            event: GatewayMessage::dispatch(EVENT_VOICE_SIGNAL, payload),
+        })
+        .await;
+}
@@ -1,36 +1,26 @@
 use std::collections::HashMap;
 use std::num::NonZeroU32;
-use std::path::PathBuf;
 use std::sync::Arc;
 
-use chrono::{DateTime, Utc};
 use governor::{DefaultKeyedRateLimiter, Quota};
 use reqwest::Client;
-use serde::Serialize;
 use sqlx::PgPool;
 use tokio::sync::RwLock;
-use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::auth::oidc::OidcClient;
+use crate::automod_engine::AutomodCache;
 use crate::config::Config;
+use crate::event_bus::EventBus;
 use crate::handlers::link_preview::LinkPreviewCacheEntry;
+use crate::mail::Mailer;
+use crate::push::PushDispatcher;
+use crate::sfu::Sfu;
+use crate::storage::Storage;
 use crate::webhook_delivery::WebhookQueue;
+use crate::websocket::channel_viewers::ChannelViewerCache;
 use crate::websocket::ConnectionManager;
 
-/// An active Go Live broadcast session within a voice channel.
-///
-/// At most one session exists per channel at a time — enforced by the
-/// `start_go_live` handler under a write lock.
-#[derive(Debug, Clone, Serialize, ToSchema)]
-pub struct GoLiveSession {
-    /// The user currently broadcasting.
-    pub broadcaster_id: Uuid,
-    /// Requested quality tier: "480p", "720p", or "1080p".
-    pub quality: String,
-    /// Wall-clock time the broadcast started (UTC).
-    pub started_at: DateTime<Utc>,
-}
-
 /// Shared application state passed to all handlers and extractors.
 ///
 /// `ConnectionManager` is cheaply cloneable (it wraps an `Arc` internally),
@@ -40,8 +30,9 @@ pub struct AppState {
     pub pool: PgPool,
     pub jwt_secret: Arc<str>,
     pub connections: ConnectionManager,
-    /// Root directory where uploaded files are stored.
-    pub upload_dir: PathBuf,
+    /// Where attachments and custom emoji images are stored: `UPLOAD_DIR` on
+    /// local disk, or an S3-compatible bucket when `Config::s3` is set.
+    pub storage: Arc<dyn Storage>,
     /// In-memory cache for Open Graph link preview metadata.
     ///
     /// Keyed by canonical URL string. Entries older than 24 hours are re-fetched.
@@ -57,24 +48,46 @@ pub struct AppState {
     pub giphy_api_key: Option<Arc<str>>,
     /// Application configuration (includes TURN settings for WebRTC).
     pub config: Arc<Config>,
-    /// Per-bot rate limiter: 50 requests/second per bot user_id.
+    /// Per-bot rate limiter: 50 requests/second per bot user_id, split evenly
+    /// across `Config::replica_count` nodes.
     ///
     /// Uses a dashmap-backed keyed rate limiter so each bot gets an independent
     /// token bucket. Bots share a single `Arc` so cloning `AppState` is cheap.
     pub bot_rate_limiter: Arc<DefaultKeyedRateLimiter<Uuid>>,
-    /// Active Go Live broadcast sessions, keyed by voice channel ID.
-    ///
-    /// At most one session per channel. Protected by a `RwLock` so the common
-    /// read path (viewer fetch) is non-exclusive while start/stop are exclusive.
-    pub go_live_sessions: Arc<RwLock<HashMap<Uuid, GoLiveSession>>>,
-    /// In-memory webhook delivery queue. Enqueue jobs via `webhook_queue.send()`.
+    /// Cached VIEW_CHANNEL recipient lists for channel-scoped broadcasts.
+    /// Invalidate through `websocket::invalidate_channel_viewers` so every
+    /// node drops its copy.
+    pub channel_viewers: ChannelViewerCache,
+    /// Compiled automod configuration per server. Invalidate through
+    /// `handlers::automod::invalidate_automod` so every node drops its copy.
+    pub automod: AutomodCache,
+    /// Durable webhook delivery queue. Enqueue jobs via `webhook_queue.send()`.
     pub webhook_queue: WebhookQueue,
+    /// Cross-node event bus. All gateway dispatches go through here so users
+    /// connected to other replicas receive them too.
+    pub events: Arc<dyn EventBus>,
+    /// Embedded voice SFU, present when `Config::sfu` is set. `None` means
+    /// voice channels use peer-to-peer mesh signaling.
+    pub sfu: Option<Arc<Sfu>>,
+    /// Web Push dispatcher, present when `Config::web_push` is set. Queue
+    /// jobs through `push::notify`.
+    pub push: Option<PushDispatcher>,
+    /// Outgoing email, present when `Config::mail` is set. Send through
+    /// `mail::send`.
+    pub mailer: Option<Mailer>,
+    /// Single sign-on client, present when `Config::oidc` is set. Caches the
+    /// provider's discovery document and signing keys.
+    pub oidc: Option<Arc<OidcClient>>,
 }
 
 impl AppState {
-    /// Construct a fresh per-bot rate limiter capped at 50 requests/second.
-    pub fn new_bot_rate_limiter() -> Arc<DefaultKeyedRateLimiter<Uuid>> {
-        let quota = Quota::per_second(NonZeroU32::new(50).expect("50 is non-zero"));
+    /// Construct a fresh per-bot rate limiter.
+    ///
+    /// Each node enforces its share of the 50 requests/second budget, so the
+    /// aggregate across `replica_count` nodes stays at roughly 50 req/s.
+    pub fn new_bot_rate_limiter(replica_count: u32) -> Arc<DefaultKeyedRateLimiter<Uuid>> {
+        let per_node = (50 / replica_count.max(1)).max(1);
+        let quota = Quota::per_second(NonZeroU32::new(per_node).expect("clamped to >= 1"));
         Arc::new(DefaultKeyedRateLimiter::dashmap(quota))
     }
 }
@@ -0,0 +1,478 @@
+//! Uploads that are still arriving.
+//!
+//! # Design
+//!
+//! Attachments reach the server either in one multipart request
+//! (`POST /messages/:id/attachments`) or as a resumable upload sent in pieces
+//! (`handlers::uploads`). Either way the bytes go to a file under
+//! `UPLOAD_STAGING_DIR` chunk by chunk through a [`StagingFile`], so a 50 MB
+//! video never sits in memory, and are handed to storage only once complete.
+//!
+//! [`StagingFile`] enforces the size limit on every chunk and sniffs the MIME
+//! type from magic bytes as soon as the first [`SNIFF_LEN`] bytes are in, so
+//! a disallowed file is refused without reading the rest of it. Files with no
+//! magic bytes are accepted as `text/plain` only if they are UTF-8 throughout,
+//! which is checked once the file is complete.
+//!
+//! Resumable uploads are rows in the `uploads` table, with their bytes in
+//! `{UPLOAD_STAGING_DIR}/{upload_id}`. [`start_sweeper`] removes them once
+//! they expire.
+
+use std::fmt;
+use std::io::{self, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use sqlx::PgPool;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use uuid::Uuid;
+
+use crate::error::AppError;
+
+/// Bytes read before the MIME type is sniffed. Every allowed format is
+/// recognised well within this.
+pub const SNIFF_LEN: usize = 8192;
+
+/// How long a resumable upload may stay unfinished or unattached.
+pub const UPLOAD_EXPIRY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How often [`start_sweeper`] looks for expired uploads.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Why a [`StagingFile`] refused an upload.
+#[derive(Debug)]
+pub enum UploadError {
+    /// Nothing was uploaded.
+    Empty,
+    /// The upload is larger than this many bytes.
+    TooLarge(u64),
+    /// No magic bytes were recognised and the file is not UTF-8 text.
+    Undetectable,
+    /// The file was sniffed as a type that is not allowed.
+    NotAllowed(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "Files must not be empty"),
+            Self::TooLarge(limit) => {
+                write!(f, "File size exceeds the {} MB limit", limit / 1_048_576)
+            }
+            Self::Undetectable => write!(
+                f,
+                "File type could not be determined. Please upload a supported file type (image, video, audio, PDF, or text)."
+            ),
+            Self::NotAllowed(mime_type) => write!(f, "File type '{mime_type}' is not allowed"),
+            Self::Io(e) => write!(f, "I/O error: {e}"),
+        }
+    }
+}
+
+impl From<io::Error> for UploadError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<UploadError> for AppError {
+    fn from(e: UploadError) -> Self {
+        match e {
+            UploadError::Io(e) => {
+                tracing::error!(error = ?e, "Failed to write staged upload");
+                AppError::Internal
+            }
+            e => AppError::Validation(e.to_string()),
+        }
+    }
+}
+
+/// Path of the staged bytes for upload `id`.
+pub fn staging_path(dir: &Path, id: Uuid) -> PathBuf {
+    dir.join(id.to_string())
+}
+
+/// A file being written to the staging directory one chunk at a time.
+pub struct StagingFile {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    limit: u64,
+    allowed: &'static [&'static str],
+    /// The first bytes of the file, kept until the type is sniffed.
+    head: Vec<u8>,
+    mime_type: Option<String>,
+}
+
+impl StagingFile {
+    /// Start an empty file at `path` that accepts up to `limit` bytes of the
+    /// `allowed` MIME types.
+    pub async fn create(
+        path: PathBuf,
+        limit: u64,
+        allowed: &'static [&'static str],
+    ) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        let file = File::create(&path).await?;
+        Ok(Self {
+            path,
+            file,
+            written: 0,
+            limit,
+            allowed,
+            head: Vec::new(),
+            mime_type: None,
+        })
+    }
+
+    /// Reopen the file at `path` to continue after its first `offset` bytes.
+    /// Anything past `offset`, left by a request that failed before its
+    /// progress was recorded, is discarded.
+    pub async fn resume(
+        path: PathBuf,
+        offset: u64,
+        mime_type: Option<String>,
+        limit: u64,
+        allowed: &'static [&'static str],
+    ) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .await?;
+        if file.metadata().await?.len() < offset {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("staged upload {path:?} is shorter than its offset {offset}"),
+            ));
+        }
+        file.set_len(offset).await?;
+
+        let mut head = Vec::new();
+        if mime_type.is_none() {
+            (&mut file)
+                .take(SNIFF_LEN as u64)
+                .read_to_end(&mut head)
+                .await?;
+        }
+        file.seek(SeekFrom::Start(offset)).await?;
+
+        Ok(Self {
+            path,
+            file,
+            written: offset,
+            limit,
+            allowed,
+            head,
+            mime_type,
+        })
+    }
+
+    /// Bytes in the file so far.
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// The sniffed MIME type, once [`SNIFF_LEN`] bytes are in.
+    pub fn mime_type(&self) -> Option<&str> {
+        self.mime_type.as_deref()
+    }
+
+    /// Append `chunk`. Fails without writing if it would take the file past
+    /// the limit, and as soon as the first bytes show a disallowed type.
+    pub async fn write(&mut self, chunk: &[u8]) -> Result<(), UploadError> {
+        if self.written + chunk.len() as u64 > self.limit {
+            return Err(UploadError::TooLarge(self.limit));
+        }
+
+        if self.mime_type.is_none() {
+            let take = (SNIFF_LEN - self.head.len()).min(chunk.len());
+            self.head.extend_from_slice(&chunk[..take]);
+            if self.head.len() == SNIFF_LEN {
+                self.mime_type = Some(sniff(&self.head, false, self.allowed)?);
+                self.head = Vec::new();
+            }
+        }
+
+        self.file.write_all(chunk).await?;
+        self.written += chunk.len() as u64;
+        Ok(())
+    }
+
+    /// Flush everything written so far to disk.
+    pub async fn sync(&mut self) -> io::Result<()> {
+        self.file.flush().await?;
+        self.file.sync_data().await
+    }
+
+    /// Sync the complete file and settle its type. Returns the size and MIME
+    /// type.
+    pub async fn finish(mut self) -> Result<(u64, String), UploadError> {
+        self.sync().await?;
+        if self.written == 0 {
+            return Err(UploadError::Empty);
+        }
+
+        let mime_type = match self.mime_type.take() {
+            Some(mime_type) => mime_type,
+            None => sniff(&self.head, self.written <= SNIFF_LEN as u64, self.allowed)?,
+        };
+        if mime_type == "text/plain" && self.written > SNIFF_LEN as u64 {
+            validate_text(&self.path).await?;
+        }
+        Ok((self.written, mime_type))
+    }
+}
+
+/// MIME type of a file that starts with `head`, which is the whole file when
+/// `complete`. The client-supplied Content-Type is never consulted, which
+/// prevents stored XSS via disguised HTML uploads.
+fn sniff(head: &[u8], complete: bool, allowed: &[&str]) -> Result<String, UploadError> {
+    let mime_type = match infer::get(head) {
+        Some(t) => t.mime_type(),
+        None if is_utf8(head, complete) => "text/plain",
+        None => {
+            tracing::warn!(
+                sniffed = head.len(),
+                "MIME type could not be detected from magic bytes for binary file"
+            );
+            return Err(UploadError::Undetectable);
+        }
+    };
+    if !allowed.contains(&mime_type) {
+        return Err(UploadError::NotAllowed(mime_type.to_string()));
+    }
+    Ok(mime_type.to_string())
+}
+
+/// Whether `buf` is UTF-8. Unless `complete`, it may stop partway through a
+/// character.
+fn is_utf8(buf: &[u8], complete: bool) -> bool {
+    match std::str::from_utf8(buf) {
+        Ok(_) => true,
+        Err(e) => !complete && e.error_len().is_none(),
+    }
+}
+
+/// Check that the whole file at `path` is UTF-8 without loading it at once.
+async fn validate_text(path: &Path) -> Result<(), UploadError> {
+    let mut file = File::open(path).await?;
+    let mut buf = vec![0; 64 * 1024];
+    // Bytes of a character split across reads, carried to the front of `buf`.
+    let mut carried = 0;
+    loop {
+        let n = file.read(&mut buf[carried..]).await?;
+        if n == 0 {
+            return if carried == 0 {
+                Ok(())
+            } else {
+                Err(UploadError::Undetectable)
+            };
+        }
+        let filled = carried + n;
+        match std::str::from_utf8(&buf[..filled]) {
+            Ok(_) => carried = 0,
+            Err(e) if e.error_len().is_none() => {
+                buf.copy_within(e.valid_up_to()..filled, 0);
+                carried = filled - e.valid_up_to();
+            }
+            Err(_) => return Err(UploadError::Undetectable),
+        }
+    }
+}
+
+// ── Sweeper ───────────────────────────────────────────────────────────────────
+
+/// Spawn the background task that removes expired uploads.
+///
+/// Call once in `main`.
+pub fn start_sweeper(pool: PgPool, staging_dir: PathBuf) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            match sweep(&pool, &staging_dir).await {
+                Ok(0) => {}
+                Ok(n) => tracing::info!(files = n, "Removed expired uploads"),
+                Err(e) => tracing::warn!(error = ?e, "Failed to sweep expired uploads"),
+            }
+        }
+    });
+}
+
+/// Delete expired resumable uploads with their files, and staged files older
+/// than [`UPLOAD_EXPIRY`] that belong to no upload (left by a crash
+/// mid-request). Returns how many files were removed.
+pub async fn sweep(pool: &PgPool, staging_dir: &Path) -> Result<usize, sqlx::Error> {
+    let expired: Vec<Uuid> = sqlx::query_scalar(
+        "DELETE FROM uploads
+         WHERE expires_at < NOW() AND (locked_until IS NULL OR locked_until < NOW())
+         RETURNING id",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut removed = 0;
+    for id in expired {
+        if remove_staged(&staging_path(staging_dir, id)).await {
+            removed += 1;
+        }
+    }
+
+    let mut entries = match tokio::fs::read_dir(staging_dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(removed),
+        Err(e) => {
+            tracing::warn!(error = ?e, dir = ?staging_dir, "Failed to list staging directory");
+            return Ok(removed);
+        }
+    };
+    let mut stale: Vec<(Uuid, PathBuf)> = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let Some(id) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| Uuid::parse_str(name).ok())
+        else {
+            continue;
+        };
+        let old = entry
+            .metadata()
+            .await
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age > UPLOAD_EXPIRY);
+        if old {
+            stale.push((id, entry.path()));
+        }
+    }
+    if stale.is_empty() {
+        return Ok(removed);
+    }
+
+    let ids: Vec<Uuid> = stale.iter().map(|(id, _)| *id).collect();
+    let live: Vec<Uuid> = sqlx::query_scalar("SELECT id FROM uploads WHERE id = ANY($1)")
+        .bind(&ids)
+        .fetch_all(pool)
+        .await?;
+    for (id, path) in stale {
+        if !live.contains(&id) && remove_staged(&path).await {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Remove a staged file, logging failures. Returns whether a file was removed.
+pub async fn remove_staged(path: &Path) -> bool {
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => true,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => false,
+        Err(e) => {
+            tracing::warn!(error = ?e, path = ?path, "Failed to remove staged upload");
+            false
+        }
+    }
+}
+
+// ── Unit tests ────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALLOWED: &[&str] = &["image/png", "text/plain"];
+
+    fn temp_path() -> PathBuf {
+        std::env::temp_dir()
+            .join("together_staging_unit")
+            .join(Uuid::new_v4().to_string())
+    }
+
+    #[test]
+    fn sniff_allows_truncated_utf8_until_complete() {
+        // "é" is two bytes; the head may end between them.
+        let head = "caf\u{e9}".as_bytes();
+        let cut = &head[..head.len() - 1];
+        assert_eq!(sniff(cut, false, ALLOWED).unwrap(), "text/plain");
+        assert!(matches!(
+            sniff(cut, true, ALLOWED),
+            Err(UploadError::Undetectable)
+        ));
+    }
+
+    #[test]
+    fn sniff_rejects_disallowed_types() {
+        let pdf = b"%PDF-1.4\n";
+        assert!(matches!(
+            sniff(pdf, false, ALLOWED),
+            Err(UploadError::NotAllowed(t)) if t == "application/pdf"
+        ));
+    }
+
+    #[tokio::test]
+    async fn staging_file_enforces_limit_per_chunk() {
+        let path = temp_path();
+        let mut file = StagingFile::create(path.clone(), 10, ALLOWED)
+            .await
+            .unwrap();
+        file.write(b"hello").await.unwrap();
+        assert!(matches!(
+            file.write(b"world!").await,
+            Err(UploadError::TooLarge(10))
+        ));
+        assert_eq!(file.written(), 5);
+        tokio::fs::remove_file(path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn staging_file_validates_text_across_reads() {
+        // Multi-byte characters straddle the 64 KiB read boundaries.
+        let text = "\u{6587}".repeat(50_000);
+        let path = temp_path();
+        let mut file = StagingFile::create(path.clone(), 1 << 20, ALLOWED)
+            .await
+            .unwrap();
+        for chunk in text.as_bytes().chunks(1000) {
+            file.write(chunk).await.unwrap();
+        }
+        let (size, mime_type) = file.finish().await.unwrap();
+        assert_eq!(size, text.len() as u64);
+        assert_eq!(mime_type, "text/plain");
+
+        // A stray invalid byte far past the sniffed head is still caught.
+        let mut file = StagingFile::resume(path.clone(), size, None, 1 << 20, ALLOWED)
+            .await
+            .unwrap();
+        file.write(&[0xff]).await.unwrap();
+        assert!(matches!(
+            file.finish().await,
+            Err(UploadError::Undetectable)
+        ));
+        tokio::fs::remove_file(path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn resume_discards_unrecorded_bytes() {
+        let path = temp_path();
+        let mut file = StagingFile::create(path.clone(), 100, ALLOWED)
+            .await
+            .unwrap();
+        file.write(b"hello world").await.unwrap();
+        file.sync().await.unwrap();
+
+        let mut file = StagingFile::resume(path.clone(), 5, None, 100, ALLOWED)
+            .await
+            .unwrap();
+        file.write(b", there").await.unwrap();
+        file.finish().await.unwrap();
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), b"hello, there");
+        tokio::fs::remove_file(path).await.unwrap();
+    }
+}
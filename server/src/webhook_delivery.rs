@@ -1,11 +1,23 @@
-//! In-memory webhook delivery queue with exponential-backoff retry.
+//! Durable webhook delivery queue with exponential-backoff retry.
 //!
 //! # Design
 //!
-//! An unbounded `mpsc` channel carries `DeliveryJob` values from event
-//! handlers to a single background worker task.  The worker spawns one
-//! independent task per delivery job; each task runs up to MAX_ATTEMPTS
-//! attempts sequentially, sleeping between attempts (5 s then 15 s).
+//! Every outbound delivery is a row in `webhook_deliveries`, so queued and
+//! retrying deliveries survive restarts and admins can inspect them through
+//! the deliveries API. Event handlers insert rows through [`WebhookQueue`];
+//! a background worker claims due rows with `FOR UPDATE SKIP LOCKED` (safe
+//! with several replicas polling the same table), makes one HTTP attempt per
+//! claim, records it in `webhook_delivery_attempts`, and either marks the row
+//! `succeeded`, schedules the next attempt with exponential backoff, or moves
+//! it to the `failed` dead-letter state after [`MAX_ATTEMPTS`]. Only
+//! enqueuing checks `webhooks.enabled` up front; deliveries still queued or
+//! retrying when their webhook is disabled are dead-lettered when they come
+//! due instead of being sent.
+//!
+//! A claimed row carries a lease (`locked_until`). If the process dies while
+//! a request is in flight, the lease expires and another worker retries it.
+//!
+//! Completed deliveries are deleted after [`RETENTION_DAYS`].
 //!
 //! # Signature format
 //!
@@ -19,35 +31,37 @@
 //! verification libraries work out of the box.
 
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
 use sqlx::PgPool;
-use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::{Notify, Semaphore};
 use uuid::Uuid;
 
 // ── Public API ────────────────────────────────────────────────────────────────
 
-/// A queued webhook delivery job.
+/// Attempts made before a delivery is dead-lettered as `failed`.
+pub const MAX_ATTEMPTS: i32 = 8;
+/// Completed (succeeded or failed) deliveries are kept this long.
+pub const RETENTION_DAYS: i64 = 30;
+
+/// A webhook delivery to enqueue.
 #[derive(Debug, Clone)]
 pub struct DeliveryJob {
-    /// Webhook DB row id — used to update delivery stats.
+    /// Webhook DB row id. URL and secret are read from it at send time.
     pub webhook_id: Uuid,
-    /// Target URL.
-    pub url: String,
-    /// HMAC-SHA256 signing secret (plaintext).
-    pub secret: String,
+    /// Event name, e.g. `message.created` or `ping`.
+    pub event_type: String,
     /// Serialised JSON event payload.
     pub payload: String,
 }
 
 impl DeliveryJob {
-    pub fn new(webhook_id: Uuid, url: String, secret: String, payload: String) -> Self {
+    pub fn new(webhook_id: Uuid, event_type: impl Into<String>, payload: String) -> Self {
         Self {
             webhook_id,
-            url,
-            secret,
+            event_type: event_type.into(),
             payload,
         }
     }
@@ -55,127 +69,332 @@ impl DeliveryJob {
 
 /// Cheap handle for enqueuing delivery jobs from anywhere in the server.
 #[derive(Clone)]
-pub struct WebhookQueue(Sender<DeliveryJob>);
+pub struct WebhookQueue {
+    pool: PgPool,
+    wake: Arc<Notify>,
+}
 
 impl WebhookQueue {
-    /// Enqueue a delivery job.  Uses `try_send` to avoid blocking the caller.
-    /// If the queue is full the job is dropped (load-shedding) and a warning is logged.
-    pub fn send(&self, job: DeliveryJob) {
-        if let Err(e) = self.0.try_send(job) {
-            match e {
-                tokio::sync::mpsc::error::TrySendError::Full(_) => {
-                    tracing::warn!("Webhook delivery queue is full; dropping job");
-                }
-                tokio::sync::mpsc::error::TrySendError::Closed(_) => {
-                    // Worker has stopped (shutdown); silently drop.
-                }
-            }
+    /// Persist a delivery and wake the local worker. Returns the delivery id.
+    pub async fn send(&self, job: DeliveryJob) -> Result<Uuid, sqlx::Error> {
+        let id: Uuid = sqlx::query_scalar(
+            "INSERT INTO webhook_deliveries (webhook_id, event_type, payload)
+             VALUES ($1, $2, $3)
+             RETURNING id",
+        )
+        .bind(job.webhook_id)
+        .bind(&job.event_type)
+        .bind(&job.payload)
+        .fetch_one(&self.pool)
+        .await?;
+        self.wake.notify_one();
+        Ok(id)
+    }
+
+    /// Queue a fresh delivery with the same webhook and payload as
+    /// `delivery_id`. Returns the new delivery id, or `None` if the original
+    /// does not exist.
+    pub async fn redeliver(&self, delivery_id: Uuid) -> Result<Option<Uuid>, sqlx::Error> {
+        let id: Option<Uuid> = sqlx::query_scalar(
+            "INSERT INTO webhook_deliveries (webhook_id, event_type, payload, redelivery_of)
+             SELECT webhook_id, event_type, payload, id
+             FROM webhook_deliveries WHERE id = $1
+             RETURNING id",
+        )
+        .bind(delivery_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        if id.is_some() {
+            self.wake.notify_one();
         }
+        Ok(id)
     }
 }
 
 /// Spawn the background delivery worker and return a queue handle.
 ///
-/// Call once in `main` after the DB pool and HTTP client are ready.
+/// Call once in `main` after the DB pool and HTTP client are ready. Deliveries
+/// left pending by a previous run are picked up immediately.
 pub fn start_worker(pool: PgPool, http_client: reqwest::Client) -> WebhookQueue {
-    let (tx, rx) = mpsc::channel(10_000);
-    tokio::spawn(run_worker(rx, pool, http_client));
-    WebhookQueue(tx)
+    let wake = Arc::new(Notify::new());
+    tokio::spawn(run_worker(pool.clone(), http_client, wake.clone()));
+    tokio::spawn(run_sweeper(pool.clone()));
+    WebhookQueue { pool, wake }
+}
+
+/// Delay before the next attempt after `attempts` failed attempts:
+/// 10 s, 20 s, 40 s, … capped at one hour.
+pub fn backoff_delay(attempts: i32) -> Duration {
+    let exp = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    Duration::from_secs((BASE_BACKOFF_SECS << exp).min(MAX_BACKOFF_SECS))
 }
 
 // ── Internal worker ───────────────────────────────────────────────────────────
 
-const MAX_ATTEMPTS: u8 = 3;
-/// Delay before attempt N (0-indexed): 5 s before attempt 2, 15 s before attempt 3.
-const RETRY_DELAYS_SECS: [u64; 2] = [5, 15];
-
-async fn run_worker(mut rx: Receiver<DeliveryJob>, pool: PgPool, http_client: reqwest::Client) {
-    while let Some(job) = rx.recv().await {
-        let pool = pool.clone();
-        let client = http_client.clone();
-        // Each job runs in its own task; retries happen sequentially within
-        // that task (sleep between attempts) rather than via nested spawns,
-        // which keeps the future Send.
-        tokio::spawn(run_job(job, pool, client));
-    }
+const BASE_BACKOFF_SECS: u64 = 10;
+const MAX_BACKOFF_SECS: u64 = 3600;
+/// How long a claimed delivery is reserved; well above the request timeout.
+const LEASE_SECS: f64 = 60.0;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Fallback poll for retries coming due and rows enqueued on other nodes.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Maximum concurrent HTTP requests per node.
+const MAX_IN_FLIGHT: usize = 32;
+const SWEEP_INTERVAL: Duration = Duration::from_secs(3600);
+
+#[derive(sqlx::FromRow)]
+struct ClaimedDelivery {
+    id: Uuid,
+    webhook_id: Uuid,
+    event_type: String,
+    payload: String,
+    attempt_count: i32,
+    url: String,
+    secret: String,
 }
 
-/// Execute a single delivery job, retrying on failure up to MAX_ATTEMPTS times.
-async fn run_job(job: DeliveryJob, pool: PgPool, client: reqwest::Client) {
-    let mut last_success = false;
-
-    for attempt in 1u8..=MAX_ATTEMPTS {
-        // Sleep before retry attempts.
-        if attempt > 1 {
-            let delay = RETRY_DELAYS_SECS[(attempt - 2) as usize];
-            tracing::info!(
-                webhook_id = %job.webhook_id,
-                attempt,
-                retry_delay_secs = delay,
-                "Webhook delivery failed; retrying"
-            );
-            tokio::time::sleep(Duration::from_secs(delay)).await;
+async fn run_worker(pool: PgPool, http_client: reqwest::Client, wake: Arc<Notify>) {
+    let permits = Arc::new(Semaphore::new(MAX_IN_FLIGHT));
+    loop {
+        let available = permits.available_permits();
+        if available > 0 {
+            match claim_due(&pool, available as i64).await {
+                Ok(claimed) => {
+                    let saturated = claimed.len() == available;
+                    for delivery in claimed {
+                        let permit = permits
+                            .clone()
+                            .acquire_owned()
+                            .await
+                            .expect("delivery semaphore is never closed");
+                        let pool = pool.clone();
+                        let client = http_client.clone();
+                        tokio::spawn(async move {
+                            run_attempt(delivery, &pool, &client).await;
+                            drop(permit);
+                        });
+                    }
+                    // More rows may already be due; claim again right away.
+                    if saturated {
+                        continue;
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(error = ?e, "Failed to claim webhook deliveries");
+                }
+            }
         }
 
-        last_success = attempt_delivery(&job, attempt, &client).await;
-        if last_success {
-            break;
+        tokio::select! {
+            _ = wake.notified() => {}
+            _ = tokio::time::sleep(POLL_INTERVAL) => {}
         }
     }
+}
 
-    if last_success {
-        tracing::debug!(webhook_id = %job.webhook_id, "Webhook delivered successfully");
-        if let Err(e) = sqlx::query(
-            "UPDATE webhooks SET delivery_failures = 0, last_used_at = NOW() WHERE id = $1",
-        )
-        .bind(job.webhook_id)
-        .execute(&pool)
-        .await
-        {
-            tracing::warn!(webhook_id = %job.webhook_id, error = ?e, "Failed to update webhook delivery stats");
-        }
-    } else {
+/// Claim up to `limit` due deliveries (pending and due, or in flight with an
+/// expired lease) of enabled webhooks and count the attempt about to be made.
+///
+/// Due deliveries of a webhook that was disabled after they were queued are
+/// moved to `failed` instead, where they can still be redelivered by hand.
+async fn claim_due(pool: &PgPool, limit: i64) -> Result<Vec<ClaimedDelivery>, sqlx::Error> {
+    sqlx::query(
+        "UPDATE webhook_deliveries d
+         SET status = 'failed', locked_until = NULL, completed_at = NOW()
+         FROM webhooks w
+         WHERE w.id = d.webhook_id
+           AND NOT w.enabled
+           AND ((d.status = 'pending' AND d.next_attempt_at <= NOW())
+                OR (d.status = 'in_flight' AND d.locked_until < NOW()))",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query_as::<_, ClaimedDelivery>(
+        "WITH due AS (
+             SELECT d.id FROM webhook_deliveries d
+             JOIN webhooks w ON w.id = d.webhook_id
+             WHERE ((d.status = 'pending' AND d.next_attempt_at <= NOW())
+                 OR (d.status = 'in_flight' AND d.locked_until < NOW()))
+               AND w.enabled
+             ORDER BY d.next_attempt_at
+             LIMIT $1
+             FOR UPDATE OF d SKIP LOCKED
+         )
+         UPDATE webhook_deliveries d
+         SET status = 'in_flight',
+             locked_until = NOW() + make_interval(secs => $2),
+             attempt_count = d.attempt_count + 1
+         FROM due, webhooks w
+         WHERE d.id = due.id AND w.id = d.webhook_id
+         RETURNING d.id, d.webhook_id, d.event_type, d.payload, d.attempt_count, w.url, w.secret",
+    )
+    .bind(limit)
+    .bind(LEASE_SECS)
+    .fetch_all(pool)
+    .await
+}
+
+/// Make one attempt for a claimed delivery and record the outcome.
+async fn run_attempt(delivery: ClaimedDelivery, pool: &PgPool, client: &reqwest::Client) {
+    let started = Instant::now();
+    let result = attempt_delivery(&delivery, client).await;
+    let latency_ms = started.elapsed().as_millis().min(i32::MAX as u128) as i32;
+
+    let (response_status, error) = match &result {
+        Ok(status) => (Some(*status as i32), None),
+        Err(e) => (None, Some(e.clone())),
+    };
+    let succeeded = matches!(result, Ok(status) if (200..300).contains(&status));
+    let exhausted = !succeeded && delivery.attempt_count >= MAX_ATTEMPTS;
+
+    if let Err(e) = record_attempt(
+        pool,
+        &delivery,
+        response_status,
+        error,
+        latency_ms,
+        succeeded,
+        exhausted,
+    )
+    .await
+    {
+        // The lease will expire and the delivery will be retried.
+        tracing::warn!(delivery_id = %delivery.id, error = ?e, "Failed to record webhook attempt");
+        return;
+    }
+
+    let stats = if succeeded {
+        tracing::debug!(delivery_id = %delivery.id, "Webhook delivered successfully");
+        "UPDATE webhooks SET delivery_failures = 0, last_used_at = NOW() WHERE id = $1"
+    } else if exhausted {
         tracing::warn!(
-            webhook_id = %job.webhook_id,
+            delivery_id = %delivery.id,
+            webhook_id = %delivery.webhook_id,
             "Webhook delivery exhausted all retries"
         );
-        if let Err(e) = sqlx::query(
-            "UPDATE webhooks SET delivery_failures = delivery_failures + 1 WHERE id = $1",
-        )
-        .bind(job.webhook_id)
-        .execute(&pool)
+        "UPDATE webhooks SET delivery_failures = delivery_failures + 1 WHERE id = $1"
+    } else {
+        tracing::info!(
+            delivery_id = %delivery.id,
+            attempt = delivery.attempt_count,
+            retry_delay_secs = backoff_delay(delivery.attempt_count).as_secs(),
+            "Webhook delivery failed; retrying"
+        );
+        return;
+    };
+
+    if let Err(e) = sqlx::query(stats)
+        .bind(delivery.webhook_id)
+        .execute(pool)
         .await
-        {
-            tracing::warn!(webhook_id = %job.webhook_id, error = ?e, "Failed to update webhook delivery stats");
-        }
+    {
+        tracing::warn!(webhook_id = %delivery.webhook_id, error = ?e, "Failed to update webhook delivery stats");
     }
 }
 
-/// Make one HTTP POST attempt. Returns `true` on a 2xx response.
-async fn attempt_delivery(job: &DeliveryJob, attempt: u8, client: &reqwest::Client) -> bool {
-    let signature = sign_payload(&job.secret, job.payload.as_bytes());
+async fn record_attempt(
+    pool: &PgPool,
+    delivery: &ClaimedDelivery,
+    response_status: Option<i32>,
+    error: Option<String>,
+    latency_ms: i32,
+    succeeded: bool,
+    exhausted: bool,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "INSERT INTO webhook_delivery_attempts
+             (delivery_id, attempt, response_status, error, latency_ms)
+         VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(delivery.id)
+    .bind(delivery.attempt_count)
+    .bind(response_status)
+    .bind(error)
+    .bind(latency_ms)
+    .execute(&mut *tx)
+    .await?;
+
+    if succeeded || exhausted {
+        sqlx::query(
+            "UPDATE webhook_deliveries
+             SET status = $2, locked_until = NULL, completed_at = NOW()
+             WHERE id = $1",
+        )
+        .bind(delivery.id)
+        .bind(if succeeded { "succeeded" } else { "failed" })
+        .execute(&mut *tx)
+        .await?;
+    } else {
+        sqlx::query(
+            "UPDATE webhook_deliveries
+             SET status = 'pending', locked_until = NULL,
+                 next_attempt_at = NOW() + make_interval(secs => $2)
+             WHERE id = $1",
+        )
+        .bind(delivery.id)
+        .bind(backoff_delay(delivery.attempt_count).as_secs_f64())
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await
+}
+
+/// Make one HTTP POST attempt. Returns the response status, or a description
+/// of the transport error when no response was received.
+async fn attempt_delivery(
+    delivery: &ClaimedDelivery,
+    client: &reqwest::Client,
+) -> Result<u16, String> {
+    let signature = sign_payload(&delivery.secret, delivery.payload.as_bytes());
 
     let result = client
-        .post(&job.url)
+        .post(&delivery.url)
         .header("Content-Type", "application/json")
         .header("X-Together-Signature-256", &signature)
-        .header("X-Together-Hook-ID", job.webhook_id.to_string())
-        .header("X-Together-Delivery-Attempt", attempt.to_string())
-        .body(job.payload.clone())
-        .timeout(Duration::from_secs(10))
+        .header("X-Together-Hook-ID", delivery.webhook_id.to_string())
+        .header("X-Together-Delivery-ID", delivery.id.to_string())
+        .header("X-Together-Event", &delivery.event_type)
+        .header(
+            "X-Together-Delivery-Attempt",
+            delivery.attempt_count.to_string(),
+        )
+        .body(delivery.payload.clone())
+        .timeout(REQUEST_TIMEOUT)
         .send()
         .await;
 
     match result {
-        Ok(resp) => resp.status().is_success(),
+        Ok(resp) => Ok(resp.status().as_u16()),
         Err(e) => {
             tracing::warn!(
-                webhook_id = %job.webhook_id,
-                attempt,
+                delivery_id = %delivery.id,
+                attempt = delivery.attempt_count,
                 error = %e,
                 "Webhook HTTP request failed"
             );
-            false
+            Err(e.to_string())
+        }
+    }
+}
+
+/// Periodically delete completed deliveries past the retention window.
+async fn run_sweeper(pool: PgPool) {
+    let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = sqlx::query(
+            "DELETE FROM webhook_deliveries
+             WHERE completed_at < NOW() - make_interval(days => $1)",
+        )
+        .bind(RETENTION_DAYS as i32)
+        .execute(&pool)
+        .await
+        {
+            tracing::warn!(error = ?e, "Failed to sweep old webhook deliveries");
         }
     }
 }
@@ -194,44 +413,14 @@ fn sign_payload(secret: &str, body: &[u8]) -> String {
 
 // ── Fire helpers (called from event handlers) ─────────────────────────────────
 
-/// Query all enabled webhooks for a server that subscribe to `event_type`,
-/// then enqueue a delivery job for each one.
+/// Queue a delivery of `event_type` for every enabled webhook on the server
+/// that subscribes to it.
 pub async fn fire_event(
     queue: &WebhookQueue,
-    pool: &PgPool,
     server_id: Uuid,
     event_type: &str,
     payload: serde_json::Value,
 ) {
-    #[derive(sqlx::FromRow)]
-    struct WebhookRow {
-        id: Uuid,
-        url: String,
-        secret: String,
-    }
-
-    let webhooks: Vec<WebhookRow> = match sqlx::query_as::<_, WebhookRow>(
-        "SELECT id, url, secret FROM webhooks
-         WHERE server_id = $1
-           AND enabled = TRUE
-           AND event_types @> to_jsonb($2::text)",
-    )
-    .bind(server_id)
-    .bind(event_type)
-    .fetch_all(pool)
-    .await
-    {
-        Ok(rows) => rows,
-        Err(e) => {
-            tracing::error!(error = ?e, "Failed to query webhooks for event dispatch");
-            return;
-        }
-    };
-
-    if webhooks.is_empty() {
-        return;
-    }
-
     let envelope = serde_json::json!({
         "event": event_type,
         "server_id": server_id,
@@ -246,9 +435,24 @@ pub async fn fire_event(
         }
     };
 
-    let body = Arc::new(body);
-    for wh in webhooks {
-        queue.send(DeliveryJob::new(wh.id, wh.url, wh.secret, (*body).clone()));
+    match sqlx::query(
+        "INSERT INTO webhook_deliveries (webhook_id, event_type, payload)
+         SELECT id, $2, $3 FROM webhooks
+         WHERE server_id = $1
+           AND enabled = TRUE
+           AND event_types @> to_jsonb($2::text)",
+    )
+    .bind(server_id)
+    .bind(event_type)
+    .bind(&body)
+    .execute(&queue.pool)
+    .await
+    {
+        Ok(r) if r.rows_affected() > 0 => queue.wake.notify_one(),
+        Ok(_) => {}
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to queue webhook deliveries for event dispatch");
+        }
     }
 }
 
@@ -279,4 +483,16 @@ mod tests {
         let b = sign_payload("key2", b"body");
         assert_ne!(a, b);
     }
+
+    #[test]
+    fn backoff_doubles_from_ten_seconds() {
+        assert_eq!(backoff_delay(1), Duration::from_secs(10));
+        assert_eq!(backoff_delay(2), Duration::from_secs(20));
+        assert_eq!(backoff_delay(3), Duration::from_secs(40));
+    }
+
+    #[test]
+    fn backoff_is_capped_at_one_hour() {
+        assert_eq!(backoff_delay(MAX_ATTEMPTS + 20), Duration::from_secs(3600));
+    }
 }
@@ -0,0 +1,133 @@
+//! Per-node cache of which server members can view a channel.
+//!
+//! Channel-scoped gateway events (messages, reactions, pins, typing) are only
+//! delivered to members whose effective permissions include VIEW_CHANNEL.
+//! Resolving that for every event would hit the database on each keystroke's
+//! TYPING_START, so the resolved recipient list is cached per channel.
+//!
+//! Entries are dropped for a whole server whenever something that feeds
+//! permission resolution changes (overrides, roles, role assignments,
+//! membership). Those invalidations travel over the event bus so every node
+//! drops its copy; the TTL only bounds staleness if an invalidation is lost.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// Upper bound on how long a cached recipient list is trusted.
+pub const VIEWER_CACHE_TTL: Duration = Duration::from_secs(60);
+
+struct Entry {
+    server_id: Uuid,
+    viewers: Arc<[Uuid]>,
+    cached_at: Instant,
+}
+
+#[derive(Clone, Default)]
+pub struct ChannelViewerCache {
+    entries: Arc<RwLock<HashMap<Uuid, Entry>>>,
+    /// Bumped on every invalidation so a fill that raced with one is discarded.
+    generation: Arc<AtomicU64>,
+}
+
+impl ChannelViewerCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the cached viewers of `channel_id` if present and fresh.
+    pub async fn get(&self, channel_id: Uuid) -> Option<Arc<[Uuid]>> {
+        let entries = self.entries.read().await;
+        entries
+            .get(&channel_id)
+            .filter(|e| e.cached_at.elapsed() < VIEWER_CACHE_TTL)
+            .map(|e| e.viewers.clone())
+    }
+
+    /// Token to pass to [`insert`](Self::insert); call before querying the
+    /// database so an invalidation that lands mid-query is not overwritten.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Cache a freshly resolved viewer list and return it.
+    ///
+    /// The entry is not stored if the cache was invalidated since
+    /// `generation` was taken.
+    pub async fn insert(
+        &self,
+        server_id: Uuid,
+        channel_id: Uuid,
+        viewers: Vec<Uuid>,
+        generation: u64,
+    ) -> Arc<[Uuid]> {
+        let viewers: Arc<[Uuid]> = viewers.into();
+        let mut entries = self.entries.write().await;
+        if self.generation.load(Ordering::Acquire) == generation {
+            entries.insert(
+                channel_id,
+                Entry {
+                    server_id,
+                    viewers: viewers.clone(),
+                    cached_at: Instant::now(),
+                },
+            );
+        }
+        viewers
+    }
+
+    /// Drop every cached channel belonging to `server_id`.
+    pub async fn invalidate_server(&self, server_id: Uuid) {
+        let mut entries = self.entries.write().await;
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        entries.retain(|_, e| e.server_id != server_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn insert_then_get_returns_viewers() {
+        let cache = ChannelViewerCache::new();
+        let (server, channel, user) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let gen = cache.generation();
+        cache.insert(server, channel, vec![user], gen).await;
+        assert_eq!(&*cache.get(channel).await.unwrap(), &[user]);
+    }
+
+    #[tokio::test]
+    async fn invalidate_server_only_drops_that_server() {
+        let cache = ChannelViewerCache::new();
+        let (s1, s2) = (Uuid::new_v4(), Uuid::new_v4());
+        let (c1, c2) = (Uuid::new_v4(), Uuid::new_v4());
+        let gen = cache.generation();
+        cache.insert(s1, c1, vec![], gen).await;
+        cache.insert(s2, c2, vec![], gen).await;
+
+        cache.invalidate_server(s1).await;
+
+        assert!(cache.get(c1).await.is_none());
+        assert!(cache.get(c2).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn fill_racing_an_invalidation_is_discarded() {
+        let cache = ChannelViewerCache::new();
+        let (server, channel) = (Uuid::new_v4(), Uuid::new_v4());
+        let gen = cache.generation();
+        cache.invalidate_server(server).await;
+
+        let returned = cache
+            .insert(server, channel, vec![Uuid::new_v4()], gen)
+            .await;
+
+        assert_eq!(returned.len(), 1, "caller still gets the list it resolved");
+        assert!(cache.get(channel).await.is_none());
+    }
+}
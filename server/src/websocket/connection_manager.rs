@@ -1,22 +1,125 @@
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
 
 use tokio::sync::{mpsc, RwLock};
 use uuid::Uuid;
 
-/// `(connection_id, sender)` stored per user in the connection map.
-type ConnEntry = (Uuid, mpsc::UnboundedSender<String>);
+use super::events::GatewayMessage;
 
-/// Tracks active WebSocket connections keyed by user ID.
+/// How long a disconnected session keeps buffering events for a RESUME.
+pub const RESUME_WINDOW: Duration = Duration::from_secs(120);
+
+/// Maximum number of dispatches retained per session for replay. Once a
+/// session falls further behind than this, RESUME fails and the client
+/// receives a fresh READY instead.
+pub const REPLAY_BUFFER_CAPACITY: usize = 500;
+
+/// How often [`ConnectionManager::start_pruning`] drops expired sessions.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A single gateway session and its replay buffer.
+///
+/// The session outlives the socket it was created on: when the socket drops
+/// the sender is cleared and dispatches keep accumulating in `buffer` until
+/// the client resumes or `RESUME_WINDOW` elapses.
+struct Session {
+    /// Outbound channel of the attached socket, or `None` while detached.
+    tx: Option<mpsc::UnboundedSender<String>>,
+    /// `(seq, serialized dispatch)` pairs in ascending `seq` order.
+    buffer: VecDeque<(u64, Arc<str>)>,
+    /// Highest sequence number evicted from `buffer` because of the capacity
+    /// cap. A client that last saw a seq below this cannot be resumed.
+    evicted_through: u64,
+    /// When the socket dropped; `None` while attached.
+    detached_at: Option<Instant>,
+}
+
+impl Session {
+    fn new(tx: mpsc::UnboundedSender<String>) -> Self {
+        Self {
+            tx: Some(tx),
+            buffer: VecDeque::new(),
+            evicted_through: 0,
+            detached_at: None,
+        }
+    }
+
+    fn is_live(&self) -> bool {
+        self.tx.is_some()
+    }
+
+    fn is_expired(&self, now: Instant) -> bool {
+        self.detached_at
+            .is_some_and(|at| now.duration_since(at) >= RESUME_WINDOW)
+    }
+
+    /// Record a dispatch in the replay buffer and forward it if attached.
+    fn push(&mut self, seq: u64, json: &Arc<str>) {
+        if self.buffer.len() >= REPLAY_BUFFER_CAPACITY {
+            if let Some((evicted, _)) = self.buffer.pop_front() {
+                self.evicted_through = evicted;
+            }
+        }
+        self.buffer.push_back((seq, Arc::clone(json)));
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(json.to_string());
+        }
+    }
+}
+
+/// A session behind its own lock, so dispatches to different sessions do
+/// not wait for each other.
+type SharedSession = Arc<Mutex<Session>>;
+
+/// Sessions for a single user, keyed by session ID.
+type SessionMap = HashMap<Uuid, SharedSession>;
+
+/// Lock a session. Nothing panics while holding one, but a poisoned lock
+/// still holds a usable session.
+fn lock(session: &SharedSession) -> MutexGuard<'_, Session> {
+    session.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Why a RESUME request could not be honoured. In every case the caller
+/// falls back to a fresh READY.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ResumeError {
+    /// No session with that ID exists for this user (never existed, expired,
+    /// or belongs to someone else).
+    UnknownSession,
+    /// The session is still attached to another socket.
+    SessionActive,
+    /// Events after the client's last seen seq have already been evicted.
+    BufferExpired,
+}
+
+/// Tracks active WebSocket sessions keyed by user ID.
 ///
 /// Cheaply cloneable — all clones share the same underlying map via `Arc`.
 ///
-/// Each connection entry stores a per-connection UUID alongside the sender.
-/// This allows `remove` to be session-aware: a reconnecting user's old
-/// cleanup task will not evict the new connection's entry.
+/// A user may hold any number of concurrent gateway sessions (desktop client,
+/// web tab, bot shard). Each session is identified by a session UUID (sent to
+/// the client in READY) so that `remove` only detaches the session that
+/// actually closed and a later RESUME can pick it back up.
+///
+/// Every DISPATCH is stamped with a sequence number from a single counter and
+/// recorded in each recipient session's replay buffer. Sequence numbers are
+/// strictly increasing within a session but not contiguous, since a session
+/// only sees the events addressed to its user.
+///
+/// # Locking
+///
+/// The outer map lock is only taken for writing to add, detach or resume a
+/// session. Dispatches hold it for reading and lock just the recipient
+/// sessions, always in (user, session) order so two dispatches cannot
+/// deadlock. The sequence number is taken while those locks are held, so
+/// any two dispatches sharing a session reach it in sequence order.
 #[derive(Clone, Default)]
 pub struct ConnectionManager {
-    connections: Arc<RwLock<HashMap<Uuid, ConnEntry>>>,
+    connections: Arc<RwLock<HashMap<Uuid, SessionMap>>>,
+    seq: Arc<AtomicU64>,
 }
 
 impl ConnectionManager {
@@ -24,75 +127,253 @@ impl ConnectionManager {
         Self::default()
     }
 
-    /// Register a new connection for the given user and return its connection ID.
-    ///
-    /// If the user already has a connection (e.g. they reconnected), the old
-    /// sender is replaced. The old send half will be dropped, closing the
-    /// previous connection's outbound channel. This causes the old connection's
-    /// send task to terminate, which triggers its `select!` cleanup path —
-    /// the previous session self-disconnects without any explicit intervention.
+    /// Spawn a background task that drops detached sessions once their
+    /// resume window has elapsed, freeing their replay buffers. The task
+    /// ends when every clone of the manager has been dropped.
+    pub fn start_pruning(&self) {
+        let connections = Arc::downgrade(&self.connections);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let Some(connections) = connections.upgrade() else {
+                    return;
+                };
+                prune_expired(&mut *connections.write().await, Instant::now());
+            }
+        });
+    }
+
+    /// Register a new session for the given user and return its session ID.
     ///
-    /// The returned connection ID must be passed to `remove` so that a stale
-    /// cleanup task cannot evict a newer connection for the same user.
+    /// Existing sessions for the same user are left untouched — events are
+    /// fanned out to every live session.
     pub async fn add(&self, user_id: Uuid, tx: mpsc::UnboundedSender<String>) -> Uuid {
-        let conn_id = Uuid::new_v4();
+        let session_id = Uuid::new_v4();
+        self.attach(user_id, session_id, tx).await;
+        session_id
+    }
+
+    /// Register a session under a caller-chosen ID.
+    ///
+    /// The gateway picks the ID up front so it can be included in READY
+    /// before the session starts receiving events.
+    pub async fn attach(&self, user_id: Uuid, session_id: Uuid, tx: mpsc::UnboundedSender<String>) {
         self.connections
             .write()
             .await
-            .insert(user_id, (conn_id, tx));
-        conn_id
+            .entry(user_id)
+            .or_default()
+            .insert(session_id, Arc::new(Mutex::new(Session::new(tx))));
     }
 
-    /// Remove the connection for the given user, but only if `conn_id` matches
-    /// the currently registered connection.
+    /// Detach a session from its socket.
+    ///
+    /// The session keeps buffering dispatches for `RESUME_WINDOW` so the
+    /// client can RESUME it from a new socket.
     ///
-    /// This guard prevents a reconnecting user's old cleanup task from evicting
-    /// the new connection's sender after `add` has already replaced it.
-    pub async fn remove(&self, user_id: Uuid, conn_id: Uuid) {
-        let mut conns = self.connections.write().await;
-        if let Some((existing_id, _)) = conns.get(&user_id) {
-            if *existing_id == conn_id {
-                conns.remove(&user_id);
+    /// Returns `true` when this left the user with no live sessions, so callers
+    /// can run "user went offline" side effects exactly once. Detaching an
+    /// unknown or already-detached session is a no-op and returns `false`.
+    pub async fn remove(&self, user_id: Uuid, session_id: Uuid) -> bool {
+        // The write lock makes detaching and the liveness check one step, so
+        // two sessions closing at once cannot both report the last one.
+        let conns = self.connections.write().await;
+        let Some(sessions) = conns.get(&user_id) else {
+            return false;
+        };
+        let Some(session) = sessions.get(&session_id) else {
+            return false;
+        };
+        {
+            let mut session = lock(session);
+            if session.tx.take().is_none() {
+                return false;
+            }
+            session.detached_at = Some(Instant::now());
+        }
+        !sessions.values().any(|s| lock(s).is_live())
+    }
+
+    /// Re-attach a detached session to a new socket, queueing every buffered
+    /// dispatch with a sequence number greater than `last_seq` onto `tx`.
+    ///
+    /// Replay and re-attachment happen under the write lock, so no dispatch
+    /// can be lost or reordered between the replayed backlog and live
+    /// traffic. Returns the number of replayed events.
+    pub async fn resume(
+        &self,
+        user_id: Uuid,
+        session_id: Uuid,
+        last_seq: u64,
+        tx: mpsc::UnboundedSender<String>,
+    ) -> Result<usize, ResumeError> {
+        let conns = self.connections.write().await;
+        let mut session = conns
+            .get(&user_id)
+            .and_then(|s| s.get(&session_id))
+            .map(lock)
+            .ok_or(ResumeError::UnknownSession)?;
+        // Expired but not pruned yet is as good as gone.
+        if session.is_expired(Instant::now()) {
+            return Err(ResumeError::UnknownSession);
+        }
+        if session.is_live() {
+            return Err(ResumeError::SessionActive);
+        }
+        if last_seq < session.evicted_through {
+            return Err(ResumeError::BufferExpired);
+        }
+
+        let mut replayed = 0;
+        for (_, json) in session.buffer.iter().filter(|(s, _)| *s > last_seq) {
+            let _ = tx.send(json.to_string());
+            replayed += 1;
+        }
+        session.tx = Some(tx);
+        session.detached_at = None;
+        Ok(replayed)
+    }
+
+    /// The most recently assigned dispatch sequence number.
+    pub fn current_seq(&self) -> u64 {
+        self.seq.load(Ordering::Relaxed)
+    }
+
+    /// Stamp a DISPATCH with the next sequence number, then deliver it to
+    /// every session (live or awaiting resume) of every listed user.
+    pub async fn dispatch_to_users(&self, user_ids: &[Uuid], event: GatewayMessage) {
+        // Serialized once without `s`; the sequence number is spliced in
+        // below once it is known. The envelope always has `op`, so the
+        // object is never empty.
+        let body = match serde_json::to_string(&GatewayMessage { s: None, ..event }) {
+            Ok(json) => json,
+            Err(e) => {
+                tracing::error!(
+                    error = ?e,
+                    "Failed to serialize gateway event; this is a programming error"
+                );
+                return;
             }
+        };
+
+        let conns = self.connections.read().await;
+        let mut targets: Vec<(Uuid, Uuid, &SharedSession)> = user_ids
+            .iter()
+            .filter_map(|user_id| Some((user_id, conns.get(user_id)?)))
+            .flat_map(|(user_id, sessions)| sessions.iter().map(|(id, s)| (*user_id, *id, s)))
+            .collect();
+        targets.sort_unstable_by_key(|(user_id, session_id, _)| (*user_id, *session_id));
+        targets.dedup_by_key(|(user_id, session_id, _)| (*user_id, *session_id));
+
+        let mut sessions: Vec<MutexGuard<'_, Session>> =
+            targets.iter().map(|(_, _, s)| lock(s)).collect();
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed) + 1;
+        let json: Arc<str> = format!("{{\"s\":{seq},{}", &body[1..]).into();
+        for session in &mut sessions {
+            session.push(seq, &json);
         }
     }
 
-    /// Send a JSON-serialized message to a single user.
+    /// Convenience wrapper for `dispatch_to_users` with a single recipient.
+    pub async fn dispatch_to_user(&self, user_id: Uuid, event: GatewayMessage) {
+        self.dispatch_to_users(&[user_id], event).await;
+    }
+
+    /// Send a raw JSON message to every live session of a single user.
     ///
-    /// Silently ignores sends to users who are not connected or whose channel
-    /// has already been closed — a failed send is always non-fatal.
+    /// Unlike `dispatch_to_users` the message is neither sequenced nor
+    /// buffered for replay. Silently ignores sends to users who are not
+    /// connected or whose channel has already been closed.
     pub async fn send_to_user(&self, user_id: Uuid, message: &str) {
+        self.broadcast_to_users(&[user_id], message).await;
+    }
+
+    /// Send a raw JSON message to one specific session only.
+    ///
+    /// Used for replies that belong to a single connection (e.g. HEARTBEAT_ACK)
+    /// and must not be echoed to the user's other devices.
+    pub async fn send_to_connection(&self, user_id: Uuid, session_id: Uuid, message: &str) {
         let conns = self.connections.read().await;
-        if let Some((_, tx)) = conns.get(&user_id) {
-            let _ = tx.send(message.to_owned());
+        if let Some(session) = conns.get(&user_id).and_then(|s| s.get(&session_id)) {
+            if let Some(tx) = &lock(session).tx {
+                let _ = tx.send(message.to_owned());
+            }
         }
     }
 
-    /// Send a JSON-serialized message to every user in the provided list.
+    /// Send a raw JSON message to every live session of every user in the
+    /// provided list. Not sequenced or buffered.
     ///
     /// Stale or disconnected entries are silently skipped.
     pub async fn broadcast_to_users(&self, user_ids: &[Uuid], message: &str) {
         let conns = self.connections.read().await;
         for user_id in user_ids {
-            if let Some((_, tx)) = conns.get(user_id) {
-                let _ = tx.send(message.to_owned());
+            if let Some(sessions) = conns.get(user_id) {
+                for session in sessions.values() {
+                    if let Some(tx) = &lock(session).tx {
+                        let _ = tx.send(message.to_owned());
+                    }
+                }
             }
         }
     }
 
-    /// Returns `true` if the user currently has an active WebSocket connection.
+    /// Returns `true` if the user currently has at least one live WebSocket
+    /// session.
     #[allow(dead_code)]
     pub async fn is_connected(&self, user_id: Uuid) -> bool {
-        self.connections.read().await.contains_key(&user_id)
+        self.connections
+            .read()
+            .await
+            .get(&user_id)
+            .is_some_and(|s| s.values().any(|s| lock(s).is_live()))
     }
 
-    /// Returns the number of currently connected users.
+    /// Returns the number of live sessions the given user currently holds.
+    #[allow(dead_code)]
+    pub async fn session_count(&self, user_id: Uuid) -> usize {
+        self.connections
+            .read()
+            .await
+            .get(&user_id)
+            .map_or(0, |s| s.values().filter(|s| lock(s).is_live()).count())
+    }
+
+    /// Returns the total number of live sessions across all users.
     #[allow(dead_code)]
     pub async fn connection_count(&self) -> usize {
-        self.connections.read().await.len()
+        self.connections
+            .read()
+            .await
+            .values()
+            .flat_map(HashMap::values)
+            .filter(|s| lock(s).is_live())
+            .count()
+    }
+
+    /// Number of sessions held, live or awaiting resume.
+    #[cfg(test)]
+    async fn held_session_count(&self) -> usize {
+        self.connections
+            .read()
+            .await
+            .values()
+            .map(HashMap::len)
+            .sum()
     }
 }
 
+/// Drop detached sessions whose resume window has elapsed, and users left
+/// with no sessions at all.
+fn prune_expired(conns: &mut HashMap<Uuid, SessionMap>, now: Instant) {
+    conns.retain(|_, sessions| {
+        sessions.retain(|_, s| !lock(s).is_expired(now));
+        !sessions.is_empty()
+    });
+}
+
 // ── Unit tests ────────────────────────────────────────────────────────────────
 
 #[cfg(test)]
@@ -149,7 +430,7 @@ mod tests {
 
         // First connection
         let old_conn_id = mgr.add(user, tx1).await;
-        // User reconnects — old sender is replaced
+        // User reconnects before the old session's cleanup has run
         mgr.add(user, tx2).await;
         // Old connection's cleanup fires with the stale conn_id
         mgr.remove(user, old_conn_id).await;
@@ -160,6 +441,59 @@ mod tests {
         assert_eq!(rx2.recv().await.unwrap(), "hello");
     }
 
+    #[tokio::test]
+    async fn multiple_sessions_all_receive_messages() {
+        let mgr = ConnectionManager::new();
+        let user = Uuid::new_v4();
+        let (tx1, mut rx1) = make_channel();
+        let (tx2, mut rx2) = make_channel();
+
+        mgr.add(user, tx1).await;
+        mgr.add(user, tx2).await;
+        assert_eq!(mgr.session_count(user).await, 2);
+
+        mgr.send_to_user(user, "direct").await;
+        mgr.broadcast_to_users(&[user], "fanout").await;
+
+        assert_eq!(rx1.recv().await.unwrap(), "direct");
+        assert_eq!(rx1.recv().await.unwrap(), "fanout");
+        assert_eq!(rx2.recv().await.unwrap(), "direct");
+        assert_eq!(rx2.recv().await.unwrap(), "fanout");
+    }
+
+    #[tokio::test]
+    async fn remove_reports_last_session() {
+        let mgr = ConnectionManager::new();
+        let user = Uuid::new_v4();
+        let (tx1, _rx1) = make_channel();
+        let (tx2, _rx2) = make_channel();
+
+        let c1 = mgr.add(user, tx1).await;
+        let c2 = mgr.add(user, tx2).await;
+
+        assert!(!mgr.remove(user, c1).await, "one session still open");
+        assert!(mgr.is_connected(user).await);
+        assert!(mgr.remove(user, c2).await, "last session closed");
+        assert!(!mgr.is_connected(user).await);
+        // Removing again must not report a second "went offline" transition.
+        assert!(!mgr.remove(user, c2).await);
+    }
+
+    #[tokio::test]
+    async fn send_to_connection_targets_single_session() {
+        let mgr = ConnectionManager::new();
+        let user = Uuid::new_v4();
+        let (tx1, mut rx1) = make_channel();
+        let (tx2, mut rx2) = make_channel();
+
+        let c1 = mgr.add(user, tx1).await;
+        mgr.add(user, tx2).await;
+
+        mgr.send_to_connection(user, c1, "only-one").await;
+        assert_eq!(rx1.recv().await.unwrap(), "only-one");
+        assert!(rx2.try_recv().is_err());
+    }
+
     #[tokio::test]
     async fn send_to_user_delivers_message() {
         let mgr = ConnectionManager::new();
@@ -225,8 +559,199 @@ mod tests {
         mgr.add(u2, tx2).await;
         assert_eq!(mgr.connection_count().await, 2);
 
+        // A second session for the same user counts as another connection.
+        let (tx3, _rx3) = make_channel();
+        mgr.add(u2, tx3).await;
+        assert_eq!(mgr.connection_count().await, 3);
+
         mgr.remove(u1, conn_id1).await;
-        assert_eq!(mgr.connection_count().await, 1);
+        assert_eq!(mgr.connection_count().await, 2);
+    }
+
+    fn parse(frame: &str) -> GatewayMessage {
+        serde_json::from_str(frame).unwrap()
+    }
+
+    #[tokio::test]
+    async fn dispatch_assigns_increasing_sequence_numbers() {
+        let mgr = ConnectionManager::new();
+        let user = Uuid::new_v4();
+        let (tx, mut rx) = make_channel();
+        mgr.add(user, tx).await;
+
+        mgr.dispatch_to_user(user, GatewayMessage::dispatch("A", serde_json::json!({})))
+            .await;
+        mgr.dispatch_to_user(user, GatewayMessage::dispatch("B", serde_json::json!({})))
+            .await;
+
+        let a = parse(&rx.recv().await.unwrap());
+        let b = parse(&rx.recv().await.unwrap());
+        assert_eq!(a.t.as_deref(), Some("A"));
+        assert!(b.s.unwrap() > a.s.unwrap());
+        assert_eq!(mgr.current_seq(), b.s.unwrap());
+    }
+
+    #[tokio::test]
+    async fn resume_replays_events_missed_while_detached() {
+        let mgr = ConnectionManager::new();
+        let user = Uuid::new_v4();
+        let (tx1, mut rx1) = make_channel();
+        let session = mgr.add(user, tx1).await;
+
+        mgr.dispatch_to_user(
+            user,
+            GatewayMessage::dispatch("SEEN", serde_json::json!({})),
+        )
+        .await;
+        let last_seen = parse(&rx1.recv().await.unwrap()).s.unwrap();
+
+        assert!(mgr.remove(user, session).await);
+        mgr.dispatch_to_user(
+            user,
+            GatewayMessage::dispatch("MISSED", serde_json::json!({})),
+        )
+        .await;
+
+        let (tx2, mut rx2) = make_channel();
+        let replayed = mgr.resume(user, session, last_seen, tx2).await.unwrap();
+        assert_eq!(replayed, 1);
+        assert_eq!(
+            parse(&rx2.recv().await.unwrap()).t.as_deref(),
+            Some("MISSED")
+        );
+        assert!(mgr.is_connected(user).await);
+
+        // Live traffic continues on the resumed socket.
+        mgr.dispatch_to_user(
+            user,
+            GatewayMessage::dispatch("LIVE", serde_json::json!({})),
+        )
+        .await;
+        assert_eq!(parse(&rx2.recv().await.unwrap()).t.as_deref(), Some("LIVE"));
+    }
+
+    #[tokio::test]
+    async fn resume_rejects_unknown_live_and_foreign_sessions() {
+        let mgr = ConnectionManager::new();
+        let user = Uuid::new_v4();
+        let (tx, _rx) = make_channel();
+        let session = mgr.add(user, tx).await;
+
+        let (tx2, _rx2) = make_channel();
+        assert_eq!(
+            mgr.resume(user, session, 0, tx2.clone()).await,
+            Err(ResumeError::SessionActive)
+        );
+        assert_eq!(
+            mgr.resume(user, Uuid::new_v4(), 0, tx2.clone()).await,
+            Err(ResumeError::UnknownSession)
+        );
+        mgr.remove(user, session).await;
+        assert_eq!(
+            mgr.resume(Uuid::new_v4(), session, 0, tx2).await,
+            Err(ResumeError::UnknownSession)
+        );
+    }
+
+    #[tokio::test]
+    async fn resume_fails_once_buffer_overflowed() {
+        let mgr = ConnectionManager::new();
+        let user = Uuid::new_v4();
+        let (tx, _rx) = make_channel();
+        let session = mgr.add(user, tx).await;
+        mgr.remove(user, session).await;
+
+        for _ in 0..=REPLAY_BUFFER_CAPACITY {
+            mgr.dispatch_to_user(user, GatewayMessage::dispatch("X", serde_json::json!({})))
+                .await;
+        }
+
+        let (tx2, _rx2) = make_channel();
+        assert_eq!(
+            mgr.resume(user, session, 0, tx2).await,
+            Err(ResumeError::BufferExpired)
+        );
+    }
+
+    #[tokio::test]
+    async fn raw_sends_are_not_sequenced() {
+        let mgr = ConnectionManager::new();
+        let user = Uuid::new_v4();
+        let (tx, mut rx) = make_channel();
+        let session = mgr.add(user, tx).await;
+
+        mgr.send_to_user(user, "raw").await;
+        assert_eq!(rx.recv().await.unwrap(), "raw");
+        mgr.remove(user, session).await;
+
+        let (tx2, _rx2) = make_channel();
+        assert_eq!(mgr.resume(user, session, 0, tx2).await, Ok(0));
+    }
+
+    #[tokio::test]
+    async fn dispatches_reach_each_session_in_sequence_order() {
+        let mgr = ConnectionManager::new();
+        let (u1, u2) = (Uuid::new_v4(), Uuid::new_v4());
+        let (tx1, mut rx1) = make_channel();
+        let (tx2, mut rx2) = make_channel();
+        mgr.add(u1, tx1).await;
+        mgr.add(u2, tx2).await;
+
+        let tasks: Vec<_> = (0..50)
+            .map(|i| {
+                let mgr = mgr.clone();
+                // Overlapping recipient lists, in both orders.
+                let users = if i % 2 == 0 {
+                    vec![u1, u2]
+                } else {
+                    vec![u2, u1, u2]
+                };
+                tokio::spawn(async move {
+                    mgr.dispatch_to_users(
+                        &users,
+                        GatewayMessage::dispatch("X", serde_json::json!({})),
+                    )
+                    .await;
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        for rx in [&mut rx1, &mut rx2] {
+            let mut seqs = Vec::new();
+            while let Ok(frame) = rx.try_recv() {
+                seqs.push(parse(&frame).s.unwrap());
+            }
+            assert_eq!(seqs.len(), 50, "each dispatch delivered once");
+            assert!(seqs.windows(2).all(|w| w[0] < w[1]), "{seqs:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn expired_sessions_are_not_resumed_and_get_pruned() {
+        let mgr = ConnectionManager::new();
+        let user = Uuid::new_v4();
+        let (tx, _rx) = make_channel();
+        let session = mgr.add(user, tx).await;
+        mgr.remove(user, session).await;
+
+        // Backdate the detach past the resume window.
+        {
+            let conns = mgr.connections.read().await;
+            lock(&conns[&user][&session]).detached_at =
+                Some(Instant::now() - RESUME_WINDOW - Duration::from_secs(1));
+        }
+        let (tx2, _rx2) = make_channel();
+        assert_eq!(
+            mgr.resume(user, session, 0, tx2).await,
+            Err(ResumeError::UnknownSession)
+        );
+
+        assert_eq!(mgr.held_session_count().await, 1);
+        prune_expired(&mut *mgr.connections.write().await, Instant::now());
+        assert_eq!(mgr.held_session_count().await, 0);
     }
 
     #[tokio::test]
@@ -2,9 +2,14 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 /// Envelope for all gateway messages (both client→server and server→client).
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GatewayMessage {
     pub op: GatewayOp,
+    /// Sequence number — set on DISPATCH messages only, assigned by
+    /// `ConnectionManager` at fan-out time. Clients echo the last one they
+    /// processed in RESUME.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub s: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub t: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -15,6 +20,7 @@ impl GatewayMessage {
     pub fn dispatch(event_type: &str, data: Value) -> Self {
         Self {
             op: GatewayOp::Dispatch,
+            s: None,
             t: Some(event_type.to_owned()),
             d: Some(data),
         }
@@ -23,6 +29,7 @@ impl GatewayMessage {
     pub fn heartbeat_ack() -> Self {
         Self {
             op: GatewayOp::HeartbeatAck,
+            s: None,
             t: None,
             d: None,
         }
@@ -47,11 +54,17 @@ pub enum GatewayOp {
     /// same voice channel. The server verifies channel co-membership before
     /// forwarding — signals to users in different channels are silently dropped.
     VoiceSignal,
+    /// Client → server: first frame on a socket opened with `?resume=true`.
+    /// Carries `{ "session_id", "seq" }`; the server replays every dispatch
+    /// after `seq` and finishes with a RESUMED dispatch, or sends a fresh
+    /// READY if the session can no longer be resumed.
+    Resume,
 }
 
 // ── Server-to-client event type strings ──────────────────────────────────────
 
 pub const EVENT_READY: &str = "READY";
+pub const EVENT_RESUMED: &str = "RESUMED";
 pub const EVENT_MESSAGE_CREATE: &str = "MESSAGE_CREATE";
 pub const EVENT_MESSAGE_UPDATE: &str = "MESSAGE_UPDATE";
 pub const EVENT_MESSAGE_DELETE: &str = "MESSAGE_DELETE";
@@ -59,6 +72,10 @@ pub const EVENT_PRESENCE_UPDATE: &str = "PRESENCE_UPDATE";
 pub const EVENT_VOICE_STATE_UPDATE: &str = "VOICE_STATE_UPDATE";
 pub const EVENT_VOICE_SIGNAL: &str = "VOICE_SIGNAL";
 pub const EVENT_DM_CHANNEL_CREATE: &str = "DM_CHANNEL_CREATE";
+pub const EVENT_DM_CHANNEL_UPDATE: &str = "DM_CHANNEL_UPDATE";
+pub const EVENT_DM_CHANNEL_DELETE: &str = "DM_CHANNEL_DELETE";
+pub const EVENT_DM_CHANNEL_RECIPIENT_ADD: &str = "DM_CHANNEL_RECIPIENT_ADD";
+pub const EVENT_DM_CHANNEL_RECIPIENT_REMOVE: &str = "DM_CHANNEL_RECIPIENT_REMOVE";
 pub const EVENT_DM_MESSAGE_CREATE: &str = "DM_MESSAGE_CREATE";
 pub const EVENT_REACTION_ADD: &str = "REACTION_ADD";
 pub const EVENT_REACTION_REMOVE: &str = "REACTION_REMOVE";
@@ -72,6 +89,9 @@ pub const EVENT_CUSTOM_EMOJI_CREATE: &str = "CUSTOM_EMOJI_CREATE";
 pub const EVENT_CUSTOM_EMOJI_DELETE: &str = "CUSTOM_EMOJI_DELETE";
 pub const EVENT_GO_LIVE_START: &str = "GO_LIVE_START";
 pub const EVENT_GO_LIVE_STOP: &str = "GO_LIVE_STOP";
+pub const EVENT_GO_LIVE_VIEWER_UPDATE: &str = "GO_LIVE_VIEWER_UPDATE";
+pub const EVENT_READ_STATE_UPDATE: &str = "READ_STATE_UPDATE";
+pub const EVENT_NOTIFICATION_SETTINGS_UPDATE: &str = "NOTIFICATION_SETTINGS_UPDATE";
 pub const EVENT_MEMBER_KICK: &str = "MEMBER_KICK";
 pub const EVENT_MEMBER_BAN: &str = "MEMBER_BAN";
 pub const EVENT_MEMBER_TIMEOUT: &str = "MEMBER_TIMEOUT";
@@ -13,20 +13,22 @@ use tokio::sync::mpsc;
 use uuid::Uuid;
 
 use super::events::{
-    GatewayMessage, GatewayOp, EVENT_PRESENCE_UPDATE, EVENT_READY, EVENT_TYPING_START,
-    EVENT_VOICE_SIGNAL, EVENT_VOICE_STATE_UPDATE,
+    GatewayMessage, GatewayOp, EVENT_PRESENCE_UPDATE, EVENT_READY, EVENT_RESUMED,
+    EVENT_TYPING_START, EVENT_VOICE_SIGNAL, EVENT_VOICE_STATE_UPDATE,
 };
 use crate::{
     auth::{validate_token, TokenType},
+    handlers::go_live::end_go_live_for_user,
     models::{
         ChannelPermissionOverride, DirectMessageChannelDto, Role, Server, UnreadCount, User,
-        UserDto, VoiceStateDto,
+        UserDto, VoiceState, VoiceStateDto,
     },
+    sfu::SfuSignal,
     state::AppState,
 };
 
 /// Per-channel mention count returned in the READY payload.
-#[derive(Debug, sqlx::FromRow, serde::Serialize)]
+#[derive(Debug, serde::Serialize)]
 struct MentionCount {
     channel_id: Uuid,
     count: i64,
@@ -50,6 +52,10 @@ pub struct WsParams {
     /// exchange this for a short-lived JWT (`token` param) to avoid the static
     /// token appearing in server access logs.
     pub bot_token: Option<String>,
+    /// When set, the server holds back READY and expects the client's first
+    /// frame to be a RESUME for a previous session.
+    #[serde(default)]
+    pub resume: bool,
 }
 
 // ============================================================================
@@ -58,6 +64,7 @@ pub struct WsParams {
 
 /// GET /ws?token=<access_token> — upgrade to a WebSocket connection.
 /// GET /ws?bot_token=<static_token> — upgrade as a bot.
+/// Append `&resume=true` to either form to resume a dropped session.
 ///
 /// The token is validated before the upgrade is accepted; invalid tokens get a
 /// plain 401 without an upgrade attempt.
@@ -121,38 +128,88 @@ pub async fn websocket_handler(
             .into_response();
     };
 
-    ws.on_upgrade(move |socket| handle_socket(socket, user_id, state))
+    let resume = params.resume;
+    ws.on_upgrade(move |socket| handle_socket(socket, user_id, resume, state))
 }
 
 // ============================================================================
 // Connection lifecycle
 // ============================================================================
 
-async fn handle_socket(socket: WebSocket, user_id: Uuid, state: AppState) {
+async fn handle_socket(socket: WebSocket, user_id: Uuid, resume: bool, state: AppState) {
     let (mut ws_sender, mut ws_receiver) = socket.split();
     let (tx, mut rx) = mpsc::unbounded_channel::<String>();
 
-    // Build and send READY before registering so the client receives user
-    // context before any events can arrive.
-    let ready_json = match build_ready(&state, user_id).await {
-        Some(json) => json,
-        None => {
-            tracing::warn!(
-                user_id = %user_id,
-                "Failed to build READY payload; closing connection"
-            );
-            return;
-        }
+    let resume_request = if resume {
+        read_resume_frame(&mut ws_receiver, user_id).await
+    } else {
+        None
     };
 
-    if ws_sender.send(Message::Text(ready_json)).await.is_err() {
-        // Client disconnected before READY could be sent.
-        return;
-    }
+    // Try to re-attach to the previous session. Missed dispatches are queued
+    // onto `tx` and flushed by the send task below, followed by RESUMED.
+    let resumed = match resume_request {
+        Some((session_id, last_seq)) => match state
+            .connections
+            .resume(user_id, session_id, last_seq, tx.clone())
+            .await
+        {
+            Ok(replayed) => {
+                let event = GatewayMessage::dispatch(
+                    EVENT_RESUMED,
+                    json!({ "session_id": session_id, "replayed": replayed }),
+                );
+                if let Ok(json) = serde_json::to_string(&event) {
+                    let _ = tx.send(json);
+                }
+                Some(session_id)
+            }
+            Err(reason) => {
+                tracing::debug!(
+                    user_id = %user_id,
+                    session_id = %session_id,
+                    reason = ?reason,
+                    "Gateway session cannot be resumed; sending fresh READY"
+                );
+                None
+            }
+        },
+        None => None,
+    };
 
-    // Register connection and go online *after* READY is delivered,
-    // so no broadcast events can arrive before the client has its initial state.
-    let conn_id = state.connections.add(user_id, tx).await;
+    let session_id = match resumed {
+        Some(session_id) => {
+            drop(tx);
+            session_id
+        }
+        None => {
+            let session_id = Uuid::new_v4();
+
+            // Build and send READY before registering so the client receives
+            // user context before any events can arrive.
+            let ready_json = match build_ready(&state, user_id, session_id).await {
+                Some(json) => json,
+                None => {
+                    tracing::warn!(
+                        user_id = %user_id,
+                        "Failed to build READY payload; closing connection"
+                    );
+                    return;
+                }
+            };
+
+            if ws_sender.send(Message::Text(ready_json)).await.is_err() {
+                // Client disconnected before READY could be sent.
+                return;
+            }
+
+            // Register the session *after* READY is delivered, so no broadcast
+            // events can arrive before the client has its initial state.
+            // Other sessions for the same user stay registered alongside this one.
+            state.connections.attach(user_id, session_id, tx).await;
+            session_id
+        }
+    };
     set_presence(&state, user_id, "online", None, None).await;
 
     // Forward outbound events from the mpsc channel to the WebSocket.
@@ -201,7 +258,7 @@ async fn handle_socket(socket: WebSocket, user_id: Uuid, state: AppState) {
                                     }
                                     last_messages.push(now);
 
-                                    handle_client_message(user_id, &text, &state_clone).await;
+                                    handle_client_message(user_id, session_id, &text, &state_clone).await;
                                 }
                                 Message::Close(_) => break,
                                 _ => {}
@@ -232,18 +289,58 @@ async fn handle_socket(socket: WebSocket, user_id: Uuid, state: AppState) {
         _ = &mut recv_task => send_task.abort(),
     }
 
-    // Clean up on disconnect.
-    state.connections.remove(user_id, conn_id).await;
-    cleanup_voice_on_disconnect(&state, user_id).await;
-    set_presence(&state, user_id, "offline", None, None).await;
+    // Clean up on disconnect. The session itself stays resumable for
+    // `RESUME_WINDOW`. Voice state and presence are per-user, so they are only
+    // torn down once the user's last live session has closed — closing a web
+    // tab must not drop the desktop client out of a call.
+    let was_last_session = state.connections.remove(user_id, session_id).await;
+    if was_last_session {
+        cleanup_voice_on_disconnect(&state, user_id).await;
+        set_presence(&state, user_id, "offline", None, None).await;
+    }
 }
 
 // ============================================================================
 // Inbound message handling
 // ============================================================================
 
-/// Process a text frame received from the client.
-async fn handle_client_message(user_id: Uuid, text: &str, state: &AppState) {
+/// Wait for the RESUME frame that must open a `?resume=true` connection.
+///
+/// Returns the `(session_id, seq)` pair on success. Any other first frame, a
+/// malformed payload, or no frame within 10 seconds yields `None`, and the
+/// caller falls back to a fresh READY.
+async fn read_resume_frame(
+    ws_receiver: &mut futures::stream::SplitStream<WebSocket>,
+    user_id: Uuid,
+) -> Option<(Uuid, u64)> {
+    let frame = tokio::time::timeout(Duration::from_secs(10), ws_receiver.next()).await;
+    let Ok(Some(Ok(Message::Text(text)))) = frame else {
+        tracing::debug!(user_id = %user_id, "No RESUME frame received; sending fresh READY");
+        return None;
+    };
+    if text.len() > 16_384 {
+        return None;
+    }
+
+    let msg = serde_json::from_str::<GatewayMessage>(&text).ok()?;
+    if msg.op != GatewayOp::Resume {
+        tracing::debug!(
+            user_id = %user_id,
+            op = ?msg.op,
+            "Expected RESUME as first frame; sending fresh READY"
+        );
+        return None;
+    }
+    let data = msg.d?;
+    let session_id = data["session_id"]
+        .as_str()
+        .and_then(|s| Uuid::parse_str(s).ok())?;
+    let seq = data["seq"].as_u64()?;
+    Some((session_id, seq))
+}
+
+/// Process a text frame received from the client on session `session_id`.
+async fn handle_client_message(user_id: Uuid, session_id: Uuid, text: &str, state: &AppState) {
     let Ok(msg) = serde_json::from_str::<GatewayMessage>(text) else {
         // Ignore unparseable frames — don't disconnect for bad JSON.
         tracing::debug!(user_id = %user_id, "Received unparseable WebSocket frame; ignoring");
@@ -254,7 +351,10 @@ async fn handle_client_message(user_id: Uuid, text: &str, state: &AppState) {
         GatewayOp::Heartbeat => {
             let ack = GatewayMessage::heartbeat_ack();
             if let Ok(json) = serde_json::to_string(&ack) {
-                state.connections.send_to_user(user_id, &json).await;
+                state
+                    .connections
+                    .send_to_connection(user_id, session_id, &json)
+                    .await;
             }
         }
         GatewayOp::PresenceUpdate => {
@@ -283,7 +383,8 @@ async fn handle_client_message(user_id: Uuid, text: &str, state: &AppState) {
                 );
             }
         },
-        // Client should not send Dispatch or HeartbeatAck; log at debug so
+        // Client should not send Dispatch or HeartbeatAck, and RESUME is only
+        // valid as the first frame of a `?resume=true` socket; log at debug so
         // client-side protocol bugs are visible without polluting warn logs.
         _ => {
             tracing::debug!(
@@ -343,6 +444,8 @@ async fn cleanup_voice_on_disconnect(state: &AppState, user_id: Uuid) {
         }
     };
 
+    end_go_live_for_user(state, user_id, channel_id).await;
+
     // Scope the DELETE to the specific channel_id captured above.
     // This prevents a race where the user reconnects and joins a new channel
     // between the SELECT and DELETE — without the channel_id guard the stale
@@ -387,6 +490,9 @@ async fn cleanup_voice_on_disconnect(state: &AppState, user_id: Uuid) {
         map.insert("username".to_owned(), serde_json::json!(username));
     }
 
+    if let Some(sfu) = &state.sfu {
+        sfu.remove_peer(user_id).await;
+    }
     super::broadcast_to_server(state, server_id, EVENT_VOICE_STATE_UPDATE, payload).await;
 }
 
@@ -405,7 +511,15 @@ async fn cleanup_voice_on_disconnect(state: &AppState, user_id: Uuid) {
 /// omits `to_user_id` entirely — the receiver already knows they are the
 /// target. Both `sdp` and `candidate` are forwarded as-is; whichever was
 /// absent in the original signal will be `null` in the relayed message.
+///
+/// A signal without `to_user_id` but with a `transport` is addressed to the
+/// embedded SFU; see [`handle_sfu_signal`].
 async fn handle_voice_signal(user_id: Uuid, data: serde_json::Value, state: &AppState) {
+    if data["to_user_id"].is_null() && !data["transport"].is_null() {
+        handle_sfu_signal(user_id, data, state).await;
+        return;
+    }
+
     let to_user_id = match data["to_user_id"]
         .as_str()
         .and_then(|s| Uuid::parse_str(s).ok())
@@ -497,19 +611,58 @@ async fn handle_voice_signal(user_id: Uuid, data: serde_json::Value, state: &App
         "stream_type":  data["stream_type"],
     });
 
-    let event = GatewayMessage::dispatch(EVENT_VOICE_SIGNAL, relayed);
-    match serde_json::to_string(&event) {
-        Ok(json) => {
-            state.connections.send_to_user(to_user_id, &json).await;
+    super::broadcast_to_user_list(state, &[to_user_id], EVENT_VOICE_SIGNAL, relayed).await;
+}
+
+/// Hand a signal to the embedded SFU on behalf of `user_id`.
+///
+/// Dropped (debug-logged) when the SFU is disabled, the payload is malformed,
+/// or the user is not in a voice channel. Negotiation failures are reported
+/// to the client as a `VOICE_SIGNAL` with `type: "error"` so it can fall back
+/// or retry instead of waiting for an answer that never comes.
+async fn handle_sfu_signal(user_id: Uuid, data: serde_json::Value, state: &AppState) {
+    let Some(sfu) = &state.sfu else {
+        tracing::debug!(user_id = %user_id, "SFU signal received but the SFU is disabled; dropping");
+        return;
+    };
+    let signal: SfuSignal = match serde_json::from_value(data) {
+        Ok(signal) => signal,
+        Err(e) => {
+            tracing::debug!(user_id = %user_id, error = %e, "Malformed SFU signal; dropping");
+            return;
+        }
+    };
+
+    let voice = match sqlx::query_as::<_, VoiceState>(
+        "SELECT user_id, channel_id, self_mute, self_deaf, self_video, self_screen,
+                server_mute, server_deaf, joined_at
+         FROM voice_states WHERE user_id = $1",
+    )
+    .bind(user_id)
+    .fetch_optional(&state.pool)
+    .await
+    {
+        Ok(Some(voice)) => voice,
+        Ok(None) => {
+            tracing::debug!(user_id = %user_id, "SFU signal from a user not in voice; dropping");
+            return;
         }
         Err(e) => {
-            tracing::error!(
-                from_user_id = %user_id,
-                to_user_id   = %to_user_id,
-                error        = ?e,
-                "Failed to serialize VOICE_SIGNAL relay; this is a programming error"
-            );
+            tracing::warn!(user_id = %user_id, error = ?e, "DB error loading voice state; SFU signal dropped");
+            return;
         }
+    };
+
+    let transport = signal.transport;
+    if let Err(e) = sfu.handle_signal(&voice, signal).await {
+        tracing::debug!(user_id = %user_id, error = %e, "SFU rejected signal");
+        let payload = serde_json::json!({
+            "from_user_id": null,
+            "transport": transport,
+            "type": "error",
+            "message": e.to_string(),
+        });
+        super::broadcast_to_user_list(state, &[user_id], EVENT_VOICE_SIGNAL, payload).await;
     }
 }
 
@@ -519,10 +672,13 @@ async fn handle_voice_signal(user_id: Uuid, data: serde_json::Value, state: &App
 
 /// Build the READY event payload for the connecting user.
 ///
+/// `session_id` identifies the gateway session being opened; the client passes
+/// it back in RESUME after a reconnect.
+///
 /// Returns `None` if the user no longer exists in the database or if a
 /// database error occurs. Either case is treated as fatal for this
 /// connection's READY handshake.
-async fn build_ready(state: &AppState, user_id: Uuid) -> Option<String> {
+async fn build_ready(state: &AppState, user_id: Uuid, session_id: Uuid) -> Option<String> {
     let user: UserDto = match sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
         .bind(user_id)
         .fetch_optional(&state.pool)
@@ -569,157 +725,68 @@ async fn build_ready(state: &AppState, user_id: Uuid) -> Option<String> {
         }
     };
 
-    // DM channels: fetch channels this user participates in, plus the
-    // recipient's public profile for each channel.
-    #[derive(sqlx::FromRow)]
-    struct DmRow {
-        channel_id: Uuid,
-        channel_created_at: chrono::DateTime<chrono::Utc>,
-        recipient_id: Uuid,
-        recipient_username: String,
-        recipient_avatar_url: Option<String>,
-        recipient_bio: Option<String>,
-        recipient_pronouns: Option<String>,
-        recipient_status: String,
-        recipient_custom_status: Option<String>,
-        recipient_created_at: chrono::DateTime<chrono::Utc>,
-        last_message_at: Option<chrono::DateTime<chrono::Utc>>,
-    }
-
-    let dm_channels: Vec<DirectMessageChannelDto> = match sqlx::query_as::<_, DmRow>(
-        "SELECT
-            dmc.id             AS channel_id,
-            dmc.created_at     AS channel_created_at,
-            u.id               AS recipient_id,
-            u.username         AS recipient_username,
-            u.avatar_url       AS recipient_avatar_url,
-            u.bio              AS recipient_bio,
-            u.pronouns         AS recipient_pronouns,
-            u.status           AS recipient_status,
-            u.custom_status    AS recipient_custom_status,
-            u.created_at       AS recipient_created_at,
-            (SELECT MAX(dm.created_at)
-             FROM direct_messages dm
-             WHERE dm.channel_id = dmc.id AND dm.deleted = FALSE
-            ) AS last_message_at
-         FROM direct_message_channels dmc
-         JOIN direct_message_members dmm1 ON dmm1.channel_id = dmc.id AND dmm1.user_id = $1
-         JOIN direct_message_members dmm2 ON dmm2.channel_id = dmc.id AND dmm2.user_id != $1
-         JOIN users u ON u.id = dmm2.user_id
-         ORDER BY last_message_at DESC NULLS LAST",
-    )
-    .bind(user_id)
-    .fetch_all(&state.pool)
-    .await
-    {
-        Ok(rows) => rows
-            .into_iter()
-            .map(|r| DirectMessageChannelDto {
-                id: r.channel_id,
-                recipient: UserDto {
-                    id: r.recipient_id,
-                    username: r.recipient_username,
-                    email: None,
-                    avatar_url: r.recipient_avatar_url,
-                    bio: r.recipient_bio,
-                    pronouns: r.recipient_pronouns,
-
-                    status: r.recipient_status,
-                    custom_status: r.recipient_custom_status,
-                    activity: None,
-                    created_at: r.recipient_created_at,
-                    is_admin: false,
-                },
-                created_at: r.channel_created_at,
-                last_message_at: r.last_message_at,
-            })
-            .collect(),
-        Err(e) => {
-            tracing::warn!(
-                user_id = %user_id,
-                error   = ?e,
-                "Failed to fetch DM channels for READY payload; client will receive empty DM list"
-            );
-            vec![]
-        }
-    };
-
-    // Unread counts: messages created after the user's last read timestamp
-    // for channels they belong to (both server channels and DM channels).
-    // Each UNION branch is scoped by JOINing to the appropriate channel table
-    // so that a channel_read_states row for a server channel is never matched
-    // against direct_messages (and vice versa).
-    let unread_counts: Vec<UnreadCount> = match sqlx::query_as::<_, UnreadCount>(
-        "SELECT
-            crs.channel_id,
-            COUNT(m.id) AS unread_count
-         FROM channel_read_states crs
-         JOIN channels c ON c.id = crs.channel_id
-         JOIN messages m ON m.channel_id = crs.channel_id
-             AND m.created_at > crs.last_read_at
-             AND m.deleted = FALSE
-             AND m.author_id != $1
-         WHERE crs.user_id = $1
-         GROUP BY crs.channel_id
-         HAVING COUNT(m.id) > 0
-
-         UNION ALL
-
-         SELECT
-            crs.channel_id,
-            COUNT(dm.id) AS unread_count
-         FROM channel_read_states crs
-         JOIN direct_message_channels dmc ON dmc.id = crs.channel_id
-         JOIN direct_messages dm ON dm.channel_id = crs.channel_id
-             AND dm.created_at > crs.last_read_at
-             AND dm.deleted = FALSE
-             AND dm.author_id != $1
-         WHERE crs.user_id = $1
-         GROUP BY crs.channel_id
-         HAVING COUNT(dm.id) > 0",
-    )
-    .bind(user_id)
-    .fetch_all(&state.pool)
-    .await
-    {
-        Ok(rows) => rows,
-        Err(e) => {
-            tracing::warn!(
-                user_id = %user_id,
-                error   = ?e,
-                "Failed to fetch unread counts for READY payload; client will not see unread indicators"
-            );
-            vec![]
-        }
-    };
+    // DM channels (1:1 and group) with every other participant's public profile.
+    let dm_channels: Vec<DirectMessageChannelDto> =
+        match crate::handlers::dm::fetch_dm_channel_dtos(&state.pool, user_id, None).await {
+            Ok(channels) => channels,
+            Err(e) => {
+                tracing::warn!(
+                    user_id = %user_id,
+                    error   = ?e,
+                    "Failed to fetch DM channels for READY payload; client will receive empty DM list"
+                );
+                vec![]
+            }
+        };
 
-    // Mention counts: server-channel messages created after the user's last read
-    // that contain this user's ID in mention_user_ids OR have mention_everyone set.
-    let mention_counts: Vec<MentionCount> = match sqlx::query_as::<_, MentionCount>(
-        "SELECT crs.channel_id, COUNT(*) AS count
-         FROM messages m
-         JOIN channel_read_states crs
-           ON crs.channel_id = m.channel_id AND crs.user_id = $1
-         WHERE m.deleted = FALSE
-           AND m.created_at > crs.last_read_at
-           AND m.author_id != $1
-           AND ($1 = ANY(m.mention_user_ids) OR m.mention_everyone = TRUE)
-         GROUP BY crs.channel_id",
-    )
-    .bind(user_id)
-    .fetch_all(&state.pool)
-    .await
-    {
+    // Read position plus unread and mention counts for every channel the
+    // user can see. `unread_counts` / `mention_counts` are the older
+    // sparse forms of the same data.
+    let read_states = match crate::handlers::read_states::ready_read_states(state, user_id).await {
         Ok(rows) => rows,
         Err(e) => {
             tracing::warn!(
                 user_id = %user_id,
                 error   = ?e,
-                "Failed to fetch mention counts for READY payload; client will not see mention badges"
+                "Failed to compute read states for READY payload; client will not see unread indicators"
             );
             vec![]
         }
     };
+    let unread_counts: Vec<UnreadCount> = read_states
+        .iter()
+        .filter(|rs| rs.unread_count > 0)
+        .map(|rs| UnreadCount {
+            channel_id: rs.channel_id,
+            unread_count: rs.unread_count,
+        })
+        .collect();
+    let mention_counts: Vec<MentionCount> = read_states
+        .iter()
+        .filter(|rs| rs.mention_count > 0)
+        .map(|rs| MentionCount {
+            channel_id: rs.channel_id,
+            count: rs.mention_count,
+        })
+        .collect();
+
+    let notification_settings =
+        match crate::handlers::notification_settings::load_notification_settings(
+            &state.pool,
+            user_id,
+        )
+        .await
+        {
+            Ok(rows) => rows,
+            Err(e) => {
+                tracing::warn!(
+                    user_id = %user_id,
+                    error   = ?e,
+                    "Failed to fetch notification settings for READY payload; client will use defaults"
+                );
+                vec![]
+            }
+        };
 
     // Roles for each server the user belongs to, grouped by server_id.
     let server_ids: Vec<Uuid> = servers.iter().map(|s| s.id).collect();
@@ -790,9 +857,13 @@ async fn build_ready(state: &AppState, user_id: Uuid) -> Option<String> {
     let payload = GatewayMessage::dispatch(
         EVENT_READY,
         json!({
+            "session_id": session_id,
+            "seq": state.connections.current_seq(),
             "user": user,
             "servers": servers,
             "dm_channels": dm_channels,
+            "read_states": read_states,
+            "notification_settings": notification_settings,
             "unread_counts": unread_counts,
             "mention_counts": mention_counts,
             "server_roles": server_roles_map,
@@ -819,7 +890,7 @@ async fn build_ready(state: &AppState, user_id: Uuid) -> Option<String> {
 
 /// Update a user's status in the database and broadcast a PRESENCE_UPDATE
 /// event to all server co-members. Members without an active WebSocket
-/// connection are silently skipped by `broadcast_to_users`.
+/// connection on any node are silently skipped.
 pub async fn set_presence(
     state: &AppState,
     user_id: Uuid,
@@ -866,7 +937,9 @@ pub async fn set_presence(
         }
     };
 
-    let event = GatewayMessage::dispatch(
+    super::broadcast_to_user_list(
+        state,
+        &member_ids,
         EVENT_PRESENCE_UPDATE,
         json!({
             "user_id": user_id,
@@ -874,23 +947,8 @@ pub async fn set_presence(
             "custom_status": custom_status,
             "activity": activity,
         }),
-    );
-
-    match serde_json::to_string(&event) {
-        Ok(json) => {
-            state
-                .connections
-                .broadcast_to_users(&member_ids, &json)
-                .await;
-        }
-        Err(e) => {
-            tracing::error!(
-                user_id = %user_id,
-                error = ?e,
-                "Failed to serialize presence event; this is a programming error"
-            );
-        }
-    }
+    )
+    .await;
 }
 
 // ============================================================================
@@ -899,8 +957,8 @@ pub async fn set_presence(
 
 /// Handle a TYPING_START event from a client.
 ///
-/// Broadcasts a TYPING_START event to all members of the channel who can see
-/// the typing indicator. The event includes the user ID, username, and channel ID.
+/// Broadcasts a TYPING_START event to every server member with VIEW_CHANNEL
+/// on the channel. The event includes the user ID, username, and channel ID.
 /// Clients should auto-expire the typing indicator after ~10 seconds if no
 /// further TYPING_START events are received.
 async fn handle_typing_start(user_id: Uuid, data: serde_json::Value, state: &AppState) {
@@ -918,7 +976,7 @@ async fn handle_typing_start(user_id: Uuid, data: serde_json::Value, state: &App
         }
     };
 
-    // Verify user is a member of the server that owns this channel
+    // Resolve the server that owns this channel
     let server_id: Option<Uuid> =
         match sqlx::query_scalar("SELECT server_id FROM channels WHERE id = $1")
             .bind(channel_id)
@@ -941,27 +999,21 @@ async fn handle_typing_start(user_id: Uuid, data: serde_json::Value, state: &App
         None => return,
     };
 
-    // Verify membership
-    let is_member: bool = match sqlx::query_scalar(
-        "SELECT EXISTS(SELECT 1 FROM server_members WHERE server_id = $1 AND user_id = $2)",
-    )
-    .bind(server_id)
-    .bind(user_id)
-    .fetch_one(&state.pool)
-    .await
-    {
-        Ok(b) => b,
+    // Only members who can view the channel may type in it, and only they
+    // receive the indicator.
+    let viewers = match super::channel_viewers(state, server_id, channel_id).await {
+        Ok(ids) => ids,
         Err(e) => {
-            tracing::warn!(error = ?e, "Failed to verify membership for TYPING_START");
+            tracing::warn!(error = ?e, "Failed to resolve channel viewers for TYPING_START");
             return;
         }
     };
 
-    if !is_member {
+    if !viewers.contains(&user_id) {
         tracing::debug!(
             user_id = %user_id,
             channel_id = %channel_id,
-            "Non-member attempted TYPING_START"
+            "User without VIEW_CHANNEL attempted TYPING_START"
         );
         return;
     }
@@ -974,21 +1026,9 @@ async fn handle_typing_start(user_id: Uuid, data: serde_json::Value, state: &App
         .ok()
         .flatten();
 
-    // Get all members of the server to broadcast to
-    let member_ids: Vec<Uuid> =
-        match sqlx::query_scalar("SELECT user_id FROM server_members WHERE server_id = $1")
-            .bind(server_id)
-            .fetch_all(&state.pool)
-            .await
-        {
-            Ok(ids) => ids,
-            Err(e) => {
-                tracing::warn!(error = ?e, "Failed to fetch server members for TYPING_START");
-                return;
-            }
-        };
-
-    let event = GatewayMessage::dispatch(
+    super::broadcast_to_user_list(
+        state,
+        &viewers,
         EVENT_TYPING_START,
         json!({
             "user_id": user_id,
@@ -996,17 +1036,6 @@ async fn handle_typing_start(user_id: Uuid, data: serde_json::Value, state: &App
             "channel_id": channel_id,
             "timestamp": chrono::Utc::now().to_rfc3339(),
         }),
-    );
-
-    match serde_json::to_string(&event) {
-        Ok(json) => {
-            state
-                .connections
-                .broadcast_to_users(&member_ids, &json)
-                .await;
-        }
-        Err(e) => {
-            tracing::error!(error = ?e, "Failed to serialize TYPING_START event");
-        }
-    }
+    )
+    .await;
 }
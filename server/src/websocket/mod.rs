@@ -1,3 +1,4 @@
+pub mod channel_viewers;
 pub mod connection_manager;
 pub mod events;
 pub mod handler;
@@ -11,36 +12,29 @@ pub use handler::websocket_handler;
 use serde_json::Value;
 use uuid::Uuid;
 
+use crate::event_bus::BusMessage;
+use crate::handlers::shared::compute_channel_viewers;
 use crate::state::AppState;
-use events::{GatewayMessage, GatewayOp};
+use events::GatewayMessage;
 
-/// Serialize and send a gateway DISPATCH event to a fixed list of user IDs.
+/// Send a gateway DISPATCH event to a fixed list of user IDs.
 ///
-/// Used for DM broadcasts where the recipient list is already known.
+/// Used for DM broadcasts where the recipient list is already known. The
+/// event is published on the event bus, so recipients connected to any node
+/// receive it.
 pub async fn broadcast_to_user_list(
     state: &AppState,
     user_ids: &[Uuid],
     event_type: &str,
     data: Value,
 ) {
-    let event = GatewayMessage {
-        op: GatewayOp::Dispatch,
-        t: Some(event_type.to_owned()),
-        d: Some(data),
-    };
-
-    match serde_json::to_string(&event) {
-        Ok(json) => {
-            state.connections.broadcast_to_users(user_ids, &json).await;
-        }
-        Err(e) => {
-            tracing::error!(
-                event_type = %event_type,
-                error = ?e,
-                "Failed to serialize gateway event; this is a programming error"
-            );
-        }
-    }
+    state
+        .events
+        .publish(BusMessage::Dispatch {
+            user_ids: user_ids.to_vec(),
+            event: GatewayMessage::dispatch(event_type, data),
+        })
+        .await;
 }
 
 /// Fetch all members of a server and broadcast a gateway DISPATCH event to
@@ -68,26 +62,78 @@ pub async fn broadcast_to_server(state: &AppState, server_id: Uuid, event_type:
         }
     };
 
-    let event = GatewayMessage {
-        op: GatewayOp::Dispatch,
-        t: Some(event_type.to_owned()),
-        d: Some(data),
-    };
+    state
+        .events
+        .publish(BusMessage::Dispatch {
+            user_ids: member_ids,
+            event: GatewayMessage::dispatch(event_type, data),
+        })
+        .await;
+}
 
-    match serde_json::to_string(&event) {
-        Ok(json) => {
-            state
-                .connections
-                .broadcast_to_users(&member_ids, &json)
-                .await;
-        }
+/// Broadcast a gateway DISPATCH event for `channel_id` to every member of
+/// `server_id` who can view that channel.
+///
+/// Use this instead of [`broadcast_to_server`] for anything that reveals
+/// channel content (messages, reactions, pins, typing, threads) so a
+/// `channel_permission_overrides` entry that hides the channel over REST also
+/// hides it on the gateway. Recipient lists are cached per channel; see
+/// [`channel_viewers`].
+pub async fn broadcast_to_channel(
+    state: &AppState,
+    server_id: Uuid,
+    channel_id: Uuid,
+    event_type: &str,
+    data: Value,
+) {
+    let viewers = match channel_viewers(state, server_id, channel_id).await {
+        Ok(ids) => ids,
         Err(e) => {
-            tracing::error!(
+            tracing::warn!(
                 server_id = %server_id,
+                channel_id = %channel_id,
                 event_type = %event_type,
                 error = ?e,
-                "Failed to serialize gateway event; this is a programming error"
+                "Failed to resolve channel viewers for event broadcast; real-time event will not be delivered"
             );
+            return;
         }
+    };
+
+    state
+        .events
+        .publish(BusMessage::Dispatch {
+            user_ids: viewers.to_vec(),
+            event: GatewayMessage::dispatch(event_type, data),
+        })
+        .await;
+}
+
+/// Return the members of `server_id` who can view `channel_id`, from the
+/// node's cache when possible.
+pub async fn channel_viewers(
+    state: &AppState,
+    server_id: Uuid,
+    channel_id: Uuid,
+) -> crate::error::AppResult<std::sync::Arc<[Uuid]>> {
+    if let Some(viewers) = state.channel_viewers.get(channel_id).await {
+        return Ok(viewers);
     }
+    let generation = state.channel_viewers.generation();
+    let viewers = compute_channel_viewers(&state.pool, server_id, channel_id).await?;
+    Ok(state
+        .channel_viewers
+        .insert(server_id, channel_id, viewers, generation)
+        .await)
+}
+
+/// Drop cached channel recipient lists for `server_id` on every node.
+///
+/// Call after any change that can alter who sees a channel: permission
+/// overrides, role permissions, role assignments and server membership.
+pub async fn invalidate_channel_viewers(state: &AppState, server_id: Uuid) {
+    state
+        .events
+        .publish(BusMessage::InvalidateChannelViewers { server_id })
+        .await;
 }
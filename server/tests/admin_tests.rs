@@ -84,6 +84,11 @@ async fn admin_stats_returns_data() {
     assert!(body["total_channels"].is_number());
     assert!(body["active_ws_connections"].is_number());
     assert!(body["db_latency_ms"].is_number());
+    assert!(body["attachment_bytes"].is_number());
+    // Attachments plus custom emojis, both from the database.
+    assert!(body["storage_bytes"].as_u64().unwrap() >= body["attachment_bytes"].as_u64().unwrap());
+    assert!(body["top_storage_servers"].is_array());
+    assert!(body.get("server_storage_quota_bytes").is_some());
 }
 
 // ============================================================================
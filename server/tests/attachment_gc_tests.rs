@@ -0,0 +1,209 @@
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use axum::http::StatusCode;
+use common::*;
+use together_server::attachment_gc;
+use together_server::storage::{LocalStorage, Storage};
+
+const GRACE: Duration = Duration::from_secs(60 * 60);
+
+/// A server with one message carrying a text attachment, stored under a
+/// directory of its own so the collector only sees this test's files.
+struct Fixture {
+    app: axum::Router,
+    pool: sqlx::PgPool,
+    storage: Arc<dyn Storage>,
+    root: PathBuf,
+    token: String,
+    server_id: String,
+    message_id: String,
+    /// Storage key of the attachment.
+    key: String,
+}
+
+async fn setup() -> Fixture {
+    let root = std::env::temp_dir().join(format!("together-gc-{}", uuid::Uuid::new_v4()));
+    let storage: Arc<dyn Storage> = Arc::new(LocalStorage::new(root.clone()));
+    let pool = test_pool().await;
+    let (app, _) = create_test_app_with_storage(pool.clone(), storage.clone());
+
+    let token = register_and_get_token(app.clone(), &unique_username(), "password123").await;
+    let server = create_server(app.clone(), &token, &unique_username()).await;
+    let server_id = server["id"].as_str().unwrap().to_string();
+    let channel = create_channel(app.clone(), &token, &server_id, "general").await;
+    let message = create_message(app.clone(), &token, channel["id"].as_str().unwrap(), "hi").await;
+    let message_id = message["id"].as_str().unwrap().to_string();
+
+    let (status, body) = post_multipart_authed(
+        app.clone(),
+        &format!("/messages/{message_id}/attachments"),
+        &token,
+        &[MultipartFile {
+            field_name: "files",
+            filename: "notes.txt",
+            content_type: "text/plain",
+            data: b"collect me eventually",
+        }],
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    let key = body[0]["url"]
+        .as_str()
+        .unwrap()
+        .strip_prefix("/files/")
+        .unwrap()
+        .to_string();
+
+    Fixture {
+        app,
+        pool,
+        storage,
+        root,
+        token,
+        server_id,
+        message_id,
+        key,
+    }
+}
+
+/// Write a file under `root` as if it had been stored two hours ago.
+fn write_old(root: &Path, key: &str) {
+    let path = root.join(key);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, b"old").unwrap();
+    age(root, key);
+}
+
+/// Make the stored file under `key` two hours old.
+fn age(root: &Path, key: &str) {
+    let file = std::fs::File::options()
+        .write(true)
+        .open(root.join(key))
+        .unwrap();
+    file.set_modified(SystemTime::now() - 2 * GRACE).unwrap();
+}
+
+async fn collect(f: &Fixture) -> attachment_gc::CollectReport {
+    attachment_gc::collect(&f.pool, f.storage.as_ref(), GRACE)
+        .await
+        .unwrap()
+}
+
+// ============================================================================
+// Deleted messages
+// ============================================================================
+
+#[tokio::test]
+async fn deleted_message_attachments_are_collected_after_grace() {
+    let f = setup().await;
+    let (status, _) = delete_authed(
+        f.app.clone(),
+        &format!("/messages/{}", f.message_id),
+        &f.token,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    // Within the grace period the row and the file stay.
+    collect(&f).await;
+    assert!(f.root.join(&f.key).exists());
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM attachments WHERE message_id = $1")
+        .bind(uuid::Uuid::parse_str(&f.message_id).unwrap())
+        .fetch_one(&f.pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+
+    sqlx::query("UPDATE messages SET deleted_at = NOW() - INTERVAL '2 hours' WHERE id = $1")
+        .bind(uuid::Uuid::parse_str(&f.message_id).unwrap())
+        .execute(&f.pool)
+        .await
+        .unwrap();
+    // Collectors of other tests may have taken the row already, leaving the
+    // file to this one as an orphan; either way both end up gone.
+    age(&f.root, &f.key);
+    collect(&f).await;
+    assert!(!f.root.join(&f.key).exists());
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM attachments WHERE message_id = $1")
+        .bind(uuid::Uuid::parse_str(&f.message_id).unwrap())
+        .fetch_one(&f.pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+}
+
+#[tokio::test]
+async fn live_attachments_are_never_collected() {
+    let f = setup().await;
+    age(&f.root, &f.key);
+
+    let report = collect(&f).await;
+    assert!(f.root.join(&f.key).exists());
+    assert!(!report.missing_files.contains(&f.key));
+}
+
+// ============================================================================
+// Reconciliation
+// ============================================================================
+
+#[tokio::test]
+async fn files_of_deleted_servers_are_collected() {
+    let f = setup().await;
+    age(&f.root, &f.key);
+
+    let (status, _) = delete_authed(
+        f.app.clone(),
+        &format!("/servers/{}", f.server_id),
+        &f.token,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let report = collect(&f).await;
+    assert!(report.orphaned_files >= 1);
+    assert!(!f.root.join(&f.key).exists());
+}
+
+#[tokio::test]
+async fn orphans_are_kept_until_grace_and_other_files_alone() {
+    let f = setup().await;
+    let fresh = format!("{}/0000_in_flight.txt", f.message_id);
+    let old = format!("{}/0000_crashed.txt", f.message_id);
+    let emoji = format!("custom_emojis/{}/gone.png", uuid::Uuid::new_v4());
+    let unrelated = "exports/archive.zip";
+    std::fs::write(f.root.join(&fresh), b"new").unwrap();
+    write_old(&f.root, &old);
+    write_old(&f.root, &emoji);
+    write_old(&f.root, unrelated);
+
+    let report = collect(&f).await;
+    assert_eq!(report.orphaned_files, 2);
+    assert_eq!(report.orphaned_bytes, 6);
+    assert!(f.root.join(&fresh).exists());
+    assert!(!f.root.join(&old).exists());
+    assert!(!f.root.join(&emoji).exists());
+    assert!(f.root.join(unrelated).exists());
+    assert!(f.root.join(&f.key).exists());
+}
+
+#[tokio::test]
+async fn missing_files_are_reported_not_removed() {
+    let f = setup().await;
+    std::fs::remove_file(f.root.join(&f.key)).unwrap();
+
+    let report = collect(&f).await;
+    assert!(report.missing_files.contains(&f.key));
+
+    let (status, body) = get_authed(
+        f.app.clone(),
+        &format!("/messages/{}/attachments", f.message_id),
+        &f.token,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().unwrap().len(), 1);
+}
@@ -71,7 +71,7 @@ fn txt_file<'a>(name: &'a str, data: &'a [u8]) -> MultipartFile<'a> {
     }
 }
 
-/// Minimal 1×1 PNG (67 bytes). Magic bytes let `infer` detect it as `image/png`.
+/// Minimal 1×1 PNG (69 bytes). Magic bytes let `infer` detect it as `image/png`.
 /// Use this instead of plain-text fixtures wherever the upload must succeed
 /// (plain ASCII has no magic bytes and is rejected as `application/octet-stream`).
 fn png_file(name: &'static str) -> MultipartFile<'static> {
@@ -82,9 +82,9 @@ fn png_file(name: &'static str) -> MultipartFile<'static> {
         0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, // width=1, height=1
         0x08, 0x02, 0x00, 0x00, 0x00, 0x90, 0x77, 0x53, // bit depth, color type, ...
         0xDE, 0x00, 0x00, 0x00, 0x0C, 0x49, 0x44, 0x41, // IDAT length + type
-        0x54, 0x08, 0xD7, 0x63, 0xF8, 0xCF, 0xC0, 0x00, // IDAT data
-        0x00, 0x00, 0x02, 0x00, 0x01, 0xE2, 0x21, 0xBC, // IDAT data cont.
-        0x33, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4E, // IEND length + type
+        0x54, 0x78, 0xDA, 0x63, 0xF8, 0xCF, 0xC0, 0x00, // IDAT data (zlib)
+        0x00, 0x03, 0x01, 0x01, 0x00, 0xF7, 0x03, 0x41, // IDAT data cont. + CRC
+        0x43, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4E, // IEND length + type
         0x44, 0xAE, 0x42, 0x60, 0x82, // IEND data
     ];
     MultipartFile {
@@ -503,6 +503,286 @@ async fn serve_file_not_in_db_returns_404() {
 
 // ── MIME-type rejection for disallowed file types ─────────────────────────────
 
+// ── Storage quotas ────────────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn upload_rejected_over_server_quota() {
+    let f = setup().await;
+    let quota = together_server::config::StorageQuota {
+        per_server: Some(100),
+        per_user: None,
+    };
+    let (app, _) = create_test_app_with_quota(test_pool().await, quota);
+    let uri = format!("/messages/{}/attachments", f.message_id);
+
+    let (status, body) = post_multipart_authed(
+        app.clone(),
+        &uri,
+        &f.owner_token,
+        &[txt_file("a.txt", &[b'a'; 60])],
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+
+    let (status, body) = post_multipart_authed(
+        app.clone(),
+        &uri,
+        &f.owner_token,
+        &[txt_file("b.txt", &[b'b'; 60])],
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].as_str().unwrap().contains("server"), "{body}");
+
+    // What still fits is accepted.
+    let (status, body) =
+        post_multipart_authed(app, &uri, &f.owner_token, &[txt_file("c.txt", &[b'c'; 40])]).await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+}
+
+#[tokio::test]
+async fn upload_rejected_over_user_quota() {
+    let f = setup().await;
+    let quota = together_server::config::StorageQuota {
+        per_server: None,
+        per_user: Some(100),
+    };
+    let (app, _) = create_test_app_with_quota(test_pool().await, quota);
+    let uri = format!("/messages/{}/attachments", f.message_id);
+
+    let (status, body) = post_multipart_authed(
+        app.clone(),
+        &uri,
+        &f.owner_token,
+        &[
+            txt_file("a.txt", &[b'a'; 60]),
+            txt_file("b.txt", &[b'b'; 60]),
+        ],
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        body["error"].as_str().unwrap().contains("You have used"),
+        "{body}"
+    );
+
+    // Nothing from the refused request was kept.
+    let (_, body) = get_authed(app, &uri, &f.owner_token).await;
+    assert!(body.as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn parallel_uploads_do_not_overshoot_quota() {
+    let f = setup().await;
+    let quota = together_server::config::StorageQuota {
+        per_server: None,
+        per_user: Some(100),
+    };
+    let (app, _) = create_test_app_with_quota(test_pool().await, quota);
+    let uri = format!("/messages/{}/attachments", f.message_id);
+
+    // Each upload fits on its own, but only two of them fit together.
+    let uploads = (0..6).map(|_| {
+        let (app, uri, token) = (app.clone(), uri.clone(), f.owner_token.clone());
+        tokio::spawn(async move {
+            post_multipart_authed(app, &uri, &token, &[txt_file("a.txt", &[b'a'; 40])]).await
+        })
+    });
+    let mut created = 0;
+    for upload in uploads.collect::<Vec<_>>() {
+        let (status, body) = upload.await.unwrap();
+        match status {
+            StatusCode::CREATED => created += 1,
+            StatusCode::BAD_REQUEST => {}
+            _ => panic!("unexpected {status}: {body}"),
+        }
+    }
+    assert_eq!(created, 2);
+
+    let (_, body) = get_authed(app, &uri, &f.owner_token).await;
+    assert_eq!(body.as_array().unwrap().len(), 2);
+}
+
+// ── Range and conditional requests ────────────────────────────────────────────
+
+/// Upload a text attachment and return its URL.
+async fn upload_text(f: &Fixture, app: axum::Router, data: &[u8]) -> String {
+    let uri = format!("/messages/{}/attachments", f.message_id);
+    let (status, body) =
+        post_multipart_authed(app, &uri, &f.owner_token, &[txt_file("clip.txt", data)]).await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    body[0]["url"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn serve_file_answers_byte_ranges() {
+    use axum::http::header;
+
+    let f = setup().await;
+    let pool = test_pool().await;
+    let app = create_test_app(pool);
+    let data: Vec<u8> = (0..1000).map(|i| b'a' + (i % 26) as u8).collect();
+    let url = upload_text(&f, app.clone(), &data).await;
+
+    let (status, headers, body) = get_with_headers(
+        app.clone(),
+        &url,
+        Some(&f.member_token),
+        &[(header::RANGE, "bytes=100-199")],
+    )
+    .await;
+    assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+    assert_eq!(headers[header::CONTENT_RANGE], "bytes 100-199/1000");
+    assert_eq!(headers[header::CONTENT_LENGTH], "100");
+    assert_eq!(headers[header::CONTENT_TYPE], "text/plain");
+    assert_eq!(body, &data[100..200]);
+
+    let (status, headers, body) = get_with_headers(
+        app.clone(),
+        &url,
+        Some(&f.member_token),
+        &[(header::RANGE, "bytes=-10")],
+    )
+    .await;
+    assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+    assert_eq!(headers[header::CONTENT_RANGE], "bytes 990-999/1000");
+    assert_eq!(body, &data[990..]);
+
+    let (status, headers, body) = get_with_headers(
+        app.clone(),
+        &url,
+        Some(&f.member_token),
+        &[(header::RANGE, "bytes=1000-")],
+    )
+    .await;
+    assert_eq!(status, StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(headers[header::CONTENT_RANGE], "bytes */1000");
+    assert!(body.is_empty());
+
+    // A range guarded by a stale If-Range gets the whole file.
+    let (status, headers, body) = get_with_headers(
+        app,
+        &url,
+        Some(&f.member_token),
+        &[
+            (header::RANGE, "bytes=0-9"),
+            (header::IF_RANGE, "\"stale\""),
+        ],
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[header::ACCEPT_RANGES], "bytes");
+    assert_eq!(body, data);
+}
+
+#[tokio::test]
+async fn serve_file_with_control_characters_in_headers() {
+    use axum::http::header;
+
+    let f = setup().await;
+    let pool = test_pool().await;
+    let app = create_test_app(pool.clone());
+    let url = upload_text(&f, app.clone(), b"hello").await;
+
+    // Control characters are dropped from the file name.
+    sqlx::query("UPDATE attachments SET filename = $1 WHERE url = $2")
+        .bind("a\x01b\x1bc\x7f.txt")
+        .bind(&url)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let (status, headers, _) =
+        get_with_headers(app.clone(), &url, Some(&f.member_token), &[]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        headers[header::CONTENT_DISPOSITION],
+        "attachment; filename=\"abc.txt\""
+    );
+
+    // A stored value that cannot go into a header is an error, not a panic.
+    sqlx::query("UPDATE attachments SET mime_type = $1 WHERE url = $2")
+        .bind("text/plain\x01")
+        .bind(&url)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let (status, _, _) = get_with_headers(app, &url, Some(&f.member_token), &[]).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[tokio::test]
+async fn serve_file_answers_conditional_requests() {
+    use axum::http::header;
+
+    let f = setup().await;
+    let pool = test_pool().await;
+    let app = create_test_app(pool);
+    let url = upload_text(&f, app.clone(), b"cache me").await;
+
+    let (status, headers, _) = get_with_headers(app.clone(), &url, Some(&f.owner_token), &[]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[header::CACHE_CONTROL], "private, no-cache");
+    let etag = headers[header::ETAG].to_str().unwrap().to_string();
+    let last_modified = headers[header::LAST_MODIFIED].to_str().unwrap().to_string();
+
+    let (status, headers, body) = get_with_headers(
+        app.clone(),
+        &url,
+        Some(&f.owner_token),
+        &[(header::IF_NONE_MATCH, &etag)],
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+    assert_eq!(headers[header::ETAG], etag.as_str());
+    assert!(body.is_empty());
+
+    let (status, _, _) = get_with_headers(
+        app.clone(),
+        &url,
+        Some(&f.owner_token),
+        &[(header::IF_MODIFIED_SINCE, &last_modified)],
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+
+    let (status, _, body) = get_with_headers(
+        app,
+        &url,
+        Some(&f.owner_token),
+        &[(header::IF_NONE_MATCH, "\"something-else\"")],
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, b"cache me");
+}
+
+/// Range and conditional requests go through the same access checks: an
+/// outsider learns nothing, not even whether their cached copy is current.
+#[tokio::test]
+async fn range_and_conditional_requests_still_require_membership() {
+    use axum::http::header;
+
+    let f = setup().await;
+    let pool = test_pool().await;
+    let app = create_test_app(pool);
+    let url = upload_text(&f, app.clone(), b"members only").await;
+    let (_, headers, _) = get_with_headers(app.clone(), &url, Some(&f.owner_token), &[]).await;
+    let etag = headers[header::ETAG].to_str().unwrap().to_string();
+
+    for request in [
+        vec![(header::RANGE, "bytes=0-3")],
+        vec![(header::IF_NONE_MATCH, etag.as_str())],
+    ] {
+        let (status, _, body) =
+            get_with_headers(app.clone(), &url, Some(&f.outsider_token), &request).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(!body.starts_with(b"memb"));
+
+        let (status, _, _) = get_with_headers(app.clone(), &url, None, &request).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+}
+
 /// An ELF binary (Linux executable) must be rejected — `infer` detects its
 /// magic bytes as `application/x-executable`, which is not in the allowlist.
 #[tokio::test]
@@ -575,3 +855,181 @@ async fn upload_zip_file_rejected() {
         "ZIP archive should be rejected but got: {body}"
     );
 }
+
+/// Text is sniffed from its first bytes but must be UTF-8 to the end: a
+/// binary byte far past the sniffed head still gets the file rejected, and
+/// nothing is left in the staging directory.
+#[tokio::test]
+async fn upload_text_with_late_binary_bytes_rejected() {
+    let f = setup().await;
+    let pool = test_pool().await;
+    let app = create_test_app(pool);
+
+    let mut data = "plain text line\n".repeat(10_000).into_bytes();
+    data.extend_from_slice(&[0xff, 0xfe, 0x00]);
+
+    let uri = format!("/messages/{}/attachments", f.message_id);
+    let (status, body) =
+        post_multipart_authed(app, &uri, &f.owner_token, &[txt_file("late.txt", &data)]).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+
+    let leftover = std::fs::read_dir(test_staging_dir())
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .filter(|e| {
+                    std::fs::read(e.path())
+                        .map(|d| d.ends_with(&[0xff, 0xfe, 0x00]))
+                        .unwrap_or(false)
+                })
+                .count()
+        })
+        .unwrap_or(0);
+    assert_eq!(leftover, 0, "staged file left behind");
+}
+
+// ── Image processing ──────────────────────────────────────────────────────────
+
+/// Marker written into the EXIF segment of `photo_with_exif`, standing in for
+/// a GPS position or camera serial number.
+const EXIF_SECRET: &[u8] = b"GPS 51.5007 N 0.1246 W";
+
+/// A `width`×`height` JPEG with an EXIF segment right after the SOI marker.
+fn photo_with_exif(width: u32, height: u32) -> Vec<u8> {
+    use image::ImageEncoder;
+
+    let pixels = image::RgbImage::from_fn(width, height, |x, y| {
+        image::Rgb([(x % 256) as u8, (y % 256) as u8, 128])
+    });
+    let mut jpeg = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, 85)
+        .write_image(&pixels, width, height, image::ExtendedColorType::Rgb8)
+        .unwrap();
+
+    let mut app1 = b"Exif\x00\x00MM\x00\x2a\x00\x00\x00\x08\x00\x00\x00\x00\x00\x00".to_vec();
+    app1.extend_from_slice(EXIF_SECRET);
+
+    let mut out = jpeg[..2].to_vec();
+    out.extend_from_slice(&[0xFF, 0xE1]);
+    out.extend_from_slice(&((app1.len() + 2) as u16).to_be_bytes());
+    out.extend_from_slice(&app1);
+    out.extend_from_slice(&jpeg[2..]);
+    out
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+async fn upload_photo(f: &Fixture, app: axum::Router, data: &[u8]) -> serde_json::Value {
+    let uri = format!("/messages/{}/attachments", f.message_id);
+    let file = MultipartFile {
+        field_name: "files",
+        filename: "holiday.jpg",
+        content_type: "image/jpeg",
+        data,
+    };
+    let (status, body) = post_multipart_authed(app, &uri, &f.owner_token, &[file]).await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    body[0].clone()
+}
+
+/// Images get their dimensions recorded and their EXIF removed.
+#[tokio::test]
+async fn image_upload_records_dimensions_and_strips_exif() {
+    let f = setup().await;
+    let app = create_test_app(test_pool().await);
+    let photo = photo_with_exif(600, 400);
+    assert!(contains(&photo, EXIF_SECRET));
+
+    let att = upload_photo(&f, app.clone(), &photo).await;
+    assert_eq!(att["width"], 600);
+    assert_eq!(att["height"], 400);
+
+    let (status, stored) = get_raw_authed(app, att["url"].as_str().unwrap(), &f.member_token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!contains(&stored, EXIF_SECRET));
+    assert!(!contains(&stored, b"Exif\x00\x00"));
+    assert_eq!(att["file_size"], stored.len());
+
+    let decoded = image::load_from_memory(&stored).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (600, 400));
+}
+
+/// `?size=` serves a WebP thumbnail that fits the requested box, or the
+/// original when the image is not larger than it.
+#[tokio::test]
+async fn serve_file_size_returns_thumbnail() {
+    let f = setup().await;
+    let app = create_test_app(test_pool().await);
+    let att = upload_photo(&f, app.clone(), &photo_with_exif(600, 400)).await;
+    let url = att["url"].as_str().unwrap();
+
+    let (status, thumb) =
+        get_raw_authed(app.clone(), &format!("{url}?size=256"), &f.owner_token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        image::guess_format(&thumb).unwrap(),
+        image::ImageFormat::WebP
+    );
+    let decoded = image::load_from_memory(&thumb).unwrap();
+    assert_eq!(decoded.width(), 256);
+    assert!(decoded.height() <= 256);
+
+    let (_, original) = get_raw_authed(app.clone(), url, &f.owner_token).await;
+    let (status, large) =
+        get_raw_authed(app.clone(), &format!("{url}?size=1024"), &f.owner_token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(large, original);
+
+    let (status, _) = get_raw_authed(app, &format!("{url}?size=100"), &f.owner_token).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+/// Non-image attachments have no dimensions and ignore `?size=`.
+#[tokio::test]
+async fn non_image_attachments_have_no_thumbnails() {
+    let f = setup().await;
+    let app = create_test_app(test_pool().await);
+
+    let uri = format!("/messages/{}/attachments", f.message_id);
+    let (status, body) = post_multipart_authed(
+        app.clone(),
+        &uri,
+        &f.owner_token,
+        &[txt_file("notes.txt", b"just some notes")],
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    assert!(body[0]["width"].is_null());
+
+    let url = format!("{}?size=256", body[0]["url"].as_str().unwrap());
+    let (status, bytes) = get_raw_authed(app, &url, &f.owner_token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(bytes, b"just some notes");
+}
+
+/// A file that looks like an image but does not decode is rejected.
+#[tokio::test]
+async fn corrupt_image_rejected() {
+    let f = setup().await;
+    let app = create_test_app(test_pool().await);
+
+    let mut corrupt = png_file("broken.png").data[..16].to_vec();
+    corrupt.extend_from_slice(&[0xAB; 64]);
+
+    let uri = format!("/messages/{}/attachments", f.message_id);
+    let (status, body) = post_multipart_authed(
+        app,
+        &uri,
+        &f.owner_token,
+        &[MultipartFile {
+            field_name: "files",
+            filename: "broken.png",
+            content_type: "image/png",
+            data: &corrupt,
+        }],
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+}
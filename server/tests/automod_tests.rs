@@ -944,3 +944,377 @@ async fn automod_disabled_allows_all_messages() {
     .await;
     assert_eq!(status, StatusCode::CREATED);
 }
+
+// ============================================================================
+// Rules: /servers/:id/automod/rules
+// ============================================================================
+
+/// Register an owner, create a server with a text channel, and return
+/// `(token, server_id, channel_id)`.
+async fn setup_rules_server(app: axum::Router) -> (String, String, String) {
+    let token =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "pass1234").await;
+    let server = common::create_server(app.clone(), &token, "Rules").await;
+    let server_id = server["id"].as_str().unwrap().to_owned();
+    let channel = common::create_channel(app.clone(), &token, &server_id, "general").await;
+    let channel_id = channel["id"].as_str().unwrap().to_owned();
+    (token, server_id, channel_id)
+}
+
+async fn create_rule(
+    app: axum::Router,
+    token: &str,
+    server_id: &str,
+    body: serde_json::Value,
+) -> serde_json::Value {
+    let (status, rule) = common::post_json_authed(
+        app,
+        &format!("/servers/{server_id}/automod/rules"),
+        token,
+        body,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "create rule failed: {rule}");
+    rule
+}
+
+async fn send(
+    app: axum::Router,
+    token: &str,
+    channel_id: &str,
+    content: &str,
+) -> (StatusCode, serde_json::Value) {
+    common::post_json_authed(
+        app,
+        &format!("/channels/{channel_id}/messages"),
+        token,
+        json!({ "content": content }),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn rule_crud_round_trip() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, server_id, _) = setup_rules_server(app.clone()).await;
+
+    let rule = create_rule(
+        app.clone(),
+        &token,
+        &server_id,
+        json!({
+            "name": "No scams",
+            "trigger": { "type": "keyword", "keywords": ["free nitro"] },
+            "actions": [{ "type": "delete" }]
+        }),
+    )
+    .await;
+    assert_eq!(rule["enabled"], true);
+    assert_eq!(rule["trigger"]["type"], "keyword");
+    let rule_id = rule["id"].as_str().unwrap();
+
+    let (status, updated) = common::patch_json_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/automod/rules/{rule_id}"),
+        &token,
+        json!({ "name": "No scams v2", "enabled": false }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["name"], "No scams v2");
+    assert_eq!(updated["enabled"], false);
+    assert_eq!(updated["trigger"]["keywords"][0], "free nitro");
+
+    let (_, list) = common::get_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/automod/rules"),
+        &token,
+    )
+    .await;
+    assert_eq!(list.as_array().unwrap().len(), 1);
+
+    let (status, _) = common::delete_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/automod/rules/{rule_id}"),
+        &token,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = common::delete_authed(
+        app,
+        &format!("/servers/{server_id}/automod/rules/{rule_id}"),
+        &token,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn rule_validation_errors_return_400() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, server_id, _) = setup_rules_server(app.clone()).await;
+    let other = common::create_server(app.clone(), &token, "Other").await;
+    let foreign_channel =
+        common::create_channel(app.clone(), &token, other["id"].as_str().unwrap(), "x").await;
+
+    for body in [
+        json!({ "name": "bad", "trigger": { "type": "regex", "patterns": ["(unclosed"] },
+                "actions": [{ "type": "delete" }] }),
+        json!({ "name": "bad", "trigger": { "type": "invite" }, "actions": [] }),
+        json!({ "name": "", "trigger": { "type": "invite" }, "actions": [{ "type": "delete" }] }),
+        json!({ "name": "bad", "trigger": { "type": "caps", "min_ratio": 1.5, "min_letters": 5 },
+                "actions": [{ "type": "delete" }] }),
+        json!({ "name": "bad", "trigger": { "type": "invite" },
+                "actions": [{ "type": "timeout", "duration_minutes": 0 }] }),
+        json!({ "name": "bad", "trigger": { "type": "invite" },
+                "actions": [{ "type": "alert", "channel_id": foreign_channel["id"] }] }),
+        json!({ "name": "bad", "trigger": { "type": "invite" },
+                "actions": [{ "type": "delete" }], "channel_ids": [foreign_channel["id"]] }),
+    ] {
+        let (status, _) = common::post_json_authed(
+            app.clone(),
+            &format!("/servers/{server_id}/automod/rules"),
+            &token,
+            body.clone(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "accepted {body}");
+    }
+}
+
+#[tokio::test]
+async fn rules_non_owner_forbidden() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, server_id, _) = setup_rules_server(app.clone()).await;
+    common::make_server_public(app.clone(), &token, &server_id).await;
+    let member_token =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "pass1234").await;
+    common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/join"),
+        &member_token,
+        json!({}),
+    )
+    .await;
+
+    let (status, _) = common::get_authed(
+        app,
+        &format!("/servers/{server_id}/automod/rules"),
+        &member_token,
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn keyword_rule_blocks_and_logs_without_legacy_config() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, server_id, channel_id) = setup_rules_server(app.clone()).await;
+
+    let rule = create_rule(
+        app.clone(),
+        &token,
+        &server_id,
+        json!({
+            "name": "No scams",
+            "trigger": { "type": "keyword", "keywords": ["free nitro"] },
+            "actions": [{ "type": "delete" }]
+        }),
+    )
+    .await;
+
+    let (status, _) = send(app.clone(), &token, &channel_id, "get FREE NITRO here").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(app.clone(), &token, &channel_id, "nitro is free-ish").await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (_, logs) =
+        common::get_authed(app, &format!("/servers/{server_id}/automod/logs"), &token).await;
+    assert_eq!(logs[0]["rule_type"], "keyword");
+    assert_eq!(logs[0]["rule_id"], rule["id"]);
+    assert_eq!(logs[0]["action_taken"], "delete");
+    assert_eq!(logs[0]["matched_term"], "FREE NITRO");
+}
+
+#[tokio::test]
+async fn word_filter_matches_whole_words_only() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, server_id, channel_id) = setup_rules_server(app.clone()).await;
+
+    common::patch_json_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/automod"),
+        &token,
+        json!({ "enabled": true, "word_filter_enabled": true }),
+    )
+    .await;
+    common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/automod/words"),
+        &token,
+        json!({ "word": "ass" }),
+    )
+    .await;
+
+    let (status, _) = send(app.clone(), &token, &channel_id, "first class seats").await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = send(app, &token, &channel_id, "what an ass").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn exempt_role_bypasses_rule() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, server_id, channel_id) = setup_rules_server(app.clone()).await;
+    common::make_server_public(app.clone(), &token, &server_id).await;
+
+    let member = common::register_user(app.clone(), &common::unique_username(), "pass1234").await;
+    let member_token = member["access_token"].as_str().unwrap();
+    let member_id = member["user"]["id"].as_str().unwrap();
+    common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/join"),
+        member_token,
+        json!({}),
+    )
+    .await;
+
+    let (_, role) = common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/roles"),
+        &token,
+        json!({ "name": "Trusted" }),
+    )
+    .await;
+    let role_id = role["id"].as_str().unwrap();
+
+    create_rule(
+        app.clone(),
+        &token,
+        &server_id,
+        json!({
+            "name": "No links",
+            "trigger": { "type": "link" },
+            "actions": [{ "type": "delete" }],
+            "exempt_role_ids": [role_id]
+        }),
+    )
+    .await;
+
+    let link = "see https://evil.test/x";
+    let (status, _) = send(app.clone(), member_token, &channel_id, link).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = common::put_json_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/members/{member_id}/roles/{role_id}"),
+        &token,
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, _) = send(app, member_token, &channel_id, link).await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn alert_action_posts_report_to_log_channel() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, server_id, channel_id) = setup_rules_server(app.clone()).await;
+    let log_channel = common::create_channel(app.clone(), &token, &server_id, "mod-log").await;
+    let log_channel_id = log_channel["id"].as_str().unwrap();
+
+    create_rule(
+        app.clone(),
+        &token,
+        &server_id,
+        json!({
+            "name": "Shouting",
+            "trigger": { "type": "caps", "min_ratio": 0.8, "min_letters": 5 },
+            "actions": [{ "type": "alert", "channel_id": log_channel_id }]
+        }),
+    )
+    .await;
+
+    // Alert-only rules report without blocking.
+    let (status, _) = send(app.clone(), &token, &channel_id, "WHY IS THIS BROKEN").await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (_, messages) =
+        common::get_authed(app, &format!("/channels/{log_channel_id}/messages"), &token).await;
+    assert_eq!(messages[0]["webhook"]["username"], "AutoMod");
+    assert!(messages[0]["webhook"]["id"].is_null());
+    let embed = &messages[0]["embeds"][0];
+    assert_eq!(embed["title"], "Automod rule triggered: Shouting");
+    assert_eq!(embed["description"], "WHY IS THIS BROKEN");
+}
+
+#[tokio::test]
+async fn timeout_action_times_out_author() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, server_id, channel_id) = setup_rules_server(app.clone()).await;
+
+    create_rule(
+        app.clone(),
+        &token,
+        &server_id,
+        json!({
+            "name": "Mass mention",
+            "trigger": { "type": "mention_spam", "max_mentions": 2 },
+            "actions": [{ "type": "delete" }, { "type": "timeout", "duration_minutes": 10 }]
+        }),
+    )
+    .await;
+
+    let (status, _) = send(app.clone(), &token, &channel_id, "@a @b @c").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(app, &token, &channel_id, "sorry").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn rule_changes_take_effect_immediately() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, server_id, channel_id) = setup_rules_server(app.clone()).await;
+
+    // Warm the compiled cache before any rule exists.
+    let (status, _) = send(app.clone(), &token, &channel_id, "discord.gg/abc").await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let rule = create_rule(
+        app.clone(),
+        &token,
+        &server_id,
+        json!({
+            "name": "No invites",
+            "trigger": { "type": "invite" },
+            "actions": [{ "type": "delete" }]
+        }),
+    )
+    .await;
+    let (status, _) = send(app.clone(), &token, &channel_id, "discord.gg/def").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    common::patch_json_authed(
+        app.clone(),
+        &format!(
+            "/servers/{server_id}/automod/rules/{}",
+            rule["id"].as_str().unwrap()
+        ),
+        &token,
+        json!({ "enabled": false }),
+    )
+    .await;
+    let (status, _) = send(app, &token, &channel_id, "discord.gg/ghi").await;
+    assert_eq!(status, StatusCode::CREATED);
+}
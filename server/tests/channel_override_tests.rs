@@ -323,3 +323,322 @@ async fn delete_override_restores_access() {
     .await;
     assert_eq!(status, StatusCode::CREATED);
 }
+
+// ============================================================================
+// Gateway broadcasts respect VIEW_CHANNEL
+// ============================================================================
+
+/// Like `setup_server_with_channel_and_member`, but with gateway sessions
+/// registered for both the owner and the member.
+async fn setup_with_gateway() -> (
+    axum::Router,
+    String,
+    String,
+    String,
+    String,
+    String,
+    tokio::sync::mpsc::UnboundedReceiver<String>,
+    tokio::sync::mpsc::UnboundedReceiver<String>,
+) {
+    let pool = common::test_pool().await;
+    sqlx::query("UPDATE instance_settings SET registration_mode = 'open' WHERE id = 1")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let (app, state) = common::create_test_app_with_state(pool);
+
+    let owner_body =
+        common::register_user(app.clone(), &common::unique_username(), "pass1234").await;
+    let owner_token = owner_body["access_token"].as_str().unwrap().to_owned();
+    let owner_id = owner_body["user"]["id"].as_str().unwrap().to_owned();
+    let member_body =
+        common::register_user(app.clone(), &common::unique_username(), "pass1234").await;
+    let member_token = member_body["access_token"].as_str().unwrap().to_owned();
+    let member_user_id = member_body["user"]["id"].as_str().unwrap().to_owned();
+
+    let server = common::create_server(app.clone(), &owner_token, "Gateway Filter").await;
+    let server_id = server["id"].as_str().unwrap().to_owned();
+    common::make_server_public(app.clone(), &owner_token, &server_id).await;
+    common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/join"),
+        &member_token,
+        json!({}),
+    )
+    .await;
+
+    let channel = common::create_channel(app.clone(), &owner_token, &server_id, "secret").await;
+    let channel_id = channel["id"].as_str().unwrap().to_owned();
+
+    let (owner_tx, owner_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (member_tx, member_rx) = tokio::sync::mpsc::unbounded_channel();
+    state
+        .connections
+        .add(owner_id.parse().unwrap(), owner_tx)
+        .await;
+    state
+        .connections
+        .add(member_user_id.parse().unwrap(), member_tx)
+        .await;
+
+    (
+        app,
+        owner_token,
+        member_token,
+        server_id,
+        channel_id,
+        member_user_id,
+        owner_rx,
+        member_rx,
+    )
+}
+
+/// Drain every frame queued so far and return the event names.
+fn drain_events(rx: &mut tokio::sync::mpsc::UnboundedReceiver<String>) -> Vec<String> {
+    let mut events = Vec::new();
+    while let Ok(frame) = rx.try_recv() {
+        let v: serde_json::Value = serde_json::from_str(&frame).unwrap();
+        events.push(v["t"].as_str().unwrap_or_default().to_owned());
+    }
+    events
+}
+
+#[tokio::test]
+async fn message_create_skips_member_denied_view_channel() {
+    let (app, owner_token, _, _, channel_id, member_user_id, mut owner_rx, mut member_rx) =
+        setup_with_gateway().await;
+
+    common::put_json_authed(
+        app.clone(),
+        &format!("/channels/{channel_id}/overrides"),
+        &owner_token,
+        json!({ "user_id": member_user_id, "allow": 0, "deny": 1 }),
+    )
+    .await;
+    drain_events(&mut owner_rx);
+    drain_events(&mut member_rx);
+
+    common::create_message(app, &owner_token, &channel_id, "top secret").await;
+
+    assert_eq!(drain_events(&mut owner_rx), vec!["MESSAGE_CREATE"]);
+    assert!(drain_events(&mut member_rx).is_empty());
+}
+
+#[tokio::test]
+async fn deleting_override_restores_gateway_delivery() {
+    let (app, owner_token, _, _, channel_id, member_user_id, _owner_rx, mut member_rx) =
+        setup_with_gateway().await;
+
+    let (_, ov) = common::put_json_authed(
+        app.clone(),
+        &format!("/channels/{channel_id}/overrides"),
+        &owner_token,
+        json!({ "user_id": member_user_id, "allow": 0, "deny": 1 }),
+    )
+    .await;
+    let override_id = ov["id"].as_str().unwrap().to_owned();
+
+    // Populate the viewer cache while the member is hidden.
+    common::create_message(app.clone(), &owner_token, &channel_id, "hidden").await;
+
+    common::delete_authed(
+        app.clone(),
+        &format!("/channels/{channel_id}/overrides/{override_id}"),
+        &owner_token,
+    )
+    .await;
+    drain_events(&mut member_rx);
+
+    common::create_message(app, &owner_token, &channel_id, "visible").await;
+
+    assert_eq!(drain_events(&mut member_rx), vec!["MESSAGE_CREATE"]);
+}
+
+#[tokio::test]
+async fn role_assignment_updates_gateway_recipients() {
+    let (app, owner_token, _, server_id, channel_id, member_user_id, _owner_rx, mut member_rx) =
+        setup_with_gateway().await;
+
+    let (_, role) = common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/roles"),
+        &owner_token,
+        json!({ "name": "Muted Viewer", "permissions": 0 }),
+    )
+    .await;
+    let role_id = role["id"].as_str().unwrap();
+    common::put_json_authed(
+        app.clone(),
+        &format!("/channels/{channel_id}/overrides"),
+        &owner_token,
+        json!({ "role_id": role_id, "allow": 0, "deny": 1 }),
+    )
+    .await;
+
+    // Member can still see the channel before getting the role.
+    common::create_message(app.clone(), &owner_token, &channel_id, "before").await;
+    assert!(drain_events(&mut member_rx).contains(&"MESSAGE_CREATE".to_owned()));
+
+    common::put_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/members/{member_user_id}/roles/{role_id}"),
+        &owner_token,
+    )
+    .await;
+    drain_events(&mut member_rx);
+
+    common::create_message(app, &owner_token, &channel_id, "after").await;
+    assert!(drain_events(&mut member_rx).is_empty());
+}
+
+#[tokio::test]
+async fn reaction_and_pin_events_skip_member_denied_view_channel() {
+    let (app, owner_token, _, _, channel_id, member_user_id, mut owner_rx, mut member_rx) =
+        setup_with_gateway().await;
+
+    common::put_json_authed(
+        app.clone(),
+        &format!("/channels/{channel_id}/overrides"),
+        &owner_token,
+        json!({ "user_id": member_user_id, "allow": 0, "deny": 1 }),
+    )
+    .await;
+    let message = common::create_message(app.clone(), &owner_token, &channel_id, "hi").await;
+    let message_id = message["id"].as_str().unwrap();
+    drain_events(&mut owner_rx);
+    drain_events(&mut member_rx);
+
+    common::put_authed(
+        app.clone(),
+        &format!("/channels/{channel_id}/messages/{message_id}/reactions/%F0%9F%91%8D"),
+        &owner_token,
+    )
+    .await;
+    common::post_json_authed(
+        app,
+        &format!("/channels/{channel_id}/messages/{message_id}/pin"),
+        &owner_token,
+        json!({}),
+    )
+    .await;
+
+    assert_eq!(
+        drain_events(&mut owner_rx),
+        vec!["REACTION_ADD", "MESSAGE_PIN"]
+    );
+    assert!(drain_events(&mut member_rx).is_empty());
+}
+
+// ============================================================================
+// Bulk viewer resolution agrees with per-member resolution
+// ============================================================================
+
+#[tokio::test]
+async fn channel_viewers_match_per_member_permissions() {
+    use together_server::handlers::shared::{
+        compute_channel_permissions, compute_channel_viewers, PERMISSION_VIEW_CHANNEL,
+    };
+
+    let (app, owner_token, _, server_id, channel_id, plain_id, pool) =
+        setup_server_with_channel_and_member().await;
+
+    // Join one more member per rule under test.
+    let mut join = Vec::new();
+    for _ in 0..4 {
+        let body = common::register_user(app.clone(), &common::unique_username(), "pass1234").await;
+        let token = body["access_token"].as_str().unwrap().to_owned();
+        common::post_json_authed(
+            app.clone(),
+            &format!("/servers/{server_id}/join"),
+            &token,
+            json!({}),
+        )
+        .await;
+        join.push(body["user"]["id"].as_str().unwrap().to_owned());
+    }
+    let [admin_id, hidden_id, allowed_id, denied_id] = join.try_into().unwrap();
+
+    let create_role = |name: &'static str, permissions: i64| {
+        let (app, owner_token, server_id) = (app.clone(), owner_token.clone(), server_id.clone());
+        async move {
+            let (_, role) = common::post_json_authed(
+                app,
+                &format!("/servers/{server_id}/roles"),
+                &owner_token,
+                json!({ "name": name, "permissions": permissions }),
+            )
+            .await;
+            role["id"].as_str().unwrap().to_owned()
+        }
+    };
+    let admin_role = create_role("Admin", 8192).await;
+    let hidden_role = create_role("Hidden", 0).await;
+    for (user_id, role_id) in [
+        (&admin_id, &admin_role),
+        (&admin_id, &hidden_role),
+        (&hidden_id, &hidden_role),
+        (&allowed_id, &hidden_role),
+    ] {
+        common::put_authed(
+            app.clone(),
+            &format!("/servers/{server_id}/members/{user_id}/roles/{role_id}"),
+            &owner_token,
+        )
+        .await;
+    }
+    for body in [
+        json!({ "role_id": hidden_role, "allow": 0, "deny": 1 }),
+        json!({ "user_id": allowed_id, "allow": 1, "deny": 0 }),
+        json!({ "user_id": denied_id, "allow": 0, "deny": 1 }),
+    ] {
+        let (status, _) = common::put_json_authed(
+            app.clone(),
+            &format!("/channels/{channel_id}/overrides"),
+            &owner_token,
+            body,
+        )
+        .await;
+        assert!(status.is_success());
+    }
+
+    let server_id: uuid::Uuid = server_id.parse().unwrap();
+    let channel_id: uuid::Uuid = channel_id.parse().unwrap();
+    let mut viewers = compute_channel_viewers(&pool, server_id, channel_id)
+        .await
+        .unwrap();
+    viewers.sort();
+
+    let members: Vec<uuid::Uuid> =
+        sqlx::query_scalar("SELECT user_id FROM server_members WHERE server_id = $1")
+            .bind(server_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    let mut expected = Vec::new();
+    for user_id in members {
+        let perms = compute_channel_permissions(&pool, server_id, channel_id, user_id)
+            .await
+            .unwrap();
+        if perms & PERMISSION_VIEW_CHANNEL != 0 {
+            expected.push(user_id);
+        }
+    }
+    expected.sort();
+    assert_eq!(viewers, expected);
+
+    // And the fixture exercises every rule.
+    let id = |s: &str| s.parse::<uuid::Uuid>().unwrap();
+    for visible in [&plain_id, &admin_id, &allowed_id] {
+        assert!(
+            viewers.contains(&id(visible)),
+            "{visible} should see the channel"
+        );
+    }
+    for hidden in [&hidden_id, &denied_id] {
+        assert!(
+            !viewers.contains(&id(hidden)),
+            "{hidden} should not see the channel"
+        );
+    }
+}
@@ -5,7 +5,7 @@
 use axum::{
     body::Body,
     http::{header, Method, Request, StatusCode},
-    routing::{delete, get, patch, post, put},
+    routing::{delete, get, head, patch, post, put},
     Router,
 };
 use http_body_util::BodyExt;
@@ -18,13 +18,23 @@ use tokio::sync::RwLock;
 use tower::ServiceExt;
 
 use together_server::{
+    automod_engine::AutomodCache,
+    config::StorageQuota,
+    event_bus::{EventBus, LocalEventBus, LocalState},
     handlers,
     state::AppState,
+    storage::{LocalStorage, Storage},
     webhook_delivery,
-    websocket::{websocket_handler, ConnectionManager},
+    websocket::{channel_viewers::ChannelViewerCache, websocket_handler, ConnectionManager},
 };
 
 pub const TEST_JWT_SECRET: &str = "test-secret-min-32-characters-long!!";
+/// Passkey relying party every test app is configured with.
+pub const TEST_WEBAUTHN_RP_ID: &str = "localhost";
+pub const TEST_WEBAUTHN_ORIGIN: &str = "http://localhost:5173";
+/// Client credentials for the stand-in identity provider in SSO tests.
+pub const TEST_OIDC_CLIENT_ID: &str = "together-test";
+pub const TEST_OIDC_CLIENT_SECRET: &str = "together-test-secret";
 
 /// Shared upload directory for all integration tests.
 ///
@@ -34,6 +44,11 @@ pub fn test_upload_dir() -> PathBuf {
     std::env::temp_dir().join("together_test_uploads")
 }
 
+/// Shared staging directory for uploads that are still arriving.
+pub fn test_staging_dir() -> PathBuf {
+    std::env::temp_dir().join("together_test_upload_staging")
+}
+
 /// Connect to the test database specified by DATABASE_URL.
 ///
 /// Each test that calls this gets its own pool. Tests use UUID-based usernames
@@ -49,6 +64,146 @@ pub async fn test_pool() -> PgPool {
 
 /// Build the full application router wired to a test database pool.
 pub fn create_test_app(pool: PgPool) -> Router {
+    create_test_app_with_state(pool).0
+}
+
+/// Like [`create_test_app`], but also return the `AppState` so tests can
+/// register gateway sessions on `state.connections` and observe dispatches.
+pub fn create_test_app_with_state(pool: PgPool) -> (Router, AppState) {
+    build_test_app(pool, false, None, None, None, None, StorageQuota::default())
+}
+
+/// Like [`create_test_app_with_state`], with the embedded voice SFU enabled.
+pub fn create_test_app_with_sfu(pool: PgPool) -> (Router, AppState) {
+    build_test_app(pool, true, None, None, None, None, StorageQuota::default())
+}
+
+/// Like [`create_test_app_with_state`], with Web Push enabled under a fresh
+/// VAPID key and `http://` endpoints allowed, so tests can point
+/// subscriptions at a local stand-in push service.
+pub fn create_test_app_with_push(pool: PgPool, vapid_private_key: &str) -> (Router, AppState) {
+    build_test_app(
+        pool,
+        false,
+        Some(together_server::config::WebPushConfig {
+            vapid_private_key: vapid_private_key.to_string(),
+            subject: "mailto:tests@example.com".to_string(),
+            allow_http_endpoints: true,
+        }),
+        None,
+        None,
+        None,
+        StorageQuota::default(),
+    )
+}
+
+/// Like [`create_test_app_with_state`], with email going to a fresh
+/// directory through the file transport. Returns that directory; see
+/// [`read_mail`]. The mention digest is enabled but its worker is not
+/// started, so tests drive it with `mail::digest::run_due`.
+pub fn create_test_app_with_mail(pool: PgPool) -> (Router, AppState, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("together-mail-{}", uuid::Uuid::new_v4()));
+    let (router, state) = build_test_app(
+        pool,
+        false,
+        None,
+        Some(together_server::config::MailConfig {
+            transport: together_server::config::MailTransport::File(dir.clone()),
+            from: "Together <noreply@example.com>".to_string(),
+            public_url: "https://chat.example.com".to_string(),
+            template_dir: None,
+            digest_interval_hours: Some(24),
+        }),
+        None,
+        None,
+        StorageQuota::default(),
+    );
+    (router, state, dir)
+}
+
+/// Emails written to `dir` by the file transport, oldest first, waiting up
+/// to a few seconds for at least `count` to arrive (sending runs in a
+/// background task).
+pub async fn read_mail(dir: &std::path::Path, count: usize) -> Vec<Value> {
+    for _ in 0..50 {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+            .map(|entries| entries.filter_map(|e| e.ok().map(|e| e.path())).collect())
+            .unwrap_or_default();
+        if paths.len() >= count {
+            paths.sort();
+            return paths
+                .iter()
+                .map(|p| serde_json::from_slice(&std::fs::read(p).unwrap()).unwrap())
+                .collect();
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    panic!("expected {count} email(s) in {}", dir.display());
+}
+
+/// Like [`create_test_app_with_state`], with single sign-on configured by
+/// `oidc` (usually from [`test_oidc_config`]).
+pub fn create_test_app_with_oidc(
+    pool: PgPool,
+    oidc: together_server::config::OidcConfig,
+) -> (Router, AppState) {
+    build_test_app(
+        pool,
+        false,
+        None,
+        None,
+        Some(oidc),
+        None,
+        StorageQuota::default(),
+    )
+}
+
+/// Like [`create_test_app_with_state`], with uploads going to `storage`
+/// instead of [`test_upload_dir`].
+pub fn create_test_app_with_storage(pool: PgPool, storage: Arc<dyn Storage>) -> (Router, AppState) {
+    build_test_app(
+        pool,
+        false,
+        None,
+        None,
+        None,
+        Some(storage),
+        StorageQuota::default(),
+    )
+}
+
+/// Like [`create_test_app_with_state`], with attachment storage limited by
+/// `quota`.
+pub fn create_test_app_with_quota(pool: PgPool, quota: StorageQuota) -> (Router, AppState) {
+    build_test_app(pool, false, None, None, None, None, quota)
+}
+
+/// Single sign-on settings for a stand-in provider at `issuer`, with no
+/// group mappings.
+pub fn test_oidc_config(issuer: &str) -> together_server::config::OidcConfig {
+    together_server::config::OidcConfig {
+        issuer: issuer.to_string(),
+        client_id: TEST_OIDC_CLIENT_ID.to_string(),
+        client_secret: Some(TEST_OIDC_CLIENT_SECRET.to_string()),
+        redirect_uri: "http://localhost:5173/auth/sso".to_string(),
+        scopes: vec!["openid".into(), "email".into(), "profile".into()],
+        provider_name: "Test IdP".to_string(),
+        groups_claim: "groups".to_string(),
+        admin_groups: vec![],
+        role_mappings: vec![],
+        disable_registration: false,
+    }
+}
+
+fn build_test_app(
+    pool: PgPool,
+    with_sfu: bool,
+    web_push: Option<together_server::config::WebPushConfig>,
+    mail: Option<together_server::config::MailConfig>,
+    oidc: Option<together_server::config::OidcConfig>,
+    storage: Option<Arc<dyn Storage>>,
+    storage_quota: StorageQuota,
+) -> (Router, AppState) {
     let http_client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(10))
         .build()
@@ -62,27 +217,96 @@ pub fn create_test_app(pool: PgPool) -> Router {
             server_port: 8080,
             is_dev: true,
             upload_dir: test_upload_dir(),
+            upload_staging_dir: test_staging_dir(),
+            s3: None,
+            storage_quota: StorageQuota::default(),
+            attachment_gc_grace: std::time::Duration::from_secs(168 * 60 * 60),
             allowed_origins: vec![],
             turn: None,
+            sfu: None,
+            event_bus: together_server::config::EventBusKind::Local,
+            replica_count: 1,
+            web_push: None,
+            mail: None,
+            webauthn: None,
+            oidc: None,
         }
     });
+    let config = together_server::config::Config {
+        webauthn: Some(together_server::config::WebAuthnConfig {
+            rp_id: TEST_WEBAUTHN_RP_ID.to_string(),
+            rp_name: "Together".to_string(),
+            origins: vec![TEST_WEBAUTHN_ORIGIN.to_string()],
+        }),
+        oidc: oidc.clone(),
+        upload_staging_dir: test_staging_dir(),
+        storage_quota,
+        ..config
+    };
 
     let webhook_queue = webhook_delivery::start_worker(pool.clone(), http_client.clone());
 
+    let local_state = LocalState {
+        connections: ConnectionManager::new(),
+        channel_viewers: ChannelViewerCache::new(),
+        automod: AutomodCache::new(),
+    };
+
+    let events: Arc<dyn EventBus> = Arc::new(LocalEventBus::new(local_state.clone()));
+    let sfu = with_sfu.then(|| {
+        together_server::sfu::Sfu::new(
+            &together_server::config::SfuConfig {
+                public_ips: vec![],
+                udp_ports: None,
+            },
+            events.clone(),
+        )
+        .expect("Failed to start test SFU")
+    });
+
+    let (push, push_jobs) = match &web_push {
+        Some(config) => {
+            let (push, jobs) = together_server::push::PushDispatcher::new(config)
+                .expect("Invalid test Web Push configuration");
+            (Some(push), Some(jobs))
+        }
+        None => (None, None),
+    };
+
+    let mailer = mail.map(|config| {
+        together_server::mail::Mailer::new(&config).expect("Invalid test email configuration")
+    });
+
+    let oidc = oidc.map(|config| {
+        Arc::new(together_server::auth::oidc::OidcClient::new(
+            config,
+            http_client.clone(),
+        ))
+    });
+
     let state = AppState {
         pool,
         jwt_secret: Arc::from(TEST_JWT_SECRET),
-        connections: ConnectionManager::new(),
-        upload_dir: test_upload_dir(),
+        connections: local_state.connections.clone(),
+        storage: storage.unwrap_or_else(|| Arc::new(LocalStorage::new(test_upload_dir()))),
         link_preview_cache: Arc::new(RwLock::new(HashMap::new())),
         http_client,
         giphy_api_key: None,
         config: Arc::new(config),
-        bot_rate_limiter: AppState::new_bot_rate_limiter(),
-        go_live_sessions: Arc::new(RwLock::new(HashMap::new())),
+        bot_rate_limiter: AppState::new_bot_rate_limiter(1),
+        channel_viewers: local_state.channel_viewers.clone(),
+        automod: local_state.automod.clone(),
         webhook_queue,
+        events,
+        sfu,
+        push,
+        mailer,
+        oidc,
     };
-    Router::new()
+    if let Some(jobs) = push_jobs {
+        together_server::push::start_worker(state.clone(), jobs);
+    }
+    let router = Router::new()
         .route("/health", get(handlers::health_check))
         .route("/health/ready", get(handlers::readiness_check))
         .route("/health/live", get(handlers::liveness_check))
@@ -105,6 +329,10 @@ pub fn create_test_app(pool: PgPool) -> Router {
             "/admin/users/:user_id",
             patch(handlers::admin::update_user).delete(handlers::admin::delete_user),
         )
+        .route(
+            "/admin/users/:user_id/two-factor",
+            delete(handlers::admin::reset_two_factor),
+        )
         .route("/admin/servers", get(handlers::admin::list_servers))
         .route(
             "/admin/servers/:server_id",
@@ -144,6 +372,15 @@ pub fn create_test_app(pool: PgPool) -> Router {
             "/servers/:id/members/:user_id/timeout",
             post(handlers::moderation::timeout_member).delete(handlers::moderation::remove_timeout),
         )
+        .route(
+            "/servers/:id/members/:user_id/voice",
+            patch(handlers::voice::moderate_voice_state)
+                .delete(handlers::voice::disconnect_voice_member),
+        )
+        .route(
+            "/servers/:id/members/:user_id/voice/move",
+            post(handlers::voice::move_voice_member),
+        )
         // Role management routes
         .route(
             "/servers/:id/roles",
@@ -211,6 +448,19 @@ pub fn create_test_app(pool: PgPool) -> Router {
             "/channels/:channel_id/messages",
             get(handlers::messages::list_messages),
         )
+        .route(
+            "/channels/:channel_id/scheduled-messages",
+            post(handlers::scheduled_messages::create_scheduled_message),
+        )
+        .route(
+            "/users/@me/scheduled-messages",
+            get(handlers::scheduled_messages::list_scheduled_messages),
+        )
+        .route(
+            "/users/@me/scheduled-messages/:id",
+            patch(handlers::scheduled_messages::update_scheduled_message)
+                .delete(handlers::scheduled_messages::delete_scheduled_message),
+        )
         .route(
             "/messages/:message_id",
             patch(handlers::messages::update_message),
@@ -228,6 +478,23 @@ pub fn create_test_app(pool: PgPool) -> Router {
             "/channels/:channel_id/messages/:message_id/thread",
             post(handlers::messages::create_thread_reply),
         )
+        // Forum routes
+        .route(
+            "/channels/:channel_id/forum/tags",
+            get(handlers::forum::list_forum_tags).post(handlers::forum::create_forum_tag),
+        )
+        .route(
+            "/channels/:channel_id/forum/tags/:tag_id",
+            patch(handlers::forum::update_forum_tag).delete(handlers::forum::delete_forum_tag),
+        )
+        .route(
+            "/channels/:channel_id/posts",
+            get(handlers::forum::list_forum_posts),
+        )
+        .route(
+            "/channels/:channel_id/posts/:post_id",
+            patch(handlers::forum::update_forum_post),
+        )
         // Attachment routes
         .route(
             "/messages/:message_id/attachments",
@@ -237,6 +504,17 @@ pub fn create_test_app(pool: PgPool) -> Router {
             "/messages/:message_id/attachments",
             get(handlers::attachments::list_attachments),
         )
+        .route(
+            "/messages/:message_id/attachments/uploads",
+            post(handlers::attachments::attach_uploads),
+        )
+        .route("/uploads", post(handlers::uploads::create_upload))
+        .route(
+            "/uploads/:id",
+            head(handlers::uploads::head_upload)
+                .patch(handlers::uploads::patch_upload)
+                .delete(handlers::uploads::delete_upload),
+        )
         .route(
             "/files/:message_id/*filepath",
             get(handlers::attachments::serve_file),
@@ -281,9 +559,104 @@ pub fn create_test_app(pool: PgPool) -> Router {
             "/channels/:channel_id/ack",
             post(handlers::read_states::ack_channel),
         )
+        .route("/servers/:id/ack", post(handlers::read_states::ack_server))
+        // Notification settings routes
+        .route(
+            "/users/@me/notification-settings",
+            get(handlers::notification_settings::list_notification_settings),
+        )
+        .route(
+            "/servers/:id/notification-settings",
+            put(handlers::notification_settings::put_server_notification_settings)
+                .delete(handlers::notification_settings::delete_server_notification_settings),
+        )
+        .route(
+            "/servers/:id/categories/:category/notification-settings",
+            put(handlers::notification_settings::put_category_notification_settings)
+                .delete(handlers::notification_settings::delete_category_notification_settings),
+        )
+        .route(
+            "/channels/:channel_id/notification-settings",
+            put(handlers::notification_settings::put_channel_notification_settings)
+                .delete(handlers::notification_settings::delete_channel_notification_settings),
+        )
+        .route(
+            "/dm-channels/:id/notification-settings",
+            put(handlers::notification_settings::put_dm_notification_settings)
+                .delete(handlers::notification_settings::delete_dm_notification_settings),
+        )
+        // Web Push routes
+        .route(
+            "/push/vapid-public-key",
+            get(handlers::push::get_vapid_public_key),
+        )
+        .route(
+            "/users/@me/push-subscriptions",
+            get(handlers::push::list_push_subscriptions)
+                .post(handlers::push::create_push_subscription),
+        )
+        .route(
+            "/users/@me/push-subscriptions/:id",
+            delete(handlers::push::delete_push_subscription),
+        )
+        .route(
+            "/users/@me/push-preferences",
+            get(handlers::push::get_push_preferences).put(handlers::push::update_push_preferences),
+        )
+        .route(
+            "/users/@me/email/verification",
+            post(handlers::email::resend_verification_email),
+        )
+        .route(
+            "/users/@me/email-preferences",
+            get(handlers::email::get_email_preferences)
+                .put(handlers::email::update_email_preferences),
+        )
+        .route(
+            "/users/@me/two-factor",
+            get(handlers::two_factor::get_two_factor),
+        )
+        .route(
+            "/users/@me/two-factor/setup",
+            post(handlers::two_factor::setup_two_factor),
+        )
+        .route(
+            "/users/@me/two-factor/confirm",
+            post(handlers::two_factor::confirm_two_factor),
+        )
+        .route(
+            "/users/@me/two-factor/disable",
+            post(handlers::two_factor::disable_two_factor),
+        )
+        .route(
+            "/users/@me/two-factor/recovery-codes",
+            post(handlers::two_factor::regenerate_recovery_codes),
+        )
+        .route(
+            "/users/@me/passkeys",
+            get(handlers::passkeys::list_passkeys).post(handlers::passkeys::create_passkey),
+        )
+        .route(
+            "/users/@me/passkeys/options",
+            post(handlers::passkeys::passkey_creation_options),
+        )
+        .route(
+            "/users/@me/passkeys/:id",
+            patch(handlers::passkeys::update_passkey).delete(handlers::passkeys::delete_passkey),
+        )
         // DM routes
         .route("/dm-channels", post(handlers::dm::open_dm_channel))
         .route("/dm-channels", get(handlers::dm::list_dm_channels))
+        .route("/dm-channels/groups", post(handlers::dm::create_group_dm))
+        .route(
+            "/dm-channels/:id",
+            patch(handlers::dm::update_group_dm).delete(handlers::dm::leave_dm_channel),
+        )
+        .route(
+            "/dm-channels/:id/recipients/:user_id",
+            put(handlers::dm::add_group_dm_recipient)
+                .delete(handlers::dm::remove_group_dm_recipient),
+        )
         .route(
             "/dm-channels/:id/messages",
             post(handlers::dm::send_dm_message),
@@ -327,6 +700,15 @@ pub fn create_test_app(pool: PgPool) -> Router {
             "/servers/:id/automod/words/:word",
             delete(handlers::automod::remove_word_filter),
         )
+        .route(
+            "/servers/:id/automod/rules",
+            get(handlers::automod::list_automod_rules).post(handlers::automod::create_automod_rule),
+        )
+        .route(
+            "/servers/:id/automod/rules/:rule_id",
+            patch(handlers::automod::update_automod_rule)
+                .delete(handlers::automod::delete_automod_rule),
+        )
         .route(
             "/servers/:id/automod/logs",
             get(handlers::automod::list_automod_logs),
@@ -357,6 +739,37 @@ pub fn create_test_app(pool: PgPool) -> Router {
             post(handlers::auth::forgot_password),
         )
         .route("/auth/reset-password", post(handlers::auth::reset_password))
+        .route("/auth/verify-email", post(handlers::email::verify_email))
+        .route(
+            "/auth/two-factor/verify",
+            post(handlers::two_factor::verify_two_factor),
+        )
+        .route(
+            "/auth/two-factor/setup",
+            post(handlers::two_factor::setup_two_factor_at_login),
+        )
+        .route(
+            "/auth/two-factor/enroll",
+            post(handlers::two_factor::enroll_two_factor),
+        )
+        .route(
+            "/auth/two-factor/passkey/options",
+            post(handlers::passkeys::passkey_second_factor_options),
+        )
+        .route(
+            "/auth/two-factor/passkey",
+            post(handlers::passkeys::verify_two_factor_passkey),
+        )
+        .route(
+            "/auth/passkey/options",
+            post(handlers::passkeys::passkey_login_options),
+        )
+        .route(
+            "/auth/passkey/login",
+            post(handlers::passkeys::passkey_login),
+        )
+        .route("/auth/oidc/authorize", post(handlers::oidc::oidc_authorize))
+        .route("/auth/oidc/callback", post(handlers::oidc::oidc_callback))
         // Custom emoji routes
         .route(
             "/servers/:id/emojis",
@@ -398,6 +811,34 @@ pub fn create_test_app(pool: PgPool) -> Router {
             "/servers/:id/webhooks/:webhook_id/test",
             post(handlers::webhooks::test_webhook),
         )
+        .route(
+            "/servers/:id/webhooks/:webhook_id/deliveries",
+            get(handlers::webhooks::list_deliveries),
+        )
+        .route(
+            "/servers/:id/webhooks/:webhook_id/deliveries/:delivery_id/redeliver",
+            post(handlers::webhooks::redeliver_delivery),
+        )
+        // Incoming webhook management (protected, admin/owner only)
+        .route(
+            "/servers/:id/incoming-webhooks",
+            get(handlers::incoming_webhooks::list_incoming_webhooks)
+                .post(handlers::incoming_webhooks::create_incoming_webhook),
+        )
+        .route(
+            "/servers/:id/incoming-webhooks/:webhook_id",
+            patch(handlers::incoming_webhooks::update_incoming_webhook)
+                .delete(handlers::incoming_webhooks::delete_incoming_webhook),
+        )
+        .route(
+            "/servers/:id/incoming-webhooks/:webhook_id/token",
+            post(handlers::incoming_webhooks::regenerate_incoming_webhook_token),
+        )
+        // Incoming webhook execution (public — the token in the URL is the credential)
+        .route(
+            "/webhooks/:webhook_id/:token",
+            post(handlers::incoming_webhooks::execute_incoming_webhook),
+        )
         // Go Live routes
         .route(
             "/channels/:channel_id/go-live",
@@ -411,13 +852,23 @@ pub fn create_test_app(pool: PgPool) -> Router {
             "/channels/:channel_id/go-live",
             get(handlers::go_live::get_go_live),
         )
+        .route(
+            "/channels/:channel_id/go-live/viewers",
+            post(handlers::go_live::join_go_live).delete(handlers::go_live::leave_go_live),
+        )
+        .route(
+            "/servers/:id/go-live/settings",
+            get(handlers::go_live::get_go_live_settings)
+                .patch(handlers::go_live::update_go_live_settings),
+        )
         // Export routes
         .route("/servers/:id/export", get(handlers::export::export_server))
         // Giphy routes
         .route("/giphy/search", get(handlers::giphy::search_giphy))
         // WebSocket gateway
         .route("/ws", get(websocket_handler))
-        .with_state(state)
+        .with_state(state.clone());
+    (router, state)
 }
 
 /// Generate a username that is unique per test invocation.
@@ -578,6 +1029,28 @@ pub async fn get_raw_no_auth(app: Router, uri: &str) -> (StatusCode, Vec<u8>) {
     (status, bytes.to_vec())
 }
 
+/// GET a URL with extra request headers (and auth when `token` is given) and
+/// return the status, response headers and raw body.
+pub async fn get_with_headers(
+    app: Router,
+    uri: &str,
+    token: Option<&str>,
+    headers: &[(header::HeaderName, &str)],
+) -> (StatusCode, header::HeaderMap, Vec<u8>) {
+    let mut req = Request::builder().method(Method::GET).uri(uri);
+    if let Some(token) = token {
+        req = req.header(header::AUTHORIZATION, format!("Bearer {token}"));
+    }
+    for (name, value) in headers {
+        req = req.header(name, *value);
+    }
+    let response = app.oneshot(req.body(Body::empty()).unwrap()).await.unwrap();
+    let status = response.status();
+    let response_headers = response.headers().clone();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    (status, response_headers, bytes.to_vec())
+}
+
 // ── Scenario helpers ─────────────────────────────────────────────────────────
 
 /// Register a fresh user and return the full response body.
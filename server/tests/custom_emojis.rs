@@ -13,9 +13,9 @@ fn tiny_png() -> Vec<u8> {
     vec![
         0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
         0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f,
-        0x15, 0xc4, 0x89, 0x00, 0x00, 0x00, 0x0b, 0x49, 0x44, 0x41, 0x54, 0x08, 0xd7, 0x63, 0x60,
-        0x00, 0x00, 0x00, 0x02, 0x00, 0x01, 0xe2, 0x21, 0xbc, 0x33, 0x00, 0x00, 0x00, 0x00, 0x49,
-        0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+        0x15, 0xc4, 0x89, 0x00, 0x00, 0x00, 0x0b, 0x49, 0x44, 0x41, 0x54, 0x78, 0xda, 0x63, 0x60,
+        0x00, 0x02, 0x00, 0x00, 0x05, 0x00, 0x01, 0xe9, 0xfa, 0xdc, 0xd8, 0x00, 0x00, 0x00, 0x00,
+        0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
     ]
 }
 
@@ -35,7 +35,17 @@ async fn upload_emoji(
     server_id: &str,
     name: &str,
 ) -> (StatusCode, serde_json::Value) {
-    let png = tiny_png();
+    upload_emoji_image(app, token, server_id, name, &tiny_png()).await
+}
+
+/// Like [`upload_emoji`], with `png` as the image.
+async fn upload_emoji_image(
+    app: axum::Router,
+    token: &str,
+    server_id: &str,
+    name: &str,
+    png: &[u8],
+) -> (StatusCode, serde_json::Value) {
     let files = [
         MultipartFile {
             field_name: "name",
@@ -47,7 +57,7 @@ async fn upload_emoji(
             field_name: "image",
             filename: "emoji.png",
             content_type: "image/png",
-            data: &png,
+            data: png,
         },
     ];
     post_multipart_authed(app, &format!("/servers/{server_id}/emojis"), token, &files).await
@@ -243,3 +253,70 @@ async fn non_member_cannot_list() {
     let (status, _) = get_authed(app.clone(), &format!("/servers/{sid}/emojis"), &outsider).await;
     assert_eq!(status, StatusCode::NOT_FOUND);
 }
+
+// ============================================================================
+// GET /emojis/:emoji_id?size= — thumbnails
+// ============================================================================
+
+#[tokio::test]
+async fn emoji_dimensions_and_thumbnails() {
+    use image::ImageEncoder;
+
+    let pool = test_pool().await;
+    let app = create_test_app(pool);
+    let (token, sid) = setup_server(app.clone()).await;
+
+    let pixels = image::RgbaImage::from_pixel(200, 100, image::Rgba([250, 200, 0, 255]));
+    let mut png = Vec::new();
+    image::codecs::png::PngEncoder::new(&mut png)
+        .write_image(&pixels, 200, 100, image::ExtendedColorType::Rgba8)
+        .unwrap();
+
+    let (status, body) = upload_emoji_image(app.clone(), &token, &sid, "wide", &png).await;
+    assert_eq!(status, StatusCode::CREATED, "upload failed: {body}");
+    assert_eq!(body["width"], 200);
+    assert_eq!(body["height"], 100);
+    let url = body["url"].as_str().unwrap();
+
+    let (status, thumb) = get_raw_no_auth(app.clone(), &format!("{url}?size=64")).await;
+    assert_eq!(status, StatusCode::OK);
+    let decoded = image::load_from_memory_with_format(&thumb, image::ImageFormat::WebP).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (64, 32));
+
+    let (status, _) = get_raw_no_auth(app.clone(), &format!("{url}?size=48")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // A 1×1 emoji has no thumbnails; the original is served.
+    let (_, body) = upload_emoji(app.clone(), &token, &sid, "dot").await;
+    let url = body["url"].as_str().unwrap();
+    let (status, bytes) = get_raw_no_auth(app, &format!("{url}?size=32")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(bytes, tiny_png());
+}
+
+// ============================================================================
+// GET /emojis/:emoji_id — revalidation
+// ============================================================================
+
+#[tokio::test]
+async fn emoji_revalidates_with_etag() {
+    use axum::http::header;
+
+    let pool = test_pool().await;
+    let app = create_test_app(pool);
+    let (token, sid) = setup_server(app.clone()).await;
+    let (_, body) = upload_emoji(app.clone(), &token, &sid, "cached").await;
+    let url = body["url"].as_str().unwrap();
+
+    let (status, headers, bytes) = get_with_headers(app.clone(), url, None, &[]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(bytes, tiny_png());
+    assert_eq!(headers[header::CACHE_CONTROL], "public, max-age=86400");
+    let etag = headers[header::ETAG].to_str().unwrap().to_string();
+
+    let (status, headers, bytes) =
+        get_with_headers(app, url, None, &[(header::IF_NONE_MATCH, &etag)]).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+    assert_eq!(headers[header::CACHE_CONTROL], "public, max-age=86400");
+    assert!(bytes.is_empty());
+}
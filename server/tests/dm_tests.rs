@@ -395,3 +395,328 @@ async fn both_participants_see_dm_channel() {
     // B sees A as the recipient
     assert_eq!(channels[0]["recipient"]["id"], id_a);
 }
+
+// ============================================================================
+// Group DMs
+// ============================================================================
+
+/// Register `n` users; return their (token, id) pairs.
+async fn setup_users(app: axum::Router, n: usize) -> Vec<(String, String)> {
+    let mut users = Vec::with_capacity(n);
+    for _ in 0..n {
+        let body = common::register_user(app.clone(), &common::unique_username(), "pass1234").await;
+        users.push((
+            body["access_token"].as_str().unwrap().to_owned(),
+            body["user"]["id"].as_str().unwrap().to_owned(),
+        ));
+    }
+    users
+}
+
+/// Create a group DM owned by `users[0]` with everyone else in `users`.
+async fn create_group(
+    app: axum::Router,
+    users: &[(String, String)],
+    name: &str,
+) -> serde_json::Value {
+    let ids: Vec<&str> = users[1..].iter().map(|(_, id)| id.as_str()).collect();
+    let (status, body) = common::post_json_authed(
+        app,
+        "/dm-channels/groups",
+        &users[0].0,
+        json!({ "user_ids": ids, "name": name }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "create group failed: {body}");
+    body
+}
+
+fn drain_events(rx: &mut tokio::sync::mpsc::UnboundedReceiver<String>) -> Vec<String> {
+    let mut events = Vec::new();
+    while let Ok(frame) = rx.try_recv() {
+        let v: serde_json::Value = serde_json::from_str(&frame).unwrap();
+        events.push(v["t"].as_str().unwrap_or_default().to_owned());
+    }
+    events
+}
+
+#[tokio::test]
+async fn create_group_dm_lists_all_recipients() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let users = setup_users(app.clone(), 3).await;
+
+    let group = create_group(app.clone(), &users, "Weekend plans").await;
+    assert_eq!(group["is_group"], true);
+    assert_eq!(group["name"], "Weekend plans");
+    assert_eq!(group["owner_id"], users[0].1);
+    assert!(group["recipient"].is_null());
+    assert_eq!(group["recipients"].as_array().unwrap().len(), 2);
+
+    // Every participant sees the group, minus themselves in `recipients`.
+    let (_, list) = common::get_authed(app, "/dm-channels", &users[2].0).await;
+    assert_eq!(list[0]["id"], group["id"]);
+    let recipient_ids: Vec<&str> = list[0]["recipients"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|u| u["id"].as_str().unwrap())
+        .collect();
+    assert!(recipient_ids.contains(&users[0].1.as_str()));
+    assert!(!recipient_ids.contains(&users[2].1.as_str()));
+}
+
+#[tokio::test]
+async fn create_group_dm_validates_participants() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let users = setup_users(app.clone(), 2).await;
+    let token = &users[0].0;
+
+    // Listing only yourself leaves nobody to talk to.
+    let (status, _) = common::post_json_authed(
+        app.clone(),
+        "/dm-channels/groups",
+        token,
+        json!({ "user_ids": [users[0].1] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let too_many: Vec<String> = (0..10).map(|_| Uuid::new_v4().to_string()).collect();
+    let (status, _) = common::post_json_authed(
+        app.clone(),
+        "/dm-channels/groups",
+        token,
+        json!({ "user_ids": too_many }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = common::post_json_authed(
+        app,
+        "/dm-channels/groups",
+        token,
+        json!({ "user_ids": [users[1].1, Uuid::new_v4()] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn open_dm_channel_ignores_shared_groups() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let users = setup_users(app.clone(), 3).await;
+    let group = create_group(app.clone(), &users, "Trio").await;
+
+    let dm = common::open_dm_channel(app, &users[0].0, &users[1].1).await;
+    assert_ne!(dm["id"], group["id"]);
+    assert_eq!(dm["is_group"], false);
+    assert_eq!(dm["recipient"]["id"], users[1].1);
+}
+
+#[tokio::test]
+async fn any_member_can_rename_group_dm() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let users = setup_users(app.clone(), 3).await;
+    let group = create_group(app.clone(), &users, "Old name").await;
+    let uri = format!("/dm-channels/{}", group["id"].as_str().unwrap());
+
+    let (status, body) = common::patch_json_authed(
+        app.clone(),
+        &uri,
+        &users[1].0,
+        json!({ "name": "New name" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["name"], "New name");
+
+    // An empty name clears it.
+    let (_, body) =
+        common::patch_json_authed(app.clone(), &uri, &users[1].0, json!({ "name": "  " })).await;
+    assert!(body["name"].is_null());
+
+    let outsider = setup_users(app.clone(), 1).await;
+    let (status, _) =
+        common::patch_json_authed(app, &uri, &outsider[0].0, json!({ "name": "Mine" })).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn rename_rejects_one_to_one_dm() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token_a, _id_a, _token_b, id_b) = setup_two_users(app.clone()).await;
+    let dm = common::open_dm_channel(app.clone(), &token_a, &id_b).await;
+    let uri = format!("/dm-channels/{}", dm["id"].as_str().unwrap());
+
+    let (status, _) =
+        common::patch_json_authed(app.clone(), &uri, &token_a, json!({ "name": "Us" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = common::delete_authed(app, &uri, &token_a).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn add_recipient_is_idempotent_and_capped() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let users = setup_users(app.clone(), 10).await;
+    let group = create_group(app.clone(), &users[..9], "Almost full").await;
+    let group_id = group["id"].as_str().unwrap();
+
+    // Any member may add participants.
+    let extra = setup_users(app.clone(), 1).await;
+    let uri = format!("/dm-channels/{group_id}/recipients/{}", users[9].1);
+    let (status, _) = common::put_authed(app.clone(), &uri, &users[3].0).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = common::put_authed(app.clone(), &uri, &users[3].0).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (_, list) = common::get_authed(app.clone(), "/dm-channels", &users[9].0).await;
+    assert_eq!(list[0]["id"], group_id);
+    assert_eq!(list[0]["recipients"].as_array().unwrap().len(), 9);
+
+    let (status, _) = common::put_authed(
+        app,
+        &format!("/dm-channels/{group_id}/recipients/{}", extra[0].1),
+        &users[0].0,
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn only_owner_can_remove_others() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let users = setup_users(app.clone(), 3).await;
+    let group = create_group(app.clone(), &users, "Crew").await;
+    let group_id = group["id"].as_str().unwrap();
+    let uri = format!("/dm-channels/{group_id}/recipients/{}", users[2].1);
+
+    let (status, _) = common::delete_authed(app.clone(), &uri, &users[1].0).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = common::delete_authed(app.clone(), &uri, &users[0].0).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (_, list) = common::get_authed(app.clone(), "/dm-channels", &users[2].0).await;
+    assert_eq!(list, json!([]));
+    let (status, _) = common::get_authed(
+        app,
+        &format!("/dm-channels/{group_id}/messages"),
+        &users[2].0,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn owner_leaving_transfers_ownership() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let users = setup_users(app.clone(), 3).await;
+    let group = create_group(app.clone(), &users, "Handoff").await;
+    let uri = format!("/dm-channels/{}", group["id"].as_str().unwrap());
+
+    let (status, _) = common::delete_authed(app.clone(), &uri, &users[0].0).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    // The longest-standing remaining member becomes the owner.
+    let (_, list) = common::get_authed(app, "/dm-channels", &users[2].0).await;
+    assert_eq!(list[0]["owner_id"], users[1].1);
+    assert_eq!(list[0]["recipients"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn last_member_leaving_deletes_group() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool.clone());
+    let users = setup_users(app.clone(), 3).await;
+    let group = create_group(app.clone(), &users, "Short-lived").await;
+    let group_id: Uuid = group["id"].as_str().unwrap().parse().unwrap();
+    let uri = format!("/dm-channels/{group_id}");
+
+    for (token, _) in &users {
+        let (status, _) = common::delete_authed(app.clone(), &uri, token).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+
+    let exists: bool =
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM direct_message_channels WHERE id = $1)")
+            .bind(group_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert!(!exists);
+}
+
+#[tokio::test]
+async fn group_messages_reach_every_member() {
+    let pool = common::test_pool().await;
+    let (app, state) = common::create_test_app_with_state(pool);
+    let users = setup_users(app.clone(), 3).await;
+    let group = create_group(app.clone(), &users, "Broadcast").await;
+    let group_id = group["id"].as_str().unwrap();
+
+    let mut receivers = Vec::new();
+    for (_, id) in &users {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        state.connections.add(id.parse().unwrap(), tx).await;
+        receivers.push(rx);
+    }
+
+    common::send_dm_message(app.clone(), &users[1].0, group_id, "hello all").await;
+    for rx in &mut receivers {
+        assert_eq!(drain_events(rx), vec!["DM_MESSAGE_CREATE"]);
+    }
+
+    let (status, _) = common::post_json_authed(
+        app,
+        &format!("/dm-channels/{group_id}/ack"),
+        &users[2].0,
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn membership_changes_emit_gateway_events() {
+    let pool = common::test_pool().await;
+    let (app, state) = common::create_test_app_with_state(pool);
+    let users = setup_users(app.clone(), 4).await;
+    let group = create_group(app.clone(), &users[..3], "Events").await;
+    let group_id = group["id"].as_str().unwrap();
+
+    let (owner_tx, mut owner_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (new_tx, mut new_rx) = tokio::sync::mpsc::unbounded_channel();
+    state
+        .connections
+        .add(users[0].1.parse().unwrap(), owner_tx)
+        .await;
+    state
+        .connections
+        .add(users[3].1.parse().unwrap(), new_tx)
+        .await;
+
+    let uri = format!("/dm-channels/{group_id}/recipients/{}", users[3].1);
+    common::put_authed(app.clone(), &uri, &users[0].0).await;
+    assert_eq!(
+        drain_events(&mut owner_rx),
+        vec!["DM_CHANNEL_RECIPIENT_ADD"]
+    );
+    assert_eq!(drain_events(&mut new_rx), vec!["DM_CHANNEL_CREATE"]);
+
+    common::delete_authed(app, &uri, &users[3].0).await;
+    assert_eq!(
+        drain_events(&mut owner_rx),
+        vec!["DM_CHANNEL_RECIPIENT_REMOVE"]
+    );
+    assert_eq!(drain_events(&mut new_rx), vec!["DM_CHANNEL_DELETE"]);
+}
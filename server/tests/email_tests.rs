@@ -0,0 +1,403 @@
+mod common;
+
+use std::{path::Path, time::Duration};
+
+use axum::{http::StatusCode, Router};
+use serde_json::{json, Value};
+
+// ============================================================================
+// Test fixture helpers
+// ============================================================================
+
+/// Pull the value of `param` out of the first link in an email's text body.
+fn link_param(email: &Value, param: &str) -> String {
+    let text = email["text"].as_str().unwrap();
+    let start = text
+        .find(&format!("{param}="))
+        .unwrap_or_else(|| panic!("no {param} link in email: {text}"))
+        + param.len()
+        + 1;
+    text[start..]
+        .split(|c: char| c.is_whitespace() || c == '&')
+        .next()
+        .unwrap()
+        .to_string()
+}
+
+/// Register a user with an email address and return (token, email).
+async fn register_with_email(app: Router, username: &str) -> (String, String) {
+    let email = format!("{username}@example.com");
+    let (status, body) = common::post_json(
+        app,
+        "/auth/register",
+        json!({ "username": username, "password": "password123", "email": email }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "register failed: {body}");
+    (body["access_token"].as_str().unwrap().to_string(), email)
+}
+
+/// Register a user and verify their address through the emailed link.
+async fn register_verified(app: Router, dir: &Path, username: &str) -> (String, String) {
+    let (token, email) = register_with_email(app.clone(), username).await;
+    let mail = common::read_mail(dir, 1).await;
+    let (status, body) = common::post_json(
+        app,
+        "/auth/verify-email",
+        json!({ "token": link_param(&mail[0], "token") }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "verify failed: {body}");
+    (token, email)
+}
+
+/// Emails already written to `dir` for one recipient. Digests are sent
+/// inline by `run_due`, and may include other test users' digests.
+fn read_mail_to(dir: &Path, to: &str) -> Vec<Value> {
+    std::fs::read_dir(dir)
+        .unwrap()
+        .filter_map(|e| serde_json::from_slice::<Value>(&std::fs::read(e.ok()?.path()).ok()?).ok())
+        .filter(|email| email["to"] == to)
+        .collect()
+}
+
+fn clear_mail(dir: &Path) {
+    let _ = std::fs::remove_dir_all(dir);
+    std::fs::create_dir_all(dir).unwrap();
+}
+
+// ============================================================================
+// Password reset
+// ============================================================================
+
+#[tokio::test]
+async fn forgot_password_emails_reset_link() {
+    let pool = common::test_pool().await;
+    let (app, _, dir) = common::create_test_app_with_mail(pool);
+    let username = common::unique_username();
+    let (_, email) = register_with_email(app.clone(), &username).await;
+    common::read_mail(&dir, 1).await;
+    clear_mail(&dir);
+
+    let (status, body) = common::post_json(
+        app.clone(),
+        "/auth/forgot-password",
+        json!({ "email": email }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "body: {body}");
+    assert!(body.get("token").is_none(), "token must not leak: {body}");
+
+    let mail = common::read_mail(&dir, 1).await;
+    assert_eq!(mail[0]["to"], email);
+    assert_eq!(mail[0]["subject"], "Reset your Together password");
+    assert!(mail[0]["text"]
+        .as_str()
+        .unwrap()
+        .contains("https://chat.example.com/reset-password?token="));
+    assert!(mail[0]["html"].as_str().unwrap().contains(&username));
+
+    let (status, body) = common::post_json(
+        app.clone(),
+        "/auth/reset-password",
+        json!({ "token": link_param(&mail[0], "token"), "new_password": "newpassword456" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "reset failed: {body}");
+
+    let (status, _) = common::post_json(
+        app,
+        "/auth/login",
+        json!({ "username": username, "password": "newpassword456" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn forgot_password_unknown_email_gets_same_response() {
+    let pool = common::test_pool().await;
+    let (app, _, dir) = common::create_test_app_with_mail(pool);
+    let (_, known) = register_with_email(app.clone(), &common::unique_username()).await;
+    common::read_mail(&dir, 1).await;
+    clear_mail(&dir);
+
+    let (status, unknown_body) = common::post_json(
+        app.clone(),
+        "/auth/forgot-password",
+        json!({ "email": format!("{}@example.com", common::unique_username()) }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, known_body) =
+        common::post_json(app, "/auth/forgot-password", json!({ "email": known })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(unknown_body, known_body);
+
+    // Only the known address gets mail.
+    let mail = common::read_mail(&dir, 1).await;
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    assert_eq!(mail[0]["to"], known);
+}
+
+#[tokio::test]
+async fn forgot_password_is_throttled_per_user() {
+    let pool = common::test_pool().await;
+    let (app, _, dir) = common::create_test_app_with_mail(pool);
+    let (_, email) = register_with_email(app.clone(), &common::unique_username()).await;
+    common::read_mail(&dir, 1).await;
+    clear_mail(&dir);
+
+    for _ in 0..2 {
+        let (status, _) = common::post_json(
+            app.clone(),
+            "/auth/forgot-password",
+            json!({ "email": email }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    common::read_mail(&dir, 1).await;
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+}
+
+// ============================================================================
+// Email verification
+// ============================================================================
+
+#[tokio::test]
+async fn register_sends_verification_and_verify_marks_user() {
+    let pool = common::test_pool().await;
+    let (app, _, dir) = common::create_test_app_with_mail(pool);
+    let username = common::unique_username();
+
+    let (token, email) = register_with_email(app.clone(), &username).await;
+    let (_, me) = common::get_authed(app.clone(), "/users/@me", &token).await;
+    assert_eq!(me["email_verified"], false);
+
+    let mail = common::read_mail(&dir, 1).await;
+    assert_eq!(mail[0]["to"], email);
+    let verify_token = link_param(&mail[0], "token");
+
+    let (status, body) = common::post_json(
+        app.clone(),
+        "/auth/verify-email",
+        json!({ "token": verify_token }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "body: {body}");
+
+    let (_, me) = common::get_authed(app.clone(), "/users/@me", &token).await;
+    assert_eq!(me["email_verified"], true);
+
+    // Tokens are single-use.
+    let (status, _) =
+        common::post_json(app, "/auth/verify-email", json!({ "token": verify_token })).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn verify_email_rejects_unknown_token() {
+    let pool = common::test_pool().await;
+    let (app, _, _) = common::create_test_app_with_mail(pool);
+
+    let (status, _) = common::post_json(
+        app,
+        "/auth/verify-email",
+        json!({ "token": "not-a-real-token" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn resend_verification_has_cooldown() {
+    let pool = common::test_pool().await;
+    let (app, _, dir) = common::create_test_app_with_mail(pool.clone());
+    let username = common::unique_username();
+    let (token, _) = register_with_email(app.clone(), &username).await;
+    common::read_mail(&dir, 1).await;
+
+    // Right after registering, the cooldown applies.
+    let (status, _) = common::post_json_authed(
+        app.clone(),
+        "/users/@me/email/verification",
+        &token,
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    sqlx::query(
+        "UPDATE email_verification_tokens SET created_at = NOW() - INTERVAL '2 minutes'
+         WHERE user_id = (SELECT id FROM users WHERE username = $1)",
+    )
+    .bind(&username)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let (status, _) =
+        common::post_json_authed(app, "/users/@me/email/verification", &token, json!({})).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    common::read_mail(&dir, 2).await;
+}
+
+#[tokio::test]
+async fn resend_verification_404_when_not_configured() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let token =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "pass1234").await;
+
+    let (status, _) =
+        common::post_json_authed(app, "/users/@me/email/verification", &token, json!({})).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+// ============================================================================
+// Invites
+// ============================================================================
+
+#[tokio::test]
+async fn invite_can_be_emailed() {
+    let pool = common::test_pool().await;
+    let (app, _, dir) = common::create_test_app_with_mail(pool);
+    let owner = common::unique_username();
+    let token = common::register_and_get_token(app.clone(), &owner, "pass1234").await;
+    let server = common::create_server(app.clone(), &token, "Mail Club").await;
+    let server_id = server["id"].as_str().unwrap();
+
+    let (status, invite) = common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/invites"),
+        &token,
+        json!({ "max_uses": 1, "email": "friend@example.com" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "body: {invite}");
+    let code = invite["code"].as_str().unwrap();
+
+    let mail = common::read_mail(&dir, 1).await;
+    assert_eq!(mail[0]["to"], "friend@example.com");
+    let text = mail[0]["text"].as_str().unwrap();
+    assert!(text.contains(&format!("https://chat.example.com/invite/{code}")));
+    assert!(text.contains(&owner));
+    assert!(text.contains("Mail Club"));
+
+    let (status, _) = common::post_json_authed(
+        app,
+        &format!("/servers/{server_id}/invites"),
+        &token,
+        json!({ "email": "not-an-email" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn invite_email_requires_mail_configuration() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let token =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "pass1234").await;
+    let server = common::create_server(app.clone(), &token, "No Mail").await;
+
+    let (status, _) = common::post_json_authed(
+        app,
+        &format!("/servers/{}/invites", server["id"].as_str().unwrap()),
+        &token,
+        json!({ "email": "friend@example.com" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+// ============================================================================
+// Mention digest
+// ============================================================================
+
+#[tokio::test]
+async fn digest_requires_verified_email() {
+    let pool = common::test_pool().await;
+    let (app, _, _) = common::create_test_app_with_mail(pool);
+    let (token, _) = register_with_email(app.clone(), &common::unique_username()).await;
+
+    let (status, prefs) =
+        common::get_authed(app.clone(), "/users/@me/email-preferences", &token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(prefs["mention_digest"], false);
+
+    let (status, _) = common::put_json_authed(
+        app,
+        "/users/@me/email-preferences",
+        &token,
+        json!({ "mention_digest": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn digest_lists_unread_mentions() {
+    let pool = common::test_pool().await;
+    let (app, state, dir) = common::create_test_app_with_mail(pool);
+
+    let owner_token =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "pass1234").await;
+    let server = common::create_server(app.clone(), &owner_token, "Digest Server").await;
+    let server_id = server["id"].as_str().unwrap();
+    common::make_server_public(app.clone(), &owner_token, server_id).await;
+    let channel = common::create_channel(app.clone(), &owner_token, server_id, "digest-chan").await;
+    let channel_id = channel["id"].as_str().unwrap();
+
+    let reader = common::unique_username();
+    let (reader_token, reader_email) = register_verified(app.clone(), &dir, &reader).await;
+    let (status, _) = common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/join"),
+        &reader_token,
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, prefs) = common::put_json_authed(
+        app.clone(),
+        "/users/@me/email-preferences",
+        &reader_token,
+        json!({ "mention_digest": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "body: {prefs}");
+    assert_eq!(prefs["mention_digest"], true);
+
+    // Nothing new yet: claimed, but no email.
+    clear_mail(&dir);
+    together_server::mail::digest::run_due(&state, Duration::ZERO)
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(read_mail_to(&dir, &reader_email).is_empty());
+
+    common::create_message(
+        app.clone(),
+        &owner_token,
+        channel_id,
+        &format!("hey @{reader}"),
+    )
+    .await;
+    together_server::mail::digest::run_due(&state, Duration::ZERO)
+        .await
+        .unwrap();
+
+    let mail = read_mail_to(&dir, &reader_email);
+    assert_eq!(mail.len(), 1, "expected one digest");
+    let text = mail[0]["text"].as_str().unwrap();
+    assert!(text.contains("#digest-chan in Digest Server"), "{text}");
+    assert!(text.contains("1 mention"), "{text}");
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use together_server::automod_engine::AutomodCache;
+use together_server::event_bus::{BusMessage, EventBus, LocalState, PgEventBus};
+use together_server::websocket::channel_viewers::ChannelViewerCache;
+use together_server::websocket::events::GatewayMessage;
+use together_server::websocket::ConnectionManager;
+
+fn local_state() -> LocalState {
+    LocalState {
+        connections: ConnectionManager::new(),
+        channel_viewers: ChannelViewerCache::new(),
+        automod: AutomodCache::new(),
+    }
+}
+
+/// Wait for the next frame on `rx`, failing the test after a few seconds.
+async fn next_frame(rx: &mut mpsc::UnboundedReceiver<String>) -> serde_json::Value {
+    let frame = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("timed out waiting for cross-node event")
+        .expect("channel closed");
+    serde_json::from_str(&frame).unwrap()
+}
+
+#[tokio::test]
+async fn dispatch_reaches_user_connected_to_other_node() {
+    let pool = common::test_pool().await;
+    let node_a = local_state();
+    let node_b = local_state();
+    let bus_a = PgEventBus::start(pool.clone(), node_a.clone())
+        .await
+        .unwrap();
+    let _bus_b = PgEventBus::start(pool.clone(), node_b.clone())
+        .await
+        .unwrap();
+
+    let user = Uuid::new_v4();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    node_b.connections.add(user, tx).await;
+
+    bus_a
+        .publish(BusMessage::Dispatch {
+            user_ids: vec![user],
+            event: GatewayMessage::dispatch("MESSAGE_CREATE", serde_json::json!({"x": 1})),
+        })
+        .await;
+
+    let frame = next_frame(&mut rx).await;
+    assert_eq!(frame["t"], "MESSAGE_CREATE");
+    assert_eq!(frame["d"]["x"], 1);
+    assert!(
+        frame["s"].is_u64(),
+        "receiving node assigns its own sequence number"
+    );
+}
+
+#[tokio::test]
+async fn publishing_node_delivers_locally_exactly_once() {
+    let pool = common::test_pool().await;
+    let node_a = local_state();
+    let bus_a = PgEventBus::start(pool.clone(), node_a.clone())
+        .await
+        .unwrap();
+
+    let user = Uuid::new_v4();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    node_a.connections.add(user, tx).await;
+
+    bus_a
+        .publish(BusMessage::Dispatch {
+            user_ids: vec![user],
+            event: GatewayMessage::dispatch("TYPING_START", serde_json::json!({})),
+        })
+        .await;
+
+    assert_eq!(next_frame(&mut rx).await["t"], "TYPING_START");
+    // The node's own NOTIFY echo must be ignored.
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn oversized_dispatch_is_spilled_and_delivered() {
+    let pool = common::test_pool().await;
+    let node_a = local_state();
+    let node_b = local_state();
+    let bus_a = PgEventBus::start(pool.clone(), node_a.clone())
+        .await
+        .unwrap();
+    let _bus_b = PgEventBus::start(pool.clone(), node_b.clone())
+        .await
+        .unwrap();
+
+    let user = Uuid::new_v4();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    node_b.connections.add(user, tx).await;
+
+    let content = "x".repeat(20_000);
+    bus_a
+        .publish(BusMessage::Dispatch {
+            user_ids: vec![user],
+            event: GatewayMessage::dispatch(
+                "MESSAGE_CREATE",
+                serde_json::json!({ "content": content }),
+            ),
+        })
+        .await;
+
+    let frame = next_frame(&mut rx).await;
+    assert_eq!(frame["d"]["content"].as_str().unwrap().len(), 20_000);
+}
@@ -0,0 +1,429 @@
+mod common;
+
+use std::io::Read;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+// ============================================================================
+// Test fixture helpers
+// ============================================================================
+
+/// Register an owner, create a server with a forum channel, and return
+/// `(app, owner_token, server_id, channel_id)`.
+async fn setup_forum() -> (axum::Router, String, String, String) {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let token =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "pass1234").await;
+    let server = common::create_server(app.clone(), &token, "Forum Guild").await;
+    let server_id = server["id"].as_str().unwrap().to_owned();
+
+    let (status, channel) = common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/channels"),
+        &token,
+        json!({ "name": "help", "type": "forum" }),
+    )
+    .await;
+    assert_eq!(
+        status,
+        StatusCode::CREATED,
+        "create forum failed: {channel}"
+    );
+    assert_eq!(channel["type"], "forum");
+    let channel_id = channel["id"].as_str().unwrap().to_owned();
+    (app, token, server_id, channel_id)
+}
+
+/// Register a user and join them to the (public) server; returns
+/// `(token, user_id)`.
+async fn join_member(app: axum::Router, owner: &str, server_id: &str) -> (String, String) {
+    common::make_server_public(app.clone(), owner, server_id).await;
+    let body = common::register_user(app.clone(), &common::unique_username(), "pass1234").await;
+    let token = body["access_token"].as_str().unwrap().to_owned();
+    let user_id = body["user"]["id"].as_str().unwrap().to_owned();
+    common::post_json_authed(
+        app,
+        &format!("/servers/{server_id}/join"),
+        &token,
+        json!({}),
+    )
+    .await;
+    (token, user_id)
+}
+
+async fn create_tag(app: axum::Router, token: &str, channel_id: &str, name: &str) -> String {
+    let (status, tag) = common::post_json_authed(
+        app,
+        &format!("/channels/{channel_id}/forum/tags"),
+        token,
+        json!({ "name": name }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "create tag failed: {tag}");
+    tag["id"].as_str().unwrap().to_owned()
+}
+
+async fn create_post(
+    app: axum::Router,
+    token: &str,
+    channel_id: &str,
+    title: &str,
+    tag_ids: &[&str],
+) -> Value {
+    let (status, post) = common::post_json_authed(
+        app,
+        &format!("/channels/{channel_id}/messages"),
+        token,
+        json!({ "content": format!("Body of {title}"), "title": title, "tag_ids": tag_ids }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "create post failed: {post}");
+    post
+}
+
+fn titles(posts: &Value) -> Vec<&str> {
+    posts
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["forum_post"]["title"].as_str().unwrap())
+        .collect()
+}
+
+// ============================================================================
+// Tags
+// ============================================================================
+
+#[tokio::test]
+async fn tag_crud() {
+    let (app, token, _, channel_id) = setup_forum().await;
+    let uri = format!("/channels/{channel_id}/forum/tags");
+
+    let bug = create_tag(app.clone(), &token, &channel_id, "bug").await;
+    create_tag(app.clone(), &token, &channel_id, "question").await;
+
+    let (status, _) =
+        common::post_json_authed(app.clone(), &uri, &token, json!({ "name": "bug" })).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, renamed) = common::patch_json_authed(
+        app.clone(),
+        &format!("{uri}/{bug}"),
+        &token,
+        json!({ "name": "defect", "emoji": "🐛" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(renamed["name"], "defect");
+    assert_eq!(renamed["emoji"], "🐛");
+
+    let (status, tags) = common::get_authed(app.clone(), &uri, &token).await;
+    assert_eq!(status, StatusCode::OK);
+    let names: Vec<&str> = tags
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["defect", "question"]);
+
+    let (status, _) = common::delete_authed(app.clone(), &format!("{uri}/{bug}"), &token).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, tags) = common::get_authed(app, &uri, &token).await;
+    assert_eq!(tags.as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn tags_require_manage_channels_and_forum_channel() {
+    let (app, owner, server_id, channel_id) = setup_forum().await;
+    let (member, _) = join_member(app.clone(), &owner, &server_id).await;
+
+    let (status, _) = common::post_json_authed(
+        app.clone(),
+        &format!("/channels/{channel_id}/forum/tags"),
+        &member,
+        json!({ "name": "spam" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let text = common::create_channel(app.clone(), &owner, &server_id, "general").await;
+    let (status, _) = common::post_json_authed(
+        app,
+        &format!("/channels/{}/forum/tags", text["id"].as_str().unwrap()),
+        &owner,
+        json!({ "name": "bug" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+// ============================================================================
+// Posts
+// ============================================================================
+
+#[tokio::test]
+async fn post_requires_title_in_forum_only() {
+    let (app, token, server_id, channel_id) = setup_forum().await;
+
+    let (status, _) = common::post_json_authed(
+        app.clone(),
+        &format!("/channels/{channel_id}/messages"),
+        &token,
+        json!({ "content": "no title" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let text = common::create_channel(app.clone(), &token, &server_id, "general").await;
+    let (status, _) = common::post_json_authed(
+        app,
+        &format!("/channels/{}/messages", text["id"].as_str().unwrap()),
+        &token,
+        json!({ "content": "hello", "title": "Not a forum" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn post_tags_must_belong_to_channel() {
+    let (app, token, server_id, channel_id) = setup_forum().await;
+    let (_, other) = common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/channels"),
+        &token,
+        json!({ "name": "ideas", "type": "forum" }),
+    )
+    .await;
+    let foreign = create_tag(app.clone(), &token, other["id"].as_str().unwrap(), "idea").await;
+
+    let (status, body) = common::post_json_authed(
+        app,
+        &format!("/channels/{channel_id}/messages"),
+        &token,
+        json!({ "content": "x", "title": "Crash on start", "tag_ids": [foreign] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "Tags must come from this channel's tag set");
+}
+
+#[tokio::test]
+async fn posts_sort_by_latest_activity_and_filter_by_tag() {
+    let (app, token, _, channel_id) = setup_forum().await;
+    let bug = create_tag(app.clone(), &token, &channel_id, "bug").await;
+
+    let first = create_post(app.clone(), &token, &channel_id, "First", &[&bug]).await;
+    create_post(app.clone(), &token, &channel_id, "Second", &[]).await;
+    create_post(app.clone(), &token, &channel_id, "Third", &[&bug]).await;
+    assert_eq!(first["forum_post"]["tag_ids"], json!([bug]));
+
+    let posts_uri = format!("/channels/{channel_id}/posts");
+    let (status, posts) = common::get_authed(app.clone(), &posts_uri, &token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(titles(&posts), vec!["Third", "Second", "First"]);
+
+    // A reply in the first post's thread moves it to the top.
+    let (status, reply) = common::post_json_authed(
+        app.clone(),
+        &format!(
+            "/channels/{channel_id}/messages/{}/thread",
+            first["id"].as_str().unwrap()
+        ),
+        &token,
+        json!({ "content": "Same here" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(reply["thread_id"], first["id"]);
+
+    let (_, posts) = common::get_authed(app.clone(), &posts_uri, &token).await;
+    assert_eq!(titles(&posts), vec!["First", "Third", "Second"]);
+    assert_eq!(posts[0]["thread_reply_count"], 1);
+
+    let (_, tagged) =
+        common::get_authed(app.clone(), &format!("{posts_uri}?tag_id={bug}"), &token).await;
+    assert_eq!(titles(&tagged), vec!["First", "Third"]);
+
+    let (_, page) = common::get_authed(
+        app,
+        &format!("{posts_uri}?before={}", first["id"].as_str().unwrap()),
+        &token,
+    )
+    .await;
+    assert_eq!(titles(&page), vec!["Third", "Second"]);
+}
+
+#[tokio::test]
+async fn update_post_title_and_tags() {
+    let (app, owner, server_id, channel_id) = setup_forum().await;
+    let bug = create_tag(app.clone(), &owner, &channel_id, "bug").await;
+    let (member, _) = join_member(app.clone(), &owner, &server_id).await;
+    let post = create_post(app.clone(), &member, &channel_id, "Crash", &[]).await;
+    let uri = format!(
+        "/channels/{channel_id}/posts/{}",
+        post["id"].as_str().unwrap()
+    );
+
+    let (status, updated) = common::patch_json_authed(
+        app.clone(),
+        &uri,
+        &member,
+        json!({ "title": "Crash on start", "tag_ids": [bug] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["forum_post"]["title"], "Crash on start");
+    assert_eq!(updated["forum_post"]["tag_ids"], json!([bug]));
+
+    // Another member without MANAGE_MESSAGES cannot retitle it.
+    let (other, _) = join_member(app.clone(), &owner, &server_id).await;
+    let (status, _) =
+        common::patch_json_authed(app.clone(), &uri, &other, json!({ "title": "Mine" })).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Deleting the tag removes it from the post.
+    common::delete_authed(
+        app.clone(),
+        &format!("/channels/{channel_id}/forum/tags/{bug}"),
+        &owner,
+    )
+    .await;
+    let (_, posts) =
+        common::get_authed(app, &format!("/channels/{channel_id}/posts"), &owner).await;
+    assert_eq!(posts[0]["forum_post"]["tag_ids"], json!([]));
+}
+
+// ============================================================================
+// Channel overrides, search and export
+// ============================================================================
+
+#[tokio::test]
+async fn overrides_apply_to_posts_and_replies() {
+    let (app, owner, server_id, channel_id) = setup_forum().await;
+    let (member, member_id) = join_member(app.clone(), &owner, &server_id).await;
+    let post = create_post(app.clone(), &owner, &channel_id, "Rules", &[]).await;
+    let thread_uri = format!(
+        "/channels/{channel_id}/messages/{}/thread",
+        post["id"].as_str().unwrap()
+    );
+
+    // Deny SEND_MESSAGES: no new posts and no replies.
+    common::put_json_authed(
+        app.clone(),
+        &format!("/channels/{channel_id}/overrides"),
+        &owner,
+        json!({ "user_id": member_id, "allow": 0, "deny": 2 }),
+    )
+    .await;
+    let (status, _) = common::post_json_authed(
+        app.clone(),
+        &format!("/channels/{channel_id}/messages"),
+        &member,
+        json!({ "content": "hi", "title": "Hello" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = common::post_json_authed(
+        app.clone(),
+        &thread_uri,
+        &member,
+        json!({ "content": "hi" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Deny VIEW_CHANNEL: the post list and threads are hidden.
+    common::put_json_authed(
+        app.clone(),
+        &format!("/channels/{channel_id}/overrides"),
+        &owner,
+        json!({ "user_id": member_id, "allow": 0, "deny": 3 }),
+    )
+    .await;
+    let (status, _) = common::get_authed(
+        app.clone(),
+        &format!("/channels/{channel_id}/posts"),
+        &member,
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = common::get_authed(app, &thread_uri, &member).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn search_matches_post_titles_and_replies() {
+    let (app, token, server_id, channel_id) = setup_forum().await;
+    let post = create_post(
+        app.clone(),
+        &token,
+        &channel_id,
+        "Kaleidoscope renderer",
+        &[],
+    )
+    .await;
+    common::post_json_authed(
+        app.clone(),
+        &format!(
+            "/channels/{channel_id}/messages/{}/thread",
+            post["id"].as_str().unwrap()
+        ),
+        &token,
+        json!({ "content": "Try the quasar shader" }),
+    )
+    .await;
+
+    let (status, found) = common::get_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/search?q=kaleidoscope"),
+        &token,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(found["total"], 1);
+    assert_eq!(found["results"][0]["id"], post["id"]);
+    assert_eq!(found["results"][0]["post_title"], "Kaleidoscope renderer");
+
+    let (_, found) = common::get_authed(
+        app,
+        &format!("/servers/{server_id}/search?q=quasar"),
+        &token,
+    )
+    .await;
+    assert_eq!(found["results"][0]["thread_id"], post["id"]);
+    assert_eq!(found["results"][0]["post_title"], "Kaleidoscope renderer");
+}
+
+#[tokio::test]
+async fn export_includes_forum_posts_and_tags() {
+    let (app, token, server_id, channel_id) = setup_forum().await;
+    let bug = create_tag(app.clone(), &token, &channel_id, "bug").await;
+    create_post(app.clone(), &token, &channel_id, "Exported post", &[&bug]).await;
+
+    let (status, bytes) =
+        common::get_raw_authed(app, &format!("/servers/{server_id}/export"), &token).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+    let mut tags_json = String::new();
+    let mut messages_jsonl = String::new();
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).unwrap();
+        let name = file.name().to_owned();
+        if name.ends_with("/forum_tags.json") {
+            file.read_to_string(&mut tags_json).unwrap();
+        } else if name.ends_with(&format!("-{channel_id}.jsonl")) {
+            file.read_to_string(&mut messages_jsonl).unwrap();
+        }
+    }
+
+    let tags: Value = serde_json::from_str(&tags_json).unwrap();
+    assert_eq!(tags[0]["name"], "bug");
+    let post: Value = serde_json::from_str(messages_jsonl.lines().next().unwrap()).unwrap();
+    assert_eq!(post["title"], "Exported post");
+    assert_eq!(post["tag_ids"], json!([bug]));
+}
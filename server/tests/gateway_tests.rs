@@ -0,0 +1,155 @@
+mod common;
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use together_server::websocket::events::GatewayMessage;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use uuid::Uuid;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// Serve `app` on an ephemeral local port and return its address.
+async fn serve(app: axum::Router) -> SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    addr
+}
+
+async fn connect(addr: SocketAddr, query: &str) -> Socket {
+    let (socket, _) = connect_async(format!("ws://{addr}/ws?{query}"))
+        .await
+        .expect("WebSocket upgrade failed");
+    socket
+}
+
+/// Read the next text frame as JSON, failing after five seconds.
+async fn next_frame(socket: &mut Socket) -> Value {
+    loop {
+        let msg = tokio::time::timeout(Duration::from_secs(5), socket.next())
+            .await
+            .expect("timed out waiting for a gateway frame")
+            .expect("socket closed")
+            .unwrap();
+        if let Message::Text(text) = msg {
+            return serde_json::from_str(&text).unwrap();
+        }
+    }
+}
+
+/// Read frames until the dispatch named `event` arrives, returning the
+/// frames read before it and the dispatch itself.
+async fn frames_until(socket: &mut Socket, event: &str) -> (Vec<Value>, Value) {
+    let mut before = Vec::new();
+    loop {
+        let frame = next_frame(socket).await;
+        if frame["t"] == event {
+            return (before, frame);
+        }
+        before.push(frame);
+    }
+}
+
+// ============================================================================
+// RESUME
+// ============================================================================
+
+#[tokio::test]
+async fn resume_replays_dispatches_missed_while_disconnected() {
+    let pool = common::test_pool().await;
+    let (app, state) = common::create_test_app_with_state(pool);
+    let body = common::register_user(app.clone(), &common::unique_username(), "pass1234").await;
+    let token = body["access_token"].as_str().unwrap().to_owned();
+    let user_id: Uuid = body["user"]["id"].as_str().unwrap().parse().unwrap();
+    let addr = serve(app).await;
+
+    let mut socket = connect(addr, &format!("token={token}")).await;
+    let ready = next_frame(&mut socket).await;
+    assert_eq!(ready["t"], "READY");
+    let session_id = ready["d"]["session_id"].as_str().unwrap().to_owned();
+    let seq = ready["d"]["seq"].as_u64().unwrap();
+
+    socket.close(None).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while state.connections.is_connected(user_id).await {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("session was not detached");
+
+    for n in 0..2 {
+        state
+            .connections
+            .dispatch_to_user(
+                user_id,
+                GatewayMessage::dispatch("TEST_EVENT", json!({ "n": n })),
+            )
+            .await;
+    }
+
+    let mut socket = connect(addr, &format!("token={token}&resume=true")).await;
+    socket
+        .send(Message::Text(
+            json!({ "op": "RESUME", "d": { "session_id": session_id, "seq": seq } }).to_string(),
+        ))
+        .await
+        .unwrap();
+
+    let (replayed, resumed) = frames_until(&mut socket, "RESUMED").await;
+    assert_eq!(resumed["d"]["session_id"], session_id.as_str());
+    let missed: Vec<&Value> = replayed.iter().filter(|f| f["t"] == "TEST_EVENT").collect();
+    assert_eq!(missed.len(), 2, "{replayed:?}");
+    assert_eq!(missed[0]["d"]["n"], 0);
+    assert_eq!(missed[1]["d"]["n"], 1);
+    assert!(missed[0]["s"].as_u64().unwrap() > seq);
+    assert!(missed[0]["s"].as_u64() < missed[1]["s"].as_u64());
+    assert!(replayed.iter().all(|f| f["t"] != "READY"));
+    assert!(state.connections.is_connected(user_id).await);
+
+    // The resumed session receives live dispatches again.
+    state
+        .connections
+        .dispatch_to_user(
+            user_id,
+            GatewayMessage::dispatch("TEST_EVENT", json!({ "n": 2 })),
+        )
+        .await;
+    let (_, live) = frames_until(&mut socket, "TEST_EVENT").await;
+    assert_eq!(live["d"]["n"], 2);
+}
+
+#[tokio::test]
+async fn resume_of_unknown_session_falls_back_to_ready() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let token =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "pass1234").await;
+    let addr = serve(app).await;
+
+    let mut socket = connect(addr, &format!("token={token}&resume=true")).await;
+    socket
+        .send(Message::Text(
+            json!({ "op": "RESUME", "d": { "session_id": Uuid::new_v4(), "seq": 0 } }).to_string(),
+        ))
+        .await
+        .unwrap();
+
+    let ready = next_frame(&mut socket).await;
+    assert_eq!(ready["t"], "READY");
+}
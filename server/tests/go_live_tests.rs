@@ -42,7 +42,6 @@ async fn join_voice(app: axum::Router, token: &str, channel_id: &str) {
 struct GoLiveFixture {
     owner_token: String,
     member_token: String,
-    #[allow(dead_code)]
     server_id: String,
     voice_channel_id: String,
     text_channel_id: String,
@@ -378,3 +377,261 @@ async fn non_member_cannot_get_go_live() {
 
     assert_eq!(status, StatusCode::NOT_FOUND);
 }
+
+// ============================================================================
+// Viewers, cleanup and per-server limits
+// ============================================================================
+
+async fn user_id(app: axum::Router, token: &str) -> String {
+    let (_, me) = common::get_authed(app, "/users/@me", token).await;
+    me["id"].as_str().unwrap().to_owned()
+}
+
+async fn go_live(app: axum::Router, token: &str, channel_id: &str, quality: &str) {
+    let (status, body) = common::post_json_authed(
+        app,
+        &format!("/channels/{channel_id}/go-live"),
+        token,
+        json!({ "quality": quality }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "setup go_live failed: {body}");
+}
+
+#[tokio::test]
+async fn viewers_join_and_leave_update_count() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let f = setup(app.clone()).await;
+    let viewers = format!("/channels/{}/go-live/viewers", f.voice_channel_id);
+
+    join_voice(app.clone(), &f.owner_token, &f.voice_channel_id).await;
+    join_voice(app.clone(), &f.member_token, &f.voice_channel_id).await;
+    go_live(app.clone(), &f.owner_token, &f.voice_channel_id, "720p").await;
+
+    let (status, body) =
+        common::post_json_authed(app.clone(), &viewers, &f.member_token, json!({})).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["viewer_count"], 1);
+
+    // Watching twice is idempotent.
+    let (_, body) =
+        common::post_json_authed(app.clone(), &viewers, &f.member_token, json!({})).await;
+    assert_eq!(body["viewer_count"], 1);
+
+    let (status, _) = common::delete_authed(app.clone(), &viewers, &f.member_token).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (_, body) = common::get_authed(
+        app.clone(),
+        &format!("/channels/{}/go-live", f.voice_channel_id),
+        &f.owner_token,
+    )
+    .await;
+    assert_eq!(body["viewer_count"], 0);
+
+    let (status, _) = common::delete_authed(app, &viewers, &f.member_token).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn viewer_must_be_in_channel_and_not_broadcaster() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let f = setup(app.clone()).await;
+    let viewers = format!("/channels/{}/go-live/viewers", f.voice_channel_id);
+
+    join_voice(app.clone(), &f.owner_token, &f.voice_channel_id).await;
+    go_live(app.clone(), &f.owner_token, &f.voice_channel_id, "720p").await;
+
+    let (status, _) =
+        common::post_json_authed(app.clone(), &viewers, &f.member_token, json!({})).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = common::post_json_authed(app, &viewers, &f.owner_token, json!({})).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn leaving_voice_ends_broadcast_and_viewing() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let f = setup(app.clone()).await;
+    let session = format!("/channels/{}/go-live", f.voice_channel_id);
+    let voice = format!("/channels/{}/voice", f.voice_channel_id);
+
+    join_voice(app.clone(), &f.owner_token, &f.voice_channel_id).await;
+    join_voice(app.clone(), &f.member_token, &f.voice_channel_id).await;
+    go_live(app.clone(), &f.owner_token, &f.voice_channel_id, "720p").await;
+    common::post_json_authed(
+        app.clone(),
+        &format!("{session}/viewers"),
+        &f.member_token,
+        json!({}),
+    )
+    .await;
+
+    // A viewer leaving voice drops out of the count.
+    common::delete_authed(app.clone(), &voice, &f.member_token).await;
+    let (_, body) = common::get_authed(app.clone(), &session, &f.owner_token).await;
+    assert_eq!(body["viewer_count"], 0);
+
+    // The broadcaster leaving voice ends the session.
+    common::delete_authed(app.clone(), &voice, &f.owner_token).await;
+    let (status, _) = common::get_authed(app, &session, &f.owner_token).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn session_survives_app_restart() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool.clone());
+    let f = setup(app.clone()).await;
+
+    join_voice(app.clone(), &f.owner_token, &f.voice_channel_id).await;
+    go_live(app, &f.owner_token, &f.voice_channel_id, "1080p").await;
+
+    // A fresh app on the same database stands in for a restarted replica.
+    let restarted = common::create_test_app(pool);
+    let (status, body) = common::get_authed(
+        restarted,
+        &format!("/channels/{}/go-live", f.voice_channel_id),
+        &f.member_token,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["quality"], "1080p");
+}
+
+#[tokio::test]
+async fn concurrent_stream_cap_is_enforced() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let f = setup(app.clone()).await;
+    let vc2 = create_voice_channel(app.clone(), &f.owner_token, &f.server_id, "Room 2").await;
+    let vc2_id = vc2["id"].as_str().unwrap();
+
+    let (status, body) = common::patch_json_authed(
+        app.clone(),
+        &format!("/servers/{}/go-live/settings", f.server_id),
+        &f.owner_token,
+        json!({ "max_concurrent_streams": 1 }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["max_concurrent_streams"], 1);
+
+    join_voice(app.clone(), &f.owner_token, &f.voice_channel_id).await;
+    go_live(app.clone(), &f.owner_token, &f.voice_channel_id, "720p").await;
+
+    join_voice(app.clone(), &f.member_token, vc2_id).await;
+    let (status, _) = common::post_json_authed(
+        app.clone(),
+        &format!("/channels/{vc2_id}/go-live"),
+        &f.member_token,
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Changing quality on the existing stream does not count against the cap.
+    go_live(app.clone(), &f.owner_token, &f.voice_channel_id, "480p").await;
+
+    common::delete_authed(
+        app.clone(),
+        &format!("/channels/{}/go-live", f.voice_channel_id),
+        &f.owner_token,
+    )
+    .await;
+    go_live(app, &f.member_token, vc2_id, "720p").await;
+}
+
+#[tokio::test]
+async fn quality_cap_exempts_configured_roles() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let f = setup(app.clone()).await;
+    let member_id = user_id(app.clone(), &f.member_token).await;
+
+    let (_, role) = common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{}/roles", f.server_id),
+        &f.owner_token,
+        json!({ "name": "Streamer", "permissions": 0 }),
+    )
+    .await;
+    let role_id = role["id"].as_str().unwrap();
+
+    let (status, body) = common::patch_json_authed(
+        app.clone(),
+        &format!("/servers/{}/go-live/settings", f.server_id),
+        &f.owner_token,
+        json!({ "max_quality": "720p", "high_quality_role_ids": [role_id] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    join_voice(app.clone(), &f.member_token, &f.voice_channel_id).await;
+    let uri = format!("/channels/{}/go-live", f.voice_channel_id);
+    let (status, _) = common::post_json_authed(
+        app.clone(),
+        &uri,
+        &f.member_token,
+        json!({ "quality": "1080p" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    go_live(app.clone(), &f.member_token, &f.voice_channel_id, "720p").await;
+
+    common::put_authed(
+        app.clone(),
+        &format!(
+            "/servers/{}/members/{member_id}/roles/{role_id}",
+            f.server_id
+        ),
+        &f.owner_token,
+    )
+    .await;
+    go_live(app, &f.member_token, &f.voice_channel_id, "1080p").await;
+}
+
+#[tokio::test]
+async fn go_live_settings_defaults_and_owner_only() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let f = setup(app.clone()).await;
+    let uri = format!("/servers/{}/go-live/settings", f.server_id);
+
+    let (status, body) = common::get_authed(app.clone(), &uri, &f.member_token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["max_concurrent_streams"], 0);
+    assert_eq!(body["max_quality"], "1080p");
+
+    let (status, _) = common::patch_json_authed(
+        app.clone(),
+        &uri,
+        &f.member_token,
+        json!({ "max_quality": "480p" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = common::patch_json_authed(
+        app.clone(),
+        &uri,
+        &f.owner_token,
+        json!({ "max_quality": "4k" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = common::patch_json_authed(
+        app,
+        &uri,
+        &f.owner_token,
+        json!({ "high_quality_role_ids": [uuid::Uuid::new_v4()] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,294 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::json;
+
+// ============================================================================
+// Test fixture helpers
+// ============================================================================
+
+/// Register a user, create a server with a text channel, and return
+/// `(token, server_id, channel_id)`.
+async fn setup_channel(app: axum::Router) -> (String, String, String) {
+    let token =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "pass1234").await;
+    let server = common::create_server(app.clone(), &token, "Incoming Guild").await;
+    let server_id = server["id"].as_str().unwrap().to_owned();
+    let channel = common::create_channel(app.clone(), &token, &server_id, "alerts").await;
+    let channel_id = channel["id"].as_str().unwrap().to_owned();
+    (token, server_id, channel_id)
+}
+
+/// Create an incoming webhook and return the execute URL.
+async fn create_incoming(app: axum::Router, token: &str, sid: &str, cid: &str) -> String {
+    let (status, body) = common::post_json_authed(
+        app,
+        &format!("/servers/{sid}/incoming-webhooks"),
+        token,
+        json!({ "name": "CI", "channel_id": cid }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "create failed: {body}");
+    body["url"].as_str().unwrap().to_owned()
+}
+
+// ============================================================================
+// Management
+// ============================================================================
+
+#[tokio::test]
+async fn create_returns_token_once_and_list_omits_it() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, sid, cid) = setup_channel(app.clone()).await;
+
+    let (status, body) = common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{sid}/incoming-webhooks"),
+        &token,
+        json!({ "name": "CI", "channel_id": cid, "avatar_url": "https://example.com/ci.png" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "create failed: {body}");
+    let hook_id = body["webhook"]["id"].as_str().unwrap();
+    let secret = body["token"].as_str().unwrap();
+    assert_eq!(secret.len(), 64);
+    assert_eq!(body["url"], format!("/webhooks/{hook_id}/{secret}"));
+    assert_eq!(body["webhook"]["channel_id"], cid);
+
+    let (status, list) =
+        common::get_authed(app, &format!("/servers/{sid}/incoming-webhooks"), &token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(list.as_array().unwrap().len(), 1);
+    assert!(list[0].get("token").is_none());
+    assert!(list[0].get("token_hash").is_none());
+}
+
+#[tokio::test]
+async fn create_requires_manage_webhooks() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (owner_token, sid, cid) = setup_channel(app.clone()).await;
+    common::make_server_public(app.clone(), &owner_token, &sid).await;
+
+    let member_token =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "pass1234").await;
+    common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{sid}/join"),
+        &member_token,
+        json!({}),
+    )
+    .await;
+
+    let (status, _) = common::post_json_authed(
+        app,
+        &format!("/servers/{sid}/incoming-webhooks"),
+        &member_token,
+        json!({ "name": "CI", "channel_id": cid }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn create_rejects_channel_from_other_server() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, sid, _) = setup_channel(app.clone()).await;
+    let other = common::create_server(app.clone(), &token, "Other").await;
+    let other_channel =
+        common::create_channel(app.clone(), &token, other["id"].as_str().unwrap(), "x").await;
+
+    let (status, _) = common::post_json_authed(
+        app,
+        &format!("/servers/{sid}/incoming-webhooks"),
+        &token,
+        json!({ "name": "CI", "channel_id": other_channel["id"] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn regenerated_token_replaces_old_url() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, sid, cid) = setup_channel(app.clone()).await;
+    let old_url = create_incoming(app.clone(), &token, &sid, &cid).await;
+    let hook_id = old_url.split('/').nth(2).unwrap();
+
+    let (status, body) = common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{sid}/incoming-webhooks/{hook_id}/token"),
+        &token,
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let new_url = body["url"].as_str().unwrap();
+    assert_ne!(new_url, old_url);
+
+    let (status, _) = common::post_json(app.clone(), &old_url, json!({ "content": "hi" })).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = common::post_json(app, new_url, json!({ "content": "hi" })).await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
+// ============================================================================
+// Execute
+// ============================================================================
+
+#[tokio::test]
+async fn execute_posts_message_with_webhook_author_and_embeds() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, sid, cid) = setup_channel(app.clone()).await;
+    let url = create_incoming(app.clone(), &token, &sid, &cid).await;
+
+    let (status, msg) = common::post_json(
+        app.clone(),
+        &url,
+        json!({
+            "content": "Build #42 passed",
+            "username": "GitHub Actions",
+            "avatar_url": "https://example.com/gh.png",
+            "embeds": [{
+                "title": "main @ abc123",
+                "url": "https://ci.example.com/42",
+                "color": 3066993,
+                "fields": [{ "name": "Duration", "value": "3m", "inline": true }]
+            }]
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "execute failed: {msg}");
+    assert!(msg["author_id"].is_null());
+    assert_eq!(msg["webhook"]["username"], "GitHub Actions");
+    assert_eq!(msg["webhook"]["avatar_url"], "https://example.com/gh.png");
+    assert_eq!(msg["embeds"][0]["fields"][0]["name"], "Duration");
+
+    let (status, list) =
+        common::get_authed(app, &format!("/channels/{cid}/messages"), &token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(list[0]["id"], msg["id"]);
+    assert_eq!(list[0]["webhook"]["username"], "GitHub Actions");
+    assert_eq!(list[0]["embeds"][0]["title"], "main @ abc123");
+}
+
+#[tokio::test]
+async fn execute_defaults_to_webhook_name() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, sid, cid) = setup_channel(app.clone()).await;
+    let url = create_incoming(app.clone(), &token, &sid, &cid).await;
+
+    let (status, msg) = common::post_json(app, &url, json!({ "content": "deploy done" })).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(msg["webhook"]["username"], "CI");
+    assert!(msg["webhook"]["id"].is_string());
+    assert_eq!(msg["embeds"], json!([]));
+}
+
+#[tokio::test]
+async fn execute_with_wrong_token_is_not_found() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, sid, cid) = setup_channel(app.clone()).await;
+    let url = create_incoming(app.clone(), &token, &sid, &cid).await;
+    let hook_id = url.split('/').nth(2).unwrap();
+
+    let (status, _) = common::post_json(
+        app,
+        &format!("/webhooks/{hook_id}/{}", "0".repeat(64)),
+        json!({ "content": "hi" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn execute_rejects_empty_and_invalid_payloads() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, sid, cid) = setup_channel(app.clone()).await;
+    let url = create_incoming(app.clone(), &token, &sid, &cid).await;
+
+    for body in [
+        json!({}),
+        json!({ "content": "   " }),
+        json!({ "embeds": [{}] }),
+        json!({ "embeds": [{ "title": "x", "color": 16777216 }] }),
+        json!({ "embeds": [{ "title": "x", "url": "javascript:alert(1)" }] }),
+        json!({ "content": "x", "avatar_url": "ftp://example.com/a.png" }),
+    ] {
+        let (status, _) = common::post_json(app.clone(), &url, body.clone()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "accepted {body}");
+    }
+}
+
+#[tokio::test]
+async fn execute_is_subject_to_word_filter() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, sid, cid) = setup_channel(app.clone()).await;
+    let url = create_incoming(app.clone(), &token, &sid, &cid).await;
+
+    common::patch_json_authed(
+        app.clone(),
+        &format!("/servers/{sid}/automod"),
+        &token,
+        json!({ "enabled": true, "word_filter_enabled": true, "word_filter_action": "ban" }),
+    )
+    .await;
+    common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{sid}/automod/words"),
+        &token,
+        json!({ "word": "badword" }),
+    )
+    .await;
+
+    let (status, _) = common::post_json(
+        app.clone(),
+        &url,
+        json!({ "content": "this is a badword alert" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // The block is logged against the webhook, and nobody is banned for it.
+    let (_, logs) =
+        common::get_authed(app.clone(), &format!("/servers/{sid}/automod/logs"), &token).await;
+    assert_eq!(logs[0]["rule_type"], "word_filter");
+    assert!(logs[0]["user_id"].is_null());
+    assert_eq!(logs[0]["username"], "CI");
+
+    let (status, _) = common::post_json(app, &url, json!({ "content": "all clear" })).await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn execute_broadcasts_message_create() {
+    let pool = common::test_pool().await;
+    let (app, state) = common::create_test_app_with_state(pool);
+    let owner = common::register_user(app.clone(), &common::unique_username(), "pass1234").await;
+    let token = owner["access_token"].as_str().unwrap().to_owned();
+    let owner_id = owner["user"]["id"].as_str().unwrap().parse().unwrap();
+    let server = common::create_server(app.clone(), &token, "Gateway").await;
+    let sid = server["id"].as_str().unwrap().to_owned();
+    let channel = common::create_channel(app.clone(), &token, &sid, "alerts").await;
+    let cid = channel["id"].as_str().unwrap().to_owned();
+    let url = create_incoming(app.clone(), &token, &sid, &cid).await;
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    state.connections.add(owner_id, tx).await;
+
+    let (status, msg) = common::post_json(app, &url, json!({ "content": "pager fired" })).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let frame: serde_json::Value = serde_json::from_str(&rx.try_recv().unwrap()).unwrap();
+    assert_eq!(frame["t"], "MESSAGE_CREATE");
+    assert_eq!(frame["d"]["id"], msg["id"]);
+    assert_eq!(frame["d"]["webhook"]["username"], "CI");
+}
@@ -0,0 +1,309 @@
+mod common;
+
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use serde_json::json;
+use together_server::{handlers::read_states::ready_read_states, models::ReadStateDto};
+
+// ============================================================================
+// Test fixture helpers
+// ============================================================================
+
+struct Fixture {
+    owner_token: String,
+    owner_id: uuid::Uuid,
+    owner_name: String,
+    member_token: String,
+    server_id: String,
+    channel_id: String,
+}
+
+/// Owner creates a server with one channel in the "Text" category; a second
+/// user joins it.
+async fn setup(app: axum::Router) -> Fixture {
+    let owner_name = common::unique_username();
+    let owner = common::register_user(app.clone(), &owner_name, "pass1234").await;
+    let owner_token = owner["access_token"].as_str().unwrap().to_owned();
+    let owner_id = owner["user"]["id"].as_str().unwrap().parse().unwrap();
+    let server = common::create_server(app.clone(), &owner_token, "Notify Guild").await;
+    let server_id = server["id"].as_str().unwrap().to_owned();
+    let (status, channel) = common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/channels"),
+        &owner_token,
+        json!({ "name": "general", "type": "text", "category": "Text" }),
+    )
+    .await;
+    assert_eq!(
+        status,
+        StatusCode::CREATED,
+        "create channel failed: {channel}"
+    );
+    let channel_id = channel["id"].as_str().unwrap().to_owned();
+
+    let member_token =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "pass1234").await;
+    common::make_server_public(app.clone(), &owner_token, &server_id).await;
+    common::post_json_authed(
+        app,
+        &format!("/servers/{server_id}/join"),
+        &member_token,
+        json!({}),
+    )
+    .await;
+
+    Fixture {
+        owner_token,
+        owner_id,
+        owner_name,
+        member_token,
+        server_id,
+        channel_id,
+    }
+}
+
+async fn counts_for(state: &together_server::state::AppState, f: &Fixture) -> ReadStateDto {
+    ready_read_states(state, f.owner_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|rs| rs.channel_id.to_string() == f.channel_id)
+        .expect("channel missing from read states")
+}
+
+// ============================================================================
+// Endpoints
+// ============================================================================
+
+#[tokio::test]
+async fn put_and_list_settings() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let f = setup(app.clone()).await;
+
+    let (status, body) = common::put_json_authed(
+        app.clone(),
+        &format!("/servers/{}/notification-settings", f.server_id),
+        &f.owner_token,
+        json!({ "level": "mentions", "suppress_everyone": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["scope"], "server");
+    assert_eq!(body["level"], "mentions");
+
+    // PUT replaces the row: omitted fields go back to inheriting.
+    let (status, body) = common::put_json_authed(
+        app.clone(),
+        &format!("/servers/{}/notification-settings", f.server_id),
+        &f.owner_token,
+        json!({ "level": "all" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["suppress_everyone"].is_null());
+
+    let (status, body) = common::put_json_authed(
+        app.clone(),
+        &format!(
+            "/servers/{}/categories/Text/notification-settings",
+            f.server_id
+        ),
+        &f.owner_token,
+        json!({ "level": "none" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["category"], "Text");
+
+    let (status, body) =
+        common::get_authed(app, "/users/@me/notification-settings", &f.owner_token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn put_rejects_invalid_level_and_non_members() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let f = setup(app.clone()).await;
+
+    let (status, _) = common::put_json_authed(
+        app.clone(),
+        &format!("/channels/{}/notification-settings", f.channel_id),
+        &f.owner_token,
+        json!({ "level": "loud" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let outsider =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "pass1234").await;
+    let (status, _) = common::put_json_authed(
+        app,
+        &format!("/servers/{}/notification-settings", f.server_id),
+        &outsider,
+        json!({ "level": "none" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn put_dispatches_settings_to_own_sessions() {
+    let pool = common::test_pool().await;
+    let (app, state) = common::create_test_app_with_state(pool);
+    let f = setup(app.clone()).await;
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    state.connections.add(f.owner_id, tx).await;
+
+    common::put_json_authed(
+        app,
+        &format!("/channels/{}/notification-settings", f.channel_id),
+        &f.owner_token,
+        json!({ "level": "none" }),
+    )
+    .await;
+
+    let mut update = None;
+    while let Ok(frame) = rx.try_recv() {
+        let v: serde_json::Value = serde_json::from_str(&frame).unwrap();
+        if v["t"] == "NOTIFICATION_SETTINGS_UPDATE" {
+            update = Some(v);
+        }
+    }
+    let update = update.expect("no NOTIFICATION_SETTINGS_UPDATE dispatched");
+    let settings = update["d"]["notification_settings"].as_array().unwrap();
+    assert_eq!(settings.len(), 1);
+    assert_eq!(settings[0]["channel_id"], f.channel_id.as_str());
+}
+
+// ============================================================================
+// Effect on unread and mention counts
+// ============================================================================
+
+#[tokio::test]
+async fn channel_mute_zeroes_counts_until_removed() {
+    let pool = common::test_pool().await;
+    let (app, state) = common::create_test_app_with_state(pool);
+    let f = setup(app.clone()).await;
+    common::create_message(
+        app.clone(),
+        &f.member_token,
+        &f.channel_id,
+        &format!("@{} look", f.owner_name),
+    )
+    .await;
+
+    let uri = format!("/channels/{}/notification-settings", f.channel_id);
+    common::put_json_authed(
+        app.clone(),
+        &uri,
+        &f.owner_token,
+        json!({ "muted_until": Utc::now() + Duration::hours(1) }),
+    )
+    .await;
+    let rs = counts_for(&state, &f).await;
+    assert_eq!((rs.unread_count, rs.mention_count), (0, 0));
+
+    // An expired mute is ignored.
+    common::put_json_authed(
+        app.clone(),
+        &uri,
+        &f.owner_token,
+        json!({ "muted_until": Utc::now() - Duration::hours(1) }),
+    )
+    .await;
+    let rs = counts_for(&state, &f).await;
+    assert_eq!((rs.unread_count, rs.mention_count), (1, 1));
+
+    let (status, _) = common::delete_authed(app, &uri, &f.owner_token).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(counts_for(&state, &f).await.unread_count, 1);
+}
+
+#[tokio::test]
+async fn category_level_is_overridden_by_channel() {
+    let pool = common::test_pool().await;
+    let (app, state) = common::create_test_app_with_state(pool);
+    let f = setup(app.clone()).await;
+    common::create_message(app.clone(), &f.member_token, &f.channel_id, "hi").await;
+
+    common::put_json_authed(
+        app.clone(),
+        &format!(
+            "/servers/{}/categories/Text/notification-settings",
+            f.server_id
+        ),
+        &f.owner_token,
+        json!({ "level": "none" }),
+    )
+    .await;
+    assert_eq!(counts_for(&state, &f).await.unread_count, 0);
+
+    common::put_json_authed(
+        app,
+        &format!("/channels/{}/notification-settings", f.channel_id),
+        &f.owner_token,
+        json!({ "level": "all" }),
+    )
+    .await;
+    assert_eq!(counts_for(&state, &f).await.unread_count, 1);
+}
+
+#[tokio::test]
+async fn suppress_everyone_keeps_direct_mentions() {
+    let pool = common::test_pool().await;
+    let (app, state) = common::create_test_app_with_state(pool);
+    let f = setup(app.clone()).await;
+    common::create_message(app.clone(), &f.member_token, &f.channel_id, "@everyone").await;
+    common::create_message(
+        app.clone(),
+        &f.member_token,
+        &f.channel_id,
+        &format!("@everyone and @{}", f.owner_name),
+    )
+    .await;
+    assert_eq!(counts_for(&state, &f).await.mention_count, 2);
+
+    common::put_json_authed(
+        app,
+        &format!("/servers/{}/notification-settings", f.server_id),
+        &f.owner_token,
+        json!({ "suppress_everyone": true }),
+    )
+    .await;
+    let rs = counts_for(&state, &f).await;
+    assert_eq!(rs.unread_count, 2);
+    assert_eq!(rs.mention_count, 1);
+}
+
+#[tokio::test]
+async fn dm_mute_zeroes_unread() {
+    let pool = common::test_pool().await;
+    let (app, state) = common::create_test_app_with_state(pool);
+    let f = setup(app.clone()).await;
+    let (_, member) = common::get_authed(app.clone(), "/users/@me", &f.member_token).await;
+    let dm =
+        common::open_dm_channel(app.clone(), &f.owner_token, member["id"].as_str().unwrap()).await;
+    let dm_id = dm["id"].as_str().unwrap();
+    common::send_dm_message(app.clone(), &f.member_token, dm_id, "psst").await;
+
+    let (status, _) = common::put_json_authed(
+        app,
+        &format!("/dm-channels/{dm_id}/notification-settings"),
+        &f.owner_token,
+        json!({ "level": "none" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let rs = ready_read_states(&state, f.owner_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|rs| rs.channel_id.to_string() == dm_id)
+        .unwrap();
+    assert_eq!(rs.unread_count, 0);
+}
@@ -0,0 +1,580 @@
+mod common;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::{get, post},
+    Form, Json, Router,
+};
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use serde_json::{json, Value};
+use serial_test::serial;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use together_server::config::OidcConfig;
+
+// ============================================================================
+// Stand-in identity provider
+// ============================================================================
+
+/// A code handed out by the provider, waiting to be exchanged.
+struct IssuedCode {
+    claims: Value,
+    code_challenge: String,
+}
+
+/// A minimal OIDC provider: discovery, JWKS and a token endpoint that checks
+/// client credentials and PKCE, signing ID tokens with ES256.
+#[derive(Clone)]
+struct MockIdp {
+    issuer: String,
+    key: Arc<SigningKey>,
+    codes: Arc<Mutex<HashMap<String, IssuedCode>>>,
+}
+
+impl MockIdp {
+    async fn start() -> Self {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let idp = MockIdp {
+            issuer: format!("http://{}", listener.local_addr().unwrap()),
+            key: Arc::new(SigningKey::random(&mut rand::rngs::OsRng)),
+            codes: Arc::default(),
+        };
+        let app = Router::new()
+            .route("/.well-known/openid-configuration", get(discovery))
+            .route("/jwks", get(jwks))
+            .route("/token", post(token))
+            .with_state(idp.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        idp
+    }
+
+    fn sign(&self, claims: &Value) -> String {
+        let header = json!({ "alg": "ES256", "typ": "JWT", "kid": "test-key" });
+        let message = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let signature: Signature = self.key.sign(message.as_bytes());
+        format!("{message}.{}", URL_SAFE_NO_PAD.encode(signature.to_bytes()))
+    }
+
+    /// What the provider does once the user logs in at `authorization_url`:
+    /// remember the request and return the code for the redirect. `claims`
+    /// are added to the standard ones.
+    fn approve(&self, authorization_url: &str, claims: Value) -> String {
+        let url = url::Url::parse(authorization_url).unwrap();
+        let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        assert_eq!(params["response_type"], "code");
+        assert_eq!(params["client_id"], common::TEST_OIDC_CLIENT_ID);
+        assert_eq!(params["code_challenge_method"], "S256");
+
+        let now = chrono::Utc::now().timestamp();
+        let mut id_claims = json!({
+            "iss": self.issuer,
+            "aud": common::TEST_OIDC_CLIENT_ID,
+            "iat": now,
+            "exp": now + 300,
+            "nonce": params["nonce"],
+        });
+        for (key, value) in claims.as_object().unwrap() {
+            id_claims[key] = value.clone();
+        }
+
+        let code = uuid::Uuid::new_v4().to_string();
+        self.codes.lock().unwrap().insert(
+            code.clone(),
+            IssuedCode {
+                claims: id_claims,
+                code_challenge: params["code_challenge"].clone(),
+            },
+        );
+        code
+    }
+}
+
+async fn discovery(State(idp): State<MockIdp>) -> Json<Value> {
+    Json(json!({
+        "issuer": idp.issuer,
+        "authorization_endpoint": format!("{}/authorize", idp.issuer),
+        "token_endpoint": format!("{}/token", idp.issuer),
+        "jwks_uri": format!("{}/jwks", idp.issuer),
+        "token_endpoint_auth_methods_supported": ["client_secret_basic"],
+    }))
+}
+
+async fn jwks(State(idp): State<MockIdp>) -> Json<Value> {
+    let point = idp.key.verifying_key().to_encoded_point(false);
+    Json(json!({
+        "keys": [{
+            "kty": "EC",
+            "crv": "P-256",
+            "kid": "test-key",
+            "use": "sig",
+            "alg": "ES256",
+            "x": URL_SAFE_NO_PAD.encode(point.x().unwrap()),
+            "y": URL_SAFE_NO_PAD.encode(point.y().unwrap()),
+        }]
+    }))
+}
+
+async fn token(
+    State(idp): State<MockIdp>,
+    headers: HeaderMap,
+    Form(form): Form<HashMap<String, String>>,
+) -> (StatusCode, Json<Value>) {
+    let invalid = |error: &str| (StatusCode::BAD_REQUEST, Json(json!({ "error": error })));
+
+    let expected_auth = format!(
+        "Basic {}",
+        STANDARD.encode(format!(
+            "{}:{}",
+            common::TEST_OIDC_CLIENT_ID,
+            common::TEST_OIDC_CLIENT_SECRET
+        ))
+    );
+    if headers.get("authorization").and_then(|v| v.to_str().ok()) != Some(&expected_auth) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "error": "invalid_client" })),
+        );
+    }
+    if form.get("grant_type").map(String::as_str) != Some("authorization_code") {
+        return invalid("unsupported_grant_type");
+    }
+    let Some(issued) = form
+        .get("code")
+        .and_then(|code| idp.codes.lock().unwrap().remove(code))
+    else {
+        return invalid("invalid_grant");
+    };
+    let verifier = form.get("code_verifier").cloned().unwrap_or_default();
+    if URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())) != issued.code_challenge {
+        return invalid("invalid_grant");
+    }
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "access_token": "provider-access-token",
+            "token_type": "Bearer",
+            "id_token": idp.sign(&issued.claims),
+        })),
+    )
+}
+
+// ============================================================================
+// Test fixture helpers
+// ============================================================================
+
+async fn setup() -> (Router, PgPool, MockIdp) {
+    setup_with(|_| {}).await
+}
+
+async fn setup_with(configure: impl FnOnce(&mut OidcConfig)) -> (Router, PgPool, MockIdp) {
+    let pool = common::test_pool().await;
+    let idp = MockIdp::start().await;
+    let mut config = common::test_oidc_config(&idp.issuer);
+    configure(&mut config);
+    let (app, _) = common::create_test_app_with_oidc(pool.clone(), config);
+    (app, pool, idp)
+}
+
+async fn set_registration_mode(pool: &PgPool, mode: &str) {
+    sqlx::query("UPDATE instance_settings SET registration_mode = $1 WHERE id = 1")
+        .bind(mode)
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+/// Run a whole SSO login with `authorize` as the start request and `claims`
+/// from the provider.
+async fn sso_login_with(
+    app: Router,
+    idp: &MockIdp,
+    authorize: Value,
+    claims: Value,
+) -> (StatusCode, Value) {
+    let (status, start) = common::post_json(app.clone(), "/auth/oidc/authorize", authorize).await;
+    assert_eq!(status, StatusCode::OK, "authorize failed: {start}");
+    let code = idp.approve(start["authorization_url"].as_str().unwrap(), claims);
+    common::post_json(
+        app,
+        "/auth/oidc/callback",
+        json!({ "code": code, "state": start["state"] }),
+    )
+    .await
+}
+
+async fn sso_login(app: Router, idp: &MockIdp, claims: Value) -> (StatusCode, Value) {
+    sso_login_with(app, idp, json!({}), claims).await
+}
+
+fn subject() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+#[tokio::test]
+async fn sso_endpoints_404_when_not_configured() {
+    let app = common::create_test_app(common::test_pool().await);
+
+    let (status, _) = common::post_json(app.clone(), "/auth/oidc/authorize", json!({})).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (_, body) = common::get_no_auth(app, "/instance/registration-mode").await;
+    assert!(body["sso"].is_null());
+}
+
+#[tokio::test]
+async fn registration_mode_advertises_provider() {
+    let (app, _, _) = setup_with(|c| c.disable_registration = true).await;
+
+    let (status, body) = common::get_no_auth(app.clone(), "/instance/registration-mode").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["sso"]["provider_name"], "Test IdP");
+    assert_eq!(body["sso"]["password_registration"], false);
+
+    let (status, _) = common::post_json(
+        app,
+        "/auth/register",
+        json!({ "username": common::unique_username(), "password": "password123" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+// ============================================================================
+// Account provisioning and linking
+// ============================================================================
+
+#[tokio::test]
+#[serial]
+async fn first_login_creates_account_and_later_logins_reuse_it() {
+    let (app, pool, idp) = setup().await;
+    set_registration_mode(&pool, "open").await;
+    let sub = subject();
+    let username = common::unique_username();
+    let email = format!("{username}@example.com");
+
+    let (status, body) = sso_login(
+        app.clone(),
+        &idp,
+        json!({
+            "sub": sub,
+            "preferred_username": username,
+            "email": email,
+            "email_verified": true,
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body["access_token"].is_string());
+    assert_eq!(body["user"]["username"], username.as_str());
+    assert_eq!(body["user"]["email"], email.as_str());
+    assert_eq!(body["user"]["email_verified"], true);
+    let user_id = body["user"]["id"].clone();
+
+    // The subject is what identifies the user; the name can change.
+    let (status, body) = sso_login(
+        app.clone(),
+        &idp,
+        json!({ "sub": sub, "preferred_username": "renamed_at_idp" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["user"]["id"], user_id);
+
+    // The random password set at creation is not known to anyone.
+    let (status, _) = common::post_json(
+        app,
+        "/auth/login",
+        json!({ "username": username, "password": "" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+#[serial]
+async fn taken_username_gets_a_suffix() {
+    let (app, pool, idp) = setup().await;
+    set_registration_mode(&pool, "open").await;
+    let username = common::unique_username();
+    common::register_user(app.clone(), &username, "password123").await;
+
+    let (status, body) = sso_login(
+        app,
+        &idp,
+        json!({ "sub": subject(), "preferred_username": username }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let created = body["user"]["username"].as_str().unwrap();
+    assert_ne!(created, username);
+    assert!(created.starts_with(&username[..20.min(username.len())]));
+}
+
+#[tokio::test]
+#[serial]
+async fn verified_email_links_existing_account() {
+    let (app, pool, idp) = setup().await;
+    set_registration_mode(&pool, "open").await;
+    let username = common::unique_username();
+    let email = format!("{username}@example.com");
+    let local = common::register_user(app.clone(), &username, "password123").await;
+    sqlx::query("UPDATE users SET email = $2, email_verified_at = NOW() WHERE username = $1")
+        .bind(&username)
+        .bind(&email)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // An address the provider has not verified is not enough.
+    let (status, body) = sso_login(
+        app.clone(),
+        &idp,
+        json!({ "sub": subject(), "email": email, "email_verified": false }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_ne!(body["user"]["id"], local["user"]["id"]);
+    assert!(body["user"]["email"].is_null());
+
+    let (status, body) = sso_login(
+        app,
+        &idp,
+        json!({ "sub": subject(), "email": email.to_uppercase(), "email_verified": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["user"]["id"], local["user"]["id"]);
+}
+
+#[tokio::test]
+#[serial]
+async fn unverified_local_email_is_not_linked() {
+    let (app, pool, idp) = setup().await;
+    set_registration_mode(&pool, "open").await;
+    let username = common::unique_username();
+    let email = format!("{username}@example.com");
+    common::register_user(app.clone(), &username, "password123").await;
+    sqlx::query("UPDATE users SET email = $2 WHERE username = $1")
+        .bind(&username)
+        .bind(&email)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let (status, _) = sso_login(
+        app,
+        &idp,
+        json!({ "sub": subject(), "email": email, "email_verified": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+#[serial]
+async fn registration_mode_controls_provisioning() {
+    let (app, pool, idp) = setup().await;
+
+    // Link an account and set up an invite while registration is open.
+    set_registration_mode(&pool, "open").await;
+    let owner =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "password123")
+            .await;
+    let server = common::create_server(app.clone(), &owner, "SSO Invites").await;
+    let (_, invite) = common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{}/invites", server["id"].as_str().unwrap()),
+        &owner,
+        json!({}),
+    )
+    .await;
+    let linked = subject();
+    let (status, _) = sso_login(app.clone(), &idp, json!({ "sub": linked })).await;
+    assert_eq!(status, StatusCode::OK);
+
+    set_registration_mode(&pool, "closed").await;
+    let (status, _) = sso_login(app.clone(), &idp, json!({ "sub": subject() })).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = sso_login(app.clone(), &idp, json!({ "sub": linked })).await;
+    assert_eq!(status, StatusCode::OK);
+
+    set_registration_mode(&pool, "invite_only").await;
+    let (status, _) = sso_login(app.clone(), &idp, json!({ "sub": subject() })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = sso_login_with(
+        app,
+        &idp,
+        json!({ "invite_code": invite["code"] }),
+        json!({ "sub": subject() }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    set_registration_mode(&pool, "open").await;
+}
+
+// ============================================================================
+// Group mapping
+// ============================================================================
+
+#[tokio::test]
+#[serial]
+async fn groups_set_admin_and_server_roles() {
+    let pool = common::test_pool().await;
+    set_registration_mode(&pool, "open").await;
+
+    // A server with a role to map, created through a plain test app.
+    let plain = common::create_test_app(pool.clone());
+    let owner =
+        common::register_and_get_token(plain.clone(), &common::unique_username(), "password123")
+            .await;
+    let server = common::create_server(plain.clone(), &owner, "SSO Roles").await;
+    let server_id = server["id"].as_str().unwrap().to_string();
+    common::make_server_public(plain.clone(), &owner, &server_id).await;
+    let (status, role) = common::post_json_authed(
+        plain,
+        &format!("/servers/{server_id}/roles"),
+        &owner,
+        json!({ "name": "Engineering" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{role}");
+    let role_id = uuid::Uuid::parse_str(role["id"].as_str().unwrap()).unwrap();
+
+    let (app, _, idp) = setup_with(|c| {
+        c.admin_groups = vec!["together-admins".into()];
+        c.role_mappings = vec![("engineering".into(), role_id)];
+    })
+    .await;
+    let sub = subject();
+    let claims = |groups: Value| json!({ "sub": sub, "groups": groups });
+
+    let (status, body) = sso_login(app.clone(), &idp, claims(json!(["together-admins"]))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["user"]["is_admin"], true);
+    let token = body["access_token"].as_str().unwrap().to_string();
+    let user_id = uuid::Uuid::parse_str(body["user"]["id"].as_str().unwrap()).unwrap();
+
+    let (status, _) = common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/join"),
+        &token,
+        json!({}),
+    )
+    .await;
+    assert!(status.is_success());
+
+    let has_role = || async {
+        sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM member_roles WHERE user_id = $1 AND role_id = $2)",
+        )
+        .bind(user_id)
+        .bind(role_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap()
+    };
+    assert!(!has_role().await);
+
+    let (_, body) = sso_login(app.clone(), &idp, claims(json!(["engineering"]))).await;
+    assert_eq!(body["user"]["is_admin"], false);
+    assert!(has_role().await);
+
+    let (_, body) = sso_login(app, &idp, claims(json!([]))).await;
+    assert_eq!(body["user"]["is_admin"], false);
+    assert!(!has_role().await);
+}
+
+// ============================================================================
+// Rejected logins
+// ============================================================================
+
+#[tokio::test]
+#[serial]
+async fn state_is_single_use() {
+    let (app, pool, idp) = setup().await;
+    set_registration_mode(&pool, "open").await;
+
+    let (_, start) = common::post_json(app.clone(), "/auth/oidc/authorize", json!({})).await;
+    let authorization_url = start["authorization_url"].as_str().unwrap();
+    let code = idp.approve(authorization_url, json!({ "sub": subject() }));
+    let callback = json!({ "code": code, "state": start["state"] });
+
+    let (status, _) = common::post_json(app.clone(), "/auth/oidc/callback", callback.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = common::post_json(app.clone(), "/auth/oidc/callback", callback).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = common::post_json(
+        app,
+        "/auth/oidc/callback",
+        json!({ "code": "whatever", "state": "made-up" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+#[serial]
+async fn code_from_another_login_is_refused() {
+    let (app, pool, idp) = setup().await;
+    set_registration_mode(&pool, "open").await;
+
+    // A code issued for one authorization request, redeemed with another
+    // request's state: the PKCE verifier and the nonce do not match.
+    let (_, first) = common::post_json(app.clone(), "/auth/oidc/authorize", json!({})).await;
+    let (_, second) = common::post_json(app.clone(), "/auth/oidc/authorize", json!({})).await;
+    let code = idp.approve(
+        first["authorization_url"].as_str().unwrap(),
+        json!({ "sub": subject() }),
+    );
+
+    let (status, body) = common::post_json(
+        app,
+        "/auth/oidc/callback",
+        json!({ "code": code, "state": second["state"] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "{body}");
+}
+
+#[tokio::test]
+#[serial]
+async fn id_token_for_another_client_is_refused() {
+    let (app, pool, idp) = setup().await;
+    set_registration_mode(&pool, "open").await;
+
+    let (status, _) = sso_login(
+        app.clone(),
+        &idp,
+        json!({ "sub": subject(), "aud": "someone-else" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = sso_login(
+        app,
+        &idp,
+        json!({ "sub": subject(), "exp": chrono::Utc::now().timestamp() - 3600 }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
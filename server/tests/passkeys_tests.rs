@@ -0,0 +1,547 @@
+mod common;
+
+use axum::{http::StatusCode, Router};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::Value as Cbor;
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use serde_json::{json, Value};
+use serial_test::serial;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+// ============================================================================
+// Test fixture helpers
+// ============================================================================
+
+const PASSWORD: &str = "password123";
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_DATA: u8 = 0x40;
+
+fn b64(bytes: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// A software ES256 authenticator, built independently of the server's
+/// verifier.
+struct Authenticator {
+    key: SigningKey,
+    credential_id: Vec<u8>,
+    sign_count: u32,
+    origin: String,
+}
+
+impl Authenticator {
+    fn new() -> Self {
+        Authenticator {
+            key: SigningKey::random(&mut rand::rngs::OsRng),
+            credential_id: rand::random::<[u8; 16]>().to_vec(),
+            sign_count: 0,
+            origin: common::TEST_WEBAUTHN_ORIGIN.to_string(),
+        }
+    }
+
+    fn cose_key(&self) -> Vec<u8> {
+        let point = self.key.verifying_key().to_encoded_point(false);
+        let key = Cbor::Map(vec![
+            (Cbor::from(1), Cbor::from(2)),
+            (Cbor::from(3), Cbor::from(-7)),
+            (Cbor::from(-1), Cbor::from(1)),
+            (Cbor::from(-2), Cbor::Bytes(point.x().unwrap().to_vec())),
+            (Cbor::from(-3), Cbor::Bytes(point.y().unwrap().to_vec())),
+        ]);
+        let mut out = Vec::new();
+        ciborium::ser::into_writer(&key, &mut out).unwrap();
+        out
+    }
+
+    fn authenticator_data(&self, flags: u8, attested: bool) -> Vec<u8> {
+        let mut data = Sha256::digest(common::TEST_WEBAUTHN_RP_ID.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend_from_slice(&self.sign_count.to_be_bytes());
+        if attested {
+            data.extend_from_slice(&[0; 16]);
+            data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+            data.extend_from_slice(&self.credential_id);
+            data.extend_from_slice(&self.cose_key());
+        }
+        data
+    }
+
+    fn client_data(&self, kind: &str, options: &Value) -> Vec<u8> {
+        serde_json::to_vec(&json!({
+            "type": kind,
+            "challenge": options["challenge"],
+            "origin": self.origin,
+            "crossOrigin": false,
+        }))
+        .unwrap()
+    }
+
+    /// `credential.toJSON()` for `navigator.credentials.create(options)`.
+    fn create(&self, options: &Value) -> Value {
+        let auth_data = self.authenticator_data(
+            FLAG_USER_PRESENT | FLAG_USER_VERIFIED | FLAG_ATTESTED_DATA,
+            true,
+        );
+        let attestation = Cbor::Map(vec![
+            (Cbor::from("fmt"), Cbor::from("none")),
+            (Cbor::from("attStmt"), Cbor::Map(vec![])),
+            (Cbor::from("authData"), Cbor::Bytes(auth_data)),
+        ]);
+        let mut attestation_object = Vec::new();
+        ciborium::ser::into_writer(&attestation, &mut attestation_object).unwrap();
+
+        json!({
+            "id": b64(&self.credential_id),
+            "rawId": b64(&self.credential_id),
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": b64(&self.client_data("webauthn.create", options)),
+                "attestationObject": b64(&attestation_object),
+                "transports": ["internal", "hybrid", "carrier-pigeon"],
+            },
+            "clientExtensionResults": {},
+        })
+    }
+
+    /// `credential.toJSON()` for `navigator.credentials.get(options)`.
+    fn get(&mut self, options: &Value, user_verified: bool, user_id: &str) -> Value {
+        self.sign_count += 1;
+        let flags = if user_verified {
+            FLAG_USER_PRESENT | FLAG_USER_VERIFIED
+        } else {
+            FLAG_USER_PRESENT
+        };
+        let auth_data = self.authenticator_data(flags, false);
+        let client_data = self.client_data("webauthn.get", options);
+
+        let mut message = auth_data.clone();
+        message.extend_from_slice(&Sha256::digest(&client_data));
+        let signature: Signature = self.key.sign(&message);
+        let user_handle = uuid::Uuid::parse_str(user_id).unwrap();
+
+        json!({
+            "id": b64(&self.credential_id),
+            "rawId": b64(&self.credential_id),
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": b64(&client_data),
+                "authenticatorData": b64(&auth_data),
+                "signature": b64(signature.to_der().as_bytes()),
+                "userHandle": b64(user_handle.as_bytes()),
+            },
+        })
+    }
+}
+
+/// Register a passkey for the user behind `token`.
+async fn register_passkey(app: Router, token: &str, authenticator: &Authenticator) -> Value {
+    let (status, options) = common::post_json_authed(
+        app.clone(),
+        "/users/@me/passkeys/options",
+        token,
+        json!({ "password": PASSWORD }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "options failed: {options}");
+
+    let (status, passkey) = common::post_json_authed(
+        app,
+        "/users/@me/passkeys",
+        token,
+        json!({ "name": "Laptop", "credential": authenticator.create(&options) }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "register failed: {passkey}");
+    passkey
+}
+
+/// Run a passwordless login with `authenticator`.
+async fn passkey_login(
+    app: Router,
+    authenticator: &mut Authenticator,
+    user_id: &str,
+    user_verified: bool,
+) -> (StatusCode, Value) {
+    let (status, options) =
+        common::post_json(app.clone(), "/auth/passkey/options", json!({})).await;
+    assert_eq!(status, StatusCode::OK, "options failed: {options}");
+    let credential = authenticator.get(&options, user_verified, user_id);
+    common::post_json(
+        app,
+        "/auth/passkey/login",
+        json!({ "credential": credential }),
+    )
+    .await
+}
+
+/// A registered user with one passkey. Returns (username, user id, token,
+/// authenticator).
+async fn user_with_passkey(app: Router) -> (String, String, String, Authenticator) {
+    let username = common::unique_username();
+    let registered = common::register_user(app.clone(), &username, PASSWORD).await;
+    let token = registered["access_token"].as_str().unwrap().to_string();
+    let user_id = registered["user"]["id"].as_str().unwrap().to_string();
+    let authenticator = Authenticator::new();
+    register_passkey(app, &token, &authenticator).await;
+    (username, user_id, token, authenticator)
+}
+
+/// Tests share the instance_settings row, so every test resets the 2FA
+/// requirement first and runs #[serial].
+async fn setup() -> (Router, PgPool) {
+    let pool = common::test_pool().await;
+    sqlx::query("UPDATE instance_settings SET require_two_factor = 'off' WHERE id = 1")
+        .execute(&pool)
+        .await
+        .unwrap();
+    (common::create_test_app(pool.clone()), pool)
+}
+
+// ============================================================================
+// Registration and management
+// ============================================================================
+
+#[tokio::test]
+#[serial]
+async fn register_and_list_passkeys() {
+    let (app, _) = setup().await;
+    let username = common::unique_username();
+    let token = common::register_and_get_token(app.clone(), &username, PASSWORD).await;
+
+    let (status, _) = common::post_json_authed(
+        app.clone(),
+        "/users/@me/passkeys/options",
+        &token,
+        json!({ "password": "wrong-password" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let authenticator = Authenticator::new();
+    let passkey = register_passkey(app.clone(), &token, &authenticator).await;
+    assert_eq!(passkey["name"], "Laptop");
+    assert_eq!(passkey["transports"], json!(["internal", "hybrid"]));
+    assert!(passkey["last_used_at"].is_null());
+
+    let (status, list) = common::get_authed(app.clone(), "/users/@me/passkeys", &token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(list.as_array().unwrap().len(), 1);
+    assert_eq!(list[0]["id"], passkey["id"]);
+
+    // New options exclude the registered credential, and registering it
+    // again is a conflict.
+    let (_, options) = common::post_json_authed(
+        app.clone(),
+        "/users/@me/passkeys/options",
+        &token,
+        json!({ "password": PASSWORD }),
+    )
+    .await;
+    assert_eq!(options["rp"]["id"], common::TEST_WEBAUTHN_RP_ID);
+    assert_eq!(
+        options["excludeCredentials"][0]["id"],
+        b64(&authenticator.credential_id)
+    );
+    let (status, _) = common::post_json_authed(
+        app,
+        "/users/@me/passkeys",
+        &token,
+        json!({ "credential": authenticator.create(&options) }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+#[serial]
+async fn registration_rejects_wrong_origin_and_reused_challenge() {
+    let (app, _) = setup().await;
+    let token =
+        common::register_and_get_token(app.clone(), &common::unique_username(), PASSWORD).await;
+
+    let (_, options) = common::post_json_authed(
+        app.clone(),
+        "/users/@me/passkeys/options",
+        &token,
+        json!({ "password": PASSWORD }),
+    )
+    .await;
+    let mut phished = Authenticator::new();
+    phished.origin = "https://evil.example".to_string();
+    let (status, _) = common::post_json_authed(
+        app.clone(),
+        "/users/@me/passkeys",
+        &token,
+        json!({ "credential": phished.create(&options) }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let authenticator = Authenticator::new();
+    let (_, options) = common::post_json_authed(
+        app.clone(),
+        "/users/@me/passkeys/options",
+        &token,
+        json!({ "password": PASSWORD }),
+    )
+    .await;
+    let credential = authenticator.create(&options);
+    let (status, _) = common::post_json_authed(
+        app.clone(),
+        "/users/@me/passkeys",
+        &token,
+        json!({ "credential": credential }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // The challenge is used up.
+    let (status, _) = common::post_json_authed(
+        app,
+        "/users/@me/passkeys",
+        &token,
+        json!({ "credential": Authenticator::new().create(&options) }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+#[serial]
+async fn rename_and_delete_passkey() {
+    let (app, _) = setup().await;
+    let (username, _, token, _) = user_with_passkey(app.clone()).await;
+    let (_, list) = common::get_authed(app.clone(), "/users/@me/passkeys", &token).await;
+    let id = list[0]["id"].as_str().unwrap();
+
+    let (status, renamed) = common::patch_json_authed(
+        app.clone(),
+        &format!("/users/@me/passkeys/{id}"),
+        &token,
+        json!({ "name": "YubiKey" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(renamed["name"], "YubiKey");
+
+    // Another user cannot touch it.
+    let other =
+        common::register_and_get_token(app.clone(), &common::unique_username(), PASSWORD).await;
+    let (status, _) =
+        common::delete_authed(app.clone(), &format!("/users/@me/passkeys/{id}"), &other).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) =
+        common::delete_authed(app.clone(), &format!("/users/@me/passkeys/{id}"), &token).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    // Without a passkey, the password login needs no second step.
+    let (_, body) = common::post_json(
+        app,
+        "/auth/login",
+        json!({ "username": username, "password": PASSWORD }),
+    )
+    .await;
+    assert!(body["access_token"].is_string(), "body: {body}");
+}
+
+// ============================================================================
+// Passwordless login
+// ============================================================================
+
+#[tokio::test]
+#[serial]
+async fn passkey_login_issues_tokens() {
+    let (app, _) = setup().await;
+    let (_, user_id, token, mut authenticator) = user_with_passkey(app.clone()).await;
+
+    let (status, body) = passkey_login(app.clone(), &mut authenticator, &user_id, true).await;
+    assert_eq!(status, StatusCode::OK, "body: {body}");
+    assert!(body["access_token"].is_string());
+    assert!(body["refresh_token"].is_string());
+    assert_eq!(body["user"]["id"], user_id.as_str());
+
+    let (_, list) = common::get_authed(app, "/users/@me/passkeys", &token).await;
+    assert!(list[0]["last_used_at"].is_string());
+}
+
+#[tokio::test]
+#[serial]
+async fn passkey_login_rejects_bad_assertions() {
+    let (app, _) = setup().await;
+    let (_, user_id, _, mut authenticator) = user_with_passkey(app.clone()).await;
+
+    // The authenticator only checked presence, not the user.
+    let (status, _) = passkey_login(app.clone(), &mut authenticator, &user_id, false).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // A counter that does not go up suggests a cloned authenticator.
+    let (status, _) = passkey_login(app.clone(), &mut authenticator, &user_id, true).await;
+    assert_eq!(status, StatusCode::OK);
+    authenticator.sign_count -= 1;
+    let (status, _) = passkey_login(app.clone(), &mut authenticator, &user_id, true).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // An unknown credential.
+    let (status, _) = passkey_login(app.clone(), &mut Authenticator::new(), &user_id, true).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // A challenge works once.
+    let (_, options) = common::post_json(app.clone(), "/auth/passkey/options", json!({})).await;
+    authenticator.sign_count += 10;
+    let credential = authenticator.get(&options, true, &user_id);
+    let (status, _) = common::post_json(
+        app.clone(),
+        "/auth/passkey/login",
+        json!({ "credential": credential }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let credential = authenticator.get(&options, true, &user_id);
+    let (status, _) = common::post_json(
+        app,
+        "/auth/passkey/login",
+        json!({ "credential": credential }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+// ============================================================================
+// Second factor
+// ============================================================================
+
+#[tokio::test]
+#[serial]
+async fn passkey_completes_password_login() {
+    let (app, _) = setup().await;
+    let (username, user_id, _, mut authenticator) = user_with_passkey(app.clone()).await;
+
+    let (status, body) = common::post_json(
+        app.clone(),
+        "/auth/login",
+        json!({ "username": username, "password": PASSWORD }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["two_factor"], "verify");
+    assert_eq!(body["methods"], json!(["passkey"]));
+    let challenge = body["challenge"].as_str().unwrap().to_string();
+
+    let (status, options) = common::post_json(
+        app.clone(),
+        "/auth/two-factor/passkey/options",
+        json!({ "challenge": challenge }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "body: {options}");
+    assert_eq!(
+        options["allowCredentials"][0]["id"],
+        b64(&authenticator.credential_id)
+    );
+
+    // Presence is enough for the second step.
+    let credential = authenticator.get(&options, false, &user_id);
+    let (status, body) = common::post_json(
+        app.clone(),
+        "/auth/two-factor/passkey",
+        json!({ "challenge": challenge, "credential": credential }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "body: {body}");
+    assert_eq!(body["user"]["id"], user_id.as_str());
+
+    // The login challenge is used up.
+    let (status, _) = common::post_json(
+        app,
+        "/auth/two-factor/passkey/options",
+        json!({ "challenge": challenge }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+#[serial]
+async fn another_users_passkey_cannot_complete_login() {
+    let (app, _) = setup().await;
+    let (username, user_id, _, mut authenticator) = user_with_passkey(app.clone()).await;
+    let (_, other_id, _, mut other) = user_with_passkey(app.clone()).await;
+
+    let (_, body) = common::post_json(
+        app.clone(),
+        "/auth/login",
+        json!({ "username": username, "password": PASSWORD }),
+    )
+    .await;
+    let challenge = body["challenge"].as_str().unwrap().to_string();
+
+    let (_, options) = common::post_json(
+        app.clone(),
+        "/auth/two-factor/passkey/options",
+        json!({ "challenge": challenge }),
+    )
+    .await;
+    let credential = other.get(&options, true, &other_id);
+    let (status, _) = common::post_json(
+        app.clone(),
+        "/auth/two-factor/passkey",
+        json!({ "challenge": challenge, "credential": credential }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // The failure counted against the challenge, but it is still usable.
+    let (_, options) = common::post_json(
+        app.clone(),
+        "/auth/two-factor/passkey/options",
+        json!({ "challenge": challenge }),
+    )
+    .await;
+    let credential = authenticator.get(&options, false, &user_id);
+    let (status, _) = common::post_json(
+        app,
+        "/auth/two-factor/passkey",
+        json!({ "challenge": challenge, "credential": credential }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+#[serial]
+async fn required_two_factor_accepts_passkey() {
+    let (app, pool) = setup().await;
+    let (username, _, token, _) = user_with_passkey(app.clone()).await;
+    sqlx::query("UPDATE instance_settings SET require_two_factor = 'everyone' WHERE id = 1")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // A passkey satisfies the requirement: login asks to verify, not enroll.
+    let (_, body) = common::post_json(
+        app.clone(),
+        "/auth/login",
+        json!({ "username": username, "password": PASSWORD }),
+    )
+    .await;
+    assert_eq!(body["two_factor"], "verify");
+
+    // The last passkey of a covered user cannot be removed.
+    let (_, list) = common::get_authed(app.clone(), "/users/@me/passkeys", &token).await;
+    let (status, _) = common::delete_authed(
+        app,
+        &format!("/users/@me/passkeys/{}", list[0]["id"].as_str().unwrap()),
+        &token,
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    sqlx::query("UPDATE instance_settings SET require_two_factor = 'off' WHERE id = 1")
+        .execute(&pool)
+        .await
+        .unwrap();
+}
@@ -0,0 +1,459 @@
+mod common;
+
+use std::time::Duration;
+
+use aes_gcm::{aead::Aead, Aes128Gcm, KeyInit};
+use axum::{
+    body::Bytes,
+    http::{HeaderMap, StatusCode},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hkdf::Hkdf;
+use p256::{ecdh::diffie_hellman, elliptic_curve::sec1::ToEncodedPoint, PublicKey, SecretKey};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tokio::sync::mpsc;
+
+// ============================================================================
+// Test fixture helpers
+// ============================================================================
+
+/// A request received by the stand-in push service.
+struct Delivery {
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+/// Keys a browser would generate when subscribing.
+struct Browser {
+    secret: SecretKey,
+    auth: [u8; 16],
+}
+
+impl Browser {
+    fn new() -> Self {
+        Self {
+            secret: SecretKey::random(&mut rand::thread_rng()),
+            auth: rand::random(),
+        }
+    }
+
+    fn keys(&self) -> Value {
+        json!({
+            "p256dh": URL_SAFE_NO_PAD.encode(self.secret.public_key().to_encoded_point(false).as_bytes()),
+            "auth": URL_SAFE_NO_PAD.encode(self.auth),
+        })
+    }
+
+    /// Decrypt an `aes128gcm` body (RFC 8291) and parse the JSON payload.
+    fn decrypt(&self, body: &[u8]) -> Value {
+        let salt = &body[..16];
+        let key_len = body[20] as usize;
+        let as_public = PublicKey::from_sec1_bytes(&body[21..21 + key_len]).unwrap();
+        let shared = diffie_hellman(self.secret.to_nonzero_scalar(), as_public.as_affine());
+
+        let mut key_info = b"WebPush: info\0".to_vec();
+        key_info.extend_from_slice(self.secret.public_key().to_encoded_point(false).as_bytes());
+        key_info.extend_from_slice(as_public.to_encoded_point(false).as_bytes());
+        let mut ikm = [0u8; 32];
+        Hkdf::<Sha256>::new(Some(&self.auth), shared.raw_secret_bytes())
+            .expand(&key_info, &mut ikm)
+            .unwrap();
+        let prk = Hkdf::<Sha256>::new(Some(salt), &ikm);
+        let mut cek = [0u8; 16];
+        let mut nonce = [0u8; 12];
+        prk.expand(b"Content-Encoding: aes128gcm\0", &mut cek)
+            .unwrap();
+        prk.expand(b"Content-Encoding: nonce\0", &mut nonce)
+            .unwrap();
+
+        let mut record = Aes128Gcm::new(&cek.into())
+            .decrypt(&nonce.into(), &body[21 + key_len..])
+            .unwrap();
+        assert_eq!(record.pop(), Some(0x02));
+        serde_json::from_slice(&record).unwrap()
+    }
+}
+
+fn vapid_private_key() -> String {
+    let key = SecretKey::random(&mut rand::thread_rng());
+    URL_SAFE_NO_PAD.encode(key.to_bytes())
+}
+
+/// Start a local push service that answers every POST with `status` and
+/// forwards what it received. Returns the endpoint URL.
+async fn spawn_push_service(status: StatusCode) -> (String, mpsc::UnboundedReceiver<Delivery>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let app = axum::Router::new().route(
+        "/push",
+        axum::routing::post(move |headers: HeaderMap, body: Bytes| {
+            let tx = tx.clone();
+            async move {
+                let _ = tx.send(Delivery { headers, body });
+                status
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{addr}/push"), rx)
+}
+
+async fn next_delivery(rx: &mut mpsc::UnboundedReceiver<Delivery>) -> Delivery {
+    tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("no push was delivered")
+        .unwrap()
+}
+
+async fn assert_no_delivery(rx: &mut mpsc::UnboundedReceiver<Delivery>) {
+    let received = tokio::time::timeout(Duration::from_millis(750), rx.recv()).await;
+    assert!(received.is_err(), "unexpected push delivery");
+}
+
+async fn subscribe(app: axum::Router, token: &str, endpoint: &str, browser: &Browser) -> Value {
+    let (status, body) = common::post_json_authed(
+        app,
+        "/users/@me/push-subscriptions",
+        token,
+        json!({ "endpoint": endpoint, "keys": browser.keys() }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "subscribe failed: {body}");
+    body
+}
+
+struct Fixture {
+    owner_token: String,
+    member_token: String,
+    member_name: String,
+    channel_id: String,
+}
+
+/// Owner creates a public server with one channel; a second user joins it.
+async fn setup(app: axum::Router) -> Fixture {
+    let owner_token =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "pass1234").await;
+    let server = common::create_server(app.clone(), &owner_token, "Push Guild").await;
+    let server_id = server["id"].as_str().unwrap().to_owned();
+    common::make_server_public(app.clone(), &owner_token, &server_id).await;
+    let channel = common::create_channel(app.clone(), &owner_token, &server_id, "general").await;
+
+    let member_name = common::unique_username();
+    let member_token = common::register_and_get_token(app.clone(), &member_name, "pass1234").await;
+    let (status, _) = common::post_json_authed(
+        app,
+        &format!("/servers/{server_id}/join"),
+        &member_token,
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    Fixture {
+        owner_token,
+        member_token,
+        member_name,
+        channel_id: channel["id"].as_str().unwrap().to_owned(),
+    }
+}
+
+// ============================================================================
+// Subscriptions
+// ============================================================================
+
+#[tokio::test]
+async fn push_endpoints_404_when_not_configured() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let token =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "pass1234").await;
+
+    let (status, _) = common::get_authed(app.clone(), "/push/vapid-public-key", &token).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = common::post_json_authed(
+        app,
+        "/users/@me/push-subscriptions",
+        &token,
+        json!({ "endpoint": "https://push.example.com/x", "keys": Browser::new().keys() }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn subscription_lifecycle() {
+    let pool = common::test_pool().await;
+    let (app, _state) = common::create_test_app_with_push(pool, &vapid_private_key());
+    let token =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "pass1234").await;
+
+    let (status, body) = common::get_authed(app.clone(), "/push/vapid-public-key", &token).await;
+    assert_eq!(status, StatusCode::OK);
+    let public_key = URL_SAFE_NO_PAD
+        .decode(body["public_key"].as_str().unwrap())
+        .unwrap();
+    assert!(PublicKey::from_sec1_bytes(&public_key).is_ok());
+
+    let endpoint = format!("https://push.example.com/{}", uuid::Uuid::new_v4());
+    let browser = Browser::new();
+    let created = subscribe(app.clone(), &token, &endpoint, &browser).await;
+    // Subscribing the same endpoint again replaces it rather than adding one.
+    let again = subscribe(app.clone(), &token, &endpoint, &Browser::new()).await;
+    assert_eq!(created["id"], again["id"]);
+
+    let (status, list) =
+        common::get_authed(app.clone(), "/users/@me/push-subscriptions", &token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(list.as_array().unwrap().len(), 1);
+    assert_eq!(list[0]["endpoint"], endpoint);
+
+    let id = created["id"].as_str().unwrap();
+    let uri = format!("/users/@me/push-subscriptions/{id}");
+    let (status, _) = common::delete_authed(app.clone(), &uri, &token).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = common::delete_authed(app, &uri, &token).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn subscription_rejects_bad_keys() {
+    let pool = common::test_pool().await;
+    let (app, _state) = common::create_test_app_with_push(pool, &vapid_private_key());
+    let token =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "pass1234").await;
+
+    let (status, _) = common::post_json_authed(
+        app.clone(),
+        "/users/@me/push-subscriptions",
+        &token,
+        json!({
+            "endpoint": "https://push.example.com/bad",
+            "keys": { "p256dh": "bm90LWEta2V5", "auth": "c2hvcnQ" },
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = common::post_json_authed(
+        app,
+        "/users/@me/push-subscriptions",
+        &token,
+        json!({ "endpoint": "ftp://push.example.com/bad", "keys": Browser::new().keys() }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+// ============================================================================
+// Delivery
+// ============================================================================
+
+#[tokio::test]
+async fn offline_dm_recipient_gets_encrypted_push() {
+    let pool = common::test_pool().await;
+    let (app, _state) = common::create_test_app_with_push(pool, &vapid_private_key());
+    let sender_name = common::unique_username();
+    let sender = common::register_user(app.clone(), &sender_name, "pass1234").await;
+    let sender_token = sender["access_token"].as_str().unwrap();
+    let recipient =
+        common::register_user(app.clone(), &common::unique_username(), "pass1234").await;
+    let recipient_token = recipient["access_token"].as_str().unwrap();
+    let recipient_id = recipient["user"]["id"].as_str().unwrap();
+
+    let (endpoint, mut deliveries) = spawn_push_service(StatusCode::CREATED).await;
+    let browser = Browser::new();
+    subscribe(app.clone(), recipient_token, &endpoint, &browser).await;
+
+    let dm = common::open_dm_channel(app.clone(), sender_token, recipient_id).await;
+    let dm_id = dm["id"].as_str().unwrap();
+    let message = common::send_dm_message(app.clone(), sender_token, dm_id, "hello there").await;
+
+    let delivery = next_delivery(&mut deliveries).await;
+    assert_eq!(delivery.headers["content-encoding"], "aes128gcm");
+    assert_eq!(delivery.headers["ttl"], "86400");
+    assert!(delivery.headers["authorization"]
+        .to_str()
+        .unwrap()
+        .starts_with("vapid t="));
+
+    let payload = browser.decrypt(&delivery.body);
+    assert_eq!(payload["type"], "dm");
+    assert_eq!(payload["channel_id"], dm_id);
+    assert_eq!(payload["message_id"], message["id"]);
+    assert_eq!(payload["author"]["username"], sender_name);
+    assert_eq!(payload["body"], "hello there");
+    assert!(payload["server_id"].is_null());
+
+    // The push service accepted it, so the subscription records a success.
+    for _ in 0..50 {
+        let (_, list) = common::get_authed(
+            app.clone(),
+            "/users/@me/push-subscriptions",
+            recipient_token,
+        )
+        .await;
+        if !list[0]["last_success_at"].is_null() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("successful delivery was not recorded");
+}
+
+#[tokio::test]
+async fn mention_pushes_but_plain_message_does_not() {
+    let pool = common::test_pool().await;
+    let (app, _state) = common::create_test_app_with_push(pool, &vapid_private_key());
+    let f = setup(app.clone()).await;
+    let (endpoint, mut deliveries) = spawn_push_service(StatusCode::CREATED).await;
+    let browser = Browser::new();
+    subscribe(app.clone(), &f.member_token, &endpoint, &browser).await;
+
+    common::create_message(
+        app.clone(),
+        &f.owner_token,
+        &f.channel_id,
+        "nobody in particular",
+    )
+    .await;
+    assert_no_delivery(&mut deliveries).await;
+
+    common::create_message(
+        app.clone(),
+        &f.owner_token,
+        &f.channel_id,
+        &format!("hey @{}", f.member_name),
+    )
+    .await;
+    let payload = browser.decrypt(&next_delivery(&mut deliveries).await.body);
+    assert_eq!(payload["type"], "mention");
+    assert_eq!(payload["channel_id"], f.channel_id);
+}
+
+#[tokio::test]
+async fn disabled_push_preferences_suppress_delivery() {
+    let pool = common::test_pool().await;
+    let (app, _state) = common::create_test_app_with_push(pool, &vapid_private_key());
+    let f = setup(app.clone()).await;
+    let (endpoint, mut deliveries) = spawn_push_service(StatusCode::CREATED).await;
+    subscribe(app.clone(), &f.member_token, &endpoint, &Browser::new()).await;
+
+    let (status, body) = common::put_json_authed(
+        app.clone(),
+        "/users/@me/push-preferences",
+        &f.member_token,
+        json!({ "enabled": false }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["enabled"], false);
+    let (_, body) =
+        common::get_authed(app.clone(), "/users/@me/push-preferences", &f.member_token).await;
+    assert_eq!(body["enabled"], false);
+
+    common::create_message(
+        app,
+        &f.owner_token,
+        &f.channel_id,
+        &format!("hey @{}", f.member_name),
+    )
+    .await;
+    assert_no_delivery(&mut deliveries).await;
+}
+
+#[tokio::test]
+async fn dnd_and_muted_users_are_not_pushed() {
+    let pool = common::test_pool().await;
+    let (app, _state) = common::create_test_app_with_push(pool, &vapid_private_key());
+    let f = setup(app.clone()).await;
+    let (endpoint, mut deliveries) = spawn_push_service(StatusCode::CREATED).await;
+    subscribe(app.clone(), &f.member_token, &endpoint, &Browser::new()).await;
+    let mention = format!("hey @{}", f.member_name);
+
+    let (status, _) = common::patch_json_authed(
+        app.clone(),
+        "/users/@me",
+        &f.member_token,
+        json!({ "status": "dnd" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    common::create_message(app.clone(), &f.owner_token, &f.channel_id, &mention).await;
+    assert_no_delivery(&mut deliveries).await;
+
+    common::patch_json_authed(
+        app.clone(),
+        "/users/@me",
+        &f.member_token,
+        json!({ "status": "online" }),
+    )
+    .await;
+    let (status, _) = common::put_json_authed(
+        app.clone(),
+        &format!("/channels/{}/notification-settings", f.channel_id),
+        &f.member_token,
+        json!({ "level": "none" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    common::create_message(app, &f.owner_token, &f.channel_id, &mention).await;
+    assert_no_delivery(&mut deliveries).await;
+}
+
+#[tokio::test]
+async fn connected_users_are_not_pushed() {
+    let pool = common::test_pool().await;
+    let (app, state) = common::create_test_app_with_push(pool, &vapid_private_key());
+    let f = setup(app.clone()).await;
+    let (endpoint, mut deliveries) = spawn_push_service(StatusCode::CREATED).await;
+    subscribe(app.clone(), &f.member_token, &endpoint, &Browser::new()).await;
+
+    let (_, me) = common::get_authed(app.clone(), "/users/@me", &f.member_token).await;
+    let member_id: uuid::Uuid = me["id"].as_str().unwrap().parse().unwrap();
+    let (tx, _rx) = mpsc::unbounded_channel();
+    state.connections.add(member_id, tx).await;
+
+    common::create_message(
+        app,
+        &f.owner_token,
+        &f.channel_id,
+        &format!("hey @{}", f.member_name),
+    )
+    .await;
+    assert_no_delivery(&mut deliveries).await;
+}
+
+#[tokio::test]
+async fn gone_subscription_is_removed() {
+    let pool = common::test_pool().await;
+    let (app, _state) = common::create_test_app_with_push(pool, &vapid_private_key());
+    let f = setup(app.clone()).await;
+    let (endpoint, mut deliveries) = spawn_push_service(StatusCode::GONE).await;
+    subscribe(app.clone(), &f.member_token, &endpoint, &Browser::new()).await;
+
+    common::create_message(
+        app.clone(),
+        &f.owner_token,
+        &f.channel_id,
+        &format!("hey @{}", f.member_name),
+    )
+    .await;
+    next_delivery(&mut deliveries).await;
+
+    for _ in 0..50 {
+        let (_, list) = common::get_authed(
+            app.clone(),
+            "/users/@me/push-subscriptions",
+            &f.member_token,
+        )
+        .await;
+        if list.as_array().unwrap().is_empty() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("expired subscription was not removed");
+}
@@ -162,3 +162,204 @@ async fn test_ack_dm_channel_requires_auth() {
 
     assert_eq!(status, StatusCode::UNAUTHORIZED);
 }
+
+// ============================================================================
+// Unread and mention counts
+// ============================================================================
+
+struct CountsFixture {
+    owner_token: String,
+    owner_id: uuid::Uuid,
+    member_token: String,
+    server_id: String,
+    channel_id: String,
+}
+
+/// Owner creates a server and channel; a second user joins it.
+async fn setup_counts(app: axum::Router) -> CountsFixture {
+    let owner_name = common::unique_username();
+    let owner = common::register_user(app.clone(), &owner_name, "pass1234").await;
+    let owner_token = owner["access_token"].as_str().unwrap().to_owned();
+    let owner_id = owner["user"]["id"].as_str().unwrap().parse().unwrap();
+    let server = common::create_server(app.clone(), &owner_token, "Counts Guild").await;
+    let server_id = server["id"].as_str().unwrap().to_owned();
+    let channel = common::create_channel(app.clone(), &owner_token, &server_id, "general").await;
+    let channel_id = channel["id"].as_str().unwrap().to_owned();
+
+    let member_token =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "pass1234").await;
+    common::make_server_public(app.clone(), &owner_token, &server_id).await;
+    common::post_json_authed(
+        app,
+        &format!("/servers/{server_id}/join"),
+        &member_token,
+        json!({}),
+    )
+    .await;
+
+    CountsFixture {
+        owner_token,
+        owner_id,
+        member_token,
+        server_id,
+        channel_id,
+    }
+}
+
+fn state_for<'a>(
+    states: &'a [together_server::models::ReadStateDto],
+    channel_id: &str,
+) -> &'a together_server::models::ReadStateDto {
+    states
+        .iter()
+        .find(|rs| rs.channel_id.to_string() == channel_id)
+        .expect("channel missing from read states")
+}
+
+#[tokio::test]
+async fn ready_counts_unread_and_mentions_without_prior_ack() {
+    let pool = common::test_pool().await;
+    let (app, state) = common::create_test_app_with_state(pool);
+    let f = setup_counts(app.clone()).await;
+    let (_, owner) = common::get_authed(app.clone(), "/users/@me", &f.owner_token).await;
+    let owner_name = owner["username"].as_str().unwrap();
+
+    common::create_message(app.clone(), &f.member_token, &f.channel_id, "hello").await;
+    common::create_message(
+        app.clone(),
+        &f.member_token,
+        &f.channel_id,
+        &format!("hey @{owner_name}"),
+    )
+    .await;
+    common::create_message(app.clone(), &f.member_token, &f.channel_id, "@everyone").await;
+    // The user's own messages never count as unread.
+    common::create_message(app, &f.owner_token, &f.channel_id, "mine").await;
+
+    let states = together_server::handlers::read_states::ready_read_states(&state, f.owner_id)
+        .await
+        .unwrap();
+    let rs = state_for(&states, &f.channel_id);
+    assert_eq!(rs.unread_count, 3);
+    assert_eq!(rs.mention_count, 2);
+    assert!(rs.last_read_at.is_none());
+}
+
+#[tokio::test]
+async fn ack_to_message_keeps_later_messages_unread() {
+    let pool = common::test_pool().await;
+    let (app, state) = common::create_test_app_with_state(pool);
+    let f = setup_counts(app.clone()).await;
+
+    let first = common::create_message(app.clone(), &f.member_token, &f.channel_id, "one").await;
+    common::create_message(app.clone(), &f.member_token, &f.channel_id, "two").await;
+    common::create_message(app.clone(), &f.member_token, &f.channel_id, "three").await;
+
+    let (status, _) = common::post_json_authed(
+        app.clone(),
+        &format!("/channels/{}/ack", f.channel_id),
+        &f.owner_token,
+        json!({ "message_id": first["id"] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let states = together_server::handlers::read_states::ready_read_states(&state, f.owner_id)
+        .await
+        .unwrap();
+    let rs = state_for(&states, &f.channel_id);
+    assert_eq!(rs.unread_count, 2);
+    assert_eq!(
+        rs.last_message_id.map(|id| id.to_string()).as_deref(),
+        first["id"].as_str()
+    );
+
+    // A message from another channel cannot be used as the cursor.
+    let other = common::create_channel(app.clone(), &f.owner_token, &f.server_id, "other").await;
+    let foreign = common::create_message(
+        app.clone(),
+        &f.owner_token,
+        other["id"].as_str().unwrap(),
+        "x",
+    )
+    .await;
+    let (status, _) = common::post_json_authed(
+        app,
+        &format!("/channels/{}/ack", f.channel_id),
+        &f.owner_token,
+        json!({ "message_id": foreign["id"] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn ack_dispatches_read_state_update_to_own_sessions() {
+    let pool = common::test_pool().await;
+    let (app, state) = common::create_test_app_with_state(pool);
+    let f = setup_counts(app.clone()).await;
+    common::create_message(app.clone(), &f.member_token, &f.channel_id, "ping").await;
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    state.connections.add(f.owner_id, tx).await;
+
+    let (status, _) = common::post_json_authed(
+        app,
+        &format!("/channels/{}/ack", f.channel_id),
+        &f.owner_token,
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let mut update = None;
+    while let Ok(frame) = rx.try_recv() {
+        let v: serde_json::Value = serde_json::from_str(&frame).unwrap();
+        if v["t"] == "READ_STATE_UPDATE" {
+            update = Some(v);
+        }
+    }
+    let update = update.expect("no READ_STATE_UPDATE dispatched");
+    let rs = &update["d"]["read_states"][0];
+    assert_eq!(rs["channel_id"], f.channel_id.as_str());
+    assert_eq!(rs["unread_count"], 0);
+    assert!(rs["last_message_id"].is_string());
+}
+
+#[tokio::test]
+async fn ack_server_marks_every_channel_read() {
+    let pool = common::test_pool().await;
+    let (app, state) = common::create_test_app_with_state(pool);
+    let f = setup_counts(app.clone()).await;
+    let second = common::create_channel(app.clone(), &f.owner_token, &f.server_id, "random").await;
+    let second_id = second["id"].as_str().unwrap();
+
+    common::create_message(app.clone(), &f.member_token, &f.channel_id, "a").await;
+    common::create_message(app.clone(), &f.member_token, second_id, "b").await;
+
+    let (status, _) = common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{}/ack", f.server_id),
+        &f.owner_token,
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let states = together_server::handlers::read_states::ready_read_states(&state, f.owner_id)
+        .await
+        .unwrap();
+    assert_eq!(state_for(&states, &f.channel_id).unread_count, 0);
+    assert_eq!(state_for(&states, second_id).unread_count, 0);
+
+    let outsider =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "pass1234").await;
+    let (status, _) = common::post_json_authed(
+        app,
+        &format!("/servers/{}/ack", f.server_id),
+        &outsider,
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -217,6 +217,50 @@ async fn registration_invite_only_with_invalid_code_fails() {
     reset_registration_mode(&pool).await;
 }
 
+#[tokio::test]
+#[serial]
+async fn registration_invite_only_failed_signup_keeps_invite_use() {
+    let (app, admin_token, pool) = setup_admin().await;
+    let server = common::create_server(app.clone(), &admin_token, "Invite Only").await;
+    let (status, invite) = common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{}/invites", server["id"].as_str().unwrap()),
+        &admin_token,
+        json!({ "max_uses": 1 }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{invite}");
+    let code = invite["code"].as_str().unwrap();
+    let taken = common::unique_username();
+    common::register_user(app.clone(), &taken, "pass1234").await;
+
+    set_registration_mode(&pool, "invite_only").await;
+
+    // The username is taken, so the signup fails after the invite checks out.
+    let (status, _) = common::post_json(
+        app.clone(),
+        "/auth/register",
+        json!({ "username": taken, "password": "pass1234", "invite_code": code }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // The single use is still available.
+    let (status, body) = common::post_json(
+        app,
+        "/auth/register",
+        json!({
+            "username": common::unique_username(),
+            "password": "pass1234",
+            "invite_code": code
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+
+    reset_registration_mode(&pool).await;
+}
+
 // ============================================================================
 // Server require_invite
 // ============================================================================
@@ -0,0 +1,313 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::json;
+use together_server::{scheduled_messages, state::AppState};
+use uuid::Uuid;
+
+// ============================================================================
+// Test fixture helpers
+// ============================================================================
+
+/// Register a user, create a server with a text channel, and return
+/// `(token, username, server_id, channel_id)`.
+async fn setup_channel(app: axum::Router) -> (String, String, String, String) {
+    let username = common::unique_username();
+    let token = common::register_and_get_token(app.clone(), &username, "pass1234").await;
+    let server = common::create_server(app.clone(), &token, "Scheduling Guild").await;
+    let server_id = server["id"].as_str().unwrap().to_owned();
+    let channel = common::create_channel(app.clone(), &token, &server_id, "announcements").await;
+    let channel_id = channel["id"].as_str().unwrap().to_owned();
+    (token, username, server_id, channel_id)
+}
+
+fn in_one_hour() -> String {
+    (chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339()
+}
+
+async fn schedule(
+    app: axum::Router,
+    token: &str,
+    channel_id: &str,
+    body: serde_json::Value,
+) -> serde_json::Value {
+    let (status, scheduled) = common::post_json_authed(
+        app,
+        &format!("/channels/{channel_id}/scheduled-messages"),
+        token,
+        body,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "schedule failed: {scheduled}");
+    scheduled
+}
+
+/// Make a scheduled message due now and run the scheduler until it has been
+/// posted or marked failed. Other tests may run the scheduler concurrently
+/// and claim the row first, so poll for the outcome.
+async fn run_until_processed(state: &AppState, id: &str) {
+    let id: Uuid = id.parse().unwrap();
+    sqlx::query(
+        "UPDATE scheduled_messages SET send_at = NOW() - INTERVAL '1 second' WHERE id = $1",
+    )
+    .bind(id)
+    .execute(&state.pool)
+    .await
+    .unwrap();
+    for _ in 0..50 {
+        scheduled_messages::dispatch_due(state).await.unwrap();
+        let status: Option<String> =
+            sqlx::query_scalar("SELECT status FROM scheduled_messages WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&state.pool)
+                .await
+                .unwrap();
+        if status.as_deref().is_none_or(|s| s == "failed") {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    panic!("scheduled message {id} was never processed");
+}
+
+// ============================================================================
+// CRUD
+// ============================================================================
+
+#[tokio::test]
+async fn create_and_list_scheduled_messages() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, _, _, channel_id) = setup_channel(app.clone()).await;
+
+    let scheduled = schedule(
+        app.clone(),
+        &token,
+        &channel_id,
+        json!({ "content": "Release at noon", "send_at": in_one_hour() }),
+    )
+    .await;
+    assert_eq!(scheduled["status"], "pending");
+    assert_eq!(scheduled["kind"], "message");
+    assert_eq!(scheduled["channel_id"], channel_id);
+
+    let (status, list) = common::get_authed(app, "/users/@me/scheduled-messages", &token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(list.as_array().unwrap().len(), 1);
+    assert_eq!(list[0]["id"], scheduled["id"]);
+}
+
+#[tokio::test]
+async fn create_rejects_invalid_input() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, _, _, channel_id) = setup_channel(app.clone()).await;
+    let past = (chrono::Utc::now() - chrono::Duration::minutes(1)).to_rfc3339();
+    let too_far = (chrono::Utc::now() + chrono::Duration::days(400)).to_rfc3339();
+
+    for body in [
+        json!({ "content": "hi", "send_at": past }),
+        json!({ "content": "hi", "send_at": too_far }),
+        json!({ "content": "   ", "send_at": in_one_hour() }),
+        json!({ "content": "x".repeat(3901), "send_at": in_one_hour(), "kind": "reminder" }),
+    ] {
+        let (status, _) = common::post_json_authed(
+            app.clone(),
+            &format!("/channels/{channel_id}/scheduled-messages"),
+            &token,
+            body.clone(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "accepted {body}");
+    }
+}
+
+#[tokio::test]
+async fn non_member_cannot_schedule() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (_, _, _, channel_id) = setup_channel(app.clone()).await;
+    let outsider =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "pass1234").await;
+
+    let (status, _) = common::post_json_authed(
+        app,
+        &format!("/channels/{channel_id}/scheduled-messages"),
+        &outsider,
+        json!({ "content": "sneaky", "send_at": in_one_hour() }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn cancel_removes_message_and_is_owner_only() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, _, _, channel_id) = setup_channel(app.clone()).await;
+    let scheduled = schedule(
+        app.clone(),
+        &token,
+        &channel_id,
+        json!({ "content": "never mind", "send_at": in_one_hour() }),
+    )
+    .await;
+    let uri = format!(
+        "/users/@me/scheduled-messages/{}",
+        scheduled["id"].as_str().unwrap()
+    );
+
+    let other =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "pass1234").await;
+    let (status, _) = common::delete_authed(app.clone(), &uri, &other).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = common::delete_authed(app.clone(), &uri, &token).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (_, list) = common::get_authed(app, "/users/@me/scheduled-messages", &token).await;
+    assert_eq!(list, json!([]));
+}
+
+#[tokio::test]
+async fn update_changes_content_and_time() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, _, _, channel_id) = setup_channel(app.clone()).await;
+    let scheduled = schedule(
+        app.clone(),
+        &token,
+        &channel_id,
+        json!({ "content": "draft", "send_at": in_one_hour() }),
+    )
+    .await;
+    let later = chrono::Utc::now() + chrono::Duration::hours(2);
+
+    let (status, updated) = common::patch_json_authed(
+        app,
+        &format!(
+            "/users/@me/scheduled-messages/{}",
+            scheduled["id"].as_str().unwrap()
+        ),
+        &token,
+        json!({ "content": "final", "send_at": later.to_rfc3339() }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["content"], "final");
+    let send_at: chrono::DateTime<chrono::Utc> =
+        updated["send_at"].as_str().unwrap().parse().unwrap();
+    assert!((send_at - later).num_seconds().abs() < 1);
+}
+
+// ============================================================================
+// Scheduler
+// ============================================================================
+
+#[tokio::test]
+async fn due_message_is_posted_after_restart() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool.clone());
+    let (token, _, _, channel_id) = setup_channel(app.clone()).await;
+    let scheduled = schedule(
+        app.clone(),
+        &token,
+        &channel_id,
+        json!({ "content": "Good morning!", "send_at": in_one_hour() }),
+    )
+    .await;
+    let id = scheduled["id"].as_str().unwrap();
+
+    // A fresh AppState stands in for a restarted server: the queue lives in
+    // the database, not in the process that accepted the request.
+    let (_, restarted) = common::create_test_app_with_state(pool);
+    run_until_processed(&restarted, id).await;
+
+    let (_, messages) = common::get_authed(
+        app.clone(),
+        &format!("/channels/{channel_id}/messages"),
+        &token,
+    )
+    .await;
+    assert_eq!(messages[0]["id"], id);
+    assert_eq!(messages[0]["content"], "Good morning!");
+
+    let (_, list) = common::get_authed(app, "/users/@me/scheduled-messages", &token).await;
+    assert_eq!(list, json!([]));
+}
+
+#[tokio::test]
+async fn reminder_mentions_its_author() {
+    let pool = common::test_pool().await;
+    let (app, state) = common::create_test_app_with_state(pool);
+    let (token, username, _, channel_id) = setup_channel(app.clone()).await;
+    let scheduled = schedule(
+        app.clone(),
+        &token,
+        &channel_id,
+        json!({ "content": "stand-up", "send_at": in_one_hour(), "kind": "reminder" }),
+    )
+    .await;
+    run_until_processed(&state, scheduled["id"].as_str().unwrap()).await;
+
+    let (_, messages) =
+        common::get_authed(app, &format!("/channels/{channel_id}/messages"), &token).await;
+    assert_eq!(
+        messages[0]["content"],
+        format!("@{username} Reminder: stand-up")
+    );
+    assert_eq!(messages[0]["mention_user_ids"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn blocked_message_fails_and_can_be_requeued() {
+    let pool = common::test_pool().await;
+    let (app, state) = common::create_test_app_with_state(pool);
+    let (token, _, server_id, channel_id) = setup_channel(app.clone()).await;
+    let scheduled = schedule(
+        app.clone(),
+        &token,
+        &channel_id,
+        json!({ "content": "buy free nitro", "send_at": in_one_hour() }),
+    )
+    .await;
+    let id = scheduled["id"].as_str().unwrap();
+
+    // Automod runs at send time, not when the message is queued.
+    let (status, _) = common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/automod/rules"),
+        &token,
+        json!({
+            "name": "Scams",
+            "trigger": { "type": "keyword", "keywords": ["free nitro"] },
+            "actions": [{ "type": "delete" }]
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    run_until_processed(&state, id).await;
+
+    let (_, list) = common::get_authed(app.clone(), "/users/@me/scheduled-messages", &token).await;
+    assert_eq!(list[0]["status"], "failed");
+    assert_eq!(
+        list[0]["last_error"],
+        "Message blocked by automod rule 'Scams'"
+    );
+
+    let (status, requeued) = common::patch_json_authed(
+        app.clone(),
+        &format!("/users/@me/scheduled-messages/{id}"),
+        &token,
+        json!({ "content": "buy a gift" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(requeued["status"], "pending");
+    assert!(requeued["last_error"].is_null());
+
+    run_until_processed(&state, id).await;
+    let (_, messages) =
+        common::get_authed(app, &format!("/channels/{channel_id}/messages"), &token).await;
+    assert_eq!(messages[0]["content"], "buy a gift");
+}
@@ -0,0 +1,640 @@
+mod common;
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, HeaderMap, Method, Request, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Router,
+};
+use bytes::Bytes;
+use common::*;
+use hmac::{Hmac, Mac};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use together_server::attachment_gc;
+use together_server::config::S3Config;
+use together_server::storage::{self, MigrationReport, S3Storage, Storage};
+use tower::ServiceExt;
+
+// ============================================================================
+// Stand-in object store
+// ============================================================================
+
+const ACCESS_KEY: &str = "together-test";
+const SECRET_KEY: &str = "together-test-secret-key";
+const BUCKET: &str = "uploads";
+const LAST_MODIFIED: &str = "2020-01-01T00:00:00.000Z";
+
+/// A minimal S3-compatible bucket with MinIO's path-style addressing: PUT,
+/// GET, HEAD and DELETE objects and ListObjectsV2, with every request's
+/// SigV4 signature (header or presigned) checked like a real server would.
+/// Listings report every object as written at [`LAST_MODIFIED`].
+#[derive(Clone)]
+struct MockS3 {
+    endpoint: String,
+    objects: Arc<Mutex<BTreeMap<String, (Bytes, String)>>>,
+}
+
+impl MockS3 {
+    async fn start() -> Self {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let s3 = MockS3 {
+            endpoint: format!("http://{}", listener.local_addr().unwrap()),
+            objects: Arc::default(),
+        };
+        let app = Router::new().fallback(handle).with_state(s3.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        s3
+    }
+
+    fn config(&self, secret: &str) -> S3Config {
+        S3Config {
+            endpoint: self.endpoint.clone(),
+            public_endpoint: self.endpoint.clone(),
+            bucket: BUCKET.to_string(),
+            region: "us-east-1".to_string(),
+            access_key_id: ACCESS_KEY.to_string(),
+            secret_access_key: secret.to_string(),
+            path_style: true,
+            presign_expiry_secs: 120,
+        }
+    }
+
+    fn storage(&self) -> Arc<dyn Storage> {
+        Arc::new(S3Storage::new(self.config(SECRET_KEY)).unwrap())
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.objects.lock().unwrap().keys().cloned().collect()
+    }
+
+    fn object(&self, key: &str) -> Option<Bytes> {
+        self.objects
+            .lock()
+            .unwrap()
+            .get(key)
+            .map(|(data, _)| data.clone())
+    }
+}
+
+fn sigv4_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Recompute the signature the client should have sent.
+fn expected_signature(
+    method: &Method,
+    uri: &Uri,
+    query: &[(String, String)],
+    headers: &HeaderMap,
+    signed_headers: &str,
+    payload_hash: &str,
+    amz_date: &str,
+) -> String {
+    let mut pairs: Vec<String> = query
+        .iter()
+        .filter(|(name, _)| name != "X-Amz-Signature")
+        .map(|(name, value)| format!("{}={}", sigv4_encode(name), sigv4_encode(value)))
+        .collect();
+    pairs.sort();
+    let canonical_headers: String = signed_headers
+        .split(';')
+        .map(|name| {
+            let value = headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("");
+            format!("{name}:{}\n", value.trim())
+        })
+        .collect();
+    let canonical_request = format!(
+        "{method}\n{}\n{}\n{canonical_headers}\n{signed_headers}\n{payload_hash}",
+        uri.path(),
+        pairs.join("&")
+    );
+    let date = &amz_date[..8];
+    let scope = format!("{date}/us-east-1/s3/aws4_request");
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+    let mut key = hmac(format!("AWS4{SECRET_KEY}").as_bytes(), date);
+    for part in ["us-east-1", "s3", "aws4_request"] {
+        key = hmac(&key, part);
+    }
+    hex(&hmac(&key, &string_to_sign))
+}
+
+fn s3_error(status: StatusCode, code: &str) -> Response {
+    (status, format!("<Error><Code>{code}</Code></Error>")).into_response()
+}
+
+async fn handle(
+    State(s3): State<MockS3>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let query: Vec<(String, String)> =
+        url::form_urlencoded::parse(uri.query().unwrap_or("").as_bytes())
+            .into_owned()
+            .collect();
+    let params: HashMap<&str, &str> = query
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+
+    // ── Authenticate ──
+    let presigned = params.contains_key("X-Amz-Signature");
+    let (signature, signed_headers, payload_hash, amz_date) = if presigned {
+        assert_eq!(method, Method::GET, "only downloads are presigned");
+        assert!(
+            params["X-Amz-Credential"].starts_with(&format!("{ACCESS_KEY}/")),
+            "unexpected credential"
+        );
+        let signed_at =
+            chrono::NaiveDateTime::parse_from_str(params["X-Amz-Date"], "%Y%m%dT%H%M%SZ")
+                .unwrap()
+                .and_utc();
+        let expires: i64 = params["X-Amz-Expires"].parse().unwrap();
+        if chrono::Utc::now() > signed_at + chrono::Duration::seconds(expires) {
+            return s3_error(StatusCode::FORBIDDEN, "AccessDenied");
+        }
+        (
+            params["X-Amz-Signature"].to_string(),
+            params["X-Amz-SignedHeaders"].to_string(),
+            "UNSIGNED-PAYLOAD".to_string(),
+            params["X-Amz-Date"].to_string(),
+        )
+    } else {
+        let Some(auth) = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+        else {
+            return s3_error(StatusCode::FORBIDDEN, "AccessDenied");
+        };
+        let fields: HashMap<&str, &str> = auth
+            .trim_start_matches("AWS4-HMAC-SHA256 ")
+            .split(", ")
+            .filter_map(|field| field.split_once('='))
+            .collect();
+        assert!(fields["Credential"].starts_with(&format!("{ACCESS_KEY}/")));
+        let payload_hash = headers["x-amz-content-sha256"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            payload_hash,
+            hex(&Sha256::digest(&body)),
+            "payload hash mismatch"
+        );
+        (
+            fields["Signature"].to_string(),
+            fields["SignedHeaders"].to_string(),
+            payload_hash,
+            headers["x-amz-date"].to_str().unwrap().to_string(),
+        )
+    };
+    let expected = expected_signature(
+        &method,
+        &uri,
+        &query,
+        &headers,
+        &signed_headers,
+        &payload_hash,
+        &amz_date,
+    );
+    if signature != expected {
+        return s3_error(StatusCode::FORBIDDEN, "SignatureDoesNotMatch");
+    }
+
+    // ── Serve ──
+    let path = urlencoding::decode(uri.path()).unwrap().into_owned();
+    let Some(rest) = path.strip_prefix(&format!("/{BUCKET}")) else {
+        return s3_error(StatusCode::NOT_FOUND, "NoSuchBucket");
+    };
+    let key = rest.trim_start_matches('/');
+    let mut objects = s3.objects.lock().unwrap();
+
+    if key.is_empty() {
+        assert_eq!(params.get("list-type"), Some(&"2"));
+        // Two keys per page, so listings exercise continuation.
+        let prefix = params.get("prefix").copied().unwrap_or("");
+        let after = params.get("continuation-token").copied().unwrap_or("");
+        let matching: Vec<_> = objects
+            .iter()
+            .filter(|(k, _)| k.starts_with(prefix) && k.as_str() > after)
+            .collect();
+        let page = &matching[..matching.len().min(2)];
+        let mut xml = format!(
+            "<ListBucketResult><IsTruncated>{}</IsTruncated>",
+            matching.len() > 2
+        );
+        for (k, (data, _)) in page {
+            xml += &format!(
+                "<Contents><Key>{k}</Key><LastModified>{LAST_MODIFIED}</LastModified>\
+                 <Size>{}</Size></Contents>",
+                data.len()
+            );
+        }
+        if matching.len() > 2 {
+            xml += &format!(
+                "<NextContinuationToken>{}</NextContinuationToken>",
+                page[1].0
+            );
+        }
+        xml += "</ListBucketResult>";
+        return xml.into_response();
+    }
+
+    match method {
+        Method::PUT => {
+            let content_type = headers
+                .get(header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("binary/octet-stream")
+                .to_string();
+            objects.insert(key.to_string(), (body, content_type));
+            StatusCode::OK.into_response()
+        }
+        Method::DELETE => {
+            objects.remove(key);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Method::HEAD | Method::GET => {
+            let Some((data, stored_type)) = objects.get(key).cloned() else {
+                return s3_error(StatusCode::NOT_FOUND, "NoSuchKey");
+            };
+            let mut response = Response::builder()
+                .header(
+                    header::CONTENT_TYPE,
+                    params
+                        .get("response-content-type")
+                        .copied()
+                        .unwrap_or(&stored_type),
+                )
+                .header(header::CONTENT_LENGTH, data.len());
+            if let Some(disposition) = params.get("response-content-disposition") {
+                response = response.header(header::CONTENT_DISPOSITION, *disposition);
+            }
+            if let Some(cache) = params.get("response-cache-control") {
+                response = response.header(header::CACHE_CONTROL, *cache);
+            }
+            let body = if method == Method::HEAD {
+                Body::empty()
+            } else {
+                Body::from(data)
+            };
+            response.body(body).unwrap()
+        }
+        _ => s3_error(StatusCode::METHOD_NOT_ALLOWED, "MethodNotAllowed"),
+    }
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// GET `uri` and return the response without following redirects.
+async fn get_response(app: Router, uri: &str, token: Option<&str>) -> Response {
+    let mut req = Request::builder().method(Method::GET).uri(uri);
+    if let Some(token) = token {
+        req = req.header(header::AUTHORIZATION, format!("Bearer {token}"));
+    }
+    app.oneshot(req.body(Body::empty()).unwrap()).await.unwrap()
+}
+
+/// Follow a download redirect to the bucket, like a browser would.
+async fn follow(response: &Response) -> reqwest::Response {
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    let location = response.headers()[header::LOCATION].to_str().unwrap();
+    reqwest::get(location).await.unwrap()
+}
+
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let pixels = image::RgbImage::from_fn(width, height, |x, y| {
+        image::Rgb([(x % 256) as u8, (y % 256) as u8, 64])
+    });
+    let mut out = std::io::Cursor::new(Vec::new());
+    pixels.write_to(&mut out, image::ImageFormat::Png).unwrap();
+    out.into_inner()
+}
+
+/// Owner and outsider tokens plus a message owned by the owner.
+async fn setup(app: Router) -> (String, String, String, String) {
+    let owner = register_and_get_token(app.clone(), &unique_username(), "password123").await;
+    let outsider = register_and_get_token(app.clone(), &unique_username(), "password123").await;
+    let server = create_server(app.clone(), &owner, &unique_username()).await;
+    let server_id = server["id"].as_str().unwrap().to_string();
+    let channel = create_channel(app.clone(), &owner, &server_id, "general").await;
+    let message = create_message(app, &owner, channel["id"].as_str().unwrap(), "photo").await;
+    (
+        owner,
+        outsider,
+        server_id,
+        message["id"].as_str().unwrap().to_string(),
+    )
+}
+
+async fn upload(
+    app: Router,
+    token: &str,
+    message_id: &str,
+    name: &str,
+    data: &[u8],
+) -> (StatusCode, serde_json::Value) {
+    let file = MultipartFile {
+        field_name: "files",
+        filename: name,
+        content_type: "image/png",
+        data,
+    };
+    post_multipart_authed(
+        app,
+        &format!("/messages/{message_id}/attachments"),
+        token,
+        &[file],
+    )
+    .await
+}
+
+// ============================================================================
+// Attachments
+// ============================================================================
+
+/// Uploads land in the bucket with their thumbnails, and downloads redirect
+/// to presigned URLs that serve the server's headers.
+#[tokio::test]
+async fn attachments_are_stored_in_bucket_and_downloads_redirect() {
+    let s3 = MockS3::start().await;
+    let (app, state) = create_test_app_with_storage(test_pool().await, s3.storage());
+    let (owner, _, _, message_id) = setup(app.clone()).await;
+
+    let photo = png(600, 400);
+    let (status, body) = upload(app.clone(), &owner, &message_id, "beach.png", &photo).await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    let url = body[0]["url"].as_str().unwrap();
+    let key = url.strip_prefix("/files/").unwrap();
+
+    let mut expected = vec![
+        key.to_string(),
+        format!("{key}.256.webp"),
+        format!("{key}.512.webp"),
+    ];
+    expected.sort();
+    assert_eq!(s3.keys(), expected);
+    for file in state.storage.list("").await.unwrap() {
+        assert_eq!(file.size, s3.object(&file.key).unwrap().len() as u64);
+    }
+
+    let response = get_response(app.clone(), url, Some(&owner)).await;
+    assert!(response.headers()[header::CACHE_CONTROL]
+        .to_str()
+        .unwrap()
+        .starts_with("private, max-age="));
+    let location = response.headers()[header::LOCATION].to_str().unwrap();
+    assert!(location.starts_with(&format!("{}/{BUCKET}/{key}?", s3.endpoint)));
+    let file = follow(&response).await;
+    assert_eq!(file.status().as_u16(), 200);
+    assert_eq!(file.headers()["content-type"], "image/png");
+    assert_eq!(
+        file.headers()["content-disposition"],
+        "inline; filename=\"beach.png\""
+    );
+    assert_eq!(file.bytes().await.unwrap(), s3.object(key).unwrap());
+
+    let response = get_response(app, &format!("{url}?size=256"), Some(&owner)).await;
+    let thumbnail = follow(&response).await;
+    assert_eq!(thumbnail.status().as_u16(), 200);
+    assert_eq!(thumbnail.headers()["content-type"], "image/webp");
+    let thumbnail = thumbnail.bytes().await.unwrap();
+    assert_eq!(thumbnail, s3.object(&format!("{key}.256.webp")).unwrap());
+    assert_eq!(image::load_from_memory(&thumbnail).unwrap().width(), 256);
+}
+
+/// Files that need no processing are streamed from the staging directory
+/// to the bucket, with the signed payload hash computed from disk.
+#[tokio::test]
+async fn staged_files_are_streamed_to_bucket() {
+    let s3 = MockS3::start().await;
+    let (app, _) = create_test_app_with_storage(test_pool().await, s3.storage());
+    let (owner, _, _, message_id) = setup(app.clone()).await;
+
+    let text = "streamed to the bucket\n".repeat(20_000).into_bytes();
+    let (status, body) = upload(app, &owner, &message_id, "log.txt", &text).await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    assert_eq!(body[0]["mime_type"], "text/plain");
+
+    let key = body[0]["url"]
+        .as_str()
+        .unwrap()
+        .strip_prefix("/files/")
+        .unwrap();
+    assert_eq!(s3.object(key).unwrap(), text);
+}
+
+/// The attachment collector lists the bucket like a directory: objects no
+/// row refers to are deleted, and those of a deleted server follow once the
+/// server is gone.
+#[tokio::test]
+async fn collector_removes_orphans_from_bucket() {
+    let s3 = MockS3::start().await;
+    let storage = s3.storage();
+    let (app, _) = create_test_app_with_storage(test_pool().await, storage.clone());
+    let (owner, _, server_id, message_id) = setup(app.clone()).await;
+    let pool = test_pool().await;
+    let grace = std::time::Duration::from_secs(60 * 60);
+
+    let (status, body) = upload(app.clone(), &owner, &message_id, "kept.png", &png(4, 4)).await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    let key = body[0]["url"]
+        .as_str()
+        .unwrap()
+        .strip_prefix("/files/")
+        .unwrap()
+        .to_string();
+    let stray = format!("{message_id}/0000_stray.png");
+    storage
+        .put(&stray, Bytes::from_static(b"stray"), "image/png")
+        .await
+        .unwrap();
+
+    let report = attachment_gc::collect(&pool, storage.as_ref(), grace)
+        .await
+        .unwrap();
+    assert!(report.orphaned_files >= 1);
+    assert_eq!(s3.keys(), vec![key]);
+
+    let (status, _) = delete_authed(app, &format!("/servers/{server_id}"), &owner).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    attachment_gc::collect(&pool, storage.as_ref(), grace)
+        .await
+        .unwrap();
+    assert!(s3.keys().is_empty());
+}
+
+/// Membership is checked before a presigned URL is handed out.
+#[tokio::test]
+async fn download_redirect_requires_membership() {
+    let s3 = MockS3::start().await;
+    let (app, _) = create_test_app_with_storage(test_pool().await, s3.storage());
+    let (owner, outsider, _, message_id) = setup(app.clone()).await;
+
+    let (status, body) = upload(app.clone(), &owner, &message_id, "a.png", &png(8, 8)).await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    let url = body[0]["url"].as_str().unwrap();
+
+    let response = get_response(app.clone(), url, Some(&outsider)).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(response.headers().get(header::LOCATION).is_none());
+
+    let response = get_response(app, url, None).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+/// A bucket that rejects the server's credentials fails the upload without
+/// leaving an attachment row behind.
+#[tokio::test]
+async fn rejected_credentials_fail_upload_cleanly() {
+    let s3 = MockS3::start().await;
+    let storage = Arc::new(S3Storage::new(s3.config("wrong-secret")).unwrap());
+    let (app, _) = create_test_app_with_storage(test_pool().await, storage);
+    let (owner, _, _, message_id) = setup(app.clone()).await;
+
+    let (status, _) = upload(app.clone(), &owner, &message_id, "a.png", &png(8, 8)).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(s3.keys().is_empty());
+
+    let (status, body) =
+        get_authed(app, &format!("/messages/{message_id}/attachments"), &owner).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!([]));
+}
+
+// ============================================================================
+// Custom emojis
+// ============================================================================
+
+/// Emoji images go to the bucket under `custom_emojis/{id}/`, are served by
+/// redirect with the public cache header, and are all removed on delete.
+#[tokio::test]
+async fn emojis_are_stored_in_bucket_and_deleted_with_thumbnails() {
+    let s3 = MockS3::start().await;
+    let (app, _) = create_test_app_with_storage(test_pool().await, s3.storage());
+    let (owner, _, server_id, _) = setup(app.clone()).await;
+
+    let image = png(100, 100);
+    let files = [
+        MultipartFile {
+            field_name: "name",
+            filename: "",
+            content_type: "text/plain",
+            data: b"party",
+        },
+        MultipartFile {
+            field_name: "image",
+            filename: "party.png",
+            content_type: "image/png",
+            data: &image,
+        },
+    ];
+    let (status, emoji) = post_multipart_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/emojis"),
+        &owner,
+        &files,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{emoji}");
+    let emoji_id = emoji["id"].as_str().unwrap();
+
+    // The original plus the 32 and 64 pixel thumbnails.
+    let keys = s3.keys();
+    assert_eq!(keys.len(), 3, "{keys:?}");
+    assert!(keys
+        .iter()
+        .all(|k| k.starts_with(&format!("custom_emojis/{emoji_id}/"))));
+
+    let response = get_response(app.clone(), &format!("/emojis/{emoji_id}?size=32"), None).await;
+    let file = follow(&response).await;
+    assert_eq!(file.status().as_u16(), 200);
+    assert_eq!(file.headers()["content-type"], "image/webp");
+    assert_eq!(file.headers()["cache-control"], "public, max-age=86400");
+
+    let (status, _) = delete_authed(
+        app,
+        &format!("/servers/{server_id}/emojis/{emoji_id}"),
+        &owner,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(s3.keys().is_empty());
+}
+
+// ============================================================================
+// Migration
+// ============================================================================
+
+/// `migrate_local` copies a whole upload directory and skips what is already
+/// there on a second run.
+#[tokio::test]
+async fn migrate_local_copies_upload_dir_once() {
+    let s3 = MockS3::start().await;
+    let dir = std::env::temp_dir().join(format!("together-migrate-{}", uuid::Uuid::new_v4()));
+    let files = [
+        ("0a1b/aa_photo.png", png(4, 4)),
+        ("0a1b/aa_photo.png.256.webp", b"RIFF....WEBP".to_vec()),
+        ("0a1b/bb_notes.txt", b"meeting notes".to_vec()),
+        ("custom_emojis/5e2f/cc.png", png(2, 2)),
+    ];
+    for (path, data) in &files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, data).unwrap();
+    }
+
+    let target = s3.storage();
+    let report = storage::migrate_local(&dir, target.as_ref()).await.unwrap();
+    assert_eq!(
+        report,
+        MigrationReport {
+            copied: 4,
+            skipped: 0,
+            bytes: files.iter().map(|(_, d)| d.len() as u64).sum(),
+        }
+    );
+    for (key, data) in &files {
+        assert_eq!(s3.object(key).unwrap(), data[..], "{key}");
+    }
+    assert_eq!(
+        s3.objects.lock().unwrap()["0a1b/aa_photo.png"].1,
+        "image/png"
+    );
+
+    let report = storage::migrate_local(&dir, target.as_ref()).await.unwrap();
+    assert_eq!((report.copied, report.skipped), (0, 4));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
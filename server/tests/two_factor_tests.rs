@@ -0,0 +1,545 @@
+mod common;
+
+use axum::{http::StatusCode, Router};
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use serial_test::serial;
+use sha1::Sha1;
+use sqlx::PgPool;
+
+// ============================================================================
+// Test fixture helpers
+// ============================================================================
+
+const PASSWORD: &str = "password123";
+
+/// The TOTP code for `secret` at `offset` steps from now, computed
+/// independently of the server's implementation.
+fn totp_code(secret: &str, offset: i64) -> String {
+    let key = BASE32_NOPAD.decode(secret.as_bytes()).unwrap();
+    let step = (chrono::Utc::now().timestamp() / 30 + offset) as u64;
+    let mut mac = Hmac::<Sha1>::new_from_slice(&key).unwrap();
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    let offset = (digest[19] & 0x0f) as usize;
+    let value = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    format!("{:06}", value % 1_000_000)
+}
+
+async fn login(app: Router, username: &str) -> (StatusCode, Value) {
+    common::post_json(
+        app,
+        "/auth/login",
+        json!({ "username": username, "password": PASSWORD }),
+    )
+    .await
+}
+
+/// Register a user and turn 2FA on. Returns (username, access token,
+/// secret, recovery codes).
+async fn user_with_two_factor(app: Router) -> (String, String, String, Vec<String>) {
+    let username = common::unique_username();
+    let token = common::register_and_get_token(app.clone(), &username, PASSWORD).await;
+
+    let (status, setup) = common::post_json_authed(
+        app.clone(),
+        "/users/@me/two-factor/setup",
+        &token,
+        json!({ "password": PASSWORD }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "setup failed: {setup}");
+    let secret = setup["secret"].as_str().unwrap().to_string();
+
+    let (status, confirmed) = common::post_json_authed(
+        app,
+        "/users/@me/two-factor/confirm",
+        &token,
+        json!({ "code": totp_code(&secret, 0) }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "confirm failed: {confirmed}");
+    let recovery_codes = confirmed["recovery_codes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c.as_str().unwrap().to_string())
+        .collect();
+
+    (username, token, secret, recovery_codes)
+}
+
+/// Tests share the instance_settings row, so every test resets the 2FA
+/// requirement first and runs #[serial].
+async fn setup() -> (Router, PgPool) {
+    let pool = common::test_pool().await;
+    set_policy(&pool, "off").await;
+    (common::create_test_app(pool.clone()), pool)
+}
+
+async fn set_policy(pool: &PgPool, policy: &str) {
+    sqlx::query("UPDATE instance_settings SET require_two_factor = $1 WHERE id = 1")
+        .bind(policy)
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+// ============================================================================
+// Enrollment
+// ============================================================================
+
+#[tokio::test]
+#[serial]
+async fn setup_returns_secret_and_qr_code() {
+    let (app, _) = setup().await;
+    let username = common::unique_username();
+    let token = common::register_and_get_token(app.clone(), &username, PASSWORD).await;
+
+    let (status, _) = common::post_json_authed(
+        app.clone(),
+        "/users/@me/two-factor/setup",
+        &token,
+        json!({ "password": "wrong-password" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, setup) = common::post_json_authed(
+        app.clone(),
+        "/users/@me/two-factor/setup",
+        &token,
+        json!({ "password": PASSWORD }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "body: {setup}");
+    let secret = setup["secret"].as_str().unwrap();
+    assert_eq!(secret.len(), 32);
+    assert!(setup["otpauth_url"].as_str().unwrap().starts_with(&format!(
+        "otpauth://totp/Together:{username}?secret={secret}"
+    )));
+    assert!(setup["qr_svg"].as_str().unwrap().contains("<svg"));
+
+    // Not enabled until confirmed.
+    let (_, status_body) = common::get_authed(app, "/users/@me/two-factor", &token).await;
+    assert_eq!(status_body["enabled"], false);
+}
+
+#[tokio::test]
+#[serial]
+async fn confirm_enables_two_factor() {
+    let (app, _) = setup().await;
+    let (_, token, _, recovery_codes) = user_with_two_factor(app.clone()).await;
+    assert_eq!(recovery_codes.len(), 10);
+
+    let (status, body) = common::get_authed(app.clone(), "/users/@me/two-factor", &token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["enabled"], true);
+    assert_eq!(body["required"], false);
+    assert_eq!(body["recovery_codes_remaining"], 10);
+
+    // A second setup is refused while 2FA is on.
+    let (status, _) = common::post_json_authed(
+        app,
+        "/users/@me/two-factor/setup",
+        &token,
+        json!({ "password": PASSWORD }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+#[serial]
+async fn confirm_rejects_wrong_code() {
+    let (app, _) = setup().await;
+    let token =
+        common::register_and_get_token(app.clone(), &common::unique_username(), PASSWORD).await;
+    let (_, setup) = common::post_json_authed(
+        app.clone(),
+        "/users/@me/two-factor/setup",
+        &token,
+        json!({ "password": PASSWORD }),
+    )
+    .await;
+    let secret = setup["secret"].as_str().unwrap();
+
+    let (status, _) = common::post_json_authed(
+        app,
+        "/users/@me/two-factor/confirm",
+        &token,
+        json!({ "code": totp_code(secret, 5) }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+// ============================================================================
+// Login
+// ============================================================================
+
+#[tokio::test]
+#[serial]
+async fn login_with_two_factor_returns_challenge() {
+    let (app, _) = setup().await;
+    let (username, _, secret, _) = user_with_two_factor(app.clone()).await;
+
+    let (status, body) = login(app.clone(), &username).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.get("access_token").is_none(), "no tokens yet: {body}");
+    assert_eq!(body["two_factor"], "verify");
+    assert_eq!(body["expires_in_seconds"], 300);
+    let challenge = body["challenge"].as_str().unwrap();
+
+    // The code used to confirm enrollment cannot be replayed; use the next
+    // step, which the server also accepts.
+    let (status, body) = common::post_json(
+        app.clone(),
+        "/auth/two-factor/verify",
+        json!({ "challenge": challenge, "code": totp_code(&secret, 1) }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "body: {body}");
+    let access_token = body["access_token"].as_str().unwrap();
+    assert!(body["refresh_token"].is_string());
+    assert_eq!(body["user"]["username"], username);
+
+    let (status, _) = common::get_authed(app.clone(), "/users/@me", access_token).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The challenge is single-use.
+    let (status, _) = common::post_json(
+        app,
+        "/auth/two-factor/verify",
+        json!({ "challenge": challenge, "code": totp_code(&secret, 1) }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+#[serial]
+async fn codes_cannot_be_replayed() {
+    let (app, _) = setup().await;
+    let (username, _, secret, _) = user_with_two_factor(app.clone()).await;
+
+    let next = totp_code(&secret, 1);
+    let (_, body) = login(app.clone(), &username).await;
+    let (status, _) = common::post_json(
+        app.clone(),
+        "/auth/two-factor/verify",
+        json!({ "challenge": body["challenge"], "code": next }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The same code, and codes from earlier steps, are refused.
+    for code in [next, totp_code(&secret, 0)] {
+        let (_, body) = login(app.clone(), &username).await;
+        let (status, _) = common::post_json(
+            app.clone(),
+            "/auth/two-factor/verify",
+            json!({ "challenge": body["challenge"], "code": code }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn challenge_locks_after_too_many_wrong_codes() {
+    let (app, _) = setup().await;
+    let (username, _, secret, _) = user_with_two_factor(app.clone()).await;
+
+    let (_, body) = login(app.clone(), &username).await;
+    let challenge = body["challenge"].as_str().unwrap();
+    for _ in 0..5 {
+        let (status, _) = common::post_json(
+            app.clone(),
+            "/auth/two-factor/verify",
+            json!({ "challenge": challenge, "code": "000000x" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    let (status, _) = common::post_json(
+        app,
+        "/auth/two-factor/verify",
+        json!({ "challenge": challenge, "code": totp_code(&secret, 1) }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+#[serial]
+async fn recovery_code_logs_in_once() {
+    let (app, _) = setup().await;
+    let (username, token, _, recovery_codes) = user_with_two_factor(app.clone()).await;
+    let code = recovery_codes[0].to_uppercase();
+
+    let (_, body) = login(app.clone(), &username).await;
+    let (status, body) = common::post_json(
+        app.clone(),
+        "/auth/two-factor/verify",
+        json!({ "challenge": body["challenge"], "code": code }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "body: {body}");
+
+    let (_, body) = login(app.clone(), &username).await;
+    let (status, _) = common::post_json(
+        app.clone(),
+        "/auth/two-factor/verify",
+        json!({ "challenge": body["challenge"], "code": code }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (_, status_body) = common::get_authed(app, "/users/@me/two-factor", &token).await;
+    assert_eq!(status_body["recovery_codes_remaining"], 9);
+}
+
+#[tokio::test]
+#[serial]
+async fn regenerate_replaces_recovery_codes() {
+    let (app, _) = setup().await;
+    let (username, token, _, old_codes) = user_with_two_factor(app.clone()).await;
+
+    let (status, body) = common::post_json_authed(
+        app.clone(),
+        "/users/@me/two-factor/recovery-codes",
+        &token,
+        json!({ "code": old_codes[0] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "body: {body}");
+    let new_codes = body["recovery_codes"].as_array().unwrap();
+    assert_eq!(new_codes.len(), 10);
+
+    let (_, body) = login(app.clone(), &username).await;
+    let (status, _) = common::post_json(
+        app,
+        "/auth/two-factor/verify",
+        json!({ "challenge": body["challenge"], "code": old_codes[1] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+#[serial]
+async fn disable_requires_password_and_code() {
+    let (app, _) = setup().await;
+    let (username, token, _, recovery_codes) = user_with_two_factor(app.clone()).await;
+
+    let (status, _) = common::post_json_authed(
+        app.clone(),
+        "/users/@me/two-factor/disable",
+        &token,
+        json!({ "password": PASSWORD, "code": "not-a-code" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = common::post_json_authed(
+        app.clone(),
+        "/users/@me/two-factor/disable",
+        &token,
+        json!({ "password": PASSWORD, "code": recovery_codes[0] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "body: {body}");
+    assert_eq!(body["enabled"], false);
+
+    let (status, body) = login(app, &username).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(
+        body["access_token"].is_string(),
+        "plain login again: {body}"
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn admin_can_reset_two_factor() {
+    let (app, pool) = setup().await;
+    let admin = common::unique_username();
+    let admin_token = common::register_and_get_token(app.clone(), &admin, PASSWORD).await;
+    sqlx::query("UPDATE users SET is_admin = true WHERE username = $1")
+        .bind(&admin)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let (username, token, _, _) = user_with_two_factor(app.clone()).await;
+    let (_, me) = common::get_authed(app.clone(), "/users/@me", &token).await;
+
+    let (status, _) = common::delete_authed(
+        app.clone(),
+        &format!("/admin/users/{}/two-factor", me["id"].as_str().unwrap()),
+        &token,
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = common::delete_authed(
+        app.clone(),
+        &format!("/admin/users/{}/two-factor", me["id"].as_str().unwrap()),
+        &admin_token,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (_, body) = login(app, &username).await;
+    assert!(body["access_token"].is_string(), "2FA removed: {body}");
+
+    let (server_id, actor, target_type, created_at): (
+        Option<uuid::Uuid>,
+        Option<String>,
+        Option<String>,
+        Option<chrono::DateTime<chrono::Utc>>,
+    ) = sqlx::query_as(
+        "SELECT a.server_id, u.username, a.target_type, a.created_at
+         FROM audit_logs a JOIN users u ON u.id = a.actor_id
+         WHERE a.action = 'user_two_factor_reset' AND a.target_id = $1",
+    )
+    .bind(uuid::Uuid::parse_str(me["id"].as_str().unwrap()).unwrap())
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(server_id, None);
+    assert_eq!(actor.as_deref(), Some(admin.as_str()));
+    assert_eq!(target_type.as_deref(), Some("user"));
+    assert!(created_at.is_some());
+}
+
+// ============================================================================
+// Instance requirement
+// ============================================================================
+
+#[tokio::test]
+#[serial]
+async fn required_two_factor_enrolls_at_registration() {
+    let (app, pool) = setup().await;
+    set_policy(&pool, "everyone").await;
+
+    let username = common::unique_username();
+    let (status, body) = common::post_json(
+        app.clone(),
+        "/auth/register",
+        json!({ "username": username, "password": PASSWORD }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "body: {body}");
+    assert!(body.get("access_token").is_none(), "body: {body}");
+    assert!(body.get("refresh_token").is_none(), "body: {body}");
+    assert_eq!(body["two_factor"], "enroll");
+
+    let user_id: uuid::Uuid = sqlx::query_scalar("SELECT id FROM users WHERE username = $1")
+        .bind(&username)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    let sessions: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sessions WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(sessions, 0);
+
+    // The challenge walks the new account through enrollment.
+    let challenge = body["challenge"].as_str().unwrap();
+    let (status, setup) = common::post_json(
+        app,
+        "/auth/two-factor/setup",
+        json!({ "challenge": challenge }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "body: {setup}");
+
+    set_policy(&pool, "off").await;
+}
+
+#[tokio::test]
+#[serial]
+async fn required_two_factor_enrolls_at_login() {
+    let (app, pool) = setup().await;
+    let username = common::unique_username();
+    let registered = common::register_user(app.clone(), &username, PASSWORD).await;
+    sqlx::query("UPDATE users SET is_admin = true WHERE username = $1")
+        .bind(&username)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    set_policy(&pool, "admins").await;
+
+    // Existing sessions cannot be refreshed until the user enrolls.
+    let (status, _) = common::post_json(
+        app.clone(),
+        "/auth/refresh",
+        json!({ "refresh_token": registered["refresh_token"] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, body) = login(app.clone(), &username).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["two_factor"], "enroll");
+    let challenge = body["challenge"].as_str().unwrap().to_string();
+
+    // An enroll challenge is not a verify challenge.
+    let (status, _) = common::post_json(
+        app.clone(),
+        "/auth/two-factor/verify",
+        json!({ "challenge": challenge, "code": "123456" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, setup) = common::post_json(
+        app.clone(),
+        "/auth/two-factor/setup",
+        json!({ "challenge": challenge }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "body: {setup}");
+    let secret = setup["secret"].as_str().unwrap();
+
+    let (status, body) = common::post_json(
+        app.clone(),
+        "/auth/two-factor/enroll",
+        json!({ "challenge": challenge, "code": totp_code(secret, 0) }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "body: {body}");
+    assert!(body["access_token"].is_string());
+    assert_eq!(body["user"]["id"], registered["user"]["id"]);
+    assert_eq!(body["recovery_codes"].as_array().unwrap().len(), 10);
+    let token = body["access_token"].as_str().unwrap();
+
+    let (_, status_body) = common::get_authed(app.clone(), "/users/@me/two-factor", token).await;
+    assert_eq!(status_body["enabled"], true);
+    assert_eq!(status_body["required"], true);
+
+    // Covered users cannot turn it off.
+    let (status, _) = common::post_json_authed(
+        app,
+        "/users/@me/two-factor/disable",
+        token,
+        json!({ "password": PASSWORD, "code": body["recovery_codes"][0] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    set_policy(&pool, "off").await;
+}
@@ -0,0 +1,457 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode},
+    response::Response,
+    Router,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use common::*;
+use http_body_util::BodyExt;
+use serde_json::Value;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+// ── Setup helpers ─────────────────────────────────────────────────────────────
+
+struct Fixture {
+    app: Router,
+    pool: sqlx::PgPool,
+    /// Token of the message author.
+    token: String,
+    /// Token of another user with no access to anything.
+    other_token: String,
+    message_id: String,
+}
+
+async fn setup() -> Fixture {
+    let pool = test_pool().await;
+    let app = create_test_app(pool.clone());
+
+    let token = register_and_get_token(app.clone(), &unique_username(), "password123").await;
+    let other_token = register_and_get_token(app.clone(), &unique_username(), "password123").await;
+    let server = create_server(app.clone(), &token, &unique_username()).await;
+    let channel = create_channel(
+        app.clone(),
+        &token,
+        server["id"].as_str().unwrap(),
+        "general",
+    )
+    .await;
+    let message = create_message(app.clone(), &token, channel["id"].as_str().unwrap(), "hi").await;
+
+    Fixture {
+        app,
+        pool,
+        token,
+        other_token,
+        message_id: message["id"].as_str().unwrap().to_string(),
+    }
+}
+
+async fn request(
+    app: Router,
+    method: Method,
+    uri: &str,
+    token: &str,
+    headers: &[(&str, String)],
+    body: Body,
+) -> Response {
+    let mut req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {token}"));
+    for (name, value) in headers {
+        req = req.header(*name, value);
+    }
+    app.oneshot(req.body(body).unwrap()).await.unwrap()
+}
+
+/// POST /uploads and return the new upload's id.
+async fn create_upload(f: &Fixture, filename: &str, length: usize) -> String {
+    let response = request(
+        f.app.clone(),
+        Method::POST,
+        "/uploads",
+        &f.token,
+        &[
+            ("upload-length", length.to_string()),
+            (
+                "upload-metadata",
+                format!("filename {}", STANDARD.encode(filename)),
+            ),
+        ],
+        Body::empty(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let location = response.headers()[header::LOCATION]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body: Value =
+        serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes()).unwrap();
+    assert_eq!(
+        location,
+        format!("/uploads/{}", body["id"].as_str().unwrap())
+    );
+    assert_eq!(body["filename"], filename);
+    assert_eq!(body["upload_offset"], 0);
+    body["id"].as_str().unwrap().to_string()
+}
+
+async fn patch_upload(f: &Fixture, token: &str, id: &str, offset: usize, body: Body) -> Response {
+    request(
+        f.app.clone(),
+        Method::PATCH,
+        &format!("/uploads/{id}"),
+        token,
+        &[
+            (
+                header::CONTENT_TYPE.as_str(),
+                "application/offset+octet-stream".to_string(),
+            ),
+            ("upload-offset", offset.to_string()),
+        ],
+        body,
+    )
+    .await
+}
+
+/// HEAD /uploads/:id and return the status and `Upload-Offset`.
+async fn upload_offset(f: &Fixture, token: &str, id: &str) -> (StatusCode, Option<u64>) {
+    let response = request(
+        f.app.clone(),
+        Method::HEAD,
+        &format!("/uploads/{id}"),
+        token,
+        &[],
+        Body::empty(),
+    )
+    .await;
+    let offset = response
+        .headers()
+        .get("upload-offset")
+        .map(|v| v.to_str().unwrap().parse().unwrap());
+    (response.status(), offset)
+}
+
+/// Text large enough that sniffing and UTF-8 validation see separate chunks.
+fn sample_text() -> Vec<u8> {
+    "Grüße aus dem Upload-Test.\n".repeat(2_000).into_bytes()
+}
+
+// ── Resumable uploads ─────────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn upload_in_pieces_and_attach_to_message() {
+    let f = setup().await;
+    let data = sample_text();
+    let id = create_upload(&f, "notes.txt", data.len()).await;
+
+    let split = 10_001; // not on a character boundary
+    let response = patch_upload(&f, &f.token, &id, 0, Body::from(data[..split].to_vec())).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(response.headers()["upload-offset"], split.to_string());
+    assert_eq!(response.headers()["tus-resumable"], "1.0.0");
+    assert_eq!(
+        upload_offset(&f, &f.token, &id).await,
+        (StatusCode::OK, Some(split as u64))
+    );
+
+    let response = patch_upload(&f, &f.token, &id, split, Body::from(data[split..].to_vec())).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(response.headers()["upload-offset"], data.len().to_string());
+
+    let uri = format!("/messages/{}/attachments/uploads", f.message_id);
+    let (status, body) = post_json_authed(
+        f.app.clone(),
+        &uri,
+        &f.token,
+        serde_json::json!({ "upload_ids": [id] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    let attachment = &body[0];
+    assert_eq!(attachment["filename"], "notes.txt");
+    assert_eq!(attachment["mime_type"], "text/plain");
+    assert_eq!(attachment["file_size"], data.len() as i64);
+
+    let (status, served) =
+        get_raw_authed(f.app.clone(), attachment["url"].as_str().unwrap(), &f.token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(served, data);
+
+    // The upload is consumed: its staged bytes are gone and it can't be
+    // attached again.
+    let staged = test_staging_dir().join(&id);
+    assert!(!staged.exists(), "staged file left behind");
+    assert_eq!(
+        upload_offset(&f, &f.token, &id).await.0,
+        StatusCode::NOT_FOUND
+    );
+    let (status, _) = post_json_authed(
+        f.app.clone(),
+        &uri,
+        &f.token,
+        serde_json::json!({ "upload_ids": [id] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn interrupted_patch_keeps_received_bytes() {
+    let f = setup().await;
+    let data = sample_text();
+    let id = create_upload(&f, "notes.txt", data.len()).await;
+
+    // The connection drops after the first 20,000 bytes.
+    let first = bytes::Bytes::from(data[..20_000].to_vec());
+    let stream = futures::stream::iter(vec![
+        Ok(first),
+        Err(std::io::Error::new(
+            std::io::ErrorKind::ConnectionReset,
+            "connection reset",
+        )),
+    ]);
+    let response = patch_upload(&f, &f.token, &id, 0, Body::from_stream(stream)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(upload_offset(&f, &f.token, &id).await.1, Some(20_000));
+
+    // Resuming from the reported offset completes the file.
+    let response = patch_upload(
+        &f,
+        &f.token,
+        &id,
+        20_000,
+        Body::from(data[20_000..].to_vec()),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let staged = tokio::fs::read(test_staging_dir().join(&id)).await.unwrap();
+    assert_eq!(staged, data);
+}
+
+#[tokio::test]
+async fn patch_with_stale_offset_returns_409() {
+    let f = setup().await;
+    let id = create_upload(&f, "notes.txt", 100).await;
+
+    let response = patch_upload(&f, &f.token, &id, 0, Body::from(vec![b'a'; 40])).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    // Resending the first piece does not append it twice.
+    let response = patch_upload(&f, &f.token, &id, 0, Body::from(vec![b'a'; 40])).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert_eq!(upload_offset(&f, &f.token, &id).await.1, Some(40));
+}
+
+#[tokio::test]
+async fn patch_while_another_request_holds_the_upload_returns_409() {
+    let f = setup().await;
+    let id = create_upload(&f, "notes.txt", 100).await;
+
+    sqlx::query("UPDATE uploads SET locked_until = NOW() + INTERVAL '1 minute' WHERE id = $1")
+        .bind(Uuid::parse_str(&id).unwrap())
+        .execute(&f.pool)
+        .await
+        .unwrap();
+
+    let response = patch_upload(&f, &f.token, &id, 0, Body::from(vec![b'a'; 10])).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn patch_past_upload_length_returns_400() {
+    let f = setup().await;
+    let id = create_upload(&f, "notes.txt", 10).await;
+
+    let response = patch_upload(&f, &f.token, &id, 0, Body::from(vec![b'a'; 11])).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(upload_offset(&f, &f.token, &id).await.1, Some(0));
+}
+
+#[tokio::test]
+async fn patch_requires_offset_content_type() {
+    let f = setup().await;
+    let id = create_upload(&f, "notes.txt", 10).await;
+
+    let response = request(
+        f.app.clone(),
+        Method::PATCH,
+        &format!("/uploads/{id}"),
+        &f.token,
+        &[
+            (header::CONTENT_TYPE.as_str(), "text/plain".to_string()),
+            ("upload-offset", "0".to_string()),
+        ],
+        Body::from(vec![b'a'; 10]),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn disallowed_file_type_cancels_upload_on_first_bytes() {
+    let f = setup().await;
+    // A ZIP local file header, padded past the sniffing window. Only the
+    // first piece is ever sent.
+    let mut zip = b"PK\x03\x04\x14\x00\x00\x00\x08\x00".to_vec();
+    zip.resize(16_384, 0);
+    let id = create_upload(&f, "archive.zip", 1_000_000).await;
+
+    let response = patch_upload(&f, &f.token, &id, 0, Body::from(zip)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert!(
+        String::from_utf8_lossy(&body).contains("application/zip"),
+        "{body:?}"
+    );
+
+    assert_eq!(
+        upload_offset(&f, &f.token, &id).await.0,
+        StatusCode::NOT_FOUND
+    );
+    assert!(!test_staging_dir().join(&id).exists());
+}
+
+#[tokio::test]
+async fn create_upload_validates_length() {
+    let f = setup().await;
+    for length in [None, Some("0"), Some("52428801"), Some("lots")] {
+        let headers: Vec<(&str, String)> = length
+            .map(|l| vec![("upload-length", l.to_string())])
+            .unwrap_or_default();
+        let response = request(
+            f.app.clone(),
+            Method::POST,
+            "/uploads",
+            &f.token,
+            &headers,
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{length:?}");
+    }
+}
+
+#[tokio::test]
+async fn create_upload_validates_filename() {
+    let f = setup().await;
+    for filename in ["a\x01b.txt".to_string(), "a".repeat(129)] {
+        let response = request(
+            f.app.clone(),
+            Method::POST,
+            "/uploads",
+            &f.token,
+            &[
+                ("upload-length", "10".to_string()),
+                (
+                    "upload-metadata",
+                    format!("filename {}", STANDARD.encode(&filename)),
+                ),
+            ],
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{filename:?}");
+    }
+    create_upload(&f, &"a".repeat(128), 10).await;
+}
+
+#[tokio::test]
+async fn uploads_are_private_to_their_creator() {
+    let f = setup().await;
+    let id = create_upload(&f, "notes.txt", 10).await;
+
+    assert_eq!(
+        upload_offset(&f, &f.other_token, &id).await.0,
+        StatusCode::NOT_FOUND
+    );
+    let response = patch_upload(&f, &f.other_token, &id, 0, Body::from(vec![b'a'; 10])).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let (status, _) = delete_authed(f.app.clone(), &format!("/uploads/{id}"), &f.other_token).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let response = patch_upload(&f, &f.token, &id, 0, Body::from(vec![b'a'; 10])).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn attach_rejects_incomplete_and_foreign_uploads() {
+    let f = setup().await;
+    let uri = format!("/messages/{}/attachments/uploads", f.message_id);
+
+    let id = create_upload(&f, "notes.txt", 100).await;
+    let response = patch_upload(&f, &f.token, &id, 0, Body::from(vec![b'a'; 50])).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let (status, _) = post_json_authed(
+        f.app.clone(),
+        &uri,
+        &f.token,
+        serde_json::json!({ "upload_ids": [id] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = post_json_authed(
+        f.app.clone(),
+        &uri,
+        &f.token,
+        serde_json::json!({ "upload_ids": [Uuid::new_v4()] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = get_authed(
+        f.app.clone(),
+        &format!("/messages/{}/attachments", f.message_id),
+        &f.token,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().unwrap().len(), 0);
+}
+
+#[tokio::test]
+async fn delete_upload_discards_bytes() {
+    let f = setup().await;
+    let id = create_upload(&f, "notes.txt", 100).await;
+    let response = patch_upload(&f, &f.token, &id, 0, Body::from(vec![b'a'; 50])).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let (status, _) = delete_authed(f.app.clone(), &format!("/uploads/{id}"), &f.token).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(!test_staging_dir().join(&id).exists());
+    assert_eq!(
+        upload_offset(&f, &f.token, &id).await.0,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn sweep_removes_expired_uploads() {
+    let f = setup().await;
+    let id = create_upload(&f, "notes.txt", 100).await;
+    let staged = test_staging_dir().join(&id);
+    assert!(staged.exists());
+
+    sqlx::query("UPDATE uploads SET expires_at = NOW() - INTERVAL '1 second' WHERE id = $1")
+        .bind(Uuid::parse_str(&id).unwrap())
+        .execute(&f.pool)
+        .await
+        .unwrap();
+    together_server::uploads::sweep(&f.pool, &test_staging_dir())
+        .await
+        .unwrap();
+
+    assert!(!staged.exists());
+    let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM uploads WHERE id = $1")
+        .bind(Uuid::parse_str(&id).unwrap())
+        .fetch_one(&f.pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining, 0);
+}
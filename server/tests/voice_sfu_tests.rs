@@ -0,0 +1,416 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+use together_server::{
+    models::VoiceState,
+    sfu::{Sfu, SfuError, SfuSignal, SfuSignalType, SfuTransport},
+    state::AppState,
+};
+use tokio::sync::mpsc::UnboundedReceiver;
+use uuid::Uuid;
+use webrtc::api::interceptor_registry::register_default_interceptors;
+use webrtc::api::media_engine::{MediaEngine, MIME_TYPE_OPUS};
+use webrtc::api::{APIBuilder, API};
+use webrtc::ice_transport::ice_candidate::RTCIceCandidateInit;
+use webrtc::interceptor::registry::Registry;
+use webrtc::media::Sample;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::rtp_transceiver::rtp_codec::RTCRtpCodecCapability;
+use webrtc::track::track_local::track_local_static_sample::TrackLocalStaticSample;
+
+// ============================================================================
+// Test fixture helpers
+// ============================================================================
+
+/// Two members of a server with two voice channels.
+struct Fixture {
+    owner_token: String,
+    owner_id: Uuid,
+    member_token: String,
+    member_id: Uuid,
+    vc1_id: String,
+    vc2_id: String,
+}
+
+async fn setup(app: axum::Router) -> Fixture {
+    let owner = common::register_user(app.clone(), &common::unique_username(), "pass1234").await;
+    let owner_token = owner["access_token"].as_str().unwrap().to_owned();
+    let member = common::register_user(app.clone(), &common::unique_username(), "pass1234").await;
+    let member_token = member["access_token"].as_str().unwrap().to_owned();
+
+    let server = common::create_server(app.clone(), &owner_token, "SFU Guild").await;
+    let server_id = server["id"].as_str().unwrap().to_owned();
+    common::make_server_public(app.clone(), &owner_token, &server_id).await;
+    common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/join"),
+        &member_token,
+        json!({}),
+    )
+    .await;
+
+    let mut vc_ids = Vec::new();
+    for name in ["Lobby", "Stage"] {
+        let (status, vc) = common::post_json_authed(
+            app.clone(),
+            &format!("/servers/{server_id}/channels"),
+            &owner_token,
+            json!({ "name": name, "type": "voice" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        vc_ids.push(vc["id"].as_str().unwrap().to_owned());
+    }
+
+    Fixture {
+        owner_token,
+        owner_id: owner["user"]["id"].as_str().unwrap().parse().unwrap(),
+        member_token,
+        member_id: member["user"]["id"].as_str().unwrap().parse().unwrap(),
+        vc1_id: vc_ids.remove(0),
+        vc2_id: vc_ids.remove(0),
+    }
+}
+
+async fn join(app: axum::Router, token: &str, channel_id: &str) -> Value {
+    let (status, body) = common::post_json_authed(
+        app,
+        &format!("/channels/{channel_id}/voice"),
+        token,
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "join failed: {body}");
+    body
+}
+
+async fn voice_state(state: &AppState, user_id: Uuid) -> VoiceState {
+    sqlx::query_as::<_, VoiceState>(
+        "SELECT user_id, channel_id, self_mute, self_deaf, self_video, self_screen,
+                server_mute, server_deaf, joined_at
+         FROM voice_states WHERE user_id = $1",
+    )
+    .bind(user_id)
+    .fetch_one(&state.pool)
+    .await
+    .unwrap()
+}
+
+fn sfu(state: &AppState) -> &Arc<Sfu> {
+    state.sfu.as_ref().expect("SFU enabled")
+}
+
+fn client_api() -> API {
+    let mut media_engine = MediaEngine::default();
+    media_engine.register_default_codecs().unwrap();
+    let registry = register_default_interceptors(Registry::new(), &mut media_engine).unwrap();
+    APIBuilder::new()
+        .with_media_engine(media_engine)
+        .with_interceptor_registry(registry)
+        .build()
+}
+
+/// A publisher connection carrying one Opus track, plus its offer with all
+/// ICE candidates gathered so no trickle is needed towards the SFU.
+async fn publisher_with_audio(
+    api: &API,
+) -> (Arc<RTCPeerConnection>, Arc<TrackLocalStaticSample>, String) {
+    let pc = Arc::new(
+        api.new_peer_connection(RTCConfiguration::default())
+            .await
+            .unwrap(),
+    );
+    let track = Arc::new(TrackLocalStaticSample::new(
+        RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_OPUS.to_owned(),
+            ..Default::default()
+        },
+        "mic".to_owned(),
+        "local".to_owned(),
+    ));
+    pc.add_track(track.clone()).await.unwrap();
+
+    let offer = pc.create_offer(None).await.unwrap();
+    let mut gathered = pc.gathering_complete_promise().await;
+    pc.set_local_description(offer).await.unwrap();
+    let _ = gathered.recv().await;
+    let sdp = pc.local_description().await.unwrap().sdp;
+    (pc, track, sdp)
+}
+
+fn signal(transport: SfuTransport, signal_type: SfuSignalType, sdp: Option<String>) -> SfuSignal {
+    SfuSignal {
+        transport,
+        signal_type,
+        sdp,
+        candidate: None,
+    }
+}
+
+/// Keep sending Opus frames (content is irrelevant; the SFU never decodes).
+fn send_audio(track: Arc<TrackLocalStaticSample>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let sample = Sample {
+                data: bytes::Bytes::from_static(&[0xf8, 0xff, 0xfe]),
+                duration: Duration::from_millis(20),
+                ..Default::default()
+            };
+            if track.write_sample(&sample).await.is_err() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+}
+
+/// Act as a client's gateway handler: apply SFU signals dispatched to
+/// `voice.user_id` to its connections and answer subscriber offers.
+/// Reports the stream id of every track received on the subscriber side.
+fn run_client(
+    sfu: Arc<Sfu>,
+    voice: VoiceState,
+    api: Arc<API>,
+    publisher: Arc<RTCPeerConnection>,
+    mut rx: UnboundedReceiver<String>,
+) -> tokio::sync::mpsc::UnboundedReceiver<String> {
+    let (streams_tx, streams_rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let subscriber = Arc::new(
+            api.new_peer_connection(RTCConfiguration::default())
+                .await
+                .unwrap(),
+        );
+        subscriber.on_track(Box::new(move |track, _| {
+            let streams_tx = streams_tx.clone();
+            Box::pin(async move {
+                if let Some(track) = track {
+                    let _ = streams_tx.send(track.stream_id().await);
+                }
+            })
+        }));
+
+        let mut early_candidates: Vec<(SfuTransport, RTCIceCandidateInit)> = Vec::new();
+        while let Some(frame) = rx.recv().await {
+            let v: Value = serde_json::from_str(&frame).unwrap();
+            if v["t"] != "VOICE_SIGNAL" || v["d"]["transport"].is_null() {
+                continue;
+            }
+            let d = &v["d"];
+            let transport: SfuTransport = serde_json::from_value(d["transport"].clone()).unwrap();
+            let pc = match transport {
+                SfuTransport::Publisher => &publisher,
+                SfuTransport::Subscriber => &subscriber,
+            };
+            match d["type"].as_str().unwrap() {
+                "answer" => {
+                    let sdp = d["sdp"].as_str().unwrap().to_owned();
+                    pc.set_remote_description(RTCSessionDescription::answer(sdp).unwrap())
+                        .await
+                        .unwrap();
+                }
+                "offer" => {
+                    let sdp = d["sdp"].as_str().unwrap().to_owned();
+                    pc.set_remote_description(RTCSessionDescription::offer(sdp).unwrap())
+                        .await
+                        .unwrap();
+                    let answer = pc.create_answer(None).await.unwrap();
+                    let mut gathered = pc.gathering_complete_promise().await;
+                    pc.set_local_description(answer).await.unwrap();
+                    let _ = gathered.recv().await;
+                    let sdp = pc.local_description().await.unwrap().sdp;
+                    sfu.handle_signal(
+                        &voice,
+                        signal(SfuTransport::Subscriber, SfuSignalType::Answer, Some(sdp)),
+                    )
+                    .await
+                    .unwrap();
+                }
+                "candidate" => {
+                    let init: RTCIceCandidateInit =
+                        serde_json::from_str(d["candidate"].as_str().unwrap()).unwrap();
+                    early_candidates.push((transport, init));
+                }
+                other => panic!("unexpected SFU signal {other}: {d}"),
+            }
+            // Candidates can overtake the description they belong to.
+            let mut pending = Vec::new();
+            for (transport, init) in early_candidates.drain(..) {
+                let pc = match transport {
+                    SfuTransport::Publisher => &publisher,
+                    SfuTransport::Subscriber => &subscriber,
+                };
+                if pc.remote_description().await.is_none()
+                    || pc.add_ice_candidate(init.clone()).await.is_err()
+                {
+                    pending.push((transport, init));
+                }
+            }
+            early_candidates = pending;
+        }
+    });
+    streams_rx
+}
+
+// ============================================================================
+// Voice mode
+// ============================================================================
+
+#[tokio::test]
+async fn join_reports_mesh_mode_without_sfu() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let f = setup(app.clone()).await;
+
+    let body = join(app, &f.owner_token, &f.vc1_id).await;
+    assert_eq!(body["mode"], "mesh");
+    assert_eq!(body["channel_id"], f.vc1_id);
+}
+
+#[tokio::test]
+async fn join_reports_sfu_mode_when_enabled() {
+    let pool = common::test_pool().await;
+    let (app, _) = common::create_test_app_with_sfu(pool);
+    let f = setup(app.clone()).await;
+
+    let body = join(app, &f.owner_token, &f.vc1_id).await;
+    assert_eq!(body["mode"], "sfu");
+    assert_eq!(body["self_mute"], false);
+}
+
+// ============================================================================
+// Sessions
+// ============================================================================
+
+#[tokio::test]
+async fn subscriber_answer_without_session_is_rejected() {
+    let pool = common::test_pool().await;
+    let (app, state) = common::create_test_app_with_sfu(pool);
+    let f = setup(app.clone()).await;
+    join(app, &f.owner_token, &f.vc1_id).await;
+    let voice = voice_state(&state, f.owner_id).await;
+
+    let err = sfu(&state)
+        .handle_signal(
+            &voice,
+            signal(
+                SfuTransport::Subscriber,
+                SfuSignalType::Answer,
+                Some("v=0".into()),
+            ),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, SfuError::NoSession), "got {err:?}");
+
+    let err = sfu(&state)
+        .handle_signal(
+            &voice,
+            signal(SfuTransport::Subscriber, SfuSignalType::Offer, None),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, SfuError::InvalidSignal(_)), "got {err:?}");
+}
+
+#[tokio::test]
+async fn session_follows_voice_state() {
+    let pool = common::test_pool().await;
+    let (app, state) = common::create_test_app_with_sfu(pool);
+    let f = setup(app.clone()).await;
+    let vc1: Uuid = f.vc1_id.parse().unwrap();
+    let vc2: Uuid = f.vc2_id.parse().unwrap();
+
+    join(app.clone(), &f.owner_token, &f.vc1_id).await;
+    let api = client_api();
+    let (_pc, _track, offer) = publisher_with_audio(&api).await;
+    sfu(&state)
+        .handle_signal(
+            &voice_state(&state, f.owner_id).await,
+            signal(SfuTransport::Publisher, SfuSignalType::Offer, Some(offer)),
+        )
+        .await
+        .unwrap();
+    assert_eq!(sfu(&state).participants(vc1).await, vec![f.owner_id]);
+
+    // Switching channels closes the old session.
+    join(app.clone(), &f.owner_token, &f.vc2_id).await;
+    assert!(sfu(&state).participants(vc1).await.is_empty());
+
+    let (_pc, _track, offer) = publisher_with_audio(&api).await;
+    sfu(&state)
+        .handle_signal(
+            &voice_state(&state, f.owner_id).await,
+            signal(SfuTransport::Publisher, SfuSignalType::Offer, Some(offer)),
+        )
+        .await
+        .unwrap();
+    assert_eq!(sfu(&state).participants(vc2).await, vec![f.owner_id]);
+
+    let (status, _) = common::delete_authed(
+        app,
+        &format!("/channels/{}/voice", f.vc2_id),
+        &f.owner_token,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(sfu(&state).participants(vc2).await.is_empty());
+}
+
+// ============================================================================
+// Forwarding
+// ============================================================================
+
+#[tokio::test]
+async fn sfu_forwards_audio_between_participants() {
+    let pool = common::test_pool().await;
+    let (app, state) = common::create_test_app_with_sfu(pool);
+    let f = setup(app.clone()).await;
+    join(app.clone(), &f.owner_token, &f.vc1_id).await;
+    join(app.clone(), &f.member_token, &f.vc1_id).await;
+
+    let api = Arc::new(client_api());
+    let mut received = Vec::new();
+    let mut senders = Vec::new();
+    for user_id in [f.owner_id, f.member_id] {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        state.connections.add(user_id, tx).await;
+
+        let voice = voice_state(&state, user_id).await;
+        let (publisher, track, offer) = publisher_with_audio(&api).await;
+        received.push(run_client(
+            sfu(&state).clone(),
+            voice.clone(),
+            api.clone(),
+            publisher,
+            rx,
+        ));
+        sfu(&state)
+            .handle_signal(
+                &voice,
+                signal(SfuTransport::Publisher, SfuSignalType::Offer, Some(offer)),
+            )
+            .await
+            .unwrap();
+        senders.push(send_audio(track));
+    }
+
+    // Each participant receives the other's audio, labelled with their id.
+    for (streams, from) in received.iter_mut().zip([f.member_id, f.owner_id]) {
+        let stream_id = tokio::time::timeout(Duration::from_secs(20), streams.recv())
+            .await
+            .expect("no forwarded track within 20s")
+            .unwrap();
+        assert_eq!(stream_id, from.to_string());
+    }
+
+    for sender in senders {
+        sender.abort();
+    }
+}
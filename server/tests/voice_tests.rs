@@ -31,7 +31,6 @@ struct Fixture {
     owner_token: String,
     member_token: String,
     outsider_token: String,
-    #[allow(dead_code)]
     server_id: String,
     vc1_id: String,
     vc2_id: String,
@@ -844,8 +843,8 @@ async fn server_mute_preserved_across_channel_switch() {
     let owner_id = Uuid::parse_str(me["id"].as_str().unwrap()).unwrap();
     let vc1_id = Uuid::parse_str(&f.vc1_id).unwrap();
 
-    // Seed a voice_states row with server_mute = TRUE directly in the DB,
-    // simulating a moderator action (no REST endpoint exposes this field).
+    // Seed a voice_states row with server_mute = TRUE directly in the DB.
+    // The owner cannot be moderated, so the REST endpoint cannot set it here.
     sqlx::query(
         "INSERT INTO voice_states (user_id, channel_id, server_mute)
          VALUES ($1, $2, TRUE)
@@ -956,3 +955,346 @@ async fn participant_list_includes_video_fields() {
     assert!(!participants[0]["self_video"].as_bool().unwrap());
     assert!(!participants[0]["self_screen"].as_bool().unwrap());
 }
+
+// ============================================================================
+// Moderator voice controls
+// ============================================================================
+
+async fn user_id(app: axum::Router, token: &str) -> String {
+    let (_, me) = common::get_authed(app, "/users/@me", token).await;
+    me["id"].as_str().unwrap().to_owned()
+}
+
+/// Join `server_id` as a fresh user and return their token and user ID.
+async fn join_new_member(app: axum::Router, server_id: &str) -> (String, String) {
+    let token =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "pass1234").await;
+    common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/join"),
+        &token,
+        json!({}),
+    )
+    .await;
+    let id = user_id(app, &token).await;
+    (token, id)
+}
+
+async fn join_voice(app: axum::Router, token: &str, channel_id: &str) {
+    let (status, body) = common::post_json_authed(
+        app,
+        &format!("/channels/{channel_id}/voice"),
+        token,
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "join failed: {body}");
+}
+
+/// Create a role with MUTE_MEMBERS and assign it to each of `user_ids`.
+async fn grant_mute_members(
+    app: axum::Router,
+    owner_token: &str,
+    server_id: &str,
+    user_ids: &[&str],
+) {
+    let (status, role) = common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{server_id}/roles"),
+        owner_token,
+        json!({ "name": "Voice Mod", "permissions": 128 }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "create role failed: {role}");
+    let role_id = role["id"].as_str().unwrap();
+    for user_id in user_ids {
+        common::put_authed(
+            app.clone(),
+            &format!("/servers/{server_id}/members/{user_id}/roles/{role_id}"),
+            owner_token,
+        )
+        .await;
+    }
+}
+
+#[tokio::test]
+async fn server_mute_blocks_self_unmute() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let f = setup(app.clone()).await;
+    let member_id = user_id(app.clone(), &f.member_token).await;
+    join_voice(app.clone(), &f.member_token, &f.vc1_id).await;
+
+    let (status, body) = common::patch_json_authed(
+        app.clone(),
+        &format!("/servers/{}/members/{member_id}/voice", f.server_id),
+        &f.owner_token,
+        json!({ "server_mute": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["server_mute"], true);
+    assert_eq!(body["server_deaf"], false);
+
+    let (status, _) = common::patch_json_authed(
+        app.clone(),
+        &format!("/channels/{}/voice", f.vc1_id),
+        &f.member_token,
+        json!({ "self_mute": false }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Other toggles still work while server-muted.
+    let (status, _) = common::patch_json_authed(
+        app,
+        &format!("/channels/{}/voice", f.vc1_id),
+        &f.member_token,
+        json!({ "self_video": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn server_deafen_blocks_self_undeafen_until_cleared() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let f = setup(app.clone()).await;
+    let member_id = user_id(app.clone(), &f.member_token).await;
+    join_voice(app.clone(), &f.member_token, &f.vc1_id).await;
+    let uri = format!("/servers/{}/members/{member_id}/voice", f.server_id);
+
+    let (status, _) = common::patch_json_authed(
+        app.clone(),
+        &uri,
+        &f.owner_token,
+        json!({ "server_deaf": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let undeafen = json!({ "self_deaf": false });
+    let (status, _) = common::patch_json_authed(
+        app.clone(),
+        &format!("/channels/{}/voice", f.vc1_id),
+        &f.member_token,
+        undeafen.clone(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, body) = common::patch_json_authed(
+        app.clone(),
+        &uri,
+        &f.owner_token,
+        json!({ "server_deaf": false }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["server_deaf"], false);
+
+    let (status, _) = common::patch_json_authed(
+        app,
+        &format!("/channels/{}/voice", f.vc1_id),
+        &f.member_token,
+        undeafen,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn moderate_voice_with_no_fields_returns_400() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let f = setup(app.clone()).await;
+    let member_id = user_id(app.clone(), &f.member_token).await;
+    join_voice(app.clone(), &f.member_token, &f.vc1_id).await;
+
+    let (status, _) = common::patch_json_authed(
+        app,
+        &format!("/servers/{}/members/{member_id}/voice", f.server_id),
+        &f.owner_token,
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn moderate_voice_requires_mute_members() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let f = setup(app.clone()).await;
+    let (other_token, other_id) = join_new_member(app.clone(), &f.server_id).await;
+    join_voice(app.clone(), &other_token, &f.vc1_id).await;
+
+    let (status, _) = common::patch_json_authed(
+        app,
+        &format!("/servers/{}/members/{other_id}/voice", f.server_id),
+        &f.member_token,
+        json!({ "server_mute": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn moderator_cannot_target_equal_role() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let f = setup(app.clone()).await;
+    let member_id = user_id(app.clone(), &f.member_token).await;
+    let (other_token, other_id) = join_new_member(app.clone(), &f.server_id).await;
+    join_voice(app.clone(), &other_token, &f.vc1_id).await;
+    let uri = format!("/servers/{}/members/{other_id}/voice", f.server_id);
+
+    grant_mute_members(app.clone(), &f.owner_token, &f.server_id, &[&member_id]).await;
+    let (status, body) = common::patch_json_authed(
+        app.clone(),
+        &uri,
+        &f.member_token,
+        json!({ "server_mute": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // Once the target holds a role at the same height, the moderator is blocked.
+    grant_mute_members(
+        app.clone(),
+        &f.owner_token,
+        &f.server_id,
+        &[&member_id, &other_id],
+    )
+    .await;
+    let (status, _) =
+        common::patch_json_authed(app, &uri, &f.member_token, json!({ "server_mute": false }))
+            .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn moderate_member_not_in_voice_returns_404() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let f = setup(app.clone()).await;
+    let member_id = user_id(app.clone(), &f.member_token).await;
+
+    let (status, _) = common::patch_json_authed(
+        app.clone(),
+        &format!("/servers/{}/members/{member_id}/voice", f.server_id),
+        &f.owner_token,
+        json!({ "server_mute": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = common::delete_authed(
+        app,
+        &format!("/servers/{}/members/{member_id}/voice", f.server_id),
+        &f.owner_token,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn move_member_to_another_voice_channel() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let f = setup(app.clone()).await;
+    let member_id = user_id(app.clone(), &f.member_token).await;
+    join_voice(app.clone(), &f.member_token, &f.vc1_id).await;
+
+    let (status, body) = common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{}/members/{member_id}/voice/move", f.server_id),
+        &f.owner_token,
+        json!({ "channel_id": f.vc2_id }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["channel_id"], f.vc2_id);
+
+    let (_, vc1) = common::get_authed(
+        app.clone(),
+        &format!("/channels/{}/voice", f.vc1_id),
+        &f.owner_token,
+    )
+    .await;
+    assert!(vc1.as_array().unwrap().is_empty());
+    let (_, vc2) = common::get_authed(
+        app.clone(),
+        &format!("/channels/{}/voice", f.vc2_id),
+        &f.owner_token,
+    )
+    .await;
+    assert_eq!(vc2[0]["user_id"], member_id);
+
+    let (_, logs) = common::get_authed(
+        app,
+        &format!(
+            "/servers/{}/audit-logs?action=member_voice_move",
+            f.server_id
+        ),
+        &f.owner_token,
+    )
+    .await;
+    assert_eq!(logs.as_array().unwrap().len(), 1);
+    assert_eq!(logs[0]["target_id"], member_id);
+}
+
+#[tokio::test]
+async fn move_member_to_text_channel_returns_400() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let f = setup(app.clone()).await;
+    let member_id = user_id(app.clone(), &f.member_token).await;
+    join_voice(app.clone(), &f.member_token, &f.vc1_id).await;
+
+    let (status, _) = common::post_json_authed(
+        app,
+        &format!("/servers/{}/members/{member_id}/voice/move", f.server_id),
+        &f.owner_token,
+        json!({ "channel_id": f.text_channel_id }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn disconnect_member_removes_voice_state() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let f = setup(app.clone()).await;
+    let member_id = user_id(app.clone(), &f.member_token).await;
+    join_voice(app.clone(), &f.member_token, &f.vc1_id).await;
+
+    let (status, _) = common::delete_authed(
+        app.clone(),
+        &format!("/servers/{}/members/{member_id}/voice", f.server_id),
+        &f.owner_token,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (_, vc1) = common::get_authed(
+        app.clone(),
+        &format!("/channels/{}/voice", f.vc1_id),
+        &f.owner_token,
+    )
+    .await;
+    assert!(vc1.as_array().unwrap().is_empty());
+
+    let (_, logs) = common::get_authed(
+        app,
+        &format!(
+            "/servers/{}/audit-logs?action=member_voice_disconnect",
+            f.server_id
+        ),
+        &f.owner_token,
+    )
+    .await;
+    assert_eq!(logs.as_array().unwrap().len(), 1);
+}
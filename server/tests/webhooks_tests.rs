@@ -489,3 +489,326 @@ async fn create_webhook_no_auth() {
 
     assert_eq!(status, StatusCode::UNAUTHORIZED);
 }
+
+// ============================================================================
+// Durable delivery queue — /deliveries and /redeliver
+// ============================================================================
+
+/// Start a local HTTP receiver that answers every POST with `status` and
+/// returns its URL.
+async fn spawn_receiver(status: StatusCode) -> String {
+    let app =
+        axum::Router::new().route("/hook", axum::routing::post(move || async move { status }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}/hook")
+}
+
+/// Like [`spawn_receiver`], but also count the requests received.
+async fn spawn_counting_receiver(
+    status: StatusCode,
+) -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    let hits = std::sync::Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let app = axum::Router::new().route(
+        "/hook",
+        axum::routing::post(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async move { status }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{addr}/hook"), hits)
+}
+
+/// Create a webhook pointing at `url` and return its id.
+async fn create_hook(app: axum::Router, token: &str, sid: &str, url: &str) -> String {
+    let (status, body) = common::post_json_authed(
+        app,
+        &format!("/servers/{sid}/webhooks"),
+        token,
+        json!({ "name": "receiver", "url": url, "event_types": ["message.created"] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "create failed: {body}");
+    body["webhook"]["id"].as_str().unwrap().to_owned()
+}
+
+/// Poll the deliveries endpoint until `done` accepts the list, failing after
+/// a few seconds.
+async fn wait_for_deliveries(
+    app: axum::Router,
+    token: &str,
+    sid: &str,
+    wh_id: &str,
+    done: impl Fn(&[serde_json::Value]) -> bool,
+) -> Vec<serde_json::Value> {
+    for _ in 0..100 {
+        let (status, body) = common::get_authed(
+            app.clone(),
+            &format!("/servers/{sid}/webhooks/{wh_id}/deliveries"),
+            token,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "list deliveries failed: {body}");
+        let deliveries = body["deliveries"].as_array().unwrap().clone();
+        if done(&deliveries) {
+            return deliveries;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    panic!("deliveries never reached the expected state");
+}
+
+#[tokio::test]
+async fn delivery_is_recorded_with_attempt_history() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, sid) = setup_server(app.clone()).await;
+    let url = spawn_receiver(StatusCode::OK).await;
+    let wh_id = create_hook(app.clone(), &token, &sid, &url).await;
+
+    common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{sid}/webhooks/{wh_id}/test"),
+        &token,
+        json!({}),
+    )
+    .await;
+
+    let deliveries = wait_for_deliveries(app, &token, &sid, &wh_id, |d| {
+        d.first().is_some_and(|d| d["status"] == "succeeded")
+    })
+    .await;
+    let delivery = &deliveries[0];
+    assert_eq!(delivery["event_type"], "ping");
+    assert_eq!(delivery["payload"]["event"], "ping");
+    assert_eq!(delivery["attempt_count"], 1);
+    assert_eq!(delivery["attempts"][0]["response_status"], 200);
+    assert!(delivery["attempts"][0]["latency_ms"].is_number());
+    assert!(delivery["completed_at"].is_string());
+}
+
+#[tokio::test]
+async fn failed_delivery_is_scheduled_for_retry() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, sid) = setup_server(app.clone()).await;
+    let url = spawn_receiver(StatusCode::INTERNAL_SERVER_ERROR).await;
+    let wh_id = create_hook(app.clone(), &token, &sid, &url).await;
+
+    common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{sid}/webhooks/{wh_id}/test"),
+        &token,
+        json!({}),
+    )
+    .await;
+
+    let deliveries = wait_for_deliveries(app, &token, &sid, &wh_id, |d| {
+        d.first()
+            .is_some_and(|d| d["status"] == "pending" && d["attempt_count"] == 1)
+    })
+    .await;
+    let delivery = &deliveries[0];
+    assert_eq!(delivery["attempts"][0]["response_status"], 500);
+    let next: chrono::DateTime<chrono::Utc> = delivery["next_attempt_at"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(
+        next > chrono::Utc::now(),
+        "retry must be scheduled with backoff"
+    );
+}
+
+#[tokio::test]
+async fn delivery_is_dead_lettered_after_max_attempts() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool.clone());
+    let (token, sid) = setup_server(app.clone()).await;
+    let url = spawn_receiver(StatusCode::INTERNAL_SERVER_ERROR).await;
+    let wh_id = create_hook(app.clone(), &token, &sid, &url).await;
+
+    // A delivery one attempt away from exhausting its retries.
+    sqlx::query(
+        "INSERT INTO webhook_deliveries (webhook_id, event_type, payload, attempt_count)
+         VALUES ($1, 'ping', '{}', $2)",
+    )
+    .bind(uuid::Uuid::parse_str(&wh_id).unwrap())
+    .bind(together_server::webhook_delivery::MAX_ATTEMPTS - 1)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    wait_for_deliveries(app.clone(), &token, &sid, &wh_id, |d| {
+        d.first().is_some_and(|d| d["status"] == "failed")
+    })
+    .await;
+
+    let (_, webhook) =
+        common::get_authed(app, &format!("/servers/{sid}/webhooks/{wh_id}"), &token).await;
+    assert_eq!(webhook["delivery_failures"], 1);
+}
+
+#[tokio::test]
+async fn pending_retry_of_disabled_webhook_is_not_sent() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool.clone());
+    let (token, sid) = setup_server(app.clone()).await;
+    let (url, hits) = spawn_counting_receiver(StatusCode::INTERNAL_SERVER_ERROR).await;
+    let wh_id = create_hook(app.clone(), &token, &sid, &url).await;
+
+    common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{sid}/webhooks/{wh_id}/test"),
+        &token,
+        json!({}),
+    )
+    .await;
+    wait_for_deliveries(app.clone(), &token, &sid, &wh_id, |d| {
+        d.first()
+            .is_some_and(|d| d["status"] == "pending" && d["attempt_count"] == 1)
+    })
+    .await;
+
+    let (status, _) = common::patch_json_authed(
+        app.clone(),
+        &format!("/servers/{sid}/webhooks/{wh_id}"),
+        &token,
+        json!({ "enabled": false }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Bring the retry due right away instead of waiting out the backoff.
+    sqlx::query("UPDATE webhook_deliveries SET next_attempt_at = NOW() WHERE webhook_id = $1")
+        .bind(uuid::Uuid::parse_str(&wh_id).unwrap())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let deliveries = wait_for_deliveries(app, &token, &sid, &wh_id, |d| {
+        d.first().is_some_and(|d| d["status"] == "failed")
+    })
+    .await;
+    assert_eq!(deliveries[0]["attempt_count"], 1);
+    assert_eq!(deliveries[0]["attempts"].as_array().unwrap().len(), 1);
+    assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn redeliver_queues_a_copy_of_the_delivery() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool.clone());
+    let (token, sid) = setup_server(app.clone()).await;
+    let url = spawn_receiver(StatusCode::OK).await;
+    let wh_id = create_hook(app.clone(), &token, &sid, &url).await;
+
+    let original: uuid::Uuid = sqlx::query_scalar(
+        "INSERT INTO webhook_deliveries (webhook_id, event_type, payload, status, completed_at)
+         VALUES ($1, 'message.created', '{\"event\":\"message.created\"}', 'failed', NOW())
+         RETURNING id",
+    )
+    .bind(uuid::Uuid::parse_str(&wh_id).unwrap())
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+
+    let (status, body) = common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{sid}/webhooks/{wh_id}/deliveries/{original}/redeliver"),
+        &token,
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED, "redeliver failed: {body}");
+    let new_id = body["id"].as_str().unwrap().to_owned();
+
+    let deliveries = wait_for_deliveries(app, &token, &sid, &wh_id, |d| {
+        d.iter()
+            .any(|d| d["id"] == new_id.as_str() && d["status"] == "succeeded")
+    })
+    .await;
+    let copy = deliveries
+        .iter()
+        .find(|d| d["id"] == new_id.as_str())
+        .unwrap();
+    assert_eq!(copy["redelivery_of"], original.to_string());
+    assert_eq!(copy["payload"]["event"], "message.created");
+}
+
+#[tokio::test]
+async fn redeliver_unknown_delivery_not_found() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, sid) = setup_server(app.clone()).await;
+    let wh_id = create_hook(app.clone(), &token, &sid, "https://example.com/webhook").await;
+
+    let fake_id = uuid::Uuid::new_v4();
+    let (status, _) = common::post_json_authed(
+        app,
+        &format!("/servers/{sid}/webhooks/{wh_id}/deliveries/{fake_id}/redeliver"),
+        &token,
+        json!({}),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn list_deliveries_rejects_unknown_status() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (token, sid) = setup_server(app.clone()).await;
+    let wh_id = create_hook(app.clone(), &token, &sid, "https://example.com/webhook").await;
+
+    let (status, _) = common::get_authed(
+        app,
+        &format!("/servers/{sid}/webhooks/{wh_id}/deliveries?status=lost"),
+        &token,
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn non_owner_member_cannot_list_deliveries() {
+    let pool = common::test_pool().await;
+    let app = common::create_test_app(pool);
+    let (owner_token, sid) = setup_server(app.clone()).await;
+    let wh_id = create_hook(
+        app.clone(),
+        &owner_token,
+        &sid,
+        "https://example.com/webhook",
+    )
+    .await;
+
+    common::make_server_public(app.clone(), &owner_token, &sid).await;
+    let member =
+        common::register_and_get_token(app.clone(), &common::unique_username(), "pass1234").await;
+    common::post_json_authed(
+        app.clone(),
+        &format!("/servers/{sid}/join"),
+        &member,
+        json!({}),
+    )
+    .await;
+
+    let (status, _) = common::get_authed(
+        app,
+        &format!("/servers/{sid}/webhooks/{wh_id}/deliveries"),
+        &member,
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}